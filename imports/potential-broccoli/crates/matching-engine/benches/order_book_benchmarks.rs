@@ -3,9 +3,10 @@
 //! This module provides comprehensive performance testing for the matching engine,
 //! measuring key operations under various load conditions.
 
-use criterion::{black_box, criterion_group, criterion_main, Criterion, BenchmarkId};
-use matching_engine::{LimitOrderBook, Order, OrderSide, Price, Quantity, types::{OrderId, UserId}};
-use rust_decimal::Decimal;
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion, BenchmarkId, Throughput};
+use matching_engine::{BookConfig, LimitOrderBook, Order, OrderSide, Price, Quantity, TieredStorage, types::{OrderId, UserId, UserIdPool}};
+use matching_engine::marketdata::binary::{BinaryCodec, MarketDataEvent, MarketDataMessage, TickScale};
+use matching_engine::order_book::MarketDepth;
 use std::time::Duration;
 
 fn create_test_order(side: OrderSide, price_cents: i64, quantity: u64) -> Order {
@@ -31,7 +32,7 @@ fn bench_order_operations(c: &mut Criterion) {
         b.iter(|| {
             counter += 1;
             let order = create_test_order(
-                if counter % 2 == 0 { OrderSide::Buy } else { OrderSide::Sell },
+                if counter.is_multiple_of(2) { OrderSide::Buy } else { OrderSide::Sell },
                 15000 + (counter as i64 % 100) - 50, // Price variation around $150
                 100,
             );
@@ -51,16 +52,43 @@ fn bench_order_operations(c: &mut Criterion) {
             book.add_order(order).unwrap();
         }
         
+        // Each cancelled order is replaced at its price, so every iteration cancels a resting order
         let mut index = 0;
         b.iter(|| {
-            if index < order_ids.len() {
-                let order_id = order_ids[index];
-                index += 1;
-                black_box(book.cancel_order(order_id));
-            }
+            let order_id = order_ids[index];
+            let _ = black_box(book.cancel_order(order_id));
+            let order = create_test_order(OrderSide::Buy, 15000 - index as i64, 100);
+            order_ids[index] = order.id;
+            book.add_order(order).unwrap();
+            index = (index + 1) % order_ids.len();
         });
     });
     
+    // Cancelling from the middle of one deep level, then refilling it at the back so its depth holds.
+    // The front half of the queue never moves, so the order just behind it is always mid-queue, and
+    // each replacement lands in the back half, in the slot of the order it replaced
+    for depth in [1000, 10000] {
+        let mut book = LimitOrderBook::new("AAPL".to_string()).unwrap();
+        let mut back_half = Vec::new();
+        for i in 0..depth {
+            let order = create_test_order(OrderSide::Buy, 15000, 100);
+            if i >= depth / 2 {
+                back_half.push(order.id);
+            }
+            book.add_order(order).unwrap();
+        }
+        group.bench_with_input(BenchmarkId::new("cancel_mid_queue", depth), &depth, |b, _| {
+            let mut next = 0;
+            b.iter(|| {
+                black_box(book.cancel_order(back_half[next]).unwrap());
+                let order = create_test_order(OrderSide::Buy, 15000, 100);
+                back_half[next] = order.id;
+                book.add_order(order).unwrap();
+                next = (next + 1) % back_half.len();
+            });
+        });
+    }
+    
     group.finish();
 }
 
@@ -107,6 +135,20 @@ fn bench_query_operations(c: &mut Criterion) {
             },
         );
         
+        // Benchmark level_quantity() at 100 bid levels spread across the side, one map lookup each
+        let prices: Vec<Price> = (0..*size).step_by(*size as usize / 100).map(|i| Price::from_cents(15000 - i).unwrap()).collect();
+        group.bench_with_input(
+            BenchmarkId::new("level_lookup", size),
+            size,
+            |b, _| {
+                b.iter(|| {
+                    for price in &prices {
+                        black_box(book.level_quantity(OrderSide::Buy, *price));
+                    }
+                });
+            },
+        );
+        
         // Benchmark market_depth()
         for depth_levels in [5, 10, 20].iter() {
             group.bench_with_input(
@@ -119,6 +161,25 @@ fn bench_query_operations(c: &mut Criterion) {
         }
     }
     
+    // Deep book with activity far from the touch: the depth cache should absorb it
+    let mut book = LimitOrderBook::new("AAPL".to_string()).unwrap();
+    for i in 0..1000 {
+        book.add_order(create_test_order(OrderSide::Buy, 15000 - i, 100)).unwrap();
+        book.add_order(create_test_order(OrderSide::Sell, 15001 + i, 100)).unwrap();
+    }
+    for (name, price) in [("market_depth_after_deep_mutation", 14960), ("market_depth_after_touch_mutation", 15000)] {
+        group.bench_function(name, |b| {
+            b.iter(|| {
+                let order = create_test_order(OrderSide::Buy, price, 100);
+                let order_id = order.id;
+                book.add_order(order).unwrap();
+                black_box(book.market_depth(10));
+                book.cancel_order(order_id).unwrap();
+                black_box(book.market_depth(10));
+            });
+        });
+    }
+    
     group.finish();
 }
 
@@ -158,6 +219,60 @@ fn bench_order_matching(c: &mut Criterion) {
         );
     }
     
+    // Resting an order inside a deep book and cancelling it, each finding its level in the side's map
+    for depth in [1000, 10000] {
+        let mut book = LimitOrderBook::new("AAPL".to_string()).unwrap();
+        for i in 0..depth {
+            book.add_order(create_test_order(OrderSide::Buy, 15000 - i, 100)).unwrap();
+            book.add_order(create_test_order(OrderSide::Sell, 15001 + i, 100)).unwrap();
+        }
+        group.bench_with_input(
+            BenchmarkId::new("rest_and_cancel_in_deep_book", depth),
+            &depth,
+            |b, &depth| {
+                let mut i = 0;
+                b.iter(|| {
+                    let order = create_test_order(OrderSide::Buy, 15000 - (i * 7919) % depth, 100);
+                    let order_id = order.id;
+                    black_box(book.add_order(order).unwrap());
+                    black_box(book.cancel_order(order_id).unwrap());
+                    i += 1;
+                });
+            },
+        );
+    }
+    
+    // Every submission captures its arrival benchmark, so the sweep is timed
+    // once and the slippage report computed from its result on its own
+    let sweep_book = || {
+        let mut book = LimitOrderBook::new("AAPL".to_string()).unwrap();
+        book.add_order(create_test_order(OrderSide::Buy, 14999, 100)).unwrap();
+        for i in 0..100 {
+            book.add_order(create_test_order(OrderSide::Sell, 15001 + i, 100)).unwrap();
+        }
+        book
+    };
+    group.bench_function("sweep_submit", |b| {
+        b.iter_custom(|iters| {
+            let mut total_duration = Duration::new(0, 0);
+            
+            for _ in 0..iters {
+                let mut book = sweep_book();
+                let buy_order = create_test_order(OrderSide::Buy, 15010, 1000);
+                let start = std::time::Instant::now();
+                black_box(book.submit_order(buy_order).unwrap());
+                total_duration += start.elapsed();
+            }
+            
+            total_duration
+        });
+    });
+    
+    let result = sweep_book().submit_order(create_test_order(OrderSide::Buy, 15010, 1000)).unwrap();
+    group.bench_function("slippage_report", |b| {
+        b.iter(|| black_box(black_box(&result).slippage_report()));
+    });
+    
     group.finish();
 }
 
@@ -198,6 +313,30 @@ fn bench_serialization(c: &mut Criterion) {
                 });
             },
         );
+        
+        // Benchmark binary serialization
+        #[cfg(feature = "binary-snapshots")]
+        group.bench_with_input(
+            BenchmarkId::new("binary_serialize", size),
+            &book,
+            |b, book| {
+                b.iter(|| {
+                    black_box(book.to_bytes().unwrap());
+                });
+            },
+        );
+        
+        // Benchmark binary deserialization
+        #[cfg(feature = "binary-snapshots")]
+        group.bench_with_input(
+            BenchmarkId::new("binary_deserialize", size),
+            &book.to_bytes().unwrap(),
+            |b, bytes| {
+                b.iter(|| {
+                    black_box(LimitOrderBook::from_bytes(bytes).unwrap());
+                });
+            },
+        );
     }
     
     group.finish();
@@ -266,12 +405,476 @@ fn bench_hft_simulation(c: &mut Criterion) {
     group.finish();
 }
 
+/// Top-of-book operations on a 50k-level book, with and without hot/cold tiering
+fn bench_tiered_storage(c: &mut Criterion) {
+    let mut group = c.benchmark_group("tiered_storage");
+    group.measurement_time(Duration::from_secs(10));
+    
+    for (name, tiering) in [("untiered", None), ("tiered", Some(TieredStorage::new(64)))] {
+        let mut book = LimitOrderBook::new("AAPL".to_string()).unwrap();
+        book.set_tiered_storage(tiering);
+        for i in 0..25_000 {
+            book.add_order(create_test_order(OrderSide::Buy, 50_000 - i, 100)).unwrap();
+            book.add_order(create_test_order(OrderSide::Sell, 50_001 + i, 100)).unwrap();
+        }
+        
+        group.bench_function(BenchmarkId::new("add_cancel_at_touch", name), |b| {
+            let mut counter = 0i64;
+            b.iter(|| {
+                counter += 1;
+                let order = create_test_order(OrderSide::Buy, 49_990 + counter % 10, 100);
+                let order_id = order.id;
+                black_box(book.add_order(order).unwrap());
+                let _ = black_box(book.cancel_order(order_id));
+            });
+        });
+        
+        group.bench_function(BenchmarkId::new("best_bid_ask", name), |b| {
+            b.iter(|| {
+                black_box(book.best_bid());
+                black_box(book.best_ask());
+            });
+        });
+    }
+    
+    group.finish();
+}
+
+/// Batch submission against the equivalent `add_order` loop
+fn bench_batch_submission(c: &mut Criterion) {
+    let mut group = c.benchmark_group("batch_submission");
+    
+    // A burst that rests on both sides and crosses about a third of the time
+    let burst = || -> Vec<Order> {
+        (0..1_000i64)
+            .map(|i| {
+                let side = if i % 2 == 0 { OrderSide::Buy } else { OrderSide::Sell };
+                let offset = (i * 7) % 30;
+                let price = match side {
+                    OrderSide::Buy => 14_990 + offset,
+                    OrderSide::Sell => 15_010 - offset,
+                };
+                create_test_order(side, price, 100)
+            })
+            .collect()
+    };
+    let book = || LimitOrderBook::new("AAPL".to_string()).unwrap();
+    
+    group.bench_function("add_order_loop", |b| {
+        b.iter_batched(
+            || (book(), burst()),
+            |(mut book, orders)| {
+                for order in orders {
+                    let _ = black_box(book.add_order(order));
+                }
+            },
+            BatchSize::SmallInput,
+        );
+    });
+    
+    group.bench_function("add_orders", |b| {
+        b.iter_batched(
+            || (book(), burst()),
+            |(mut book, orders)| black_box(book.add_orders(orders)),
+            BatchSize::SmallInput,
+        );
+    });
+    
+    group.finish();
+}
+
+/// One-call BBO against the five individual top-of-book queries
+fn bench_bbo(c: &mut Criterion) {
+    let mut group = c.benchmark_group("bbo");
+    
+    // 10k resting orders: two per level, 2,500 levels per side
+    let mut book = LimitOrderBook::new("AAPL".to_string()).unwrap();
+    for i in 0..2_500 {
+        for _ in 0..2 {
+            book.add_order(create_test_order(OrderSide::Buy, 15_000 - i, 100)).unwrap();
+            book.add_order(create_test_order(OrderSide::Sell, 15_001 + i, 100)).unwrap();
+        }
+    }
+    
+    group.bench_function("bbo", |b| {
+        b.iter(|| black_box(book.bbo()));
+    });
+    group.bench_function("five_calls", |b| {
+        b.iter(|| {
+            black_box((
+                book.best_bid(),
+                book.best_ask(),
+                book.best_bid_quantity(),
+                book.best_ask_quantity(),
+                book.spread(),
+            ))
+        });
+    });
+    
+    group.finish();
+}
+
+/// Lazy level iteration against building a depth snapshot
+/// 
+/// Each iteration first mutates both touches, so `market_depth` rebuilds
+/// its snapshot rather than serving it from the depth cache.
+fn bench_level_iteration(c: &mut Criterion) {
+    let mut group = c.benchmark_group("level_iteration");
+    
+    // 10k resting orders: two per level, 2,500 levels per side
+    let mut book = LimitOrderBook::new("AAPL".to_string()).unwrap();
+    for i in 0..2_500 {
+        for _ in 0..2 {
+            book.add_order(create_test_order(OrderSide::Buy, 15_000 - i, 100)).unwrap();
+            book.add_order(create_test_order(OrderSide::Sell, 15_001 + i, 100)).unwrap();
+        }
+    }
+    
+    let touch = |book: &mut LimitOrderBook| {
+        for (side, price) in [(OrderSide::Buy, 15_000), (OrderSide::Sell, 15_001)] {
+            let order = create_test_order(side, price, 100);
+            let order_id = order.id;
+            book.add_order(order).unwrap();
+            book.cancel_order(order_id).unwrap();
+        }
+    };
+    
+    for levels in [10, 100] {
+        group.bench_with_input(BenchmarkId::new("market_depth", levels), &levels, |b, &levels| {
+            b.iter(|| {
+                touch(&mut book);
+                let depth = book.market_depth(levels);
+                black_box(depth.bids.iter().chain(&depth.asks).map(|level| level.quantity.value()).sum::<u64>())
+            });
+        });
+        group.bench_with_input(BenchmarkId::new("iter_levels", levels), &levels, |b, &levels| {
+            b.iter(|| {
+                touch(&mut book);
+                let quantity = |side| book.iter_levels(side).take(levels).map(|level| level.quantity.value()).sum::<u64>();
+                black_box(quantity(OrderSide::Buy) + quantity(OrderSide::Sell))
+            });
+        });
+    }
+    
+    group.finish();
+}
+
+/// Range cancellation of a fixed band as the rest of the side deepens
+fn bench_range_cancel(c: &mut Criterion) {
+    let mut group = c.benchmark_group("range_cancel");
+    
+    for depth in [100i64, 1_000, 10_000] {
+        // One order per level, asks from 150.00 upward
+        let book = || {
+            let mut book = LimitOrderBook::new("AAPL".to_string()).unwrap();
+            for i in 0..depth {
+                book.add_order(create_test_order(OrderSide::Sell, 15_000 + i, 100)).unwrap();
+            }
+            book
+        };
+        let from = Price::from_cents(15_010).unwrap();
+        let to = Price::from_cents(15_019).unwrap();
+        
+        group.bench_with_input(BenchmarkId::new("ten_levels", depth), &depth, |b, _| {
+            b.iter_batched(
+                book,
+                // Hand the book back so dropping it falls outside the measurement
+                |mut book| {
+                    black_box(book.cancel_orders_in_range(OrderSide::Sell, from, to));
+                    book
+                },
+                BatchSize::LargeInput,
+            );
+        });
+    }
+    
+    group.finish();
+}
+
+/// One aggressor sweeping a single deep price level, filling from the front of its queue
+///
+/// Throughput per fill should hold roughly flat as the level deepens. The
+/// unit test `test_deep_level_sweep_fills_every_order` checks the same sweep's trades.
+fn bench_deep_level_sweep(c: &mut Criterion) {
+    let mut group = c.benchmark_group("deep_level_sweep");
+    
+    for depth in [1_000u64, 10_000, 50_000] {
+        let book = || {
+            let mut book = LimitOrderBook::new("AAPL".to_string()).unwrap();
+            for _ in 0..depth {
+                book.add_order(create_test_order(OrderSide::Sell, 15_000, 10)).unwrap();
+            }
+            book
+        };
+        
+        group.throughput(Throughput::Elements(depth));
+        group.bench_with_input(BenchmarkId::new("one_level", depth), &depth, |b, &depth| {
+            b.iter_batched(
+                book,
+                |mut book| {
+                    black_box(book.add_order(create_test_order(OrderSide::Buy, 15_000, 10 * depth)).unwrap());
+                    book
+                },
+                BatchSize::LargeInput,
+            );
+        });
+    }
+    
+    group.finish();
+}
+
+/// Trades executed with the recent trade history already full, so each one evicts the oldest
+fn bench_trade_history(c: &mut Criterion) {
+    let mut group = c.benchmark_group("trade_history");
+    
+    for capacity in [1_000usize, 10_000] {
+        // One large ask, and a full history of single-lot trades against it
+        let book = || {
+            let mut book = LimitOrderBook::with_max_recent_trades("AAPL".to_string(), capacity).unwrap();
+            book.add_order(create_test_order(OrderSide::Sell, 15_000, 1_000_000)).unwrap();
+            for _ in 0..capacity {
+                book.add_order(create_test_order(OrderSide::Buy, 15_000, 1)).unwrap();
+            }
+            book
+        };
+        let buys: Vec<Order> = (0..1_000).map(|_| create_test_order(OrderSide::Buy, 15_000, 1)).collect();
+        
+        group.throughput(Throughput::Elements(buys.len() as u64));
+        group.bench_with_input(BenchmarkId::new("past_capacity", capacity), &capacity, |b, _| {
+            b.iter_batched(
+                || (book(), buys.clone()),
+                |(mut book, buys)| {
+                    for buy in buys {
+                        black_box(book.add_order(buy).unwrap());
+                    }
+                    book
+                },
+                BatchSize::LargeInput,
+            );
+        });
+    }
+    
+    group.finish();
+}
+
+/// add_order throughput for 100 users placing 100k orders, each order's user ID
+/// built from a fresh string or taken from a pool
+fn bench_user_ids(c: &mut Criterion) {
+    let mut group = c.benchmark_group("user_ids");
+    group.sample_size(10);
+    
+    let users: Vec<String> = (0..100).map(|i| format!("account-{i:03}")).collect();
+    const ORDERS: usize = 100_000;
+    // Alternating sides around 150.00, so a share of orders trade
+    let order = |i: usize, user_id: UserId| {
+        let side = if i.is_multiple_of(2) { OrderSide::Buy } else { OrderSide::Sell };
+        let offset = (i % 7) as i64 - 3;
+        let price = Price::from_cents(15_000 + if side == OrderSide::Buy { offset } else { -offset }).unwrap();
+        Order::new(OrderId::new(), user_id, side, price, Quantity::new(10).unwrap())
+    };
+    
+    group.throughput(Throughput::Elements(ORDERS as u64));
+    group.bench_function("fresh_strings", |b| {
+        b.iter_batched(
+            || LimitOrderBook::new("AAPL".to_string()).unwrap(),
+            |mut book| {
+                for i in 0..ORDERS {
+                    let user_id = UserId::new(users[i % users.len()].clone());
+                    black_box(book.add_order(order(i, user_id)).unwrap());
+                }
+                book
+            },
+            BatchSize::LargeInput,
+        );
+    });
+    group.bench_function("pooled", |b| {
+        b.iter_batched(
+            || (LimitOrderBook::new("AAPL".to_string()).unwrap(), UserIdPool::new()),
+            |(mut book, mut pool)| {
+                for i in 0..ORDERS {
+                    let user_id = pool.get_or_intern(&users[i % users.len()]);
+                    black_box(book.add_order(order(i, user_id)).unwrap());
+                }
+                book
+            },
+            BatchSize::LargeInput,
+        );
+    });
+    
+    group.finish();
+}
+
+/// Binary market data encoding throughput
+/// Polling depth with a fresh `MarketDepth` per call vs. refilling one buffer
+///
+/// Throughput is depth snapshots per second, so the gap between the two at
+/// equal wall time is what the avoided allocations are worth.
+fn bench_depth_polling(c: &mut Criterion) {
+    let mut group = c.benchmark_group("depth_polling");
+    
+    // 10k orders: 5k bid levels and 5k ask levels
+    let mut book = LimitOrderBook::new("AAPL".to_string()).unwrap();
+    for i in 0..5_000 {
+        book.add_order(create_test_order(OrderSide::Buy, 15_000 - i, 100)).unwrap();
+        book.add_order(create_test_order(OrderSide::Sell, 15_001 + i, 100)).unwrap();
+    }
+    
+    group.throughput(Throughput::Elements(1));
+    for levels in [10usize, 100] {
+        group.bench_with_input(BenchmarkId::new("allocating", levels), &levels, |b, &levels| {
+            b.iter(|| black_box(book.market_depth(levels)));
+        });
+        let mut depth = MarketDepth::default();
+        group.bench_with_input(BenchmarkId::new("reused_buffer", levels), &levels, |b, &levels| {
+            b.iter(|| {
+                book.market_depth_into(levels, &mut depth);
+                black_box(&depth);
+            });
+        });
+    }
+    
+    group.finish();
+}
+
+fn bench_market_data_encoding(c: &mut Criterion) {
+    let mut group = c.benchmark_group("market_data_encoding");
+    let codec = BinaryCodec::new(TickScale::new(Price::from_cents(1).unwrap()));
+    
+    // A feed's worth of adds and level updates, with a top-10 snapshot every 100 messages
+    let mut book = LimitOrderBook::new("AAPL".to_string()).unwrap();
+    for i in 0..50 {
+        book.add_order(create_test_order(OrderSide::Buy, 15_000 - i, 100)).unwrap();
+        book.add_order(create_test_order(OrderSide::Sell, 15_001 + i, 100)).unwrap();
+    }
+    let depth = book.market_depth(10);
+    let messages: Vec<MarketDataMessage> = (0..1_000u64)
+        .map(|i| {
+            let side = if i % 2 == 0 { OrderSide::Buy } else { OrderSide::Sell };
+            let price = Price::from_cents(15_000 + (i % 50) as i64).unwrap();
+            let event = match i % 100 {
+                0 => MarketDataEvent::snapshot(&depth, 10),
+                n if n % 3 == 0 => MarketDataEvent::AddOrder { order_id: OrderId::new(), side, price, quantity: Quantity::new(100).unwrap() },
+                _ => MarketDataEvent::LevelUpdate { side, price, quantity: Quantity::new(100 + i).unwrap(), order_count: 3 },
+            };
+            MarketDataMessage { symbol: book.symbol().clone(), sequence: i, event }
+        })
+        .collect();
+    
+    group.throughput(Throughput::Elements(messages.len() as u64));
+    group.bench_function("mixed_1000", |b| {
+        let mut out = Vec::with_capacity(64 * messages.len());
+        b.iter(|| {
+            out.clear();
+            for message in &messages {
+                codec.encode(message, &mut out).unwrap();
+            }
+            black_box(out.len());
+        });
+    });
+    
+    group.finish();
+}
+
+/// A burst of resting orders and its cancel-all, on a default book and on one sized for it
+///
+/// The sized book never regrows its lookup or slabs; the compacted one has
+/// given its burst capacity back and regrows into the next burst.
+fn bench_book_config(c: &mut Criterion) {
+    let mut group = c.benchmark_group("book_config");
+    const ORDERS: i64 = 10_000;
+    let config = BookConfig { expected_price_levels: 100, expected_orders: ORDERS as usize, ..Default::default() };
+    let burst = |book: &mut LimitOrderBook| {
+        let mut ids = Vec::with_capacity(ORDERS as usize);
+        for i in 0..ORDERS {
+            let order = match i % 2 {
+                0 => create_test_order(OrderSide::Buy, 15_000 - i % 100, 100),
+                _ => create_test_order(OrderSide::Sell, 15_001 + i % 100, 100),
+            };
+            ids.push(order.id);
+            book.add_order(order).unwrap();
+        }
+        for id in ids {
+            book.cancel_order(id).unwrap();
+        }
+    };
+    let setup = |name: &str| match name {
+        "default" => LimitOrderBook::new("AAPL".to_string()).unwrap(),
+        "with_config" => LimitOrderBook::with_config("AAPL".to_string(), config).unwrap(),
+        _ => {
+            let mut book = LimitOrderBook::with_config("AAPL".to_string(), config).unwrap();
+            burst(&mut book);
+            book.compact();
+            book
+        },
+    };
+    
+    group.throughput(Throughput::Elements(ORDERS as u64));
+    for name in ["default", "with_config", "compacted"] {
+        group.bench_function(BenchmarkId::new("burst_and_cancel_all", name), |b| {
+            b.iter_batched(
+                || setup(name),
+                |mut book| {
+                    burst(&mut book);
+                    book
+                },
+                BatchSize::LargeInput,
+            );
+        });
+    }
+    
+    group.finish();
+}
+
+/// Publishing a 10k-order book after each touch-level change: a full clone against a copy-on-write snapshot
+fn bench_book_snapshots(c: &mut Criterion) {
+    let mut group = c.benchmark_group("book_snapshots");
+    let mut book = LimitOrderBook::new("AAPL".to_string()).unwrap();
+    for i in 0..5_000 {
+        book.add_order(create_test_order(OrderSide::Buy, 15_000 - i % 500, 100)).unwrap();
+        book.add_order(create_test_order(OrderSide::Sell, 15_001 + i % 500, 100)).unwrap();
+    }
+    
+    // Each iteration rests and cancels one bid at the touch, then publishes
+    let touch = |book: &mut LimitOrderBook| {
+        let order = create_test_order(OrderSide::Buy, 15_000, 100);
+        let order_id = order.id;
+        book.add_order(order).unwrap();
+        book.cancel_order(order_id).unwrap();
+    };
+    group.bench_function("clone", |b| {
+        b.iter(|| {
+            touch(&mut book);
+            black_box(book.clone());
+        });
+    });
+    group.bench_function("snapshot", |b| {
+        b.iter(|| {
+            touch(&mut book);
+            black_box(book.snapshot());
+        });
+    });
+    
+    group.finish();
+}
+
 criterion_group!(
     benches, 
     bench_order_operations,
     bench_query_operations, 
     bench_order_matching,
     bench_serialization,
-    bench_hft_simulation
+    bench_hft_simulation,
+    bench_tiered_storage,
+    bench_batch_submission,
+    bench_range_cancel,
+    bench_deep_level_sweep,
+    bench_trade_history,
+    bench_user_ids,
+    bench_bbo,
+    bench_level_iteration,
+    bench_depth_polling,
+    bench_market_data_encoding,
+    bench_book_config,
+    bench_book_snapshots
 );
 criterion_main!(benches);
\ No newline at end of file
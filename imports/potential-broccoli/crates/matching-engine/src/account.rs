@@ -0,0 +1,109 @@
+//! Account hierarchy mapping users to firms and desks
+
+use crate::types::{DeskId, FirmId, UserId};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Firm (and optional desk) a user trades for
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AccountInfo {
+    pub firm_id: FirmId,
+    pub desk_id: Option<DeskId>,
+}
+
+/// Registry mapping users to their position in the firm hierarchy
+///
+/// Users without an entry are treated as standalone accounts: they only
+/// share a scope with themselves.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AccountRegistry {
+    accounts: HashMap<UserId, AccountInfo>,
+}
+
+impl AccountRegistry {
+    /// Creates an empty registry
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// Maps a user to a firm and optional desk, replacing any previous mapping
+    pub fn register(&mut self, user_id: UserId, firm_id: FirmId, desk_id: Option<DeskId>) -> Option<AccountInfo> {
+        self.accounts.insert(user_id, AccountInfo { firm_id, desk_id })
+    }
+    
+    /// Removes a user's mapping
+    pub fn unregister(&mut self, user_id: &UserId) -> Option<AccountInfo> {
+        self.accounts.remove(user_id)
+    }
+    
+    /// Gets a user's mapping
+    pub fn get(&self, user_id: &UserId) -> Option<&AccountInfo> {
+        self.accounts.get(user_id)
+    }
+    
+    /// Gets the firm a user belongs to
+    pub fn firm_of(&self, user_id: &UserId) -> Option<&FirmId> {
+        self.accounts.get(user_id).map(|info| &info.firm_id)
+    }
+    
+    /// Checks if two users are mapped to the same firm
+    pub fn same_firm(&self, a: &UserId, b: &UserId) -> bool {
+        match (self.firm_of(a), self.firm_of(b)) {
+            (Some(firm_a), Some(firm_b)) => firm_a == firm_b,
+            _ => false,
+        }
+    }
+    
+    /// Checks if two users are mapped to the same desk of the same firm
+    pub fn same_desk(&self, a: &UserId, b: &UserId) -> bool {
+        match (self.accounts.get(a), self.accounts.get(b)) {
+            (Some(info_a), Some(info_b)) => {
+                info_a.firm_id == info_b.firm_id
+                    && info_a.desk_id.is_some()
+                    && info_a.desk_id == info_b.desk_id
+            },
+            _ => false,
+        }
+    }
+    
+    /// Number of mapped users
+    pub fn len(&self) -> usize {
+        self.accounts.len()
+    }
+    
+    /// Checks if no users are mapped
+    pub fn is_empty(&self) -> bool {
+        self.accounts.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn user(name: &str) -> UserId {
+        UserId::new(name.to_string())
+    }
+
+    #[test]
+    fn test_firm_and_desk_membership() {
+        let mut registry = AccountRegistry::new();
+        let firm = FirmId::new("ACME".to_string());
+        let rates = Some(DeskId::new("rates".to_string()));
+        
+        registry.register(user("alice"), firm.clone(), rates.clone());
+        registry.register(user("bob"), firm.clone(), rates);
+        registry.register(user("carol"), firm.clone(), Some(DeskId::new("fx".to_string())));
+        registry.register(user("dave"), firm, None);
+        
+        assert!(registry.same_desk(&user("alice"), &user("bob")));
+        assert!(!registry.same_desk(&user("alice"), &user("carol")));
+        assert!(!registry.same_desk(&user("dave"), &user("dave"))); // No desk assigned
+        assert!(registry.same_firm(&user("alice"), &user("dave")));
+        assert!(!registry.same_firm(&user("alice"), &user("unmapped")));
+        
+        registry.unregister(&user("bob"));
+        assert!(!registry.same_firm(&user("alice"), &user("bob")));
+        assert_eq!(registry.len(), 3);
+    }
+}
@@ -0,0 +1,515 @@
+//! Post-trade allocation of omnibus fills to sub-accounts
+//!
+//! Institutional flow is traded through a single omnibus user and split across
+//! sub-accounts after execution. The [`AllocationEngine`] tracks every recorded
+//! trade, the splits applied to it, and an audit trail of every change.
+
+use crate::{
+    order_book::{quantity_scale, Trade},
+    types::{SubAccountId, Symbol},
+    MatchingEngineError, Quantity,
+};
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::{btree_map::Entry, BTreeMap};
+
+/// Identifies a recorded trade
+///
+/// Trade IDs are only unique within one book, so allocations are keyed by the
+/// symbol the trade printed on as well.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct TradeKey {
+    pub symbol: Option<Symbol>,
+    pub trade_id: u64,
+}
+
+impl TradeKey {
+    /// Creates a key for a trade on the given symbol
+    pub fn new(symbol: Option<Symbol>, trade_id: u64) -> Self {
+        Self { symbol, trade_id }
+    }
+}
+
+impl From<&Trade> for TradeKey {
+    fn from(trade: &Trade) -> Self {
+        Self::new(trade.symbol.clone(), trade.trade_id)
+    }
+}
+
+impl std::fmt::Display for TradeKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.symbol {
+            Some(symbol) => write!(f, "{}#{}", symbol, self.trade_id),
+            None => write!(f, "{}", self.trade_id),
+        }
+    }
+}
+
+/// Quantity of a trade assigned to a single sub-account
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AllocationSplit {
+    pub sub_account: SubAccountId,
+    pub quantity: Quantity,
+}
+
+/// A recorded trade together with its current allocation
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TradeAllocation {
+    pub trade: Trade,
+    pub splits: Vec<AllocationSplit>,
+}
+
+impl TradeAllocation {
+    /// Total quantity allocated so far
+    pub fn allocated_quantity(&self) -> u64 {
+        self.splits.iter().map(|split| split.quantity.value()).sum()
+    }
+
+    /// Quantity still waiting to be allocated
+    pub fn unallocated_quantity(&self) -> u64 {
+        self.trade.quantity.value() - self.allocated_quantity()
+    }
+
+    /// Checks if the whole trade quantity has been allocated
+    pub fn is_fully_allocated(&self) -> bool {
+        self.unallocated_quantity() == 0
+    }
+}
+
+/// Kind of change recorded in the allocation audit trail
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AllocationAction {
+    /// Splits added to a trade (initial allocation or top-up)
+    Allocate,
+    /// Existing splits replaced by a new set
+    Amend,
+}
+
+/// Audit record of a single allocation change
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AllocationAuditEntry {
+    pub trade: TradeKey,
+    pub action: AllocationAction,
+    pub previous: Vec<AllocationSplit>,
+    pub current: Vec<AllocationSplit>,
+    pub reason: Option<String>,
+    pub timestamp: DateTime<Utc>,
+}
+
+/// Allocates omnibus trades to sub-accounts
+///
+/// Trades are registered with [`AllocationEngine::record_trade`] and stay
+/// queryable as unallocated until their full quantity has been split.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AllocationEngine {
+    /// Recorded trades keyed by symbol and trade ID (serialized as a list)
+    #[serde(serialize_with = "serialize_trades", deserialize_with = "deserialize_trades")]
+    trades: BTreeMap<TradeKey, TradeAllocation>,
+
+    /// Every allocation change in the order it was applied
+    audit_log: Vec<AllocationAuditEntry>,
+}
+
+impl AllocationEngine {
+    /// Creates an empty allocation engine
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers a trade for allocation
+    ///
+    /// Recording a trade whose symbol and trade ID are already known is
+    /// rejected and leaves the existing allocation untouched.
+    pub fn record_trade(&mut self, trade: Trade) -> crate::Result<()> {
+        match self.trades.entry(TradeKey::from(&trade)) {
+            Entry::Occupied(entry) => {
+                Err(MatchingEngineError::DuplicateTrade(entry.key().clone()))
+            }
+            Entry::Vacant(entry) => {
+                entry.insert(TradeAllocation { trade, splits: Vec::new() });
+                Ok(())
+            }
+        }
+    }
+
+    /// Registers several trades for allocation
+    ///
+    /// Stops at the first duplicate; the trades before it stay recorded.
+    pub fn record_trades(&mut self, trades: impl IntoIterator<Item = Trade>) -> crate::Result<()> {
+        for trade in trades {
+            self.record_trade(trade)?;
+        }
+        Ok(())
+    }
+
+    /// Allocates the outstanding quantity of a trade
+    ///
+    /// The splits must sum exactly to the unallocated quantity, which for a
+    /// fresh trade is the full trade quantity.
+    pub fn allocate(
+        &mut self,
+        trade: &TradeKey,
+        splits: Vec<(SubAccountId, Quantity)>,
+    ) -> crate::Result<&TradeAllocation> {
+        let allocation = self.trade_allocation(trade)?;
+        let requested = split_total(&splits);
+        let available = allocation.unallocated_quantity();
+        if requested > available {
+            return Err(MatchingEngineError::OverAllocation { trade: trade.clone(), requested, available });
+        }
+        if requested < available {
+            return Err(MatchingEngineError::AllocationMismatch {
+                trade: trade.clone(),
+                expected: available,
+                allocated: requested,
+            });
+        }
+        self.apply_splits(trade, splits)
+    }
+
+    /// Allocates part of a trade, leaving the rest for a later top-up
+    ///
+    /// The splits may not exceed the unallocated quantity.
+    pub fn allocate_partial(
+        &mut self,
+        trade: &TradeKey,
+        splits: Vec<(SubAccountId, Quantity)>,
+    ) -> crate::Result<&TradeAllocation> {
+        let allocation = self.trade_allocation(trade)?;
+        let requested = split_total(&splits);
+        let available = allocation.unallocated_quantity();
+        if requested > available {
+            return Err(MatchingEngineError::OverAllocation { trade: trade.clone(), requested, available });
+        }
+        self.apply_splits(trade, splits)
+    }
+
+    /// Replaces the allocation of a trade with a new set of splits
+    ///
+    /// The new splits must sum exactly to the trade quantity. The previous
+    /// splits are kept in the audit trail together with the reason.
+    pub fn reallocate(
+        &mut self,
+        trade: &TradeKey,
+        splits: Vec<(SubAccountId, Quantity)>,
+        reason: impl Into<String>,
+    ) -> crate::Result<&TradeAllocation> {
+        let allocation = self.trade_allocation(trade)?;
+        let requested = split_total(&splits);
+        let expected = allocation.trade.quantity.value();
+        if requested > expected {
+            return Err(MatchingEngineError::OverAllocation {
+                trade: trade.clone(),
+                requested,
+                available: expected,
+            });
+        }
+        if requested < expected {
+            return Err(MatchingEngineError::AllocationMismatch {
+                trade: trade.clone(),
+                expected,
+                allocated: requested,
+            });
+        }
+
+        let allocation = self.trades.get_mut(trade).expect("trade checked above");
+        let previous = std::mem::take(&mut allocation.splits);
+        merge_splits(&mut allocation.splits, splits);
+        self.audit_log.push(AllocationAuditEntry {
+            trade: trade.clone(),
+            action: AllocationAction::Amend,
+            previous,
+            current: allocation.splits.clone(),
+            reason: Some(reason.into()),
+            timestamp: Utc::now(),
+        });
+
+        Ok(allocation)
+    }
+
+    /// Gets the allocation state of a trade
+    pub fn allocation(&self, trade: &TradeKey) -> Option<&TradeAllocation> {
+        self.trades.get(trade)
+    }
+
+    /// Gets every trade that still has quantity waiting to be allocated
+    pub fn unallocated_trades(&self) -> Vec<&TradeAllocation> {
+        self.trades
+            .values()
+            .filter(|allocation| !allocation.is_fully_allocated())
+            .collect()
+    }
+
+    /// Gets the total quantity allocated to a sub-account, in shares
+    ///
+    /// Trades from books with different quantity scales count their lots in
+    /// different units, so the total is summed in shares rather than lots.
+    pub fn allocated_volume(&self, sub_account: &SubAccountId) -> Decimal {
+        self.splits_for(sub_account)
+            .map(|(trade, split)| split.quantity.to_decimal(trade.quantity_scale))
+            .sum()
+    }
+
+    /// Gets the total notional (price × quantity) allocated to a sub-account
+    pub fn allocated_notional(&self, sub_account: &SubAccountId) -> Decimal {
+        self.splits_for(sub_account)
+            .map(|(trade, split)| quantity_scale::notional(trade.price, split.quantity.value(), trade.quantity_scale))
+            .sum()
+    }
+
+    /// Gets the allocation audit trail in the order changes were applied
+    pub fn audit_log(&self) -> &[AllocationAuditEntry] {
+        &self.audit_log
+    }
+
+    // === Private Implementation ===
+
+    fn trade_allocation(&self, trade: &TradeKey) -> crate::Result<&TradeAllocation> {
+        self.trades
+            .get(trade)
+            .ok_or_else(|| MatchingEngineError::TradeNotFound(trade.clone()))
+    }
+
+    fn apply_splits(
+        &mut self,
+        trade: &TradeKey,
+        splits: Vec<(SubAccountId, Quantity)>,
+    ) -> crate::Result<&TradeAllocation> {
+        let allocation = self.trades.get_mut(trade)
+            .ok_or_else(|| MatchingEngineError::TradeNotFound(trade.clone()))?;
+        let previous = allocation.splits.clone();
+        merge_splits(&mut allocation.splits, splits);
+        self.audit_log.push(AllocationAuditEntry {
+            trade: trade.clone(),
+            action: AllocationAction::Allocate,
+            previous,
+            current: allocation.splits.clone(),
+            reason: None,
+            timestamp: Utc::now(),
+        });
+
+        Ok(allocation)
+    }
+
+    fn splits_for<'a>(
+        &'a self,
+        sub_account: &'a SubAccountId,
+    ) -> impl Iterator<Item = (&'a Trade, &'a AllocationSplit)> + 'a {
+        self.trades.values().flat_map(move |allocation| {
+            allocation.splits
+                .iter()
+                .filter(move |split| &split.sub_account == sub_account)
+                .map(move |split| (&allocation.trade, split))
+        })
+    }
+}
+
+/// Writes the recorded trades as a list; each key is carried by its trade
+fn serialize_trades<S: Serializer>(trades: &BTreeMap<TradeKey, TradeAllocation>, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_seq(trades.values())
+}
+
+/// Reads the recorded trades back, rebuilding each key from its trade
+fn deserialize_trades<'de, D: Deserializer<'de>>(deserializer: D) -> Result<BTreeMap<TradeKey, TradeAllocation>, D::Error> {
+    let allocations = Vec::<TradeAllocation>::deserialize(deserializer)?;
+    Ok(allocations
+        .into_iter()
+        .map(|allocation| (TradeKey::from(&allocation.trade), allocation))
+        .collect())
+}
+
+fn split_total(splits: &[(SubAccountId, Quantity)]) -> u64 {
+    splits.iter().map(|(_, quantity)| quantity.value()).sum()
+}
+
+/// Adds splits to an existing list, combining entries for the same sub-account
+fn merge_splits(existing: &mut Vec<AllocationSplit>, splits: Vec<(SubAccountId, Quantity)>) {
+    for (sub_account, quantity) in splits {
+        match existing.iter_mut().find(|split| split.sub_account == sub_account) {
+            Some(split) => split.quantity = split.quantity + quantity,
+            None => existing.push(AllocationSplit { sub_account, quantity }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{types::OrderId, Price};
+
+    fn create_trade(trade_id: u64, price_cents: i64, quantity: u64) -> Trade {
+        Trade {
+            trade_id,
+            buy_order_id: OrderId::new(),
+            sell_order_id: OrderId::new(),
+            maker_order_id: None,
+            taker_order_id: None,
+            aggressor_side: None,
+            buyer_user_id: None,
+            seller_user_id: None,
+            price: Price::from_cents(price_cents).unwrap(),
+            quantity: Quantity::new(quantity).unwrap(),
+            timestamp: Utc::now(),
+            same_firm: false,
+            maker_fee: Decimal::ZERO,
+            taker_fee: Decimal::ZERO,
+            symbol: None,
+            sequence: 0,
+            quantity_scale: 0,
+        }
+    }
+
+    fn key(trade_id: u64) -> TradeKey {
+        TradeKey::new(None, trade_id)
+    }
+
+    fn account(name: &str) -> SubAccountId {
+        SubAccountId::new(name.to_string())
+    }
+
+    fn qty(value: u64) -> Quantity {
+        Quantity::new(value).unwrap()
+    }
+
+    #[test]
+    fn test_full_allocation() {
+        let mut engine = AllocationEngine::new();
+        engine.record_trade(create_trade(1, 15000, 100)).unwrap();
+        assert_eq!(engine.unallocated_trades().len(), 1);
+
+        let allocation = engine
+            .allocate(&key(1), vec![(account("A"), qty(60)), (account("B"), qty(40))])
+            .unwrap();
+        assert!(allocation.is_fully_allocated());
+        assert!(engine.unallocated_trades().is_empty());
+        assert_eq!(engine.allocated_volume(&account("A")), Decimal::from(60));
+        assert_eq!(engine.allocated_notional(&account("B")), Decimal::new(600000, 2));
+    }
+
+    #[test]
+    fn test_allocation_must_sum_to_trade_quantity() {
+        let mut engine = AllocationEngine::new();
+        engine.record_trade(create_trade(1, 15000, 100)).unwrap();
+
+        let result = engine.allocate(&key(1), vec![(account("A"), qty(60))]);
+        assert_eq!(
+            result.unwrap_err(),
+            MatchingEngineError::AllocationMismatch { trade: key(1), expected: 100, allocated: 60 }
+        );
+
+        let result = engine.allocate(&key(1), vec![(account("A"), qty(60)), (account("B"), qty(41))]);
+        assert_eq!(
+            result.unwrap_err(),
+            MatchingEngineError::OverAllocation { trade: key(1), requested: 101, available: 100 }
+        );
+
+        assert!(engine.allocation(&key(1)).unwrap().splits.is_empty());
+        assert!(engine.audit_log().is_empty());
+        assert_eq!(engine.allocate(&key(2), vec![]).unwrap_err(), MatchingEngineError::TradeNotFound(key(2)));
+    }
+
+    #[test]
+    fn test_partial_allocation_and_top_up() {
+        let mut engine = AllocationEngine::new();
+        engine.record_trade(create_trade(7, 10000, 300)).unwrap();
+
+        engine.allocate_partial(&key(7), vec![(account("A"), qty(100))]).unwrap();
+        assert_eq!(engine.unallocated_trades()[0].unallocated_quantity(), 200);
+
+        // Over-allocating the remainder is rejected and leaves the state untouched
+        let result = engine.allocate_partial(&key(7), vec![(account("B"), qty(201))]);
+        assert_eq!(
+            result.unwrap_err(),
+            MatchingEngineError::OverAllocation { trade: key(7), requested: 201, available: 200 }
+        );
+
+        engine.allocate_partial(&key(7), vec![(account("A"), qty(50))]).unwrap();
+        engine.allocate(&key(7), vec![(account("B"), qty(150))]).unwrap();
+
+        let allocation = engine.allocation(&key(7)).unwrap();
+        assert!(allocation.is_fully_allocated());
+        assert_eq!(allocation.splits.len(), 2); // Top-up merged into the existing split
+        assert_eq!(engine.allocated_volume(&account("A")), Decimal::from(150));
+        assert_eq!(engine.audit_log().len(), 3);
+    }
+
+    #[test]
+    fn test_reallocation_records_audit_entry() {
+        let mut engine = AllocationEngine::new();
+        engine.record_trade(create_trade(3, 5000, 100)).unwrap();
+        engine.allocate(&key(3), vec![(account("A"), qty(100))]).unwrap();
+
+        assert!(engine.reallocate(&key(3), vec![(account("B"), qty(99))], "typo").is_err());
+
+        engine
+            .reallocate(&key(3), vec![(account("A"), qty(30)), (account("B"), qty(70))], "client instruction")
+            .unwrap();
+
+        assert_eq!(engine.allocated_volume(&account("A")), Decimal::from(30));
+        assert_eq!(engine.allocated_volume(&account("B")), Decimal::from(70));
+
+        let entry = engine.audit_log().last().unwrap();
+        assert_eq!(entry.action, AllocationAction::Amend);
+        assert_eq!(entry.previous, vec![AllocationSplit { sub_account: account("A"), quantity: qty(100) }]);
+        assert_eq!(entry.current.len(), 2);
+        assert_eq!(entry.reason.as_deref(), Some("client instruction"));
+    }
+
+    #[test]
+    fn test_duplicate_trades_are_rejected() {
+        let mut engine = AllocationEngine::new();
+        engine.record_trade(create_trade(1, 15000, 100)).unwrap();
+        engine.allocate(&key(1), vec![(account("A"), qty(100))]).unwrap();
+
+        let result = engine.record_trade(create_trade(1, 16000, 50));
+        assert_eq!(result.unwrap_err(), MatchingEngineError::DuplicateTrade(key(1)));
+
+        // The original trade and its allocation are untouched
+        let allocation = engine.allocation(&key(1)).unwrap();
+        assert_eq!(allocation.trade.quantity, qty(100));
+        assert!(allocation.is_fully_allocated());
+    }
+
+    #[test]
+    fn test_fractional_trades_are_valued_in_shares() {
+        let mut engine = AllocationEngine::new();
+        let mut trade = create_trade(1, 15000, 1_500);
+        trade.quantity_scale = 3;
+        engine.record_trade(trade).unwrap();
+        engine.allocate(&key(1), vec![(account("A"), qty(1_250)), (account("B"), qty(250))]).unwrap();
+
+        // 1.25 and 0.25 shares at 150.00
+        assert_eq!(engine.allocated_volume(&account("A")), Decimal::new(1_250, 3));
+        assert_eq!(engine.allocated_notional(&account("A")), Decimal::new(18_750, 2));
+        assert_eq!(engine.allocated_notional(&account("B")), Decimal::new(3_750, 2));
+
+        // Volume from a whole-share book adds up in the same unit
+        engine.record_trade(create_trade(2, 15000, 2)).unwrap();
+        engine.allocate(&key(2), vec![(account("A"), qty(2))]).unwrap();
+        assert_eq!(engine.allocated_volume(&account("A")), Decimal::new(3_250, 3));
+    }
+
+    #[test]
+    fn test_trade_ids_are_scoped_by_symbol() {
+        let symbol = |name: &str| Some(Symbol::new(name.to_string()).unwrap());
+        let mut engine = AllocationEngine::new();
+        let mut aapl = create_trade(1, 15000, 100);
+        aapl.symbol = symbol("AAPL");
+        let mut msft = create_trade(1, 30000, 40);
+        msft.symbol = symbol("MSFT");
+        engine.record_trades([aapl, msft]).unwrap();
+        assert_eq!(engine.unallocated_trades().len(), 2);
+
+        let msft_key = TradeKey::new(symbol("MSFT"), 1);
+        engine.allocate(&msft_key, vec![(account("A"), qty(40))]).unwrap();
+        assert_eq!(engine.allocated_notional(&account("A")), Decimal::new(1_200_000, 2));
+        assert!(engine.allocation(&key(1)).is_none());
+
+        // Allocations survive a round trip with their keys
+        let json = serde_json::to_string(&engine).unwrap();
+        let restored: AllocationEngine = serde_json::from_str(&json).unwrap();
+        assert!(restored.allocation(&msft_key).unwrap().is_fully_allocated());
+        assert_eq!(restored.unallocated_trades().len(), 1);
+    }
+}
@@ -0,0 +1,101 @@
+//! Multi-symbol engine owning one order book per symbol
+
+use crate::{
+    events::{ExecutionResult, ModifyResult},
+    order_book::LimitOrderBook,
+    types::{OrderId, Symbol},
+    MatchingEngineError, Order, Price, Quantity,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+mod basket;
+mod oco;
+
+pub use basket::{BasketReject, BasketResult, ConstituentFailure};
+pub use oco::{OcoLink, OcoPolicy};
+
+/// Routes orders to per-symbol books and enforces cross-book contingencies
+///
+/// Contingent actions triggered by a fill are applied before the call that
+/// produced the fill returns. The engine serializes as a whole, including
+/// every book and all contingency bookkeeping.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MultiBookEngine {
+    books: HashMap<Symbol, LimitOrderBook>,
+    
+    /// Cross-book OCO links by link ID
+    #[serde(default)]
+    oco_links: BTreeMap<u64, OcoLink>,
+    
+    #[serde(default)]
+    next_link_id: u64,
+}
+
+impl MultiBookEngine {
+    /// Creates an engine with no books
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// Adds a book, failing if one already exists for its symbol
+    pub fn add_book(&mut self, book: LimitOrderBook) -> crate::Result<()> {
+        let symbol = book.symbol().clone();
+        if self.books.contains_key(&symbol) {
+            return Err(MatchingEngineError::DuplicateSymbol(symbol.to_string()));
+        }
+        self.books.insert(symbol, book);
+        Ok(())
+    }
+    
+    /// Gets the book for a symbol
+    pub fn book(&self, symbol: &Symbol) -> Option<&LimitOrderBook> {
+        self.books.get(symbol)
+    }
+    
+    /// Gets mutable access to the book for a symbol
+    ///
+    /// Changes made directly on the book bypass contingency enforcement;
+    /// links whose legs disappear this way are dissolved on the next engine call.
+    pub fn book_mut(&mut self, symbol: &Symbol) -> Option<&mut LimitOrderBook> {
+        self.books.get_mut(symbol)
+    }
+    
+    /// Gets the symbols with a book, in no particular order
+    pub fn symbols(&self) -> impl Iterator<Item = &Symbol> {
+        self.books.keys()
+    }
+    
+    /// Submits an order to a symbol's book, then applies any contingencies its fills trigger
+    pub fn submit_order(&mut self, symbol: &Symbol, order: Order) -> crate::Result<ExecutionResult> {
+        let mut result = self.book_entry(symbol)?.submit_order(order)?;
+        self.apply_contingencies(symbol, &result.trades, &mut result.events);
+        Ok(result)
+    }
+    
+    /// Cancels an order, dissolving any contingency link it belongs to
+    pub fn cancel_order(&mut self, symbol: &Symbol, order_id: OrderId) -> crate::Result<Order> {
+        let order = self.book_entry(symbol)?.cancel_order(order_id)?;
+        self.prune_links();
+        Ok(order)
+    }
+    
+    /// Modifies an order, then applies any contingencies a cancel-replace fill triggers
+    pub fn modify_order(
+        &mut self,
+        symbol: &Symbol,
+        order_id: OrderId,
+        new_price: Option<Price>,
+        new_quantity: Option<Quantity>,
+    ) -> crate::Result<ModifyResult> {
+        let mut result = self.book_entry(symbol)?.modify_order(order_id, new_price, new_quantity)?;
+        self.apply_contingencies(symbol, &result.trades, &mut result.events);
+        Ok(result)
+    }
+    
+    fn book_entry(&mut self, symbol: &Symbol) -> crate::Result<&mut LimitOrderBook> {
+        self.books
+            .get_mut(symbol)
+            .ok_or_else(|| MatchingEngineError::UnknownSymbol(symbol.to_string()))
+    }
+}
@@ -0,0 +1,245 @@
+//! All-or-nothing basket submission across symbols
+//!
+//! Atomicity is about acceptance, not execution: every constituent is
+//! validated before any book is touched, and a basket that fails validation
+//! leaves every book unchanged. Accepted constituents are then applied in
+//! submission order and may trade on entry. If an apply fails after
+//! validation passed, the resting remainders of the constituents already
+//! applied are cancelled; trades they produced cannot be undone and are
+//! reported alongside the rollback.
+
+use super::MultiBookEngine;
+use crate::{
+    events::ExecutionResult,
+    types::Symbol,
+    MatchingEngineError, Order,
+};
+use std::collections::HashSet;
+
+/// Accepted basket: one execution result per constituent, in submission order
+#[derive(Debug, Clone, PartialEq)]
+pub struct BasketResult {
+    pub constituents: Vec<(Symbol, ExecutionResult)>,
+}
+
+/// A constituent that could not be accepted
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConstituentFailure {
+    /// Position of the constituent in the submitted basket
+    pub index: usize,
+    pub symbol: Symbol,
+    pub error: MatchingEngineError,
+}
+
+/// Rejected basket
+#[derive(Debug, Clone, PartialEq)]
+pub struct BasketReject {
+    /// Every constituent that failed, in submission order
+    pub failures: Vec<ConstituentFailure>,
+    /// Constituents applied before a failure, with what happened to them;
+    /// their resting remainders have been cancelled. Empty when the basket
+    /// failed validation.
+    pub rolled_back: Vec<(Symbol, ExecutionResult)>,
+}
+
+impl std::fmt::Display for BasketReject {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "basket rejected: ")?;
+        for (i, failure) in self.failures.iter().enumerate() {
+            if i > 0 {
+                write!(f, "; ")?;
+            }
+            write!(f, "#{} {}: {}", failure.index, failure.symbol, failure.error)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for BasketReject {}
+
+impl MultiBookEngine {
+    /// Submits one order per symbol, accepting all of them or none
+    ///
+    /// See the module documentation for the exact atomicity guarantee.
+    pub fn submit_basket(&mut self, orders: Vec<(Symbol, Order)>) -> Result<BasketResult, BasketReject> {
+        let failures = self.validate_basket(&orders);
+        if !failures.is_empty() {
+            return Err(BasketReject { failures, rolled_back: Vec::new() });
+        }
+        self.apply_basket(orders)
+    }
+    
+    /// Runs every check for every constituent without mutating anything
+    fn validate_basket(&self, orders: &[(Symbol, Order)]) -> Vec<ConstituentFailure> {
+        let mut symbols = HashSet::new();
+        let mut failures = Vec::new();
+        
+        for (index, (symbol, order)) in orders.iter().enumerate() {
+            let check = if !symbols.insert(symbol) {
+                Err(MatchingEngineError::DuplicateSymbol(symbol.to_string()))
+            } else {
+                self.books
+                    .get(symbol)
+                    .ok_or_else(|| MatchingEngineError::UnknownSymbol(symbol.to_string()))
+                    .and_then(|book| book.validate_order(order))
+            };
+            if let Err(error) = check {
+                failures.push(ConstituentFailure { index, symbol: symbol.clone(), error });
+            }
+        }
+        failures
+    }
+    
+    fn apply_basket(&mut self, orders: Vec<(Symbol, Order)>) -> Result<BasketResult, BasketReject> {
+        let mut applied: Vec<(Symbol, ExecutionResult)> = Vec::new();
+        
+        for (index, (symbol, order)) in orders.into_iter().enumerate() {
+            match self.submit_order(&symbol, order) {
+                Ok(result) => applied.push((symbol, result)),
+                Err(error) => {
+                    for (applied_symbol, result) in &applied {
+                        // Already filled or cancelled remainders have nothing to roll back
+                        let _ = self.cancel_order(applied_symbol, result.order_id);
+                    }
+                    return Err(BasketReject {
+                        failures: vec![ConstituentFailure { index, symbol, error }],
+                        rolled_back: applied,
+                    });
+                },
+            }
+        }
+        
+        Ok(BasketResult { constituents: applied })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        BookConfig, LimitOrderBook, OcoPolicy, OrderSide, Price, PriceBand,
+    };
+    use rust_decimal::Decimal;
+    use crate::order_book::test_support::create_order;
+
+    fn symbol(name: &str) -> Symbol {
+        Symbol::new(name.to_string()).unwrap()
+    }
+
+    /// AAA, BBB, and CCC each with a 100-lot ask at 100.00; CCC is halted
+    fn engine() -> MultiBookEngine {
+        let mut engine = MultiBookEngine::new();
+        for name in ["AAA", "BBB", "CCC"] {
+            let mut book = LimitOrderBook::new(name.to_string()).unwrap();
+            book.add_order(create_order(OrderSide::Sell, 10000, 100, "program")).unwrap();
+            engine.add_book(book).unwrap();
+        }
+        engine.book_mut(&symbol("CCC")).unwrap().halt();
+        engine
+    }
+
+    fn snapshot(engine: &MultiBookEngine) -> String {
+        let mut books: Vec<_> = engine.symbols()
+            .map(|symbol| serde_json::to_string(engine.book(symbol).unwrap()).unwrap())
+            .collect();
+        books.sort();
+        books.concat()
+    }
+
+    #[test]
+    fn test_basket_with_one_bad_constituent_changes_nothing() {
+        let mut engine = engine();
+        let before = snapshot(&engine);
+        
+        let reject = engine.submit_basket(vec![
+            (symbol("AAA"), create_order(OrderSide::Buy, 10000, 50, "program")),
+            (symbol("BBB"), create_order(OrderSide::Buy, 9900, 50, "program")),
+            (symbol("CCC"), create_order(OrderSide::Buy, 10000, 50, "program")), // Would trade into a halted book
+            (symbol("DDD"), create_order(OrderSide::Buy, 10000, 50, "program")),
+        ]).unwrap_err();
+        
+        let failed: Vec<usize> = reject.failures.iter().map(|failure| failure.index).collect();
+        assert_eq!(failed, vec![2, 3]);
+        assert!(matches!(reject.failures[0].error, MatchingEngineError::BookHalted(_)));
+        assert!(matches!(reject.failures[1].error, MatchingEngineError::UnknownSymbol(_)));
+        assert!(reject.rolled_back.is_empty());
+        assert_eq!(snapshot(&engine), before);
+    }
+
+    #[test]
+    fn test_accepted_basket_applies_in_order() {
+        let mut engine = engine();
+        
+        let result = engine.submit_basket(vec![
+            (symbol("BBB"), create_order(OrderSide::Buy, 10000, 150, "program")),
+            (symbol("AAA"), create_order(OrderSide::Buy, 9900, 50, "program")),
+        ]).unwrap();
+        
+        let symbols: Vec<&str> = result.constituents.iter().map(|(symbol, _)| symbol.as_str()).collect();
+        assert_eq!(symbols, vec!["BBB", "AAA"]);
+        assert_eq!(result.constituents[0].1.trades.len(), 1);
+        assert!(result.constituents[1].1.trades.is_empty());
+        assert_eq!(engine.book(&symbol("BBB")).unwrap().best_bid_quantity().unwrap().value(), 50);
+    }
+
+    #[test]
+    fn test_duplicate_symbol_rejected() {
+        let mut engine = engine();
+        let reject = engine.submit_basket(vec![
+            (symbol("AAA"), create_order(OrderSide::Buy, 9900, 50, "program")),
+            (symbol("AAA"), create_order(OrderSide::Buy, 9800, 50, "program")),
+        ]).unwrap_err();
+        assert!(matches!(reject.failures[..], [ConstituentFailure { index: 1, error: MatchingEngineError::DuplicateSymbol(_), .. }]));
+        assert!(engine.book(&symbol("AAA")).unwrap().best_bid().is_none());
+    }
+
+    #[test]
+    fn test_off_tick_constituent_changes_nothing() {
+        let mut engine = engine();
+        let config = BookConfig { tick_size: Decimal::new(5, 2), ..BookConfig::default() };
+        engine.add_book(LimitOrderBook::with_config("TCK".to_string(), config).unwrap()).unwrap();
+        let before = snapshot(&engine);
+        
+        let reject = engine.submit_basket(vec![
+            (symbol("AAA"), create_order(OrderSide::Buy, 10000, 50, "program")),
+            (symbol("TCK"), create_order(OrderSide::Buy, 9903, 50, "program")),
+        ]).unwrap_err();
+        
+        assert!(matches!(
+            reject.failures[..],
+            [ConstituentFailure { index: 1, error: MatchingEngineError::InvalidTick { .. }, .. }]
+        ));
+        assert!(reject.rolled_back.is_empty());
+        assert_eq!(snapshot(&engine), before);
+    }
+
+    #[test]
+    fn test_apply_failure_rolls_back_applied_constituents() {
+        let mut engine = engine();
+        let ask_id = |engine: &MultiBookEngine, name: &str| {
+            engine.book(&symbol(name)).unwrap().orders_at_price(OrderSide::Sell, Price::from_cents(10000).unwrap()).unwrap()[0].id
+        };
+        // BBB's band falls back to 50.00 once its ask is gone and the book no longer quotes a mid
+        let bbb = engine.book_mut(&symbol("BBB")).unwrap();
+        bbb.add_order(create_order(OrderSide::Buy, 9800, 100, "program")).unwrap();
+        bbb.set_price_band(Some(PriceBand::new(Decimal::new(10, 2)).with_fallback_reference(Price::from_cents(5000).unwrap())));
+        let (aaa_ask, bbb_ask) = (ask_id(&engine, "AAA"), ask_id(&engine, "BBB"));
+        engine.link_oco((symbol("AAA"), aaa_ask), (symbol("BBB"), bbb_ask), OcoPolicy::CancelOnFill).unwrap();
+        
+        // Both constituents validate, but AAA's fill cancels BBB's ask before BBB's turn
+        let reject = engine.submit_basket(vec![
+            (symbol("AAA"), create_order(OrderSide::Buy, 10000, 150, "program")),
+            (symbol("BBB"), create_order(OrderSide::Buy, 9900, 50, "program")),
+        ]).unwrap_err();
+        
+        assert!(matches!(
+            reject.failures[..],
+            [ConstituentFailure { index: 1, error: MatchingEngineError::PriceOutsideBand { .. }, .. }]
+        ));
+        // The 100 traded on AAA stands; its resting remainder is gone
+        assert_eq!(reject.rolled_back.len(), 1);
+        assert_eq!(reject.rolled_back[0].1.trades.len(), 1);
+        assert!(engine.book(&symbol("AAA")).unwrap().best_bid().is_none());
+        assert_eq!(engine.book(&symbol("BBB")).unwrap().best_bid_quantity().unwrap().value(), 100);
+    }
+}
@@ -0,0 +1,284 @@
+//! Cross-book one-cancels-other (OCO) contingencies
+//!
+//! A link joins two resting orders, usually on different symbols. When either
+//! leg fills, the engine applies the link's policy to the other leg before
+//! returning from the call that produced the fill. Links dissolve as soon as
+//! either leg stops resting, whether filled, cancelled, or expired.
+
+use super::MultiBookEngine;
+use crate::{
+    events::{CancelReason, OrderEvent},
+    order_book::Trade,
+    types::{OrderId, Symbol},
+    MatchingEngineError, Quantity,
+};
+use serde::{Deserialize, Serialize};
+
+/// Action applied to the other leg when one leg of a link fills
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum OcoPolicy {
+    /// Cancel the other leg on any fill
+    CancelOnFill,
+    /// Reduce the other leg by the same fraction of its linked size as the
+    /// filled leg has executed, rounding the cumulative reduction down
+    ReduceProportionally,
+}
+
+/// Link between two resting orders
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OcoLink {
+    pub legs: [(Symbol, OrderId); 2],
+    pub policy: OcoPolicy,
+    /// Remaining quantity of each leg when the link was created
+    pub linked_quantity: [u64; 2],
+    /// Quantity each leg has filled since the link was created
+    pub filled: [u64; 2],
+    /// Quantity removed from each leg by proportional reduction
+    pub reduced: [u64; 2],
+}
+
+impl MultiBookEngine {
+    /// Links two resting orders so a fill on either triggers `policy` on the other
+    ///
+    /// Returns the link ID. Each order may belong to at most one link.
+    pub fn link_oco(
+        &mut self,
+        leg_a: (Symbol, OrderId),
+        leg_b: (Symbol, OrderId),
+        policy: OcoPolicy,
+    ) -> crate::Result<u64> {
+        self.prune_links();
+        let mut linked_quantity = [0; 2];
+        for (index, (symbol, order_id)) in [&leg_a, &leg_b].into_iter().enumerate() {
+            let book = self.books
+                .get(symbol)
+                .ok_or_else(|| MatchingEngineError::UnknownSymbol(symbol.to_string()))?;
+            let order = book.get_order(*order_id)
+                .ok_or_else(|| MatchingEngineError::OrderNotFound(order_id.to_string()))?;
+            if self.find_leg(symbol, *order_id).is_some() {
+                return Err(MatchingEngineError::AlreadyLinked(order_id.to_string()));
+            }
+            linked_quantity[index] = order.remaining_quantity.value();
+        }
+        if leg_a == leg_b {
+            return Err(MatchingEngineError::AlreadyLinked(leg_a.1.to_string()));
+        }
+        
+        let link_id = self.next_link_id;
+        self.next_link_id += 1;
+        self.oco_links.insert(link_id, OcoLink {
+            legs: [leg_a, leg_b],
+            policy,
+            linked_quantity,
+            filled: [0; 2],
+            reduced: [0; 2],
+        });
+        Ok(link_id)
+    }
+    
+    /// Removes a link without touching its orders
+    pub fn unlink_oco(&mut self, link_id: u64) -> Option<OcoLink> {
+        self.oco_links.remove(&link_id)
+    }
+    
+    /// Gets a link by ID
+    pub fn oco_link(&self, link_id: u64) -> Option<&OcoLink> {
+        self.oco_links.get(&link_id)
+    }
+    
+    /// Gets the number of active links
+    pub fn oco_link_count(&self) -> usize {
+        self.oco_links.len()
+    }
+    
+    /// Applies the contingencies triggered by fills on `symbol`, reporting them in `events`
+    pub(super) fn apply_contingencies(&mut self, symbol: &Symbol, trades: &[Trade], events: &mut Vec<OrderEvent>) {
+        for trade in trades {
+            for order_id in [trade.buy_order_id, trade.sell_order_id] {
+                if let Some((link_id, leg)) = self.find_leg(symbol, order_id) {
+                    self.trigger(link_id, leg, trade.quantity.value(), events);
+                }
+            }
+        }
+        self.prune_links();
+    }
+    
+    /// Dissolves links with a leg that is no longer resting
+    pub(super) fn prune_links(&mut self) {
+        let books = &self.books;
+        self.oco_links.retain(|_, link| {
+            link.legs.iter().all(|(symbol, order_id)| {
+                books.get(symbol).is_some_and(|book| book.get_order(*order_id).is_some())
+            })
+        });
+    }
+    
+    fn find_leg(&self, symbol: &Symbol, order_id: OrderId) -> Option<(u64, usize)> {
+        self.oco_links.iter().find_map(|(link_id, link)| {
+            link.legs.iter()
+                .position(|(leg_symbol, leg_id)| leg_symbol == symbol && *leg_id == order_id)
+                .map(|leg| (*link_id, leg))
+        })
+    }
+    
+    fn trigger(&mut self, link_id: u64, leg: usize, quantity: u64, events: &mut Vec<OrderEvent>) {
+        let Some(link) = self.oco_links.get_mut(&link_id) else {
+            return;
+        };
+        let other = 1 - leg;
+        let (symbol, order_id) = link.legs[other].clone();
+        let reason = CancelReason::Contingency;
+        
+        let reduction = match link.policy {
+            OcoPolicy::CancelOnFill => None,
+            OcoPolicy::ReduceProportionally => {
+                link.filled[leg] += quantity;
+                let target = (link.filled[leg] as u128 * link.linked_quantity[other] as u128
+                    / link.linked_quantity[leg].max(1) as u128) as u64;
+                let delta = target.saturating_sub(link.reduced[other]);
+                link.reduced[other] = link.reduced[other].max(target);
+                Some(delta)
+            },
+        };
+        
+        let Some(book) = self.books.get_mut(&symbol) else {
+            return;
+        };
+        let Some(order) = book.get_order(order_id) else {
+            return;
+        };
+        match reduction {
+            Some(0) => {},
+            Some(delta) if delta < order.remaining_quantity.value() => {
+                let new_quantity = Quantity::new_allow_zero(order.original_quantity.value() - delta);
+                if book.modify_order(order_id, None, Some(new_quantity)).is_ok() {
+                    events.push(OrderEvent::Decremented {
+                        order_id,
+                        quantity: Quantity::new_allow_zero(delta),
+                        reason,
+                    });
+                }
+            },
+            _ => {
+                if let Ok(order) = book.cancel_order(order_id) {
+                    events.push(OrderEvent::Cancelled { order, reason });
+                }
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        LimitOrderBook, OrderSide,
+    };
+    use crate::order_book::test_support::create_order;
+
+    fn symbol(name: &str) -> Symbol {
+        Symbol::new(name.to_string()).unwrap()
+    }
+
+    /// Engine with a 100-lot AAA bid and a `b_quantity` BBB ask, linked under `policy`
+    fn linked_engine(policy: OcoPolicy, b_quantity: u64) -> (MultiBookEngine, OrderId, OrderId) {
+        let mut engine = MultiBookEngine::new();
+        engine.add_book(LimitOrderBook::new("AAA".to_string()).unwrap()).unwrap();
+        engine.add_book(LimitOrderBook::new("BBB".to_string()).unwrap()).unwrap();
+        
+        let a = create_order(OrderSide::Buy, 10000, 100, "hedger");
+        let b = create_order(OrderSide::Sell, 5000, b_quantity, "hedger");
+        let (a_id, b_id) = (a.id, b.id);
+        engine.submit_order(&symbol("AAA"), a).unwrap();
+        engine.submit_order(&symbol("BBB"), b).unwrap();
+        engine.link_oco((symbol("AAA"), a_id), (symbol("BBB"), b_id), policy).unwrap();
+        (engine, a_id, b_id)
+    }
+
+    #[test]
+    fn test_fill_on_a_cancels_b() {
+        let (mut engine, a_id, b_id) = linked_engine(OcoPolicy::CancelOnFill, 50);
+        
+        let result = engine.submit_order(&symbol("AAA"), create_order(OrderSide::Sell, 10000, 10, "hedger")).unwrap();
+        
+        assert_eq!(result.trades[0].buy_order_id, a_id);
+        assert!(matches!(
+            &result.events[..],
+            [OrderEvent::Cancelled { order, reason: CancelReason::Contingency }] if order.id == b_id
+        ));
+        assert!(engine.book(&symbol("BBB")).unwrap().get_order(b_id).is_none());
+        assert_eq!(engine.oco_link_count(), 0);
+        
+        // A keeps its remaining 90 and is no longer linked
+        assert_eq!(engine.book(&symbol("AAA")).unwrap().get_order(a_id).unwrap().remaining_quantity.value(), 90);
+    }
+
+    #[test]
+    fn test_proportional_reduction() {
+        let (mut engine, _, b_id) = linked_engine(OcoPolicy::ReduceProportionally, 50);
+        let remaining = |engine: &MultiBookEngine| {
+            engine.book(&symbol("BBB")).unwrap().get_order(b_id).map(|o| o.remaining_quantity.value())
+        };
+        
+        // 33% of A filled: floor(33 × 50 / 100) = 16 removed from B
+        let result = engine.submit_order(&symbol("AAA"), create_order(OrderSide::Sell, 10000, 33, "hedger")).unwrap();
+        assert!(matches!(&result.events[..], [OrderEvent::Decremented { quantity, .. }] if quantity.value() == 16));
+        assert_eq!(remaining(&engine), Some(34));
+        
+        // Cumulative 50%: 25 removed in total, so 9 more
+        engine.submit_order(&symbol("AAA"), create_order(OrderSide::Sell, 10000, 17, "hedger")).unwrap();
+        assert_eq!(remaining(&engine), Some(25));
+        
+        // A fills completely: B is cancelled and the link dissolves
+        let result = engine.submit_order(&symbol("AAA"), create_order(OrderSide::Sell, 10000, 50, "hedger")).unwrap();
+        assert!(matches!(&result.events[..], [OrderEvent::Cancelled { .. }]));
+        assert_eq!(remaining(&engine), None);
+        assert_eq!(engine.oco_link_count(), 0);
+    }
+
+    #[test]
+    fn test_manual_cancel_dissolves_link() {
+        let (mut engine, a_id, b_id) = linked_engine(OcoPolicy::CancelOnFill, 50);
+        
+        engine.cancel_order(&symbol("AAA"), a_id).unwrap();
+        assert_eq!(engine.oco_link_count(), 0);
+        
+        // B no longer reacts to anything on AAA
+        let trades = engine.submit_order(&symbol("BBB"), create_order(OrderSide::Buy, 5000, 10, "hedger")).unwrap().trades;
+        assert_eq!(trades[0].sell_order_id, b_id);
+        assert!(engine.book(&symbol("BBB")).unwrap().get_order(b_id).is_some());
+    }
+
+    #[test]
+    fn test_links_survive_snapshots() {
+        let (engine, _, b_id) = linked_engine(OcoPolicy::CancelOnFill, 50);
+        
+        let json = serde_json::to_string(&engine).unwrap();
+        let mut restored: MultiBookEngine = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored.oco_link_count(), 1);
+        
+        restored.submit_order(&symbol("AAA"), create_order(OrderSide::Sell, 10000, 1, "hedger")).unwrap();
+        assert!(restored.book(&symbol("BBB")).unwrap().get_order(b_id).is_none());
+    }
+
+    #[test]
+    fn test_link_validation() {
+        let (mut engine, a_id, _) = linked_engine(OcoPolicy::CancelOnFill, 50);
+        let c = create_order(OrderSide::Buy, 4900, 10, "hedger");
+        let c_id = c.id;
+        engine.submit_order(&symbol("BBB"), c).unwrap();
+        
+        assert!(matches!(
+            engine.link_oco((symbol("AAA"), a_id), (symbol("BBB"), c_id), OcoPolicy::CancelOnFill),
+            Err(MatchingEngineError::AlreadyLinked(_))
+        ));
+        assert!(matches!(
+            engine.link_oco((symbol("CCC"), a_id), (symbol("BBB"), c_id), OcoPolicy::CancelOnFill),
+            Err(MatchingEngineError::UnknownSymbol(_))
+        ));
+        assert!(matches!(
+            engine.link_oco((symbol("BBB"), OrderId::new()), (symbol("BBB"), c_id), OcoPolicy::CancelOnFill),
+            Err(MatchingEngineError::OrderNotFound(_))
+        ));
+    }
+}
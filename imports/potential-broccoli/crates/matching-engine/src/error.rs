@@ -1,5 +1,7 @@
 //! Error types for the matching engine
 
+use crate::{allocation::TradeKey, order_book::QuantityRule, types::{OrderId, Symbol}, Price, Quantity};
+use rust_decimal::Decimal;
 use thiserror::Error;
 
 /// Comprehensive error handling for all matching engine operations
@@ -31,4 +33,67 @@ pub enum MatchingEngineError {
     
     #[error("Invariant violation: {0}")]
     InvariantViolation(String),
+    
+    #[error("Trade not found: {0}")]
+    TradeNotFound(TradeKey),
+    
+    #[error("Trade already recorded: {0}")]
+    DuplicateTrade(TradeKey),
+    
+    #[error("Over-allocation of trade {trade}. Requested: {requested}, Unallocated: {available}")]
+    OverAllocation { trade: TradeKey, requested: u64, available: u64 },
+    
+    #[error("Allocation of trade {trade} must sum to {expected}, got {allocated}")]
+    AllocationMismatch { trade: TradeKey, expected: u64, allocated: u64 },
+    
+    #[error("Book is halted: {0}")]
+    BookHalted(String),
+    
+    #[error("Book only accepts cancels: {0}")]
+    BookCancelOnly(String),
+    
+    #[error("Book is closed: {0}")]
+    BookClosed(String),
+    
+    #[error("Book is not collecting orders for an auction: {0}")]
+    NotInAuction(String),
+    
+    #[error("Unknown symbol: {0}")]
+    UnknownSymbol(String),
+    
+    #[error("A book already exists for symbol: {0}")]
+    DuplicateSymbol(String),
+    
+    #[error("Order is already linked: {0}")]
+    AlreadyLinked(String),
+    
+    #[error("Position limit {limit} of {max} exceeded for {user_id}: worst case {worst_case}")]
+    PositionLimitExceeded { user_id: String, limit: String, max: u64, worst_case: i64 },
+    
+    #[error("Post-only order {order_id} would cross the opposing price {opposing_price}")]
+    PostOnlyWouldCross { order_id: OrderId, opposing_price: Price },
+    
+    #[error("Reduce-only order {order_id} would increase the net position {position} of {user_id}")]
+    ReduceOnlyWouldIncrease { order_id: OrderId, user_id: String, position: i64 },
+    
+    #[error("Duplicate order ID: {0}")]
+    DuplicateOrderId(OrderId),
+    
+    #[error("Client order ID {client_order_id} is already live for {user_id}")]
+    DuplicateClientOrderId { user_id: String, client_order_id: String },
+    
+    #[error("Order for {got} sent to the {expected} book")]
+    SymbolMismatch { expected: Symbol, got: Symbol },
+    
+    #[error("Price {price} is outside the band [{lower}, {upper}]")]
+    PriceOutsideBand { price: Price, lower: Decimal, upper: Decimal },
+    
+    #[error("Price {price} is not a multiple of the tick size {tick_size}")]
+    InvalidTick { price: Price, tick_size: Decimal },
+    
+    #[error("Quantity {quantity} breaks the {rule}")]
+    InvalidLot { quantity: Quantity, rule: QuantityRule },
+    
+    #[error("Engine task has stopped")]
+    EngineStopped,
 }
\ No newline at end of file
@@ -0,0 +1,117 @@
+//! Order lifecycle events reported alongside trades
+
+use crate::{order_book::{BracketState, Trade}, MatchingEngineError, Order, OrderSide, OrderStatus, Price, Quantity, types::OrderId};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+/// Reason an order was removed or reduced by the engine rather than filled
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CancelReason {
+    /// Self-trade prevention stopped the order from trading
+    SelfTradePrevention,
+    /// The order was fully offset against the same user's opposite order
+    Netting,
+    /// The book halted while the order was matching
+    Halted,
+    /// A contingency linked to another order (possibly on another book) fired
+    Contingency,
+    /// The order would have breached its user's position limit
+    PositionLimit,
+    /// The reduce-only order was larger than the position it could reduce
+    ReduceOnly,
+    /// The unfilled remainder of an order that may not rest, such as a market order
+    Unfilled,
+}
+
+/// Event produced while processing an order, other than a trade
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum OrderEvent {
+    /// An order was cancelled by the engine
+    Cancelled {
+        order: Order,
+        reason: CancelReason,
+    },
+    /// An order's quantity was reduced by the engine without trading
+    Decremented {
+        order_id: OrderId,
+        quantity: Quantity,
+        reason: CancelReason,
+    },
+    /// A trade printed at or through a pending stop's trigger and the stop
+    /// was released to the book as a market order
+    StopTriggered {
+        order_id: OrderId,
+        trigger: Price,
+        last_price: Price,
+    },
+    /// A bracket was placed, or its entry or exits filled
+    BracketUpdated {
+        entry_order_id: OrderId,
+        take_profit_id: OrderId,
+        stop_loss_id: OrderId,
+        state: BracketState,
+        /// Filled entry quantity not yet closed by an exit
+        open_quantity: Quantity,
+    },
+    /// A good-till-date order reached its expiry and was removed
+    Expired {
+        order: Order,
+    },
+    /// An incoming order was offset against the same user's resting order
+    /// at the same price, without a trade
+    Netted {
+        incoming_order_id: OrderId,
+        resting_order_id: OrderId,
+        quantity: Quantity,
+    },
+    /// Session volume reached the book's limit and the book halted
+    VolumeLimitBreached {
+        traded_quantity: u64,
+        traded_notional: Decimal,
+    },
+}
+
+/// Full result of submitting an order
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExecutionResult {
+    /// ID of the submitted order
+    pub order_id: OrderId,
+    /// Side of the submitted order
+    pub side: OrderSide,
+    /// Status of the submitted order once processing finished
+    pub status: OrderStatus,
+    /// Trades executed, in execution order
+    pub trades: Vec<Trade>,
+    /// Non-trade events, in the order they occurred
+    pub events: Vec<OrderEvent>,
+    /// Benchmark price prevailing when the order arrived (see `slippage_report`)
+    pub arrival_benchmark: Option<Decimal>,
+}
+
+/// Result of modifying a resting order
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ModifyResult {
+    /// The order as it rested before the modification
+    pub previous: Order,
+    /// Status of the order once processing finished
+    pub status: OrderStatus,
+    /// Trades executed when a cancel-replace re-entered the book
+    pub trades: Vec<Trade>,
+    /// Non-trade events, in the order they occurred
+    pub events: Vec<OrderEvent>,
+    /// Set when the order moved to the back of the queue
+    pub priority_lost: bool,
+}
+
+/// Outcome of one order in a batch (see `LimitOrderBook::add_orders`)
+#[derive(Debug, Clone, PartialEq)]
+pub struct OrderOutcome {
+    /// ID of the submitted order
+    pub order_id: OrderId,
+    /// Status of the order once processing finished (`None` if rejected)
+    pub status: Option<OrderStatus>,
+    /// Trades executed, in execution order
+    pub trades: Vec<Trade>,
+    /// Why the order was rejected
+    pub error: Option<MatchingEngineError>,
+}
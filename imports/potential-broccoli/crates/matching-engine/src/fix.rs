@@ -0,0 +1,438 @@
+//! FIX 4.4 decoding of order entry messages
+//!
+//! [`parse_new_order_single`] turns a NewOrderSingle (35=D) into an
+//! [`Order`] and [`parse_cancel_request`] turns an OrderCancelRequest (35=F)
+//! into a [`CancelRequest`]. Each message is checked as a whole before any
+//! field is read: every field must be a numeric tag and a value terminated
+//! by SOH, the header must open with BeginString, BodyLength, and MsgType,
+//! and the message must end with a CheckSum matching everything before it.
+//!
+//! Tags the engine has no use for are skipped, including repeating groups
+//! such as parties and allocations. The tags it does read may appear only
+//! once. Every problem is reported as a [`FixError`] naming the tag at
+//! fault, so a gateway can build a reject without parsing error text.
+//!
+//! In the other direction, [`execution_report`] renders an order's state
+//! after an ack, fill, or cancel as an ExecutionReport (35=8).
+//!
+//! Available with the `fix` feature.
+
+use crate::{
+    types::{OrderId, Symbol, UserId},
+    LimitOrderBook, MatchingEngineError, Order, OrderSide, Price, Quantity, TimeInForce,
+};
+use chrono::{DateTime, NaiveDateTime, Utc};
+use rust_decimal::Decimal;
+use thiserror::Error;
+
+mod execution_report;
+
+pub use execution_report::{execution_report, ExecType};
+
+/// Field delimiter of the FIX tag=value encoding
+pub const SOH: char = '\x01';
+
+/// The only BeginString this module accepts
+pub const BEGIN_STRING: &str = "FIX.4.4";
+
+/// Tags read by this module
+pub mod tag {
+    pub const ACCOUNT: u32 = 1;
+    pub const BEGIN_STRING: u32 = 8;
+    pub const BODY_LENGTH: u32 = 9;
+    pub const CHECKSUM: u32 = 10;
+    pub const CL_ORD_ID: u32 = 11;
+    pub const MSG_TYPE: u32 = 35;
+    pub const ORDER_ID: u32 = 37;
+    pub const ORDER_QTY: u32 = 38;
+    pub const ORD_TYPE: u32 = 40;
+    pub const ORIG_CL_ORD_ID: u32 = 41;
+    pub const PRICE: u32 = 44;
+    pub const SENDER_COMP_ID: u32 = 49;
+    pub const SIDE: u32 = 54;
+    pub const SYMBOL: u32 = 55;
+    pub const TIME_IN_FORCE: u32 = 59;
+    pub const STOP_PX: u32 = 99;
+    pub const EXPIRE_TIME: u32 = 126;
+}
+
+/// Why a FIX message could not be decoded
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum FixError {
+    #[error("Malformed field {field:?} at byte {offset}")]
+    MalformedField { offset: usize, field: String },
+
+    #[error("Missing required tag {0}")]
+    MissingTag(u32),
+
+    #[error("Tag {0} appears more than once")]
+    DuplicateTag(u32),
+
+    #[error("Tag {tag} must be the {expected} field")]
+    MisplacedTag { tag: u32, expected: &'static str },
+
+    #[error("Unsupported BeginString {0}")]
+    UnsupportedVersion(String),
+
+    #[error("BodyLength {declared} does not match the {actual} bytes of the body")]
+    BodyLengthMismatch { declared: usize, actual: usize },
+
+    #[error("CheckSum {declared:03} does not match the computed {computed:03}")]
+    ChecksumMismatch { declared: u8, computed: u8 },
+
+    #[error("Expected MsgType {expected}, got {got}")]
+    UnexpectedMsgType { expected: &'static str, got: String },
+
+    #[error("Invalid value {value:?} for tag {tag}: {reason}")]
+    InvalidValue { tag: u32, value: String, reason: String },
+
+    #[error("Unsupported value {value:?} for tag {tag}")]
+    UnsupportedValue { tag: u32, value: String },
+}
+
+/// Result type for FIX decoding
+pub type Result<T> = std::result::Result<T, FixError>;
+
+/// The data of an OrderCancelRequest needed to cancel the order
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CancelRequest {
+    /// Client order ID of the order to cancel (OrigClOrdID)
+    pub orig_client_order_id: String,
+    /// Client order ID of the cancel request itself (ClOrdID)
+    pub client_order_id: String,
+    /// Engine order ID of the order to cancel, when the client echoes it (OrderID)
+    pub order_id: Option<OrderId>,
+    /// Owner of the order: the Account if given, otherwise the SenderCompID
+    pub user_id: UserId,
+    /// Symbol of the order's book
+    pub symbol: Symbol,
+    /// Side of the order to cancel
+    pub side: OrderSide,
+}
+
+impl CancelRequest {
+    /// Cancels the requested order on its book
+    ///
+    /// Uses the engine order ID when the request carries one and the client
+    /// order ID otherwise. Either way, only an order of the requesting user
+    /// is found.
+    pub fn cancel_on(&self, book: &mut LimitOrderBook) -> crate::Result<Order> {
+        match self.order_id {
+            Some(order_id) if book.get_order(order_id).is_some_and(|order| order.user_id == self.user_id) => {
+                book.cancel_order(order_id)
+            },
+            Some(order_id) => Err(MatchingEngineError::OrderNotFound(order_id.to_string())),
+            None => book.cancel_by_client_id(&self.user_id, &self.orig_client_order_id),
+        }
+    }
+}
+
+/// Decodes a NewOrderSingle into an order under a fresh order ID
+///
+/// OrdType 1 (market), 2 (limit), and 3 (stop, triggered at StopPx) are
+/// supported. TimeInForce defaults to 0 (day) as in the FIX specification;
+/// 1 (GTC), 4 (FOK), 7 (at the close), and 6 (GTD, expiring at ExpireTime)
+/// are supported too. The order's user is its Account if given, otherwise
+/// the SenderCompID.
+///
+/// OrderQty is decimal shares, read into lots of the book's
+/// `quantity_scale` (see `LimitOrderBook::quantity_scale`), zero for books
+/// counting whole shares. A quantity finer than the scale is rejected.
+pub fn parse_new_order_single(raw: &str, quantity_scale: u32) -> Result<Order> {
+    let message = Message::parse(raw)?;
+    message.expect_type("D")?;
+
+    let client_order_id = message.require(tag::CL_ORD_ID)?;
+    let side = message.side()?;
+    let quantity = message.convert(tag::ORDER_QTY, |value| {
+        let shares = value.parse::<Decimal>().map_err(|e| e.to_string())?;
+        Quantity::from_decimal(shares, quantity_scale).map_err(|e| e.to_string())
+    })?;
+    let user_id = message.user_id()?;
+    let id = OrderId::new();
+    let order = match message.require(tag::ORD_TYPE)? {
+        "1" => Order::market(id, user_id, side, quantity),
+        "2" => Order::new(id, user_id, side, message.convert(tag::PRICE, Price::from_str)?, quantity),
+        "3" => Order::stop(id, user_id, side, message.convert(tag::STOP_PX, Price::from_str)?, quantity),
+        other => return Err(FixError::UnsupportedValue { tag: tag::ORD_TYPE, value: other.to_string() }),
+    };
+    let order = order.with_client_order_id(client_order_id).with_symbol(message.symbol()?);
+
+    Ok(match message.get(tag::TIME_IN_FORCE)?.unwrap_or("0") {
+        "0" => order.with_time_in_force(TimeInForce::Day),
+        "1" => order.with_time_in_force(TimeInForce::GTC),
+        "4" => order.with_time_in_force(TimeInForce::FOK),
+        "7" => order.with_time_in_force(TimeInForce::AtTheClose),
+        "6" => order.with_expiry(message.convert(tag::EXPIRE_TIME, parse_utc_timestamp)?),
+        other => return Err(FixError::UnsupportedValue { tag: tag::TIME_IN_FORCE, value: other.to_string() }),
+    })
+}
+
+/// Decodes an OrderCancelRequest
+pub fn parse_cancel_request(raw: &str) -> Result<CancelRequest> {
+    let message = Message::parse(raw)?;
+    message.expect_type("F")?;
+
+    let order_id = message.get(tag::ORDER_ID)?
+        .map(|_| message.convert(tag::ORDER_ID, |value| uuid::Uuid::parse_str(value).map(OrderId::from_uuid)))
+        .transpose()?;
+    Ok(CancelRequest {
+        orig_client_order_id: message.require(tag::ORIG_CL_ORD_ID)?.to_string(),
+        client_order_id: message.require(tag::CL_ORD_ID)?.to_string(),
+        order_id,
+        user_id: message.user_id()?,
+        symbol: message.symbol()?,
+        side: message.side()?,
+    })
+}
+
+/// Wraps body fields, starting with MsgType, in a header and a CheckSum trailer
+fn frame(body: &str) -> String {
+    let message = format!("8={BEGIN_STRING}{SOH}9={}{SOH}{body}", body.len());
+    let checksum = checksum(message.as_bytes());
+    format!("{message}10={checksum:03}{SOH}")
+}
+
+/// Sums the bytes of a message up to its CheckSum field, modulo 256
+fn checksum(bytes: &[u8]) -> u8 {
+    bytes.iter().fold(0u8, |sum, &byte| sum.wrapping_add(byte))
+}
+
+/// Parses a UTCTimestamp, with or without milliseconds
+fn parse_utc_timestamp(value: &str) -> std::result::Result<DateTime<Utc>, String> {
+    NaiveDateTime::parse_from_str(value, "%Y%m%d-%H:%M:%S%.f")
+        .map(|timestamp| timestamp.and_utc())
+        .map_err(|e| e.to_string())
+}
+
+/// A structurally valid message's fields, in order
+struct Message<'a> {
+    fields: Vec<(u32, &'a str)>,
+}
+
+impl<'a> Message<'a> {
+    /// Splits a message into fields and checks its header, body length, and checksum
+    fn parse(raw: &'a str) -> Result<Self> {
+        let mut fields = Vec::new();
+        let mut offsets = Vec::new();
+        let mut offset = 0;
+        for piece in raw.split_inclusive(SOH) {
+            let field = piece.strip_suffix(SOH)
+                .and_then(|field| {
+                    let (tag, value) = field.split_once('=')?;
+                    let canonical = !tag.starts_with('0') && tag.bytes().all(|b| b.is_ascii_digit());
+                    let tag = tag.parse::<u32>().ok().filter(|_| canonical)?;
+                    (!value.is_empty()).then_some((tag, value))
+                })
+                .ok_or_else(|| FixError::MalformedField { offset, field: piece.trim_end_matches(SOH).to_string() })?;
+            fields.push(field);
+            offsets.push(offset);
+            offset += piece.len();
+        }
+        let message = Self { fields };
+
+        for (index, tag, expected) in [(0, tag::BEGIN_STRING, "first"), (1, tag::BODY_LENGTH, "second"), (2, tag::MSG_TYPE, "third")] {
+            message.expect_at(index, tag, expected)?;
+        }
+        let last = message.fields.len() - 1;
+        message.expect_at(last, tag::CHECKSUM, "last")?;
+        for tag in [tag::BEGIN_STRING, tag::BODY_LENGTH, tag::MSG_TYPE, tag::CHECKSUM] {
+            message.get(tag)?;
+        }
+
+        let version = message.fields[0].1;
+        if version != BEGIN_STRING {
+            return Err(FixError::UnsupportedVersion(version.to_string()));
+        }
+        let declared = message.convert(tag::BODY_LENGTH, |value| value.parse::<usize>().map_err(|e| e.to_string()))?;
+        let actual = offsets[last] - offsets[2];
+        if declared != actual {
+            return Err(FixError::BodyLengthMismatch { declared, actual });
+        }
+        let declared = message.convert(tag::CHECKSUM, |value| match value.len() {
+            3 => value.parse::<u8>().map_err(|e| e.to_string()),
+            _ => Err("must be three digits".to_string()),
+        })?;
+        let computed = checksum(&raw.as_bytes()[..offsets[last]]);
+        if declared != computed {
+            return Err(FixError::ChecksumMismatch { declared, computed });
+        }
+        Ok(message)
+    }
+
+    fn expect_at(&self, index: usize, tag: u32, expected: &'static str) -> Result<()> {
+        match self.fields.get(index) {
+            Some(&(found, _)) if found == tag => Ok(()),
+            _ if self.fields.iter().any(|&(found, _)| found == tag) => Err(FixError::MisplacedTag { tag, expected }),
+            _ => Err(FixError::MissingTag(tag)),
+        }
+    }
+
+    fn expect_type(&self, expected: &'static str) -> Result<()> {
+        match self.fields[2].1 {
+            got if got == expected => Ok(()),
+            got => Err(FixError::UnexpectedMsgType { expected, got: got.to_string() }),
+        }
+    }
+
+    /// Gets a tag's value, which must not repeat
+    fn get(&self, tag: u32) -> Result<Option<&'a str>> {
+        let mut values = self.fields.iter().filter(|&&(found, _)| found == tag).map(|&(_, value)| value);
+        match (values.next(), values.next()) {
+            (_, Some(_)) => Err(FixError::DuplicateTag(tag)),
+            (value, None) => Ok(value),
+        }
+    }
+
+    fn require(&self, tag: u32) -> Result<&'a str> {
+        self.get(tag)?.ok_or(FixError::MissingTag(tag))
+    }
+
+    /// Converts a required tag's value, reporting a failed conversion against the tag
+    fn convert<T, E: ToString>(&self, tag: u32, convert: impl FnOnce(&str) -> std::result::Result<T, E>) -> Result<T> {
+        let value = self.require(tag)?;
+        convert(value).map_err(|e| FixError::InvalidValue { tag, value: value.to_string(), reason: e.to_string() })
+    }
+
+    fn side(&self) -> Result<OrderSide> {
+        match self.require(tag::SIDE)? {
+            "1" => Ok(OrderSide::Buy),
+            "2" => Ok(OrderSide::Sell),
+            other => Err(FixError::UnsupportedValue { tag: tag::SIDE, value: other.to_string() }),
+        }
+    }
+
+    fn symbol(&self) -> Result<Symbol> {
+        self.convert(tag::SYMBOL, |value| Symbol::new(value.to_string()))
+    }
+
+    fn user_id(&self) -> Result<UserId> {
+        let user = match self.get(tag::ACCOUNT)? {
+            Some(account) => account,
+            None => self.require(tag::SENDER_COMP_ID)?,
+        };
+        Ok(UserId::new(user.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{OrderStatus, OrderType};
+    use chrono::TimeZone;
+
+    /// Reads a fixture, logged with `|` for SOH as FIX logs usually are
+    fn fixture(name: &str) -> String {
+        let path = format!("{}/tests/fixtures/fix/{name}.fix", env!("CARGO_MANIFEST_DIR"));
+        std::fs::read_to_string(path).unwrap().trim_end().replace('|', &SOH.to_string())
+    }
+
+    /// Encodes body fields into a complete message with a correct length and checksum
+    fn encode(body: &[(u32, &str)]) -> String {
+        frame(&body.iter().map(|(tag, value)| format!("{tag}={value}{SOH}")).collect::<String>())
+    }
+
+    #[test]
+    fn test_decodes_new_order_corpus() {
+        let limit = parse_new_order_single(&fixture("new_order_limit_gtc"), 0).unwrap();
+        assert_eq!((limit.side, limit.price, limit.original_quantity.value()), (OrderSide::Buy, Price::from_str("150.25").unwrap(), 100));
+        assert_eq!((limit.order_type, limit.time_in_force, limit.status), (OrderType::Limit, TimeInForce::GTC, OrderStatus::Active));
+        assert_eq!(limit.client_order_id.as_deref(), Some("ORD-20261015-0001"));
+        assert_eq!((limit.user_id.as_str(), limit.symbol.as_ref().map(Symbol::as_str)), ("ACC-7781", Some("AAPL")));
+
+        let market = parse_new_order_single(&fixture("new_order_market_day"), 0).unwrap();
+        assert_eq!((market.side, market.order_type, market.time_in_force), (OrderSide::Sell, OrderType::Market, TimeInForce::Day));
+        assert_eq!(market.user_id.as_str(), "BRKGW01");
+
+        // Party and allocation groups repeat their tags and are skipped
+        let grouped = parse_new_order_single(&fixture("new_order_parties_and_allocs"), 0).unwrap();
+        assert_eq!((grouped.side, grouped.price, grouped.original_quantity.value()), (OrderSide::Sell, Price::from_str("151.00").unwrap(), 100));
+        assert_eq!(grouped.time_in_force, TimeInForce::Day);
+
+        let stop = parse_new_order_single(&fixture("new_order_stop_gtc"), 0).unwrap();
+        assert_eq!(stop.stop_trigger(), Some(Price::from_str("212.50").unwrap()));
+
+        let gtd = parse_new_order_single(&fixture("new_order_gtd"), 0).unwrap();
+        assert_eq!(gtd.expires_at, Some(Utc.with_ymd_and_hms(2026, 10, 16, 20, 0, 0).unwrap()));
+    }
+
+    #[test]
+    fn test_rejects_bad_messages_with_typed_errors() {
+        assert_eq!(parse_new_order_single(&fixture("new_order_bad_checksum"), 0), Err(FixError::ChecksumMismatch { declared: 34, computed: 33 }));
+        assert_eq!(parse_new_order_single(&fixture("new_order_missing_qty"), 0), Err(FixError::MissingTag(tag::ORDER_QTY)));
+        assert_eq!(
+            parse_new_order_single(&fixture("new_order_ioc"), 0),
+            Err(FixError::UnsupportedValue { tag: tag::TIME_IN_FORCE, value: "3".to_string() })
+        );
+        assert!(matches!(parse_new_order_single(&fixture("cancel_by_client_id"), 0), Err(FixError::UnexpectedMsgType { expected: "D", .. })));
+
+        let order = [(35, "D"), (49, "GW"), (11, "C1"), (55, "AAPL"), (54, "1"), (38, "10"), (40, "2")];
+        let with = |extra: &[(u32, &'static str)]| encode(&[order.as_slice(), extra].concat());
+        assert!(parse_new_order_single(&with(&[(44, "150")]), 0).is_ok());
+        assert_eq!(parse_new_order_single(&with(&[]), 0), Err(FixError::MissingTag(tag::PRICE)));
+        assert!(matches!(parse_new_order_single(&with(&[(44, "-1")]), 0), Err(FixError::InvalidValue { tag: tag::PRICE, .. })));
+        assert!(matches!(parse_new_order_single(&with(&[(44, "abc")]), 0), Err(FixError::InvalidValue { tag: tag::PRICE, .. })));
+        assert_eq!(parse_new_order_single(&with(&[(44, "150"), (11, "C2")]), 0), Err(FixError::DuplicateTag(tag::CL_ORD_ID)));
+        assert_eq!(parse_new_order_single(&with(&[(44, "150"), (54, "5")]), 0), Err(FixError::DuplicateTag(tag::SIDE)));
+
+        // Structural damage: bad fields, truncation, header order, body length, version
+        let valid = with(&[(44, "150")]);
+        assert!(matches!(parse_new_order_single(&valid.replace("54=1", "54"), 0), Err(FixError::MalformedField { .. })));
+        assert!(matches!(parse_new_order_single(&valid.replace("54=1", "x4=1"), 0), Err(FixError::MalformedField { .. })));
+        assert!(matches!(parse_new_order_single(&valid.replace("54=1", "54="), 0), Err(FixError::MalformedField { .. })));
+        assert!(matches!(parse_new_order_single(&valid[..valid.len() - 1], 0), Err(FixError::MalformedField { .. })));
+        assert!(parse_new_order_single(&valid[..valid.len() / 2], 0).is_err());
+        assert_eq!(parse_new_order_single("", 0), Err(FixError::MissingTag(tag::BEGIN_STRING)));
+        assert!(matches!(parse_new_order_single(&valid.replacen("9=", "9=1", 1), 0), Err(FixError::BodyLengthMismatch { .. })));
+        assert!(matches!(parse_new_order_single(&valid.replace("FIX.4.4", "FIX.4.2"), 0), Err(FixError::UnsupportedVersion(_))));
+        let swapped = format!("9=0{SOH}8=FIX.4.4{SOH}35=D{SOH}10=000{SOH}");
+        assert_eq!(parse_new_order_single(&swapped, 0), Err(FixError::MisplacedTag { tag: tag::BEGIN_STRING, expected: "first" }));
+    }
+
+    #[test]
+    fn test_fractional_order_quantity_round_trips_through_the_execution_report() {
+        let new_order = |quantity| encode(&[(35, "D"), (49, "GW"), (11, "C1"), (55, "FRAC"), (54, "1"), (38, quantity), (40, "2"), (44, "150")]);
+        let order = parse_new_order_single(&new_order("0.5"), 3).unwrap();
+        assert_eq!(order.original_quantity.value(), 500);
+
+        let mut book = LimitOrderBook::with_quantity_scale("FRAC".to_string(), 3).unwrap();
+        book.add_order(order.clone()).unwrap();
+        let report = execution_report(&order, None, ExecType::New, book.quantity_scale());
+        let reported = Message::parse(&report).unwrap().convert(tag::ORDER_QTY, str::parse::<Decimal>).unwrap();
+        assert_eq!(reported, Decimal::new(5, 1));
+
+        // Finer than the book's lots, or than whole shares on an unscaled book
+        assert!(matches!(parse_new_order_single(&new_order("0.0005"), 3), Err(FixError::InvalidValue { tag: tag::ORDER_QTY, .. })));
+        assert!(matches!(parse_new_order_single(&new_order("0.5"), 0), Err(FixError::InvalidValue { tag: tag::ORDER_QTY, .. })));
+    }
+
+    #[test]
+    fn test_cancel_requests_cancel_the_users_order() {
+        let by_id = parse_cancel_request(&fixture("cancel_with_order_id")).unwrap();
+        assert_eq!(by_id.order_id.map(|id| id.to_string()).as_deref(), Some("6f1c1a0e-5b7e-4c49-9a43-2f2b0f3f8d11"));
+        assert_eq!((by_id.orig_client_order_id.as_str(), by_id.client_order_id.as_str()), ("ORD-20261015-0001", "CXL-20261015-0001"));
+        assert_eq!((by_id.user_id.as_str(), by_id.side), ("BRKGW01", OrderSide::Buy));
+
+        let mut book = LimitOrderBook::new("AAPL".to_string()).unwrap();
+        let order = parse_new_order_single(&fixture("new_order_parties_and_allocs"), 0).unwrap();
+        let order_id = order.id;
+        book.add_order(order).unwrap();
+
+        // The order ID alone doesn't let another user cancel the order
+        let foreign = CancelRequest { order_id: Some(order_id), ..by_id };
+        assert_eq!(foreign.cancel_on(&mut book), Err(MatchingEngineError::OrderNotFound(order_id.to_string())));
+
+        let request = parse_cancel_request(&fixture("cancel_by_client_id")).unwrap();
+        assert_eq!(request.order_id, None);
+        let cancelled = request.cancel_on(&mut book).unwrap();
+        assert_eq!((cancelled.id, cancelled.status), (order_id, OrderStatus::Cancelled));
+        assert_eq!(
+            parse_cancel_request(&encode(&[(35, "F"), (49, "GW"), (41, "C1"), (11, "C2"), (37, "not-a-uuid"), (55, "AAPL"), (54, "1")])),
+            Err(FixError::InvalidValue {
+                tag: tag::ORDER_ID,
+                value: "not-a-uuid".to_string(),
+                reason: uuid::Uuid::parse_str("not-a-uuid").unwrap_err().to_string(),
+            })
+        );
+    }
+}
@@ -0,0 +1,234 @@
+//! ExecutionReport rendering
+//!
+//! Quantities are read off the order rather than tracked alongside it:
+//! CumQty is `filled_quantity()` and LeavesQty is `remaining_quantity`
+//! while the order can still trade, so a report can't disagree with the
+//! book about the order it describes. They are written as decimal shares
+//! at the book's quantity scale, as FIX expects, rather than as lot counts.
+//! Session fields (CompIDs, MsgSeqNum, SendingTime) are the session layer's
+//! to add.
+
+use super::{frame, SOH};
+use crate::{order_book::Trade, Order, OrderSide, OrderStatus, OrderType, Quantity};
+use std::fmt::Write;
+
+/// Why an ExecutionReport is sent (tag 150)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExecType {
+    /// The order was accepted
+    New,
+    /// The order traded
+    Trade,
+    /// The order was cancelled
+    Canceled,
+    /// The order was modified
+    Replaced,
+    /// The order reached the end of its time in force
+    Expired,
+}
+
+impl ExecType {
+    fn code(self) -> char {
+        match self {
+            ExecType::New => '0',
+            ExecType::Canceled => '4',
+            ExecType::Replaced => '5',
+            ExecType::Expired => 'C',
+            ExecType::Trade => 'F',
+        }
+    }
+}
+
+/// Renders a FIX 4.4 ExecutionReport for an order as it stands after the event
+///
+/// Pass the trade for fills: it supplies LastPx and LastQty and the ExecID,
+/// which is the trade ID and the order's side, so the buyer's and seller's
+/// reports of a trade are told apart. Other reports are identified by the
+/// order ID, the ExecType, and the time of the order's last update.
+///
+/// AvgPx is only known from the order when it hasn't traded, or has traded
+/// just once in the given trade; otherwise the tag is left out for the
+/// gateway, which sees every fill, to add.
+///
+/// `quantity_scale` is the scale of the order's book (see
+/// `LimitOrderBook::quantity_scale`), zero for books counting whole shares.
+pub fn execution_report(order: &Order, trade: Option<&Trade>, exec_type: ExecType, quantity_scale: u32) -> String {
+    debug_assert!(trade.is_none_or(|trade| trade.buy_order_id == order.id || trade.sell_order_id == order.id));
+    debug_assert!(trade.is_none_or(|trade| trade.quantity_scale == quantity_scale));
+    let shares = |quantity: Quantity| quantity.to_decimal(quantity_scale);
+    let filled = order.filled_quantity();
+    let leaves = match order.status {
+        OrderStatus::Cancelled | OrderStatus::Expired => Quantity::new_allow_zero(0),
+        _ => order.remaining_quantity,
+    };
+    let exec_id = match trade {
+        Some(trade) => format!("{}-{}", trade.trade_id, side_code(order.side)),
+        None => format!("{}-{}-{}", order.id, exec_type.code(), order.updated_at.timestamp_micros()),
+    };
+    let transact_time = trade.map_or(order.updated_at, |trade| trade.timestamp);
+
+    let mut body = String::new();
+    let mut field = |tag: u32, value: &dyn std::fmt::Display| {
+        write!(body, "{tag}={value}{SOH}").expect("writing to a String cannot fail");
+    };
+    field(35, &'8');
+    field(37, &order.id);
+    if let Some(client_order_id) = &order.client_order_id {
+        field(11, client_order_id);
+    }
+    field(17, &exec_id);
+    field(150, &exec_type.code());
+    field(39, &ord_status(order.status));
+    if let Some(symbol) = &order.symbol {
+        field(55, symbol);
+    }
+    field(54, &side_code(order.side));
+    match order.order_type {
+        OrderType::Market => field(40, &'1'),
+        OrderType::Limit => {
+            field(40, &'2');
+            field(44, &order.price.value());
+        },
+        OrderType::Stop { trigger } => {
+            field(40, &'3');
+            field(99, &trigger.value());
+        },
+    }
+    field(38, &shares(order.original_quantity));
+    if let Some(trade) = trade {
+        field(32, &shares(trade.quantity));
+        field(31, &trade.price.value());
+    }
+    field(151, &shares(leaves));
+    field(14, &shares(filled));
+    match trade {
+        _ if filled.value() == 0 => field(6, &0),
+        Some(trade) if trade.quantity == filled => field(6, &trade.price.value()),
+        _ => {},
+    }
+    field(60, &transact_time.format("%Y%m%d-%H:%M:%S%.3f"));
+    frame(&body)
+}
+
+/// OrdStatus (tag 39) of an order status
+fn ord_status(status: OrderStatus) -> char {
+    match status {
+        OrderStatus::Active => '0',
+        OrderStatus::PartiallyFilled => '1',
+        OrderStatus::Filled => '2',
+        OrderStatus::Cancelled => '4',
+        OrderStatus::Expired => 'C',
+        OrderStatus::PendingActivation => 'A',
+    }
+}
+
+fn side_code(side: OrderSide) -> char {
+    match side {
+        OrderSide::Buy => '1',
+        OrderSide::Sell => '2',
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        fix::{checksum, tag, Message},
+        types::{OrderId, Symbol, UserId},
+        LimitOrderBook, Price,
+    };
+    use crate::order_book::test_support::create_order;
+
+    /// Checks a report's framing and reads back the fields a test cares about
+    fn fields(report: &str, tags: &[u32]) -> Vec<Option<String>> {
+        let message = Message::parse(report).unwrap();
+        message.expect_type("8").unwrap();
+        tags.iter().map(|&tag| message.get(tag).unwrap().map(str::to_string)).collect()
+    }
+
+    fn status_and_quantities(report: &str) -> Vec<Option<String>> {
+        fields(report, &[150, 39, 151, 14])
+    }
+
+    fn some(values: &[&str]) -> Vec<Option<String>> {
+        values.iter().map(|value| Some(value.to_string())).collect()
+    }
+
+    #[test]
+    fn test_checksum_and_body_length_match_the_bytes() {
+        assert_eq!(checksum(format!("8=FIX.4.4{SOH}9=5{SOH}35=0{SOH}").as_bytes()), 163);
+
+        let order = create_order(OrderSide::Buy, 15000, 100, "alice").with_client_order_id("C-1");
+        let report = execution_report(&order, None, ExecType::New, 0);
+        let (message, trailer) = report.split_at(report.len() - 7);
+        let sum: u32 = message.bytes().map(u32::from).sum();
+        assert_eq!(trailer, format!("10={:03}{SOH}", sum % 256));
+
+        let body_start = report.find("35=").unwrap();
+        assert_eq!(report[body_start..report.len() - 7].len().to_string(), fields(&report, &[tag::BODY_LENGTH])[0].clone().unwrap());
+        assert_eq!(fields(&report, &[11, 54, 40, 44, 38]), some(&["C-1", "1", "2", "150.00", "100"]));
+    }
+
+    #[test]
+    fn test_ord_status_follows_the_order() {
+        let mut order = create_order(OrderSide::Sell, 15000, 100, "alice");
+        let cases = [
+            (OrderStatus::Active, "0"),
+            (OrderStatus::PartiallyFilled, "1"),
+            (OrderStatus::Filled, "2"),
+            (OrderStatus::Cancelled, "4"),
+            (OrderStatus::Expired, "C"),
+            (OrderStatus::PendingActivation, "A"),
+        ];
+        for (status, expected) in cases {
+            order.status = status;
+            assert_eq!(fields(&execution_report(&order, None, ExecType::New, 0), &[39]), some(&[expected]));
+        }
+
+        // A cancelled order has nothing left to trade, whatever it had remaining
+        order.fill(Quantity::new(30).unwrap()).unwrap();
+        order.cancel();
+        assert_eq!(status_and_quantities(&execution_report(&order, None, ExecType::Canceled, 0)), some(&["4", "4", "0", "30"]));
+    }
+
+    #[test]
+    fn test_lifecycle_ack_partial_fill_fill() {
+        let mut book = LimitOrderBook::new("AAPL".to_string()).unwrap();
+        let submitted = create_order(OrderSide::Buy, 15000, 100, "alice").with_client_order_id("C-7");
+        let mut order = submitted.clone().with_symbol(Symbol::new("AAPL".to_string()).unwrap());
+        book.add_order(submitted).unwrap();
+
+        let mut reports = vec![execution_report(&order, None, ExecType::New, 0)];
+        for quantity in [40, 60] {
+            let trades = book.add_order(create_order(OrderSide::Sell, 14900, quantity, "bob")).unwrap();
+            let trade = &trades[0];
+            order.fill(trade.quantity).unwrap();
+            reports.push(execution_report(&order, Some(trade), ExecType::Trade, 0));
+        }
+
+        let lifecycle: Vec<_> = reports.iter().map(|report| status_and_quantities(report)).collect();
+        assert_eq!(lifecycle, vec![some(&["0", "0", "100", "0"]), some(&["F", "1", "60", "40"]), some(&["F", "2", "0", "100"])]);
+        assert_eq!(fields(&reports[1], &[32, 31, 6, 55]), some(&["40", "150.00", "150.00", "AAPL"]));
+        assert_eq!(fields(&reports[2], &[32, 31, 6]), vec![Some("60".to_string()), Some("150.00".to_string()), None]);
+        assert_eq!(fields(&reports[0], &[32, 31, 6]), vec![None, None, Some("0".to_string())]);
+
+        // Each report has its own ExecID and the order is no longer in the book
+        let exec_ids: std::collections::HashSet<_> = reports.iter().map(|report| fields(report, &[17])).collect();
+        assert_eq!(exec_ids.len(), 3);
+        assert!(book.get_order(order.id).is_none());
+    }
+
+    #[test]
+    fn test_fractional_quantities_are_written_as_shares() {
+        let mut book = LimitOrderBook::with_quantity_scale("FRAC".to_string(), 3).unwrap();
+        let shares = |value: &str| Quantity::from_decimal(value.parse().unwrap(), 3).unwrap();
+        let mut order = Order::new(OrderId::new(), UserId::new("alice".to_string()), OrderSide::Buy, Price::from_cents(15000).unwrap(), shares("1.5"));
+        book.add_order(order.clone()).unwrap();
+        let sell = Order::new(OrderId::new(), UserId::new("bob".to_string()), OrderSide::Sell, Price::from_cents(15000).unwrap(), shares("0.125"));
+        let trades = book.add_order(sell).unwrap();
+        order.fill(trades[0].quantity).unwrap();
+
+        let report = execution_report(&order, Some(&trades[0]), ExecType::Trade, book.quantity_scale());
+        assert_eq!(fields(&report, &[38, 32, 151, 14]), some(&["1.500", "0.125", "1.375", "0.125"]));
+    }
+}
@@ -11,15 +11,14 @@
 //!
 //! ## Example
 //! ```rust
-//! use matching_engine::{LimitOrderBook, Order, OrderSide, Price, Quantity};
+//! use matching_engine::{LimitOrderBook, Order, OrderId, OrderSide, Price, Quantity, UserId};
 //! use rust_decimal::Decimal;
-//! use uuid::Uuid;
 //!
-//! let mut book = LimitOrderBook::new("AAPL".to_string());
+//! let mut book = LimitOrderBook::new("AAPL".to_string())?;
 //! 
 //! let buy_order = Order::new(
-//!     Uuid::new_v4(),
-//!     "user1".to_string(),
+//!     OrderId::new(),
+//!     UserId::new("user1".to_string()),
 //!     OrderSide::Buy,
 //!     Price::new(Decimal::new(15000, 2))?, // $150.00
 //!     Quantity::new(100)?,
@@ -33,19 +32,59 @@
 //! # Ok::<(), matching_engine::error::MatchingEngineError>(())
 //! ```
 
+pub mod account;
+pub mod allocation;
+pub mod engine;
 pub mod error;
+pub mod events;
+#[cfg(feature = "fix")]
+pub mod fix;
+pub mod marketdata;
 pub mod order;
 pub mod order_book;
 pub mod price;
+#[cfg(feature = "proto")]
+pub mod proto;
 pub mod quantity;
+pub mod recovery;
+#[cfg(feature = "async")]
+pub mod runner;
+#[cfg(feature = "snapshot-scheduler")]
+pub mod scheduler;
+pub mod shadow;
+pub mod sharded;
+pub mod shared;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
 pub mod types;
 
+pub use account::{AccountInfo, AccountRegistry};
+pub use allocation::{AllocationEngine, AllocationSplit, TradeAllocation, TradeKey};
+pub use engine::{BasketReject, BasketResult, ConstituentFailure, MultiBookEngine, OcoLink, OcoPolicy};
 pub use error::MatchingEngineError;
-pub use order::{Order, OrderSide, OrderStatus};
-pub use order_book::LimitOrderBook;
-pub use price::Price;
+pub use events::{CancelReason, ExecutionResult, ModifyResult, OrderEvent, OrderOutcome};
+pub use order::{Order, OrderSide, OrderStatus, OrderType, TimeInForce};
+pub use order_book::{
+    Activation, BandDepth, Bbo, BboReader, BookConfig, BookListener, BookSnapshot, BookState, BookStats, Bracket, BracketState,
+    CURRENT_SNAPSHOT_VERSION, Candle, CandleAggregator, Clock, FeeRates, FeeRounding, FeeSchedule, FillSlippage, FlushPolicy, IndicativeAuction,
+    IntegrityError, JsonlTradeReader, JsonlTradeSink, L3Detail, L3Entry, L3Snapshot, LevelIter, LevelOrders,
+    LimitOrderBook, LoggedOp, MatchPreview, MemoryReport, NotionalDepth, NotionalLevel, OpLogReader, OpLogWriter,
+    OpResult, OrderBookOp, OrderRevision, OrderTimestamp, PositionLimit, PositionLimitAction, PriceBand,
+    PublishedBbo, QuantityRule, QueuePosition, SelfTradePrevention, SessionArchive, SessionStats, SkippedLine, SlippageReport, SnapshotEnvelope,
+    SnapshotFormat, StpPolicy, StpScope, SweepCost, TieredStorage, TradeSink, UserSessionCounters, VolumeLimit,
+    VolumeUsage, WindowVwap,
+};
+pub use price::{Price, RoundingMode};
 pub use quantity::Quantity;
-pub use types::{OrderId, Symbol, UserId};
+pub use recovery::{BookDelta, BookMirror, DepthUpdate, Gap, RecoverySnapshot};
+#[cfg(feature = "async")]
+pub use runner::{CommandReply, EngineEvent, EngineHandle, EngineTask};
+#[cfg(feature = "snapshot-scheduler")]
+pub use scheduler::{SnapshotObserver, SnapshotSchedule, SnapshotScheduler, WrittenSnapshot};
+pub use shadow::{PaperFill, ShadowBook};
+pub use sharded::{PendingResult, ShardStats, ShardedEngine, ShardedHandle};
+pub use shared::SharedOrderBook;
+pub use types::{DeskId, FirmId, OrderId, SubAccountId, Symbol, UserId, UserIdPool};
 
 /// Result type for matching engine operations
 pub type Result<T> = std::result::Result<T, MatchingEngineError>;
\ No newline at end of file
@@ -0,0 +1,3 @@
+//! Market data encodings for downstream consumers
+
+pub mod binary;
@@ -0,0 +1,497 @@
+//! Compact binary market data messages
+//!
+//! An ITCH-style encoding for colocated consumers: fixed layouts, integers
+//! little-endian, no delimiters or field tags. Every message starts with the
+//! same 17-byte header:
+//!
+//! | Offset | Size | Field |
+//! |---|---|---|
+//! | 0 | 1 | message type |
+//! | 1 | 8 | symbol, ASCII, right-padded with spaces |
+//! | 9 | 8 | book sequence number |
+//!
+//! followed by the body of its type (sizes in bytes):
+//!
+//! | Type | Body |
+//! |---|---|
+//! | `A` add order | order ID (16), side (1), price (8), quantity (8) |
+//! | `X` cancel order | order ID (16), cancelled quantity (8) |
+//! | `E` order executed | order ID (16), trade ID (8), price (8), quantity (8) |
+//! | `L` level update | side (1), price (8), quantity (8), order count (4) |
+//! | `S` snapshot | bid count (2), ask count (2), then each bid, best first, and each ask: price (8), quantity (8), order count (4) |
+//!
+//! Order IDs are the UUID's 16 bytes in their usual order and sides are `B`
+//! or `S`. Prices are whole numbers of ticks of the codec's [`TickScale`]:
+//! a price between ticks fails to encode rather than being rounded.
+//! Quantities are whole numbers of lots, as the book counts them; like the
+//! tick size, the book's quantity scale is agreed out of band and set on the
+//! codec, which stamps it on the levels it decodes and refuses to encode
+//! levels counted to another scale.
+
+use crate::{
+    order_book::{MarketDepth, MarketLevel},
+    recovery::DepthUpdate,
+    types::{OrderId, Symbol},
+    MatchingEngineError, OrderSide, Price, Quantity,
+};
+use rust_decimal::{prelude::ToPrimitive, Decimal};
+
+/// Length of the header every message starts with
+pub const HEADER_LEN: usize = 17;
+
+/// Bytes the symbol is padded to
+const SYMBOL_LEN: usize = 8;
+
+/// The price of one tick, which encoded prices count in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TickScale {
+    tick_size: Price,
+}
+
+impl TickScale {
+    pub fn new(tick_size: Price) -> Self {
+        Self { tick_size }
+    }
+
+    /// Gets the price of one tick
+    pub fn tick_size(&self) -> Price {
+        self.tick_size
+    }
+
+    /// Counts the ticks in a price, failing unless it is a whole number of them
+    pub fn to_ticks(&self, price: Price) -> crate::Result<u64> {
+        let tick = self.tick_size.value();
+        let ticks = match price.value().checked_rem(tick) {
+            Some(remainder) if remainder.is_zero() => (price.value() / tick).to_u64(),
+            _ => None,
+        };
+        ticks.ok_or_else(|| MatchingEngineError::InvalidPrice(format!("{price} is not a whole number of {tick} ticks")))
+    }
+
+    /// Converts a tick count back to a price
+    pub fn to_price(&self, ticks: u64) -> crate::Result<Price> {
+        let value = Decimal::from(ticks).checked_mul(self.tick_size.value())
+            .ok_or_else(|| MatchingEngineError::InvalidPrice(format!("{ticks} ticks of {} overflow", self.tick_size)))?;
+        Price::new(value)
+    }
+}
+
+/// One market data message
+#[derive(Debug, Clone, PartialEq)]
+pub struct MarketDataMessage {
+    pub symbol: Symbol,
+    /// Book sequence number of the mutation the message reports
+    pub sequence: u64,
+    pub event: MarketDataEvent,
+}
+
+/// What a market data message reports
+#[derive(Debug, Clone, PartialEq)]
+pub enum MarketDataEvent {
+    /// An order started resting in the book
+    AddOrder { order_id: OrderId, side: OrderSide, price: Price, quantity: Quantity },
+    /// A resting order lost quantity without trading
+    CancelOrder { order_id: OrderId, quantity: Quantity },
+    /// A resting order traded
+    OrderExecuted { order_id: OrderId, trade_id: u64, price: Price, quantity: Quantity },
+    /// A price level's new totals; a quantity of zero removes the level
+    LevelUpdate { side: OrderSide, price: Price, quantity: Quantity, order_count: usize },
+    /// The top levels of both sides, best first
+    Snapshot { bids: Vec<MarketLevel>, asks: Vec<MarketLevel> },
+}
+
+impl MarketDataEvent {
+    /// A level update from the delta feed
+    pub fn level_update(update: &DepthUpdate) -> Self {
+        MarketDataEvent::LevelUpdate {
+            side: update.side,
+            price: update.price,
+            quantity: update.quantity,
+            order_count: update.order_count,
+        }
+    }
+
+    /// A snapshot of the top `levels` levels of each side
+    pub fn snapshot(depth: &MarketDepth, levels: usize) -> Self {
+        MarketDataEvent::Snapshot {
+            bids: depth.bids.iter().take(levels).cloned().collect(),
+            asks: depth.asks.iter().take(levels).cloned().collect(),
+        }
+    }
+
+    fn message_type(&self) -> u8 {
+        match self {
+            MarketDataEvent::AddOrder { .. } => b'A',
+            MarketDataEvent::CancelOrder { .. } => b'X',
+            MarketDataEvent::OrderExecuted { .. } => b'E',
+            MarketDataEvent::LevelUpdate { .. } => b'L',
+            MarketDataEvent::Snapshot { .. } => b'S',
+        }
+    }
+}
+
+/// Encodes and decodes messages with prices in ticks of one scale
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BinaryCodec {
+    scale: TickScale,
+    quantity_scale: u32,
+}
+
+impl BinaryCodec {
+    /// Creates a codec for a book counting whole shares
+    pub fn new(scale: TickScale) -> Self {
+        Self { scale, quantity_scale: 0 }
+    }
+
+    /// Sets the quantity scale of the book whose messages are coded
+    pub fn with_quantity_scale(mut self, quantity_scale: u32) -> Self {
+        self.quantity_scale = quantity_scale;
+        self
+    }
+
+    /// Gets the number of decimal places encoded quantities are counted to
+    pub fn quantity_scale(&self) -> u32 {
+        self.quantity_scale
+    }
+
+    /// Appends a message to `out`, which is left as it was if the message can't be encoded
+    pub fn encode(&self, message: &MarketDataMessage, out: &mut Vec<u8>) -> crate::Result<()> {
+        let start = out.len();
+        let result = self.write(message, out);
+        if result.is_err() {
+            out.truncate(start);
+        }
+        result
+    }
+
+    /// Decodes the message at the start of `bytes`, returning it and its length
+    pub fn decode(&self, bytes: &[u8]) -> crate::Result<(MarketDataMessage, usize)> {
+        let mut reader = Reader { bytes, offset: 0 };
+        let message_type = reader.u8()?;
+        let symbol = reader.symbol()?;
+        let sequence = reader.u64()?;
+        let event = match message_type {
+            b'A' => MarketDataEvent::AddOrder {
+                order_id: reader.order_id()?,
+                side: reader.side()?,
+                price: reader.price(self.scale)?,
+                quantity: reader.quantity()?,
+            },
+            b'X' => MarketDataEvent::CancelOrder { order_id: reader.order_id()?, quantity: reader.quantity()? },
+            b'E' => MarketDataEvent::OrderExecuted {
+                order_id: reader.order_id()?,
+                trade_id: reader.u64()?,
+                price: reader.price(self.scale)?,
+                quantity: reader.quantity()?,
+            },
+            b'L' => MarketDataEvent::LevelUpdate {
+                side: reader.side()?,
+                price: reader.price(self.scale)?,
+                quantity: Quantity::new_allow_zero(reader.u64()?),
+                order_count: reader.u32()? as usize,
+            },
+            b'S' => {
+                let (bid_count, ask_count) = (reader.u16()?, reader.u16()?);
+                let bids = (0..bid_count).map(|_| reader.level(self.scale, self.quantity_scale)).collect::<crate::Result<_>>()?;
+                let asks = (0..ask_count).map(|_| reader.level(self.scale, self.quantity_scale)).collect::<crate::Result<_>>()?;
+                MarketDataEvent::Snapshot { bids, asks }
+            },
+            other => {
+                return Err(MatchingEngineError::DeserializationError(format!("Unknown market data message type {other:#04x}")));
+            },
+        };
+        Ok((MarketDataMessage { symbol, sequence, event }, reader.offset))
+    }
+
+    fn write(&self, message: &MarketDataMessage, out: &mut Vec<u8>) -> crate::Result<()> {
+        out.push(message.event.message_type());
+        out.extend_from_slice(&encode_symbol(&message.symbol)?);
+        out.extend_from_slice(&message.sequence.to_le_bytes());
+        match &message.event {
+            MarketDataEvent::AddOrder { order_id, side, price, quantity } => {
+                out.extend_from_slice(order_id.as_uuid().as_bytes());
+                out.push(side_byte(*side));
+                out.extend_from_slice(&self.scale.to_ticks(*price)?.to_le_bytes());
+                out.extend_from_slice(&quantity.value().to_le_bytes());
+            },
+            MarketDataEvent::CancelOrder { order_id, quantity } => {
+                out.extend_from_slice(order_id.as_uuid().as_bytes());
+                out.extend_from_slice(&quantity.value().to_le_bytes());
+            },
+            MarketDataEvent::OrderExecuted { order_id, trade_id, price, quantity } => {
+                out.extend_from_slice(order_id.as_uuid().as_bytes());
+                out.extend_from_slice(&trade_id.to_le_bytes());
+                out.extend_from_slice(&self.scale.to_ticks(*price)?.to_le_bytes());
+                out.extend_from_slice(&quantity.value().to_le_bytes());
+            },
+            MarketDataEvent::LevelUpdate { side, price, quantity, order_count } => {
+                out.push(side_byte(*side));
+                out.extend_from_slice(&self.scale.to_ticks(*price)?.to_le_bytes());
+                out.extend_from_slice(&quantity.value().to_le_bytes());
+                out.extend_from_slice(&encode_order_count(*order_count)?.to_le_bytes());
+            },
+            MarketDataEvent::Snapshot { bids, asks } => {
+                for levels in [bids, asks] {
+                    let count = u16::try_from(levels.len())
+                        .map_err(|_| MatchingEngineError::SerializationError(format!("Snapshot of {} levels per side", levels.len())))?;
+                    out.extend_from_slice(&count.to_le_bytes());
+                }
+                for level in bids.iter().chain(asks) {
+                    if level.quantity_scale != self.quantity_scale {
+                        return Err(MatchingEngineError::SerializationError(format!(
+                            "Level counted to {} decimal places, codec to {}", level.quantity_scale, self.quantity_scale
+                        )));
+                    }
+                    out.extend_from_slice(&self.scale.to_ticks(level.price)?.to_le_bytes());
+                    out.extend_from_slice(&level.quantity.value().to_le_bytes());
+                    out.extend_from_slice(&encode_order_count(level.order_count)?.to_le_bytes());
+                }
+            },
+        }
+        Ok(())
+    }
+}
+
+fn encode_symbol(symbol: &Symbol) -> crate::Result<[u8; SYMBOL_LEN]> {
+    let bytes = symbol.as_str().as_bytes();
+    if bytes.len() > SYMBOL_LEN || !bytes.iter().all(u8::is_ascii_graphic) {
+        return Err(MatchingEngineError::SerializationError(format!("Symbol {symbol} is not up to {SYMBOL_LEN} ASCII characters")));
+    }
+    let mut padded = [b' '; SYMBOL_LEN];
+    padded[..bytes.len()].copy_from_slice(bytes);
+    Ok(padded)
+}
+
+fn encode_order_count(order_count: usize) -> crate::Result<u32> {
+    u32::try_from(order_count).map_err(|_| MatchingEngineError::SerializationError(format!("Order count {order_count} exceeds u32")))
+}
+
+fn side_byte(side: OrderSide) -> u8 {
+    match side {
+        OrderSide::Buy => b'B',
+        OrderSide::Sell => b'S',
+    }
+}
+
+/// Reads fields from the front of a message, failing cleanly on truncation
+struct Reader<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+}
+
+impl Reader<'_> {
+    fn take<const N: usize>(&mut self) -> crate::Result<[u8; N]> {
+        let field = self.bytes.get(self.offset..self.offset + N).ok_or_else(|| {
+            MatchingEngineError::DeserializationError(format!("Message truncated after {} bytes", self.bytes.len()))
+        })?;
+        self.offset += N;
+        Ok(field.try_into().expect("slice has the requested length"))
+    }
+
+    fn u8(&mut self) -> crate::Result<u8> {
+        Ok(self.take::<1>()?[0])
+    }
+
+    fn u16(&mut self) -> crate::Result<u16> {
+        self.take().map(u16::from_le_bytes)
+    }
+
+    fn u32(&mut self) -> crate::Result<u32> {
+        self.take().map(u32::from_le_bytes)
+    }
+
+    fn u64(&mut self) -> crate::Result<u64> {
+        self.take().map(u64::from_le_bytes)
+    }
+
+    fn symbol(&mut self) -> crate::Result<Symbol> {
+        let padded = self.take::<SYMBOL_LEN>()?;
+        let symbol = std::str::from_utf8(&padded).map(|symbol| symbol.trim_end_matches(' ').to_string());
+        symbol.map_err(|e| e.to_string())
+            .and_then(|symbol| Symbol::new(symbol).map_err(|e| e.to_string()))
+            .map_err(MatchingEngineError::DeserializationError)
+    }
+
+    fn order_id(&mut self) -> crate::Result<OrderId> {
+        Ok(OrderId::from_uuid(uuid::Uuid::from_bytes(self.take()?)))
+    }
+
+    fn side(&mut self) -> crate::Result<OrderSide> {
+        match self.u8()? {
+            b'B' => Ok(OrderSide::Buy),
+            b'S' => Ok(OrderSide::Sell),
+            other => Err(MatchingEngineError::DeserializationError(format!("Unknown side {other:#04x}"))),
+        }
+    }
+
+    fn price(&mut self, scale: TickScale) -> crate::Result<Price> {
+        let ticks = self.u64()?;
+        scale.to_price(ticks).map_err(|e| MatchingEngineError::DeserializationError(e.to_string()))
+    }
+
+    fn quantity(&mut self) -> crate::Result<Quantity> {
+        Quantity::new(self.u64()?).map_err(|e| MatchingEngineError::DeserializationError(e.to_string()))
+    }
+
+    fn level(&mut self, scale: TickScale, quantity_scale: u32) -> crate::Result<MarketLevel> {
+        Ok(MarketLevel { price: self.price(scale)?, quantity: self.quantity()?, order_count: self.u32()? as usize, quantity_scale })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{types::UserId, LimitOrderBook, Order};
+
+    fn codec() -> BinaryCodec {
+        BinaryCodec::new(TickScale::new(Price::from_cents(1).unwrap()))
+    }
+
+    fn price(value: &str) -> Price {
+        Price::from_str(value).unwrap()
+    }
+
+    fn quantity(value: u64) -> Quantity {
+        Quantity::new(value).unwrap()
+    }
+
+    fn message(sequence: u64, event: MarketDataEvent) -> MarketDataMessage {
+        MarketDataMessage { symbol: Symbol::new("AAPL".to_string()).unwrap(), sequence, event }
+    }
+
+    fn level(value: &str, size: u64, order_count: usize) -> MarketLevel {
+        MarketLevel { price: price(value), quantity: quantity(size), order_count, quantity_scale: 0 }
+    }
+
+    fn hex(digits: &str) -> Vec<u8> {
+        let digits: Vec<u8> = digits.bytes().filter(|byte| !byte.is_ascii_whitespace()).collect();
+        digits.chunks(2).map(|pair| u8::from_str_radix(std::str::from_utf8(pair).unwrap(), 16).unwrap()).collect()
+    }
+
+    /// One message of each type, with the exact bytes it must encode to
+    fn golden() -> Vec<(MarketDataMessage, Vec<u8>)> {
+        let order_id = OrderId::from_uuid(uuid::Uuid::from_u128(0x0102_0304_0506_0708_090a_0b0c_0d0e_0f10));
+        let header = |message_type: &str, sequence: &str| format!("{message_type} 4141504c20202020 {sequence}00000000000000");
+        let id = "0102030405060708090a0b0c0d0e0f10";
+        vec![
+            (
+                message(7, MarketDataEvent::AddOrder { order_id, side: OrderSide::Buy, price: price("150.25"), quantity: quantity(100) }),
+                hex(&format!("{} {id} 42 b13a000000000000 6400000000000000", header("41", "07"))),
+            ),
+            (
+                message(8, MarketDataEvent::CancelOrder { order_id, quantity: quantity(40) }),
+                hex(&format!("{} {id} 2800000000000000", header("58", "08"))),
+            ),
+            (
+                message(9, MarketDataEvent::OrderExecuted { order_id, trade_id: 42, price: price("150.25"), quantity: quantity(60) }),
+                hex(&format!("{} {id} 2a00000000000000 b13a000000000000 3c00000000000000", header("45", "09"))),
+            ),
+            (
+                message(10, MarketDataEvent::LevelUpdate {
+                    side: OrderSide::Sell,
+                    price: price("150.30"),
+                    quantity: Quantity::new_allow_zero(0),
+                    order_count: 0,
+                }),
+                hex(&format!("{} 53 b63a000000000000 0000000000000000 00000000", header("4c", "0a"))),
+            ),
+            (
+                message(11, MarketDataEvent::Snapshot { bids: vec![level("150.25", 100, 2)], asks: vec![level("150.30", 50, 1)] }),
+                hex(&format!(
+                    "{} 0100 0100 b13a000000000000 6400000000000000 02000000 b63a000000000000 3200000000000000 01000000",
+                    header("53", "0b")
+                )),
+            ),
+        ]
+    }
+
+    #[test]
+    fn test_wire_format_matches_golden_bytes() {
+        for (message, bytes) in golden() {
+            let mut encoded = Vec::new();
+            codec().encode(&message, &mut encoded).unwrap();
+            assert_eq!(encoded, bytes, "{:?}", message.event);
+            assert_eq!(codec().decode(&bytes).unwrap(), (message, bytes.len()));
+        }
+    }
+
+    #[test]
+    fn test_stream_round_trips_and_snapshot_takes_top_levels() {
+        let mut book = LimitOrderBook::new("MSFT".to_string()).unwrap();
+        for (side, cents) in [(OrderSide::Buy, 9900), (OrderSide::Buy, 9950), (OrderSide::Buy, 9800), (OrderSide::Sell, 10050), (OrderSide::Sell, 10100)] {
+            book.add_order(Order::new(OrderId::new(), UserId::new("maker".to_string()), side, Price::from_cents(cents).unwrap(), quantity(10))).unwrap();
+        }
+        let depth = book.market_depth(10);
+        let snapshot = MarketDataMessage { symbol: book.symbol().clone(), sequence: book.sequence(), event: MarketDataEvent::snapshot(&depth, 2) };
+        let MarketDataEvent::Snapshot { bids, asks } = &snapshot.event else { unreachable!() };
+        assert_eq!((bids.len(), asks.len()), (2, 2));
+        assert_eq!(bids[0].price, Price::from_cents(9950).unwrap());
+
+        let messages: Vec<MarketDataMessage> = golden().into_iter().map(|(message, _)| message).chain([snapshot]).collect();
+        let mut stream = Vec::new();
+        for message in &messages {
+            codec().encode(message, &mut stream).unwrap();
+        }
+        let mut decoded = Vec::new();
+        let mut rest = stream.as_slice();
+        while !rest.is_empty() {
+            let (message, len) = codec().decode(rest).unwrap();
+            decoded.push(message);
+            rest = &rest[len..];
+        }
+        assert_eq!(decoded, messages);
+    }
+
+    #[test]
+    fn test_fractional_levels_need_a_codec_of_their_scale() {
+        let mut book = LimitOrderBook::with_quantity_scale("FRAC".to_string(), 3).unwrap();
+        let shares = Quantity::from_decimal("0.125".parse().unwrap(), 3).unwrap();
+        book.add_order(Order::new(OrderId::new(), UserId::new("maker".to_string()), OrderSide::Buy, price("99.50"), shares)).unwrap();
+        let snapshot = message(1, MarketDataEvent::snapshot(&book.market_depth(1), 1));
+
+        let mut out = Vec::new();
+        assert!(matches!(codec().encode(&snapshot, &mut out), Err(MatchingEngineError::SerializationError(_))));
+        let fractional = codec().with_quantity_scale(3);
+        fractional.encode(&snapshot, &mut out).unwrap();
+        let (decoded, _) = fractional.decode(&out).unwrap();
+        assert_eq!(decoded, snapshot);
+        let MarketDataEvent::Snapshot { bids, .. } = decoded.event else { unreachable!() };
+        assert_eq!(bids[0].shares(), "0.125".parse().unwrap());
+    }
+
+    #[test]
+    fn test_prices_off_the_tick_and_bad_bytes_are_errors() {
+        let nickel = BinaryCodec::new(TickScale::new(Price::from_str("0.05").unwrap()));
+        let update = |value: &str| message(1, MarketDataEvent::LevelUpdate {
+            side: OrderSide::Buy,
+            price: price(value),
+            quantity: quantity(5),
+            order_count: 1,
+        });
+        let mut out = vec![0xff];
+        nickel.encode(&update("150.05"), &mut out).unwrap();
+        assert_eq!(nickel.decode(&out[1..]).unwrap().0, update("150.05"));
+
+        // A failed encode leaves the buffer as it was
+        out.truncate(1);
+        assert!(matches!(nickel.encode(&update("150.03"), &mut out), Err(MatchingEngineError::InvalidPrice(_))));
+        assert!(matches!(codec().encode(&update("150.005"), &mut out), Err(MatchingEngineError::InvalidPrice(_))));
+        let long = MarketDataMessage { symbol: Symbol::new("LONGSYMBOL".to_string()).unwrap(), ..update("150.05") };
+        assert!(matches!(codec().encode(&long, &mut out), Err(MatchingEngineError::SerializationError(_))));
+        assert_eq!(out, vec![0xff]);
+
+        let (_, bytes) = golden().remove(0);
+        for truncated in [&bytes[..0], &bytes[..HEADER_LEN - 1], &bytes[..bytes.len() - 1]] {
+            assert!(matches!(codec().decode(truncated), Err(MatchingEngineError::DeserializationError(_))));
+        }
+        let mut unknown = bytes.clone();
+        unknown[0] = b'Z';
+        let mut bad_side = bytes.clone();
+        bad_side[HEADER_LEN + 16] = b'Q';
+        let mut zero_price = bytes.clone();
+        zero_price[HEADER_LEN + 17..HEADER_LEN + 25].fill(0);
+        for corrupt in [unknown, bad_side, zero_price] {
+            assert!(matches!(codec().decode(&corrupt), Err(MatchingEngineError::DeserializationError(_))));
+        }
+    }
+}
@@ -1,16 +1,27 @@
 //! Order representation and status management
 
-use crate::{Price, Quantity, types::{OrderId, UserId}};
+use crate::{Price, Quantity, types::{OrderId, Symbol, UserId}};
 use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
 
 /// Order side (Buy or Sell)
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum OrderSide {
     Buy,
     Sell,
 }
 
+impl OrderSide {
+    /// Gets the opposite side
+    pub fn opposite(&self) -> Self {
+        match self {
+            OrderSide::Buy => OrderSide::Sell,
+            OrderSide::Sell => OrderSide::Buy,
+        }
+    }
+}
+
 impl std::fmt::Display for OrderSide {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -31,6 +42,10 @@ pub enum OrderStatus {
     Filled,
     /// Order has been cancelled
     Cancelled,
+    /// Order reached the end of its time in force without filling
+    Expired,
+    /// Order is parked until its scheduled activation time
+    PendingActivation,
 }
 
 impl std::fmt::Display for OrderStatus {
@@ -40,20 +55,76 @@ impl std::fmt::Display for OrderStatus {
             OrderStatus::PartiallyFilled => write!(f, "PARTIALLY_FILLED"),
             OrderStatus::Filled => write!(f, "FILLED"),
             OrderStatus::Cancelled => write!(f, "CANCELLED"),
+            OrderStatus::Expired => write!(f, "EXPIRED"),
+            OrderStatus::PendingActivation => write!(f, "PENDING_ACTIVATION"),
         }
     }
 }
 
-/// Represents a limit order in the order book
+/// How long an order remains working
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum TimeInForce {
+    /// Good till cancelled: rests across sessions
+    #[default]
+    GTC,
+    /// Expires when the trading session ends
+    Day,
+    /// Fill or kill: executes in full immediately or not at all
+    FOK,
+    /// Trades only in the closing auction; cancelled if it doesn't fill there
+    AtTheClose,
+}
+
+impl std::fmt::Display for TimeInForce {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TimeInForce::GTC => write!(f, "GTC"),
+            TimeInForce::Day => write!(f, "DAY"),
+            TimeInForce::FOK => write!(f, "FOK"),
+            TimeInForce::AtTheClose => write!(f, "ATC"),
+        }
+    }
+}
+
+/// Pricing instruction of an order
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum OrderType {
+    /// Trades at its limit price or better; any remainder rests
+    #[default]
+    Limit,
+    /// Trades at any price; any remainder is cancelled instead of resting
+    Market,
+    /// Held outside the book until a trade prints at or through `trigger`,
+    /// then becomes a market order
+    Stop { trigger: Price },
+}
+
+impl std::fmt::Display for OrderType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OrderType::Limit => write!(f, "LIMIT"),
+            OrderType::Market => write!(f, "MARKET"),
+            OrderType::Stop { trigger } => write!(f, "STOP@{}", trigger),
+        }
+    }
+}
+
+/// Represents an order in the order book
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Order {
     /// Unique order identifier
     pub id: OrderId,
     /// User who placed the order
     pub user_id: UserId,
+    /// Symbol the order trades; books stamp their own on orders submitted without one
+    /// 
+    /// `None` only on orders from snapshots that predate the field, which
+    /// belong to whichever book holds them.
+    #[serde(default)]
+    pub symbol: Option<Symbol>,
     /// Buy or sell side
     pub side: OrderSide,
-    /// Order price
+    /// Order price (limit orders only, see [`Order::market`])
     pub price: Price,
     /// Original quantity
     pub original_quantity: Quantity,
@@ -61,6 +132,30 @@ pub struct Order {
     pub remaining_quantity: Quantity,
     /// Current order status
     pub status: OrderStatus,
+    /// How long the order remains working
+    #[serde(default)]
+    pub time_in_force: TimeInForce,
+    /// Limit or market
+    #[serde(default)]
+    pub order_type: OrderType,
+    /// Good-till-date expiry: the order stops matching at this time
+    #[serde(default)]
+    pub expires_at: Option<DateTime<Utc>>,
+    /// Scheduled activation: the order is parked, neither matched nor displayed, until this time
+    #[serde(default)]
+    pub activate_at: Option<DateTime<Utc>>,
+    /// Rejected instead of trading on entry, so the order only ever provides liquidity
+    #[serde(default)]
+    pub post_only: bool,
+    /// May only reduce the user's net position, never open or increase it
+    #[serde(default)]
+    pub reduce_only: bool,
+    /// Matches normally but is left out of all quoted prices and depth
+    #[serde(default)]
+    pub hidden: bool,
+    /// Identifier assigned by the client, unique per user among live orders
+    #[serde(default)]
+    pub client_order_id: Option<String>,
     /// Timestamp when order was created
     pub created_at: DateTime<Utc>,
     /// Timestamp when order was last updated
@@ -80,16 +175,139 @@ impl Order {
         Self {
             id,
             user_id,
+            symbol: None,
             side,
             price,
             original_quantity: quantity,
             remaining_quantity: quantity,
             status: OrderStatus::Active,
+            time_in_force: TimeInForce::GTC,
+            order_type: OrderType::Limit,
+            expires_at: None,
+            activate_at: None,
+            post_only: false,
+            reduce_only: false,
+            hidden: false,
+            client_order_id: None,
             created_at: now,
             updated_at: now,
         }
     }
     
+    /// Creates a new market order
+    /// 
+    /// Market orders have no price. Their `price` field holds a bound every
+    /// opposite level crosses (the largest price for buys, the smallest for
+    /// sells) so that they sweep the book, and should not be read otherwise.
+    pub fn market(
+        id: OrderId,
+        user_id: UserId,
+        side: OrderSide,
+        quantity: Quantity,
+    ) -> Self {
+        Self {
+            order_type: OrderType::Market,
+            ..Self::new(id, user_id, side, Self::market_bound(side), quantity)
+        }
+    }
+    
+    /// Creates a new stop-market order
+    /// 
+    /// A buy stop triggers when a trade prints at or above `trigger`, a sell
+    /// stop at or below it. As with market orders, `price` holds a bound and
+    /// should not be read.
+    pub fn stop(
+        id: OrderId,
+        user_id: UserId,
+        side: OrderSide,
+        trigger: Price,
+        quantity: Quantity,
+    ) -> Self {
+        Self {
+            order_type: OrderType::Stop { trigger },
+            ..Self::new(id, user_id, side, Self::market_bound(side), quantity)
+        }
+    }
+    
+    pub(crate) fn market_bound(side: OrderSide) -> Price {
+        let bound = match side {
+            OrderSide::Buy => Decimal::MAX,
+            OrderSide::Sell => Decimal::new(1, 28),
+        };
+        Price::new(bound).expect("market price bound is positive")
+    }
+    
+    /// Sets a good-till-date expiry
+    pub fn with_expiry(mut self, expires_at: DateTime<Utc>) -> Self {
+        self.expires_at = Some(expires_at);
+        self
+    }
+    
+    /// Schedules the order's activation
+    pub fn with_activation(mut self, activate_at: DateTime<Utc>) -> Self {
+        self.activate_at = Some(activate_at);
+        self
+    }
+    
+    /// Marks the order post-only
+    pub fn with_post_only(mut self) -> Self {
+        self.post_only = true;
+        self
+    }
+    
+    /// Marks the order reduce-only
+    pub fn with_reduce_only(mut self) -> Self {
+        self.reduce_only = true;
+        self
+    }
+    
+    /// Marks the order hidden
+    pub fn with_hidden(mut self) -> Self {
+        self.hidden = true;
+        self
+    }
+    
+    /// Sets the symbol the order trades, so a book for any other symbol rejects it
+    pub fn with_symbol(mut self, symbol: Symbol) -> Self {
+        self.symbol = Some(symbol);
+        self
+    }
+    
+    /// Sets the client order ID
+    pub fn with_client_order_id(mut self, client_order_id: impl Into<String>) -> Self {
+        self.client_order_id = Some(client_order_id.into());
+        self
+    }
+    
+    /// Checks if the order's good-till-date expiry has passed at `now`
+    pub fn is_expired_at(&self, now: DateTime<Utc>) -> bool {
+        self.expires_at.is_some_and(|expires_at| expires_at <= now)
+    }
+    
+    /// Checks if this is a market order
+    pub fn is_market(&self) -> bool {
+        self.order_type == OrderType::Market
+    }
+    
+    /// Gets the trigger price of a stop order
+    pub fn stop_trigger(&self) -> Option<Price> {
+        match self.order_type {
+            OrderType::Stop { trigger } => Some(trigger),
+            _ => None,
+        }
+    }
+    
+    /// Checks if an unfilled remainder of this order may rest in the book
+    pub fn can_rest(&self) -> bool {
+        self.order_type == OrderType::Limit && self.time_in_force != TimeInForce::FOK
+    }
+    
+    /// Sets the order's time in force
+    pub fn with_time_in_force(mut self, time_in_force: TimeInForce) -> Self {
+        self.time_in_force = time_in_force;
+        self
+    }
+    
     /// Checks if the order can be matched against another order
     pub fn can_match(&self, other: &Order) -> bool {
         // Orders must be on opposite sides
@@ -112,6 +330,11 @@ impl Order {
     
     /// Fills a portion of the order
     pub fn fill(&mut self, quantity: Quantity) -> crate::Result<()> {
+        self.fill_at(quantity, Utc::now())
+    }
+    
+    /// Fills a portion of the order, stamping the update with `now`
+    pub fn fill_at(&mut self, quantity: Quantity, now: DateTime<Utc>) -> crate::Result<()> {
         if !quantity.can_be_satisfied_by(&self.remaining_quantity) {
             return Err(crate::MatchingEngineError::InsufficientQuantity {
                 requested: quantity.value(),
@@ -121,7 +344,7 @@ impl Order {
         
         let new_remaining = self.remaining_quantity.value() - quantity.value();
         self.remaining_quantity = Quantity::new_allow_zero(new_remaining);
-        self.updated_at = Utc::now();
+        self.updated_at = now;
         
         // Update status based on remaining quantity
         if self.remaining_quantity.value() == 0 {
@@ -133,10 +356,54 @@ impl Order {
         Ok(())
     }
     
+    /// Reduces the order's size without trading
+    /// 
+    /// Both the original and remaining quantities shrink, so `filled_quantity`
+    /// is unaffected. Reducing the remaining quantity to zero cancels the order.
+    pub fn reduce(&mut self, quantity: Quantity) -> crate::Result<()> {
+        self.reduce_at(quantity, Utc::now())
+    }
+    
+    /// Reduces the order's size as `reduce` does, stamping the update with `now`
+    pub fn reduce_at(&mut self, quantity: Quantity, now: DateTime<Utc>) -> crate::Result<()> {
+        if !quantity.can_be_satisfied_by(&self.remaining_quantity) {
+            return Err(crate::MatchingEngineError::InsufficientQuantity {
+                requested: quantity.value(),
+                available: self.remaining_quantity.value(),
+            });
+        }
+        
+        self.original_quantity = Quantity::new_allow_zero(self.original_quantity.value() - quantity.value());
+        self.remaining_quantity = Quantity::new_allow_zero(self.remaining_quantity.value() - quantity.value());
+        self.updated_at = now;
+        
+        if self.remaining_quantity.value() == 0 {
+            self.status = OrderStatus::Cancelled;
+        }
+        
+        Ok(())
+    }
+    
     /// Cancels the order
     pub fn cancel(&mut self) {
+        self.cancel_at(Utc::now());
+    }
+    
+    /// Cancels the order, stamping the update with `now`
+    pub fn cancel_at(&mut self, now: DateTime<Utc>) {
         self.status = OrderStatus::Cancelled;
-        self.updated_at = Utc::now();
+        self.updated_at = now;
+    }
+    
+    /// Marks the order expired at the end of its time in force
+    pub fn expire(&mut self) {
+        self.expire_at(Utc::now());
+    }
+    
+    /// Marks the order expired, stamping the update with `now`
+    pub fn expire_at(&mut self, now: DateTime<Utc>) {
+        self.status = OrderStatus::Expired;
+        self.updated_at = now;
     }
     
     /// Checks if the order is active (can participate in matching)
@@ -239,6 +506,22 @@ mod tests {
         assert!(order.fill(Quantity::new(1).unwrap()).is_err());
     }
 
+    #[test]
+    fn test_order_reduce() {
+        let mut order = create_test_order(OrderSide::Sell, 15000, 100);
+        order.fill(Quantity::new(30).unwrap()).unwrap();
+        
+        order.reduce(Quantity::new(50).unwrap()).unwrap();
+        assert_eq!(order.remaining_quantity.value(), 20);
+        assert_eq!(order.filled_quantity().value(), 30);
+        assert_eq!(order.status, OrderStatus::PartiallyFilled);
+        
+        assert!(order.reduce(Quantity::new(21).unwrap()).is_err());
+        order.reduce(Quantity::new(20).unwrap()).unwrap();
+        assert_eq!(order.status, OrderStatus::Cancelled);
+        assert_eq!(order.filled_quantity().value(), 30);
+    }
+
     #[test]
     fn test_order_priority() {
         let order1 = create_test_order(OrderSide::Buy, 15000, 100); // $150.00
@@ -1,12 +1,127 @@
 //! Core limit order book implementation with high-performance operations
 
 use crate::{
-    Order, OrderSide, Price, Quantity, 
+    Order, OrderSide, Price, Quantity, TimeInForce,
+    account::AccountRegistry,
+    price::DEFAULT_PRICE_SCALE,
+    types::UserId,
+    events::{CancelReason, ExecutionResult, OrderEvent, OrderOutcome},
     types::{OrderId, Symbol}, MatchingEngineError
 };
 use serde::{Deserialize, Serialize};
-use std::collections::{BTreeMap, HashMap};
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use chrono::{DateTime, Utc};
 use rust_decimal::Decimal;
+use bbo_publisher::BboPublisher;
+use book_snapshot::SnapshotCache;
+use depth_cache::DepthCache;
+use feed::DeltaFeed;
+use levels::PriceLevels;
+use lot_size::LotRules;
+use positions::PendingTotals;
+use slab::OrderLocation;
+use listener::ListenerSlot;
+use revisions::RevisionLog;
+use session::Session;
+use stops::StopBook;
+use trade_sink::TradeSinkSlot;
+
+mod activation;
+mod auction;
+mod bbo;
+mod bbo_publisher;
+mod book_snapshot;
+mod brackets;
+mod candles;
+mod checksum;
+mod client_ids;
+mod clock;
+mod closing;
+mod config;
+mod depth_cache;
+mod expiry;
+mod fees;
+mod feed;
+mod fill_or_kill;
+mod hidden;
+mod integrity;
+mod l3;
+mod levels;
+mod lot_size;
+mod listener;
+mod mass_cancel;
+mod memory;
+mod migration;
+mod modify;
+mod netting;
+mod notional;
+mod oplog;
+#[cfg(feature = "csv")]
+mod orders_csv;
+mod positions;
+mod post_only;
+mod preview;
+mod price_band;
+mod price_scale;
+pub(crate) mod quantity_scale;
+mod queue;
+mod reduce_only;
+mod revisions;
+mod seeding;
+mod session;
+mod slab;
+mod slippage;
+mod snapshot;
+mod snapshot_file;
+mod state;
+mod stats;
+mod stops;
+mod stp;
+mod sweep;
+mod tape;
+#[cfg(test)]
+pub(crate) mod test_support;
+mod trade_sink;
+mod volume_limit;
+mod vwap;
+
+pub use activation::Activation;
+pub use auction::IndicativeAuction;
+pub use bbo::Bbo;
+pub use bbo_publisher::{BboReader, PublishedBbo};
+pub use book_snapshot::BookSnapshot;
+#[cfg(feature = "snapshot-scheduler")]
+pub(crate) use book_snapshot::CapturedBook;
+pub use brackets::{Bracket, BracketState};
+pub use candles::{Candle, CandleAggregator};
+pub use clock::Clock;
+pub use config::BookConfig;
+pub use fees::{FeeRates, FeeRounding, FeeSchedule};
+pub use integrity::IntegrityError;
+pub use l3::{L3Detail, L3Entry, L3Snapshot};
+pub use levels::{LevelIter, LevelOrders, TieredStorage};
+pub use lot_size::QuantityRule;
+pub use listener::BookListener;
+pub use mass_cancel::OrderTimestamp;
+pub use memory::MemoryReport;
+pub use migration::{SnapshotEnvelope, CURRENT_SNAPSHOT_VERSION};
+pub use notional::{BandDepth, NotionalDepth, NotionalLevel};
+pub use oplog::{LoggedOp, OpLogReader, OpLogWriter, OpResult, OrderBookOp};
+pub use positions::{PositionLimit, PositionLimitAction};
+pub use preview::MatchPreview;
+pub use price_band::PriceBand;
+pub use queue::QueuePosition;
+pub use revisions::OrderRevision;
+pub use session::{SessionArchive, SessionStats, UserSessionCounters};
+pub use slippage::{FillSlippage, SlippageReport};
+pub use snapshot_file::SnapshotFormat;
+pub use state::BookState;
+pub use stats::BookStats;
+pub use stp::{SelfTradePrevention, StpPolicy, StpScope};
+pub use sweep::SweepCost;
+pub use trade_sink::{FlushPolicy, JsonlTradeReader, JsonlTradeSink, SkippedLine, TradeSink};
+pub use volume_limit::{VolumeLimit, VolumeUsage};
+pub use vwap::WindowVwap;
 
 /// Level II market data representation
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -14,50 +129,277 @@ pub struct MarketLevel {
     pub price: Price,
     pub quantity: Quantity,
     pub order_count: usize,
+    /// Decimal places `quantity` is counted to (zero on levels that predate the field)
+    #[serde(default)]
+    pub quantity_scale: u32,
 }
 
 /// Market depth information
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
 pub struct MarketDepth {
     pub bids: Vec<MarketLevel>,
     pub asks: Vec<MarketLevel>,
     pub spread: Option<Decimal>,
+    /// Book sequence number the depth was taken at
+    #[serde(default)]
+    pub sequence: u64,
 }
 
 /// Trade execution result
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Trade {
+    /// Book-assigned trade identifier, unique and increasing within a book
+    #[serde(default)]
+    pub trade_id: u64,
     pub buy_order_id: OrderId,
     pub sell_order_id: OrderId,
+    /// Resting order that provided the liquidity
+    /// 
+    /// This and the other maker/taker fields are `None` only on trades from
+    /// snapshots that predate them.
+    #[serde(default)]
+    pub maker_order_id: Option<OrderId>,
+    /// Incoming order that took the liquidity
+    #[serde(default)]
+    pub taker_order_id: Option<OrderId>,
+    /// Side of the taker
+    #[serde(default)]
+    pub aggressor_side: Option<OrderSide>,
+    /// User who bought
+    #[serde(default)]
+    pub buyer_user_id: Option<UserId>,
+    /// User who sold
+    #[serde(default)]
+    pub seller_user_id: Option<UserId>,
     pub price: Price,
     pub quantity: Quantity,
     pub timestamp: chrono::DateTime<chrono::Utc>,
+    /// Set when buyer and seller belong to the same firm (for surveillance)
+    #[serde(default)]
+    pub same_firm: bool,
+    /// Fee charged to the maker under the book's fee schedule
+    #[serde(default)]
+    pub maker_fee: Decimal,
+    /// Fee charged to the taker under the book's fee schedule
+    #[serde(default)]
+    pub taker_fee: Decimal,
+    /// Symbol of the book that executed the trade (`None` on trades from
+    /// snapshots that predate the field)
+    #[serde(default)]
+    pub symbol: Option<Symbol>,
+    /// Book sequence number of the mutation that produced this trade
+    #[serde(default)]
+    pub sequence: u64,
+    /// Decimal places `quantity` is counted to (zero on trades that predate the field)
+    #[serde(default)]
+    pub quantity_scale: u32,
 }
 
 /// High-performance limit order book implementation
 /// 
 /// Uses BTreeMap for O(log n) price-time priority and HashMap for O(1) order lookup.
 /// Maintains market data invariants and provides comprehensive query capabilities.
+/// Snapshots are validated on restore (see the snapshot module).
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(remote = "Self")]
 pub struct LimitOrderBook {
     /// Trading symbol for this order book
     symbol: Symbol,
     
     /// Buy orders sorted by price (highest first) then time (FIFO)
-    /// BTreeMap<Price, Vec<Order>> allows multiple orders at same price level
-    bids: BTreeMap<Price, Vec<Order>>,
+    /// Each price level holds multiple orders, split into hot and cold tiers when enabled
+    #[serde(deserialize_with = "levels::deserialize_bids")]
+    bids: PriceLevels,
     
     /// Sell orders sorted by price (lowest first) then time (FIFO)  
-    asks: BTreeMap<Price, Vec<Order>>,
+    #[serde(deserialize_with = "levels::deserialize_asks")]
+    asks: PriceLevels,
+    
+    /// Decimal places of one price tick; both sides key their levels by whole ticks
+    #[serde(default = "default_price_scale")]
+    price_scale: u32,
+    
+    /// Grid every resting price must be a whole multiple of, if narrower than the price scale
+    #[serde(default)]
+    tick_size: Option<Decimal>,
+    
+    /// Minimum lot and quantity step for requested order sizes (any size when `None`)
+    #[serde(default)]
+    lot_rules: Option<LotRules>,
+    
+    /// Decimal places of one quantity lot; every quantity in the book counts whole lots
+    #[serde(default)]
+    quantity_scale: u32,
     
     /// Fast order lookup by ID for cancellations and modifications
-    orders: HashMap<OrderId, (OrderSide, Price)>,
+    #[serde(deserialize_with = "slab::deserialize_locations")]
+    orders: HashMap<OrderId, OrderLocation>,
+    
+    /// Engine order IDs by user and client order ID (entries may be stale)
+    #[serde(default)]
+    client_order_ids: HashMap<UserId, HashMap<String, OrderId>>,
+    
+    /// Stop orders waiting for their trigger, outside the visible book
+    #[serde(default)]
+    stops: StopBook,
     
-    /// Recent trades for audit trail
-    recent_trades: Vec<Trade>,
+    /// Open brackets, in submission order
+    #[serde(default)]
+    brackets: Vec<Bracket>,
+    
+    /// Price of the most recent trade, which stop orders trigger off
+    #[serde(default)]
+    last_trade_price: Option<Price>,
+    
+    /// Trade statistics since the last reset
+    #[serde(default)]
+    stats: BookStats,
+    
+    /// Orders waiting for their activation time, in submission order
+    #[serde(default)]
+    parked: Vec<Order>,
+    
+    /// Each user's parked quantity by side, rebuilt from `parked` on restore
+    #[serde(skip)]
+    parked_totals: PendingTotals,
+    
+    /// At-the-close orders waiting for the closing auction, in submission order
+    #[serde(default)]
+    closing_book: Vec<Order>,
+    
+    /// Resting good-till-date orders by expiry time
+    #[serde(default)]
+    expiries: BTreeMap<DateTime<Utc>, Vec<OrderId>>,
+    
+    /// Recent trades for audit trail, oldest first, as a ring bounded by `max_recent_trades`
+    recent_trades: VecDeque<Trade>,
     
     /// Maximum number of recent trades to keep
     max_recent_trades: usize,
+    
+    /// Timestamp of the newest trade dropped from `recent_trades`
+    #[serde(default)]
+    trades_evicted_through: Option<DateTime<Utc>>,
+    
+    /// Identifier assigned to the next executed trade
+    #[serde(default = "first_trade_id")]
+    next_trade_id: u64,
+    
+    /// Firm and desk membership of users trading on this book
+    #[serde(default)]
+    accounts: AccountRegistry,
+    
+    /// Self-trade prevention settings (disabled when `None`)
+    #[serde(default)]
+    self_trade_prevention: Option<SelfTradePrevention>,
+    
+    /// Users whose offsetting same-price orders are netted instead of resting
+    #[serde(default)]
+    netting_users: HashSet<UserId>,
+    
+    /// Hot/cold level storage settings (untiered when `None`)
+    #[serde(default)]
+    tiered_storage: Option<TieredStorage>,
+    
+    /// Trading state
+    #[serde(default)]
+    state: BookState,
+    
+    /// Fat-finger band around the reference price (unchecked when `None`)
+    #[serde(default)]
+    price_band: Option<PriceBand>,
+    
+    /// Session volume limit that halts the book when reached
+    #[serde(default)]
+    volume_limit: Option<VolumeLimit>,
+    
+    /// Maker/taker fees charged on each trade (none when `None`)
+    #[serde(default)]
+    fee_schedule: Option<FeeSchedule>,
+    
+    /// Fees charged per user since the last reset
+    #[serde(default)]
+    fees_charged: HashMap<UserId, Decimal>,
+    
+    /// Volume traded in the current session
+    #[serde(default)]
+    volume_usage: VolumeUsage,
+    
+    /// Amendment histories of modified orders (disabled when `None`)
+    #[serde(default)]
+    revision_log: Option<RevisionLog>,
+    
+    /// Candles built from this book's trades (disabled when `None`)
+    #[serde(default)]
+    candles: Option<CandleAggregator>,
+    
+    /// Live statistics and counters for the current session
+    #[serde(default)]
+    session: Session,
+    
+    /// Most recently completed sessions, oldest first
+    #[serde(default)]
+    session_archives: VecDeque<SessionArchive>,
+    
+    /// Number of completed sessions to retain
+    #[serde(default)]
+    session_archive_retention: usize,
+    
+    /// Net position per user from trades on this book
+    #[serde(default)]
+    positions: HashMap<UserId, i64>,
+    
+    /// Per-user net position limits
+    #[serde(default)]
+    position_limits: HashMap<UserId, PositionLimit>,
+    
+    /// Sequence number of the last applied mutation
+    #[serde(default)]
+    sequence: u64,
+    
+    /// Number of ops applied, which is the op log sequence of the last one
+    #[serde(default)]
+    op_sequence: u64,
+    
+    /// Source of the current time
+    #[serde(default)]
+    clock: Clock,
+    
+    /// Last computed market depth, invalidated by mutations near the touch
+    #[serde(skip)]
+    depth_cache: DepthCache,
+    
+    /// Levels of the last copy-on-write snapshot and the levels changed since
+    #[serde(skip)]
+    snapshot_cache: SnapshotCache,
+    
+    /// Depth delta feed for downstream mirrors (disabled when `None`)
+    #[serde(skip)]
+    delta_feed: Option<DeltaFeed>,
+    
+    /// Synchronous listener, if one is installed
+    #[serde(skip)]
+    listener: ListenerSlot,
+    
+    /// Lock-free cell the touch is published to, if enabled
+    #[serde(skip)]
+    bbo_publisher: BboPublisher,
+    
+    /// Durable record of every trade, if a sink is attached
+    #[serde(skip)]
+    trade_sink: TradeSinkSlot,
+    
+    /// Operations applied since the op log was enabled (disabled when `None`)
+    #[serde(skip)]
+    op_log: Option<Vec<LoggedOp>>,
+}
+
+fn first_trade_id() -> u64 {
+    1
+}
+
+fn default_price_scale() -> u32 {
+    DEFAULT_PRICE_SCALE
 }
 
 impl LimitOrderBook {
@@ -65,11 +407,53 @@ impl LimitOrderBook {
     pub fn new(symbol: String) -> crate::Result<Self> {
         Ok(Self {
             symbol: Symbol::new(symbol)?,
-            bids: BTreeMap::new(),
-            asks: BTreeMap::new(),
+            bids: PriceLevels::new(OrderSide::Buy, DEFAULT_PRICE_SCALE),
+            asks: PriceLevels::new(OrderSide::Sell, DEFAULT_PRICE_SCALE),
+            price_scale: DEFAULT_PRICE_SCALE,
+            tick_size: None,
+            lot_rules: None,
+            quantity_scale: 0,
             orders: HashMap::new(),
-            recent_trades: Vec::new(),
+            client_order_ids: HashMap::new(),
+            stops: StopBook::default(),
+            brackets: Vec::new(),
+            last_trade_price: None,
+            stats: BookStats::default(),
+            parked: Vec::new(),
+            parked_totals: PendingTotals::default(),
+            closing_book: Vec::new(),
+            expiries: BTreeMap::new(),
+            recent_trades: VecDeque::new(),
             max_recent_trades: 1000,
+            trades_evicted_through: None,
+            next_trade_id: first_trade_id(),
+            accounts: AccountRegistry::new(),
+            self_trade_prevention: None,
+            netting_users: HashSet::new(),
+            tiered_storage: None,
+            state: BookState::Open,
+            price_band: None,
+            volume_limit: None,
+            fee_schedule: None,
+            fees_charged: HashMap::new(),
+            volume_usage: VolumeUsage::default(),
+            revision_log: None,
+            candles: None,
+            session: Session::default(),
+            session_archives: VecDeque::new(),
+            session_archive_retention: 0,
+            positions: HashMap::new(),
+            position_limits: HashMap::new(),
+            sequence: 0,
+            op_sequence: 0,
+            clock: Clock::System,
+            depth_cache: DepthCache::default(),
+            snapshot_cache: SnapshotCache::default(),
+            delta_feed: None,
+            listener: ListenerSlot::default(),
+            bbo_publisher: BboPublisher::default(),
+            trade_sink: TradeSinkSlot::default(),
+            op_log: None,
         })
     }
     
@@ -82,16 +466,18 @@ impl LimitOrderBook {
     
     /// Gets the best bid price (highest buy price)
     /// 
-    /// Returns `None` if there are no active bid orders.
+    /// Returns `None` if there are no active bid orders. Levels holding only
+    /// hidden orders are skipped.
     pub fn best_bid(&self) -> Option<Price> {
-        self.bids.keys().next_back().copied()
+        self.best_displayed(OrderSide::Buy)
     }
     
     /// Gets the best ask price (lowest sell price)
     /// 
-    /// Returns `None` if there are no active ask orders.
+    /// Returns `None` if there are no active ask orders. Levels holding only
+    /// hidden orders are skipped.
     pub fn best_ask(&self) -> Option<Price> {
-        self.asks.keys().next().copied()
+        self.best_displayed(OrderSide::Sell)
     }
     
     /// Calculates the bid-ask spread
@@ -104,102 +490,131 @@ impl LimitOrderBook {
         }
     }
     
+    /// Calculates the mid price, halfway between best bid and best ask
+    /// 
+    /// Returns `None` if either best bid or best ask is not available.
+    pub fn mid_price(&self) -> Option<Decimal> {
+        match (self.best_bid(), self.best_ask()) {
+            (Some(bid), Some(ask)) => Some((bid.value() + ask.value()) / Decimal::TWO),
+            _ => None,
+        }
+    }
+    
+    /// Calculates the average of best bid and best ask weighted by their own quantities
+    /// 
+    /// Leans toward the side with more quantity. Falls back to the mid price
+    /// when neither touch has active quantity, and returns `None` if either
+    /// best bid or best ask is not available.
+    pub fn weighted_mid(&self) -> Option<Decimal> {
+        self.touch_weighted(|bid_quantity, ask_quantity| (bid_quantity, ask_quantity))
+    }
+    
+    /// Calculates the microprice: best bid and best ask weighted by the opposite side's quantity
+    /// 
+    /// Leans toward the side with less quantity, which the imbalance suggests
+    /// the price will move to. Falls back to the mid price when neither touch
+    /// has active quantity, and returns `None` if either best bid or best ask
+    /// is not available.
+    pub fn microprice(&self) -> Option<Decimal> {
+        self.touch_weighted(|bid_quantity, ask_quantity| (ask_quantity, bid_quantity))
+    }
+    
+    /// Weights best bid and best ask by the weights `weights` derives from their quantities
+    fn touch_weighted(&self, weights: impl Fn(Decimal, Decimal) -> (Decimal, Decimal)) -> Option<Decimal> {
+        let (bid, ask) = (self.best_bid()?, self.best_ask()?);
+        let quantity = |quantity: Option<Quantity>| Decimal::from(quantity.map_or(0, |quantity| quantity.value()));
+        let (bid_weight, ask_weight) = weights(quantity(self.best_bid_quantity()), quantity(self.best_ask_quantity()));
+        if (bid_weight + ask_weight).is_zero() {
+            return self.mid_price();
+        }
+        Some((bid.value() * bid_weight + ask.value() * ask_weight) / (bid_weight + ask_weight))
+    }
+    
     /// Gets market depth information up to specified number of levels
     /// 
     /// # Arguments
     /// * `levels` - Maximum number of price levels to include on each side
     pub fn market_depth(&self, levels: usize) -> MarketDepth {
-        let bids = self.bids
-            .iter()
-            .rev() // Highest prices first for bids
-            .take(levels)
-            .map(|(price, orders)| {
-                let total_quantity = orders.iter()
-                    .filter(|order| order.is_active())
-                    .map(|order| order.remaining_quantity.value())
-                    .sum::<u64>();
-                
-                let order_count = orders.iter()
-                    .filter(|order| order.is_active())
-                    .count();
-                
-                MarketLevel {
-                    price: *price,
-                    quantity: if total_quantity > 0 { 
-                        Quantity::new(total_quantity).unwrap() 
-                    } else { 
-                        Quantity::new_allow_zero(0) 
-                    },
-                    order_count,
-                }
-            })
-            .filter(|level| level.quantity.value() > 0)
-            .collect();
-            
-        let asks = self.asks
-            .iter()
-            .take(levels) // Lowest prices first for asks
-            .map(|(price, orders)| {
-                let total_quantity = orders.iter()
-                    .filter(|order| order.is_active())
-                    .map(|order| order.remaining_quantity.value())
-                    .sum::<u64>();
-                
-                let order_count = orders.iter()
-                    .filter(|order| order.is_active())
-                    .count();
-                
-                MarketLevel {
-                    price: *price,
-                    quantity: if total_quantity > 0 { 
-                        Quantity::new(total_quantity).unwrap() 
-                    } else { 
-                        Quantity::new_allow_zero(0) 
-                    },
-                    order_count,
-                }
-            })
-            .filter(|level| level.quantity.value() > 0)
-            .collect();
-            
-        MarketDepth {
-            bids,
-            asks,
-            spread: self.spread(),
+        let mut depth = MarketDepth::default();
+        self.market_depth_into(levels, &mut depth);
+        depth
+    }
+    
+    /// Fills `out` with market depth up to `levels` levels per side, as `market_depth` returns it
+    /// 
+    /// Clears and refills `out`'s level vectors in place, so a caller polling
+    /// depth with the same `MarketDepth` allocates only while the vectors
+    /// grow to the depth asked for.
+    pub fn market_depth_into(&self, levels: usize, out: &mut MarketDepth) {
+        let now = self.now();
+        
+        // Orders that expired since the last mutation are invisible to the cache
+        let expired_resting = self.next_expiry().is_some_and(|at| at <= now);
+        for (side, side_levels) in [(OrderSide::Buy, &mut out.bids), (OrderSide::Sell, &mut out.asks)] {
+            side_levels.clear();
+            if expired_resting {
+                side_levels.extend(self.aggregated_levels(side, now).take(levels));
+            } else {
+                let build = |count| self.aggregated_levels(side, now).take(count).collect();
+                self.depth_cache.extend_side_levels(side, levels, build, side_levels);
+            }
+            side_levels.retain(|level| level.quantity.value() > 0);
         }
+        out.spread = self.spread();
+        out.sequence = self.sequence;
+    }
+    
+    /// Iterates the displayed levels of a side in priority order, aggregating each lazily
+    /// 
+    /// Yields the levels `market_depth` reports, one at a time and without
+    /// allocating; levels holding no active displayed quantity are skipped.
+    /// Stop early to avoid walking the whole side.
+    pub fn iter_levels(&self, side: OrderSide) -> impl DoubleEndedIterator<Item = MarketLevel> + '_ {
+        self.aggregated_levels(side, self.now())
+            .filter(|level| level.quantity.value() > 0)
+    }
+    
+    /// Gets the worst bid price (lowest displayed buy price)
+    /// 
+    /// Returns `None` if there are no active displayed bids.
+    pub fn worst_bid(&self) -> Option<Price> {
+        self.iter_levels(OrderSide::Buy).next_back().map(|level| level.price)
+    }
+    
+    /// Gets the worst ask price (highest displayed sell price)
+    /// 
+    /// Returns `None` if there are no active displayed asks.
+    pub fn worst_ask(&self) -> Option<Price> {
+        self.iter_levels(OrderSide::Sell).next_back().map(|level| level.price)
+    }
+    
+    /// Gets the bid level `n` levels from the touch, `nth_bid(0)` being the best
+    /// 
+    /// Counts only levels `market_depth` reports, so levels holding nothing
+    /// but inactive or hidden orders are skipped. Returns `None` if the side
+    /// has `n` or fewer such levels.
+    pub fn nth_bid(&self, n: usize) -> Option<MarketLevel> {
+        self.iter_levels(OrderSide::Buy).nth(n)
+    }
+    
+    /// Gets the ask level `n` levels from the touch, `nth_ask(0)` being the best
+    /// 
+    /// Skips levels like `nth_bid`.
+    pub fn nth_ask(&self, n: usize) -> Option<MarketLevel> {
+        self.iter_levels(OrderSide::Sell).nth(n)
     }
     
     /// Gets the total quantity available at the best bid
     pub fn best_bid_quantity(&self) -> Option<Quantity> {
         self.best_bid().and_then(|price| {
-            self.bids.get(&price).map(|orders| {
-                let total = orders.iter()
-                    .filter(|order| order.is_active())
-                    .map(|order| order.remaining_quantity.value())
-                    .sum::<u64>();
-                if total > 0 {
-                    Quantity::new(total).unwrap()
-                } else {
-                    Quantity::new_allow_zero(0)
-                }
-            })
+            self.bids.get(&price).map(|level| Quantity::new_allow_zero(level.totals().displayed_quantity))
         })
     }
     
     /// Gets the total quantity available at the best ask
     pub fn best_ask_quantity(&self) -> Option<Quantity> {
         self.best_ask().and_then(|price| {
-            self.asks.get(&price).map(|orders| {
-                let total = orders.iter()
-                    .filter(|order| order.is_active())
-                    .map(|order| order.remaining_quantity.value())
-                    .sum::<u64>();
-                if total > 0 {
-                    Quantity::new(total).unwrap()
-                } else {
-                    Quantity::new_allow_zero(0)
-                }
-            })
+            self.asks.get(&price).map(|level| Quantity::new_allow_zero(level.totals().displayed_quantity))
         })
     }
     
@@ -208,39 +623,194 @@ impl LimitOrderBook {
     /// Adds a new order to the book and attempts to match it
     /// 
     /// Returns a vector of trades that were executed.
-    pub fn add_order(&mut self, mut order: Order) -> crate::Result<Vec<Trade>> {
-        let mut trades = Vec::new();
+    pub fn add_order(&mut self, order: Order) -> crate::Result<Vec<Trade>> {
+        self.submit_order(order).map(|result| result.trades)
+    }
+    
+    /// Adds a batch of orders, strictly in the order given
+    /// 
+    /// Each order is processed exactly as by `add_order`. A rejected order
+    /// is reported in its outcome and does not stop the rest of the batch.
+    pub fn add_orders(&mut self, orders: Vec<Order>) -> Vec<OrderOutcome> {
+        orders.into_iter()
+            .map(|order| {
+                let order_id = order.id;
+                match self.submit_order(order) {
+                    Ok(result) => OrderOutcome { order_id, status: Some(result.status), trades: result.trades, error: None },
+                    Err(error) => OrderOutcome { order_id, status: None, trades: Vec::new(), error: Some(error) },
+                }
+            })
+            .collect()
+    }
+    
+    /// Adds a new order to the book and reports everything that happened to it
+    /// 
+    /// Unlike `add_order`, the result also carries the order's final status,
+    /// engine events such as self-trade prevention cancellations, and the
+    /// arrival benchmark used for slippage reporting. Trades include those of
+    /// any stop orders or bracket exits the order released, after the order's
+    /// own trades.
+    /// 
+    /// An order with an activation time still in the future is parked instead
+    /// (see `activate_pending`); only its symbol and its order and client order
+    /// IDs are checked until it activates.
+    pub fn submit_order(&mut self, order: Order) -> crate::Result<ExecutionResult> {
+        self.apply(OrderBookOp::Submit(order)).executed()
+    }
+    
+    fn submit(&mut self, mut order: Order) -> crate::Result<ExecutionResult> {
+        order.symbol.get_or_insert_with(|| self.symbol.clone());
+        if order.activate_at.is_some_and(|activate_at| activate_at > self.now()) {
+            self.check_state()?;
+            self.check_identity(&order)?;
+            self.check_price_scale(&order)?;
+            self.check_lot_size(&order)?;
+            self.track_client_order_id(&order);
+            return Ok(self.park(order));
+        }
+        if order.time_in_force == TimeInForce::AtTheClose {
+            return self.queue_for_close(order);
+        }
+        self.execute_order(order)
+    }
+    
+    /// Checks, matches, and rests an order that is due for processing
+    fn execute_order(&mut self, mut order: Order) -> crate::Result<ExecutionResult> {
+        let excess = match self.pre_trade_checks(&order) {
+            Ok(excess) => excess,
+            Err(error) => {
+                self.record_session_reject(&order.user_id);
+                return Err(error);
+            }
+        };
+        self.begin_mutation();
+        self.record_session_order(&order.user_id);
+        self.track_client_order_id(&order);
+        self.listener.order_accepted(&order);
+        let mut events = Vec::new();
+        
+        // Stops wait outside the book unless the last trade already triggers them
+        if order.stop_trigger().is_some() && !self.release_stop(&mut order, &mut events) {
+            let result = ExecutionResult {
+                order_id: order.id,
+                side: order.side,
+                status: order.status,
+                trades: Vec::new(),
+                events,
+                arrival_benchmark: self.arrival_benchmark(order.side),
+            };
+            self.stops.insert(order);
+            self.end_mutation();
+            return Ok(result);
+        }
+        self.trim_to_position_limit(&mut order, excess, &mut events)?;
+        self.trim_to_reduce_only(&mut order, &mut events)?;
+        let now = self.now();
+        if order.is_expired_at(now) {
+            order.expire_at(now);
+            events.push(OrderEvent::Expired { order: order.clone() });
+        } else if order.time_in_force == TimeInForce::FOK && !self.can_fill_completely(&order) {
+            order.cancel_at(now);
+            events.push(OrderEvent::Cancelled { order: order.clone(), reason: CancelReason::Unfilled });
+        }
+        let arrival_benchmark = self.arrival_benchmark(order.side);
         
         // Attempt to match the order
-        trades.extend(self.match_order(&mut order)?);
+        let mut trades = self.match_order(&mut order, &mut events)?;
+        let order_id = order.id;
+        let side = order.side;
+        
+        // Market and FOK orders never rest: cancel whatever the book couldn't fill
+        if !order.can_rest() && order.is_active() {
+            order.cancel_at(now);
+            events.push(OrderEvent::Cancelled { order: order.clone(), reason: CancelReason::Unfilled });
+        }
+        let status = order.status;
         
         // If order has remaining quantity, add to book
         if order.remaining_quantity.value() > 0 && order.is_active() {
             self.insert_order(order)?;
         }
+        self.settle_contingent_orders(&mut trades, &mut events)?;
+        for event in &events {
+            if let OrderEvent::Cancelled { order, .. } = event {
+                self.listener.order_cancelled(order);
+            }
+        }
+        self.end_mutation();
         
-        Ok(trades)
+        Ok(ExecutionResult { order_id, side, status, trades, events, arrival_benchmark })
+    }
+    
+    /// Runs every check `submit_order` applies before touching the book
+    /// 
+    /// Does not mutate anything, so callers can vet several orders before
+    /// committing to any of them.
+    pub fn validate_order(&self, order: &Order) -> crate::Result<()> {
+        self.pre_trade_checks(order).map(|_| ())
+    }
+    
+    /// Returns how much the order must be trimmed by to pass
+    fn pre_trade_checks(&self, order: &Order) -> crate::Result<u64> {
+        self.check_identity(order)?;
+        self.check_price_scale(order)?;
+        self.check_lot_size(order)?;
+        self.check_state()?;
+        self.check_price_band(order)?;
+        self.check_post_only(order)?;
+        self.check_reduce_only(order)?;
+        self.check_position_limit(order, true)
+    }
+    
+    /// Rejects an order for another symbol, or whose order or client order ID is taken
+    fn check_identity(&self, order: &Order) -> crate::Result<()> {
+        if let Some(symbol) = order.symbol.as_ref().filter(|symbol| **symbol != self.symbol) {
+            return Err(MatchingEngineError::SymbolMismatch { expected: self.symbol.clone(), got: symbol.clone() });
+        }
+        self.check_unique_id(order.id)?;
+        self.check_client_order_id(order)
+    }
+    
+    /// Rejects an ID already used by a resting, parked, or pending stop order
+    fn check_unique_id(&self, order_id: OrderId) -> crate::Result<()> {
+        if self.get_order(order_id).is_some() || self.get_pending_stop(order_id).is_some() {
+            return Err(MatchingEngineError::DuplicateOrderId(order_id));
+        }
+        Ok(())
     }
     
     /// Cancels an order by ID
     pub fn cancel_order(&mut self, order_id: OrderId) -> crate::Result<Order> {
-        let (side, price) = self.orders.remove(&order_id)
-            .ok_or_else(|| MatchingEngineError::OrderNotFound(order_id.to_string()))?;
+        self.apply(OrderBookOp::Cancel(order_id)).cancelled()
+    }
+    
+    fn cancel(&mut self, order_id: OrderId) -> crate::Result<Order> {
+        self.check_not_closed()?;
+        let Some(OrderLocation { side, price, handle }) = self.orders.remove(&order_id) else {
+            let order = self.cancel_stop(order_id)
+                .or_else(|_| self.cancel_parked(order_id))
+                .or_else(|_| self.cancel_closing(order_id))?;
+            self.record_session_cancel(&order.user_id);
+            self.listener.order_cancelled(&order);
+            return Ok(order);
+        };
+        self.begin_mutation();
+        let now = self.now();
+        self.touch_level(side, price);
             
-        let orders = match side {
+        let mut orders = match side {
             OrderSide::Buy => self.bids.get_mut(&price),
             OrderSide::Sell => self.asks.get_mut(&price),
         }.ok_or_else(|| MatchingEngineError::InvariantViolation(
             "Order exists in lookup but not in book".to_string()
         ))?;
         
-        let pos = orders.iter().position(|o| o.id == order_id)
+        let mut order = orders.remove(handle)
             .ok_or_else(|| MatchingEngineError::InvariantViolation(
                 "Order exists in lookup but not at price level".to_string()
             ))?;
             
-        let mut order = orders.remove(pos);
-        order.cancel();
+        order.cancel_at(now);
         
         // Remove empty price level
         if orders.is_empty() {
@@ -249,23 +819,66 @@ impl LimitOrderBook {
                 OrderSide::Sell => self.asks.remove(&price),
             };
         }
+        if let Some(expires_at) = order.expires_at {
+            self.untrack_expiry(order_id, expires_at);
+        }
+        self.prune_brackets();
+        self.record_session_cancel(&order.user_id);
+        self.listener.order_cancelled(&order);
+        self.end_mutation();
         
         Ok(order)
     }
     
     /// Gets an order by ID (for status queries)
+    /// 
+    /// Covers resting, parked, and at-the-close orders, but not pending stops (see `get_pending_stop`).
     pub fn get_order(&self, order_id: OrderId) -> Option<&Order> {
-        let (side, price) = self.orders.get(&order_id)?;
-        let orders = match side {
-            OrderSide::Buy => self.bids.get(price)?,
-            OrderSide::Sell => self.asks.get(price)?,
+        self.resting_order(order_id)
+            .or_else(|| self.parked_order(order_id))
+            .or_else(|| self.closing_order(order_id))
+    }
+    
+    /// Gets an order resting in the book by ID
+    fn resting_order(&self, order_id: OrderId) -> Option<&Order> {
+        let location = self.orders.get(&order_id)?;
+        match location.side {
+            OrderSide::Buy => self.bids.order(location.handle),
+            OrderSide::Sell => self.asks.order(location.handle),
+        }
+    }
+    
+    /// Gets the queue at a price level, in execution (FIFO) order
+    /// 
+    /// Includes hidden orders and any inactive orders not yet purged; see
+    /// `active_orders_at_price` to skip the latter.
+    pub fn orders_at_price(&self, side: OrderSide, price: Price) -> Option<LevelOrders<'_>> {
+        match side {
+            OrderSide::Buy => self.bids.get(&price),
+            OrderSide::Sell => self.asks.get(&price),
+        }
+    }
+    
+    /// Gets the active orders at a price level, in execution order
+    pub fn active_orders_at_price(&self, side: OrderSide, price: Price) -> impl Iterator<Item = &Order> {
+        self.orders_at_price(side, price)
+            .into_iter()
+            .flatten()
+            .filter(|order| order.is_active())
+    }
+    
+    /// Gets the total active quantity at a price level, hidden orders included
+    pub fn level_quantity(&self, side: OrderSide, price: Price) -> Option<Quantity> {
+        let level = match side {
+            OrderSide::Buy => self.bids.get(&price)?,
+            OrderSide::Sell => self.asks.get(&price)?,
         };
-        orders.iter().find(|o| o.id == order_id)
+        Some(Quantity::new_allow_zero(level.totals().quantity))
     }
     
-    /// Gets recent trades
-    pub fn recent_trades(&self) -> &[Trade] {
-        &self.recent_trades
+    /// Gets recent trades, oldest first
+    pub fn recent_trades(&self) -> impl ExactSizeIterator<Item = &Trade> + DoubleEndedIterator {
+        self.recent_trades.iter()
     }
     
     /// Checks if the order book is empty
@@ -280,70 +893,163 @@ impl LimitOrderBook {
     
     // === Private Implementation ===
     
+    /// Lazily aggregates active displayed quantity per level, in priority order
+    /// 
+    /// Levels with no active displayed orders are kept (with zero quantity) so callers
+    /// can tell how many raw levels were examined. Each level's cached totals are
+    /// used unless some resting order has expired since the last mutation.
+    fn aggregated_levels(&self, side: OrderSide, now: DateTime<Utc>) -> impl DoubleEndedIterator<Item = MarketLevel> + '_ {
+        let levels = match side {
+            OrderSide::Buy => &self.bids, // Highest prices first for bids
+            OrderSide::Sell => &self.asks, // Lowest prices first for asks
+        };
+        let expired_resting = self.next_expiry().is_some_and(|at| at <= now);
+        levels.iter()
+            .map(move |(price, orders)| {
+                let (total_quantity, order_count) = if expired_resting {
+                    orders.iter()
+                        .filter(|order| order.is_active() && !order.hidden && !order.is_expired_at(now))
+                        .fold((0, 0), |(quantity, count), order| (quantity + order.remaining_quantity.value(), count + 1))
+                } else {
+                    let totals = orders.totals();
+                    (totals.displayed_quantity, totals.displayed_orders)
+                };
+                
+                MarketLevel {
+                    price: *price,
+                    quantity: Quantity::new_allow_zero(total_quantity),
+                    order_count,
+                    quantity_scale: self.quantity_scale,
+                }
+            })
+    }
+    
     fn insert_order(&mut self, order: Order) -> crate::Result<()> {
         let side = order.side;
         let price = order.price;
         let order_id = order.id;
+        let expires_at = order.expires_at;
         
-        // Add to order lookup
-        self.orders.insert(order_id, (side, price));
+        // Add to appropriate side of the book, failing before anything else
+        // changes if the price has no level
+        let handle = match side {
+            OrderSide::Buy => hidden::enqueue(self.bids.level_mut(price)?, order),
+            OrderSide::Sell => hidden::enqueue(self.asks.level_mut(price)?, order),
+        };
         
-        // Add to appropriate side of the book
-        match side {
-            OrderSide::Buy => {
-                self.bids.entry(price).or_insert_with(Vec::new).push(order);
-            },
-            OrderSide::Sell => {
-                self.asks.entry(price).or_insert_with(Vec::new).push(order);
-            },
+        // Add to order lookup
+        self.orders.insert(order_id, OrderLocation { side, price, handle });
+        if let Some(expires_at) = expires_at {
+            self.expiries.entry(expires_at).or_default().push(order_id);
         }
+        self.touch_level(side, price);
         
         Ok(())
     }
     
-    fn match_order(&mut self, incoming_order: &mut Order) -> crate::Result<Vec<Trade>> {
+    fn match_order(&mut self, incoming_order: &mut Order, events: &mut Vec<OrderEvent>) -> crate::Result<Vec<Trade>> {
         let mut trades = Vec::new();
+        let now = self.now();
         
-        loop {
-            // Find the best opposing order (must re-evaluate each time as orders may be removed)
-            let best_opposing_price = match incoming_order.side {
-                OrderSide::Buy => {
-                    // For buy orders, match against lowest sell price
-                    self.asks.keys().next().copied()
-                },
-                OrderSide::Sell => {
-                    // For sell orders, match against highest buy price  
-                    self.bids.keys().next_back().copied()
-                },
-            };
+        // Orders collected for an auction rest, crossed or not, until it uncrosses
+        if self.state == BookState::PreOpen {
+            return Ok(trades);
+        }
+        
+        // Whether the opposing touch level has been found and touched; it is
+        // only looked up again once matching empties it
+        let mut at_level = false;
+        
+        while incoming_order.is_active() {
+            if !at_level {
+                // Find the best opposing price (re-evaluated each time a level empties)
+                let best_opposing_price = match incoming_order.side {
+                    OrderSide::Buy => {
+                        // For buy orders, match against lowest sell price
+                        self.asks.best()
+                    },
+                    OrderSide::Sell => {
+                        // For sell orders, match against highest buy price  
+                        self.bids.best()
+                    },
+                };
+                
+                let best_price = match best_opposing_price {
+                    Some(price) => price,
+                    None => break, // No opposing orders
+                };
+                
+                // Check if incoming order can match at this price level
+                let can_match_at_price = match incoming_order.side {
+                    OrderSide::Buy => incoming_order.price >= best_price,
+                    OrderSide::Sell => incoming_order.price <= best_price,
+                };
+                
+                if !can_match_at_price {
+                    break; // Price levels don't cross
+                }
+                
+                // Everything below mutates the opposing level at best_price
+                self.touch_level(incoming_order.side.opposite(), best_price);
+                at_level = true;
+            }
             
-            let best_price = match best_opposing_price {
-                Some(price) => price,
-                None => break, // No opposing orders
+            // Get the first order at the best price level (FIFO within price level)
+            let opposing_level = match incoming_order.side {
+                OrderSide::Buy => &mut self.asks,
+                OrderSide::Sell => &mut self.bids,
             };
             
-            // Check if incoming order can match at this price level
-            let can_match_at_price = match incoming_order.side {
-                OrderSide::Buy => incoming_order.price >= best_price,
-                OrderSide::Sell => incoming_order.price <= best_price,
+            let Some(mut opposing_order) = opposing_level.best_front_mut() else {
+                break; // No orders at this level
             };
-            
-            if !can_match_at_price {
-                break; // Price levels don't cross
+            if opposing_order.is_expired_at(now) {
+                // Good-till-date order past its expiry: expire it instead of trading
+                opposing_order.expire_at(now);
+                let expired = opposing_order.clone();
+                drop(opposing_order);
+                at_level = !self.remove_front_order(expired.id, expired.side)?;
+                events.push(OrderEvent::Expired { order: expired });
+                continue;
+            }
+            if !opposing_order.is_active() {
+                // Stale inactive order at the head of the queue: purge it and move on
+                let (stale_id, stale_side) = (opposing_order.id, opposing_order.side);
+                drop(opposing_order);
+                at_level = !self.remove_front_order(stale_id, stale_side)?;
+                continue;
             }
             
-            // Get the first order at the best price level (FIFO within price level)
-            let opposing_order = match incoming_order.side {
-                OrderSide::Buy => self.asks.get_mut(&best_price)
-                    .and_then(|orders| orders.first_mut()),
-                OrderSide::Sell => self.bids.get_mut(&best_price)
-                    .and_then(|orders| orders.first_mut()),
-            };
+            // Offset against the user's own order at the same price instead of trading with it
+            if netting::nets(&self.netting_users, incoming_order, &opposing_order) {
+                netting::net_against(incoming_order, &mut opposing_order, events, now)?;
+                if !opposing_order.is_active() {
+                    let (opposing_order_id, opposing_side) = (opposing_order.id, opposing_order.side);
+                    drop(opposing_order);
+                    at_level = !self.remove_front_order(opposing_order_id, opposing_side)?;
+                }
+                if !incoming_order.is_active() {
+                    break;
+                }
+                continue;
+            }
             
-            let opposing_order = match opposing_order {
-                Some(order) if order.is_active() => order,
-                _ => break, // No active orders at this level
-            };
+            // Apply self-trade prevention instead of trading within the same scope
+            if let Some(stp) = self.self_trade_prevention {
+                if stp.scope.applies(&self.accounts, &incoming_order.user_id, &opposing_order.user_id) {
+                    stp::prevent_self_trade(stp.policy, incoming_order, &mut opposing_order, events, now)?;
+                    
+                    if !opposing_order.is_active() {
+                        let (opposing_order_id, opposing_side) = (opposing_order.id, opposing_order.side);
+                        drop(opposing_order);
+                        at_level = !self.remove_front_order(opposing_order_id, opposing_side)?;
+                    }
+                    if !incoming_order.is_active() {
+                        break;
+                    }
+                    continue;
+                }
+            }
             
             // Execute the trade
             let trade_quantity = incoming_order.remaining_quantity.min(opposing_order.remaining_quantity);
@@ -352,31 +1058,62 @@ impl LimitOrderBook {
             // Save order info before modification  
             let opposing_order_id = opposing_order.id;
             let opposing_side = opposing_order.side;
+            let opposing_user_id = opposing_order.user_id.clone();
             
             // Create trade record
+            let trade_id = self.next_trade_id;
+            self.next_trade_id += 1;
+            let (maker_fee, taker_fee) = fees::trade_fees(
+                self.fee_schedule.as_ref(),
+                quantity_scale::notional(trade_price, trade_quantity.value(), self.quantity_scale),
+                (&opposing_user_id, &incoming_order.user_id),
+            );
+            let (buy_order, sell_order) = match incoming_order.side {
+                OrderSide::Buy => (&*incoming_order, &*opposing_order),
+                OrderSide::Sell => (&*opposing_order, &*incoming_order),
+            };
             let trade = Trade {
-                buy_order_id: match incoming_order.side {
-                    OrderSide::Buy => incoming_order.id,
-                    OrderSide::Sell => opposing_order.id,
-                },
-                sell_order_id: match incoming_order.side {
-                    OrderSide::Sell => incoming_order.id,
-                    OrderSide::Buy => opposing_order.id,
-                },
+                trade_id,
+                buy_order_id: buy_order.id,
+                sell_order_id: sell_order.id,
+                maker_order_id: Some(opposing_order_id),
+                taker_order_id: Some(incoming_order.id),
+                aggressor_side: Some(incoming_order.side),
+                buyer_user_id: Some(buy_order.user_id.clone()),
+                seller_user_id: Some(sell_order.user_id.clone()),
                 price: trade_price,
                 quantity: trade_quantity,
-                timestamp: chrono::Utc::now(),
+                timestamp: now,
+                symbol: Some(self.symbol.clone()),
+                sequence: self.sequence,
+                same_firm: self.accounts.same_firm(&incoming_order.user_id, &opposing_order.user_id),
+                maker_fee,
+                taker_fee,
+                quantity_scale: self.quantity_scale,
             };
             
             // Update order quantities
-            incoming_order.fill(trade_quantity)?;
-            opposing_order.fill(trade_quantity)?;
+            incoming_order.fill_at(trade_quantity, now)?;
+            opposing_order.fill_at(trade_quantity, now)?;
+            
+            // Remove filled order if completely filled
+            let opposing_filled = opposing_order.is_filled();
+            drop(opposing_order);
+            if opposing_filled {
+                at_level = !self.remove_front_order(opposing_order_id, opposing_side)?;
+            }
             
+            self.record_trade(&trade, (&incoming_order.user_id, incoming_order.side), &opposing_user_id);
             trades.push(trade);
             
-            // Remove filled order if completely filled
-            if opposing_order.is_filled() {
-                self.remove_filled_order(opposing_order_id, opposing_side, best_price)?;
+            // The fill moved both positions; cutting reduce-only orders may have emptied a level
+            if self.retrim_reduce_only([&incoming_order.user_id, &opposing_user_id], events)? {
+                at_level = false;
+            }
+            
+            // Halt once the session volume limit is reached
+            if self.record_volume(trade_price, trade_quantity, incoming_order, events) {
+                break;
             }
             
             // Stop if incoming order is fully filled
@@ -385,28 +1122,74 @@ impl LimitOrderBook {
             }
         }
         
-        // Add trades to recent history
-        self.recent_trades.extend(trades.iter().cloned());
-        if self.recent_trades.len() > self.max_recent_trades {
-            self.recent_trades.drain(0..self.recent_trades.len() - self.max_recent_trades);
-        }
+        self.remember_trades(&trades);
         
         Ok(trades)
     }
     
-    fn remove_filled_order(&mut self, order_id: OrderId, side: OrderSide, price: Price) -> crate::Result<()> {
-        // Remove from lookup
+    /// Books a committed trade against session counters, fees, statistics, and positions
+    fn record_trade(&mut self, trade: &Trade, taker: (&UserId, OrderSide), maker: &UserId) {
+        let (taker_user, taker_side) = taker;
+        self.record_session_trade(trade.price, trade.quantity, taker, maker);
+        self.record_fees((maker, trade.maker_fee), (taker_user, trade.taker_fee));
+        self.record_stats_trade(trade.price, trade.quantity);
+        self.record_candle_trade(trade);
+        self.listener.trade(trade);
+        self.trade_sink.record(trade);
+        match taker_side {
+            OrderSide::Buy => self.record_position(taker_user, maker, trade.quantity),
+            OrderSide::Sell => self.record_position(maker, taker_user, trade.quantity),
+        }
+        
+        self.last_trade_price = Some(trade.price);
+    }
+    
+    /// Adds trades to the recent history, dropping the oldest beyond its capacity
+    fn remember_trades(&mut self, trades: &[Trade]) {
+        // A sweep can print more trades than are kept: skip copying those that would go straight out
+        let skipped = trades.len().saturating_sub(self.max_recent_trades);
+        if let Some(newest_skipped) = skipped.checked_sub(1).map(|index| &trades[index]) {
+            self.recent_trades.clear();
+            self.trades_evicted_through = Some(newest_skipped.timestamp);
+        }
+        self.recent_trades.extend(trades[skipped..].iter().cloned());
+        self.evict_old_trades();
+    }
+    
+    /// Removes the order at the front of the touch level on `side`, which matching just reached
+    /// 
+    /// Matching has already touched the level. Returns whether the level emptied and is gone.
+    fn remove_front_order(&mut self, order_id: OrderId, side: OrderSide) -> crate::Result<bool> {
         self.orders.remove(&order_id);
+        let (order, emptied) = match side {
+            OrderSide::Buy => self.bids.pop_best_front(),
+            OrderSide::Sell => self.asks.pop_best_front(),
+        }.ok_or_else(|| MatchingEngineError::InvariantViolation(
+            "Resting order not found in book".to_string()
+        ))?;
+        if let Some(expires_at) = order.expires_at {
+            self.untrack_expiry(order_id, expires_at);
+        }
+        Ok(emptied)
+    }
+    
+    fn remove_resting_order(&mut self, order_id: OrderId, side: OrderSide, price: Price) -> crate::Result<()> {
+        // Remove from lookup
+        let handle = self.orders.remove(&order_id).map(|location| location.handle);
+        self.touch_level(side, price);
         
         // Remove from book
-        let orders = match side {
+        let mut orders = match side {
             OrderSide::Buy => self.bids.get_mut(&price),
             OrderSide::Sell => self.asks.get_mut(&price),
         }.ok_or_else(|| MatchingEngineError::InvariantViolation(
-            "Filled order not found in book".to_string()
+            "Resting order not found in book".to_string()
         ))?;
         
-        orders.retain(|o| o.id != order_id);
+        // Unlinking from the level's queue is O(1) wherever the order sits
+        let expires_at = handle
+            .and_then(|handle| orders.remove(handle))
+            .and_then(|o| o.expires_at);
         
         // Remove empty price level
         if orders.is_empty() {
@@ -415,6 +1198,9 @@ impl LimitOrderBook {
                 OrderSide::Sell => self.asks.remove(&price),
             };
         }
+        if let Some(expires_at) = expires_at {
+            self.untrack_expiry(order_id, expires_at);
+        }
         
         Ok(())
     }
@@ -543,4 +1329,299 @@ mod tests {
         assert_eq!(book.order_count(), 0);
         assert!(book.is_empty());
     }
+    
+    #[test]
+    fn test_deep_level_sweep_fills_every_order() {
+        // Twin of the `deep_level_sweep` benchmark: the sweep must stay exact however it is optimized
+        let depth = 10_000u64;
+        let mut book = LimitOrderBook::new("AAPL".to_string()).unwrap();
+        let mut resting = Vec::new();
+        for _ in 0..depth {
+            let order = create_test_order(OrderSide::Sell, 15000, 10);
+            resting.push(order.id);
+            book.add_order(order).unwrap();
+        }
+        
+        let trades = book.add_order(create_test_order(OrderSide::Buy, 15000, 10 * depth)).unwrap();
+        assert_eq!(trades.len() as u64, depth);
+        assert_eq!(trades.iter().map(|trade| trade.quantity.value()).sum::<u64>(), 10 * depth);
+        assert!(trades.iter().all(|trade| trade.price.as_cents() == 15000 && trade.quantity.value() == 10));
+        assert_eq!(trades.iter().map(|trade| trade.sell_order_id).collect::<Vec<_>>(), resting);
+        assert_eq!((book.order_count(), book.best_ask(), book.best_bid()), (0, None, None));
+        assert_eq!(book.recent_trades().len(), 1000);
+        assert_eq!(book.recent_trades().next_back().unwrap().trade_id, depth);
+        assert_eq!(book.verify_integrity(), Ok(()));
+    }
+    
+    #[test]
+    fn test_market_order_sweeps_and_cancels_remainder() {
+        let mut book = LimitOrderBook::new("AAPL".to_string()).unwrap();
+        book.add_order(create_test_order(OrderSide::Sell, 15000, 100)).unwrap();
+        book.add_order(create_test_order(OrderSide::Sell, 15100, 50)).unwrap();
+        book.add_order(create_test_order(OrderSide::Sell, 99900, 25)).unwrap();
+        book.add_order(create_test_order(OrderSide::Buy, 14900, 10)).unwrap();
+        
+        let market = Order::market(
+            OrderId::new(),
+            UserId::new("taker".to_string()),
+            OrderSide::Buy,
+            Quantity::new(200).unwrap(),
+        );
+        let market_id = market.id;
+        let result = book.submit_order(market).unwrap();
+        
+        // Each level trades at its resting price, however far away
+        let fills: Vec<(i64, u64)> = result.trades.iter()
+            .map(|trade| (trade.price.as_cents(), trade.quantity.value()))
+            .collect();
+        assert_eq!(fills, vec![(15000, 100), (15100, 50), (99900, 25)]);
+        
+        assert_eq!(result.status, OrderStatus::Cancelled);
+        assert!(matches!(
+            &result.events[..],
+            [OrderEvent::Cancelled { order, reason: CancelReason::Unfilled }] if order.remaining_quantity.value() == 25
+        ));
+        assert!(book.get_order(market_id).is_none());
+        assert!(book.best_ask().is_none());
+        assert_eq!(book.best_bid().unwrap().as_cents(), 14900);
+    }
+    
+    #[test]
+    fn test_batch_continues_past_rejected_order() {
+        let mut book = LimitOrderBook::new("AAPL".to_string()).unwrap();
+        let ask = create_test_order(OrderSide::Sell, 15000, 100);
+        let crossing_post_only = create_test_order(OrderSide::Buy, 15000, 10).with_post_only();
+        let buy = create_test_order(OrderSide::Buy, 15000, 60);
+        let rest = create_test_order(OrderSide::Buy, 14900, 10);
+        let ids = [ask.id, crossing_post_only.id, buy.id, rest.id];
+        
+        let outcomes = book.add_orders(vec![ask, crossing_post_only, buy, rest]);
+        
+        let order_ids: Vec<OrderId> = outcomes.iter().map(|outcome| outcome.order_id).collect();
+        assert_eq!(order_ids, ids);
+        assert_eq!(outcomes[0].status, Some(OrderStatus::Active));
+        assert!(matches!(outcomes[1].error, Some(MatchingEngineError::PostOnlyWouldCross { .. })));
+        assert_eq!(outcomes[1].status, None);
+        assert_eq!(outcomes[2].status, Some(OrderStatus::Filled));
+        assert_eq!(outcomes[2].trades[0].quantity.value(), 60);
+        assert!(outcomes[3].error.is_none() && outcomes[3].trades.is_empty());
+        assert_eq!(book.order_count(), 2);
+    }
+    
+    #[test]
+    fn test_level_queue_matches_execution_order() {
+        let mut book = LimitOrderBook::new("AAPL".to_string()).unwrap();
+        let price = Price::from_cents(15000).unwrap();
+        let orders: Vec<Order> = [30, 50, 20].iter().map(|&quantity| create_test_order(OrderSide::Sell, 15000, quantity)).collect();
+        for order in orders.clone() {
+            book.add_order(order).unwrap();
+        }
+        book.add_order(create_test_order(OrderSide::Sell, 15100, 10)).unwrap();
+        
+        let queue: Vec<OrderId> = book.orders_at_price(OrderSide::Sell, price).unwrap().iter().map(|order| order.id).collect();
+        assert_eq!(queue, orders.iter().map(|order| order.id).collect::<Vec<_>>());
+        assert_eq!(book.level_quantity(OrderSide::Sell, price).unwrap().value(), 100);
+        assert!(book.orders_at_price(OrderSide::Buy, price).is_none());
+        assert!(book.level_quantity(OrderSide::Sell, Price::from_cents(14900).unwrap()).is_none());
+        
+        // Trades consume the queue front to back, and the slice shrinks with them
+        let trades = book.add_order(create_test_order(OrderSide::Buy, 15000, 90)).unwrap();
+        let sellers: Vec<OrderId> = trades.iter().map(|trade| trade.sell_order_id).collect();
+        assert_eq!(sellers, queue);
+        let remaining = book.orders_at_price(OrderSide::Sell, price).unwrap();
+        assert_eq!(remaining.len(), 1);
+        assert_eq!((remaining[0].id, remaining[0].remaining_quantity.value()), (queue[2], 10));
+        assert_eq!(book.active_orders_at_price(OrderSide::Sell, price).count(), 1);
+    }
+    
+    #[test]
+    fn test_duplicate_order_id_is_rejected() {
+        let mut book = LimitOrderBook::new("AAPL".to_string()).unwrap();
+        let order = create_test_order(OrderSide::Buy, 15000, 100);
+        let order_id = order.id;
+        book.add_order(order.clone()).unwrap();
+        book.add_order(create_test_order(OrderSide::Sell, 15000, 40)).unwrap();
+        
+        // Reusing the ID of the partially filled resting order used to overwrite its
+        // lookup entry while both copies stayed queued, stranding one of them
+        let mut reused = order;
+        reused.price = Price::from_cents(14900).unwrap();
+        assert_eq!(book.add_order(reused), Err(MatchingEngineError::DuplicateOrderId(order_id)));
+        assert_eq!(book.order_count(), 1);
+        assert_eq!(book.market_depth(10).bids.len(), 1);
+        
+        assert_eq!(book.cancel_order(order_id).unwrap().remaining_quantity.value(), 60);
+        assert!(book.is_empty());
+        assert!(matches!(book.cancel_order(order_id), Err(MatchingEngineError::OrderNotFound(_))));
+    }
+    
+    #[test]
+    fn test_order_for_another_symbol_is_rejected() {
+        let mut book = LimitOrderBook::new("AAPL".to_string()).unwrap();
+        let msft = Symbol::new("MSFT".to_string()).unwrap();
+        let misrouted = create_test_order(OrderSide::Buy, 15000, 100).with_symbol(msft.clone());
+        assert_eq!(
+            book.add_order(misrouted),
+            Err(MatchingEngineError::SymbolMismatch { expected: book.symbol().clone(), got: msft })
+        );
+        assert!(book.is_empty());
+        
+        // Orders without a symbol take the book's, as do the trades they print
+        let bid = create_test_order(OrderSide::Buy, 15000, 100);
+        let bid_id = bid.id;
+        book.add_order(bid).unwrap();
+        assert_eq!(book.get_order(bid_id).unwrap().symbol.as_ref(), Some(book.symbol()));
+        let ask = create_test_order(OrderSide::Sell, 15000, 40).with_symbol(Symbol::new("aapl".to_string()).unwrap());
+        let trades = book.add_order(ask).unwrap();
+        assert_eq!(trades[0].symbol.as_ref(), Some(book.symbol()));
+    }
+    
+    #[test]
+    fn test_orders_and_trades_without_symbol_still_deserialize() {
+        let order = create_test_order(OrderSide::Buy, 15000, 100);
+        let mut json = serde_json::to_value(&order).unwrap();
+        json.as_object_mut().unwrap().remove("symbol");
+        let restored: Order = serde_json::from_value(json).unwrap();
+        assert_eq!(restored.symbol, None);
+        
+        let mut book = LimitOrderBook::new("AAPL".to_string()).unwrap();
+        book.add_order(order).unwrap();
+        book.add_order(create_test_order(OrderSide::Sell, 15000, 100)).unwrap();
+        let mut json = serde_json::to_value(book.recent_trades().next().unwrap()).unwrap();
+        json.as_object_mut().unwrap().remove("symbol");
+        let restored: Trade = serde_json::from_value(json).unwrap();
+        assert_eq!(restored.symbol, None);
+    }
+    
+    #[test]
+    fn test_resting_order_is_always_maker() {
+        let mut book = LimitOrderBook::new("AAPL".to_string()).unwrap();
+        let mut ask = create_test_order(OrderSide::Sell, 15000, 100);
+        ask.user_id = UserId::new("seller".to_string());
+        let ask_id = ask.id;
+        book.add_order(ask).unwrap();
+        
+        // The taker bids through the ask and trades at the maker's price
+        let bid = create_test_order(OrderSide::Buy, 15100, 60);
+        let bid_id = bid.id;
+        let trades = book.add_order(bid).unwrap();
+        let trade = &trades[0];
+        assert_eq!((trade.maker_order_id, trade.taker_order_id), (Some(ask_id), Some(bid_id)));
+        assert_eq!(trade.aggressor_side, Some(OrderSide::Buy));
+        assert_eq!(trade.buyer_user_id, Some(UserId::new("test_user".to_string())));
+        assert_eq!(trade.seller_user_id, Some(UserId::new("seller".to_string())));
+        assert_eq!(trade.price.as_cents(), 15000);
+        
+        // The same holds for a sell sweeping a better-priced bid
+        book.add_order(create_test_order(OrderSide::Buy, 14900, 50)).unwrap();
+        let sell = create_test_order(OrderSide::Sell, 14800, 80);
+        let sell_id = sell.id;
+        let trades = book.add_order(sell).unwrap();
+        assert_eq!(trades.len(), 1);
+        assert_eq!(trades[0].taker_order_id, Some(sell_id));
+        assert_eq!(trades[0].aggressor_side, Some(OrderSide::Sell));
+        assert_eq!(trades[0].sell_order_id, sell_id);
+        assert_eq!(trades[0].maker_order_id, Some(trades[0].buy_order_id));
+        assert_eq!(trades[0].price.as_cents(), 14900);
+    }
+    
+    #[test]
+    fn test_inactive_order_at_head_of_level_is_evicted() {
+        let mut book = LimitOrderBook::new("AAPL".to_string()).unwrap();
+        let stale = create_test_order(OrderSide::Sell, 15000, 100);
+        let live = create_test_order(OrderSide::Sell, 15000, 40);
+        let deeper = create_test_order(OrderSide::Sell, 15100, 40);
+        let (stale_id, live_id, deeper_id) = (stale.id, live.id, deeper.id);
+        book.add_order(stale).unwrap();
+        book.add_order(live).unwrap();
+        book.add_order(deeper).unwrap();
+        
+        // Cancel the head of the level in place, bypassing cancel_order
+        let price = Price::from_cents(15000).unwrap();
+        book.asks.front_mut(&price).unwrap().cancel();
+        
+        let trades = book.add_order(create_test_order(OrderSide::Buy, 15100, 60)).unwrap();
+        let fills: Vec<_> = trades.iter().map(|trade| (trade.sell_order_id, trade.quantity.value())).collect();
+        assert_eq!(fills, vec![(live_id, 40), (deeper_id, 20)]);
+        assert!(book.get_order(stale_id).is_none());
+        assert_eq!(book.order_count(), 1);
+        assert!(book.best_bid().is_none_or(|bid| bid < book.best_ask().unwrap()));
+    }
+    
+    #[test]
+    fn test_mid_weighted_mid_and_microprice() {
+        let mut book = LimitOrderBook::new("TEST".to_string()).unwrap();
+        book.add_order(create_test_order(OrderSide::Buy, 10000, 300)).unwrap();
+        assert_eq!((book.mid_price(), book.weighted_mid(), book.microprice()), (None, None, None));
+        
+        book.add_order(create_test_order(OrderSide::Sell, 10010, 100)).unwrap();
+        assert_eq!(book.mid_price(), Some(Decimal::new(10005, 2)));
+        // (100.00 * 300 + 100.10 * 100) / 400
+        assert_eq!(book.weighted_mid(), Some(Decimal::new(1000250, 4)));
+        // (100.00 * 100 + 100.10 * 300) / 400
+        assert_eq!(book.microprice(), Some(Decimal::new(1000750, 4)));
+    }
+    
+    #[test]
+    fn test_touch_with_only_inactive_quantity_falls_back_to_mid() {
+        let mut book = LimitOrderBook::new("TEST".to_string()).unwrap();
+        book.add_order(create_test_order(OrderSide::Buy, 10000, 300)).unwrap();
+        book.add_order(create_test_order(OrderSide::Sell, 10010, 100)).unwrap();
+        book.bids.front_mut(&Price::from_cents(10000).unwrap()).unwrap().cancel();
+        book.asks.front_mut(&Price::from_cents(10010).unwrap()).unwrap().cancel();
+        
+        assert_eq!(book.weighted_mid(), book.mid_price());
+        assert_eq!(book.microprice(), Some(Decimal::new(10005, 2)));
+        
+        // Only the bid has quantity: the microprice moves to the ask
+        book.add_order(create_test_order(OrderSide::Buy, 10000, 50)).unwrap();
+        assert_eq!(book.microprice(), Some(Decimal::new(10010, 2)));
+        assert_eq!(book.weighted_mid(), Some(Decimal::new(10000, 2)));
+    }
+    
+    #[test]
+    fn test_iter_levels_agrees_with_market_depth() {
+        let mut book = LimitOrderBook::new("TEST".to_string()).unwrap();
+        for (price_cents, quantity) in [(10000, 10), (10000, 5), (10100, 20), (10200, 30), (10300, 40)] {
+            book.add_order(create_test_order(OrderSide::Sell, price_cents, quantity)).unwrap();
+        }
+        book.add_order(create_test_order(OrderSide::Buy, 9900, 10)).unwrap();
+        book.asks.front_mut(&Price::from_cents(10100).unwrap()).unwrap().cancel();
+        
+        let depth = book.market_depth(usize::MAX);
+        assert_eq!(book.iter_levels(OrderSide::Sell).collect::<Vec<_>>(), depth.asks);
+        assert_eq!(book.iter_levels(OrderSide::Buy).collect::<Vec<_>>(), depth.bids);
+        
+        let top = book.iter_levels(OrderSide::Sell).next().unwrap();
+        assert_eq!((top.price.as_cents(), top.quantity.value(), top.order_count), (10000, 15, 2));
+        let prices: Vec<i64> = book.iter_levels(OrderSide::Sell).map(|level| level.price.as_cents()).collect();
+        assert_eq!(prices, vec![10000, 10200, 10300]);
+    }
+    
+    #[test]
+    fn test_nth_and_worst_levels_skip_inactive_and_hidden() {
+        let mut book = LimitOrderBook::new("TEST".to_string()).unwrap();
+        for (price_cents, quantity) in [(9900, 10), (9800, 20), (9700, 30), (9600, 40)] {
+            book.add_order(create_test_order(OrderSide::Buy, price_cents, quantity)).unwrap();
+        }
+        book.add_order(create_test_order(OrderSide::Buy, 9850, 5).with_hidden()).unwrap();
+        book.add_order(create_test_order(OrderSide::Buy, 9500, 5).with_hidden()).unwrap();
+        book.add_order(create_test_order(OrderSide::Sell, 10100, 15)).unwrap();
+        
+        // Cancelled in place, leaving a level of only inactive orders
+        book.bids.front_mut(&Price::from_cents(9800).unwrap()).unwrap().cancel();
+        
+        let prices: Vec<Option<i64>> = (0..4).map(|n| book.nth_bid(n).map(|level| level.price.as_cents())).collect();
+        assert_eq!(prices, vec![Some(9900), Some(9700), Some(9600), None]);
+        assert_eq!(book.nth_bid(1).unwrap().quantity.value(), 30);
+        assert_eq!(book.nth_bid(usize::MAX), None);
+        assert_eq!(book.worst_bid().unwrap().as_cents(), 9600);
+        
+        assert_eq!(book.nth_ask(0).map(|level| level.price), book.best_ask());
+        assert_eq!(book.worst_ask(), book.best_ask());
+        assert_eq!(book.nth_ask(1), None);
+        
+        let empty = LimitOrderBook::new("TEST".to_string()).unwrap();
+        assert_eq!((empty.worst_bid(), empty.worst_ask(), empty.nth_ask(0)), (None, None, None));
+    }
 }
\ No newline at end of file
@@ -0,0 +1,253 @@
+//! Scheduled order activation
+//!
+//! An order submitted before its activation time is parked: it is neither
+//! matched nor displayed, but can be looked up and cancelled by ID.
+//! `activate_pending` releases every order that has come due into normal
+//! order processing, in submission order, so orders sharing an activation
+//! time are released first in, first out. Until then a parked order can be
+//! amended in place; it keeps its place among orders due at the same time.
+
+use super::{revisions::OrderRevision, LimitOrderBook, OrderBookOp, Trade};
+use crate::{
+    events::{ExecutionResult, ModifyResult},
+    types::OrderId,
+    MatchingEngineError, Order, OrderStatus, Price, Quantity,
+};
+use chrono::{DateTime, Utc};
+
+/// Outcome of releasing one parked order
+#[derive(Debug, Clone, PartialEq)]
+pub enum Activation {
+    /// The order went through order processing
+    Executed(ExecutionResult),
+    /// The book rejected the order, which is dropped
+    Rejected { order: Order, error: MatchingEngineError },
+}
+
+impl Activation {
+    /// Gets the trades the released order produced
+    pub fn trades(&self) -> &[Trade] {
+        match self {
+            Activation::Executed(result) => &result.trades,
+            Activation::Rejected { .. } => &[],
+        }
+    }
+}
+
+impl LimitOrderBook {
+    /// Releases every parked order due at `now`, returning each one's outcome
+    ///
+    /// Released orders go through the same checks as any submitted order;
+    /// one the book rejects is dropped without affecting the others and
+    /// reported with its error.
+    pub fn activate_pending(&mut self, now: DateTime<Utc>) -> Vec<Activation> {
+        self.apply(OrderBookOp::ActivatePending(now)).activations().unwrap_or_default()
+    }
+    
+    pub(super) fn activate_due(&mut self, now: DateTime<Utc>) -> Vec<Activation> {
+        let (due, parked): (Vec<Order>, Vec<Order>) = std::mem::take(&mut self.parked)
+            .into_iter()
+            .partition(|order| order.activate_at.is_none_or(|activate_at| activate_at <= now));
+        self.parked = parked;
+        for order in &due {
+            self.parked_totals.remove(order);
+        }
+        
+        due.into_iter()
+            .map(|mut order| {
+                order.status = OrderStatus::Active;
+                match self.execute_order(order.clone()) {
+                    Ok(result) => Activation::Executed(result),
+                    Err(error) => Activation::Rejected { order, error },
+                }
+            })
+            .collect()
+    }
+    
+    /// Gets the number of parked orders
+    pub fn parked_order_count(&self) -> usize {
+        self.parked.len()
+    }
+    
+    /// Gets the earliest activation time among parked orders
+    pub fn next_activation(&self) -> Option<DateTime<Utc>> {
+        self.parked.iter().filter_map(|order| order.activate_at).min()
+    }
+    
+    pub(super) fn park(&mut self, mut order: Order) -> ExecutionResult {
+        order.status = OrderStatus::PendingActivation;
+        let result = ExecutionResult {
+            order_id: order.id,
+            side: order.side,
+            status: order.status,
+            trades: Vec::new(),
+            events: Vec::new(),
+            arrival_benchmark: self.arrival_benchmark(order.side),
+        };
+        self.parked_totals.add(&order);
+        self.parked.push(order);
+        result
+    }
+    
+    pub(super) fn parked_order(&self, order_id: OrderId) -> Option<&Order> {
+        self.parked.iter().find(|order| order.id == order_id)
+    }
+    
+    /// Amends a parked order's price and/or total quantity in place
+    pub(super) fn amend_parked(
+        &mut self,
+        order_id: OrderId,
+        new_price: Option<Price>,
+        new_quantity: Option<Quantity>,
+        context: Option<String>,
+    ) -> crate::Result<ModifyResult> {
+        let index = self.parked.iter()
+            .position(|order| order.id == order_id)
+            .ok_or_else(|| MatchingEngineError::OrderNotFound(order_id.to_string()))?;
+        let previous = self.parked[index].clone();
+        let mut amended = previous.clone();
+        amended.price = new_price.unwrap_or(previous.price);
+        amended.original_quantity = new_quantity.unwrap_or(previous.original_quantity);
+        amended.remaining_quantity = amended.original_quantity;
+        self.check_price_scale(&amended)?;
+        self.check_lot_size(&amended)?;
+        
+        let now = self.now();
+        amended.updated_at = now;
+        self.record_revision(order_id, OrderRevision {
+            sequence: self.sequence,
+            timestamp: now,
+            old_price: previous.price,
+            new_price: amended.price,
+            old_qty: previous.original_quantity,
+            new_qty: amended.original_quantity,
+            priority_lost: false,
+            context,
+        });
+        let status = amended.status;
+        self.parked_totals.replace(Some(&previous), Some(&amended));
+        self.parked[index] = amended;
+        Ok(ModifyResult { previous, status, trades: Vec::new(), events: Vec::new(), priority_lost: false })
+    }
+    
+    pub(super) fn cancel_parked(&mut self, order_id: OrderId) -> crate::Result<Order> {
+        let now = self.now();
+        let index = self.parked.iter()
+            .position(|order| order.id == order_id)
+            .ok_or_else(|| MatchingEngineError::OrderNotFound(order_id.to_string()))?;
+        let mut order = self.parked.remove(index);
+        self.parked_totals.remove(&order);
+        order.cancel_at(now);
+        Ok(order)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Activation, Trade};
+    use crate::{
+        types::OrderId,
+        LimitOrderBook, MatchingEngineError, OrderSide, OrderStatus, Price, Quantity,
+    };
+    use chrono::{DateTime, Duration, Utc};
+    use crate::order_book::test_support::create_test_order;
+
+    fn open_time() -> DateTime<Utc> {
+        Utc::now() + Duration::hours(1)
+    }
+
+    fn trades(activations: &[Activation]) -> Vec<Trade> {
+        activations.iter().flat_map(Activation::trades).cloned().collect()
+    }
+
+    #[test]
+    fn test_parked_buy_crosses_on_activation() {
+        let mut book = LimitOrderBook::new("OPEN".to_string()).unwrap();
+        let open = open_time();
+        book.add_order(create_test_order(OrderSide::Sell, 10000, 100)).unwrap();
+        
+        let parked = create_test_order(OrderSide::Buy, 10100, 60).with_activation(open);
+        let parked_id = parked.id;
+        let result = book.submit_order(parked).unwrap();
+        assert_eq!(result.status, OrderStatus::PendingActivation);
+        assert!(result.trades.is_empty());
+        assert_eq!(book.get_order(parked_id).unwrap().status, OrderStatus::PendingActivation);
+        assert!(book.best_bid().is_none());
+        assert_eq!(book.next_activation(), Some(open));
+        
+        // Nothing is due a second early
+        assert!(book.activate_pending(open - Duration::seconds(1)).is_empty());
+        
+        let trades = trades(&book.activate_pending(open));
+        assert_eq!(trades.len(), 1);
+        assert_eq!((trades[0].buy_order_id, trades[0].price.as_cents(), trades[0].quantity.value()), (parked_id, 10000, 60));
+        assert_eq!(book.parked_order_count(), 0);
+        assert_eq!(book.best_ask_quantity().unwrap().value(), 40);
+    }
+
+    #[test]
+    fn test_same_tick_activation_is_fifo() {
+        let mut book = LimitOrderBook::new("OPEN".to_string()).unwrap();
+        let open = open_time();
+        let first = create_test_order(OrderSide::Buy, 10000, 60).with_activation(open);
+        let second = create_test_order(OrderSide::Buy, 10000, 60).with_activation(open);
+        let later = create_test_order(OrderSide::Buy, 10000, 60).with_activation(open + Duration::minutes(1));
+        let (first_id, second_id) = (first.id, second.id);
+        book.add_order(first).unwrap();
+        book.add_order(later).unwrap();
+        book.add_order(second).unwrap();
+        book.add_order(create_test_order(OrderSide::Sell, 10000, 100)).unwrap();
+        assert!(book.best_bid().is_none());
+        
+        let trades = trades(&book.activate_pending(open));
+        let fills: Vec<(OrderId, u64)> = trades.iter().map(|trade| (trade.buy_order_id, trade.quantity.value())).collect();
+        assert_eq!(fills, vec![(first_id, 60), (second_id, 40)]);
+        assert_eq!(book.get_order(second_id).unwrap().status, OrderStatus::PartiallyFilled);
+        assert_eq!(book.parked_order_count(), 1);
+    }
+
+    #[test]
+    fn test_rejected_activation_is_reported() {
+        let mut book = LimitOrderBook::new("OPEN".to_string()).unwrap();
+        let open = open_time();
+        let rejected = create_test_order(OrderSide::Buy, 10000, 60).with_activation(open).with_post_only();
+        let accepted = create_test_order(OrderSide::Buy, 9900, 60).with_activation(open);
+        let (rejected_id, accepted_id) = (rejected.id, accepted.id);
+        book.add_order(rejected).unwrap();
+        book.add_order(accepted).unwrap();
+        book.add_order(create_test_order(OrderSide::Sell, 10000, 100)).unwrap();
+        
+        // The post-only bid now crosses the offer; the other order still activates
+        let activations = book.activate_pending(open);
+        assert!(matches!(
+            &activations[..],
+            [
+                Activation::Rejected { order, error: MatchingEngineError::PostOnlyWouldCross { .. } },
+                Activation::Executed(result),
+            ] if order.id == rejected_id && result.order_id == accepted_id
+        ));
+        assert!(book.get_order(rejected_id).is_none());
+        assert_eq!(book.best_bid().unwrap().as_cents(), 9900);
+    }
+
+    #[test]
+    fn test_parked_order_can_be_amended_and_cancelled() {
+        let mut book = LimitOrderBook::new("OPEN".to_string()).unwrap();
+        let open = open_time();
+        let parked = create_test_order(OrderSide::Buy, 10000, 60).with_activation(open);
+        let parked_id = parked.id;
+        book.add_order(parked).unwrap();
+        
+        let result = book.modify_order(parked_id, Some(Price::from_cents(9950).unwrap()), Some(Quantity::new(80).unwrap())).unwrap();
+        assert_eq!((result.previous.price.as_cents(), result.status), (10000, OrderStatus::PendingActivation));
+        book.reduce_quantity(parked_id, Quantity::new(50).unwrap()).unwrap();
+        let amended = book.get_order(parked_id).unwrap();
+        assert_eq!((amended.price.as_cents(), amended.remaining_quantity.value()), (9950, 50));
+        assert!(book.best_bid().is_none());
+        
+        assert_eq!(book.cancel_order(parked_id).unwrap().status, OrderStatus::Cancelled);
+        assert!(book.get_order(parked_id).is_none());
+        assert!(book.activate_pending(open).is_empty());
+        assert!(book.is_empty());
+    }
+}
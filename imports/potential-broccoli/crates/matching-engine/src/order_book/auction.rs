@@ -0,0 +1,367 @@
+//! Opening auction
+//!
+//! In the pre-open state orders are collected without matching, so the book
+//! may cross. `uncross` then executes every crossed order at a single price
+//! and opens the book. The price is the one that maximises matched volume;
+//! ties go to the smallest imbalance, then towards the side with the
+//! imbalance (the higher price for excess demand, the lower for excess
+//! supply), then to the price closest to the last trade, then to the lower
+//! price. `indicative_uncross` runs the same selection without executing, so
+//! consumers can follow the would-be price while orders accumulate.
+//!
+//! Crossed orders trade in price-time priority. Neither side of an auction
+//! trade took liquidity from the other; the order that arrived later is
+//! recorded as the taker. Self-trade prevention still applies, so it can
+//! leave the executed volume below the indicated one. The session volume
+//! limit polices continuous matching only.
+
+use super::{fees, levels::LevelOrders, quantity_scale, state::BookState, stp, LimitOrderBook, OrderBookOp, Trade};
+use crate::{events::OrderEvent, MatchingEngineError, OrderSide, Price, Quantity};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Outcome the auction would have if the book uncrossed now
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IndicativeAuction {
+    /// Single price every auction trade executes at
+    pub price: Price,
+    /// Quantity that would trade
+    pub volume: Quantity,
+    /// Side left with unmatched quantity at the auction price, if any
+    pub imbalance_side: Option<OrderSide>,
+    /// Quantity left unmatched on the imbalance side
+    pub imbalance_quantity: Quantity,
+}
+
+impl IndicativeAuction {
+    fn new(price: Price, demand: u64, supply: u64) -> Self {
+        let imbalance_side = match demand.cmp(&supply) {
+            std::cmp::Ordering::Greater => Some(OrderSide::Buy),
+            std::cmp::Ordering::Less => Some(OrderSide::Sell),
+            std::cmp::Ordering::Equal => None,
+        };
+        Self {
+            price,
+            volume: Quantity::new_allow_zero(demand.min(supply)),
+            imbalance_side,
+            imbalance_quantity: Quantity::new_allow_zero(demand.abs_diff(supply)),
+        }
+    }
+
+    /// Checks if this outcome wins the price selection over `other`, a lower price
+    fn beats(&self, other: &Self, reference: Option<Price>) -> bool {
+        if self.volume != other.volume {
+            return self.volume > other.volume;
+        }
+        if self.imbalance_quantity != other.imbalance_quantity {
+            return self.imbalance_quantity < other.imbalance_quantity;
+        }
+        match (self.imbalance_side, other.imbalance_side) {
+            (Some(OrderSide::Buy), Some(OrderSide::Buy)) => return true,
+            (Some(OrderSide::Sell), Some(OrderSide::Sell)) => return false,
+            _ => {},
+        }
+        reference.is_some_and(|reference| {
+            (self.price.value() - reference.value()).abs() < (other.price.value() - reference.value()).abs()
+        })
+    }
+}
+
+impl LimitOrderBook {
+    /// Starts collecting orders for an opening auction
+    ///
+    /// Orders rest without matching until [`uncross`](Self::uncross); market
+    /// and fill-or-kill orders, which cannot rest, are cancelled unfilled.
+    pub fn pre_open(&mut self) {
+        self.apply(OrderBookOp::SetState(BookState::PreOpen));
+    }
+
+    /// Gets the price and volume the auction would execute at now, without
+    /// changing anything
+    ///
+    /// `None` when the book is not crossed.
+    pub fn indicative_uncross(&self) -> Option<IndicativeAuction> {
+        self.equilibrium(self.now())
+    }
+
+    /// Runs the opening auction, then opens the book for continuous trading
+    ///
+    /// Returns the auction trades followed by those of any stop orders the
+    /// auction price triggered.
+    pub fn uncross(&mut self) -> crate::Result<Vec<Trade>> {
+        self.apply(OrderBookOp::Uncross).trades()
+    }
+    
+    pub(super) fn run_opening_auction(&mut self) -> crate::Result<Vec<Trade>> {
+        if self.state != BookState::PreOpen {
+            return Err(MatchingEngineError::NotInAuction(self.symbol.to_string()));
+        }
+        let now = self.now();
+        self.begin_mutation();
+        let mut events = Vec::new();
+        let mut trades = match self.equilibrium(now) {
+            Some(auction) => self.execute_auction(auction.price, now, &mut events)?,
+            None => Vec::new(),
+        };
+        self.state = BookState::Open;
+        self.settle_contingent_orders(&mut trades, &mut events)?;
+        self.end_mutation();
+
+        Ok(trades)
+    }
+
+    /// Selects the auction price among the limit prices of crossed levels
+    ///
+    /// Market orders add to the volume at every price without proposing one;
+    /// with only market orders crossed, they trade at the last trade price.
+    pub(super) fn equilibrium(&self, now: DateTime<Utc>) -> Option<IndicativeAuction> {
+        let (best_bid, best_ask) = (self.bids.best()?, self.asks.best()?);
+        if best_bid < best_ask {
+            return None;
+        }
+
+        // Tradable quantity of each level that crosses the opposite touch, and
+        // whether any of it is limit interest
+        let tradable = |(price, orders): (&Price, LevelOrders<'_>)| -> (Price, u64, bool) {
+            orders.iter()
+                .filter(|order| order.is_active() && !order.is_expired_at(now))
+                .fold((*price, 0, false), |(price, quantity, limit), order| {
+                    (price, quantity + order.remaining_quantity.value(), limit || !order.is_market())
+                })
+        };
+        let demand: Vec<_> = self.bids.iter().take_while(|(price, _)| **price >= best_ask).map(tradable).collect();
+        let supply: Vec<_> = self.asks.iter().take_while(|(price, _)| **price <= best_bid).map(tradable).collect();
+
+        let mut prices: Vec<Price> = demand.iter()
+            .chain(&supply)
+            .filter(|(_, _, limit)| *limit)
+            .map(|(price, _, _)| *price)
+            .collect();
+        if prices.is_empty() {
+            prices.extend(self.last_trade_price);
+        }
+        prices.sort();
+        prices.dedup();
+
+        // Walk the prices upwards: supply at or below the price only grows,
+        // demand at or above it only shrinks
+        let mut bought: u64 = demand.iter().map(|(_, quantity, _)| quantity).sum();
+        let mut sold = 0;
+        let mut bids = demand.iter().rev().peekable();
+        let mut asks = supply.iter().peekable();
+        let mut selected: Option<IndicativeAuction> = None;
+        for price in prices {
+            while let Some((_, quantity, _)) = bids.next_if(|(bid, _, _)| *bid < price) {
+                bought -= quantity;
+            }
+            while let Some((_, quantity, _)) = asks.next_if(|(ask, _, _)| *ask <= price) {
+                sold += quantity;
+            }
+            let candidate = IndicativeAuction::new(price, bought, sold);
+            if candidate.volume.value() == 0 {
+                continue;
+            }
+            if selected.is_none_or(|selected| candidate.beats(&selected, self.last_trade_price)) {
+                selected = Some(candidate);
+            }
+        }
+        selected
+    }
+
+    /// Trades crossed orders against each other at `price` in price-time priority
+    pub(super) fn execute_auction(
+        &mut self,
+        price: Price,
+        now: DateTime<Utc>,
+        events: &mut Vec<OrderEvent>,
+    ) -> crate::Result<Vec<Trade>> {
+        let mut trades = Vec::new();
+
+        while let (Some(bid), Some(ask)) = (self.bids.best(), self.asks.best()) {
+            if bid < price || ask > price {
+                break;
+            }
+            if self.purge_head(OrderSide::Buy, bid, now, events)? || self.purge_head(OrderSide::Sell, ask, now, events)? {
+                continue;
+            }
+            self.touch_level(OrderSide::Buy, bid);
+            self.touch_level(OrderSide::Sell, ask);
+
+            let (Some(buy), Some(sell)) = (
+                self.bids.front_mut(&bid),
+                self.asks.front_mut(&ask),
+            ) else {
+                break;
+            };
+            let (mut maker, mut taker) = if sell.created_at <= buy.created_at { (sell, buy) } else { (buy, sell) };
+
+            if let Some(stp) = self.self_trade_prevention {
+                if stp.scope.applies(&self.accounts, &taker.user_id, &maker.user_id) {
+                    stp::prevent_self_trade(stp.policy, &mut taker, &mut maker, events, now)?;
+                    drop((maker, taker));
+                    self.purge_head(OrderSide::Buy, bid, now, events)?;
+                    self.purge_head(OrderSide::Sell, ask, now, events)?;
+                    continue;
+                }
+            }
+
+            let quantity = taker.remaining_quantity.min(maker.remaining_quantity);
+            let (maker_user, taker_user, taker_side) = (maker.user_id.clone(), taker.user_id.clone(), taker.side);
+            let (maker_fee, taker_fee) = fees::trade_fees(
+                self.fee_schedule.as_ref(),
+                quantity_scale::notional(price, quantity.value(), self.quantity_scale),
+                (&maker_user, &taker_user),
+            );
+            let (buy_order, sell_order) = match taker_side {
+                OrderSide::Buy => (&*taker, &*maker),
+                OrderSide::Sell => (&*maker, &*taker),
+            };
+            let trade = Trade {
+                trade_id: self.next_trade_id,
+                buy_order_id: buy_order.id,
+                sell_order_id: sell_order.id,
+                maker_order_id: Some(maker.id),
+                taker_order_id: Some(taker.id),
+                aggressor_side: Some(taker_side),
+                buyer_user_id: Some(buy_order.user_id.clone()),
+                seller_user_id: Some(sell_order.user_id.clone()),
+                price,
+                quantity,
+                timestamp: now,
+                symbol: Some(self.symbol.clone()),
+                sequence: self.sequence,
+                same_firm: self.accounts.same_firm(&taker_user, &maker_user),
+                maker_fee,
+                taker_fee,
+                quantity_scale: self.quantity_scale,
+            };
+            self.next_trade_id += 1;
+            maker.fill_at(quantity, now)?;
+            taker.fill_at(quantity, now)?;
+            drop((maker, taker));
+
+            self.purge_head(OrderSide::Buy, bid, now, events)?;
+            self.purge_head(OrderSide::Sell, ask, now, events)?;
+            self.record_trade(&trade, (&taker_user, taker_side), &maker_user);
+            self.retrim_reduce_only([&taker_user, &maker_user], events)?;
+            trades.push(trade);
+        }
+
+        self.remember_trades(&trades);
+        Ok(trades)
+    }
+
+    /// Removes the order at the head of a level if it can no longer trade
+    ///
+    /// Returns whether an order was removed. Expired orders are reported.
+    fn purge_head(
+        &mut self,
+        side: OrderSide,
+        price: Price,
+        now: DateTime<Utc>,
+        events: &mut Vec<OrderEvent>,
+    ) -> crate::Result<bool> {
+        let levels = match side {
+            OrderSide::Buy => &mut self.bids,
+            OrderSide::Sell => &mut self.asks,
+        };
+        let Some(mut order) = levels.front_mut(&price) else {
+            return Ok(false);
+        };
+        if order.is_expired_at(now) {
+            order.expire_at(now);
+            let expired = order.clone();
+            drop(order);
+            self.remove_resting_order(expired.id, side, price)?;
+            events.push(OrderEvent::Expired { order: expired });
+            return Ok(true);
+        }
+        if !order.is_active() {
+            let order_id = order.id;
+            drop(order);
+            self.remove_resting_order(order_id, side, price)?;
+            return Ok(true);
+        }
+        Ok(false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    use crate::order_book::test_support::create_test_order;
+
+    fn pre_open_book(orders: &[(OrderSide, i64, u64)]) -> LimitOrderBook {
+        let mut book = LimitOrderBook::new("AUCT".to_string()).unwrap();
+        book.pre_open();
+        for &(side, price_cents, quantity) in orders {
+            assert!(book.add_order(create_test_order(side, price_cents, quantity)).unwrap().is_empty());
+        }
+        book
+    }
+
+    #[test]
+    fn test_indicative_uncross_predicts_uncross() {
+        let mut book = pre_open_book(&[
+            (OrderSide::Buy, 10200, 100),
+            (OrderSide::Buy, 10100, 200),
+            (OrderSide::Buy, 10000, 300),
+            (OrderSide::Sell, 9900, 150),
+            (OrderSide::Sell, 10000, 150),
+            (OrderSide::Sell, 10100, 200),
+        ]);
+        // Demand/supply at 100.00: 600/300, at 101.00: 300/500
+        let indicative = book.indicative_uncross().unwrap();
+        assert_eq!(indicative, IndicativeAuction {
+            price: Price::from_cents(10100).unwrap(),
+            volume: Quantity::new(300).unwrap(),
+            imbalance_side: Some(OrderSide::Sell),
+            imbalance_quantity: Quantity::new(200).unwrap(),
+        });
+        assert_eq!(book.state(), BookState::PreOpen);
+
+        let trades = book.uncross().unwrap();
+        assert!(trades.iter().all(|trade| trade.price == indicative.price));
+        assert_eq!(trades.iter().map(|trade| trade.quantity.value()).sum::<u64>(), indicative.volume.value());
+        assert_eq!(book.state(), BookState::Open);
+        assert!(book.indicative_uncross().is_none());
+        assert_eq!(book.best_ask().unwrap().as_cents(), 10100);
+        assert_eq!(book.best_ask_quantity().unwrap().value(), 200);
+        assert_eq!(book.best_bid().unwrap().as_cents(), 10000);
+    }
+
+    #[test]
+    fn test_imbalance_and_reference_break_ties() {
+        // Equal volume and imbalance at 100.00 and 101.00, excess demand: the higher price
+        let book = pre_open_book(&[
+            (OrderSide::Buy, 10100, 100),
+            (OrderSide::Buy, 10100, 50),
+            (OrderSide::Sell, 10000, 100),
+        ]);
+        let indicative = book.indicative_uncross().unwrap();
+        assert_eq!((indicative.price.as_cents(), indicative.imbalance_side), (10100, Some(OrderSide::Buy)));
+
+        // Balanced at both prices: the one closest to the last trade
+        let mut book = LimitOrderBook::new("AUCT".to_string()).unwrap();
+        book.add_order(create_test_order(OrderSide::Sell, 10100, 10)).unwrap();
+        book.add_order(create_test_order(OrderSide::Buy, 10100, 10)).unwrap();
+        book.pre_open();
+        book.add_order(create_test_order(OrderSide::Buy, 10100, 100)).unwrap();
+        book.add_order(create_test_order(OrderSide::Sell, 10000, 100)).unwrap();
+        let indicative = book.indicative_uncross().unwrap();
+        assert_eq!((indicative.price.as_cents(), indicative.imbalance_side), (10100, None));
+        assert_eq!(book.uncross().unwrap()[0].price, indicative.price);
+    }
+
+    #[test]
+    fn test_uncross_only_from_pre_open() {
+        let mut book = LimitOrderBook::new("AUCT".to_string()).unwrap();
+        assert_eq!(book.uncross(), Err(MatchingEngineError::NotInAuction("AUCT".to_string())));
+
+        book.pre_open();
+        assert!(book.indicative_uncross().is_none());
+        assert!(book.uncross().unwrap().is_empty());
+        assert_eq!(book.state(), BookState::Open);
+    }
+}
@@ -0,0 +1,107 @@
+//! Best bid and offer in a single call
+//!
+//! The touch on each side is found in one walk from the best price outward,
+//! counting orders exactly as [`market_depth`](LimitOrderBook::market_depth)
+//! does: inactive, expired, and hidden orders are skipped, and a level left
+//! with no displayed quantity is passed over. The result always agrees with
+//! the first level reported by a full-depth `market_depth` at the same
+//! sequence.
+
+use super::{LimitOrderBook, MarketLevel};
+use crate::{OrderSide, Quantity};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+/// Best bid and offer with their sizes, at one sequence number
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Bbo {
+    /// Best displayed bid level, if any
+    pub bid: Option<MarketLevel>,
+    /// Best displayed ask level, if any
+    pub ask: Option<MarketLevel>,
+    /// Ask minus bid, when both sides are quoted
+    pub spread: Option<Decimal>,
+    /// Halfway between bid and ask, when both sides are quoted
+    pub mid: Option<Decimal>,
+    /// Book sequence number the quote was taken at
+    pub sequence: u64,
+}
+
+impl LimitOrderBook {
+    /// Gets the best bid and offer, or `None` if neither side is quoted
+    pub fn bbo(&self) -> Option<Bbo> {
+        let bid = self.best_level(OrderSide::Buy);
+        let ask = self.best_level(OrderSide::Sell);
+        if bid.is_none() && ask.is_none() {
+            return None;
+        }
+        let (spread, mid) = match (&bid, &ask) {
+            (Some(bid), Some(ask)) => (
+                Some(ask.price.value() - bid.price.value()),
+                Some((bid.price.value() + ask.price.value()) / Decimal::TWO),
+            ),
+            _ => (None, None),
+        };
+        Some(Bbo { bid, ask, spread, mid, sequence: self.sequence })
+    }
+
+    /// First level on `side` with displayed quantity
+    fn best_level(&self, side: OrderSide) -> Option<MarketLevel> {
+        let now = self.now();
+        let levels = match side {
+            OrderSide::Buy => &self.bids,
+            OrderSide::Sell => &self.asks,
+        };
+        levels.iter().find_map(|(price, orders)| {
+            let (quantity, order_count) = orders.iter()
+                .filter(|order| order.is_active() && !order.hidden && !order.is_expired_at(now))
+                .fold((0, 0), |(quantity, count), order| (quantity + order.remaining_quantity.value(), count + 1));
+            (quantity > 0).then(|| MarketLevel {
+                price: *price,
+                quantity: Quantity::new_allow_zero(quantity),
+                order_count,
+                quantity_scale: self.quantity_scale,
+            })
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Price;
+    use crate::order_book::test_support::create_test_order;
+
+    #[test]
+    fn test_bbo_matches_individual_queries() {
+        let mut book = LimitOrderBook::new("BBO".to_string()).unwrap();
+        assert_eq!(book.bbo(), None);
+
+        book.add_order(create_test_order(OrderSide::Buy, 9900, 30)).unwrap();
+        let one_sided = book.bbo().unwrap();
+        assert_eq!((one_sided.ask, one_sided.spread, one_sided.mid), (None, None, None));
+
+        book.add_order(create_test_order(OrderSide::Buy, 9900, 20)).unwrap();
+        book.add_order(create_test_order(OrderSide::Sell, 10000, 40)).unwrap();
+        let bbo = book.bbo().unwrap();
+        let bid = bbo.bid.as_ref().unwrap();
+        assert_eq!((bid.price, bid.quantity, bid.order_count), (book.best_bid().unwrap(), book.best_bid_quantity().unwrap(), 2));
+        assert_eq!(bbo.ask.as_ref().unwrap().quantity, book.best_ask_quantity().unwrap());
+        assert_eq!((bbo.spread, bbo.mid), (book.spread(), book.mid_price()));
+        assert_eq!(bbo.sequence, book.sequence());
+    }
+
+    #[test]
+    fn test_inactive_touch_is_skipped_like_depth() {
+        let mut book = LimitOrderBook::new("BBO".to_string()).unwrap();
+        book.add_order(create_test_order(OrderSide::Sell, 10000, 40)).unwrap();
+        book.add_order(create_test_order(OrderSide::Sell, 10100, 10)).unwrap();
+        book.add_order(create_test_order(OrderSide::Sell, 10000, 5).with_hidden()).unwrap();
+        book.asks.front_mut(&Price::from_cents(10000).unwrap()).unwrap().cancel();
+
+        let depth = book.market_depth(usize::MAX);
+        let bbo = book.bbo().unwrap();
+        assert_eq!(bbo.ask.as_ref(), depth.asks.first());
+        assert_eq!(bbo.ask.unwrap().price.as_cents(), 10100);
+    }
+}
@@ -0,0 +1,273 @@
+//! Lock-free published best bid and offer
+//!
+//! A pricing loop that only needs the touch shouldn't wait on matching. With
+//! the publisher enabled, each mutation ends by working out the displayed
+//! touch on both sides from the level totals and, if it moved, storing it
+//! into a shared cell as one [`PublishedBbo`]. [`BboReader`]s load that cell
+//! without locking, so they never block the book and the book never blocks
+//! them.
+//!
+//! A reader sees the quote as of the last completed mutation, never part of
+//! one. The touch is the first level with displayed quantity, as in
+//! [`bbo`](LimitOrderBook::bbo), except that an order past its expiry counts
+//! until a mutation takes it out of the book. A crossed quote is never
+//! published: while a pre-open book is crossed, both sides are published
+//! empty. Like the listener, the publisher is runtime wiring: it is not
+//! serialized, and a cloned book starts without one.
+
+use super::{levels::PriceLevels, LimitOrderBook};
+use crate::{Price, Quantity};
+use arc_swap::ArcSwap;
+use std::sync::Arc;
+
+/// Best bid and offer as ticks and quantities, at one sequence number
+///
+/// A side with zero quantity is empty, and its ticks are zero.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct PublishedBbo {
+    /// Best displayed bid price in ticks of `10^-price_scale`
+    pub bid_ticks: i64,
+    /// Displayed quantity at the best bid
+    pub bid_quantity: u64,
+    /// Best displayed ask price in ticks of `10^-price_scale`
+    pub ask_ticks: i64,
+    /// Displayed quantity at the best ask
+    pub ask_quantity: u64,
+    /// Book sequence number of the mutation that set this quote
+    pub sequence: u64,
+    /// Decimal places the ticks count to
+    pub price_scale: u32,
+}
+
+impl PublishedBbo {
+    /// Gets the best bid price and quantity, if the bid side is quoted
+    pub fn bid(&self) -> Option<(Price, Quantity)> {
+        Self::side(self.bid_ticks, self.bid_quantity, self.price_scale)
+    }
+
+    /// Gets the best ask price and quantity, if the ask side is quoted
+    pub fn ask(&self) -> Option<(Price, Quantity)> {
+        Self::side(self.ask_ticks, self.ask_quantity, self.price_scale)
+    }
+
+    fn side(ticks: i64, quantity: u64, scale: u32) -> Option<(Price, Quantity)> {
+        if quantity == 0 {
+            return None;
+        }
+        Some((Price::from_ticks(ticks, scale).ok()?, Quantity::new_allow_zero(quantity)))
+    }
+
+    /// Whether the bid and ask are the same, whatever their sequence numbers
+    fn same_quote(&self, other: &Self) -> bool {
+        Self { sequence: other.sequence, ..*self } == *other
+    }
+}
+
+/// Cloneable handle for loading the book's latest published quote
+#[derive(Debug, Clone)]
+pub struct BboReader {
+    cell: Arc<ArcSwap<PublishedBbo>>,
+}
+
+impl BboReader {
+    /// Loads the latest published quote without locking
+    pub fn load(&self) -> PublishedBbo {
+        **self.cell.load()
+    }
+}
+
+/// The book's side of the published quote: the cell and what was last stored in it
+#[derive(Debug, Default)]
+pub(super) struct BboPublisher {
+    cell: Option<Arc<ArcSwap<PublishedBbo>>>,
+    last: PublishedBbo,
+}
+
+impl Clone for BboPublisher {
+    fn clone(&self) -> Self {
+        Self::default()
+    }
+}
+
+impl BboPublisher {
+    /// Stores a quote unless it matches the one last stored
+    fn publish(&mut self, quote: PublishedBbo) {
+        if let Some(cell) = &self.cell {
+            if !quote.same_quote(&self.last) {
+                cell.store(Arc::new(quote));
+                self.last = quote;
+            }
+        }
+    }
+}
+
+impl LimitOrderBook {
+    /// Starts publishing the best bid and offer after every mutation, returning a reader
+    ///
+    /// The current quote is published straight away. If the publisher is
+    /// already running, returns another reader of it.
+    pub fn enable_bbo_publisher(&mut self) -> BboReader {
+        if let Some(cell) = &self.bbo_publisher.cell {
+            return BboReader { cell: Arc::clone(cell) };
+        }
+        let quote = self.displayed_quote();
+        let cell = Arc::new(ArcSwap::from_pointee(quote));
+        self.bbo_publisher = BboPublisher { cell: Some(Arc::clone(&cell)), last: quote };
+        BboReader { cell }
+    }
+
+    /// Gets a reader of the published quote, if the publisher is running
+    pub fn bbo_reader(&self) -> Option<BboReader> {
+        self.bbo_publisher.cell.as_ref().map(|cell| BboReader { cell: Arc::clone(cell) })
+    }
+
+    /// Publishes the touch if it moved during the mutation just finished
+    pub(super) fn publish_bbo(&mut self) {
+        if self.bbo_publisher.cell.is_some() {
+            let quote = self.displayed_quote();
+            self.bbo_publisher.publish(quote);
+        }
+    }
+
+    /// Displayed touch on both sides, emptied if it is crossed
+    fn displayed_quote(&self) -> PublishedBbo {
+        let scale = self.price_scale;
+        let empty = PublishedBbo { sequence: self.sequence, price_scale: scale, ..Default::default() };
+        let quote = (displayed_touch(&self.bids, scale), displayed_touch(&self.asks, scale));
+        // A pre-open book can rest market orders at their side's bound, which has no ticks
+        let (Some((bid_ticks, bid_quantity)), Some((ask_ticks, ask_quantity))) = quote else {
+            return empty;
+        };
+        if bid_quantity > 0 && ask_quantity > 0 && bid_ticks >= ask_ticks {
+            return empty;
+        }
+        PublishedBbo { bid_ticks, bid_quantity, ask_ticks, ask_quantity, ..empty }
+    }
+}
+
+/// Ticks and displayed quantity of the first level on a side with displayed
+/// quantity, `(0, 0)` if there is none, or `None` if that level has no ticks
+fn displayed_touch(levels: &PriceLevels, scale: u32) -> Option<(i64, u64)> {
+    let touch = levels.iter().find_map(|(price, orders)| {
+        let quantity = orders.totals().displayed_quantity;
+        (quantity > 0).then_some((*price, quantity))
+    });
+    match touch {
+        Some((price, quantity)) => Some((price.to_ticks(scale)?, quantity)),
+        None => Some((0, 0)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::OrderSide;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::thread;
+    use crate::order_book::test_support::create_test_order;
+
+    type Quote = (Option<(Price, Quantity)>, Option<(Price, Quantity)>);
+
+    /// The published sides as `bbo` reports them
+    fn quoted(book: &LimitOrderBook) -> Quote {
+        let bbo = book.bbo();
+        let side = |level: Option<&crate::order_book::MarketLevel>| level.map(|level| (level.price, level.quantity));
+        (side(bbo.as_ref().and_then(|bbo| bbo.bid.as_ref())), side(bbo.as_ref().and_then(|bbo| bbo.ask.as_ref())))
+    }
+
+    #[test]
+    fn test_published_quote_follows_the_touch() {
+        let mut book = LimitOrderBook::new("PUB".to_string()).unwrap();
+        book.add_order(create_test_order(OrderSide::Buy, 9900, 30)).unwrap();
+        let reader = book.enable_bbo_publisher();
+        assert_eq!(reader.load().bid(), Some((Price::from_cents(9900).unwrap(), Quantity::new(30).unwrap())));
+        assert_eq!(reader.load().ask(), None);
+
+        let ask = create_test_order(OrderSide::Sell, 10000, 40);
+        let ask_id = ask.id;
+        book.add_order(ask).unwrap();
+        book.add_order(create_test_order(OrderSide::Sell, 10100, 10).with_hidden()).unwrap();
+        book.add_order(create_test_order(OrderSide::Buy, 9950, 5).with_hidden()).unwrap();
+        let published = reader.load();
+        assert_eq!((published.bid(), published.ask()), quoted(&book));
+        assert_eq!((published.bid_ticks, published.ask_ticks, published.price_scale), (9900, 10000, 2));
+
+        // A mutation away from the touch leaves the quote and its sequence alone
+        book.add_order(create_test_order(OrderSide::Buy, 9800, 10)).unwrap();
+        assert_eq!(reader.load(), published);
+
+        book.add_order(create_test_order(OrderSide::Buy, 10000, 15)).unwrap();
+        assert_eq!(reader.load().ask_quantity, 25);
+        assert_eq!(reader.load().sequence, book.sequence());
+        book.cancel_order(ask_id).unwrap();
+        assert_eq!((reader.load().bid(), reader.load().ask()), quoted(&book));
+        assert_eq!(reader.load().ask(), None);
+
+        // Readers share one cell; a cloned book starts without the publisher
+        assert_eq!(book.enable_bbo_publisher().load(), book.bbo_reader().unwrap().load());
+        assert!(book.clone().bbo_reader().is_none());
+    }
+
+    #[test]
+    fn test_crossed_pre_open_book_publishes_empty_quote() {
+        let mut book = LimitOrderBook::new("PUB".to_string()).unwrap();
+        let reader = book.enable_bbo_publisher();
+        book.pre_open();
+        book.add_order(create_test_order(OrderSide::Buy, 10100, 10)).unwrap();
+        assert_eq!(reader.load().bid_quantity, 10);
+        book.add_order(create_test_order(OrderSide::Sell, 10000, 10)).unwrap();
+        assert!(book.is_crossed());
+        let published = reader.load();
+        assert_eq!((published.bid(), published.ask()), (None, None));
+
+        book.uncross().unwrap();
+        assert_eq!((reader.load().bid(), reader.load().ask()), quoted(&book));
+    }
+
+    #[test]
+    fn test_readers_never_see_crossed_or_backward_quotes() {
+        let mut book = LimitOrderBook::new("PUB".to_string()).unwrap();
+        let reader = book.enable_bbo_publisher();
+        let done = Arc::new(AtomicBool::new(false));
+        let readers: Vec<_> = (0..4).map(|_| {
+            let (reader, done) = (reader.clone(), Arc::clone(&done));
+            thread::spawn(move || {
+                let mut last_sequence = 0;
+                let mut reads = 0;
+                while !done.load(Ordering::Acquire) || reads < 100 {
+                    let quote = reader.load();
+                    assert!(quote.sequence >= last_sequence, "sequence went backwards");
+                    last_sequence = quote.sequence;
+                    if let (Some((bid, _)), Some((ask, _))) = (quote.bid(), quote.ask()) {
+                        assert!(bid < ask, "crossed quote at sequence {}", quote.sequence);
+                    }
+                    reads += 1;
+                }
+                reads
+            })
+        }).collect();
+
+        // Quotes walk up and down and regularly trade through each other
+        let writer = thread::spawn(move || {
+            for i in 0..20_000i64 {
+                let drift = (i / 50) % 20;
+                let side = if i % 2 == 0 { OrderSide::Buy } else { OrderSide::Sell };
+                let order = create_test_order(side, 10000 + drift + (i * 7) % 11 - 5, 1 + (i % 13) as u64);
+                let order_id = order.id;
+                book.add_order(order).unwrap();
+                if i % 3 == 0 {
+                    let _ = book.cancel_order(order_id);
+                }
+            }
+            book
+        });
+        let book = writer.join().unwrap();
+        done.store(true, Ordering::Release);
+
+        for reader in readers {
+            assert!(reader.join().unwrap() >= 100);
+        }
+        let published = reader.load();
+        assert_eq!((published.bid(), published.ask()), quoted(&book));
+    }
+}
@@ -0,0 +1,411 @@
+//! Copy-on-write snapshots for readers off the matching thread
+//!
+//! A [`BookSnapshot`] is a frozen view of the resting orders at one sequence
+//! number. Each price level in it is an `Arc` shared with every other
+//! snapshot that saw the level unchanged, so taking a snapshot copies only
+//! the orders of levels touched since the previous one. The book keeps the
+//! last snapshot's levels and the set of levels mutated since; taking the
+//! next snapshot re-copies those levels and reuses the rest.
+//!
+//! Each side's level map is itself behind an `Arc`. While an older snapshot
+//! still holds the map, the first change clones it, which copies one
+//! pointer per level but none of the orders. The very first snapshot, and
+//! the first after the book is cloned or restored, copies every level.
+//!
+//! A snapshot owns its data, so it can be sent to another thread and
+//! queried or serialized there while the book keeps trading. Orders are
+//! judged expired as of the time the snapshot was taken.
+//!
+//! The snapshot scheduler captures a whole book the same way: a snapshot
+//! of the levels plus a clone of everything else, which is put back
+//! together into a book on the scheduler's thread.
+
+use super::{Bbo, BookStats, LimitOrderBook, MarketDepth, MarketLevel};
+#[cfg(feature = "snapshot-scheduler")]
+use super::levels::PriceLevels;
+use crate::{types::Symbol, Order, OrderSide, Price, Quantity};
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use serde::{Serialize, Serializer};
+use std::collections::{BTreeMap, HashSet};
+use std::sync::{Arc, Mutex};
+
+/// The orders of one price level, in queue order
+#[derive(Debug, Serialize)]
+struct SnapshotLevel {
+    price: Price,
+    orders: Vec<Order>,
+}
+
+impl SnapshotLevel {
+    /// Displayed quantity and order count, skipping orders expired at `now`
+    fn displayed(&self, now: DateTime<Utc>, quantity_scale: u32) -> MarketLevel {
+        let (quantity, order_count) = self.orders.iter()
+            .filter(|order| order.is_active() && !order.hidden && !order.is_expired_at(now))
+            .fold((0, 0), |(quantity, count), order| (quantity + order.remaining_quantity.value(), count + 1));
+        MarketLevel { price: self.price, quantity: Quantity::new_allow_zero(quantity), order_count, quantity_scale }
+    }
+}
+
+type SideLevels = Arc<BTreeMap<Price, Arc<SnapshotLevel>>>;
+
+#[derive(Debug, Default)]
+struct SnapshotState {
+    /// Whether the levels below mirror the book as of the last snapshot
+    built: bool,
+    bids: SideLevels,
+    asks: SideLevels,
+    /// Levels mutated since the last snapshot
+    dirty: HashSet<(OrderSide, Price)>,
+}
+
+/// Levels of the last snapshot, shared by `&self` snapshots
+///
+/// Cloning yields an empty cache, so clones and deserialized books copy
+/// every level on their first snapshot.
+#[derive(Debug, Default)]
+pub(super) struct SnapshotCache {
+    state: Mutex<SnapshotState>,
+}
+
+impl Clone for SnapshotCache {
+    fn clone(&self) -> Self {
+        Self::default()
+    }
+}
+
+impl SnapshotCache {
+    /// Records a mutation at a price level, to be re-copied by the next snapshot
+    pub(super) fn touch(&mut self, side: OrderSide, price: Price) {
+        let state = self.state.get_mut().unwrap_or_else(|e| e.into_inner());
+        if state.built {
+            state.dirty.insert((side, price));
+        }
+    }
+}
+
+/// Immutable view of a book's resting orders at one sequence number
+#[derive(Debug, Clone)]
+pub struct BookSnapshot {
+    symbol: Symbol,
+    sequence: u64,
+    taken_at: DateTime<Utc>,
+    stats: BookStats,
+    quantity_scale: u32,
+    bids: SideLevels,
+    asks: SideLevels,
+}
+
+impl BookSnapshot {
+    /// Gets the trading symbol of the book the snapshot was taken from
+    pub fn symbol(&self) -> &Symbol {
+        &self.symbol
+    }
+
+    /// Gets the book sequence number the snapshot was taken at
+    pub fn sequence(&self) -> u64 {
+        self.sequence
+    }
+
+    /// Gets the time the snapshot was taken, which expiry is judged against
+    pub fn taken_at(&self) -> DateTime<Utc> {
+        self.taken_at
+    }
+
+    /// Gets the book's trade statistics as of the snapshot
+    pub fn stats(&self) -> &BookStats {
+        &self.stats
+    }
+
+    /// Gets the number of decimal places the book counts quantities to
+    pub fn quantity_scale(&self) -> u32 {
+        self.quantity_scale
+    }
+
+    /// Iterates the levels of a side in priority order, each with its queued orders
+    pub fn levels(&self, side: OrderSide) -> Box<dyn Iterator<Item = (Price, &[Order])> + '_> {
+        let levels = self.side(side).values().map(|level| (level.price, level.orders.as_slice()));
+        match side {
+            OrderSide::Buy => Box::new(levels.rev()),
+            OrderSide::Sell => Box::new(levels),
+        }
+    }
+
+    /// Gets the top `levels` displayed levels per side, as `LimitOrderBook::market_depth` reported them
+    pub fn market_depth(&self, levels: usize) -> MarketDepth {
+        let side_levels = |side| self.displayed_levels(side).take(levels).collect();
+        MarketDepth {
+            bids: side_levels(OrderSide::Buy),
+            asks: side_levels(OrderSide::Sell),
+            spread: self.spread(),
+            sequence: self.sequence,
+        }
+    }
+
+    /// Gets the best bid and offer, or `None` if neither side is quoted
+    pub fn bbo(&self) -> Option<Bbo> {
+        let bid = self.displayed_levels(OrderSide::Buy).next();
+        let ask = self.displayed_levels(OrderSide::Sell).next();
+        if bid.is_none() && ask.is_none() {
+            return None;
+        }
+        let (spread, mid) = match (&bid, &ask) {
+            (Some(bid), Some(ask)) => (
+                Some(ask.price.value() - bid.price.value()),
+                Some((bid.price.value() + ask.price.value()) / Decimal::TWO),
+            ),
+            _ => (None, None),
+        };
+        Some(Bbo { bid, ask, spread, mid, sequence: self.sequence })
+    }
+
+    /// Computes the checksum of the top `levels` displayed levels per side
+    ///
+    /// Identical to [`MarketDepth::checksum`] over `market_depth(levels)`.
+    pub fn checksum(&self, levels: usize) -> u32 {
+        self.market_depth(levels).checksum(levels)
+    }
+
+    fn spread(&self) -> Option<Decimal> {
+        let bid = self.displayed_levels(OrderSide::Buy).next()?;
+        let ask = self.displayed_levels(OrderSide::Sell).next()?;
+        Some(ask.price.value() - bid.price.value())
+    }
+
+    fn side(&self, side: OrderSide) -> &BTreeMap<Price, Arc<SnapshotLevel>> {
+        match side {
+            OrderSide::Buy => &self.bids,
+            OrderSide::Sell => &self.asks,
+        }
+    }
+
+    /// Levels of a side with displayed quantity, in priority order
+    fn displayed_levels(&self, side: OrderSide) -> impl Iterator<Item = MarketLevel> + '_ {
+        let levels = self.side(side).values();
+        let levels: Box<dyn Iterator<Item = &Arc<SnapshotLevel>>> = match side {
+            OrderSide::Buy => Box::new(levels.rev()),
+            OrderSide::Sell => Box::new(levels),
+        };
+        levels
+            .map(|level| level.displayed(self.taken_at, self.quantity_scale))
+            .filter(|level| level.quantity.value() > 0)
+    }
+}
+
+impl Serialize for BookSnapshot {
+    /// Writes the symbol, sequence, time taken, statistics, and each side's levels in priority order
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        #[derive(Serialize)]
+        struct Serialized<'a> {
+            symbol: &'a Symbol,
+            sequence: u64,
+            taken_at: DateTime<Utc>,
+            stats: &'a BookStats,
+            quantity_scale: u32,
+            bids: Vec<&'a SnapshotLevel>,
+            asks: Vec<&'a SnapshotLevel>,
+        }
+        Serialized {
+            symbol: &self.symbol,
+            sequence: self.sequence,
+            taken_at: self.taken_at,
+            stats: &self.stats,
+            quantity_scale: self.quantity_scale,
+            bids: self.bids.values().rev().map(Arc::as_ref).collect(),
+            asks: self.asks.values().map(Arc::as_ref).collect(),
+        }.serialize(serializer)
+    }
+}
+
+/// A whole book as of one op sequence, captured without copying unchanged levels
+#[cfg(feature = "snapshot-scheduler")]
+#[derive(Debug)]
+pub(crate) struct CapturedBook {
+    levels: BookSnapshot,
+    /// The book with its levels, order lookup, op log, and delta feed left out
+    rest: LimitOrderBook,
+}
+
+#[cfg(feature = "snapshot-scheduler")]
+impl CapturedBook {
+    /// Gets the op sequence of the book when it was captured
+    pub(crate) fn op_sequence(&self) -> u64 {
+        self.rest.op_sequence
+    }
+
+    /// Puts the levels back into the book, rebuilding and validating its indices
+    pub(crate) fn into_book(self) -> crate::Result<LimitOrderBook> {
+        let CapturedBook { levels, mut rest } = self;
+        let queues = |side: &SideLevels| -> Vec<_> {
+            side.values().map(|level| (level.price, level.orders.iter().cloned().collect())).collect()
+        };
+        rest.bids = PriceLevels::from_queues(OrderSide::Buy, rest.price_scale, queues(&levels.bids))?;
+        rest.asks = PriceLevels::from_queues(OrderSide::Sell, rest.price_scale, queues(&levels.asks))?;
+        rest.rebuild_indices()?;
+        Ok(rest)
+    }
+}
+
+impl LimitOrderBook {
+    /// Captures the whole book for the snapshot scheduler
+    ///
+    /// The levels are taken as by [`snapshot`](Self::snapshot); the rest of
+    /// the book is cloned with the levels swapped out for the duration.
+    #[cfg(feature = "snapshot-scheduler")]
+    pub(crate) fn capture(&mut self) -> CapturedBook {
+        let levels = self.snapshot();
+        let bids = std::mem::replace(&mut self.bids, PriceLevels::new(OrderSide::Buy, self.price_scale));
+        let asks = std::mem::replace(&mut self.asks, PriceLevels::new(OrderSide::Sell, self.price_scale));
+        let orders = std::mem::take(&mut self.orders);
+        let op_log = self.op_log.take();
+        let delta_feed = self.delta_feed.take();
+        let rest = self.clone();
+        (self.bids, self.asks, self.orders, self.op_log, self.delta_feed) = (bids, asks, orders, op_log, delta_feed);
+        CapturedBook { levels, rest }
+    }
+
+    /// Takes an immutable snapshot of the resting orders, copying only levels changed since the last one
+    ///
+    /// Later mutations of the book never show through the snapshot. See the
+    /// module documentation for what is copied.
+    pub fn snapshot(&self) -> BookSnapshot {
+        let mut state = self.snapshot_cache.state.lock().unwrap_or_else(|e| e.into_inner());
+        if state.built {
+            let dirty = std::mem::take(&mut state.dirty);
+            for (side, price) in dirty {
+                let (levels, cached) = match side {
+                    OrderSide::Buy => (&self.bids, &mut state.bids),
+                    OrderSide::Sell => (&self.asks, &mut state.asks),
+                };
+                let cached = Arc::make_mut(cached);
+                match levels.get(&price).filter(|orders| !orders.is_empty()) {
+                    Some(orders) => {
+                        let orders = orders.iter().cloned().collect();
+                        cached.insert(price, Arc::new(SnapshotLevel { price, orders }));
+                    },
+                    None => {
+                        cached.remove(&price);
+                    },
+                }
+            }
+        } else {
+            let copy = |levels: &super::levels::PriceLevels| -> SideLevels {
+                Arc::new(levels.iter()
+                    .map(|(&price, orders)| (price, Arc::new(SnapshotLevel { price, orders: orders.iter().cloned().collect() })))
+                    .collect())
+            };
+            state.bids = copy(&self.bids);
+            state.asks = copy(&self.asks);
+            state.built = true;
+        }
+        BookSnapshot {
+            symbol: self.symbol.clone(),
+            sequence: self.sequence,
+            taken_at: self.now(),
+            stats: self.stats,
+            quantity_scale: self.quantity_scale,
+            bids: state.bids.clone(),
+            asks: state.asks.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Clock;
+    use chrono::Duration;
+    use crate::order_book::test_support::create_test_order;
+
+    fn deep_book() -> LimitOrderBook {
+        let mut book = LimitOrderBook::new("SNAP".to_string()).unwrap();
+        for i in 0..20 {
+            book.add_order(create_test_order(OrderSide::Buy, 9900 - i, 10 + i as u64)).unwrap();
+            book.add_order(create_test_order(OrderSide::Sell, 10000 + i, 10 + i as u64)).unwrap();
+        }
+        book
+    }
+
+    fn level(snapshot: &BookSnapshot, side: OrderSide, price_cents: i64) -> &Arc<SnapshotLevel> {
+        &snapshot.side(side)[&Price::from_cents(price_cents).unwrap()]
+    }
+
+    #[test]
+    fn test_snapshot_matches_book() {
+        let book = deep_book();
+        let snapshot = book.snapshot();
+        assert_eq!(snapshot.sequence(), book.sequence());
+        assert_eq!(snapshot.market_depth(100), book.market_depth(100));
+        assert_eq!(snapshot.market_depth(5), book.market_depth(5));
+        assert_eq!(snapshot.bbo(), book.bbo());
+        assert_eq!(snapshot.checksum(10), book.checksum(10));
+        assert_eq!(snapshot.stats(), book.stats());
+        assert_eq!(snapshot.levels(OrderSide::Buy).next().unwrap().0, book.best_bid().unwrap());
+        assert_eq!(snapshot.levels(OrderSide::Sell).count(), 20);
+        assert!(LimitOrderBook::new("SNAP".to_string()).unwrap().snapshot().bbo().is_none());
+    }
+
+    #[test]
+    fn test_snapshot_mid_sequence_is_immutable() {
+        let mut book = deep_book();
+        let top_bid = create_test_order(OrderSide::Buy, 9950, 5);
+        let top_bid_id = top_bid.id;
+        book.add_order(top_bid).unwrap();
+
+        let snapshot = book.snapshot();
+        let depth = snapshot.market_depth(100);
+        let json = serde_json::to_string(&snapshot).unwrap();
+
+        // Trade through two ask levels, cancel the new bid, and add fresh levels on both sides
+        book.add_order(create_test_order(OrderSide::Buy, 10001, 15)).unwrap();
+        book.cancel_order(top_bid_id).unwrap();
+        book.add_order(create_test_order(OrderSide::Buy, 9800, 7)).unwrap();
+        book.add_order(create_test_order(OrderSide::Sell, 10050, 7)).unwrap();
+        let later = book.snapshot();
+
+        assert_eq!(snapshot.market_depth(100), depth);
+        assert_eq!(serde_json::to_string(&snapshot).unwrap(), json);
+        assert_eq!(snapshot.bbo().unwrap().bid.unwrap().price.as_cents(), 9950);
+        assert_ne!(later.market_depth(100), depth);
+        assert_eq!(later.market_depth(100), book.market_depth(100));
+        assert_eq!(later.sequence(), book.sequence());
+    }
+
+    #[test]
+    fn test_snapshots_share_untouched_levels() {
+        let mut book = deep_book();
+        let first = book.snapshot();
+        book.add_order(create_test_order(OrderSide::Sell, 10005, 3)).unwrap();
+        let second = book.snapshot();
+
+        assert!(Arc::ptr_eq(level(&first, OrderSide::Buy, 9900), level(&second, OrderSide::Buy, 9900)));
+        assert!(Arc::ptr_eq(level(&first, OrderSide::Sell, 10004), level(&second, OrderSide::Sell, 10004)));
+        assert!(!Arc::ptr_eq(level(&first, OrderSide::Sell, 10005), level(&second, OrderSide::Sell, 10005)));
+        assert_eq!(level(&second, OrderSide::Sell, 10005).orders.len(), 2);
+
+        // Nothing changed: the same maps are handed out again
+        let third = book.snapshot();
+        assert!(Arc::ptr_eq(&second.bids, &third.bids) && Arc::ptr_eq(&second.asks, &third.asks));
+
+        // A clone starts over and copies every level
+        let cloned = book.clone().snapshot();
+        assert!(!Arc::ptr_eq(level(&third, OrderSide::Buy, 9900), level(&cloned, OrderSide::Buy, 9900)));
+        assert_eq!(cloned.market_depth(100), third.market_depth(100));
+    }
+
+    #[test]
+    fn test_snapshot_judges_expiry_when_taken() {
+        let start = Utc::now();
+        let mut book = LimitOrderBook::new("SNAP".to_string()).unwrap();
+        book.set_clock(Clock::Fixed(start));
+        book.add_order(create_test_order(OrderSide::Sell, 10000, 10).with_expiry(start + Duration::seconds(5))).unwrap();
+        book.add_order(create_test_order(OrderSide::Sell, 10001, 10)).unwrap();
+        let before = book.snapshot();
+
+        book.set_clock(Clock::Fixed(start + Duration::seconds(10)));
+        let after = book.snapshot();
+        assert_eq!(before.bbo().unwrap().ask.unwrap().price.as_cents(), 10000);
+        assert_eq!(after.bbo().unwrap().ask.unwrap().price.as_cents(), 10001);
+        assert_eq!(after.market_depth(10), book.market_depth(10));
+    }
+}
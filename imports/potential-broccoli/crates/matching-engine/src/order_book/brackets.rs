@@ -0,0 +1,401 @@
+//! Bracket orders: an entry with take-profit and stop-loss exits
+//!
+//! A bracket submits its entry straight away and holds back both exits until
+//! the entry fills. Each entry fill grows the exits by the filled quantity, so
+//! they always cover the position the bracket has opened. The exits are
+//! linked one-cancels-other: an exit fill reduces the other exit by the same
+//! quantity, cancelling it once nothing is left to close, and cancels what
+//! remains of the entry. Exits are good-till-cancelled and skip pre-trade
+//! checks, since the entry has already passed them.
+//!
+//! Exits trade during the call that filled the entry or triggered them, and
+//! each state change is reported as `OrderEvent::BracketUpdated`. A bracket
+//! closes once none of its orders remain in the book, so cancelling the
+//! entry before it fills cancels the whole bracket.
+
+use super::{slab::OrderLocation, LimitOrderBook, OrderBookOp, Trade};
+use crate::{
+    events::{CancelReason, ExecutionResult, OrderEvent},
+    types::{OrderId, UserId},
+    MatchingEngineError, Order, OrderSide, Price, Quantity,
+};
+use serde::{Deserialize, Serialize};
+
+/// Lifecycle state of a bracket
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BracketState {
+    /// The entry is working and has not filled, so the exits are held back
+    Pending,
+    /// The entry has filled at least partly and the exits are live
+    Active,
+    /// No order of the bracket remains
+    Closed,
+}
+
+/// Entry order with take-profit and stop-loss exits
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Bracket {
+    pub entry_order_id: OrderId,
+    pub take_profit_id: OrderId,
+    pub stop_loss_id: OrderId,
+    /// Limit price of the take-profit exit
+    pub take_profit: Price,
+    /// Trigger price of the stop-loss exit
+    pub stop_loss: Price,
+    pub state: BracketState,
+    /// Quantity the entry has filled
+    pub entry_filled: u64,
+    /// Quantity the exits have filled
+    pub exit_filled: u64,
+    user_id: UserId,
+    exit_side: OrderSide,
+}
+
+impl Bracket {
+    /// Gets the filled entry quantity not yet closed by an exit
+    pub fn open_quantity(&self) -> u64 {
+        self.entry_filled.saturating_sub(self.exit_filled)
+    }
+    
+    fn event(&self) -> OrderEvent {
+        OrderEvent::BracketUpdated {
+            entry_order_id: self.entry_order_id,
+            take_profit_id: self.take_profit_id,
+            stop_loss_id: self.stop_loss_id,
+            state: self.state,
+            open_quantity: Quantity::new_allow_zero(self.open_quantity()),
+        }
+    }
+    
+    fn exit(&self, order_id: OrderId, quantity: u64) -> Order {
+        let quantity = Quantity::new_allow_zero(quantity);
+        if order_id == self.take_profit_id {
+            Order::new(order_id, self.user_id.clone(), self.exit_side, self.take_profit, quantity)
+        } else {
+            Order::stop(order_id, self.user_id.clone(), self.exit_side, self.stop_loss, quantity)
+        }
+    }
+    
+    fn sibling(&self, exit_id: OrderId) -> OrderId {
+        if exit_id == self.take_profit_id {
+            self.stop_loss_id
+        } else {
+            self.take_profit_id
+        }
+    }
+}
+
+impl LimitOrderBook {
+    /// Submits an entry order with take-profit and stop-loss exits
+    ///
+    /// The exits take the opposite side to the entry: a buy entry sells at
+    /// `take_profit` or below `stop_loss`, so `stop_loss` must be below
+    /// `take_profit` (and above it for a sell entry). Exit order IDs are
+    /// reported in the bracket's events.
+    pub fn submit_bracket(&mut self, entry: Order, take_profit: Price, stop_loss: Price) -> crate::Result<ExecutionResult> {
+        let (take_profit_id, stop_loss_id) = (OrderId::new(), OrderId::new());
+        self.apply(OrderBookOp::SubmitBracket { entry, take_profit, stop_loss, take_profit_id, stop_loss_id }).executed()
+    }
+    
+    /// Submits a bracket whose exits take the given IDs
+    pub(super) fn place_bracket(
+        &mut self,
+        entry: Order,
+        take_profit: Price,
+        stop_loss: Price,
+        take_profit_id: OrderId,
+        stop_loss_id: OrderId,
+    ) -> crate::Result<ExecutionResult> {
+        let ordered = match entry.side {
+            OrderSide::Buy => stop_loss < take_profit,
+            OrderSide::Sell => stop_loss > take_profit,
+        };
+        if !ordered {
+            return Err(MatchingEngineError::InvalidPrice(format!(
+                "stop-loss {} is not on the losing side of take-profit {} for a {} entry",
+                stop_loss, take_profit, entry.side
+            )));
+        }
+        self.check_on_scale(take_profit)?;
+        
+        let bracket = Bracket {
+            entry_order_id: entry.id,
+            take_profit_id,
+            stop_loss_id,
+            take_profit,
+            stop_loss,
+            state: BracketState::Pending,
+            entry_filled: 0,
+            exit_filled: 0,
+            user_id: entry.user_id.clone(),
+            exit_side: entry.side.opposite(),
+        };
+        let entry_order_id = entry.id;
+        let placed = bracket.event();
+        self.brackets.push(bracket);
+        
+        match self.submit(entry) {
+            Ok(mut result) => {
+                result.events.insert(0, placed);
+                Ok(result)
+            },
+            Err(error) => {
+                self.brackets.retain(|bracket| bracket.entry_order_id != entry_order_id);
+                Err(error)
+            },
+        }
+    }
+    
+    /// Gets an open bracket by its entry order ID
+    pub fn bracket(&self, entry_order_id: OrderId) -> Option<&Bracket> {
+        self.brackets.iter().find(|bracket| bracket.entry_order_id == entry_order_id)
+    }
+    
+    /// Gets the number of open brackets
+    pub fn bracket_count(&self) -> usize {
+        self.brackets.len()
+    }
+    
+    /// Triggers stops and applies bracket contingencies until both settle
+    ///
+    /// Orders released along the way append their trades to `trades`.
+    pub(super) fn settle_contingent_orders(&mut self, trades: &mut Vec<Trade>, events: &mut Vec<OrderEvent>) -> crate::Result<()> {
+        if self.brackets.is_empty() {
+            return self.trigger_stops(trades, events);
+        }
+        
+        let mut settled = 0;
+        loop {
+            self.trigger_stops(trades, events)?;
+            if settled == trades.len() {
+                break;
+            }
+            let unsettled = trades[settled..].to_vec();
+            settled = trades.len();
+            for trade in &unsettled {
+                for order_id in [trade.buy_order_id, trade.sell_order_id] {
+                    self.apply_bracket_fill(order_id, trade.quantity.value(), trades, events)?;
+                }
+            }
+        }
+        events.extend(self.prune_brackets().iter().map(Bracket::event));
+        Ok(())
+    }
+    
+    /// Closes brackets with no order left in the book, returning them
+    pub(super) fn prune_brackets(&mut self) -> Vec<Bracket> {
+        let (mut closed, open) = std::mem::take(&mut self.brackets)
+            .into_iter()
+            .partition(|bracket| {
+                let ids = [bracket.entry_order_id, bracket.take_profit_id, bracket.stop_loss_id];
+                !ids.into_iter().any(|order_id| self.is_live(order_id))
+            });
+        self.brackets = open;
+        for bracket in &mut closed {
+            bracket.state = BracketState::Closed;
+        }
+        closed
+    }
+    
+    fn apply_bracket_fill(
+        &mut self,
+        order_id: OrderId,
+        quantity: u64,
+        trades: &mut Vec<Trade>,
+        events: &mut Vec<OrderEvent>,
+    ) -> crate::Result<()> {
+        let Some(index) = self.brackets.iter().position(|bracket| {
+            [bracket.entry_order_id, bracket.take_profit_id, bracket.stop_loss_id].contains(&order_id)
+        }) else {
+            return Ok(());
+        };
+        
+        let bracket = &mut self.brackets[index];
+        if order_id == bracket.entry_order_id {
+            bracket.entry_filled += quantity;
+            bracket.state = BracketState::Active;
+            let bracket = bracket.clone();
+            for exit_id in [bracket.stop_loss_id, bracket.take_profit_id] {
+                self.grow_exit(&bracket, exit_id, quantity, trades, events)?;
+            }
+            events.push(bracket.event());
+            return Ok(());
+        }
+        
+        // An exit fill closes part of the position: stop adding to it and shrink the other exit
+        bracket.exit_filled += quantity;
+        let bracket = bracket.clone();
+        self.cancel_contingent(bracket.entry_order_id, None, events)?;
+        self.cancel_contingent(bracket.sibling(order_id), Some(quantity), events)?;
+        if bracket.open_quantity() == 0 {
+            self.cancel_contingent(bracket.sibling(order_id), None, events)?;
+            self.brackets.remove(index);
+            events.push(Bracket { state: BracketState::Closed, ..bracket }.event());
+        } else {
+            events.push(bracket.event());
+        }
+        Ok(())
+    }
+    
+    /// Adds `quantity` to an exit, placing it if it isn't live
+    fn grow_exit(
+        &mut self,
+        bracket: &Bracket,
+        exit_id: OrderId,
+        quantity: u64,
+        trades: &mut Vec<Trade>,
+        events: &mut Vec<OrderEvent>,
+    ) -> crate::Result<()> {
+        let now = self.now();
+        let grow = |order: &mut Order| {
+            order.original_quantity = Quantity::new_allow_zero(order.original_quantity.value() + quantity);
+            order.remaining_quantity = Quantity::new_allow_zero(order.remaining_quantity.value() + quantity);
+        };
+        if let Some(&OrderLocation { side, price, .. }) = self.orders.get(&exit_id) {
+            grow(&mut *self.resting_order_mut(exit_id, side, price)?);
+            self.touch_level(side, price);
+            return Ok(());
+        }
+        if self.stops.update(exit_id, grow).is_some() {
+            return Ok(());
+        }
+        
+        let mut order = bracket.exit(exit_id, quantity);
+        if order.stop_trigger().is_some() && !self.release_stop(&mut order, events) {
+            self.stops.insert(order);
+            return Ok(());
+        }
+        trades.extend(self.match_order(&mut order, events)?);
+        if !order.can_rest() && order.is_active() {
+            order.cancel_at(now);
+            events.push(OrderEvent::Cancelled { order, reason: CancelReason::Unfilled });
+        } else if order.is_active() {
+            self.insert_order(order)?;
+        }
+        Ok(())
+    }
+    
+    /// Cancels a live order, or reduces it by `reduction` when that leaves some behind
+    fn cancel_contingent(&mut self, order_id: OrderId, reduction: Option<u64>, events: &mut Vec<OrderEvent>) -> crate::Result<()> {
+        let now = self.now();
+        let reason = CancelReason::Contingency;
+        let resting = self.orders.get(&order_id).copied();
+        let live = match resting {
+            Some(_) => self.resting_order(order_id),
+            None => self.get_pending_stop(order_id),
+        };
+        let Some(remaining) = live.map(|order| order.remaining_quantity.value()) else {
+            return Ok(());
+        };
+        
+        if let Some(reduction) = reduction.filter(|&reduction| reduction < remaining) {
+            let quantity = Quantity::new_allow_zero(reduction);
+            match resting {
+                Some(OrderLocation { side, price, .. }) => {
+                    self.resting_order_mut(order_id, side, price)?.reduce_at(quantity, now)?;
+                    self.touch_level(side, price);
+                },
+                None => self.stops.update(order_id, |order| order.reduce_at(quantity, now)).expect("pending stop was just found")?,
+            }
+            events.push(OrderEvent::Decremented { order_id, quantity, reason });
+            return Ok(());
+        }
+        
+        let mut order = match resting {
+            Some(OrderLocation { side, price, .. }) => self.take_resting_order(order_id, side, price)?,
+            None => self.stops.remove(order_id).expect("pending stop was just found"),
+        };
+        order.cancel_at(now);
+        events.push(OrderEvent::Cancelled { order, reason });
+        Ok(())
+    }
+    
+    fn is_live(&self, order_id: OrderId) -> bool {
+        self.orders.contains_key(&order_id) || self.get_pending_stop(order_id).is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::order_book::test_support::create_order;
+
+    fn price(cents: i64) -> Price {
+        Price::from_cents(cents).unwrap()
+    }
+
+    fn bracket_ids(events: &[OrderEvent]) -> (OrderId, OrderId) {
+        events.iter()
+            .find_map(|event| match event {
+                OrderEvent::BracketUpdated { take_profit_id, stop_loss_id, .. } => Some((*take_profit_id, *stop_loss_id)),
+                _ => None,
+            })
+            .unwrap()
+    }
+
+    #[test]
+    fn test_cancelling_unfilled_entry_cancels_bracket() {
+        let mut book = LimitOrderBook::new("BRKT".to_string()).unwrap();
+        let entry = create_order(OrderSide::Buy, 10000, 100, "trader");
+        let entry_id = entry.id;
+
+        let result = book.submit_bracket(entry, price(10500), price(9500)).unwrap();
+        assert!(matches!(
+            &result.events[..],
+            [OrderEvent::BracketUpdated { state: BracketState::Pending, .. }]
+        ));
+        assert_eq!(book.bracket(entry_id).unwrap().state, BracketState::Pending);
+
+        book.cancel_order(entry_id).unwrap();
+        assert_eq!(book.bracket_count(), 0);
+
+        // Nothing the bracket would have placed reacts to later trades
+        book.add_order(create_order(OrderSide::Sell, 9000, 10, "a")).unwrap();
+        book.add_order(create_order(OrderSide::Buy, 9000, 10, "b")).unwrap();
+        assert!(book.is_empty());
+        assert_eq!(book.pending_stop_count(), 0);
+    }
+
+    #[test]
+    fn test_take_profit_fill_shrinks_stop_loss_and_closes() {
+        let mut book = LimitOrderBook::new("BRKT".to_string()).unwrap();
+        book.add_order(create_order(OrderSide::Buy, 10000, 100, "mm")).unwrap();
+
+        // A sell entry fills completely, so both exits go live at 100
+        let entry = create_order(OrderSide::Sell, 10000, 100, "trader");
+        let result = book.submit_bracket(entry, price(9500), price(10500)).unwrap();
+        let (take_profit_id, stop_loss_id) = bracket_ids(&result.events);
+        assert_eq!(book.get_order(take_profit_id).unwrap().remaining_quantity.value(), 100);
+        assert_eq!(book.get_pending_stop(stop_loss_id).unwrap().remaining_quantity.value(), 100);
+
+        // A partial take-profit fill takes the same quantity off the stop-loss
+        let result = book.submit_order(create_order(OrderSide::Sell, 9500, 40, "mm")).unwrap();
+        assert!(result.events.iter().any(|event| matches!(
+            event,
+            OrderEvent::Decremented { order_id, quantity, reason: CancelReason::Contingency }
+                if *order_id == stop_loss_id && quantity.value() == 40
+        )));
+        assert_eq!(book.get_pending_stop(stop_loss_id).unwrap().remaining_quantity.value(), 60);
+
+        let result = book.submit_order(create_order(OrderSide::Sell, 9500, 60, "mm")).unwrap();
+        assert!(result.events.iter().any(|event| matches!(
+            event,
+            OrderEvent::BracketUpdated { state: BracketState::Closed, .. }
+        )));
+        assert_eq!(book.pending_stop_count(), 0);
+        assert_eq!(book.bracket_count(), 0);
+    }
+
+    #[test]
+    fn test_invalid_exit_prices_are_rejected() {
+        let mut book = LimitOrderBook::new("BRKT".to_string()).unwrap();
+        let entry = create_order(OrderSide::Buy, 10000, 100, "trader");
+
+        assert!(matches!(
+            book.submit_bracket(entry, price(9500), price(10500)),
+            Err(MatchingEngineError::InvalidPrice(_))
+        ));
+        assert_eq!(book.bracket_count(), 0);
+        assert!(book.is_empty());
+    }
+}
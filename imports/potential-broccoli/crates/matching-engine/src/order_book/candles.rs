@@ -0,0 +1,274 @@
+//! OHLCV candles from the trade stream
+//!
+//! A [`CandleAggregator`] buckets trades into fixed intervals aligned to the
+//! Unix epoch, so a one-minute candle always starts on a whole minute. A
+//! candle completes when the first trade of a later interval arrives; until
+//! then it is the current candle. Intervals without trades are skipped, or
+//! emitted as empty candles priced at the previous close when configured.
+//! Attached to a book, the aggregator is fed every committed trade; it can
+//! equally be fed from a listener or a replayed trade log.
+
+use super::{LimitOrderBook, Trade};
+use crate::Price;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+/// Open, high, low, close, and volume over one interval
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Candle {
+    /// Start of the interval, a multiple of the interval since the epoch
+    pub start_time: DateTime<Utc>,
+    pub open: Price,
+    pub high: Price,
+    pub low: Price,
+    pub close: Price,
+    /// Total quantity traded
+    pub volume: u64,
+    pub trade_count: u64,
+}
+
+impl Candle {
+    fn new(start_time: DateTime<Utc>, trade: &Trade) -> Self {
+        Self {
+            start_time,
+            open: trade.price,
+            high: trade.price,
+            low: trade.price,
+            close: trade.price,
+            volume: trade.quantity.value(),
+            trade_count: 1,
+        }
+    }
+
+    /// A candle for an interval without trades, flat at `price`
+    fn empty(start_time: DateTime<Utc>, price: Price) -> Self {
+        Self { start_time, open: price, high: price, low: price, close: price, volume: 0, trade_count: 0 }
+    }
+
+    fn record(&mut self, trade: &Trade) {
+        self.high = self.high.max(trade.price);
+        self.low = self.low.min(trade.price);
+        self.close = trade.price;
+        self.volume += trade.quantity.value();
+        self.trade_count += 1;
+    }
+}
+
+/// Buckets trades into fixed-interval candles, keeping a bounded history
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CandleAggregator {
+    interval_ms: i64,
+    retention: usize,
+    emit_empty: bool,
+    current: Option<Candle>,
+    /// Completed candles, oldest first
+    completed: VecDeque<Candle>,
+}
+
+impl CandleAggregator {
+    /// Creates an aggregator of `interval` candles keeping the last `retention` completed ones
+    ///
+    /// Intervals shorter than a millisecond are treated as one millisecond.
+    pub fn new(interval: Duration, retention: usize) -> Self {
+        Self {
+            interval_ms: interval.num_milliseconds().max(1),
+            retention,
+            emit_empty: false,
+            current: None,
+            completed: VecDeque::new(),
+        }
+    }
+
+    /// Emits an empty candle for each interval without trades instead of skipping it
+    pub fn with_empty_candles(mut self) -> Self {
+        self.emit_empty = true;
+        self
+    }
+
+    /// Gets the candle length
+    pub fn interval(&self) -> Duration {
+        Duration::milliseconds(self.interval_ms)
+    }
+
+    /// Gets the candle still collecting trades
+    pub fn current_candle(&self) -> Option<&Candle> {
+        self.current.as_ref()
+    }
+
+    /// Gets the retained completed candles, oldest first
+    pub fn completed_candles(&self) -> impl ExactSizeIterator<Item = &Candle> + DoubleEndedIterator {
+        self.completed.iter()
+    }
+
+    /// Adds a trade to its interval's candle
+    ///
+    /// A trade stamped before the current candle's interval (clock skew
+    /// between producers) is counted in the current candle.
+    pub fn record(&mut self, trade: &Trade) {
+        let start_time = self.bucket_start(trade.timestamp);
+        let Some(current) = self.current.as_mut() else {
+            self.current = Some(Candle::new(start_time, trade));
+            return;
+        };
+        if start_time <= current.start_time {
+            current.record(trade);
+            return;
+        }
+
+        let finished = *current;
+        self.complete(finished);
+        if self.emit_empty {
+            // Only the most recent `retention` empty candles could survive eviction
+            let gaps = (start_time - finished.start_time).num_milliseconds() / self.interval_ms - 1;
+            let skipped = gaps.saturating_sub(self.retention as i64).max(0);
+            for gap in skipped + 1..=gaps {
+                self.complete(Candle::empty(finished.start_time + Duration::milliseconds(gap * self.interval_ms), finished.close));
+            }
+        }
+        self.current = Some(Candle::new(start_time, trade));
+    }
+
+    fn complete(&mut self, candle: Candle) {
+        self.completed.push_back(candle);
+        while self.completed.len() > self.retention {
+            self.completed.pop_front();
+        }
+    }
+
+    /// Start of the epoch-aligned interval containing `timestamp`
+    fn bucket_start(&self, timestamp: DateTime<Utc>) -> DateTime<Utc> {
+        let millis = timestamp.timestamp_millis();
+        let start = millis - millis.rem_euclid(self.interval_ms);
+        DateTime::from_timestamp_millis(start).unwrap_or(timestamp)
+    }
+}
+
+impl LimitOrderBook {
+    /// Attaches a candle aggregator fed with every trade, or detaches it with `None`
+    pub fn set_candle_aggregator(&mut self, aggregator: Option<CandleAggregator>) {
+        self.candles = aggregator;
+    }
+
+    /// Gets the attached candle aggregator
+    pub fn candles(&self) -> Option<&CandleAggregator> {
+        self.candles.as_ref()
+    }
+
+    /// Feeds a committed trade to the candle aggregator, if attached
+    pub(super) fn record_candle_trade(&mut self, trade: &Trade) {
+        if let Some(candles) = self.candles.as_mut() {
+            candles.record(trade);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        types::{OrderId, UserId},
+        Order, OrderSide, Quantity,
+    };
+
+    fn trade_at(price_cents: i64, quantity: u64, timestamp: &str) -> Trade {
+        Trade {
+            trade_id: 0,
+            buy_order_id: OrderId::new(),
+            sell_order_id: OrderId::new(),
+            maker_order_id: None,
+            taker_order_id: None,
+            aggressor_side: None,
+            buyer_user_id: None,
+            seller_user_id: None,
+            price: Price::from_cents(price_cents).unwrap(),
+            quantity: Quantity::new(quantity).unwrap(),
+            timestamp: timestamp.parse().unwrap(),
+            same_firm: false,
+            maker_fee: Default::default(),
+            taker_fee: Default::default(),
+            symbol: None,
+            sequence: 0,
+            quantity_scale: 0,
+        }
+    }
+
+    fn ohlcv(candle: &Candle) -> (i64, i64, i64, i64, u64, u64) {
+        (
+            candle.open.as_cents(),
+            candle.high.as_cents(),
+            candle.low.as_cents(),
+            candle.close.as_cents(),
+            candle.volume,
+            candle.trade_count,
+        )
+    }
+
+    #[test]
+    fn test_burst_across_a_boundary_splits_candles() {
+        let mut candles = CandleAggregator::new(Duration::minutes(1), 10);
+        for (price_cents, quantity, timestamp) in [
+            (10000, 10, "2024-03-01T09:30:58.100Z"),
+            (10020, 5, "2024-03-01T09:30:59.000Z"),
+            (9990, 7, "2024-03-01T09:30:59.999Z"),
+            (10005, 3, "2024-03-01T09:31:00.000Z"),
+            (10030, 4, "2024-03-01T09:31:00.500Z"),
+        ] {
+            candles.record(&trade_at(price_cents, quantity, timestamp));
+        }
+
+        let completed: Vec<&Candle> = candles.completed_candles().collect();
+        assert_eq!(completed.len(), 1);
+        assert_eq!(completed[0].start_time, "2024-03-01T09:30:00Z".parse::<DateTime<Utc>>().unwrap());
+        assert_eq!(ohlcv(completed[0]), (10000, 10020, 9990, 9990, 22, 3));
+
+        let current = candles.current_candle().unwrap();
+        assert_eq!(current.start_time, "2024-03-01T09:31:00Z".parse::<DateTime<Utc>>().unwrap());
+        assert_eq!(ohlcv(current), (10005, 10030, 10005, 10030, 7, 2));
+    }
+
+    #[test]
+    fn test_gaps_are_skipped_or_filled_and_retention_is_bounded() {
+        let trades = [
+            trade_at(10000, 10, "2024-03-01T09:30:01Z"),
+            trade_at(10010, 10, "2024-03-01T09:30:04Z"),
+            trade_at(10020, 10, "2024-03-01T09:30:05Z"),
+        ];
+        let mut skipping = CandleAggregator::new(Duration::seconds(1), 10);
+        let mut filling = CandleAggregator::new(Duration::seconds(1), 10).with_empty_candles();
+        let mut bounded = CandleAggregator::new(Duration::seconds(1), 2).with_empty_candles();
+        for trade in &trades {
+            skipping.record(trade);
+            filling.record(trade);
+            bounded.record(trade);
+        }
+
+        assert_eq!(skipping.completed_candles().len(), 2);
+        let filled: Vec<_> = filling.completed_candles().map(ohlcv).collect();
+        assert_eq!(filled, vec![
+            (10000, 10000, 10000, 10000, 10, 1),
+            (10000, 10000, 10000, 10000, 0, 0),
+            (10000, 10000, 10000, 10000, 0, 0),
+            (10010, 10010, 10010, 10010, 10, 1),
+        ]);
+        let kept: Vec<_> = bounded.completed_candles().map(|candle| candle.start_time).collect();
+        assert_eq!(kept, vec![trades[0].timestamp + Duration::seconds(2), trades[1].timestamp]);
+    }
+
+    #[test]
+    fn test_book_feeds_attached_aggregator() {
+        let mut book = LimitOrderBook::new("OHLC".to_string()).unwrap();
+        book.set_candle_aggregator(Some(CandleAggregator::new(Duration::hours(1), 24)));
+        let order = |side, price_cents, quantity| {
+            let price = Price::from_cents(price_cents).unwrap();
+            Order::new(OrderId::new(), UserId::new("test_user".to_string()), side, price, Quantity::new(quantity).unwrap())
+        };
+        book.add_order(order(OrderSide::Sell, 10000, 10)).unwrap();
+        book.add_order(order(OrderSide::Sell, 10100, 10)).unwrap();
+        book.add_order(order(OrderSide::Buy, 10100, 15)).unwrap();
+
+        let current = book.candles().unwrap().current_candle().unwrap();
+        assert_eq!((current.open.as_cents(), current.close.as_cents(), current.volume), (10000, 10100, 15));
+        assert_eq!(current.trade_count, 2);
+    }
+}
@@ -0,0 +1,278 @@
+//! Depth checksums for feed consumers, and state hashes for replicas
+
+use super::{clock::Clock, BookStats, LimitOrderBook, MarketDepth, MarketLevel};
+use crate::{Order, Price, Quantity};
+use chrono::{DateTime, Utc};
+use sha2::{Digest, Sha256};
+
+impl MarketDepth {
+    /// Computes a CRC32 over the top `levels` bid and ask levels
+    /// 
+    /// Canonical encoding: for each side (bids best-first, then asks
+    /// best-first), every level is written as `<price>:<quantity>;` where the
+    /// price is the normalized decimal (no trailing zeros, so `150.50` and
+    /// `150.5` encode identically) and the quantity is the integer share
+    /// count. The two sides are separated by `|`. The CRC32 (IEEE) of the
+    /// resulting ASCII string is the checksum.
+    pub fn checksum(&self, levels: usize) -> u32 {
+        let mut encoded = String::new();
+        encode_levels(&mut encoded, &self.bids, levels);
+        encoded.push('|');
+        encode_levels(&mut encoded, &self.asks, levels);
+        crc32fast::hash(encoded.as_bytes())
+    }
+}
+
+impl LimitOrderBook {
+    /// Computes the checksum of the top `levels` displayed levels per side
+    /// 
+    /// Identical to [`MarketDepth::checksum`] over `market_depth(levels)`, so
+    /// a consumer can compare it against the checksum of its own replica.
+    pub fn checksum(&self, levels: usize) -> u32 {
+        self.market_depth(levels).checksum(levels)
+    }
+    
+    /// Computes a SHA-256 over the book's resting orders, sequence number, and statistics
+    /// 
+    /// Two books that applied the same ops hash identically, so a primary
+    /// and its standby can compare hashes to detect divergence. Canonical
+    /// encoding: the symbol and the sequence number, then every resting order
+    /// in priority order (bids best-first, then asks best-first, each level in
+    /// queue order), then the statistics. Integers are big-endian `u64`s,
+    /// strings and normalized decimals are prefixed with their byte length,
+    /// and optional values with a presence byte.
+    /// 
+    /// Order timestamps are stamped from the book's clock, so they are only
+    /// hashed on a fixed clock; on the system clock they would differ between
+    /// replicas, as would those stamped before a book switched to a fixed
+    /// clock. Pending stops, parked orders, and recent trades are not hashed.
+    pub fn state_hash(&self) -> [u8; 32] {
+        let mut hasher = StateHasher { sha: Sha256::new(), timestamps: matches!(self.clock, Clock::Fixed(_)) };
+        hasher.text(self.symbol.as_str());
+        hasher.number(self.sequence);
+        for levels in [&self.bids, &self.asks] {
+            for order in levels.iter().flat_map(|(_, orders)| orders) {
+                hasher.order(order);
+            }
+        }
+        hasher.stats(&self.stats);
+        hasher.sha.finalize().into()
+    }
+}
+
+/// Feeds the canonical encoding of book state into a SHA-256
+struct StateHasher {
+    sha: Sha256,
+    /// Whether order timestamps are part of the state
+    timestamps: bool,
+}
+
+impl StateHasher {
+    fn number(&mut self, value: u64) {
+        self.sha.update(value.to_be_bytes());
+    }
+    
+    fn text(&mut self, value: &str) {
+        self.number(value.len() as u64);
+        self.sha.update(value.as_bytes());
+    }
+    
+    fn flag(&mut self, value: bool) {
+        self.sha.update([u8::from(value)]);
+    }
+    
+    fn optional<T>(&mut self, value: Option<T>, write: impl FnOnce(&mut Self, T)) {
+        self.flag(value.is_some());
+        if let Some(value) = value {
+            write(self, value);
+        }
+    }
+    
+    fn price(&mut self, price: Price) {
+        self.text(&price.value().normalize().to_string());
+    }
+    
+    fn quantity(&mut self, quantity: Quantity) {
+        self.number(quantity.value());
+    }
+    
+    fn time(&mut self, time: DateTime<Utc>) {
+        self.text(&time.to_rfc3339());
+    }
+    
+    fn order(&mut self, order: &Order) {
+        self.sha.update(order.id.as_uuid().as_bytes());
+        self.text(order.user_id.as_str());
+        self.optional(order.symbol.as_ref(), |hasher, symbol| hasher.text(symbol.as_str()));
+        self.text(&order.side.to_string());
+        self.price(order.price);
+        self.quantity(order.original_quantity);
+        self.quantity(order.remaining_quantity);
+        self.text(&order.status.to_string());
+        self.text(&order.time_in_force.to_string());
+        self.text(&order.order_type.to_string());
+        self.optional(order.expires_at, Self::time);
+        self.optional(order.activate_at, Self::time);
+        self.flag(order.post_only);
+        self.flag(order.reduce_only);
+        self.flag(order.hidden);
+        self.optional(order.client_order_id.as_deref(), Self::text);
+        if self.timestamps {
+            self.time(order.created_at);
+            self.time(order.updated_at);
+        }
+    }
+    
+    fn stats(&mut self, stats: &BookStats) {
+        self.optional(stats.last_trade_price, Self::price);
+        self.optional(stats.last_trade_quantity, Self::quantity);
+        self.optional(stats.high, Self::price);
+        self.optional(stats.low, Self::price);
+        self.number(stats.volume);
+        self.number(stats.trade_count);
+        self.optional(stats.official_close, Self::price);
+    }
+}
+
+fn encode_levels(out: &mut String, levels: &[MarketLevel], count: usize) {
+    use std::fmt::Write;
+    for level in levels.iter().take(count) {
+        // Writing to a String never fails
+        let _ = write!(out, "{}:{};", level.price.value().normalize(), level.quantity.value());
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        types::OrderId,
+        Clock, LimitOrderBook, OrderBookOp, OrderSide, Price, Quantity,
+    };
+    use chrono::{Duration, Utc};
+    use proptest::prelude::*;
+    use crate::order_book::test_support::create_order_at;
+
+    fn book(orders: &[(OrderSide, &str, u64)]) -> LimitOrderBook {
+        let mut book = LimitOrderBook::new("CRC".to_string()).unwrap();
+        for &(side, price, quantity) in orders {
+            book.add_order(create_order_at(side, price, quantity)).unwrap();
+        }
+        book
+    }
+
+    #[test]
+    fn test_canonical_encoding_ignores_decimal_scale() {
+        let short = book(&[(OrderSide::Buy, "150.5", 10), (OrderSide::Buy, "150", 20), (OrderSide::Sell, "151.25", 5)]);
+        let long = book(&[(OrderSide::Buy, "150.50", 10), (OrderSide::Buy, "150.00", 20), (OrderSide::Sell, "151.250", 5)]);
+        assert_eq!(short.checksum(10), long.checksum(10));
+        assert_eq!(short.checksum(10), crc32fast::hash(b"150.5:10;150:20;|151.25:5;"));
+        assert_eq!(book(&[]).checksum(10), crc32fast::hash(b"|"));
+    }
+
+    #[test]
+    fn test_checksum_tracks_included_levels_only() {
+        let mut book = book(&[(OrderSide::Buy, "99", 10), (OrderSide::Buy, "98", 10), (OrderSide::Sell, "101", 10)]);
+        let before = book.checksum(1);
+        let before_deep = book.checksum(2);
+
+        // Beyond the top level: only the deeper checksum changes
+        book.add_order(create_order_at(OrderSide::Buy, "98", 1)).unwrap();
+        assert_eq!(book.checksum(1), before);
+        assert_ne!(book.checksum(2), before_deep);
+
+        book.add_order(create_order_at(OrderSide::Sell, "101", 1)).unwrap();
+        assert_ne!(book.checksum(1), before);
+        assert_eq!(book.checksum(5), book.market_depth(5).checksum(5));
+    }    
+    /// Submissions, and cancels and reductions of earlier orders, on the system
+    /// clock or on a fixed clock that ticks along
+    fn arb_ops() -> impl Strategy<Value = Vec<OrderBookOp>> {
+        let step = (0u8..10, any::<bool>(), 0i64..12, 1u64..60, any::<prop::sample::Index>());
+        (any::<bool>(), proptest::collection::vec(step, 1..120)).prop_map(|(fixed, steps)| {
+            let start = Utc::now();
+            let mut ids = Vec::new();
+            let clock = fixed.then_some(OrderBookOp::SetClock(Clock::Fixed(start)));
+            clock.into_iter().chain(steps.into_iter().enumerate()
+                .map(|(i, (kind, buy, tick, quantity, pick))| match kind {
+                    0 if !ids.is_empty() => OrderBookOp::Cancel(ids[pick.index(ids.len())]),
+                    1 if !ids.is_empty() => OrderBookOp::Reduce {
+                        order_id: ids[pick.index(ids.len())],
+                        new_quantity: Quantity::new(quantity / 4 + 1).unwrap(),
+                    },
+                    2 if fixed => OrderBookOp::SetClock(Clock::Fixed(start + Duration::seconds(i as i64))),
+                    _ => {
+                        let side = if buy { OrderSide::Buy } else { OrderSide::Sell };
+                        let mut order = create_order_at(side, &format!("{}.{:02}", 100, 90 + tick), quantity);
+                        order.id = OrderId::from_uuid(uuid::Uuid::from_u128(i as u128));
+                        order.hidden = tick % 5 == 0;
+                        ids.push(order.id);
+                        OrderBookOp::Submit(order)
+                    },
+                }))
+                .collect()
+        })
+    }
+    
+    proptest! {
+        #[test]
+        fn prop_same_ops_hash_identically(ops in arb_ops()) {
+            let mut primary = LimitOrderBook::new("HASH".to_string()).unwrap();
+            primary.enable_op_log();
+            let mut standby = LimitOrderBook::new("HASH".to_string()).unwrap();
+            for op in &ops {
+                primary.apply(op.clone());
+            }
+            // The standby applies the ops later, when the wall clock reads differently
+            for op in ops {
+                standby.apply(op);
+            }
+            prop_assert_eq!(primary.state_hash(), standby.state_hash());
+            prop_assert_eq!(LimitOrderBook::replay(primary.take_ops()).unwrap().state_hash(), primary.state_hash());
+        }
+    }
+    
+    #[test]
+    fn test_state_hash_detects_divergence() {
+        let build = || book(&[(OrderSide::Buy, "99", 10), (OrderSide::Buy, "99", 20), (OrderSide::Sell, "101", 10)]);
+        let primary = build();
+        let mut standby = primary.clone();
+        assert_eq!(primary.state_hash(), standby.state_hash());
+        
+        // One order's remaining quantity differs
+        let mut orders = standby.bids.get_mut(&Price::from_str("99").unwrap()).unwrap();
+        let (second, _) = orders.orders().entries().nth(1).unwrap();
+        orders.get_mut(second).unwrap().remaining_quantity = Quantity::new(19).unwrap();
+        assert_ne!(primary.state_hash(), standby.state_hash());
+        
+        // Independently built books differ by their order IDs, and by queue order
+        assert_ne!(build().state_hash(), primary.state_hash());
+        let mut reordered = primary.clone();
+        let mut orders = reordered.bids.get_mut(&Price::from_str("99").unwrap()).unwrap();
+        let (first, _) = orders.orders().entries().next().unwrap();
+        let head = orders.remove(first).unwrap();
+        orders.push_back(head);
+        assert_ne!(reordered.state_hash(), primary.state_hash());
+        
+        // As do statistics and the sequence number
+        let mut traded = primary.clone();
+        traded.stats.volume += 1;
+        assert_ne!(traded.state_hash(), primary.state_hash());
+        let mut advanced = primary.clone();
+        advanced.sequence += 1;
+        assert_ne!(advanced.state_hash(), primary.state_hash());
+    }
+    
+    #[test]
+    fn test_timestamps_count_only_on_a_fixed_clock() {
+        let mut primary = book(&[(OrderSide::Buy, "99", 10), (OrderSide::Sell, "101", 10)]);
+        let mut standby = primary.clone();
+        standby.asks.front_mut(&Price::from_str("101").unwrap()).unwrap().updated_at += Duration::milliseconds(3);
+        assert_eq!(primary.state_hash(), standby.state_hash());
+        
+        let start = Utc::now();
+        primary.set_clock(Clock::Fixed(start));
+        standby.set_clock(Clock::Fixed(start));
+        assert_ne!(primary.state_hash(), standby.state_hash());
+    }
+}
\ No newline at end of file
@@ -0,0 +1,125 @@
+//! Client order IDs
+//!
+//! Gateways key orders by the client order ID their users assigned, which
+//! only has to be unique per user among live orders. The index maps each
+//! pair to the engine's order ID and is checked lazily: an entry whose order
+//! has since filled or been cancelled no longer counts, so the client ID can
+//! be reused straight away. Stale entries are dropped at the end of each day.
+
+use super::{LimitOrderBook, OrderBookOp};
+use crate::{types::{OrderId, UserId}, MatchingEngineError, Order};
+
+impl LimitOrderBook {
+    /// Gets a live order (resting, parked, or a pending stop) by its client order ID
+    pub fn get_order_by_client_id(&self, user_id: &UserId, client_order_id: &str) -> Option<&Order> {
+        let order_id = self.live_client_order(user_id, client_order_id)?;
+        self.get_order(order_id).or_else(|| self.get_pending_stop(order_id))
+    }
+    
+    /// Cancels a live order by its client order ID
+    pub fn cancel_by_client_id(&mut self, user_id: &UserId, client_order_id: &str) -> crate::Result<Order> {
+        self.apply(OrderBookOp::CancelByClientId { user_id: user_id.clone(), client_order_id: client_order_id.to_string() }).cancelled()
+    }
+    
+    pub(super) fn cancel_client_order(&mut self, user_id: &UserId, client_order_id: &str) -> crate::Result<Order> {
+        let order_id = self.live_client_order(user_id, client_order_id)
+            .ok_or_else(|| MatchingEngineError::OrderNotFound(format!("{}/{}", user_id, client_order_id)))?;
+        self.cancel(order_id)
+    }
+    
+    /// Rejects an order whose client order ID is already used by one of the user's live orders
+    pub(super) fn check_client_order_id(&self, order: &Order) -> crate::Result<()> {
+        let Some(client_order_id) = &order.client_order_id else {
+            return Ok(());
+        };
+        if self.live_client_order(&order.user_id, client_order_id).is_some() {
+            return Err(MatchingEngineError::DuplicateClientOrderId {
+                user_id: order.user_id.to_string(),
+                client_order_id: client_order_id.clone(),
+            });
+        }
+        Ok(())
+    }
+    
+    /// Records an accepted order's client order ID
+    pub(super) fn track_client_order_id(&mut self, order: &Order) {
+        if let Some(client_order_id) = &order.client_order_id {
+            self.client_order_ids
+                .entry(order.user_id.clone())
+                .or_default()
+                .insert(client_order_id.clone(), order.id);
+        }
+    }
+    
+    /// Drops entries whose orders are no longer live
+    pub(super) fn prune_client_order_ids(&mut self) {
+        let mut client_order_ids = std::mem::take(&mut self.client_order_ids);
+        for ids in client_order_ids.values_mut() {
+            ids.retain(|_, order_id| self.is_live_order(*order_id));
+        }
+        client_order_ids.retain(|_, ids| !ids.is_empty());
+        self.client_order_ids = client_order_ids;
+    }
+    
+    /// Gets the order ID a client order ID refers to, if that order is still live
+    fn live_client_order(&self, user_id: &UserId, client_order_id: &str) -> Option<OrderId> {
+        let order_id = *self.client_order_ids.get(user_id)?.get(client_order_id)?;
+        self.is_live_order(order_id).then_some(order_id)
+    }
+    
+    fn is_live_order(&self, order_id: OrderId) -> bool {
+        self.get_order(order_id).is_some() || self.get_pending_stop(order_id).is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        types::UserId,
+        LimitOrderBook, MatchingEngineError, OrderSide,
+    };
+    use crate::order_book::test_support::create_order;
+
+    #[test]
+    fn test_client_id_reusable_after_fill() {
+        let mut book = LimitOrderBook::new("CLID".to_string()).unwrap();
+        let alice = UserId::new("alice".to_string());
+        book.add_order(create_order(OrderSide::Buy, 10000, 100, "alice").with_client_order_id("A-1")).unwrap();
+        assert_eq!(
+            book.add_order(create_order(OrderSide::Buy, 9900, 10, "alice").with_client_order_id("A-1")),
+            Err(MatchingEngineError::DuplicateClientOrderId {
+                user_id: "alice".to_string(),
+                client_order_id: "A-1".to_string(),
+            })
+        );
+
+        book.add_order(create_order(OrderSide::Sell, 10000, 100, "bob").with_client_order_id("B-1")).unwrap();
+        assert!(book.get_order_by_client_id(&alice, "A-1").is_none());
+
+        let reused = create_order(OrderSide::Buy, 9900, 10, "alice").with_client_order_id("A-1");
+        let reused_id = reused.id;
+        book.add_order(reused).unwrap();
+        assert_eq!(book.get_order_by_client_id(&alice, "A-1").unwrap().id, reused_id);
+        assert_eq!(book.cancel_by_client_id(&alice, "A-1").unwrap().id, reused_id);
+        assert!(matches!(
+            book.cancel_by_client_id(&alice, "A-1"),
+            Err(MatchingEngineError::OrderNotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_same_client_id_allowed_across_users() {
+        let mut book = LimitOrderBook::new("CLID".to_string()).unwrap();
+        let alice_order = create_order(OrderSide::Buy, 10000, 100, "alice").with_client_order_id("1");
+        let bob_order = create_order(OrderSide::Buy, 10000, 50, "bob").with_client_order_id("1");
+        let bob_id = bob_order.id;
+        book.add_order(alice_order).unwrap();
+        book.add_order(bob_order).unwrap();
+
+        let alice = UserId::new("alice".to_string());
+        let bob = UserId::new("bob".to_string());
+        book.cancel_by_client_id(&alice, "1").unwrap();
+        assert!(book.get_order_by_client_id(&alice, "1").is_none());
+        assert_eq!(book.get_order_by_client_id(&bob, "1").unwrap().id, bob_id);
+    }
+}
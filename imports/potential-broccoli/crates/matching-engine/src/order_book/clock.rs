@@ -0,0 +1,81 @@
+//! The book's source of the current time
+//!
+//! Everything the book does with the time — stamping trades and order
+//! updates, deciding whether an order has expired or is due to activate,
+//! hiding expired orders from depth — reads it from the book's [`Clock`].
+//! The system clock is the default. A fixed clock makes a run reproducible,
+//! which replaying an operation log relies on.
+
+use super::{session::Session, LimitOrderBook};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Where a book reads the current time from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum Clock {
+    /// The system's wall clock
+    #[default]
+    System,
+    /// Stopped at the given time until the book's clock is set again
+    Fixed(DateTime<Utc>),
+}
+
+impl Clock {
+    /// Reads the current time
+    pub fn now(&self) -> DateTime<Utc> {
+        match self {
+            Clock::System => Utc::now(),
+            Clock::Fixed(at) => *at,
+        }
+    }
+}
+
+impl LimitOrderBook {
+    /// Creates a new empty order book that reads the time from `clock`
+    pub fn with_clock(symbol: String, clock: Clock) -> crate::Result<Self> {
+        let mut book = Self::new(symbol)?;
+        book.clock = clock;
+        book.session = Session::new(String::new(), 0, clock.now());
+        Ok(book)
+    }
+
+    /// Gets the clock the book reads the time from
+    pub fn clock(&self) -> Clock {
+        self.clock
+    }
+
+    /// Reads the current time from the book's clock
+    pub(super) fn now(&self) -> DateTime<Utc> {
+        self.clock.now()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        OrderSide, OrderStatus,
+    };
+    use chrono::Duration;
+    use crate::order_book::test_support::create_test_order;
+
+    #[test]
+    fn test_fixed_clock_stamps_trades_and_governs_expiry() {
+        let start = Utc::now() + Duration::days(1);
+        let mut book = LimitOrderBook::with_clock("CLOCK".to_string(), Clock::Fixed(start)).unwrap();
+        book.add_order(create_test_order(OrderSide::Sell, 10000, 50).with_expiry(start + Duration::minutes(5))).unwrap();
+
+        // Still live by the book's clock, though the system clock says otherwise for a day
+        let trades = book.add_order(create_test_order(OrderSide::Buy, 10000, 20)).unwrap();
+        assert_eq!(trades[0].timestamp, start);
+        let resting = book.l3_snapshot(1).asks[0].order_id;
+        assert_eq!(book.get_order(resting).unwrap().updated_at, start);
+
+        book.set_clock(Clock::Fixed(start + Duration::minutes(5)));
+        assert!(book.market_depth(1).asks.is_empty());
+        let taker = create_test_order(OrderSide::Buy, 10000, 20);
+        let taker_id = taker.id;
+        assert!(book.add_order(taker).unwrap().is_empty());
+        assert_eq!(book.get_order(taker_id).unwrap().status, OrderStatus::Active);
+    }
+}
@@ -0,0 +1,169 @@
+//! Closing auction
+//!
+//! Orders with [`TimeInForce::AtTheClose`] — market-on-close and
+//! limit-on-close — take no part in continuous trading. They wait in a
+//! separate closing book, where they can be looked up and cancelled by ID,
+//! until `run_closing_auction` merges them with the continuous book and
+//! uncrosses it at a single price, chosen as for the opening auction. Auction
+//! orders that don't fill are cancelled; continuous orders keep resting with
+//! their priority. The auction price, or the last trade price when nothing
+//! crosses, becomes the official close in [`BookStats`](super::BookStats).
+
+use super::{LimitOrderBook, OrderBookOp, Trade};
+use crate::{
+    events::ExecutionResult,
+    types::OrderId,
+    MatchingEngineError, Order, TimeInForce,
+};
+
+impl LimitOrderBook {
+    /// Gets the number of orders waiting for the closing auction
+    pub fn closing_order_count(&self) -> usize {
+        self.closing_book.len()
+    }
+
+    /// Runs the closing auction, returning its trades
+    ///
+    /// Trades of any stop orders the closing price triggered follow the
+    /// auction's own. The book's trading state is left unchanged.
+    pub fn run_closing_auction(&mut self) -> crate::Result<Vec<Trade>> {
+        self.apply(OrderBookOp::RunClosingAuction).trades()
+    }
+    
+    pub(super) fn run_closing_cross(&mut self) -> crate::Result<Vec<Trade>> {
+        let now = self.now();
+        self.begin_mutation();
+        for order in std::mem::take(&mut self.closing_book) {
+            self.insert_order(order)?;
+        }
+
+        let mut events = Vec::new();
+        let mut trades = match self.equilibrium(now) {
+            Some(auction) => self.execute_auction(auction.price, now, &mut events)?,
+            None => Vec::new(),
+        };
+        self.stats.official_close = trades.first().map(|trade| trade.price).or(self.last_trade_price);
+        for mut order in self.extract_resting(|order| order.time_in_force == TimeInForce::AtTheClose) {
+            order.cancel_at(now);
+            self.listener.order_cancelled(&order);
+        }
+        self.settle_contingent_orders(&mut trades, &mut events)?;
+        self.end_mutation();
+
+        Ok(trades)
+    }
+
+    /// Checks an at-the-close order and holds it for the closing auction
+    ///
+    /// A position limit breach is rejected rather than trimmed.
+    pub(super) fn queue_for_close(&mut self, order: Order) -> crate::Result<ExecutionResult> {
+        self.check_identity(&order)?;
+        self.check_price_scale(&order)?;
+        self.check_lot_size(&order)?;
+        self.check_state()?;
+        self.check_price_band(&order)?;
+        self.check_reduce_only(&order)?;
+        self.check_position_limit(&order, false)?;
+
+        self.record_session_order(&order.user_id);
+        self.track_client_order_id(&order);
+        self.listener.order_accepted(&order);
+        let result = ExecutionResult {
+            order_id: order.id,
+            side: order.side,
+            status: order.status,
+            trades: Vec::new(),
+            events: Vec::new(),
+            arrival_benchmark: self.arrival_benchmark(order.side),
+        };
+        self.closing_book.push(order);
+        Ok(result)
+    }
+
+    pub(super) fn closing_order(&self, order_id: OrderId) -> Option<&Order> {
+        self.closing_book.iter().find(|order| order.id == order_id)
+    }
+
+    pub(super) fn cancel_closing(&mut self, order_id: OrderId) -> crate::Result<Order> {
+        let now = self.now();
+        let index = self.closing_book.iter()
+            .position(|order| order.id == order_id)
+            .ok_or_else(|| MatchingEngineError::OrderNotFound(order_id.to_string()))?;
+        let mut order = self.closing_book.remove(index);
+        order.cancel_at(now);
+        Ok(order)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        types::{OrderId, UserId},
+        LimitOrderBook, Order, OrderSide, OrderStatus, Price, Quantity, TimeInForce,
+    };
+    use crate::order_book::test_support::create_test_order;
+
+    fn market_on_close(side: OrderSide, quantity: u64) -> Order {
+        Order::market(OrderId::new(), UserId::new("moc".to_string()), side, Quantity::new(quantity).unwrap())
+            .with_time_in_force(TimeInForce::AtTheClose)
+    }
+
+    #[test]
+    fn test_empty_closing_book_closes_at_last_trade() {
+        let mut book = LimitOrderBook::new("CLOSE".to_string()).unwrap();
+        book.add_order(create_test_order(OrderSide::Sell, 10050, 10)).unwrap();
+        book.add_order(create_test_order(OrderSide::Buy, 10050, 10)).unwrap();
+        book.add_order(create_test_order(OrderSide::Buy, 10000, 10)).unwrap();
+
+        assert!(book.run_closing_auction().unwrap().is_empty());
+        assert_eq!(book.stats().official_close, Some(Price::from_cents(10050).unwrap()));
+        assert_eq!(book.order_count(), 1);
+    }
+
+    #[test]
+    fn test_market_on_close_takes_all_the_liquidity() {
+        let mut book = LimitOrderBook::new("CLOSE".to_string()).unwrap();
+        book.add_order(create_test_order(OrderSide::Buy, 10100, 100)).unwrap();
+        book.add_order(create_test_order(OrderSide::Buy, 10000, 100)).unwrap();
+        book.add_order(create_test_order(OrderSide::Buy, 9900, 100)).unwrap();
+        book.add_order(create_test_order(OrderSide::Sell, 10200, 100)).unwrap();
+
+        let moc = market_on_close(OrderSide::Sell, 250);
+        let moc_id = moc.id;
+        let result = book.submit_order(moc).unwrap();
+        assert!(result.trades.is_empty());
+        assert_eq!(book.closing_order_count(), 1);
+        assert_eq!(book.get_order(moc_id).unwrap().status, OrderStatus::Active);
+        assert_eq!(book.best_bid().unwrap().as_cents(), 10100);
+
+        // Selling 250 clears the 101.00 and 100.00 bids and half of 99.00
+        let trades = book.run_closing_auction().unwrap();
+        assert_eq!(trades.len(), 3);
+        assert!(trades.iter().all(|trade| trade.price.as_cents() == 9900 && trade.sell_order_id == moc_id));
+        assert_eq!(book.stats().official_close, Some(Price::from_cents(9900).unwrap()));
+        assert_eq!(book.best_bid_quantity().unwrap().value(), 50);
+        assert_eq!(book.best_ask().unwrap().as_cents(), 10200);
+        assert_eq!(book.closing_order_count(), 0);
+    }
+
+    #[test]
+    fn test_unfilled_auction_orders_are_cancelled() {
+        let mut book = LimitOrderBook::new("CLOSE".to_string()).unwrap();
+        book.add_order(create_test_order(OrderSide::Sell, 10000, 50)).unwrap();
+        let loc = create_test_order(OrderSide::Buy, 10000, 80).with_time_in_force(TimeInForce::AtTheClose);
+        let unmatched = create_test_order(OrderSide::Buy, 9000, 20).with_time_in_force(TimeInForce::AtTheClose);
+        let (loc_id, unmatched_id) = (loc.id, unmatched.id);
+        book.add_order(loc).unwrap();
+        book.add_order(unmatched).unwrap();
+        let pulled = market_on_close(OrderSide::Buy, 10);
+        let pulled_id = pulled.id;
+        book.add_order(pulled).unwrap();
+        assert_eq!(book.cancel_order(pulled_id).unwrap().status, OrderStatus::Cancelled);
+
+        let trades = book.run_closing_auction().unwrap();
+        assert_eq!((trades.len(), trades[0].buy_order_id, trades[0].quantity.value()), (1, loc_id, 50));
+        assert!(book.get_order(loc_id).is_none());
+        assert!(book.get_order(unmatched_id).is_none());
+        assert_eq!(book.order_count(), 0);
+    }
+}
@@ -0,0 +1,240 @@
+//! Construction with capacity hints and a tick size
+//!
+//! A book sized for its expected load allocates its order lookup, order
+//! slabs, per-level totals, and trade history up front, so reaching that
+//! load doesn't rehash or regrow them. The hints are only starting sizes:
+//! the containers still grow past them, and `compact` gives back whatever a
+//! burst left unused.
+//!
+//! The config's tick size is the venue's price grid. The book counts prices
+//! to cents, or to the tick's own decimal places if it is finer. Its lot
+//! size and quantity step do the same for order sizes (see the lot size
+//! module).
+
+use super::{levels::{PriceLevels, TieredStorage}, lot_size::LotRules, price_scale::check_tick_size, LimitOrderBook};
+use crate::{price::{DEFAULT_PRICE_SCALE, MAX_PRICE_SCALE}, OrderSide};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+
+/// Sizing and retention settings for a new order book
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BookConfig {
+    /// Price levels expected on each side
+    pub expected_price_levels: usize,
+    /// Resting orders expected across both sides
+    pub expected_orders: usize,
+    /// Number of recent trades to keep, all allocated up front
+    pub max_recent_trades: usize,
+    /// Hot/cold level storage settings (untiered when `None`)
+    pub tiered_storage: Option<TieredStorage>,
+    /// Prices must be whole multiples of this; must be positive
+    #[serde(default = "default_tick_size")]
+    pub tick_size: Decimal,
+    /// Smallest quantity an order may ask for; must be positive
+    #[serde(default = "default_lot")]
+    pub lot_size: u64,
+    /// Order quantities must be whole multiples of this; must be positive
+    #[serde(default = "default_lot")]
+    pub qty_step: u64,
+}
+
+fn default_lot() -> u64 {
+    1
+}
+
+fn default_tick_size() -> Decimal {
+    Decimal::new(1, DEFAULT_PRICE_SCALE)
+}
+
+impl Default for BookConfig {
+    /// No capacity reserved, a one-cent tick, any quantity, and the retention and storage of `LimitOrderBook::new`
+    fn default() -> Self {
+        Self {
+            expected_price_levels: 0,
+            expected_orders: 0,
+            max_recent_trades: 1000,
+            tiered_storage: None,
+            tick_size: default_tick_size(),
+            lot_size: default_lot(),
+            qty_step: default_lot(),
+        }
+    }
+}
+
+impl LimitOrderBook {
+    /// Creates a new empty order book, pre-allocated for the load `config` expects
+    ///
+    /// # Errors
+    /// Returns error if the tick size is not positive or has more than
+    /// [`MAX_PRICE_SCALE`] decimal places, or if the lot size or quantity
+    /// step is zero
+    pub fn with_config(symbol: String, config: BookConfig) -> crate::Result<Self> {
+        let mut book = Self::new(symbol)?;
+        let price_scale = config.tick_size.normalize().scale().clamp(DEFAULT_PRICE_SCALE, MAX_PRICE_SCALE);
+        check_tick_size(config.tick_size, price_scale)?;
+        book.lot_rules = Some(LotRules::new(config.lot_size, config.qty_step)?);
+
+        // Each side's slab takes half the expected orders, rounding up
+        let side_orders = config.expected_orders.div_ceil(2);
+        book.bids = PriceLevels::with_capacity(OrderSide::Buy, price_scale, config.expected_price_levels, side_orders);
+        book.asks = PriceLevels::with_capacity(OrderSide::Sell, price_scale, config.expected_price_levels, side_orders);
+        book.price_scale = price_scale;
+        book.tick_size = Some(config.tick_size);
+        book.orders = HashMap::with_capacity(config.expected_orders);
+        book.recent_trades = VecDeque::with_capacity(config.max_recent_trades);
+        book.max_recent_trades = config.max_recent_trades;
+        book.tiered_storage = config.tiered_storage;
+        Ok(book)
+    }
+
+    /// Releases capacity left over from a burst, dropping any levels without orders
+    ///
+    /// Call after a mass cancel, such as the end-of-day cancel-all, so the
+    /// quiet book that follows doesn't hold the burst's allocations. Levels
+    /// are normally removed as their last order leaves; any found empty here
+    /// are removed defensively. Resting orders keep their queue order.
+    pub fn compact(&mut self) {
+        self.bids.remove_empty_levels();
+        self.asks.remove_empty_levels();
+        self.shrink_to_fit();
+        self.client_order_ids.retain(|_, ids| !ids.is_empty());
+        self.client_order_ids.shrink_to_fit();
+        self.expiries.values_mut().for_each(Vec::shrink_to_fit);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{types::{OrderId, UserId}, Order, Price, Quantity};
+    use crate::order_book::test_support::create_test_order;
+
+    #[test]
+    fn test_with_config_preallocates() {
+        let config = BookConfig { expected_price_levels: 64, expected_orders: 1000, max_recent_trades: 50, ..Default::default() };
+        let book = LimitOrderBook::with_config("CFG".to_string(), config).unwrap();
+        assert_eq!(book.max_recent_trades(), 50);
+        assert!(book.orders.capacity() >= 1000);
+        assert!(book.recent_trades.capacity() >= 50);
+        assert!(book.bids.slot_capacity() >= 500 && book.asks.slot_capacity() >= 500);
+
+        let report = book.memory_report();
+        assert_eq!(report.order_count, 0);
+        assert!(report.spare_bytes() > LimitOrderBook::new("CFG".to_string()).unwrap().memory_report().spare_bytes());
+
+        let tiered = BookConfig { tiered_storage: Some(TieredStorage::new(4)), ..Default::default() };
+        let book = LimitOrderBook::with_config("CFG".to_string(), tiered).unwrap();
+        assert_eq!(book.tiered_storage(), Some(TieredStorage::new(4)));
+        assert_eq!(book.max_recent_trades(), 1000);
+    }
+
+    #[test]
+    fn test_compact_after_mass_cancel_keeps_book_working() {
+        let config = BookConfig { expected_price_levels: 100, expected_orders: 100_000, ..Default::default() };
+        let mut book = LimitOrderBook::with_config("CFG".to_string(), config).unwrap();
+        let mut ids = Vec::with_capacity(100_000);
+        for i in 0..100_000 {
+            let order = match i % 2 {
+                0 => create_test_order(OrderSide::Buy, 9900 - (i % 100), 10),
+                _ => create_test_order(OrderSide::Sell, 10100 + (i % 100), 10),
+            };
+            ids.push(order.id);
+            book.add_order(order).unwrap();
+        }
+        for id in ids {
+            book.cancel_order(id).unwrap();
+        }
+        let burst = book.memory_report();
+        assert_eq!(burst.order_count, 0);
+
+        book.compact();
+        let compacted = book.memory_report();
+        assert!(compacted.total_bytes() < burst.total_bytes() / 10);
+        assert_eq!(compacted.order_spare_bytes, 0);
+        assert_eq!(book.verify_integrity(), Ok(()));
+
+        // Resting, matching, and cancelling still work on the compacted book
+        let bid = create_test_order(OrderSide::Buy, 9950, 10);
+        let bid_id = bid.id;
+        book.add_order(bid).unwrap();
+        let ask = create_test_order(OrderSide::Sell, 10050, 30);
+        let ask_id = ask.id;
+        book.add_order(ask).unwrap();
+        let trades = book.add_order(create_test_order(OrderSide::Buy, 10050, 20)).unwrap();
+        assert_eq!(trades.len(), 1);
+        assert_eq!(trades[0].quantity.value(), 20);
+        book.cancel_order(bid_id).unwrap();
+        assert_eq!(book.get_order(ask_id).unwrap().remaining_quantity.value(), 10);
+        assert_eq!(book.best_ask().unwrap().as_cents(), 10050);
+        assert!(book.best_bid().is_none());
+        assert_eq!(book.verify_integrity(), Ok(()));
+    }
+
+    #[test]
+    fn test_compact_drops_empty_levels() {
+        let mut book = LimitOrderBook::new("CFG".to_string()).unwrap();
+        book.add_order(create_test_order(OrderSide::Buy, 9900, 10)).unwrap();
+        let price = Price::from_cents(9800).unwrap();
+        book.bids.level_mut(price).unwrap();
+        assert!(book.verify_integrity().is_err());
+
+        book.compact();
+        assert_eq!(book.price_level_count(OrderSide::Buy), 1);
+        assert_eq!(book.verify_integrity(), Ok(()));
+    }
+
+    fn ticked_book(tick_size: &str) -> LimitOrderBook {
+        let config = BookConfig { tick_size: tick_size.parse().unwrap(), ..Default::default() };
+        LimitOrderBook::with_config("CFG".to_string(), config).unwrap()
+    }
+
+    fn priced_order(side: OrderSide, price: &str) -> Order {
+        Order::new(OrderId::new(), UserId::new("test_user".to_string()), side, Price::from_str(price).unwrap(), Quantity::new(10).unwrap())
+    }
+
+    fn is_off_tick(result: crate::Result<impl std::fmt::Debug>, price: &str) -> bool {
+        matches!(result, Err(crate::MatchingEngineError::InvalidTick { price: rejected, .. }) if rejected == Price::from_str(price).unwrap())
+    }
+
+    #[test]
+    fn test_orders_must_be_on_the_tick_grid() {
+        for (tick_size, on_tick, off_tick) in [("0.01", "150.01", "150.010000001"), ("0.05", "150.05", "150.03"), ("0.25", "150.75", "150.10")] {
+            let mut book = ticked_book(tick_size);
+            assert_eq!(book.tick_size(), Some(tick_size.parse().unwrap()));
+            let resting = priced_order(OrderSide::Buy, on_tick);
+            let resting_id = resting.id;
+            book.add_order(resting).unwrap();
+            assert!(is_off_tick(book.add_order(priced_order(OrderSide::Sell, off_tick)), off_tick), "{off_tick} on {tick_size}");
+            assert!(is_off_tick(book.modify_order(resting_id, Some(Price::from_str(off_tick).unwrap()), None), off_tick));
+            assert!(book.modify_order(resting_id, Some(Price::from_str("149").unwrap()), None).is_ok());
+        }
+
+        // Trailing zeros and high precision multiples are still on the grid
+        let mut book = ticked_book("0.25");
+        book.add_order(priced_order(OrderSide::Sell, "101.500000000")).unwrap();
+        assert!(is_off_tick(book.add_order(priced_order(OrderSide::Sell, "101.250000001")), "101.250000001"));
+        assert_eq!(book.best_ask(), Some(Price::from_str("101.5").unwrap()));
+    }
+
+    #[test]
+    fn test_tick_size_is_checked_at_config_time() {
+        for tick_size in ["0", "-0.01"] {
+            let config = BookConfig { tick_size: tick_size.parse().unwrap(), ..Default::default() };
+            assert!(LimitOrderBook::with_config("CFG".to_string(), config).is_err(), "{tick_size}");
+        }
+        let too_fine = BookConfig { tick_size: rust_decimal::Decimal::new(1, MAX_PRICE_SCALE + 1), ..Default::default() };
+        assert!(LimitOrderBook::with_config("CFG".to_string(), too_fine).is_err());
+
+        // A tick finer than a cent widens the price scale to fit it
+        let mut book = ticked_book("0.005");
+        assert_eq!(book.price_scale(), 3);
+        book.add_order(priced_order(OrderSide::Buy, "99.995")).unwrap();
+        assert!(is_off_tick(book.add_order(priced_order(OrderSide::Buy, "99.994")), "99.994"));
+
+        // The tick travels with the snapshot
+        let restored = LimitOrderBook::from_json(&book.to_json().unwrap()).unwrap();
+        assert_eq!(restored.tick_size(), book.tick_size());
+        assert!(is_off_tick(restored.validate_order(&priced_order(OrderSide::Buy, "99.994")), "99.994"));
+    }
+}
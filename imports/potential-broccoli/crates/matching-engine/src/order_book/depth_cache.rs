@@ -0,0 +1,202 @@
+//! Dirty-flag cache for market depth snapshots
+//!
+//! Each side caches the raw top-N levels from its last `market_depth` build
+//! together with a price watermark: the price of the N-th level. A mutation
+//! strictly beyond the watermark cannot change the cached levels, so only
+//! mutations at or inside it (or anywhere on a side with fewer than N levels)
+//! invalidate the side.
+
+use super::MarketLevel;
+use crate::{OrderSide, Price};
+use std::sync::Mutex;
+
+/// Cached raw levels for one side of the book
+#[derive(Debug, Clone)]
+struct SideCache {
+    /// Number of levels requested when the cache was built
+    requested: usize,
+    /// Levels in priority order, including levels with no active quantity
+    levels: Vec<MarketLevel>,
+}
+
+impl SideCache {
+    /// Price beyond which mutations leave this cache valid
+    fn watermark(&self) -> Option<Price> {
+        if self.levels.len() < self.requested {
+            None // The whole side fits in the cache
+        } else {
+            self.levels.last().map(|level| level.price)
+        }
+    }
+    
+    fn is_affected_by(&self, side: OrderSide, price: Price) -> bool {
+        match (self.watermark(), side) {
+            (None, _) => true,
+            (Some(watermark), OrderSide::Buy) => price >= watermark,
+            (Some(watermark), OrderSide::Sell) => price <= watermark,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct CacheState {
+    bids: Option<SideCache>,
+    asks: Option<SideCache>,
+}
+
+/// Market depth cache shared by `&self` queries
+/// 
+/// Cloning yields an empty cache, so clones and deserialized books always
+/// start by computing depth from scratch.
+#[derive(Debug, Default)]
+pub(super) struct DepthCache {
+    state: Mutex<CacheState>,
+}
+
+impl Clone for DepthCache {
+    fn clone(&self) -> Self {
+        Self::default()
+    }
+}
+
+impl DepthCache {
+    /// Records a mutation at a price level, invalidating the side if needed
+    pub(super) fn touch(&mut self, side: OrderSide, price: Price) {
+        let state = self.state.get_mut().unwrap_or_else(|e| e.into_inner());
+        let cache = match side {
+            OrderSide::Buy => &mut state.bids,
+            OrderSide::Sell => &mut state.asks,
+        };
+        if cache.as_ref().is_some_and(|c| c.is_affected_by(side, price)) {
+            *cache = None;
+        }
+    }
+    
+    /// Appends the top `levels` raw levels of a side to `out`, building them on a miss
+    pub(super) fn extend_side_levels(
+        &self,
+        side: OrderSide,
+        levels: usize,
+        build: impl FnOnce(usize) -> Vec<MarketLevel>,
+        out: &mut Vec<MarketLevel>,
+    ) {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let cache = match side {
+            OrderSide::Buy => &mut state.bids,
+            OrderSide::Sell => &mut state.asks,
+        };
+        
+        match cache {
+            Some(cached) if cached.requested >= levels => {
+                out.extend(cached.levels.iter().take(levels).cloned());
+            },
+            _ => {
+                let built = build(levels);
+                out.extend_from_slice(&built);
+                *cache = Some(SideCache { requested: levels, levels: built });
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{order_book::MarketDepth, Clock, LimitOrderBook, OrderBookOp, OrderSide};
+    use chrono::{Duration, Utc};
+    use proptest::prelude::*;
+    use crate::order_book::test_support::create_test_order;
+
+    fn is_cached(book: &LimitOrderBook, side: OrderSide) -> bool {
+        let state = book.depth_cache.state.lock().unwrap();
+        match side {
+            OrderSide::Buy => state.bids.is_some(),
+            OrderSide::Sell => state.asks.is_some(),
+        }
+    }
+
+    #[test]
+    fn test_deep_mutation_keeps_cache() {
+        let mut book = LimitOrderBook::new("CACHE".to_string()).unwrap();
+        for i in 0..50 {
+            book.add_order(create_test_order(OrderSide::Buy, 10000 - i, 100)).unwrap();
+            book.add_order(create_test_order(OrderSide::Sell, 10001 + i, 100)).unwrap();
+        }
+        let depth = book.market_depth(10);
+        assert!(is_cached(&book, OrderSide::Buy) && is_cached(&book, OrderSide::Sell));
+        
+        // Level 40 on the bid side is beyond the top 10
+        let deep = create_test_order(OrderSide::Buy, 9960, 100);
+        let deep_id = deep.id;
+        book.add_order(deep).unwrap();
+        book.cancel_order(deep_id).unwrap();
+        assert!(is_cached(&book, OrderSide::Buy));
+        let after = book.market_depth(10);
+        assert_eq!((after.bids, after.asks), (depth.bids.clone(), depth.asks.clone()));
+        assert_eq!(after.sequence, depth.sequence + 2);
+        assert_eq!(book.market_depth(5).bids, depth.bids[..5]);
+        
+        // A change at the 10th level invalidates only that side
+        book.add_order(create_test_order(OrderSide::Buy, 9991, 100)).unwrap();
+        assert!(!is_cached(&book, OrderSide::Buy));
+        assert!(is_cached(&book, OrderSide::Sell));
+        assert_eq!(book.market_depth(10).bids[9].quantity.value(), 200);
+        
+        // Trades invalidate the side they consume
+        book.add_order(create_test_order(OrderSide::Buy, 10001, 50)).unwrap();
+        assert!(!is_cached(&book, OrderSide::Sell));
+        assert_eq!(book.market_depth(10).asks[0].quantity.value(), 50);
+    }
+
+    #[test]
+    fn test_short_side_invalidated_by_any_mutation() {
+        let mut book = LimitOrderBook::new("CACHE".to_string()).unwrap();
+        book.add_order(create_test_order(OrderSide::Sell, 10001, 100)).unwrap();
+        assert_eq!(book.market_depth(10).asks.len(), 1);
+        
+        // The side had fewer than 10 levels, so a far-away level still enters the top 10
+        book.add_order(create_test_order(OrderSide::Sell, 20000, 100)).unwrap();
+        assert_eq!(book.market_depth(10).asks.len(), 2);
+    }
+
+    proptest! {
+        #[test]
+        fn prop_depth_into_reused_buffer_matches_fresh_depth(
+            steps in proptest::collection::vec((0u8..10, any::<bool>(), 0i64..30, 1u64..60, any::<prop::sample::Index>(), 0usize..12), 1..80),
+        ) {
+            let start = Utc::now();
+            let mut book = LimitOrderBook::with_clock("DEPTH".to_string(), Clock::Fixed(start)).unwrap();
+            let mut ids = Vec::new();
+            let mut reused = MarketDepth::default();
+            for (i, (kind, buy, tick, quantity, pick, levels)) in steps.into_iter().enumerate() {
+                match kind {
+                    0 if !ids.is_empty() => {
+                        let _ = book.cancel_order(ids[pick.index(ids.len())]);
+                    },
+                    // Moving the clock past an expiry leaves expired orders resting until the next mutation
+                    1 => {
+                        book.apply(OrderBookOp::SetClock(Clock::Fixed(start + Duration::minutes(i as i64))));
+                    },
+                    _ => {
+                        // Bids from 100.10 down, asks from 99.95 up, overlapping enough to trade
+                        let (side, price) = if buy { (OrderSide::Buy, 10010 - tick) } else { (OrderSide::Sell, 9995 + tick) };
+                        let mut order = create_test_order(side, price, quantity);
+                        if kind == 2 {
+                            order = order.with_hidden();
+                        } else if kind == 3 {
+                            order = order.with_expiry(start + Duration::minutes(i as i64 + tick % 4));
+                        }
+                        ids.push(order.id);
+                        let _ = book.add_order(order);
+                    },
+                }
+                
+                // Fresh, cached, and buffer-reusing paths all agree, down to the bytes
+                let fresh = book.clone().market_depth(levels);
+                book.market_depth_into(levels, &mut reused);
+                prop_assert_eq!(&reused, &fresh);
+                prop_assert_eq!(&book.market_depth(levels), &fresh);
+                prop_assert_eq!(serde_json::to_string(&reused).unwrap(), serde_json::to_string(&fresh).unwrap());
+            }
+        }
+    }
+}
@@ -0,0 +1,147 @@
+//! Good-till-date expiry
+//!
+//! Orders carrying `expires_at` stop matching once that time passes:
+//! matching expires them instead of trading when it reaches them, and depth
+//! excludes them. They stay in the book until [`purge_expired`] sweeps them
+//! out or matching runs into them. Pending good-till-date stops are swept
+//! out by [`purge_expired`] too.
+//!
+//! [`purge_expired`]: LimitOrderBook::purge_expired
+
+use super::{LimitOrderBook, OrderBookOp};
+use crate::{types::OrderId, Order};
+use chrono::{DateTime, Utc};
+
+impl LimitOrderBook {
+    /// Removes every resting order and pending stop whose expiry is at or before `now`
+    ///
+    /// Returns the removed orders marked `Expired`, earliest expiry first.
+    pub fn purge_expired(&mut self, now: DateTime<Utc>) -> Vec<Order> {
+        self.apply(OrderBookOp::PurgeExpired(now)).orders()
+    }
+    
+    pub(super) fn expire_due(&mut self, now: DateTime<Utc>) -> Vec<Order> {
+        let due: Vec<OrderId> = self.expiries
+            .range(..=now)
+            .flat_map(|(_, order_ids)| order_ids.iter().copied())
+            .collect();
+        let stops = self.stops.take_expired(now);
+        if due.is_empty() && stops.is_empty() {
+            return Vec::new();
+        }
+        
+        self.begin_mutation();
+        let mut expired = Vec::with_capacity(due.len() + stops.len());
+        for order_id in due {
+            let Some(mut order) = self.get_order(order_id).cloned() else {
+                continue;
+            };
+            if self.remove_resting_order(order_id, order.side, order.price).is_ok() {
+                order.expire_at(now);
+                expired.push(order);
+            }
+        }
+        expired.extend(stops);
+        expired.sort_by_key(|order| order.expires_at);
+        self.prune_brackets();
+        self.end_mutation();
+        expired
+    }
+    
+    /// Gets the earliest expiry among resting good-till-date orders
+    pub fn next_expiry(&self) -> Option<DateTime<Utc>> {
+        self.expiries.keys().next().copied()
+    }
+    
+    /// Drops an order from the expiry index once it leaves the book
+    pub(super) fn untrack_expiry(&mut self, order_id: OrderId, expires_at: DateTime<Utc>) {
+        if let Some(order_ids) = self.expiries.get_mut(&expires_at) {
+            order_ids.retain(|id| *id != order_id);
+            if order_ids.is_empty() {
+                self.expiries.remove(&expires_at);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        events::OrderEvent,
+        Clock, LimitOrderBook, OrderSide, OrderStatus,
+    };
+    use chrono::{Duration, Utc};
+    use crate::order_book::test_support::create_test_order;
+
+    #[test]
+    fn test_purge_removes_expired_orders_and_empty_levels() {
+        let mut book = LimitOrderBook::new("GTD".to_string()).unwrap();
+        let now = Utc::now();
+        let early = create_test_order(OrderSide::Buy, 10000, 100).with_expiry(now + Duration::hours(1));
+        let late = create_test_order(OrderSide::Buy, 9900, 100).with_expiry(now + Duration::hours(2));
+        let (early_id, late_id) = (early.id, late.id);
+        book.add_order(early).unwrap();
+        book.add_order(late).unwrap();
+        book.add_order(create_test_order(OrderSide::Buy, 9900, 50)).unwrap();
+        assert_eq!(book.next_expiry(), Some(now + Duration::hours(1)));
+        
+        assert!(book.purge_expired(now).is_empty());
+        
+        let expired = book.purge_expired(now + Duration::minutes(90));
+        assert_eq!(expired.len(), 1);
+        assert_eq!((expired[0].id, expired[0].status), (early_id, OrderStatus::Expired));
+        assert!(book.get_order(early_id).is_none());
+        assert_eq!(book.best_bid().unwrap().as_cents(), 9900);
+        
+        let expired = book.purge_expired(now + Duration::days(1));
+        assert_eq!(expired[0].id, late_id);
+        assert_eq!(book.best_bid_quantity().unwrap().value(), 50);
+        assert_eq!(book.next_expiry(), None);
+        assert_eq!(book.order_count(), 1);
+    }
+
+    #[test]
+    fn test_expired_orders_stop_matching_before_purge() {
+        let start = Utc::now();
+        let mut book = LimitOrderBook::with_clock("GTD".to_string(), Clock::Fixed(start)).unwrap();
+        let short = create_test_order(OrderSide::Sell, 10000, 100).with_expiry(start + Duration::milliseconds(20));
+        let short_id = short.id;
+        book.add_order(short).unwrap();
+        book.add_order(create_test_order(OrderSide::Sell, 10100, 100)).unwrap();
+        assert_eq!(book.market_depth(5).asks.len(), 2);
+        
+        book.set_clock(Clock::Fixed(start + Duration::milliseconds(20)));
+        
+        // Depth hides the expired order even though it is still resting
+        let depth = book.market_depth(5);
+        assert_eq!(depth.asks.len(), 1);
+        assert_eq!(depth.asks[0].price.as_cents(), 10100);
+        
+        // Matching expires it rather than trading with it
+        let result = book.submit_order(create_test_order(OrderSide::Buy, 10100, 50)).unwrap();
+        assert!(matches!(&result.events[..], [OrderEvent::Expired { order }] if order.id == short_id));
+        assert_eq!(result.trades[0].price.as_cents(), 10100);
+        assert!(book.get_order(short_id).is_none());
+        assert_eq!(book.next_expiry(), None);
+    }
+
+    #[test]
+    fn test_cancel_and_fill_clear_expiry_index() {
+        let mut book = LimitOrderBook::new("GTD".to_string()).unwrap();
+        let expiry = Utc::now() + Duration::hours(1);
+        let cancelled = create_test_order(OrderSide::Buy, 10000, 100).with_expiry(expiry);
+        let cancelled_id = cancelled.id;
+        book.add_order(cancelled).unwrap();
+        book.add_order(create_test_order(OrderSide::Sell, 10100, 100).with_expiry(expiry)).unwrap();
+        
+        book.cancel_order(cancelled_id).unwrap();
+        book.add_order(create_test_order(OrderSide::Buy, 10100, 100)).unwrap();
+        assert_eq!(book.next_expiry(), None);
+        
+        // Already-expired orders are expired on entry
+        let stale = create_test_order(OrderSide::Buy, 10000, 100).with_expiry(Utc::now());
+        let result = book.submit_order(stale).unwrap();
+        assert_eq!(result.status, OrderStatus::Expired);
+        assert!(book.is_empty());
+    }
+}
@@ -0,0 +1,148 @@
+//! Book sequence numbers and the depth delta feed
+
+use super::{levels::LevelOrders, LimitOrderBook};
+use crate::{
+    recovery::{BookDelta, DepthUpdate, RecoverySnapshot},
+    OrderSide, Price, Quantity,
+};
+
+/// Levels touched by the mutation in progress and deltas awaiting pickup
+#[derive(Debug, Clone, Default)]
+pub(super) struct DeltaFeed {
+    touched: Vec<(OrderSide, Price)>,
+    pending: Vec<BookDelta>,
+}
+
+impl LimitOrderBook {
+    /// Gets the sequence number of the last applied mutation
+    pub fn sequence(&self) -> u64 {
+        self.sequence
+    }
+    
+    /// Starts recording a [`BookDelta`] for every subsequent mutation
+    pub fn enable_delta_feed(&mut self) {
+        if self.delta_feed.is_none() {
+            self.delta_feed = Some(DeltaFeed::default());
+        }
+    }
+    
+    /// Stops recording deltas and discards any not yet taken
+    pub fn disable_delta_feed(&mut self) {
+        self.delta_feed = None;
+    }
+    
+    /// Takes the deltas recorded since the last call, oldest first
+    pub fn take_deltas(&mut self) -> Vec<BookDelta> {
+        self.delta_feed
+            .as_mut()
+            .map(|feed| std::mem::take(&mut feed.pending))
+            .unwrap_or_default()
+    }
+    
+    /// Captures the full aggregated depth together with its sequence number
+    pub fn recovery_snapshot(&self) -> RecoverySnapshot {
+        RecoverySnapshot {
+            symbol: self.symbol.clone(),
+            sequence: self.sequence,
+            depth: self.market_depth(usize::MAX),
+        }
+    }
+    
+    // === Mutation bookkeeping ===
+    
+    /// Starts a mutation, returning its sequence number
+    pub(super) fn begin_mutation(&mut self) -> u64 {
+        self.sequence += 1;
+        self.sequence
+    }
+    
+    /// Records that a price level changed during the current mutation
+    pub(super) fn touch_level(&mut self, side: OrderSide, price: Price) {
+        self.depth_cache.touch(side, price);
+        self.snapshot_cache.touch(side, price);
+        self.listener.touch(side, price);
+        match side {
+            OrderSide::Buy => self.bids.touch(price),
+            OrderSide::Sell => self.asks.touch(price),
+        }
+        if let Some(feed) = self.delta_feed.as_mut() {
+            feed.touched.push((side, price));
+        }
+    }
+    
+    /// Finishes a mutation, emitting its delta if the feed is enabled
+    pub(super) fn end_mutation(&mut self) {
+        self.settle_level_totals();
+        self.rebalance_tiers();
+        self.debug_assert_uncrossed();
+        self.listener.flush_levels();
+        self.publish_bbo();
+        
+        let Some(mut touched) = self.delta_feed.as_mut().map(|feed| std::mem::take(&mut feed.touched)) else {
+            return;
+        };
+        
+        // Report each level once, in the order it was first touched
+        let mut seen = std::collections::HashSet::new();
+        touched.retain(|level| seen.insert(*level));
+        
+        let sequence = self.sequence;
+        let updates = touched.into_iter()
+            .map(|(side, price)| {
+                let totals = match side {
+                    OrderSide::Buy => self.bids.get(&price),
+                    OrderSide::Sell => self.asks.get(&price),
+                }.as_ref().map(LevelOrders::totals).unwrap_or_default();
+                DepthUpdate {
+                    side,
+                    price,
+                    quantity: Quantity::new_allow_zero(totals.displayed_quantity),
+                    order_count: totals.displayed_orders,
+                    sequence,
+                    quantity_scale: self.quantity_scale,
+                }
+            })
+            .collect();
+        
+        if let Some(feed) = self.delta_feed.as_mut() {
+            feed.pending.push(BookDelta { sequence, updates });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{LimitOrderBook, OrderSide};
+    use crate::order_book::test_support::create_test_order;
+
+    #[test]
+    fn test_deltas_and_trades_carry_sequence() {
+        let mut book = LimitOrderBook::new("SEQ".to_string()).unwrap();
+        book.enable_delta_feed();
+        
+        let resting = create_test_order(OrderSide::Sell, 10000, 100);
+        let resting_id = resting.id;
+        book.add_order(resting).unwrap();
+        book.add_order(create_test_order(OrderSide::Sell, 10100, 100)).unwrap();
+        let trades = book.add_order(create_test_order(OrderSide::Buy, 10000, 40)).unwrap();
+        book.cancel_order(resting_id).unwrap();
+        assert!(book.cancel_order(resting_id).is_err()); // Failed cancels don't advance the sequence
+        
+        assert_eq!(book.sequence(), 4);
+        assert_eq!(trades[0].sequence, 3);
+        
+        let deltas = book.take_deltas();
+        let sequences: Vec<u64> = deltas.iter().map(|d| d.sequence).collect();
+        assert_eq!(sequences, vec![1, 2, 3, 4]);
+        
+        // The fully filled buy only changed the ask level it traded against
+        assert_eq!(deltas[2].updates.len(), 1);
+        assert_eq!(deltas[2].updates[0].quantity.value(), 60);
+        // Cancelling the last order at a level removes it
+        assert_eq!(deltas[3].updates[0].quantity.value(), 0);
+        assert_eq!(deltas[3].updates[0].order_count, 0);
+        
+        assert!(book.take_deltas().is_empty());
+        assert_eq!(book.recovery_snapshot().sequence, 4);
+    }
+}
@@ -0,0 +1,201 @@
+//! Maker/taker fees
+//!
+//! With a fee schedule configured, each trade carries the fee charged to its
+//! maker (the resting order) and its taker (the incoming order), computed on
+//! the trade's notional (price × quantity) in basis points and rounded to the
+//! schedule's precision. Per-user overrides replace both rates for that user.
+//! Fees charged accumulate per user until reset, for billing.
+
+use super::{LimitOrderBook, OrderBookOp};
+use crate::types::UserId;
+use rust_decimal::{Decimal, RoundingStrategy};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// How fees are rounded to the schedule's precision
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum FeeRounding {
+    /// Midpoints round away from zero
+    #[default]
+    HalfUp,
+    /// Midpoints round to the nearest even digit (banker's rounding)
+    HalfEven,
+}
+
+/// Maker and taker rates in basis points of notional
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FeeRates {
+    pub maker_bps: Decimal,
+    pub taker_bps: Decimal,
+}
+
+/// Fees charged on every trade of a book
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FeeSchedule {
+    /// Rates for users without an override
+    pub rates: FeeRates,
+    /// Rates replacing the defaults for specific users
+    pub overrides: HashMap<UserId, FeeRates>,
+    pub rounding: FeeRounding,
+    /// Number of decimal places fees are rounded to
+    pub decimal_places: u32,
+}
+
+impl FeeSchedule {
+    /// Creates a schedule charging the given rates, rounded half-up to cents
+    pub fn new(maker_bps: Decimal, taker_bps: Decimal) -> Self {
+        Self {
+            rates: FeeRates { maker_bps, taker_bps },
+            overrides: HashMap::new(),
+            rounding: FeeRounding::HalfUp,
+            decimal_places: 2,
+        }
+    }
+
+    /// Charges a user its own rates instead of the defaults
+    pub fn with_override(mut self, user_id: UserId, maker_bps: Decimal, taker_bps: Decimal) -> Self {
+        self.overrides.insert(user_id, FeeRates { maker_bps, taker_bps });
+        self
+    }
+
+    /// Sets how fees are rounded, and to how many decimal places
+    pub fn with_rounding(mut self, rounding: FeeRounding, decimal_places: u32) -> Self {
+        self.rounding = rounding;
+        self.decimal_places = decimal_places;
+        self
+    }
+
+    /// Gets the rates that apply to a user
+    pub fn rates_for(&self, user_id: &UserId) -> FeeRates {
+        self.overrides.get(user_id).copied().unwrap_or(self.rates)
+    }
+
+    /// Computes the fee at `bps` on `notional`
+    ///
+    /// A zero rate yields exactly zero.
+    pub fn fee(&self, notional: Decimal, bps: Decimal) -> Decimal {
+        if bps.is_zero() {
+            return Decimal::ZERO;
+        }
+        let strategy = match self.rounding {
+            FeeRounding::HalfUp => RoundingStrategy::MidpointAwayFromZero,
+            FeeRounding::HalfEven => RoundingStrategy::MidpointNearestEven,
+        };
+        (notional * bps / Decimal::from(10_000)).round_dp_with_strategy(self.decimal_places, strategy)
+    }
+}
+
+impl LimitOrderBook {
+    /// Gets the fee schedule
+    pub fn fee_schedule(&self) -> Option<&FeeSchedule> {
+        self.fee_schedule.as_ref()
+    }
+
+    /// Sets (`Some`) or removes (`None`) the fee schedule
+    ///
+    /// Applies to trades from the next mutation on; fees already charged are kept.
+    pub fn set_fee_schedule(&mut self, schedule: Option<FeeSchedule>) {
+        self.fee_schedule = schedule;
+    }
+
+    /// Gets the total fees charged to a user since the last reset
+    pub fn fees_charged(&self, user_id: &UserId) -> Decimal {
+        self.fees_charged.get(user_id).copied().unwrap_or(Decimal::ZERO)
+    }
+
+    /// Gets the total fees charged to every user since the last reset
+    pub fn fee_totals(&self) -> &HashMap<UserId, Decimal> {
+        &self.fees_charged
+    }
+
+    /// Clears the per-user fee totals, returning them (e.g. once billed)
+    pub fn reset_fee_totals(&mut self) -> HashMap<UserId, Decimal> {
+        self.apply(OrderBookOp::ResetFeeTotals).fee_totals()
+    }
+
+    /// Adds a committed trade's maker and taker fees to their users' totals
+    pub(super) fn record_fees(&mut self, maker: (&UserId, Decimal), taker: (&UserId, Decimal)) {
+        for (user_id, fee) in [maker, taker] {
+            if !fee.is_zero() {
+                *self.fees_charged.entry(user_id.clone()).or_default() += fee;
+            }
+        }
+    }
+}
+
+/// Computes the `(maker_fee, taker_fee)` of a trade of `notional` between
+/// `(maker, taker)`, both zero without a schedule
+pub(super) fn trade_fees(
+    schedule: Option<&FeeSchedule>,
+    notional: Decimal,
+    (maker, taker): (&UserId, &UserId),
+) -> (Decimal, Decimal) {
+    let Some(schedule) = schedule else {
+        return (Decimal::ZERO, Decimal::ZERO);
+    };
+    let maker_fee = schedule.fee(notional, schedule.rates_for(maker).maker_bps);
+    let taker_fee = schedule.fee(notional, schedule.rates_for(taker).taker_bps);
+    (maker_fee, taker_fee)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::OrderSide;
+    use crate::order_book::test_support::create_order;
+
+    fn dec(value: &str) -> Decimal {
+        value.parse().unwrap()
+    }
+
+    fn user(name: &str) -> UserId {
+        UserId::new(name.to_string())
+    }
+
+    #[test]
+    fn test_fees_charged_on_notional_with_overrides() {
+        let mut book = LimitOrderBook::new("FEES".to_string()).unwrap();
+        book.set_fee_schedule(Some(
+            FeeSchedule::new(dec("1"), dec("3")).with_override(user("vip"), dec("0"), dec("1.5")),
+        ));
+        book.add_order(create_order(OrderSide::Sell, 10000, 100, "maker")).unwrap();
+        book.add_order(create_order(OrderSide::Sell, 10000, 100, "vip")).unwrap();
+
+        // 150 @ 100.00 against maker (100) then vip (50)
+        let trades = book.add_order(create_order(OrderSide::Buy, 10000, 150, "taker")).unwrap();
+        assert_eq!((trades[0].maker_fee, trades[0].taker_fee), (dec("1.00"), dec("3.00")));
+        assert_eq!((trades[1].maker_fee, trades[1].taker_fee), (Decimal::ZERO, dec("1.50")));
+        assert_eq!(book.fees_charged(&user("maker")), dec("1.00"));
+        assert_eq!(book.fees_charged(&user("taker")), dec("4.50"));
+        assert_eq!(book.fees_charged(&user("vip")), Decimal::ZERO);
+
+        // vip pays its own taker rate
+        let trades = book.add_order(create_order(OrderSide::Buy, 10000, 50, "vip")).unwrap();
+        assert_eq!(trades[0].taker_fee, dec("0.75"));
+
+        let totals = book.reset_fee_totals();
+        assert_eq!(totals.len(), 3);
+        assert!(book.fee_totals().is_empty());
+    }
+
+    #[test]
+    fn test_rounding_rule() {
+        // 0.5 bps of 1 × 50.00 is 0.0025: a midpoint at 3 decimal places
+        let half_up = FeeSchedule::new(dec("0.5"), dec("0.5")).with_rounding(FeeRounding::HalfUp, 3);
+        let half_even = FeeSchedule::new(dec("0.5"), dec("0.5")).with_rounding(FeeRounding::HalfEven, 3);
+        assert_eq!(half_up.fee(dec("50"), dec("0.5")), dec("0.003"));
+        assert_eq!(half_even.fee(dec("50"), dec("0.5")), dec("0.002"));
+    }
+
+    #[test]
+    fn test_zero_fee_schedule_charges_exact_zeros() {
+        let mut book = LimitOrderBook::new("FEES".to_string()).unwrap();
+        book.set_fee_schedule(Some(FeeSchedule::new(Decimal::ZERO, Decimal::ZERO).with_rounding(FeeRounding::HalfEven, 8)));
+        book.add_order(create_order(OrderSide::Sell, 10001, 333, "maker")).unwrap();
+        let trades = book.add_order(create_order(OrderSide::Buy, 10001, 333, "taker")).unwrap();
+
+        assert_eq!(trades[0].maker_fee.scale(), 0);
+        assert!(trades[0].maker_fee.is_zero() && trades[0].taker_fee.is_zero());
+        assert!(book.fee_totals().is_empty());
+    }
+}
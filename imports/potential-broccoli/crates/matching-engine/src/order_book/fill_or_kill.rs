@@ -0,0 +1,140 @@
+//! Pre-trade liquidity check for fill-or-kill orders
+//!
+//! A FOK order only proceeds to matching when a dry run over the opposing
+//! side shows it would fill in full. The dry run visits resting orders in
+//! exactly the order matching would and stops wherever matching would stop
+//! short: at a self-trade prevention conflict that cancels or decrements
+//! the incoming order, or at a trade that trips the session volume limit
+//! with quantity still unfilled. Inactive and expired orders are skipped, as
+//! matching removes them without trading.
+
+use super::{quantity_scale, stp::StpPolicy, LimitOrderBook};
+use crate::{Order, OrderSide};
+
+impl LimitOrderBook {
+    /// Checks if an incoming order would fill completely against the book as it stands
+    pub(super) fn can_fill_completely(&self, incoming: &Order) -> bool {
+        let levels = match incoming.side {
+            OrderSide::Buy => &self.asks,
+            OrderSide::Sell => &self.bids,
+        };
+        let mut needed = incoming.remaining_quantity.value();
+        let mut usage = self.volume_usage;
+        let now = self.now();
+        
+        for (price, orders) in levels.iter() {
+            let crosses = match incoming.side {
+                OrderSide::Buy => incoming.price >= *price,
+                OrderSide::Sell => incoming.price <= *price,
+            };
+            if !crosses {
+                return false;
+            }
+            
+            for resting in orders.iter().filter(|order| order.is_active() && !order.is_expired_at(now)) {
+                if let Some(stp) = self.self_trade_prevention {
+                    if stp.scope.applies(&self.accounts, &incoming.user_id, &resting.user_id) {
+                        if stp.policy == StpPolicy::CancelResting {
+                            continue;
+                        }
+                        return false;
+                    }
+                }
+                
+                let quantity = needed.min(resting.remaining_quantity.value());
+                needed -= quantity;
+                if needed == 0 {
+                    return true;
+                }
+                
+                usage.traded_quantity += quantity;
+                usage.traded_notional += quantity_scale::notional(*price, quantity, self.quantity_scale);
+                if let Some(limit) = self.volume_limit {
+                    if !usage.breached && limit.is_reached_by(&usage) {
+                        return false;
+                    }
+                }
+            }
+        }
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        events::{CancelReason, OrderEvent},
+        LimitOrderBook, Order, OrderSide, OrderStatus, Price, SelfTradePrevention, StpPolicy, StpScope,
+        TimeInForce,
+    };
+    use crate::order_book::test_support::create_order;
+
+    fn fok(side: OrderSide, price_cents: i64, quantity: u64, user: &str) -> Order {
+        create_order(side, price_cents, quantity, user).with_time_in_force(TimeInForce::FOK)
+    }
+
+    /// Asks of 100 at 100.00 and 100.01, 100 at 100.05
+    fn book() -> LimitOrderBook {
+        let mut book = LimitOrderBook::new("FOK".to_string()).unwrap();
+        book.add_order(create_order(OrderSide::Sell, 10000, 100, "mm1")).unwrap();
+        book.add_order(create_order(OrderSide::Sell, 10001, 100, "mm2")).unwrap();
+        book.add_order(create_order(OrderSide::Sell, 10005, 100, "mm1")).unwrap();
+        book
+    }
+
+    #[test]
+    fn test_fok_fills_in_full_across_levels() {
+        let mut book = book();
+        let result = book.submit_order(fok(OrderSide::Buy, 10001, 200, "taker")).unwrap();
+        assert_eq!(result.status, OrderStatus::Filled);
+        assert_eq!(result.trades.len(), 2);
+        assert_eq!(book.best_ask().unwrap().as_cents(), 10005);
+    }
+
+    #[test]
+    fn test_fok_without_enough_crossing_liquidity_is_killed() {
+        let mut book = book();
+        let before = book.market_depth(10);
+        
+        // 300 is resting, but only 200 at or below the limit
+        let result = book.submit_order(fok(OrderSide::Buy, 10001, 201, "taker")).unwrap();
+        assert!(result.trades.is_empty());
+        assert_eq!(result.status, OrderStatus::Cancelled);
+        assert!(matches!(&result.events[..], [OrderEvent::Cancelled { reason: CancelReason::Unfilled, .. }]));
+        let after = book.market_depth(10);
+        assert_eq!((after.bids, after.asks), (before.bids, before.asks));
+        assert_eq!(book.order_count(), 3);
+    }
+
+    #[test]
+    fn test_inactive_head_order_is_skipped() {
+        let mut book = book();
+        
+        // Leave a cancelled order at the head of the 100.00 queue
+        book.asks.front_mut(&Price::from_cents(10000).unwrap()).unwrap().cancel();
+        
+        assert!(book.submit_order(fok(OrderSide::Buy, 10001, 101, "taker")).unwrap().trades.is_empty());
+        let result = book.submit_order(fok(OrderSide::Buy, 10001, 100, "taker")).unwrap();
+        assert_eq!(result.status, OrderStatus::Filled);
+        assert_eq!(result.trades[0].price.as_cents(), 10001);
+        assert_eq!(book.best_ask().unwrap().as_cents(), 10005);
+    }
+
+    #[test]
+    fn test_stp_conflict_counts_like_matching() {
+        let mut book = book();
+        book.set_self_trade_prevention(Some(SelfTradePrevention::new(StpPolicy::CancelIncoming, StpScope::User)));
+        
+        // mm1's own 100.00 ask would cancel the incoming order before any fill
+        let result = book.submit_order(fok(OrderSide::Buy, 10005, 100, "mm1")).unwrap();
+        assert_eq!(result.status, OrderStatus::Cancelled);
+        assert_eq!(book.order_count(), 3);
+        
+        // Cancelling the resting side frees the rest of the book for mm1
+        book.set_self_trade_prevention(Some(SelfTradePrevention::new(StpPolicy::CancelResting, StpScope::User)));
+        assert!(book.submit_order(fok(OrderSide::Buy, 10005, 150, "mm1")).unwrap().trades.is_empty());
+        let result = book.submit_order(fok(OrderSide::Buy, 10005, 100, "mm1")).unwrap();
+        assert_eq!(result.status, OrderStatus::Filled);
+        assert_eq!(result.trades[0].price.as_cents(), 10001);
+    }
+}
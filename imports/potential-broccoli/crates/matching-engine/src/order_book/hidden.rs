@@ -0,0 +1,97 @@
+//! Hidden (non-displayed) orders
+//!
+//! A hidden order matches like any other but never shows in quoted prices,
+//! top-of-book quantities, depth, or the delta feed. A level holding only
+//! hidden orders still exists for matching but is invisible to quotes, so
+//! an aggressor can trade at a price the book did not show. At each price,
+//! displayed orders keep time priority among themselves and always trade
+//! ahead of hidden ones, whenever the hidden orders arrived.
+
+use super::{levels::LevelMut, slab::OrderHandle, LimitOrderBook};
+use crate::{Order, OrderSide, Price};
+
+impl LimitOrderBook {
+    /// Gets the best price on a side with at least one displayed order
+    pub(super) fn best_displayed(&self, side: OrderSide) -> Option<Price> {
+        let levels = match side {
+            OrderSide::Buy => &self.bids,
+            OrderSide::Sell => &self.asks,
+        };
+        levels.iter()
+            .find(|(_, orders)| orders.iter().any(|order| !order.hidden))
+            .map(|(price, _)| *price)
+    }
+}
+
+/// Queues an order at its level: displayed orders ahead of every hidden one
+pub(super) fn enqueue(mut level: LevelMut<'_>, order: Order) -> OrderHandle {
+    if order.hidden {
+        level.push_back(order)
+    } else {
+        // Hidden orders sit at the tail, so walk back from there to the first of them
+        let first_hidden = level.orders().entries().rev()
+            .take_while(|(_, resting)| resting.hidden)
+            .last()
+            .map(|(handle, _)| handle);
+        level.insert_before(first_hidden, order)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        types::OrderId,
+        LimitOrderBook, OrderSide,
+    };
+    use crate::order_book::test_support::create_test_order;
+
+    #[test]
+    fn test_hidden_order_trades_against_empty_quote() {
+        let mut book = LimitOrderBook::new("HIDE".to_string()).unwrap();
+        let hidden = create_test_order(OrderSide::Sell, 10000, 100).with_hidden();
+        let hidden_id = hidden.id;
+        book.add_order(hidden).unwrap();
+        
+        assert!(book.best_ask().is_none());
+        assert!(book.best_ask_quantity().is_none());
+        assert!(book.market_depth(10).asks.is_empty());
+        assert_eq!(book.order_count(), 1);
+        
+        let trades = book.add_order(create_test_order(OrderSide::Buy, 10000, 40)).unwrap();
+        assert_eq!(trades.len(), 1);
+        assert_eq!(trades[0].sell_order_id, hidden_id);
+        assert_eq!(book.get_order(hidden_id).unwrap().remaining_quantity.value(), 60);
+    }
+
+    #[test]
+    fn test_displayed_orders_trade_ahead_of_earlier_hidden() {
+        let mut book = LimitOrderBook::new("HIDE".to_string()).unwrap();
+        let hidden = create_test_order(OrderSide::Buy, 10000, 100).with_hidden();
+        let hidden_id = hidden.id;
+        let displayed = create_test_order(OrderSide::Buy, 10000, 30);
+        let displayed_id = displayed.id;
+        book.add_order(hidden).unwrap();
+        book.add_order(displayed).unwrap();
+        
+        assert_eq!(book.best_bid().unwrap().as_cents(), 10000);
+        assert_eq!(book.best_bid_quantity().unwrap().value(), 30);
+        assert_eq!(book.market_depth(10).bids[0].order_count, 1);
+        
+        let trades = book.add_order(create_test_order(OrderSide::Sell, 10000, 50)).unwrap();
+        let fills: Vec<(OrderId, u64)> = trades.iter().map(|trade| (trade.buy_order_id, trade.quantity.value())).collect();
+        assert_eq!(fills, vec![(displayed_id, 30), (hidden_id, 20)]);
+    }
+
+    #[test]
+    fn test_hidden_only_level_is_skipped_by_quotes() {
+        let mut book = LimitOrderBook::new("HIDE".to_string()).unwrap();
+        book.add_order(create_test_order(OrderSide::Sell, 9900, 50).with_hidden()).unwrap();
+        book.add_order(create_test_order(OrderSide::Sell, 10000, 50)).unwrap();
+        
+        // The quote shows 100.00, but a buy there trades the hidden 99.00 first
+        assert_eq!(book.best_ask().unwrap().as_cents(), 10000);
+        let trades = book.add_order(create_test_order(OrderSide::Buy, 10000, 60)).unwrap();
+        let fills: Vec<(i64, u64)> = trades.iter().map(|trade| (trade.price.as_cents(), trade.quantity.value())).collect();
+        assert_eq!(fills, vec![(9900, 50), (10000, 10)]);
+    }
+}
@@ -0,0 +1,162 @@
+//! Structural integrity checks
+//!
+//! [`LimitOrderBook::verify_integrity`] cross-checks the price levels against
+//! the order lookup and the state every resting order must be in, reporting
+//! every violation found rather than stopping at the first. It walks each
+//! level once and the lookup once, so it suits checking restored snapshots
+//! and periodic audits, not every mutation.
+
+use super::{levels::LevelTotals, slab::OrderLocation, BookState, LimitOrderBook};
+use crate::{types::OrderId, OrderSide, OrderStatus, Price};
+use std::collections::HashSet;
+use thiserror::Error;
+
+/// A broken structural invariant found by [`LimitOrderBook::verify_integrity`]
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum IntegrityError {
+    #[error("Lookup places order {order_id} at {side:?} {price}, but it isn't queued there")]
+    DanglingLookup { order_id: OrderId, side: OrderSide, price: Price },
+
+    #[error("Order {order_id} queued at {side:?} {price} has no matching lookup entry")]
+    MissingLookup { order_id: OrderId, side: OrderSide, price: Price },
+
+    #[error("Order {order_id} is queued more than once")]
+    DuplicateOrder { order_id: OrderId },
+
+    #[error("Empty price level at {side:?} {price}")]
+    EmptyLevel { side: OrderSide, price: Price },
+
+    #[error("Resting order {order_id} has no remaining quantity")]
+    ZeroQuantity { order_id: OrderId },
+
+    #[error("Resting order {order_id} has status {status:?}")]
+    InactiveOrder { order_id: OrderId, status: OrderStatus },
+
+    #[error("Cached totals at {side:?} {price} disagree with the orders queued there")]
+    StaleLevelTotals { side: OrderSide, price: Price },
+
+    #[error("Book is crossed: bid {bid} above ask {ask}")]
+    Crossed { bid: Price, ask: Price },
+}
+
+impl LimitOrderBook {
+    /// Checks the book's structural invariants, returning every violation found
+    ///
+    /// Verifies that the order lookup and the price levels agree entry for
+    /// entry, that no level is empty, that each level's cached totals match
+    /// a fresh count of its queue, that every resting order is active with
+    /// quantity remaining, and, outside the pre-open auction, that the book
+    /// isn't crossed.
+    pub fn verify_integrity(&self) -> Result<(), Vec<IntegrityError>> {
+        let mut errors = Vec::new();
+        let mut queued = HashSet::new();
+
+        for (side, levels) in [(OrderSide::Buy, &self.bids), (OrderSide::Sell, &self.asks)] {
+            for (&price, orders) in levels.iter() {
+                if orders.is_empty() {
+                    errors.push(IntegrityError::EmptyLevel { side, price });
+                }
+                if orders.totals() != LevelTotals::of(orders.iter()) {
+                    errors.push(IntegrityError::StaleLevelTotals { side, price });
+                }
+                for (handle, order) in orders.entries() {
+                    if !queued.insert(order.id) {
+                        errors.push(IntegrityError::DuplicateOrder { order_id: order.id });
+                    }
+                    if self.orders.get(&order.id) != Some(&OrderLocation { side, price, handle }) {
+                        errors.push(IntegrityError::MissingLookup { order_id: order.id, side, price });
+                    }
+                    if order.remaining_quantity.value() == 0 {
+                        errors.push(IntegrityError::ZeroQuantity { order_id: order.id });
+                    }
+                    if !order.is_active() {
+                        errors.push(IntegrityError::InactiveOrder { order_id: order.id, status: order.status });
+                    }
+                }
+            }
+        }
+
+        for (&order_id, &OrderLocation { side, price, handle }) in &self.orders {
+            let present = match side {
+                OrderSide::Buy => self.bids.get(&price),
+                OrderSide::Sell => self.asks.get(&price),
+            }.is_some_and(|orders| orders.entries().any(|(queued, order)| queued == handle && order.id == order_id));
+            if !present {
+                errors.push(IntegrityError::DanglingLookup { order_id, side, price });
+            }
+        }
+
+        if let (Some(bid), Some(ask)) = (self.best_live_price(OrderSide::Buy), self.best_live_price(OrderSide::Sell)) {
+            if bid > ask && self.state != BookState::PreOpen {
+                errors.push(IntegrityError::Crossed { bid, ask });
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    use crate::order_book::test_support::create_test_order;
+
+    fn price(cents: i64) -> Price {
+        Price::from_cents(cents).unwrap()
+    }
+
+    #[test]
+    fn test_healthy_book_and_restored_snapshot_pass() {
+        let mut book = LimitOrderBook::new("INTEG".to_string()).unwrap();
+        book.add_order(create_test_order(OrderSide::Buy, 9900, 100)).unwrap();
+        book.add_order(create_test_order(OrderSide::Sell, 10100, 100).with_hidden()).unwrap();
+        book.add_order(create_test_order(OrderSide::Buy, 10100, 40)).unwrap();
+        assert_eq!(book.verify_integrity(), Ok(()));
+
+        let restored: LimitOrderBook = serde_json::from_str(&serde_json::to_string(&book).unwrap()).unwrap();
+        assert_eq!(restored.verify_integrity(), Ok(()));
+    }
+
+    #[test]
+    fn test_every_violation_is_reported() {
+        let mut book = LimitOrderBook::new("INTEG".to_string()).unwrap();
+        let orders = [
+            create_test_order(OrderSide::Buy, 9900, 100),
+            create_test_order(OrderSide::Buy, 9800, 100),
+            create_test_order(OrderSide::Sell, 10100, 100),
+            create_test_order(OrderSide::Sell, 10200, 100),
+        ];
+        let ids: Vec<OrderId> = orders.iter().map(|order| order.id).collect();
+        for order in orders {
+            book.add_order(order).unwrap();
+        }
+
+        book.bids.front_mut(&price(9900)).unwrap().cancel();
+        book.orders.remove(&ids[1]);
+        let stray = OrderId::new();
+        let (handle, _) = book.asks.get(&price(10100)).unwrap().entries().next().unwrap();
+        book.orders.insert(stray, OrderLocation { side: OrderSide::Sell, price: price(10100), handle });
+        book.asks.get_mut(&price(10200)).unwrap().extract(|_| true);
+        book.bids.level_mut(price(10500)).unwrap().push_back(create_test_order(OrderSide::Buy, 10500, 5));
+        let crossing = book.bids.get(&price(10500)).unwrap()[0].id;
+
+        let errors = book.verify_integrity().unwrap_err();
+        for expected in [
+            IntegrityError::InactiveOrder { order_id: ids[0], status: OrderStatus::Cancelled },
+            IntegrityError::MissingLookup { order_id: ids[1], side: OrderSide::Buy, price: price(9800) },
+            IntegrityError::MissingLookup { order_id: crossing, side: OrderSide::Buy, price: price(10500) },
+            IntegrityError::DanglingLookup { order_id: stray, side: OrderSide::Sell, price: price(10100) },
+            IntegrityError::DanglingLookup { order_id: ids[3], side: OrderSide::Sell, price: price(10200) },
+            IntegrityError::EmptyLevel { side: OrderSide::Sell, price: price(10200) },
+            IntegrityError::Crossed { bid: price(10500), ask: price(10100) },
+        ] {
+            assert!(errors.contains(&expected), "{expected} not reported in {errors:?}");
+        }
+        assert_eq!(errors.len(), 7);
+    }
+}
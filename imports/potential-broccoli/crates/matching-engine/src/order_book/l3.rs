@@ -0,0 +1,137 @@
+//! Level-3 (per-order) depth snapshots
+//!
+//! Each side lists its orders in exact matching priority: levels from the
+//! touch outward, and within a level the queue order, displayed orders ahead
+//! of hidden ones. An aggressor walking the entries in order fills against
+//! them exactly as it would against the book. Inactive and expired orders,
+//! which matching discards without trading, are never listed.
+
+use super::LimitOrderBook;
+use crate::{
+    types::{OrderId, UserId},
+    Order, Price, Quantity,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// What an L3 snapshot reveals beyond displayed orders
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct L3Detail {
+    /// List hidden orders in their queue position
+    pub include_hidden: bool,
+    /// Attach the owning user to each entry
+    pub include_user_ids: bool,
+}
+
+/// One resting order in an L3 snapshot
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct L3Entry {
+    pub price: Price,
+    pub order_id: OrderId,
+    pub remaining_quantity: Quantity,
+    pub created_at: DateTime<Utc>,
+    /// Owning user, only when requested
+    pub user_id: Option<UserId>,
+}
+
+/// Per-order depth on both sides, in matching priority
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct L3Snapshot {
+    pub bids: Vec<L3Entry>,
+    pub asks: Vec<L3Entry>,
+    /// Book sequence number the snapshot was taken at
+    pub sequence: u64,
+}
+
+impl LimitOrderBook {
+    /// Captures up to `max_orders_per_side` displayed orders per side, without user IDs
+    pub fn l3_snapshot(&self, max_orders_per_side: usize) -> L3Snapshot {
+        self.l3_snapshot_with(max_orders_per_side, L3Detail::default())
+    }
+
+    /// Captures up to `max_orders_per_side` orders per side at the given detail
+    pub fn l3_snapshot_with(&self, max_orders_per_side: usize, detail: L3Detail) -> L3Snapshot {
+        let now = self.now();
+        let entries = |levels: &super::PriceLevels| -> Vec<L3Entry> {
+            levels.iter()
+                .flat_map(|(price, orders)| orders.iter().map(move |order| (*price, order)))
+                .filter(|(_, order)| order.is_active() && !order.is_expired_at(now))
+                .filter(|(_, order)| detail.include_hidden || !order.hidden)
+                .take(max_orders_per_side)
+                .map(|(price, order)| L3Entry::new(price, order, detail))
+                .collect()
+        };
+
+        L3Snapshot {
+            bids: entries(&self.bids),
+            asks: entries(&self.asks),
+            sequence: self.sequence,
+        }
+    }
+}
+
+impl L3Entry {
+    fn new(price: Price, order: &Order, detail: L3Detail) -> Self {
+        Self {
+            price,
+            order_id: order.id,
+            remaining_quantity: order.remaining_quantity,
+            created_at: order.created_at,
+            user_id: detail.include_user_ids.then(|| order.user_id.clone()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::OrderSide;
+    use crate::order_book::test_support::create_order;
+
+    #[test]
+    fn test_l3_order_matches_sweep_fills() {
+        let mut book = LimitOrderBook::new("L3".to_string()).unwrap();
+        book.add_order(create_order(OrderSide::Sell, 10100, 30, "mm1")).unwrap();
+        book.add_order(create_order(OrderSide::Sell, 10000, 20, "mm2").with_hidden()).unwrap();
+        book.add_order(create_order(OrderSide::Sell, 10000, 40, "mm1")).unwrap();
+        book.add_order(create_order(OrderSide::Sell, 10000, 10, "mm3")).unwrap();
+        book.add_order(create_order(OrderSide::Sell, 10200, 50, "mm2")).unwrap();
+        let detail = L3Detail { include_hidden: true, include_user_ids: true };
+        let snapshot = book.l3_snapshot_with(10, detail);
+
+        let sweep = Order::market(OrderId::new(), UserId::new("taker".to_string()), OrderSide::Buy, Quantity::new(150).unwrap());
+        let trades = book.add_order(sweep).unwrap();
+        let fills: Vec<(OrderId, u64)> = trades.iter().map(|trade| (trade.sell_order_id, trade.quantity.value())).collect();
+        let listed: Vec<(OrderId, u64)> = snapshot.asks.iter()
+            .map(|entry| (entry.order_id, entry.remaining_quantity.value()))
+            .collect();
+        assert_eq!(fills, listed);
+        assert_eq!(snapshot.asks[2].user_id, Some(UserId::new("mm2".to_string())));
+    }
+
+    #[test]
+    fn test_hidden_inactive_and_excess_orders_are_left_out() {
+        let mut book = LimitOrderBook::new("L3".to_string()).unwrap();
+        let bids = [
+            create_order(OrderSide::Buy, 9900, 10, "a"),
+            create_order(OrderSide::Buy, 9900, 10, "b").with_hidden(),
+            create_order(OrderSide::Buy, 9800, 10, "c"),
+            create_order(OrderSide::Buy, 9700, 10, "d"),
+        ];
+        let ids: Vec<OrderId> = bids.iter().map(|order| order.id).collect();
+        for order in bids {
+            book.add_order(order).unwrap();
+        }
+        book.bids.front_mut(&Price::from_cents(9800).unwrap()).unwrap().cancel();
+
+        let snapshot = book.l3_snapshot(10);
+        let listed: Vec<OrderId> = snapshot.bids.iter().map(|entry| entry.order_id).collect();
+        assert_eq!(listed, vec![ids[0], ids[3]]);
+        assert!(snapshot.bids.iter().all(|entry| entry.user_id.is_none()));
+        assert!(snapshot.asks.is_empty());
+
+        let detail = L3Detail { include_hidden: true, ..L3Detail::default() };
+        let listed: Vec<OrderId> = book.l3_snapshot_with(2, detail).bids.iter().map(|entry| entry.order_id).collect();
+        assert_eq!(listed, vec![ids[0], ids[1]]);
+    }
+}
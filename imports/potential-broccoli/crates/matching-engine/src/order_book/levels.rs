@@ -0,0 +1,1243 @@
+//! Price level storage with an optional hot/cold split
+//!
+//! Each side of the book keeps its levels in two sorted maps. The hot map
+//! holds the levels nearest the touch and serves almost every operation; the
+//! cold map holds everything deeper. Every hot level is strictly better than
+//! every cold level, so the two maps concatenated in touch order are exactly
+//! the untiered side. With tiering disabled the cold map stays empty.
+//!
+//! A level is a FIFO queue linked through the side's order slab (see the
+//! slab module): matching takes fills from the front and new orders join
+//! at the back, both in O(1). The order lookup gives each resting order's
+//! slot, so a cancel unlinks it in O(1) too, however deep in the queue it
+//! sits, and the orders behind it keep their priority.
+//!
+//! Lookups fall through to the cold map, so correctness never depends on
+//! where a level currently lives. [`PriceLevels::rebalance`] runs at the end
+//! of each mutation and only moves levels between tiers: it demotes the
+//! deepest hot levels once the hot map grows to twice its target size, and
+//! promotes cold levels when the market has moved toward them.
+//!
+//! The maps are keyed by the level's price as a whole number of ticks at
+//! the book's price scale (cents unless the book asks for another), so
+//! finding a level compares integers rather than decimals. Prices cross
+//! this boundary in both directions: a price is converted to its tick
+//! count on the way in, and each level keeps its price to hand back out.
+//! A price with more decimal places than the scale allows has no level.
+//!
+//! Each level caches the totals of its active orders, adjusted order by order
+//! as they join, fill, and leave, and each side keeps running totals across
+//! its levels: the change in each level touched during a mutation is applied
+//! when it ends. Reading either is O(1). Each side also keeps the totals of
+//! every user's active orders, adjusted as they change, so a position or
+//! reduce-only check reads a user's open quantity without scanning the side.
+
+use super::{price_scale::off_scale, slab::{OrderHandle, OrderSlab}, LimitOrderBook};
+use crate::{price::{DEFAULT_PRICE_SCALE, MAX_PRICE_SCALE}, types::UserId, Order, OrderSide, Price};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::{btree_map, BTreeMap, HashMap, VecDeque};
+use std::iter::{Chain, Rev};
+use std::ops::{Add, Bound, Deref, DerefMut, Index, RangeBounds, RangeInclusive, Sub};
+
+/// Hot/cold level storage settings
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TieredStorage {
+    /// Number of levels per side kept in the hot tier
+    pub hot_levels: usize,
+}
+
+impl TieredStorage {
+    /// Creates tiered storage settings keeping `hot_levels` levels per side hot
+    pub fn new(hot_levels: usize) -> Self {
+        Self { hot_levels: hot_levels.max(1) }
+    }
+}
+
+/// Active resting quantity and order count, in all and displayed only
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub(super) struct LevelTotals {
+    /// Active quantity, hidden orders included
+    pub(super) quantity: u64,
+    /// Active orders, hidden orders included
+    pub(super) orders: usize,
+    pub(super) displayed_quantity: u64,
+    pub(super) displayed_orders: usize,
+}
+
+impl LevelTotals {
+    /// One order's contribution: nothing unless it is active
+    fn of_order(order: &Order) -> Self {
+        if !order.is_active() {
+            return Self::default();
+        }
+        let quantity = order.remaining_quantity.value();
+        let displayed = !order.hidden;
+        Self {
+            quantity,
+            orders: 1,
+            displayed_quantity: if displayed { quantity } else { 0 },
+            displayed_orders: usize::from(displayed),
+        }
+    }
+
+    /// Counts a queue from scratch
+    pub(super) fn of<'a>(orders: impl IntoIterator<Item = &'a Order>) -> Self {
+        orders.into_iter().map(Self::of_order).fold(Self::default(), |totals, order| totals + order)
+    }
+}
+
+impl Add for LevelTotals {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self {
+            quantity: self.quantity + other.quantity,
+            orders: self.orders + other.orders,
+            displayed_quantity: self.displayed_quantity + other.displayed_quantity,
+            displayed_orders: self.displayed_orders + other.displayed_orders,
+        }
+    }
+}
+
+impl Sub for LevelTotals {
+    type Output = Self;
+
+    fn sub(self, other: Self) -> Self {
+        Self {
+            quantity: self.quantity - other.quantity,
+            orders: self.orders - other.orders,
+            displayed_quantity: self.displayed_quantity - other.displayed_quantity,
+            displayed_orders: self.displayed_orders - other.displayed_orders,
+        }
+    }
+}
+
+/// Remaining quantity of one user's orders, in all and reduce-only only
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub(super) struct UserTotals {
+    pub(super) quantity: u64,
+    pub(super) reduce_only_quantity: u64,
+}
+
+impl UserTotals {
+    /// One resting order's contribution: nothing unless it is active
+    fn of_order(order: &Order) -> Self {
+        if !order.is_active() {
+            return Self::default();
+        }
+        Self::of_pending(order)
+    }
+
+    /// One pending order's contribution, whatever its status
+    pub(super) fn of_pending(order: &Order) -> Self {
+        let quantity = order.remaining_quantity.value();
+        Self {
+            quantity,
+            reduce_only_quantity: if order.reduce_only { quantity } else { 0 },
+        }
+    }
+}
+
+impl Add for UserTotals {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self {
+            quantity: self.quantity + other.quantity,
+            reduce_only_quantity: self.reduce_only_quantity + other.reduce_only_quantity,
+        }
+    }
+}
+
+impl Sub for UserTotals {
+    type Output = Self;
+
+    fn sub(self, other: Self) -> Self {
+        Self {
+            quantity: self.quantity - other.quantity,
+            reduce_only_quantity: self.reduce_only_quantity - other.reduce_only_quantity,
+        }
+    }
+}
+
+/// Totals of each user's orders, holding only users with some
+#[derive(Debug, Clone, Default)]
+pub(super) struct UserTotalsMap(HashMap<UserId, UserTotals>);
+
+impl UserTotalsMap {
+    pub(super) fn get(&self, user_id: &UserId) -> UserTotals {
+        self.0.get(user_id).copied().unwrap_or_default()
+    }
+
+    /// Replaces one order's contribution to its user's totals
+    pub(super) fn adjust(&mut self, user_id: &UserId, before: UserTotals, after: UserTotals) {
+        if before == after {
+            return;
+        }
+        let totals = match self.0.get_mut(user_id) {
+            Some(totals) => totals,
+            None => self.0.entry(user_id.clone()).or_default(),
+        };
+        *totals = *totals - before + after;
+        if *totals == UserTotals::default() {
+            self.0.remove(user_id);
+        }
+    }
+
+    fn shrink_to_fit(&mut self) {
+        self.0.shrink_to_fit();
+    }
+}
+
+/// One price level: its price, its FIFO queue, and the totals of its active orders
+///
+/// The queue is a list linked through the side's slab, which holds the
+/// orders themselves; the level keeps its two ends and its length. A level
+/// is read through [`LevelOrders`] and changed through [`LevelMut`], each
+/// pairing it with the slab. Every change goes through a method that keeps
+/// the totals in step, adjusting them by the orders added, removed, or
+/// changed, so reading them is O(1) however deep the level.
+#[derive(Debug, Clone)]
+pub(super) struct PriceLevel {
+    price: Price,
+    head: Option<OrderHandle>,
+    tail: Option<OrderHandle>,
+    len: usize,
+    totals: LevelTotals,
+}
+
+impl PriceLevel {
+    fn new(price: Price) -> Self {
+        Self { price, head: None, tail: None, len: 0, totals: LevelTotals::default() }
+    }
+}
+
+/// The orders queued at one price level, in execution (FIFO) order
+#[derive(Clone, Copy)]
+pub struct LevelOrders<'a> {
+    level: &'a PriceLevel,
+    slab: &'a OrderSlab,
+}
+
+impl<'a> LevelOrders<'a> {
+    /// Number of orders queued, inactive ones not yet purged included
+    pub fn len(&self) -> usize {
+        self.level.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.level.len == 0
+    }
+
+    /// Iterates the queue in execution order
+    pub fn iter(&self) -> LevelIter<'a> {
+        LevelIter { handles: self.handles() }
+    }
+
+    /// Gets the order at `index` in the queue, walking from the front
+    pub fn get(&self, index: usize) -> Option<&'a Order> {
+        self.iter().nth(index)
+    }
+
+    /// Gets the next order to trade
+    pub fn front(&self) -> Option<&'a Order> {
+        self.level.head.map(|handle| &self.slab[handle])
+    }
+
+    /// Price of the level, as written by the order that opened it
+    pub(super) fn price(&self) -> Price {
+        self.level.price
+    }
+
+    /// Totals of the level's active orders
+    pub(super) fn totals(&self) -> LevelTotals {
+        self.level.totals
+    }
+
+    /// Iterates the queue in execution order along with each order's slot
+    pub(super) fn entries(&self) -> impl DoubleEndedIterator<Item = (OrderHandle, &'a Order)> + 'a {
+        let slab = self.slab;
+        self.handles().map(move |handle| (handle, &slab[handle]))
+    }
+
+    fn handles(&self) -> Handles<'a> {
+        Handles { front: self.level.head, back: self.level.tail, remaining: self.level.len, slab: self.slab }
+    }
+}
+
+impl std::fmt::Debug for LevelOrders<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl Index<usize> for LevelOrders<'_> {
+    type Output = Order;
+
+    /// Reads the order at `index`, walking from the front
+    fn index(&self, index: usize) -> &Order {
+        self.get(index).expect("index is within the level")
+    }
+}
+
+impl<'a> IntoIterator for LevelOrders<'a> {
+    type Item = &'a Order;
+    type IntoIter = LevelIter<'a>;
+
+    fn into_iter(self) -> LevelIter<'a> {
+        self.iter()
+    }
+}
+
+impl<'a> IntoIterator for &LevelOrders<'a> {
+    type Item = &'a Order;
+    type IntoIter = LevelIter<'a>;
+
+    fn into_iter(self) -> LevelIter<'a> {
+        self.iter()
+    }
+}
+
+impl Serialize for LevelOrders<'_> {
+    /// Serializes as the bare queue of orders, as before levels linked them
+    /// through a slab; the price is the queue's key in the side's map
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.iter())
+    }
+}
+
+/// Slots of a level's queue, following the links from either end
+#[derive(Debug, Clone)]
+struct Handles<'a> {
+    front: Option<OrderHandle>,
+    back: Option<OrderHandle>,
+    /// Orders between `front` and `back`, both included
+    remaining: usize,
+    slab: &'a OrderSlab,
+}
+
+impl Iterator for Handles<'_> {
+    type Item = OrderHandle;
+
+    fn next(&mut self) -> Option<OrderHandle> {
+        if self.remaining == 0 {
+            return None;
+        }
+        let handle = self.front?;
+        self.remaining -= 1;
+        self.front = self.slab.next(handle);
+        Some(handle)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl DoubleEndedIterator for Handles<'_> {
+    fn next_back(&mut self) -> Option<OrderHandle> {
+        if self.remaining == 0 {
+            return None;
+        }
+        let handle = self.back?;
+        self.remaining -= 1;
+        self.back = self.slab.prev(handle);
+        Some(handle)
+    }
+}
+
+/// Iterator over the orders at one price level, in execution order
+#[derive(Debug, Clone)]
+pub struct LevelIter<'a> {
+    handles: Handles<'a>,
+}
+
+impl<'a> Iterator for LevelIter<'a> {
+    type Item = &'a Order;
+
+    fn next(&mut self) -> Option<&'a Order> {
+        let slab = self.handles.slab;
+        self.handles.next().map(|handle| &slab[handle])
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.handles.size_hint()
+    }
+}
+
+impl DoubleEndedIterator for LevelIter<'_> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let slab = self.handles.slab;
+        self.handles.next_back().map(|handle| &slab[handle])
+    }
+}
+
+impl ExactSizeIterator for LevelIter<'_> {}
+
+/// Mutable access to one price level, adding and removing orders in its side's slab
+pub(super) struct LevelMut<'a> {
+    level: &'a mut PriceLevel,
+    slab: &'a mut OrderSlab,
+    users: &'a mut UserTotalsMap,
+}
+
+impl LevelMut<'_> {
+    pub(super) fn price(&self) -> Price {
+        self.level.price
+    }
+
+    pub(super) fn orders(&self) -> LevelOrders<'_> {
+        LevelOrders { level: self.level, slab: self.slab }
+    }
+
+    pub(super) fn is_empty(&self) -> bool {
+        self.level.len == 0
+    }
+
+    pub(super) fn push_back(&mut self, order: Order) -> OrderHandle {
+        self.insert_before(None, order)
+    }
+
+    /// Queues an order just ahead of the one in slot `next`, or at the back if `None`
+    pub(super) fn insert_before(&mut self, next: Option<OrderHandle>, order: Order) -> OrderHandle {
+        self.level.totals = self.level.totals + LevelTotals::of_order(&order);
+        self.users.adjust(&order.user_id, UserTotals::default(), UserTotals::of_order(&order));
+        let prev = match next {
+            Some(next) => self.slab.prev(next),
+            None => self.level.tail,
+        };
+        let handle = self.slab.insert(order, prev, next);
+        match prev {
+            Some(prev) => self.slab.set_next(prev, Some(handle)),
+            None => self.level.head = Some(handle),
+        }
+        match next {
+            Some(next) => self.slab.set_prev(next, Some(handle)),
+            None => self.level.tail = Some(handle),
+        }
+        self.level.len += 1;
+        handle
+    }
+
+    /// Removes the order in slot `handle`, which must be queued here
+    ///
+    /// Its neighbours are linked to each other in its place, so removal is
+    /// O(1) wherever the order sits in the queue.
+    pub(super) fn remove(&mut self, handle: OrderHandle) -> Option<Order> {
+        let (order, prev, next) = self.slab.remove(handle)?;
+        match prev {
+            Some(prev) => self.slab.set_next(prev, next),
+            None => self.level.head = next,
+        }
+        match next {
+            Some(next) => self.slab.set_prev(next, prev),
+            None => self.level.tail = prev,
+        }
+        self.level.len -= 1;
+        self.level.totals = self.level.totals - LevelTotals::of_order(&order);
+        self.users.adjust(&order.user_id, UserTotals::of_order(&order), UserTotals::default());
+        Some(order)
+    }
+
+    /// Removes every order `filter` accepts, keeping the rest in queue order
+    pub(super) fn extract(&mut self, mut filter: impl FnMut(&Order) -> bool) -> Vec<Order> {
+        let matched: Vec<OrderHandle> = self.orders().entries()
+            .filter(|(_, order)| filter(order))
+            .map(|(handle, _)| handle)
+            .collect();
+        matched.into_iter().filter_map(|handle| self.remove(handle)).collect()
+    }
+
+    /// Gets the order in slot `handle`, which must be queued here
+    #[cfg(test)]
+    pub(super) fn get_mut(&mut self, handle: OrderHandle) -> Option<OrderMut<'_>> {
+        let order = self.slab.get_mut(handle)?;
+        Some(OrderMut::new(order, &mut self.level.totals, self.users))
+    }
+}
+
+/// Mutable access to one queued order that updates its level's and user's totals when dropped
+pub(super) struct OrderMut<'a> {
+    order: &'a mut Order,
+    totals: &'a mut LevelTotals,
+    users: &'a mut UserTotalsMap,
+    before: LevelTotals,
+    user_before: UserTotals,
+}
+
+impl<'a> OrderMut<'a> {
+    fn new(order: &'a mut Order, totals: &'a mut LevelTotals, users: &'a mut UserTotalsMap) -> Self {
+        let before = LevelTotals::of_order(order);
+        let user_before = UserTotals::of_order(order);
+        Self { order, totals, users, before, user_before }
+    }
+}
+
+impl Deref for OrderMut<'_> {
+    type Target = Order;
+
+    fn deref(&self) -> &Order {
+        self.order
+    }
+}
+
+impl DerefMut for OrderMut<'_> {
+    fn deref_mut(&mut self) -> &mut Order {
+        self.order
+    }
+}
+
+impl Drop for OrderMut<'_> {
+    fn drop(&mut self) {
+        *self.totals = *self.totals - self.before + LevelTotals::of_order(self.order);
+        self.users.adjust(&self.order.user_id, self.user_before, UserTotals::of_order(self.order));
+    }
+}
+
+/// Price levels for one side of the book
+#[derive(Debug, Clone)]
+pub(super) struct PriceLevels {
+    side: OrderSide,
+    /// Decimal places of one tick: levels are keyed by whole ticks of `10^-scale`
+    scale: u32,
+    hot: BTreeMap<i64, PriceLevel>,
+    cold: BTreeMap<i64, PriceLevel>,
+    /// Totals across every level as of the last settle
+    totals: LevelTotals,
+    /// Each level's contribution to `totals`, by tick count
+    level_totals: HashMap<i64, LevelTotals>,
+    /// Tick counts of the levels touched since the last settle
+    unsettled: Vec<i64>,
+    /// Totals of each user's active orders across every level, always current
+    users: UserTotalsMap,
+    /// Every order queued at any level of this side
+    slab: OrderSlab,
+}
+
+type Levels<'a> = btree_map::Values<'a, i64, PriceLevel>;
+
+/// Levels in one direction, both tiers chained
+enum Direction<'a> {
+    Ascending(Chain<Levels<'a>, Levels<'a>>),
+    Descending(Chain<Rev<Levels<'a>>, Rev<Levels<'a>>>),
+}
+
+/// Iterator over levels from the touch outward
+pub(super) struct TouchOrder<'a> {
+    levels: Direction<'a>,
+    slab: &'a OrderSlab,
+}
+
+impl<'a> Iterator for TouchOrder<'a> {
+    type Item = (&'a Price, LevelOrders<'a>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let level = match &mut self.levels {
+            Direction::Ascending(levels) => levels.next(),
+            Direction::Descending(levels) => levels.next(),
+        }?;
+        Some((&level.price, LevelOrders { level, slab: self.slab }))
+    }
+}
+
+impl DoubleEndedIterator for TouchOrder<'_> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let level = match &mut self.levels {
+            Direction::Ascending(levels) => levels.next_back(),
+            Direction::Descending(levels) => levels.next_back(),
+        }?;
+        Some((&level.price, LevelOrders { level, slab: self.slab }))
+    }
+}
+
+impl PriceLevels {
+    pub(super) fn new(side: OrderSide, scale: u32) -> Self {
+        Self {
+            side,
+            scale,
+            hot: BTreeMap::new(),
+            cold: BTreeMap::new(),
+            totals: LevelTotals::default(),
+            level_totals: HashMap::new(),
+            unsettled: Vec::new(),
+            users: UserTotalsMap::default(),
+            slab: OrderSlab::default(),
+        }
+    }
+
+    /// Creates an empty side with room for `levels` levels and `orders` orders
+    pub(super) fn with_capacity(side: OrderSide, scale: u32, levels: usize, orders: usize) -> Self {
+        Self {
+            level_totals: HashMap::with_capacity(levels),
+            slab: OrderSlab::with_capacity(orders),
+            ..Self::new(side, scale)
+        }
+    }
+
+    /// Builds a side from untiered queues, counting its totals from scratch
+    pub(super) fn from_queues(side: OrderSide, scale: u32, queues: impl IntoIterator<Item = (Price, VecDeque<Order>)>) -> crate::Result<Self> {
+        let mut slab = OrderSlab::default();
+        let mut users = UserTotalsMap::default();
+        let levels: Vec<PriceLevel> = queues.into_iter()
+            .map(|(price, orders)| {
+                let mut level = PriceLevel::new(price);
+                let mut queue = LevelMut { level: &mut level, slab: &mut slab, users: &mut users };
+                for order in orders {
+                    queue.push_back(order);
+                }
+                level
+            })
+            .collect();
+        Self::from_levels(side, scale, slab, users, levels)
+    }
+
+    /// Builds a side from untiered levels over `slab`, counting its totals from scratch
+    ///
+    /// `users` must already hold the totals of the orders in `slab`.
+    fn from_levels(
+        side: OrderSide,
+        scale: u32,
+        slab: OrderSlab,
+        users: UserTotalsMap,
+        levels: impl IntoIterator<Item = PriceLevel>,
+    ) -> crate::Result<Self> {
+        let mut hot = BTreeMap::new();
+        for level in levels {
+            hot.insert(Self::key(side, scale, level.price)?, level);
+        }
+        let level_totals: HashMap<i64, LevelTotals> = hot.iter()
+            .map(|(ticks, level)| (*ticks, level.totals))
+            .collect();
+        let totals = level_totals.values().fold(LevelTotals::default(), |totals, level| totals + *level);
+        Ok(Self { side, scale, hot, cold: BTreeMap::new(), totals, level_totals, unsettled: Vec::new(), users, slab })
+    }
+
+    /// Re-keys every level at another scale, folding them into the hot tier
+    ///
+    /// # Errors
+    /// Returns error, leaving the side as it was, if a level's price does not fit the scale
+    pub(super) fn set_scale(&mut self, scale: u32) -> crate::Result<()> {
+        if scale == self.scale {
+            return Ok(());
+        }
+        for level in self.values() {
+            Self::key(self.side, scale, level.price())?;
+        }
+        let levels = std::mem::take(&mut self.hot).into_values().chain(std::mem::take(&mut self.cold).into_values());
+        *self = Self::from_levels(self.side, scale, std::mem::take(&mut self.slab), std::mem::take(&mut self.users), levels)?;
+        Ok(())
+    }
+
+    /// Tick count keying the level at `price`, if it has one
+    ///
+    /// Market orders collected for an auction rest at their side's price
+    /// bound, which is no whole number of ticks; it takes the key beyond
+    /// every tick on that side.
+    fn ticks(side: OrderSide, scale: u32, price: &Price) -> Option<i64> {
+        price.to_ticks(scale).or_else(|| (*price == Order::market_bound(side)).then_some(match side {
+            OrderSide::Buy => i64::MAX,
+            OrderSide::Sell => 0,
+        }))
+    }
+
+    /// Tick count keying the level at `price`
+    ///
+    /// # Errors
+    /// Returns error if the price has more decimal places than the scale or too many ticks
+    fn key(side: OrderSide, scale: u32, price: Price) -> crate::Result<i64> {
+        Self::ticks(side, scale, &price).ok_or_else(|| off_scale(price, scale))
+    }
+
+    /// Best price on this side (highest bid, lowest ask)
+    pub(super) fn best(&self) -> Option<Price> {
+        Self::touch_level(self.side, &self.hot)
+            .or_else(|| Self::touch_level(self.side, &self.cold))
+            .map(|(_, level)| level.price)
+    }
+
+    pub(super) fn get(&self, price: &Price) -> Option<LevelOrders<'_>> {
+        let level = self.level(Self::ticks(self.side, self.scale, price)?)?;
+        Some(LevelOrders { level, slab: &self.slab })
+    }
+
+    pub(super) fn get_mut(&mut self, price: &Price) -> Option<LevelMut<'_>> {
+        let ticks = Self::ticks(self.side, self.scale, price)?;
+        let level = match self.hot.get_mut(&ticks) {
+            Some(level) => level,
+            None => self.cold.get_mut(&ticks)?,
+        };
+        Some(LevelMut { level, slab: &mut self.slab, users: &mut self.users })
+    }
+
+    /// Gets the next order to trade at a price
+    pub(super) fn front_mut(&mut self, price: &Price) -> Option<OrderMut<'_>> {
+        let ticks = Self::ticks(self.side, self.scale, price)?;
+        let level = match self.hot.get_mut(&ticks) {
+            Some(level) => level,
+            None => self.cold.get_mut(&ticks)?,
+        };
+        let handle = level.head?;
+        Some(OrderMut::new(&mut self.slab[handle], &mut level.totals, &mut self.users))
+    }
+
+    /// Gets the next order to trade on this side, at the front of the touch level
+    ///
+    /// The touch level is the first or last entry of its tier, so reaching it
+    /// needs neither a price-to-tick conversion nor a map search.
+    pub(super) fn best_front_mut(&mut self) -> Option<OrderMut<'_>> {
+        let level = Self::touch_entry(self.side, &mut self.hot, &mut self.cold)?.into_mut();
+        let handle = level.head?;
+        Some(OrderMut::new(&mut self.slab[handle], &mut level.totals, &mut self.users))
+    }
+
+    /// Removes the order at the front of the touch level, and the level if it empties
+    ///
+    /// Returns the order and whether its level is gone.
+    pub(super) fn pop_best_front(&mut self) -> Option<(Order, bool)> {
+        let mut entry = Self::touch_entry(self.side, &mut self.hot, &mut self.cold)?;
+        let level = entry.get_mut();
+        let handle = level.head?;
+        let order = LevelMut { level, slab: &mut self.slab, users: &mut self.users }.remove(handle)?;
+        let emptied = entry.get().len == 0;
+        if emptied {
+            entry.remove();
+        }
+        Some((order, emptied))
+    }
+
+    /// Gets the order in slot `handle`, queued at the level at `price`
+    pub(super) fn order_mut(&mut self, price: &Price, handle: OrderHandle) -> Option<OrderMut<'_>> {
+        let ticks = Self::ticks(self.side, self.scale, price)?;
+        let level = match self.hot.get_mut(&ticks) {
+            Some(level) => level,
+            None => self.cold.get_mut(&ticks)?,
+        };
+        let order = self.slab.get_mut(handle)?;
+        Some(OrderMut::new(order, &mut level.totals, &mut self.users))
+    }
+
+    /// Gets the order in slot `handle`
+    pub(super) fn order(&self, handle: OrderHandle) -> Option<&Order> {
+        self.slab.get(handle)
+    }
+
+    /// Removes the level at a price, taking its orders out of the slab in queue order
+    pub(super) fn remove(&mut self, price: &Price) -> Option<Vec<Order>> {
+        let ticks = Self::ticks(self.side, self.scale, price)?;
+        let mut level = self.hot.remove(&ticks).or_else(|| self.cold.remove(&ticks))?;
+        Some(LevelMut { level: &mut level, slab: &mut self.slab, users: &mut self.users }.extract(|_| true))
+    }
+
+    /// Gets the orders at a price, creating the level in the right tier if needed
+    ///
+    /// # Errors
+    /// Returns error if the price does not fit the scale
+    pub(super) fn level_mut(&mut self, price: Price) -> crate::Result<LevelMut<'_>> {
+        let ticks = Self::key(self.side, self.scale, price)?;
+        let deep = match Self::touch_level(self.side, &self.cold) {
+            Some((&cold_best, _)) if !self.hot.contains_key(&ticks) => !self.is_better(ticks, cold_best),
+            _ => false,
+        };
+        let tier = if deep { &mut self.cold } else { &mut self.hot };
+        let level = tier.entry(ticks).or_insert_with(|| PriceLevel::new(price));
+        Ok(LevelMut { level, slab: &mut self.slab, users: &mut self.users })
+    }
+
+    pub(super) fn is_empty(&self) -> bool {
+        self.hot.is_empty() && self.cold.is_empty()
+    }
+
+    pub(super) fn cold_len(&self) -> usize {
+        self.cold.len()
+    }
+
+    /// Number of price levels in both tiers
+    pub(super) fn len(&self) -> usize {
+        self.hot.len() + self.cold.len()
+    }
+
+    /// Number of orders queued across every level, inactive ones not yet purged included
+    pub(super) fn order_count(&self) -> usize {
+        self.slab.len()
+    }
+
+    /// Number of order slots allocated, held or free
+    pub(super) fn slot_capacity(&self) -> usize {
+        self.slab.capacity()
+    }
+
+    /// Active resting quantity and orders as of the last settle
+    pub(super) fn totals(&self) -> LevelTotals {
+        self.totals
+    }
+
+    /// Remaining quantity of a user's active orders across every level
+    pub(super) fn user_totals(&self, user_id: &UserId) -> UserTotals {
+        self.users.get(user_id)
+    }
+
+    /// Marks a level for re-counting when the current mutation ends
+    pub(super) fn touch(&mut self, price: Price) {
+        if let Some(ticks) = Self::ticks(self.side, self.scale, &price) {
+            self.unsettled.push(ticks);
+        }
+    }
+
+    /// Applies the touched levels' change in totals to the side's totals
+    pub(super) fn settle(&mut self) {
+        for ticks in std::mem::take(&mut self.unsettled) {
+            let current = self.level(ticks).map(|level| level.totals).unwrap_or_default();
+            let previous = if current.orders == 0 {
+                self.level_totals.remove(&ticks)
+            } else {
+                self.level_totals.insert(ticks, current)
+            }.unwrap_or_default();
+            self.totals = self.totals - previous + current;
+        }
+    }
+
+    /// Iterates levels from the touch outward
+    pub(super) fn iter(&self) -> TouchOrder<'_> {
+        let levels = match self.side {
+            OrderSide::Buy => Direction::Descending(self.hot.values().rev().chain(self.cold.values().rev())),
+            OrderSide::Sell => Direction::Ascending(self.hot.values().chain(self.cold.values())),
+        };
+        TouchOrder { levels, slab: &self.slab }
+    }
+
+    /// Prices of the levels within `range`, from the touch outward
+    pub(super) fn prices_in(&self, range: RangeInclusive<Price>) -> Vec<Price> {
+        let mut prices: Vec<Price> = self.range(range).map(|(price, _)| *price).collect();
+        if self.side == OrderSide::Buy {
+            prices.reverse();
+        }
+        prices
+    }
+
+    /// Levels within `range`, in no particular order
+    pub(super) fn range<R: RangeBounds<Price>>(&self, range: R) -> impl Iterator<Item = (&Price, LevelOrders<'_>)> {
+        self.tick_range(range)
+            .into_iter()
+            .flat_map(|ticks| self.hot.range(ticks).chain(self.cold.range(ticks)))
+            .map(|(_, level)| (&level.price, LevelOrders { level, slab: &self.slab }))
+    }
+
+    pub(super) fn values(&self) -> impl Iterator<Item = LevelOrders<'_>> {
+        self.hot.values().chain(self.cold.values()).map(|level| LevelOrders { level, slab: &self.slab })
+    }
+
+    /// Visits every level for changing, in no particular order
+    pub(super) fn for_each_mut(&mut self, mut visit: impl FnMut(LevelMut<'_>)) {
+        for level in self.hot.values_mut().chain(self.cold.values_mut()) {
+            visit(LevelMut { level, slab: &mut self.slab, users: &mut self.users });
+        }
+    }
+
+    /// Moves every order into a fresh slab without free slots, in queue order, and releases spare capacity
+    ///
+    /// Handles change, so the caller re-reads them from the levels.
+    pub(super) fn compact(&mut self) {
+        let mut slab = OrderSlab::with_capacity(self.slab.len());
+        for level in self.hot.values_mut().chain(self.cold.values_mut()) {
+            let orders = LevelMut { level: &mut *level, slab: &mut self.slab, users: &mut self.users }.extract(|_| true);
+            let mut packed = LevelMut { level, slab: &mut slab, users: &mut self.users };
+            for order in orders {
+                packed.push_back(order);
+            }
+        }
+        self.slab = slab;
+        self.level_totals.shrink_to_fit();
+        self.unsettled.shrink_to_fit();
+        self.users.shrink_to_fit();
+    }
+
+    /// Drops levels left without orders, returning how many there were
+    pub(super) fn remove_empty_levels(&mut self) -> usize {
+        let before = self.len();
+        self.hot.retain(|_, level| level.len > 0);
+        self.cold.retain(|_, level| level.len > 0);
+        before - self.len()
+    }
+
+    /// Moves levels between tiers so the hot tier tracks the touch
+    ///
+    /// `None` disables tiering and folds every cold level back into the hot tier.
+    pub(super) fn rebalance(&mut self, tiering: Option<TieredStorage>) {
+        let Some(TieredStorage { hot_levels }) = tiering else {
+            if !self.cold.is_empty() {
+                let cold = std::mem::take(&mut self.cold);
+                self.hot.extend(cold);
+            }
+            return;
+        };
+
+        if self.hot.len() >= hot_levels * 2 {
+            // Demote everything beyond the first `hot_levels` from the touch
+            let boundary = match self.side {
+                OrderSide::Buy => self.hot.keys().rev().nth(hot_levels - 1),
+                OrderSide::Sell => self.hot.keys().nth(hot_levels),
+            };
+            if let Some(&boundary) = boundary {
+                let mut deep = self.hot.split_off(&boundary);
+                if self.side == OrderSide::Buy {
+                    std::mem::swap(&mut self.hot, &mut deep);
+                }
+                self.cold.extend(deep);
+            }
+        } else if self.hot.len() < hot_levels / 2 + 1 {
+            // The market moved toward the cold levels: promote the nearest ones
+            while self.hot.len() < hot_levels {
+                let promoted = match self.side {
+                    OrderSide::Buy => self.cold.pop_last(),
+                    OrderSide::Sell => self.cold.pop_first(),
+                };
+                let Some((ticks, level)) = promoted else {
+                    break;
+                };
+                self.hot.insert(ticks, level);
+            }
+        }
+    }
+
+    fn level(&self, ticks: i64) -> Option<&PriceLevel> {
+        self.hot.get(&ticks).or_else(|| self.cold.get(&ticks))
+    }
+
+    /// Tick count and level nearest the touch in one tier
+    fn touch_level(side: OrderSide, levels: &BTreeMap<i64, PriceLevel>) -> Option<(&i64, &PriceLevel)> {
+        match side {
+            OrderSide::Buy => levels.last_key_value(),
+            OrderSide::Sell => levels.first_key_value(),
+        }
+    }
+
+    /// Entry of the level nearest the touch, in the hot tier unless it is empty
+    fn touch_entry<'a>(
+        side: OrderSide,
+        hot: &'a mut BTreeMap<i64, PriceLevel>,
+        cold: &'a mut BTreeMap<i64, PriceLevel>,
+    ) -> Option<btree_map::OccupiedEntry<'a, i64, PriceLevel>> {
+        let tier = if hot.is_empty() { cold } else { hot };
+        match side {
+            OrderSide::Buy => tier.last_entry(),
+            OrderSide::Sell => tier.first_entry(),
+        }
+    }
+
+    fn is_better(&self, ticks: i64, than: i64) -> bool {
+        match self.side {
+            OrderSide::Buy => ticks > than,
+            OrderSide::Sell => ticks < than,
+        }
+    }
+
+    /// Tick bounds of the levels priced within `range`, or `None` if it holds no whole tick
+    ///
+    /// Bounds between ticks round inward, so the levels found are exactly
+    /// those whose prices lie in `range`.
+    fn tick_range<R: RangeBounds<Price>>(&self, range: R) -> Option<(Bound<i64>, Bound<i64>)> {
+        let start = match range.start_bound() {
+            Bound::Unbounded => i64::MIN,
+            Bound::Included(price) => match price.ticks_floor(self.scale) {
+                (ticks, true) => ticks,
+                (ticks, false) => ticks.checked_add(1)?,
+            },
+            Bound::Excluded(price) => price.ticks_floor(self.scale).0.checked_add(1)?,
+        };
+        let end = match range.end_bound() {
+            Bound::Unbounded => i64::MAX,
+            Bound::Included(price) => price.ticks_floor(self.scale).0,
+            Bound::Excluded(price) => match price.ticks_floor(self.scale) {
+                (ticks, true) => ticks.checked_sub(1)?,
+                (ticks, false) => ticks,
+            },
+        };
+        (start <= end).then_some((Bound::Included(start), Bound::Included(end)))
+    }
+}
+
+impl Serialize for PriceLevels {
+    /// Serializes as a single price-ordered map, independent of tiering
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let (lower, upper) = match self.side {
+            OrderSide::Buy => (&self.cold, &self.hot),
+            OrderSide::Sell => (&self.hot, &self.cold),
+        };
+        serializer.collect_map(lower.values().chain(upper.values()).map(|level| (&level.price, LevelOrders { level, slab: &self.slab })))
+    }
+}
+
+/// Reads a side keyed at the finest scale its prices need
+///
+/// The book's own scale is read later in the snapshot, so restoring
+/// re-keys the side at that scale once the whole book has been read.
+fn deserialize_side<'de, D: Deserializer<'de>>(side: OrderSide, deserializer: D) -> Result<PriceLevels, D::Error> {
+    let levels = BTreeMap::<Price, VecDeque<Order>>::deserialize(deserializer)?;
+    let scale = levels.keys()
+        .map(|price| price.value().normalize().scale())
+        .filter(|&scale| scale <= MAX_PRICE_SCALE)
+        .max()
+        .unwrap_or(DEFAULT_PRICE_SCALE);
+    PriceLevels::from_queues(side, scale, levels)
+        .map_err(serde::de::Error::custom)
+}
+
+pub(super) fn deserialize_bids<'de, D: Deserializer<'de>>(deserializer: D) -> Result<PriceLevels, D::Error> {
+    deserialize_side(OrderSide::Buy, deserializer)
+}
+
+pub(super) fn deserialize_asks<'de, D: Deserializer<'de>>(deserializer: D) -> Result<PriceLevels, D::Error> {
+    deserialize_side(OrderSide::Sell, deserializer)
+}
+
+impl LimitOrderBook {
+    /// Gets the hot/cold level storage settings
+    pub fn tiered_storage(&self) -> Option<TieredStorage> {
+        self.tiered_storage
+    }
+
+    /// Enables (`Some`) or disables (`None`) hot/cold level storage
+    ///
+    /// Tiering only changes where levels are stored; every query returns the
+    /// same results either way.
+    pub fn set_tiered_storage(&mut self, tiering: Option<TieredStorage>) {
+        self.tiered_storage = tiering;
+        self.rebalance_tiers();
+    }
+
+    /// Gets the number of price levels currently held in the cold tier
+    pub fn cold_level_count(&self) -> usize {
+        self.bids.cold_len() + self.asks.cold_len()
+    }
+
+    /// Gets the active resting quantity on the bid side, hidden orders included
+    pub fn total_bid_quantity(&self) -> u64 {
+        self.bids.totals().quantity
+    }
+
+    /// Gets the active resting quantity on the ask side, hidden orders included
+    pub fn total_ask_quantity(&self) -> u64 {
+        self.asks.totals().quantity
+    }
+
+    /// Gets the number of active resting bids, hidden orders included
+    pub fn bid_order_count(&self) -> usize {
+        self.bids.totals().orders
+    }
+
+    /// Gets the number of active resting asks, hidden orders included
+    pub fn ask_order_count(&self) -> usize {
+        self.asks.totals().orders
+    }
+
+    /// Gets the number of price levels on one side
+    pub fn price_level_count(&self, side: OrderSide) -> usize {
+        match side {
+            OrderSide::Buy => self.bids.len(),
+            OrderSide::Sell => self.asks.len(),
+        }
+    }
+
+    /// Applies the levels touched by a mutation to the running totals
+    pub(super) fn settle_level_totals(&mut self) {
+        self.bids.settle();
+        self.asks.settle();
+    }
+
+    pub(super) fn rebalance_tiers(&mut self) {
+        self.bids.rebalance(self.tiered_storage);
+        self.asks.rebalance(self.tiered_storage);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{types::OrderId, Quantity};
+    use crate::order_book::test_support::create_test_order;
+
+    /// 50 bid levels at 99.00..98.51 and 50 ask levels at 101.00..101.49
+    fn deep_book(tiering: Option<TieredStorage>) -> LimitOrderBook {
+        let mut book = LimitOrderBook::new("TIER".to_string()).unwrap();
+        book.set_tiered_storage(tiering);
+        for i in 0..50 {
+            book.add_order(create_test_order(OrderSide::Buy, 9900 - i, 10)).unwrap();
+            book.add_order(create_test_order(OrderSide::Sell, 10100 + i, 10)).unwrap();
+        }
+        book
+    }
+
+    #[test]
+    fn test_deep_levels_move_to_cold_tier() {
+        let book = deep_book(Some(TieredStorage::new(8)));
+        assert!(book.bids.hot.len() < 16);
+        assert!(book.asks.hot.len() < 16);
+        assert_eq!(book.bids.values().chain(book.asks.values()).count(), 100);
+        assert!(book.cold_level_count() > 60);
+
+        // Every hot level is better than every cold level
+        assert!(book.bids.hot.keys().next().unwrap() > book.bids.cold.keys().next_back().unwrap());
+        assert!(book.asks.hot.keys().next_back().unwrap() < book.asks.cold.keys().next().unwrap());
+
+        assert_eq!(book.market_depth(100), deep_book(None).market_depth(100));
+    }
+
+    #[test]
+    fn test_cold_levels_are_reachable_and_promoted() {
+        let mut book = deep_book(Some(TieredStorage::new(4)));
+        let deep_ask = create_test_order(OrderSide::Sell, 10148, 5);
+        let deep_id = deep_ask.id;
+        book.add_order(deep_ask).unwrap();
+        assert!(book.asks.cold.contains_key(&10148));
+        assert_eq!(book.get_order(deep_id).unwrap().remaining_quantity.value(), 5);
+        book.cancel_order(deep_id).unwrap();
+
+        // Sweep the asks through the hot tier and into the cold one
+        let trades = book.add_order(create_test_order(OrderSide::Buy, 10139, 400)).unwrap();
+        assert_eq!(trades.len(), 40);
+        assert_eq!(book.best_ask().unwrap().as_cents(), 10140);
+        assert!(!book.asks.hot.is_empty());
+        assert!(book.asks.hot.contains_key(&10140));
+    }
+
+    #[test]
+    fn test_disabling_folds_cold_levels_back() {
+        let mut book = deep_book(Some(TieredStorage::new(4)));
+        assert!(book.cold_level_count() > 0);
+
+        book.set_tiered_storage(None);
+        assert_eq!(book.cold_level_count(), 0);
+        assert_eq!(book.bids.hot.len(), 50);
+    }
+
+    #[test]
+    fn test_serialization_ignores_tiers() {
+        let book = deep_book(Some(TieredStorage::new(4)));
+        assert!(book.cold_level_count() > 0);
+        let tiered = serde_json::to_value(&book).unwrap();
+        
+        // Same flat price map as an untiered book holding the same levels
+        let mut untiered = book.clone();
+        untiered.set_tiered_storage(None);
+        let plain = serde_json::to_value(&untiered).unwrap();
+        assert_eq!(tiered["bids"], plain["bids"]);
+        assert_eq!(tiered["asks"], plain["asks"]);
+
+        let restored: LimitOrderBook = serde_json::from_value(tiered).unwrap();
+        assert_eq!(restored.tiered_storage(), Some(TieredStorage::new(4)));
+        assert_eq!(restored.market_depth(100), deep_book(None).market_depth(100));
+    }
+
+    #[test]
+    fn test_level_totals_follow_each_change() {
+        let price = Price::from_cents(10000).unwrap();
+        let mut side = PriceLevels::new(OrderSide::Sell, DEFAULT_PRICE_SCALE);
+        let check = |level: LevelOrders<'_>| assert_eq!(level.totals(), LevelTotals::of(level));
+        let mut level = side.level_mut(price).unwrap();
+        let first = level.push_back(create_test_order(OrderSide::Sell, 10000, 10));
+        let hidden = level.push_back(create_test_order(OrderSide::Sell, 10000, 20).with_hidden());
+        let second = level.insert_before(Some(hidden), create_test_order(OrderSide::Sell, 10000, 30));
+        check(level.orders());
+        assert_eq!(level.orders().totals(), LevelTotals { quantity: 60, orders: 3, displayed_quantity: 40, displayed_orders: 2 });
+
+        level.get_mut(first).unwrap().fill(Quantity::new(4).unwrap()).unwrap();
+        check(level.orders());
+        level.get_mut(second).unwrap().cancel();
+        check(level.orders());
+        assert_eq!(level.orders().totals(), LevelTotals { quantity: 26, orders: 2, displayed_quantity: 6, displayed_orders: 1 });
+
+        // A guard adjusts the totals by the net change, however many edits it makes
+        let mut order = side.order_mut(&price, hidden).unwrap();
+        order.hidden = false;
+        order.fill(Quantity::new(5).unwrap()).unwrap();
+        drop(order);
+        let mut level = side.get_mut(&price).unwrap();
+        check(level.orders());
+        assert_eq!(level.remove(second).unwrap().status, crate::OrderStatus::Cancelled);
+        assert_eq!(level.extract(|order| order.hidden).len(), 0);
+        assert_eq!(level.extract(|order| order.remaining_quantity.value() == 6).len(), 1);
+        check(level.orders());
+        assert_eq!(level.orders().totals(), LevelTotals { quantity: 15, orders: 1, displayed_quantity: 15, displayed_orders: 1 });
+
+        // Levels restored from a snapshot count their totals
+        let restored = PriceLevels::from_queues(OrderSide::Sell, DEFAULT_PRICE_SCALE, [(price, level.orders().iter().cloned().collect())]).unwrap();
+        assert_eq!(restored.get(&price).unwrap().totals(), side.get(&price).unwrap().totals());
+    }
+
+    #[test]
+    fn test_queue_keeps_fifo_order_through_removals_anywhere() {
+        let price = Price::from_cents(10000).unwrap();
+        let mut side = PriceLevels::new(OrderSide::Buy, DEFAULT_PRICE_SCALE);
+        let mut level = side.level_mut(price).unwrap();
+        let mut model = VecDeque::new();
+        for quantity in 1..=20 {
+            let order = create_test_order(OrderSide::Buy, 10000, quantity);
+            model.push_back((level.push_back(order.clone()), order.id));
+        }
+
+        // Take from the middle, both ends, and the middle again, refilling at the back
+        let ids = |level: &LevelMut<'_>| level.orders().iter().map(|order| order.id).collect::<Vec<_>>();
+        for index in [10, 0, 17, 5, 5, 14] {
+            let (handle, order_id) = model.remove(index).unwrap();
+            assert_eq!(level.remove(handle).map(|order| order.id), Some(order_id));
+            assert_eq!(ids(&level), model.iter().map(|(_, id)| *id).collect::<Vec<_>>());
+            let order = create_test_order(OrderSide::Buy, 10000, 1);
+            model.push_back((level.push_back(order.clone()), order.id));
+        }
+        let reversed: Vec<OrderId> = level.orders().iter().rev().map(|order| order.id).collect();
+        assert_eq!(reversed, model.iter().rev().map(|(_, id)| *id).collect::<Vec<_>>());
+        assert_eq!(level.orders()[3].id, model[3].1);
+
+        // Emptying the level one order at a time leaves no dangling ends
+        while let Some((handle, _)) = model.pop_back() {
+            level.remove(handle).unwrap();
+        }
+        assert!(level.is_empty() && level.orders().front().is_none());
+        let order = create_test_order(OrderSide::Buy, 10000, 1);
+        level.push_back(order.clone());
+        assert_eq!(ids(&level), vec![order.id]);
+    }
+
+    #[test]
+    fn test_touch_front_falls_through_to_cold_tier() {
+        let mut book = deep_book(Some(TieredStorage::new(4)));
+        while !book.asks.hot.is_empty() {
+            book.asks.pop_best_front().unwrap();
+        }
+        let cold_best = *book.asks.cold.keys().next().unwrap();
+        let best = book.asks.best().unwrap();
+        assert_eq!(book.asks.best_front_mut().unwrap().price, best);
+
+        // Popping the last order at the touch takes its level with it
+        let (order, emptied) = book.asks.pop_best_front().unwrap();
+        assert_eq!(order.price.as_cents(), cold_best);
+        assert!(emptied && !book.asks.cold.contains_key(&cold_best));
+        assert_eq!(book.bids.pop_best_front().map(|(order, emptied)| (order.price.as_cents(), emptied)), Some((9900, true)));
+    }
+
+    #[test]
+    fn test_side_totals_follow_fills_and_cancels() {
+        let mut book = deep_book(Some(TieredStorage::new(4)));
+        assert_eq!((book.total_bid_quantity(), book.bid_order_count(), book.price_level_count(OrderSide::Buy)), (500, 50, 50));
+
+        // A deep hidden bid counts toward the totals but not the depth
+        let hidden = create_test_order(OrderSide::Buy, 9000, 25).with_hidden();
+        let hidden_id = hidden.id;
+        book.add_order(hidden).unwrap();
+        assert_eq!((book.total_bid_quantity(), book.bid_order_count()), (525, 51));
+
+        // Sweep one and a half ask levels, then cancel the partly filled order
+        book.add_order(create_test_order(OrderSide::Buy, 10101, 15)).unwrap();
+        assert_eq!((book.total_ask_quantity(), book.ask_order_count(), book.price_level_count(OrderSide::Sell)), (485, 49, 49));
+        let partial_id = book.asks.iter().next().unwrap().1[0].id;
+        book.cancel_order(partial_id).unwrap();
+        book.cancel_order(hidden_id).unwrap();
+        assert_eq!((book.total_ask_quantity(), book.ask_order_count()), (480, 48));
+        assert_eq!((book.total_bid_quantity(), book.price_level_count(OrderSide::Buy)), (500, 50));
+
+        // Restored books count their totals from scratch
+        let restored: LimitOrderBook = serde_json::from_value(serde_json::to_value(&book).unwrap()).unwrap();
+        assert_eq!((restored.total_ask_quantity(), restored.ask_order_count()), (480, 48));
+        assert_eq!((restored.total_bid_quantity(), restored.bid_order_count()), (500, 50));
+    }
+}
\ No newline at end of file
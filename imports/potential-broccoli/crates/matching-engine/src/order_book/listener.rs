@@ -0,0 +1,290 @@
+//! Synchronous book listener
+//!
+//! A listener is called back as the book changes instead of being polled.
+//! Callbacks only receive the data they describe, never the book, so a
+//! listener cannot mutate the book re-entrantly. Within a mutation the order
+//! is: the order accepted, its trades as they execute, any cancellations,
+//! then each price level it changed, once per level in the order first
+//! touched. Last comes the op itself with its result, for every op applied
+//! whether or not it changed anything, which makes the listener one place to
+//! count or audit everything the book is asked to do. The listener is
+//! runtime wiring: it is not serialized, and a cloned book starts without
+//! one.
+
+use super::{LimitOrderBook, OpResult, OrderBookOp, Trade};
+use crate::{Order, OrderSide, Price};
+
+/// Callbacks invoked as the book changes; every method defaults to a no-op
+pub trait BookListener: Send {
+    /// A trade executed
+    fn on_trade(&mut self, _trade: &Trade) {}
+
+    /// An order passed every check and is about to match
+    ///
+    /// Orders held for a future activation time are reported when they
+    /// activate; at-the-close orders when they join the closing book.
+    fn on_order_accepted(&mut self, _order: &Order) {}
+
+    /// An order was cancelled, by its owner or by the book (unfilled market
+    /// or fill-or-kill remainders, self-trade prevention, halts)
+    ///
+    /// Orders removed in bulk by mass cancels and end-of-day expiry are
+    /// returned to the caller instead.
+    fn on_order_cancelled(&mut self, _order: &Order) {}
+
+    /// The aggregate at a price level changed
+    fn on_level_changed(&mut self, _side: OrderSide, _price: Price) {}
+
+    /// An op was applied, as the book's `op_sequence`-th op, with `result`
+    ///
+    /// Rejected ops are reported too. The book clones each op for this
+    /// callback while a listener is installed.
+    fn on_op_applied(&mut self, _op_sequence: u64, _op: &OrderBookOp, _result: &OpResult) {}
+}
+
+/// The book's listener, if any, and the levels touched by the mutation in progress
+#[derive(Default)]
+pub(super) struct ListenerSlot {
+    listener: Option<Box<dyn BookListener>>,
+    touched: Vec<(OrderSide, Price)>,
+}
+
+impl std::fmt::Debug for ListenerSlot {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ListenerSlot").field("installed", &self.listener.is_some()).finish()
+    }
+}
+
+impl Clone for ListenerSlot {
+    fn clone(&self) -> Self {
+        Self::default()
+    }
+}
+
+impl ListenerSlot {
+    pub(super) fn trade(&mut self, trade: &Trade) {
+        if let Some(listener) = self.listener.as_mut() {
+            listener.on_trade(trade);
+        }
+    }
+
+    pub(super) fn order_accepted(&mut self, order: &Order) {
+        if let Some(listener) = self.listener.as_mut() {
+            listener.on_order_accepted(order);
+        }
+    }
+
+    pub(super) fn order_cancelled(&mut self, order: &Order) {
+        if let Some(listener) = self.listener.as_mut() {
+            listener.on_order_cancelled(order);
+        }
+    }
+
+    pub(super) fn touch(&mut self, side: OrderSide, price: Price) {
+        if self.listener.is_some() {
+            self.touched.push((side, price));
+        }
+    }
+
+    pub(super) fn op_applied(&mut self, op_sequence: u64, op: &OrderBookOp, result: &OpResult) {
+        if let Some(listener) = self.listener.as_mut() {
+            listener.on_op_applied(op_sequence, op, result);
+        }
+    }
+
+    /// Reports the levels touched since the last flush, each once
+    pub(super) fn flush_levels(&mut self) {
+        let Some(listener) = self.listener.as_mut() else {
+            return;
+        };
+        let mut seen = std::collections::HashSet::new();
+        for (side, price) in self.touched.drain(..) {
+            if seen.insert((side, price)) {
+                listener.on_level_changed(side, price);
+            }
+        }
+    }
+}
+
+impl LimitOrderBook {
+    /// Installs a listener, replacing any previous one
+    pub fn set_listener(&mut self, listener: Box<dyn BookListener>) {
+        self.listener = ListenerSlot { listener: Some(listener), touched: Vec::new() };
+    }
+
+    /// Removes the listener, returning it
+    pub fn take_listener(&mut self) -> Option<Box<dyn BookListener>> {
+        std::mem::take(&mut self.listener).listener
+    }
+
+    /// Checks if a listener is installed
+    pub fn has_listener(&self) -> bool {
+        self.listener.listener.is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        types::{OrderId, UserId},
+        Quantity,
+    };
+    use std::sync::{Arc, Mutex};
+    use crate::order_book::test_support::create_test_order;
+
+    #[derive(Debug, Clone, PartialEq)]
+    enum Event {
+        Trade(OrderId, OrderId, u64),
+        Accepted(OrderId),
+        Cancelled(OrderId),
+        Level(OrderSide, i64),
+    }
+
+    #[derive(Clone, Default)]
+    struct Recorder(Arc<Mutex<Vec<Event>>>);
+
+    impl Recorder {
+        fn take(&self) -> Vec<Event> {
+            std::mem::take(&mut self.0.lock().unwrap())
+        }
+    }
+
+    impl BookListener for Recorder {
+        fn on_trade(&mut self, trade: &Trade) {
+            self.0.lock().unwrap().push(Event::Trade(trade.buy_order_id, trade.sell_order_id, trade.quantity.value()));
+        }
+
+        fn on_order_accepted(&mut self, order: &Order) {
+            self.0.lock().unwrap().push(Event::Accepted(order.id));
+        }
+
+        fn on_order_cancelled(&mut self, order: &Order) {
+            self.0.lock().unwrap().push(Event::Cancelled(order.id));
+        }
+
+        fn on_level_changed(&mut self, side: OrderSide, price: Price) {
+            self.0.lock().unwrap().push(Event::Level(side, price.as_cents()));
+        }
+    }
+
+    #[test]
+    fn test_sweep_reports_exact_event_sequence() {
+        let mut book = LimitOrderBook::new("LSTN".to_string()).unwrap();
+        let asks: Vec<Order> = [(10000, 50), (10000, 30), (10100, 40), (10200, 100)]
+            .into_iter()
+            .map(|(price_cents, quantity)| create_test_order(OrderSide::Sell, price_cents, quantity))
+            .collect();
+        let ids: Vec<OrderId> = asks.iter().map(|order| order.id).collect();
+        for order in asks {
+            book.add_order(order).unwrap();
+        }
+        let recorder = Recorder::default();
+        book.set_listener(Box::new(recorder.clone()));
+
+        let sweep = Order::market(OrderId::new(), UserId::new("taker".to_string()), OrderSide::Buy, Quantity::new(100).unwrap());
+        let sweep_id = sweep.id;
+        book.add_order(sweep).unwrap();
+        assert_eq!(recorder.take(), vec![
+            Event::Accepted(sweep_id),
+            Event::Trade(sweep_id, ids[0], 50),
+            Event::Trade(sweep_id, ids[1], 30),
+            Event::Trade(sweep_id, ids[2], 20),
+            Event::Level(OrderSide::Sell, 10000),
+            Event::Level(OrderSide::Sell, 10100),
+        ]);
+
+        // A market remainder is cancelled by the book
+        let sweep = Order::market(OrderId::new(), UserId::new("taker".to_string()), OrderSide::Buy, Quantity::new(200).unwrap());
+        let sweep_id = sweep.id;
+        book.add_order(sweep).unwrap();
+        assert_eq!(recorder.take(), vec![
+            Event::Accepted(sweep_id),
+            Event::Trade(sweep_id, ids[2], 20),
+            Event::Trade(sweep_id, ids[3], 100),
+            Event::Cancelled(sweep_id),
+            Event::Level(OrderSide::Sell, 10100),
+            Event::Level(OrderSide::Sell, 10200),
+        ]);
+    }
+
+    #[test]
+    fn test_cancels_and_rejections() {
+        let mut book = LimitOrderBook::new("LSTN".to_string()).unwrap();
+        let recorder = Recorder::default();
+        book.set_listener(Box::new(recorder.clone()));
+
+        let bid = create_test_order(OrderSide::Buy, 9900, 10);
+        let bid_id = bid.id;
+        book.add_order(bid.clone()).unwrap();
+        assert!(book.add_order(bid).is_err());
+        book.cancel_order(bid_id).unwrap();
+        assert_eq!(recorder.take(), vec![
+            Event::Accepted(bid_id),
+            Event::Level(OrderSide::Buy, 9900),
+            Event::Cancelled(bid_id),
+            Event::Level(OrderSide::Buy, 9900),
+        ]);
+    }
+
+    #[test]
+    fn test_every_applied_op_is_reported_last() {
+        #[derive(Clone, Default)]
+        struct Ops(Arc<Mutex<Vec<(u64, String)>>>);
+
+        impl BookListener for Ops {
+            fn on_level_changed(&mut self, _side: OrderSide, price: Price) {
+                self.0.lock().unwrap().push((0, format!("level {}", price.as_cents())));
+            }
+
+            fn on_op_applied(&mut self, op_sequence: u64, op: &OrderBookOp, result: &OpResult) {
+                let kind = match (op, result) {
+                    (_, OpResult::Rejected(_)) => "rejected",
+                    (OrderBookOp::Submit(_), _) => "submit",
+                    (OrderBookOp::Cancel(_), _) => "cancel",
+                    _ => "other",
+                };
+                self.0.lock().unwrap().push((op_sequence, kind.to_string()));
+            }
+        }
+
+        let mut book = LimitOrderBook::new("LSTN".to_string()).unwrap();
+        let ops = Ops::default();
+        book.set_listener(Box::new(ops.clone()));
+        let bid = create_test_order(OrderSide::Buy, 9900, 10);
+        let bid_id = bid.id;
+        book.add_order(bid.clone()).unwrap();
+        assert!(book.add_order(bid).is_err());
+        book.halt();
+        book.resume();
+        book.cancel_order(bid_id).unwrap();
+        assert_eq!(*ops.0.lock().unwrap(), vec![
+            (0, "level 9900".to_string()),
+            (1, "submit".to_string()),
+            (2, "rejected".to_string()),
+            (3, "other".to_string()),
+            (4, "other".to_string()),
+            (0, "level 9900".to_string()),
+            (5, "cancel".to_string()),
+        ]);
+        assert_eq!(book.op_sequence(), 5);
+    }
+
+    #[test]
+    fn test_listener_is_neither_cloned_nor_serialized() {
+        let mut book = LimitOrderBook::new("LSTN".to_string()).unwrap();
+        let recorder = Recorder::default();
+        book.set_listener(Box::new(recorder.clone()));
+
+        let mut clone = book.clone();
+        let mut restored: LimitOrderBook = serde_json::from_str(&serde_json::to_string(&book).unwrap()).unwrap();
+        assert!(!clone.has_listener() && !restored.has_listener());
+        clone.add_order(create_test_order(OrderSide::Buy, 9900, 10)).unwrap();
+        restored.add_order(create_test_order(OrderSide::Buy, 9900, 10)).unwrap();
+        assert!(recorder.take().is_empty());
+
+        assert!(book.take_listener().is_some());
+        book.add_order(create_test_order(OrderSide::Buy, 9900, 10)).unwrap();
+        assert!(recorder.take().is_empty());
+    }
+}
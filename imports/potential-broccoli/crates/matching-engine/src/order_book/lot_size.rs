@@ -0,0 +1,186 @@
+//! Minimum lot and quantity step
+//!
+//! A book built from a [`BookConfig`](super::BookConfig) sizes orders on a
+//! grid as well as pricing them on one: an order's quantity must be at
+//! least the minimum lot and a whole multiple of the quantity step. The
+//! rules apply to the size a participant asks for: a new order's quantity,
+//! whether submitted, parked, queued for the close, or seeded, the new total
+//! of a modify, and the new remaining quantity of a reduce. Breaches are
+//! rejected with [`MatchingEngineError::InvalidLot`].
+//!
+//! The remainder a partial fill leaves is never checked and is allowed to
+//! rest. Under a 500-share lot, a 600-share buy that fills 500 rests with
+//! 100; a position limit may trim an order off the step the same way. The
+//! book does not cancel liquidity the participant sized correctly.
+
+use super::LimitOrderBook;
+use crate::{MatchingEngineError, Order, Quantity};
+use serde::{Deserialize, Serialize};
+
+/// The sizing rule an order quantity broke
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum QuantityRule {
+    /// Quantities must be at least this many units
+    MinimumLot(u64),
+    /// Quantities must be whole multiples of this many units
+    Step(u64),
+}
+
+impl std::fmt::Display for QuantityRule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::MinimumLot(lot_size) => write!(f, "minimum lot of {}", lot_size),
+            Self::Step(qty_step) => write!(f, "quantity step of {}", qty_step),
+        }
+    }
+}
+
+/// A book's minimum lot and quantity step, both positive
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub(super) struct LotRules {
+    lot_size: u64,
+    qty_step: u64,
+}
+
+impl LotRules {
+    /// Creates the rules, rejecting a zero lot size or step
+    pub(super) fn new(lot_size: u64, qty_step: u64) -> crate::Result<Self> {
+        if lot_size == 0 || qty_step == 0 {
+            return Err(MatchingEngineError::InvalidQuantity(format!(
+                "Lot size and quantity step must be positive, got: {} and {}", lot_size, qty_step
+            )));
+        }
+        Ok(Self { lot_size, qty_step })
+    }
+
+    /// Gets the first rule `quantity` breaks, if any
+    fn broken_by(&self, quantity: Quantity) -> Option<QuantityRule> {
+        if quantity.value() < self.lot_size {
+            Some(QuantityRule::MinimumLot(self.lot_size))
+        } else if !quantity.value().is_multiple_of(self.qty_step) {
+            Some(QuantityRule::Step(self.qty_step))
+        } else {
+            None
+        }
+    }
+}
+
+impl LimitOrderBook {
+    /// Gets the minimum order quantity, 1 unless the book has a lot size
+    pub fn lot_size(&self) -> u64 {
+        self.lot_rules.map_or(1, |rules| rules.lot_size)
+    }
+
+    /// Gets the step order quantities must be multiples of, 1 unless the book has one
+    pub fn qty_step(&self) -> u64 {
+        self.lot_rules.map_or(1, |rules| rules.qty_step)
+    }
+
+    /// Rejects an order whose size breaks the lot rules
+    pub(super) fn check_lot_size(&self, order: &Order) -> crate::Result<()> {
+        self.check_quantity_on_lot(order.original_quantity)
+    }
+
+    /// Rejects a requested quantity below the minimum lot or off the step
+    pub(super) fn check_quantity_on_lot(&self, quantity: Quantity) -> crate::Result<()> {
+        match self.lot_rules.and_then(|rules| rules.broken_by(quantity)) {
+            Some(rule) => Err(MatchingEngineError::InvalidLot { quantity, rule }),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        order_book::BookConfig,
+        types::{OrderId, UserId},
+        OrderSide, Price,
+    };
+    use crate::order_book::test_support::create_test_order;
+
+    fn lot_book(lot_size: u64, qty_step: u64) -> LimitOrderBook {
+        LimitOrderBook::with_config("LOT".to_string(), BookConfig { lot_size, qty_step, ..Default::default() }).unwrap()
+    }
+
+    fn broke(result: crate::Result<impl std::fmt::Debug>, expected: QuantityRule) -> bool {
+        matches!(result, Err(MatchingEngineError::InvalidLot { rule, .. }) if rule == expected)
+    }
+
+    #[test]
+    fn test_orders_must_fill_whole_steps() {
+        let mut book = lot_book(1, 100);
+        assert_eq!((book.lot_size(), book.qty_step()), (1, 100));
+        let error = book.add_order(create_test_order(OrderSide::Buy, 10000, 250)).unwrap_err();
+        assert_eq!(error, MatchingEngineError::InvalidLot { quantity: Quantity::new(250).unwrap(), rule: QuantityRule::Step(100) });
+        assert_eq!(error.to_string(), "Quantity 250 breaks the quantity step of 100");
+        book.add_order(create_test_order(OrderSide::Buy, 10000, 200)).unwrap();
+
+        // Market, parked, at-the-close, and seeded orders follow the same rules
+        let market = Order::market(OrderId::new(), UserId::new("taker".to_string()), OrderSide::Sell, Quantity::new(50).unwrap());
+        assert!(broke(book.submit_order(market), QuantityRule::Step(100)));
+        let parked = create_test_order(OrderSide::Buy, 9900, 150).with_activation(chrono::Utc::now() + chrono::Duration::hours(1));
+        assert!(broke(book.submit_order(parked), QuantityRule::Step(100)));
+        let at_close = create_test_order(OrderSide::Buy, 9900, 150).with_time_in_force(crate::TimeInForce::AtTheClose);
+        assert!(broke(book.submit_order(at_close), QuantityRule::Step(100)));
+        assert!(broke(book.seed_orders(vec![create_test_order(OrderSide::Sell, 10100, 120)]), QuantityRule::Step(100)));
+        assert_eq!(book.order_count(), 1);
+
+        // A plain book takes any size
+        let plain = LimitOrderBook::new("LOT".to_string()).unwrap();
+        assert_eq!((plain.lot_size(), plain.qty_step()), (1, 1));
+    }
+
+    #[test]
+    fn test_minimum_lot_larger_than_step() {
+        let mut book = lot_book(500, 100);
+        assert!(broke(book.add_order(create_test_order(OrderSide::Buy, 10000, 400)), QuantityRule::MinimumLot(500)));
+        assert!(broke(book.add_order(create_test_order(OrderSide::Buy, 10000, 550)), QuantityRule::Step(100)));
+        book.add_order(create_test_order(OrderSide::Buy, 10000, 500)).unwrap();
+        book.add_order(create_test_order(OrderSide::Buy, 10000, 600)).unwrap();
+        assert!(book.validate_order(&create_test_order(OrderSide::Sell, 10100, 700)).is_ok());
+
+        assert!(LimitOrderBook::with_config("LOT".to_string(), BookConfig { qty_step: 0, ..Default::default() }).is_err());
+        assert!(LimitOrderBook::with_config("LOT".to_string(), BookConfig { lot_size: 0, ..Default::default() }).is_err());
+    }
+
+    #[test]
+    fn test_modify_and_reduce_follow_the_rules() {
+        let mut book = lot_book(200, 100);
+        let order = create_test_order(OrderSide::Buy, 10000, 500);
+        let order_id = order.id;
+        book.add_order(order).unwrap();
+
+        assert!(broke(book.modify_order(order_id, None, Some(Quantity::new(450).unwrap())), QuantityRule::Step(100)));
+        assert!(broke(book.reduce_quantity(order_id, Quantity::new(100).unwrap()), QuantityRule::MinimumLot(200)));
+        assert!(broke(book.reduce_quantity(order_id, Quantity::new(250).unwrap()), QuantityRule::Step(100)));
+        book.modify_order(order_id, None, Some(Quantity::new(600).unwrap())).unwrap();
+        book.reduce_quantity(order_id, Quantity::new(300).unwrap()).unwrap();
+        assert_eq!(book.get_order(order_id).unwrap().remaining_quantity.value(), 300);
+
+        // A price-only modify leaves the size alone, so it isn't rechecked
+        book.modify_order(order_id, Some(Price::from_cents(10001).unwrap()), None).unwrap();
+    }
+
+    #[test]
+    fn test_partial_fills_may_leave_a_sub_lot_remainder_resting() {
+        let mut book = lot_book(500, 100);
+        book.add_order(create_test_order(OrderSide::Sell, 10000, 500)).unwrap();
+
+        // A 600 buy fills 500 against the ask and rests its last 100, below the lot
+        let buy = create_test_order(OrderSide::Buy, 10000, 600);
+        let buy_id = buy.id;
+        let trades = book.add_order(buy).unwrap();
+        assert_eq!(trades[0].quantity.value(), 500);
+        assert_eq!(book.get_order(buy_id).unwrap().remaining_quantity.value(), 100);
+        assert_eq!(book.best_bid(), Some(Price::from_cents(10000).unwrap()));
+        assert_eq!(book.verify_integrity(), Ok(()));
+
+        // It still trades, and reducing it must name a size that meets the rules
+        assert!(broke(book.reduce_quantity(buy_id, Quantity::new(100).unwrap()), QuantityRule::MinimumLot(500)));
+        let trades = book.add_order(create_test_order(OrderSide::Sell, 10000, 500)).unwrap();
+        assert_eq!(trades[0].quantity.value(), 100);
+        assert!(book.get_order(buy_id).is_none());
+    }
+}
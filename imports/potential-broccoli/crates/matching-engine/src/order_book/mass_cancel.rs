@@ -0,0 +1,334 @@
+//! Mass cancellation of resting orders
+//!
+//! Clears one or both sides of the book, a price range on one side, or every
+//! order older than a cutoff, in a single mutation. Every level changed is
+//! reported to the depth feed. Pending stops and parked orders are not resting
+//! and are left alone, as are recent trades and positions.
+
+use super::{LimitOrderBook, OrderBookOp};
+use crate::{types::UserId, Order, OrderSide, Price};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Which of an order's timestamps an age-based sweep compares
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum OrderTimestamp {
+    /// When the order was created
+    Created,
+    /// When the order last changed, including partial fills
+    Updated,
+}
+
+impl LimitOrderBook {
+    /// Cancels every resting order, returning them best price first on each side, bids first
+    pub fn cancel_all(&mut self) -> Vec<Order> {
+        self.apply(OrderBookOp::CancelAll).orders()
+    }
+    
+    pub(super) fn cancel_every_order(&mut self) -> Vec<Order> {
+        self.begin_mutation();
+        let mut cancelled = self.clear_side(OrderSide::Buy);
+        cancelled.extend(self.clear_side(OrderSide::Sell));
+        self.prune_brackets();
+        self.end_mutation();
+        cancelled
+    }
+    
+    /// Cancels every resting order on one side, returning them best price first
+    pub fn cancel_all_side(&mut self, side: OrderSide) -> Vec<Order> {
+        self.apply(OrderBookOp::CancelAllSide(side)).orders()
+    }
+    
+    pub(super) fn cancel_side(&mut self, side: OrderSide) -> Vec<Order> {
+        self.begin_mutation();
+        let cancelled = self.clear_side(side);
+        self.prune_brackets();
+        self.end_mutation();
+        cancelled
+    }
+    
+    /// Cancels every resting order on one side priced within `from..=to`,
+    /// returning them best price first
+    /// 
+    /// The bounds may be given in either order. Only the levels in the range
+    /// are visited, however deep the rest of the side is.
+    pub fn cancel_orders_in_range(&mut self, side: OrderSide, from: Price, to: Price) -> Vec<Order> {
+        self.apply(OrderBookOp::CancelInRange { side, from, to }).orders()
+    }
+    
+    pub(super) fn cancel_range(&mut self, side: OrderSide, from: Price, to: Price) -> Vec<Order> {
+        let range = from.min(to)..=from.max(to);
+        let prices = match side {
+            OrderSide::Buy => self.bids.prices_in(range),
+            OrderSide::Sell => self.asks.prices_in(range),
+        };
+        if prices.is_empty() {
+            return Vec::new();
+        }
+        self.begin_mutation();
+        let cancelled = self.cancel_levels(side, prices);
+        self.prune_brackets();
+        self.end_mutation();
+        cancelled
+    }
+    
+    /// Cancels every resting order whose chosen timestamp is before `cutoff`,
+    /// optionally only those of one user
+    /// 
+    /// Timestamps are not indexed, so this is a single pass over every resting
+    /// order on both sides: O(n) in the size of the book. Levels left empty are
+    /// removed. Returns the cancelled orders, bids first.
+    pub fn cancel_orders_older_than(
+        &mut self,
+        cutoff: DateTime<Utc>,
+        timestamp: OrderTimestamp,
+        user: Option<&UserId>,
+    ) -> Vec<Order> {
+        self.apply(OrderBookOp::CancelOlderThan { cutoff, timestamp, user_id: user.cloned() }).orders()
+    }
+    
+    pub(super) fn cancel_aged(
+        &mut self,
+        cutoff: DateTime<Utc>,
+        timestamp: OrderTimestamp,
+        user: Option<&UserId>,
+    ) -> Vec<Order> {
+        let now = self.now();
+        self.begin_mutation();
+        let mut cancelled = self.extract_resting(|order| {
+            let at = match timestamp {
+                OrderTimestamp::Created => order.created_at,
+                OrderTimestamp::Updated => order.updated_at,
+            };
+            at < cutoff && user.is_none_or(|user| order.user_id == *user)
+        });
+        for order in &mut cancelled {
+            order.cancel_at(now);
+        }
+        self.prune_brackets();
+        self.end_mutation();
+        cancelled
+    }
+    
+    /// Removes every resting order matching `filter` in one pass over both sides,
+    /// bids first, keeping levels, the order index, and expiries consistent
+    pub(super) fn extract_resting(&mut self, mut filter: impl FnMut(&Order) -> bool) -> Vec<Order> {
+        let mut extracted = Vec::new();
+        let mut emptied = Vec::new();
+        let mut touched = Vec::new();
+        
+        for (side, levels) in [(OrderSide::Buy, &mut self.bids), (OrderSide::Sell, &mut self.asks)] {
+            levels.for_each_mut(|mut orders| {
+                let matched = orders.extract(&mut filter);
+                if matched.is_empty() {
+                    return;
+                }
+                extracted.extend(matched);
+                touched.push((side, orders.price()));
+                if orders.is_empty() {
+                    emptied.push((side, orders.price()));
+                }
+            });
+        }
+        
+        for (side, price) in touched {
+            self.touch_level(side, price);
+        }
+        for (side, price) in emptied {
+            match side {
+                OrderSide::Buy => self.bids.remove(&price),
+                OrderSide::Sell => self.asks.remove(&price),
+            };
+        }
+        for order in &extracted {
+            self.orders.remove(&order.id);
+            if let Some(expires_at) = order.expires_at {
+                self.untrack_expiry(order.id, expires_at);
+            }
+        }
+        extracted
+    }
+    
+    fn clear_side(&mut self, side: OrderSide) -> Vec<Order> {
+        let prices = match side {
+            OrderSide::Buy => self.bids.iter().map(|(price, _)| *price).collect(),
+            OrderSide::Sell => self.asks.iter().map(|(price, _)| *price).collect(),
+        };
+        self.cancel_levels(side, prices)
+    }
+    
+    /// Removes the given levels, cancelling their orders
+    fn cancel_levels(&mut self, side: OrderSide, prices: Vec<Price>) -> Vec<Order> {
+        let now = self.now();
+        let levels = match side {
+            OrderSide::Buy => &mut self.bids,
+            OrderSide::Sell => &mut self.asks,
+        };
+        let mut cancelled = Vec::new();
+        for price in &prices {
+            cancelled.extend(levels.remove(price).into_iter().flatten());
+        }
+        
+        for price in prices {
+            self.touch_level(side, price);
+        }
+        for order in &mut cancelled {
+            self.orders.remove(&order.id);
+            if let Some(expires_at) = order.expires_at {
+                self.untrack_expiry(order.id, expires_at);
+            }
+            order.cancel_at(now);
+        }
+        cancelled
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        types::UserId,
+        LimitOrderBook, Order, OrderSide, OrderStatus, OrderTimestamp, Price,
+    };
+    use chrono::{Duration, Utc};
+    use crate::order_book::test_support::create_test_order;
+
+    /// 150 bids from 99.00 down and 150 asks from 101.00 up, after one trade at 100.00
+    fn busy_book() -> LimitOrderBook {
+        let mut book = LimitOrderBook::new("MASS".to_string()).unwrap();
+        book.add_order(create_test_order(OrderSide::Sell, 10000, 10)).unwrap();
+        book.add_order(create_test_order(OrderSide::Buy, 10000, 10)).unwrap();
+        for i in 0..150 {
+            book.add_order(create_test_order(OrderSide::Buy, 9900 - i % 30, 10)).unwrap();
+            book.add_order(create_test_order(OrderSide::Sell, 10100 + i % 30, 10)).unwrap();
+        }
+        book
+    }
+
+    #[test]
+    fn test_cancel_all_empties_book() {
+        let mut book = busy_book();
+        
+        let cancelled = book.cancel_all();
+        assert_eq!(cancelled.len(), 300);
+        assert!(cancelled.iter().all(|order| order.status == OrderStatus::Cancelled));
+        assert_eq!(cancelled[0].price.as_cents(), 9900);
+        assert!(book.is_empty());
+        assert_eq!(book.order_count(), 0);
+        assert!(book.best_bid().is_none() && book.best_ask().is_none());
+        assert!(book.market_depth(10).bids.is_empty());
+        assert_eq!(book.recent_trades().len(), 1);
+        
+        // The cleared book matches fresh orders normally
+        let ask = create_test_order(OrderSide::Sell, 10050, 20);
+        let ask_id = ask.id;
+        book.add_order(ask).unwrap();
+        let trades = book.add_order(create_test_order(OrderSide::Buy, 10100, 15)).unwrap();
+        assert_eq!((trades[0].sell_order_id, trades[0].price.as_cents(), trades[0].quantity.value()), (ask_id, 10050, 15));
+        assert_eq!(book.best_ask_quantity().unwrap().value(), 5);
+    }
+
+    #[test]
+    fn test_cancel_all_side_leaves_other_side() {
+        let mut book = busy_book();
+        
+        let cancelled = book.cancel_all_side(OrderSide::Sell);
+        assert_eq!(cancelled.len(), 150);
+        assert!(book.best_ask().is_none());
+        assert_eq!(book.best_bid().unwrap().as_cents(), 9900);
+        assert_eq!(book.order_count(), 150);
+        
+        let trades = book.add_order(create_test_order(OrderSide::Sell, 9900, 10)).unwrap();
+        assert_eq!(trades.len(), 1);
+    }
+
+    #[test]
+    fn test_cancel_orders_in_range() {
+        let mut book = busy_book();
+        let price = |cents| Price::from_cents(cents).unwrap();
+        
+        // Asks from 101.20 through 101.29, bounds given high to low
+        let cancelled = book.cancel_orders_in_range(OrderSide::Sell, price(10129), price(10120));
+        assert_eq!(cancelled.len(), 50);
+        assert!(cancelled.iter().all(|order| order.status == OrderStatus::Cancelled));
+        assert_eq!((cancelled[0].price.as_cents(), cancelled[49].price.as_cents()), (10120, 10129));
+        assert_eq!(book.order_count(), 250);
+        assert!(book.get_order(cancelled[0].id).is_none());
+        assert_eq!(book.best_ask().unwrap().as_cents(), 10100);
+        assert_eq!(book.market_depth(100).asks.last().unwrap().price.as_cents(), 10119);
+        
+        // Bids come back best first
+        let cancelled = book.cancel_orders_in_range(OrderSide::Buy, price(9890), price(9899));
+        assert_eq!((cancelled[0].price.as_cents(), cancelled.last().unwrap().price.as_cents()), (9899, 9890));
+        
+        assert!(book.cancel_orders_in_range(OrderSide::Buy, price(9000), price(9500)).is_empty());
+        assert_eq!(book.order_count(), 200);
+    }
+
+    #[test]
+    fn test_cancel_orders_in_range_covering_side() {
+        let mut ranged = busy_book();
+        let mut cleared = busy_book();
+        
+        let cancelled = ranged.cancel_orders_in_range(OrderSide::Sell, Price::from_cents(1).unwrap(), Price::from_cents(1_000_000).unwrap());
+        let expected = cleared.cancel_all_side(OrderSide::Sell);
+        let prices = |orders: &[Order]| orders.iter().map(|order| order.price).collect::<Vec<_>>();
+        assert_eq!(prices(&cancelled), prices(&expected));
+        assert_eq!(ranged.market_depth(100), cleared.market_depth(100));
+        assert_eq!(ranged.order_count(), 150);
+    }
+
+    #[test]
+    fn test_cancel_orders_older_than_by_timestamp() {
+        let cutoff = Utc::now() - Duration::minutes(1);
+        let aged = |mut order: Order| {
+            order.created_at = cutoff - Duration::minutes(5);
+            order.updated_at = order.created_at;
+            order
+        };
+        let setup = || {
+            let mut book = LimitOrderBook::new("AGE".to_string()).unwrap();
+            let stale = aged(create_test_order(OrderSide::Buy, 10000, 100));
+            let partly_filled = aged(create_test_order(OrderSide::Sell, 10100, 100));
+            let ids = (stale.id, partly_filled.id);
+            book.add_order(stale).unwrap();
+            book.add_order(partly_filled).unwrap();
+            book.add_order(create_test_order(OrderSide::Buy, 10000, 50)).unwrap();
+            
+            // Trades after the cutoff, refreshing the resting ask's `updated_at` only
+            book.add_order(create_test_order(OrderSide::Buy, 10100, 40)).unwrap();
+            (book, ids)
+        };
+        
+        let (mut book, (stale_id, partly_filled_id)) = setup();
+        let cancelled = book.cancel_orders_older_than(cutoff, OrderTimestamp::Created, None);
+        let ids: Vec<_> = cancelled.iter().map(|order| order.id).collect();
+        assert_eq!(ids, vec![stale_id, partly_filled_id]);
+        assert!(cancelled.iter().all(|order| order.status == OrderStatus::Cancelled));
+        assert!(book.best_ask().is_none());
+        assert_eq!(book.order_count(), 1);
+        
+        let (mut book, (stale_id, partly_filled_id)) = setup();
+        let cancelled = book.cancel_orders_older_than(cutoff, OrderTimestamp::Updated, None);
+        assert_eq!(cancelled.len(), 1);
+        assert_eq!(cancelled[0].id, stale_id);
+        assert_eq!(book.get_order(partly_filled_id).unwrap().remaining_quantity.value(), 60);
+    }
+
+    #[test]
+    fn test_cancel_orders_older_than_scoped_to_user() {
+        let mut book = LimitOrderBook::new("AGE".to_string()).unwrap();
+        let mut other = create_test_order(OrderSide::Buy, 9900, 10);
+        other.user_id = UserId::new("other_user".to_string());
+        book.add_order(other).unwrap();
+        for i in 0..5 {
+            book.add_order(create_test_order(OrderSide::Sell, 10100 + i, 10)).unwrap();
+        }
+        
+        let user = UserId::new("test_user".to_string());
+        let cancelled = book.cancel_orders_older_than(Utc::now() + Duration::seconds(1), OrderTimestamp::Created, Some(&user));
+        assert_eq!(cancelled.len(), 5);
+        assert!(book.best_ask().is_none());
+        assert_eq!(book.best_bid().unwrap().as_cents(), 9900);
+        assert_eq!(book.market_depth(10).asks.len(), 0);
+    }
+}
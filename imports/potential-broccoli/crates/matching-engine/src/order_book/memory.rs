@@ -0,0 +1,208 @@
+//! Memory usage estimation for capacity planning
+//!
+//! Figures are estimates built from `size_of` and tracked container lengths
+//! and capacities, not allocator introspection:
+//! - Orders count one slab slot each (the order plus its links to its queue
+//!   neighbours) plus the bytes of their heap strings (string length, as
+//!   `String` capacity isn't exposed through the ID newtypes). A user ID
+//!   shared between orders counts once.
+//! - Price levels count one BTreeMap entry (tick count key plus level
+//!   header) per level in either storage tier, with B-tree node overhead
+//!   folded in as a fixed factor.
+//! - Spare capacity is `(capacity - len) * size_of::<T>()` for each Vec.
+//!   The order slabs count every slot not holding an order, freed or never
+//!   used, as spare.
+//! - Hash maps count one bucket plus one control byte per slot of capacity.
+//! - The expiry index counts one B-tree entry per distinct expiry plus its
+//!   order ID list at capacity.
+//! - The revision log counts each history at capacity plus the length of
+//!   its context strings.
+//! - Session archives count their inline size plus trade, expired order,
+//!   and user counter containers at capacity (heap strings excluded).
+
+use super::{levels::PriceLevel, slab::{OrderLocation, OrderSlab}, LimitOrderBook, Trade};
+use crate::types::OrderId;
+use serde::{Deserialize, Serialize};
+use chrono::{DateTime, Utc};
+use std::collections::HashSet;
+use std::mem::size_of;
+
+/// Approximate per-entry overhead of a B-tree node, in bytes
+const BTREE_ENTRY_OVERHEAD: usize = 16;
+
+/// Estimated memory usage of an order book, broken down by component
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct MemoryReport {
+    /// Number of resting orders
+    pub order_count: usize,
+    /// Inline size of resting orders
+    pub order_bytes: usize,
+    /// Heap strings owned by resting orders
+    pub order_heap_bytes: usize,
+    /// Number of price levels across both sides
+    pub price_level_count: usize,
+    /// BTreeMap entries holding the price levels
+    pub price_level_bytes: usize,
+    /// Order slab slots not holding an order
+    pub order_spare_bytes: usize,
+    /// Order ID lookup table
+    pub order_index_bytes: usize,
+    /// Good-till-date expiry index
+    pub expiry_index_bytes: usize,
+    /// Retained recent trades
+    pub recent_trade_bytes: usize,
+    /// Allocated but unused capacity in the recent trade buffer
+    pub recent_trade_spare_bytes: usize,
+    /// Order amendment histories
+    pub revision_log_bytes: usize,
+    /// Retained session archives
+    pub session_archive_bytes: usize,
+}
+
+impl MemoryReport {
+    /// Total estimated bytes across all components
+    pub fn total_bytes(&self) -> usize {
+        self.order_bytes
+            + self.order_heap_bytes
+            + self.order_spare_bytes
+            + self.price_level_bytes
+            + self.order_index_bytes
+            + self.expiry_index_bytes
+            + self.recent_trade_bytes
+            + self.recent_trade_spare_bytes
+            + self.revision_log_bytes
+            + self.session_archive_bytes
+    }
+    
+    /// Estimated bytes allocated but not currently in use
+    pub fn spare_bytes(&self) -> usize {
+        self.order_spare_bytes + self.recent_trade_spare_bytes
+    }
+}
+
+impl LimitOrderBook {
+    /// Estimates the memory used by each major component of the book
+    ///
+    /// See the module documentation for the estimation methodology.
+    pub fn memory_report(&self) -> MemoryReport {
+        let mut report = MemoryReport::default();
+        
+        let mut user_ids = HashSet::new();
+        for orders in self.bids.values().chain(self.asks.values()) {
+            report.price_level_count += 1;
+            report.order_count += orders.len();
+            report.order_bytes += orders.len() * OrderSlab::slot_size();
+            report.order_heap_bytes += orders.iter()
+                .filter(|order| user_ids.insert(order.user_id.storage()))
+                .map(|order| order.user_id.as_str().len())
+                .sum::<usize>();
+        }
+        report.price_level_bytes = report.price_level_count
+            * (size_of::<i64>() + size_of::<PriceLevel>() + BTREE_ENTRY_OVERHEAD);
+        report.order_spare_bytes = [&self.bids, &self.asks].iter()
+            .map(|levels| (levels.slot_capacity() - levels.order_count()) * OrderSlab::slot_size())
+            .sum();
+        
+        report.order_index_bytes = self.orders.capacity()
+            * (size_of::<(OrderId, OrderLocation)>() + 1);
+        
+        report.expiry_index_bytes = self.expiries.values()
+            .map(|order_ids| size_of::<DateTime<Utc>>() + size_of::<Vec<OrderId>>() + BTREE_ENTRY_OVERHEAD
+                + order_ids.capacity() * size_of::<OrderId>())
+            .sum();
+        
+        report.recent_trade_bytes = self.recent_trades.len() * size_of::<Trade>();
+        report.recent_trade_spare_bytes =
+            (self.recent_trades.capacity() - self.recent_trades.len()) * size_of::<Trade>();
+        
+        report.revision_log_bytes = self.revision_log.as_ref().map_or(0, |log| log.estimated_bytes());
+        report.session_archive_bytes = self.session_archives.iter()
+            .map(|archive| archive.estimated_bytes())
+            .sum();
+        
+        report
+    }
+    
+    /// Releases spare capacity held by the book's containers
+    ///
+    /// Useful after a mass cancel or purge leaves queues and lookups
+    /// over-allocated. Resting orders move to new slots, packed in queue
+    /// order, so their slabs hold no free ones.
+    pub fn shrink_to_fit(&mut self) {
+        for levels in [&mut self.bids, &mut self.asks] {
+            levels.compact();
+            for (_, orders) in levels.iter() {
+                for (handle, order) in orders.entries() {
+                    if let Some(location) = self.orders.get_mut(&order.id) {
+                        location.handle = handle;
+                    }
+                }
+            }
+        }
+        self.orders.shrink_to_fit();
+        self.recent_trades.shrink_to_fit();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{LimitOrderBook, Order, OrderSide, Price, Quantity, types::{OrderId, UserIdPool}};
+    use crate::order_book::test_support::create_test_order;
+
+    #[test]
+    fn test_memory_report_tracks_fill_and_mass_cancel() {
+        let mut book = LimitOrderBook::new("MEM".to_string()).unwrap();
+        let empty = book.memory_report();
+        assert_eq!(empty.order_count, 0);
+        assert_eq!(empty.price_level_count, 0);
+        
+        // Build up deep queues at a handful of levels
+        let mut ids = Vec::new();
+        for i in 0..500 {
+            let order = create_test_order(OrderSide::Buy, 10000 - (i % 5), 10);
+            ids.push(order.id);
+            book.add_order(order).unwrap();
+        }
+        book.add_order(create_test_order(OrderSide::Sell, 10010, 10)).unwrap();
+        
+        let full = book.memory_report();
+        assert_eq!(full.order_count, 501);
+        assert_eq!(full.price_level_count, 6);
+        assert!(full.order_bytes > 0 && full.order_heap_bytes == 501 * "test_user".len());
+        assert!(full.total_bytes() > empty.total_bytes());
+        
+        // Fill some orders, then cancel most of what remains
+        book.add_order(create_test_order(OrderSide::Sell, 9990, 200)).unwrap();
+        assert_eq!(book.recent_trades().len(), 20);
+        for id in &ids[..490] {
+            let _ = book.cancel_order(*id);
+        }
+        
+        let cancelled = book.memory_report();
+        assert_eq!(cancelled.order_count, book.order_count());
+        assert!(cancelled.order_bytes < full.order_bytes);
+        assert!(cancelled.recent_trade_bytes > full.recent_trade_bytes);
+        
+        book.shrink_to_fit();
+        let shrunk = book.memory_report();
+        assert_eq!(shrunk.order_bytes, cancelled.order_bytes);
+        assert!(shrunk.spare_bytes() < cancelled.spare_bytes());
+        assert!(cancelled.order_spare_bytes > 0);
+        assert_eq!(shrunk.order_spare_bytes, 0);
+        assert_eq!(book.verify_integrity(), Ok(()));
+        assert!(shrunk.order_index_bytes <= cancelled.order_index_bytes);
+        assert!(shrunk.total_bytes() < cancelled.total_bytes());
+    }
+    
+    #[test]
+    fn test_pooled_user_ids_count_once() {
+        let mut book = LimitOrderBook::new("MEM".to_string()).unwrap();
+        let mut pool = UserIdPool::new();
+        for i in 0..100 {
+            let user = pool.get_or_intern(["alice", "bob"][i % 2]);
+            let price = Price::from_cents(10000 - i as i64).unwrap();
+            book.add_order(Order::new(OrderId::new(), user, OrderSide::Buy, price, Quantity::new(10).unwrap())).unwrap();
+        }
+        assert_eq!(book.memory_report().order_heap_bytes, "alice".len() + "bob".len());
+    }
+}
@@ -0,0 +1,164 @@
+//! Versioned snapshots and their migrations
+//!
+//! A [`SnapshotEnvelope`] tags a JSON snapshot with the format version it
+//! was written in. Restoring runs the payload through each migration from
+//! that version up to [`CURRENT_SNAPSHOT_VERSION`], then validates it like
+//! any other snapshot. Snapshots written before the envelope existed are
+//! bare books and are read as version 1.
+//!
+//! Fields added without changing the meaning of older data only need a
+//! serde default; a migration is for data an old snapshot lacks but a
+//! current book relies on. A snapshot newer than this library is rejected
+//! rather than loaded with fields silently dropped.
+
+use super::LimitOrderBook;
+use crate::MatchingEngineError;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Snapshot format version written by this library
+pub const CURRENT_SNAPSHOT_VERSION: u32 = 2;
+
+/// Migrations in order, the first taking version 1 to version 2
+const MIGRATIONS: [fn(Value) -> crate::Result<Value>; 1] = [migrate_v1_to_v2];
+
+/// A JSON book snapshot tagged with its format version
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SnapshotEnvelope {
+    pub version: u32,
+    pub payload: Value,
+}
+
+impl SnapshotEnvelope {
+    /// Parses an enveloped snapshot, reading a bare book as version 1
+    pub fn from_json(json: &str) -> crate::Result<Self> {
+        let value: Value = serde_json::from_str(json).map_err(|e| MatchingEngineError::DeserializationError(e.to_string()))?;
+        let enveloped = value.as_object().is_some_and(|object| object.contains_key("version") && object.contains_key("payload"));
+        if enveloped {
+            serde_json::from_value(value).map_err(|e| MatchingEngineError::DeserializationError(e.to_string()))
+        } else {
+            Ok(Self { version: 1, payload: value })
+        }
+    }
+
+    /// Serializes the envelope to JSON
+    pub fn to_json(&self) -> crate::Result<String> {
+        serde_json::to_string(self).map_err(|e| MatchingEngineError::SerializationError(e.to_string()))
+    }
+}
+
+impl LimitOrderBook {
+    /// Captures the book in the current snapshot format
+    pub fn to_snapshot(&self) -> crate::Result<SnapshotEnvelope> {
+        let payload = serde_json::to_value(self).map_err(|e| MatchingEngineError::SerializationError(e.to_string()))?;
+        Ok(SnapshotEnvelope { version: CURRENT_SNAPSHOT_VERSION, payload })
+    }
+
+    /// Restores a book from a snapshot of any supported version, migrating it forward first
+    pub fn from_snapshot(envelope: SnapshotEnvelope) -> crate::Result<Self> {
+        let SnapshotEnvelope { version, mut payload } = envelope;
+        if version == 0 || version > CURRENT_SNAPSHOT_VERSION {
+            return Err(MatchingEngineError::DeserializationError(format!(
+                "Snapshot version {version} is not supported (this library reads versions 1 through {CURRENT_SNAPSHOT_VERSION})"
+            )));
+        }
+        for migrate in &MIGRATIONS[version as usize - 1..] {
+            payload = migrate(payload)?;
+        }
+        serde_json::from_value(payload).map_err(|e| MatchingEngineError::DeserializationError(e.to_string()))
+    }
+}
+
+/// Numbers the recent trades of a version 1 snapshot and restores the last trade price
+///
+/// Version 1 trades carry no trade id, so left alone they would all read as
+/// id 0 and the book would number new trades from 1, colliding with them.
+/// They're numbered oldest first and the book continues after the last one.
+/// Trades that already have an id are kept as they are.
+fn migrate_v1_to_v2(mut payload: Value) -> crate::Result<Value> {
+    let book = payload.as_object_mut()
+        .ok_or_else(|| MatchingEngineError::DeserializationError("Snapshot payload is not a book".to_string()))?;
+
+    let mut next_trade_id = book.get("next_trade_id").and_then(Value::as_u64).unwrap_or(1);
+    let mut last_trade_price = None;
+    if let Some(Value::Array(trades)) = book.get_mut("recent_trades") {
+        for trade in trades.iter_mut().filter_map(Value::as_object_mut) {
+            if !trade.contains_key("trade_id") {
+                trade.insert("trade_id".to_string(), next_trade_id.into());
+                next_trade_id += 1;
+            }
+        }
+        last_trade_price = trades.last().and_then(|trade| trade.get("price")).cloned();
+    }
+
+    book.insert("next_trade_id".to_string(), next_trade_id.into());
+    if let Some(price) = last_trade_price {
+        book.entry("last_trade_price").or_insert(price);
+    }
+    Ok(payload)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        OrderSide, Price,
+    };
+    use serde_json::Map;
+    use crate::order_book::test_support::create_test_order;
+
+    const V1_SNAPSHOT: &str = include_str!("../../tests/fixtures/snapshot_v1.json");
+
+    #[test]
+    fn test_v1_fixture_migrates_and_keeps_trading() {
+        let envelope = SnapshotEnvelope::from_json(V1_SNAPSHOT).unwrap();
+        assert_eq!(envelope.version, 1);
+        let mut book = LimitOrderBook::from_snapshot(envelope).unwrap();
+        assert_eq!(book.verify_integrity(), Ok(()));
+        assert_eq!((book.best_bid(), book.best_ask()), (Price::from_cents(14950).ok(), Price::from_cents(15000).ok()));
+        assert_eq!(book.order_count(), 4);
+
+        let ids: Vec<u64> = book.recent_trades().map(|trade| trade.trade_id).collect();
+        assert_eq!(ids, vec![1, 2]);
+        assert_eq!(book.last_trade_price(), Price::from_cents(15000).ok());
+
+        // New trades continue the numbering and restored orders still fill
+        let trades = book.add_order(create_test_order(OrderSide::Buy, 15000, 50)).unwrap();
+        assert_eq!(trades.iter().map(|trade| trade.trade_id).collect::<Vec<_>>(), vec![3]);
+        assert_eq!(book.best_ask(), Price::from_cents(15100).ok());
+        assert_eq!(book.verify_integrity(), Ok(()));
+    }
+
+    #[test]
+    fn test_current_snapshot_round_trips_through_envelope() {
+        let mut book = LimitOrderBook::new("ENV".to_string()).unwrap();
+        book.add_order(create_test_order(OrderSide::Sell, 10000, 30)).unwrap();
+        book.add_order(create_test_order(OrderSide::Buy, 10000, 10)).unwrap();
+        book.add_order(create_test_order(OrderSide::Buy, 9900, 20)).unwrap();
+
+        let json = book.to_snapshot().unwrap().to_json().unwrap();
+        let envelope = SnapshotEnvelope::from_json(&json).unwrap();
+        assert_eq!(envelope.version, CURRENT_SNAPSHOT_VERSION);
+        let restored = LimitOrderBook::from_snapshot(envelope).unwrap();
+        assert_eq!(serde_json::to_value(&restored).unwrap(), serde_json::to_value(&book).unwrap());
+
+        // A bare current book read as version 1 keeps its trade ids
+        let bare = LimitOrderBook::from_snapshot(SnapshotEnvelope::from_json(&book.to_json().unwrap()).unwrap()).unwrap();
+        assert_eq!(serde_json::to_value(&bare).unwrap(), serde_json::to_value(&book).unwrap());
+    }
+
+    #[test]
+    fn test_unknown_versions_fail_loudly() {
+        for version in [0, CURRENT_SNAPSHOT_VERSION + 1] {
+            let envelope = SnapshotEnvelope { version, payload: serde_json::from_str(V1_SNAPSHOT).unwrap() };
+            match LimitOrderBook::from_snapshot(envelope) {
+                Err(MatchingEngineError::DeserializationError(details)) => {
+                    assert!(details.contains(&format!("Snapshot version {version} is not supported")), "{details}");
+                }
+                other => panic!("expected a deserialization error, got {other:?}"),
+            }
+        }
+        assert!(migrate_v1_to_v2(Value::Array(Vec::new())).is_err());
+        assert!(migrate_v1_to_v2(Value::Object(Map::new())).is_ok());
+    }
+}
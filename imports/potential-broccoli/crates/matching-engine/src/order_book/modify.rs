@@ -0,0 +1,337 @@
+//! Order modification (amend and cancel-replace)
+
+use super::{levels::OrderMut, revisions::OrderRevision, LimitOrderBook, OrderBookOp};
+use crate::{
+    events::ModifyResult,
+    types::OrderId,
+    MatchingEngineError, Order, OrderSide, Price, Quantity,
+};
+
+impl LimitOrderBook {
+    /// Modifies a resting or parked order's price and/or total quantity
+    ///
+    /// `new_quantity` is the order's new total size, including anything
+    /// already filled. Reducing the size at an unchanged price amends the order
+    /// in place and keeps its queue position. A price change or size increase
+    /// is a cancel-replace: the order keeps its ID but moves to the back of
+    /// the queue at its new price, and may trade immediately.
+    pub fn modify_order(
+        &mut self,
+        order_id: OrderId,
+        new_price: Option<Price>,
+        new_quantity: Option<Quantity>,
+    ) -> crate::Result<ModifyResult> {
+        self.apply(OrderBookOp::Modify { order_id, new_price, new_quantity, context: None }).modified()
+    }
+    
+    /// Modifies an order as [`modify_order`](Self::modify_order), recording
+    /// `context` with the amendment in the order's revision history
+    pub fn modify_order_with_context(
+        &mut self,
+        order_id: OrderId,
+        new_price: Option<Price>,
+        new_quantity: Option<Quantity>,
+        context: impl Into<String>,
+    ) -> crate::Result<ModifyResult> {
+        self.apply(OrderBookOp::Modify { order_id, new_price, new_quantity, context: Some(context.into()) }).modified()
+    }
+    
+    /// Reduces a resting order's remaining quantity in place, keeping its queue position
+    /// 
+    /// `new_quantity` is the new remaining quantity. It must be positive (use
+    /// `cancel_order` to remove the order) and no larger than the current
+    /// remaining quantity.
+    pub fn reduce_quantity(&mut self, order_id: OrderId, new_quantity: Quantity) -> crate::Result<ModifyResult> {
+        self.apply(OrderBookOp::Reduce { order_id, new_quantity }).modified()
+    }
+    
+    /// Checks a requested new total against the lot rules, then amends the order
+    pub(super) fn modify(
+        &mut self,
+        order_id: OrderId,
+        new_price: Option<Price>,
+        new_quantity: Option<Quantity>,
+        context: Option<String>,
+    ) -> crate::Result<ModifyResult> {
+        if let Some(new_quantity) = new_quantity {
+            self.check_quantity_on_lot(new_quantity)?;
+        }
+        self.apply_modification(order_id, new_price, new_quantity, context)
+    }
+    
+    pub(super) fn reduce(&mut self, order_id: OrderId, new_quantity: Quantity) -> crate::Result<ModifyResult> {
+        let order = self.resting_order(order_id)
+            .or_else(|| self.parked_order(order_id))
+            .ok_or_else(|| MatchingEngineError::OrderNotFound(order_id.to_string()))?;
+        if new_quantity.value() == 0 {
+            return Err(MatchingEngineError::InvalidQuantity(
+                "cannot reduce an order to zero, cancel it instead".to_string()
+            ));
+        }
+        if new_quantity > order.remaining_quantity {
+            return Err(MatchingEngineError::InvalidQuantity(format!(
+                "new quantity {} exceeds the remaining quantity {}", new_quantity, order.remaining_quantity
+            )));
+        }
+        self.check_quantity_on_lot(new_quantity)?;
+        let total = Quantity::new(order.filled_quantity().value() + new_quantity.value())?;
+        self.apply_modification(order_id, None, Some(total), None)
+    }
+    
+    pub(super) fn apply_modification(
+        &mut self,
+        order_id: OrderId,
+        new_price: Option<Price>,
+        new_quantity: Option<Quantity>,
+        context: Option<String>,
+    ) -> crate::Result<ModifyResult> {
+        self.check_amendable()?;
+        if self.parked_order(order_id).is_some() {
+            return self.amend_parked(order_id, new_price, new_quantity, context);
+        }
+        let previous = self.resting_order(order_id)
+            .cloned()
+            .ok_or_else(|| MatchingEngineError::OrderNotFound(order_id.to_string()))?;
+        
+        // Validate everything before touching the book
+        let new_price = new_price.unwrap_or(previous.price);
+        let new_quantity = new_quantity.unwrap_or(previous.original_quantity);
+        let filled = previous.filled_quantity().value();
+        if new_quantity.value() <= filled {
+            return Err(MatchingEngineError::InvalidQuantity(format!(
+                "new quantity {} does not exceed the {} already filled", new_quantity, filled
+            )));
+        }
+        if new_price == previous.price && new_quantity == previous.original_quantity {
+            return Ok(ModifyResult {
+                status: previous.status,
+                previous,
+                trades: Vec::new(),
+                events: Vec::new(),
+                priority_lost: false,
+            });
+        }
+        
+        let priority_lost = new_price != previous.price || new_quantity > previous.original_quantity;
+        if priority_lost {
+            let mut amended = previous.clone();
+            amended.price = new_price;
+            self.check_state()?;
+            if new_price != previous.price {
+                self.check_price_scale(&amended)?;
+                self.check_price_band(&amended)?;
+            }
+            self.check_post_only(&amended)?;
+        }
+        if new_quantity > previous.original_quantity {
+            let mut amended = previous.clone();
+            amended.remaining_quantity = Quantity::new_allow_zero(new_quantity.value() - filled);
+            self.check_position_limit(&amended, false)?;
+            if self.check_reduce_only(&amended)? > 0 {
+                return Err(self.reduce_only_error(&amended));
+            }
+        }
+        
+        self.begin_mutation();
+        let now = self.now();
+        let revision = OrderRevision {
+            sequence: self.sequence,
+            timestamp: now,
+            old_price: previous.price,
+            new_price,
+            old_qty: previous.original_quantity,
+            new_qty: new_quantity,
+            priority_lost,
+            context,
+        };
+        
+        let mut trades = Vec::new();
+        let mut events = Vec::new();
+        let status;
+        if priority_lost {
+            let mut order = self.take_resting_order(order_id, previous.side, previous.price)?;
+            order.price = new_price;
+            order.original_quantity = new_quantity;
+            order.remaining_quantity = Quantity::new_allow_zero(new_quantity.value() - filled);
+            order.updated_at = revision.timestamp;
+            self.record_revision(order_id, revision);
+            
+            trades = self.match_order(&mut order, &mut events)?;
+            status = order.status;
+            if order.remaining_quantity.value() > 0 && order.is_active() {
+                self.insert_order(order)?;
+            }
+            self.settle_contingent_orders(&mut trades, &mut events)?;
+        } else {
+            let reduction = Quantity::new_allow_zero(previous.original_quantity.value() - new_quantity.value());
+            let mut order = self.resting_order_mut(order_id, previous.side, previous.price)?;
+            order.reduce_at(reduction, now)?;
+            status = order.status;
+            drop(order);
+            self.touch_level(previous.side, previous.price);
+            self.record_revision(order_id, revision);
+        }
+        self.end_mutation();
+        
+        Ok(ModifyResult { previous, status, trades, events, priority_lost })
+    }
+    
+    pub(super) fn record_revision(&mut self, order_id: OrderId, revision: OrderRevision) {
+        if let Some(log) = self.revision_log.as_mut() {
+            log.record(order_id, revision);
+        }
+    }
+    
+    pub(super) fn resting_order_mut(&mut self, order_id: OrderId, side: OrderSide, price: Price) -> crate::Result<OrderMut<'_>> {
+        let levels = match side {
+            OrderSide::Buy => &mut self.bids,
+            OrderSide::Sell => &mut self.asks,
+        };
+        self.orders.get(&order_id)
+            .and_then(|location| levels.order_mut(&price, location.handle))
+            .ok_or_else(|| MatchingEngineError::InvariantViolation(
+                "Order exists in lookup but not at price level".to_string()
+            ))
+    }
+    
+    /// Removes a resting order from the book, returning it unchanged
+    pub(super) fn take_resting_order(&mut self, order_id: OrderId, side: OrderSide, price: Price) -> crate::Result<Order> {
+        let order = self.resting_order_mut(order_id, side, price)?.clone();
+        self.remove_resting_order(order_id, side, price)?;
+        Ok(order)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        types::OrderId,
+        LimitOrderBook, MatchingEngineError, Order, OrderSide, OrderStatus, Price, Quantity,
+    };
+    use crate::order_book::test_support::create_test_order;
+
+    #[test]
+    fn test_size_down_keeps_priority() {
+        let mut book = LimitOrderBook::new("MOD".to_string()).unwrap();
+        let first = create_test_order(OrderSide::Buy, 10000, 100);
+        let first_id = first.id;
+        book.add_order(first).unwrap();
+        book.add_order(create_test_order(OrderSide::Buy, 10000, 100)).unwrap();
+        
+        let result = book.modify_order(first_id, None, Some(Quantity::new(40).unwrap())).unwrap();
+        assert!(!result.priority_lost);
+        assert_eq!(result.previous.remaining_quantity.value(), 100);
+        assert_eq!(book.best_bid_quantity().unwrap().value(), 140);
+        
+        let trades = book.add_order(create_test_order(OrderSide::Sell, 10000, 40)).unwrap();
+        assert_eq!(trades[0].buy_order_id, first_id);
+    }
+
+    #[test]
+    fn test_price_change_loses_priority_and_can_match() {
+        let mut book = LimitOrderBook::new("MOD".to_string()).unwrap();
+        let first = create_test_order(OrderSide::Buy, 10000, 100);
+        let first_id = first.id;
+        book.add_order(first).unwrap();
+        book.add_order(create_test_order(OrderSide::Sell, 10100, 60)).unwrap();
+        
+        let result = book.modify_order(first_id, Some(Price::from_cents(10100).unwrap()), None).unwrap();
+        assert!(result.priority_lost);
+        assert_eq!(result.trades.len(), 1);
+        assert_eq!(result.status, OrderStatus::PartiallyFilled);
+        
+        let order = book.get_order(first_id).unwrap();
+        assert_eq!(order.price.as_cents(), 10100);
+        assert_eq!(order.remaining_quantity.value(), 40);
+        assert_eq!(order.created_at, result.previous.created_at);
+        assert_eq!(book.order_count(), 1);
+    }
+
+    #[test]
+    fn test_size_up_loses_priority() {
+        let mut book = LimitOrderBook::new("MOD".to_string()).unwrap();
+        let first = create_test_order(OrderSide::Sell, 10000, 50);
+        let second = create_test_order(OrderSide::Sell, 10000, 50);
+        let (first_id, second_id) = (first.id, second.id);
+        book.add_order(first).unwrap();
+        book.add_order(second).unwrap();
+        
+        let result = book.modify_order(first_id, None, Some(Quantity::new(80).unwrap())).unwrap();
+        assert!(result.priority_lost);
+        assert!(result.trades.is_empty());
+        assert_eq!(result.previous.id, first_id);
+        assert_eq!(book.get_order(first_id).unwrap().created_at, result.previous.created_at);
+        
+        // The enlarged order now queues behind the second one
+        let trades = book.add_order(create_test_order(OrderSide::Buy, 10000, 60)).unwrap();
+        let fills: Vec<(OrderId, u64)> = trades.iter().map(|trade| (trade.sell_order_id, trade.quantity.value())).collect();
+        assert_eq!(fills, vec![(second_id, 50), (first_id, 10)]);
+    }
+
+    #[test]
+    fn test_reduce_quantity_keeps_arrival_order() {
+        let mut book = LimitOrderBook::new("MOD".to_string()).unwrap();
+        let orders: Vec<Order> = (0..3).map(|_| create_test_order(OrderSide::Buy, 10000, 100)).collect();
+        let ids: Vec<OrderId> = orders.iter().map(|order| order.id).collect();
+        for order in orders {
+            book.add_order(order).unwrap();
+        }
+        
+        let result = book.reduce_quantity(ids[1], Quantity::new(40).unwrap()).unwrap();
+        assert!(!result.priority_lost);
+        assert_eq!(book.best_bid_quantity().unwrap().value(), 240);
+        assert_eq!(book.market_depth(1).bids[0].quantity.value(), 240);
+        
+        let trades = book.add_order(create_test_order(OrderSide::Sell, 10000, 240)).unwrap();
+        let fills: Vec<(OrderId, u64)> = trades.iter().map(|trade| (trade.buy_order_id, trade.quantity.value())).collect();
+        assert_eq!(fills, vec![(ids[0], 100), (ids[1], 40), (ids[2], 100)]);
+    }
+
+    #[test]
+    fn test_reduce_quantity_rejects_zero_and_increase() {
+        let mut book = LimitOrderBook::new("MOD".to_string()).unwrap();
+        let order = create_test_order(OrderSide::Sell, 10000, 100);
+        let order_id = order.id;
+        book.add_order(order).unwrap();
+        book.add_order(create_test_order(OrderSide::Buy, 10000, 30)).unwrap();
+        
+        for quantity in [0, 71] {
+            assert!(matches!(
+                book.reduce_quantity(order_id, Quantity::new_allow_zero(quantity)),
+                Err(MatchingEngineError::InvalidQuantity(_))
+            ));
+        }
+        assert!(matches!(
+            book.reduce_quantity(OrderId::new(), Quantity::new(1).unwrap()),
+            Err(MatchingEngineError::OrderNotFound(_))
+        ));
+        
+        // Remaining 70 of an original 100: reducing to 50 leaves the 30 filled intact
+        book.reduce_quantity(order_id, Quantity::new(50).unwrap()).unwrap();
+        let order = book.get_order(order_id).unwrap();
+        assert_eq!((order.original_quantity.value(), order.remaining_quantity.value()), (80, 50));
+    }
+
+    #[test]
+    fn test_invalid_modification_leaves_book_untouched() {
+        let mut book = LimitOrderBook::new("MOD".to_string()).unwrap();
+        assert!(matches!(
+            book.modify_order(OrderId::new(), None, None),
+            Err(MatchingEngineError::OrderNotFound(_))
+        ));
+        
+        let order = create_test_order(OrderSide::Sell, 10000, 100);
+        let order_id = order.id;
+        book.add_order(order).unwrap();
+        book.add_order(create_test_order(OrderSide::Buy, 10000, 30)).unwrap();
+        let sequence = book.sequence();
+        
+        assert!(matches!(
+            book.modify_order(order_id, Some(Price::from_cents(9900).unwrap()), Some(Quantity::new(30).unwrap())),
+            Err(MatchingEngineError::InvalidQuantity(_))
+        ));
+        assert_eq!(book.sequence(), sequence);
+        let order = book.get_order(order_id).unwrap();
+        assert_eq!((order.price.as_cents(), order.remaining_quantity.value()), (10000, 70));
+    }
+}
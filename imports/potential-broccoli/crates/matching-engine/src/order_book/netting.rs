@@ -0,0 +1,197 @@
+//! Opt-in netting of a user's offsetting orders at the same price
+//!
+//! When a netting-enabled user's incoming order reaches, in matching order,
+//! one of their own resting opposite-side orders at the incoming order's
+//! price, the two are offset against each other instead of trading:
+//! quantities are reduced (or the smaller order cancelled) and matching
+//! carries on with the residual. No trade is produced; both sides learn what
+//! happened through [`OrderEvent::Netted`] events. Better-priced liquidity,
+//! and other users' orders queued ahead at the same price, still trade
+//! first, and nothing is netted while orders collect for the opening
+//! auction. This is separate from self-trade prevention, which acts on
+//! conflicting orders at any price.
+
+use super::LimitOrderBook;
+use crate::{
+    events::{CancelReason, OrderEvent},
+    types::UserId,
+    Order, TimeInForce,
+};
+use chrono::{DateTime, Utc};
+use std::collections::HashSet;
+
+impl LimitOrderBook {
+    /// Enables netting of offsetting same-price orders for a user
+    pub fn enable_netting(&mut self, user_id: UserId) {
+        self.netting_users.insert(user_id);
+    }
+    
+    /// Disables netting for a user
+    pub fn disable_netting(&mut self, user_id: &UserId) {
+        self.netting_users.remove(user_id);
+    }
+    
+    /// Checks if netting is enabled for a user
+    pub fn is_netting_enabled(&self, user_id: &UserId) -> bool {
+        self.netting_users.contains(user_id)
+    }
+}
+
+/// Checks if matching should net `incoming` against `resting` instead of trading
+pub(super) fn nets(netting_users: &HashSet<UserId>, incoming: &Order, resting: &Order) -> bool {
+    // FOK orders must execute in full, so they are never netted
+    resting.user_id == incoming.user_id
+        && resting.price == incoming.price
+        && incoming.time_in_force != TimeInForce::FOK
+        && netting_users.contains(&incoming.user_id)
+}
+
+/// Offsets an incoming order against the same user's resting order
+///
+/// Both are reduced by the smaller remaining quantity, and each one netted
+/// away is reported cancelled. The caller is responsible for removing the
+/// resting order from the book once it is no longer active.
+pub(super) fn net_against(
+    incoming: &mut Order,
+    resting: &mut Order,
+    events: &mut Vec<OrderEvent>,
+    now: DateTime<Utc>,
+) -> crate::Result<()> {
+    let quantity = incoming.remaining_quantity.min(resting.remaining_quantity);
+    incoming.reduce_at(quantity, now)?;
+    resting.reduce_at(quantity, now)?;
+    events.push(OrderEvent::Netted { incoming_order_id: incoming.id, resting_order_id: resting.id, quantity });
+    for order in [&*resting, &*incoming] {
+        if !order.is_active() {
+            events.push(OrderEvent::Cancelled { order: order.clone(), reason: CancelReason::Netting });
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        events::{CancelReason, OrderEvent},
+        LimitOrderBook, OrderSide, OrderStatus,
+        types::UserId,
+    };
+    use crate::order_book::test_support::create_order;
+
+    fn netting_book() -> LimitOrderBook {
+        let mut book = LimitOrderBook::new("NET".to_string()).unwrap();
+        book.enable_netting(UserId::new("mm".to_string()));
+        book
+    }
+
+    #[test]
+    fn test_netting_disabled_by_default() {
+        let mut book = LimitOrderBook::new("NET".to_string()).unwrap();
+        book.add_order(create_order(OrderSide::Sell, 10000, 100, "mm")).unwrap();
+        
+        let result = book.submit_order(create_order(OrderSide::Buy, 10000, 100, "mm")).unwrap();
+        assert_eq!(result.trades.len(), 1); // No netting and no STP: a plain self-match
+        assert!(result.events.is_empty());
+    }
+
+    #[test]
+    fn test_exact_netting() {
+        let mut book = netting_book();
+        let resting = create_order(OrderSide::Sell, 10000, 100, "mm");
+        let resting_id = resting.id;
+        book.add_order(resting).unwrap();
+        
+        let result = book.submit_order(create_order(OrderSide::Buy, 10000, 100, "mm")).unwrap();
+        
+        assert!(result.trades.is_empty());
+        assert_eq!(result.status, OrderStatus::Cancelled);
+        assert!(matches!(
+            result.events[0],
+            OrderEvent::Netted { resting_order_id, quantity, .. }
+                if resting_order_id == resting_id && quantity.value() == 100
+        ));
+        assert_eq!(result.events.len(), 3); // Netted plus both cancellations
+        assert!(book.get_order(resting_id).is_none());
+        assert!(book.is_empty());
+    }
+
+    #[test]
+    fn test_partial_netting_rests_residual() {
+        let mut book = netting_book();
+        let resting = create_order(OrderSide::Sell, 10000, 60, "mm");
+        let resting_id = resting.id;
+        book.add_order(resting).unwrap();
+        book.add_order(create_order(OrderSide::Sell, 10100, 100, "other")).unwrap();
+        
+        let result = book.submit_order(create_order(OrderSide::Buy, 10000, 100, "mm")).unwrap();
+        
+        assert!(result.trades.is_empty());
+        assert!(book.get_order(resting_id).is_none());
+        let residual = book.get_order(result.order_id).unwrap().clone();
+        assert_eq!(residual.remaining_quantity.value(), 40);
+        assert_eq!(residual.filled_quantity().value(), 0);
+        assert_eq!(book.best_bid().unwrap().as_cents(), 10000);
+        assert_eq!(book.best_ask().unwrap().as_cents(), 10100);
+        
+        // The smaller incoming side is absorbed by a larger resting order
+        let result = book.submit_order(create_order(OrderSide::Sell, 10000, 10, "mm")).unwrap();
+        assert!(result.trades.is_empty());
+        assert_eq!(book.get_order(residual.id).unwrap().remaining_quantity.value(), 30);
+        assert!(matches!(
+            result.events.last(),
+            Some(OrderEvent::Cancelled { reason: CancelReason::Netting, order }) if order.id == result.order_id
+        ));
+    }
+
+    #[test]
+    fn test_better_priced_liquidity_trades_before_netting() {
+        let mut book = netting_book();
+        let own = create_order(OrderSide::Sell, 10000, 100, "mm");
+        let own_id = own.id;
+        book.add_order(own).unwrap();
+        book.add_order(create_order(OrderSide::Sell, 9990, 30, "other")).unwrap();
+        book.add_order(create_order(OrderSide::Sell, 10000, 60, "other")).unwrap();
+        
+        // The cheaper offer trades, then mm's own order nets, then the offer queued behind it trades
+        let result = book.submit_order(create_order(OrderSide::Buy, 10000, 150, "mm")).unwrap();
+        let traded: Vec<(i64, u64)> = result.trades.iter().map(|trade| (trade.price.as_cents(), trade.quantity.value())).collect();
+        assert_eq!(traded, vec![(9990, 30), (10000, 20)]);
+        assert!(matches!(
+            result.events[0],
+            OrderEvent::Netted { resting_order_id, quantity, .. } if resting_order_id == own_id && quantity.value() == 100
+        ));
+        assert_eq!(result.status, OrderStatus::Filled);
+        assert_eq!(book.best_ask_quantity().unwrap().value(), 40);
+        assert_eq!(book.net_position(&UserId::new("mm".to_string())), 50);
+        
+        // Other users queued ahead at the price trade before the netting user's order is reached
+        let mut book = netting_book();
+        book.add_order(create_order(OrderSide::Sell, 10000, 20, "other")).unwrap();
+        book.add_order(create_order(OrderSide::Sell, 10000, 100, "mm")).unwrap();
+        let result = book.submit_order(create_order(OrderSide::Buy, 10000, 50, "mm")).unwrap();
+        assert_eq!(result.trades[0].quantity.value(), 20);
+        assert!(matches!(result.events[0], OrderEvent::Netted { quantity, .. } if quantity.value() == 30));
+        assert_eq!(book.best_ask_quantity().unwrap().value(), 70);
+    }
+
+    #[test]
+    fn test_netting_against_partially_filled_order() {
+        let mut book = netting_book();
+        let resting = create_order(OrderSide::Sell, 10000, 100, "mm");
+        let resting_id = resting.id;
+        book.add_order(resting).unwrap();
+        book.add_order(create_order(OrderSide::Buy, 10000, 40, "client")).unwrap();
+        assert_eq!(book.get_order(resting_id).unwrap().remaining_quantity.value(), 60);
+        
+        let result = book.submit_order(create_order(OrderSide::Buy, 10000, 100, "mm")).unwrap();
+        
+        // Only the unfilled 60 can be netted; the filled 40 stays on the record
+        let cancelled = result.events.iter().find_map(|event| match event {
+            OrderEvent::Cancelled { order, .. } if order.id == resting_id => Some(order.clone()),
+            _ => None,
+        }).unwrap();
+        assert_eq!(cancelled.filled_quantity().value(), 40);
+        assert_eq!(book.get_order(result.order_id).unwrap().remaining_quantity.value(), 40);
+        assert!(book.best_ask().is_none());
+    }
+}
@@ -0,0 +1,157 @@
+//! Depth in notional terms
+//!
+//! Notional is price times quantity in shares at the book's quantity scale,
+//! multiplied exactly in `Decimal` with no intermediate rounding, so
+//! cumulative figures are plain sums of per-level ones. Like depth, only displayed liquidity counts.
+
+use super::{quantity_scale, sweep::displayed_quantity, LimitOrderBook, MarketLevel};
+use crate::{OrderSide, Price, Quantity};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+/// A depth level with its notional and the running totals from the touch
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NotionalLevel {
+    pub price: Price,
+    pub quantity: Quantity,
+    /// Price times quantity at this level
+    pub notional: Decimal,
+    /// Quantity from the touch through this level
+    pub cumulative_quantity: u64,
+    /// Notional from the touch through this level
+    pub cumulative_notional: Decimal,
+}
+
+/// Per-level and cumulative notional on both sides
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NotionalDepth {
+    pub bids: Vec<NotionalLevel>,
+    pub asks: Vec<NotionalLevel>,
+}
+
+/// Displayed liquidity within a percentage band around the mid price
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BandDepth {
+    pub mid: Decimal,
+    pub bid_quantity: u64,
+    pub bid_notional: Decimal,
+    pub ask_quantity: u64,
+    pub ask_notional: Decimal,
+}
+
+impl LimitOrderBook {
+    /// Gets notional depth for up to `levels` levels per side, as `market_depth` reports them
+    pub fn notional_depth(&self, levels: usize) -> NotionalDepth {
+        let depth = self.market_depth(levels);
+        NotionalDepth {
+            bids: accumulate(&depth.bids, self.quantity_scale),
+            asks: accumulate(&depth.asks, self.quantity_scale),
+        }
+    }
+
+    /// Gets the displayed quantity and notional priced within `pct` percent of the mid
+    ///
+    /// `pct` is in percentage points: `Decimal::ONE` covers bids at or above
+    /// 99% of the mid and asks at or below 101% of it. Returns `None` unless
+    /// both sides are quoted.
+    pub fn depth_within_pct(&self, pct: Decimal) -> Option<BandDepth> {
+        let mid = self.mid_price()?;
+        let offset = mid * pct / Decimal::ONE_HUNDRED;
+        let (bid_quantity, bid_notional) = self.band_liquidity(OrderSide::Buy, |price| price >= mid - offset);
+        let (ask_quantity, ask_notional) = self.band_liquidity(OrderSide::Sell, |price| price <= mid + offset);
+        Some(BandDepth { mid, bid_quantity, bid_notional, ask_quantity, ask_notional })
+    }
+
+    /// Sums displayed quantity and notional from the touch while `within` holds
+    fn band_liquidity(&self, side: OrderSide, within: impl Fn(Decimal) -> bool) -> (u64, Decimal) {
+        let now = self.now();
+        let levels = match side {
+            OrderSide::Buy => &self.bids,
+            OrderSide::Sell => &self.asks,
+        };
+        levels.iter()
+            .take_while(|(price, _)| within(price.value()))
+            .map(|(price, orders)| (price, displayed_quantity(orders, now)))
+            .fold((0, Decimal::ZERO), |(quantity, notional), (price, displayed)| {
+                (quantity + displayed, notional + quantity_scale::notional(*price, displayed, self.quantity_scale))
+            })
+    }
+}
+
+fn accumulate(levels: &[MarketLevel], scale: u32) -> Vec<NotionalLevel> {
+    let mut cumulative_quantity = 0;
+    let mut cumulative_notional = Decimal::ZERO;
+    levels.iter()
+        .map(|level| {
+            let notional = quantity_scale::notional(level.price, level.quantity.value(), scale);
+            cumulative_quantity += level.quantity.value();
+            cumulative_notional += notional;
+            NotionalLevel {
+                price: level.price,
+                quantity: level.quantity,
+                notional,
+                cumulative_quantity,
+                cumulative_notional,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    use crate::order_book::test_support::create_order_at;
+
+    /// Bids 99.99 x 300, 99.50 x 120, 98.00 x 7; asks 100.01 x 250, 100.75 x 40, 102.00 x 1000
+    fn book() -> LimitOrderBook {
+        let mut book = LimitOrderBook::new("NTNL".to_string()).unwrap();
+        for (side, price, quantity) in [
+            (OrderSide::Buy, "99.99", 300),
+            (OrderSide::Buy, "99.50", 120),
+            (OrderSide::Buy, "98.00", 7),
+            (OrderSide::Sell, "100.01", 250),
+            (OrderSide::Sell, "100.75", 40),
+            (OrderSide::Sell, "102.00", 1000),
+        ] {
+            book.add_order(create_order_at(side, price, quantity)).unwrap();
+        }
+        book
+    }
+
+    #[test]
+    fn test_notional_depth_per_level_and_cumulative() {
+        let depth = book().notional_depth(2);
+
+        let bids: Vec<(Decimal, Decimal)> = depth.bids.iter().map(|level| (level.notional, level.cumulative_notional)).collect();
+        assert_eq!(bids, vec![
+            (Decimal::new(2999700, 2), Decimal::new(2999700, 2)),
+            (Decimal::new(1194000, 2), Decimal::new(4193700, 2)),
+        ]);
+        let asks: Vec<(Decimal, Decimal)> = depth.asks.iter().map(|level| (level.notional, level.cumulative_notional)).collect();
+        assert_eq!(asks, vec![
+            (Decimal::new(2500250, 2), Decimal::new(2500250, 2)),
+            (Decimal::new(403000, 2), Decimal::new(2903250, 2)),
+        ]);
+        assert_eq!(depth.asks[1].cumulative_quantity, 290);
+    }
+
+    #[test]
+    fn test_pct_band_falling_between_levels() {
+        let book = book();
+
+        // Mid 100.00: a 1% band reaches 99.00 and 101.00, between the second and third levels
+        let band = book.depth_within_pct(Decimal::ONE).unwrap();
+        assert_eq!(band.mid, Decimal::new(100, 0));
+        assert_eq!((band.bid_quantity, band.bid_notional), (420, Decimal::new(4193700, 2)));
+        assert_eq!((band.ask_quantity, band.ask_notional), (290, Decimal::new(2903250, 2)));
+
+        // Band edges are inclusive: 2% reaches exactly 98.00 and 102.00
+        let band = book.depth_within_pct(Decimal::TWO).unwrap();
+        assert_eq!((band.bid_quantity, band.ask_quantity), (427, 1290));
+
+        let band = book.depth_within_pct(Decimal::ZERO).unwrap();
+        assert_eq!((band.bid_quantity, band.ask_quantity), (0, 0));
+        assert!(LimitOrderBook::new("NTNL".to_string()).unwrap().depth_within_pct(Decimal::ONE).is_none());
+    }
+}
@@ -0,0 +1,654 @@
+//! Operation log and deterministic replay
+//!
+//! Every order-flow mutation of the book — submissions, seeds, cancels, amendments,
+//! mass cancels, expiry and activation sweeps, state transitions, session
+//! rollover, and clock changes — is an [`OrderBookOp`] applied through
+//! [`LimitOrderBook::apply`]; the public mutators are thin wrappers over it.
+//! So are the setters and resets of state that trading accumulates: net
+//! position overrides, statistics, session statistics, and fee totals
+//! resets, and the recent trade retention. With the op log enabled, each op
+//! is recorded before it is applied, so the log is a complete write-ahead
+//! record of the book's history. Rejected ops are recorded too: replaying
+//! them rejects them again.
+//!
+//! Replaying a log on a fixed [`Clock`] reproduces the book exactly, down to
+//! its snapshot bytes. On the system clock, timestamps and expiry decisions
+//! follow the wall clock of the replay instead. Configuration (fees, limits,
+//! bands, accounts, listeners, feeds) is not part of the log; to replay onto
+//! a configured book, configure it first and `apply` the ops one by one.
+//!
+//! Each logged op carries its op sequence: the count of ops the book had
+//! applied once it was applied. Snapshots carry the book's count too, so
+//! [`LimitOrderBook::recover`] can restore a snapshot and replay only the
+//! part of a log that comes after it, even when the log overlaps it. The
+//! depth sequence of the delta feed can't serve as the key: it only moves
+//! when depth may change, so clock changes, halts, and rejected orders
+//! would share a number with the mutation before them.
+//!
+//! [`OpLogWriter`] and [`OpLogReader`] persist a log as JSON Lines, one op
+//! per line.
+
+use super::{
+    activation::Activation,
+    clock::Clock,
+    session::{SessionArchive, SessionStats},
+    state::BookState,
+    stats::BookStats,
+    LimitOrderBook, OrderTimestamp, Trade,
+};
+use crate::{
+    events::{ExecutionResult, ModifyResult},
+    types::{OrderId, UserId},
+    MatchingEngineError, Order, OrderSide, Price, Quantity,
+};
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::{BufRead, Write};
+
+/// A mutation of the book, as recorded in the op log
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum OrderBookOp {
+    /// Creates the book; the first op of every log and never applied to an existing book
+    Create { symbol: String, clock: Clock },
+    /// Sets the clock the book reads the time from
+    SetClock(Clock),
+    /// Submits an order, as `submit_order`
+    Submit(Order),
+    /// Submits a bracket entry with exits under the given IDs, as `submit_bracket`
+    SubmitBracket {
+        entry: Order,
+        take_profit: Price,
+        stop_loss: Price,
+        take_profit_id: OrderId,
+        stop_loss_id: OrderId,
+    },
+    /// Rests orders without matching them, as `seed_orders`
+    Seed(Vec<Order>),
+    /// Cancels an order by ID
+    Cancel(OrderId),
+    /// Cancels a user's live order by client order ID
+    CancelByClientId { user_id: UserId, client_order_id: String },
+    /// Modifies a resting order's price and/or total quantity
+    Modify {
+        order_id: OrderId,
+        new_price: Option<Price>,
+        new_quantity: Option<Quantity>,
+        context: Option<String>,
+    },
+    /// Reduces a resting order's remaining quantity in place
+    Reduce { order_id: OrderId, new_quantity: Quantity },
+    /// Cancels every resting order
+    CancelAll,
+    /// Cancels every resting order on one side
+    CancelAllSide(OrderSide),
+    /// Cancels the resting orders on one side priced within a range
+    CancelInRange { side: OrderSide, from: Price, to: Price },
+    /// Cancels resting orders whose chosen timestamp is before a cutoff
+    CancelOlderThan { cutoff: DateTime<Utc>, timestamp: OrderTimestamp, user_id: Option<UserId> },
+    /// Expires resting orders due by the given time
+    PurgeExpired(DateTime<Utc>),
+    /// Activates parked orders due by the given time
+    ActivatePending(DateTime<Utc>),
+    /// Moves the book to a trading state
+    SetState(BookState),
+    /// Runs the opening auction
+    Uncross,
+    /// Runs the closing auction
+    RunClosingAuction,
+    /// Expires every Day order
+    EndOfDay,
+    /// Closes the session and starts the next one under the given ID
+    RollSession(String),
+    /// Overrides a user's net position
+    SetNetPosition { user_id: UserId, position: i64 },
+    /// Starts a new trade statistics period
+    ResetStats,
+    /// Starts the session's statistics and user counters over
+    ResetSessionStats,
+    /// Clears the per-user fee totals
+    ResetFeeTotals,
+    /// Sets the number of recent trades retained
+    SetMaxRecentTrades(usize),
+}
+
+/// An op as recorded in the log, with its op sequence
+///
+/// The `Create` that starts a log carries the number of ops the book had
+/// applied before logging began, zero for a fresh book.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LoggedOp {
+    pub sequence: u64,
+    pub op: OrderBookOp,
+}
+
+/// What applying an [`OrderBookOp`] produced
+#[derive(Debug, Clone, PartialEq)]
+pub enum OpResult {
+    /// The op has nothing to report (state, clock, and setting changes)
+    Done,
+    /// A submitted order's full result
+    Executed(ExecutionResult),
+    /// The order a cancel removed
+    Cancelled(Order),
+    /// The result of an amendment
+    Modified(ModifyResult),
+    /// Orders cancelled or expired in bulk, or seeded
+    Orders(Vec<Order>),
+    /// Trades of an auction
+    Trades(Vec<Trade>),
+    /// The outcome of each parked order released, in release order
+    Activated(Vec<Activation>),
+    /// The archive of the session just closed
+    SessionRolled(Box<SessionArchive>),
+    /// The trade statistics period just finished
+    Stats(BookStats),
+    /// The session statistics just reset
+    SessionStats(SessionStats),
+    /// The per-user fee totals just cleared
+    FeeTotals(HashMap<UserId, Decimal>),
+    /// The op was rejected and changed nothing
+    Rejected(MatchingEngineError),
+}
+
+impl OpResult {
+    pub(super) fn executed(self) -> crate::Result<ExecutionResult> {
+        match self {
+            OpResult::Executed(result) => Ok(result),
+            other => Err(other.into_error()),
+        }
+    }
+
+    pub(super) fn cancelled(self) -> crate::Result<Order> {
+        match self {
+            OpResult::Cancelled(order) => Ok(order),
+            other => Err(other.into_error()),
+        }
+    }
+
+    pub(super) fn modified(self) -> crate::Result<ModifyResult> {
+        match self {
+            OpResult::Modified(result) => Ok(result),
+            other => Err(other.into_error()),
+        }
+    }
+
+    pub(super) fn trades(self) -> crate::Result<Vec<Trade>> {
+        match self {
+            OpResult::Trades(trades) => Ok(trades),
+            other => Err(other.into_error()),
+        }
+    }
+
+    pub(super) fn activations(self) -> crate::Result<Vec<Activation>> {
+        match self {
+            OpResult::Activated(activations) => Ok(activations),
+            other => Err(other.into_error()),
+        }
+    }
+
+    pub(super) fn seeded(self) -> crate::Result<Vec<Order>> {
+        match self {
+            OpResult::Orders(orders) => Ok(orders),
+            other => Err(other.into_error()),
+        }
+    }
+
+    pub(super) fn orders(self) -> Vec<Order> {
+        match self {
+            OpResult::Orders(orders) => orders,
+            other => unreachable!("bulk op produced {other:?}"),
+        }
+    }
+
+    pub(super) fn session_archive(self) -> SessionArchive {
+        match self {
+            OpResult::SessionRolled(archive) => *archive,
+            other => unreachable!("session roll produced {other:?}"),
+        }
+    }
+
+    pub(super) fn stats(self) -> BookStats {
+        match self {
+            OpResult::Stats(stats) => stats,
+            other => unreachable!("statistics reset produced {other:?}"),
+        }
+    }
+
+    pub(super) fn session_stats(self) -> SessionStats {
+        match self {
+            OpResult::SessionStats(stats) => stats,
+            other => unreachable!("session statistics reset produced {other:?}"),
+        }
+    }
+
+    pub(super) fn fee_totals(self) -> HashMap<UserId, Decimal> {
+        match self {
+            OpResult::FeeTotals(totals) => totals,
+            other => unreachable!("fee totals reset produced {other:?}"),
+        }
+    }
+
+    fn into_error(self) -> MatchingEngineError {
+        match self {
+            OpResult::Rejected(error) => error,
+            other => unreachable!("op produced {other:?}"),
+        }
+    }
+}
+
+impl<T> From<crate::Result<T>> for OpResult
+where
+    T: Into<OpResult>,
+{
+    fn from(result: crate::Result<T>) -> Self {
+        result.map_or_else(OpResult::Rejected, Into::into)
+    }
+}
+
+impl From<ExecutionResult> for OpResult {
+    fn from(result: ExecutionResult) -> Self {
+        OpResult::Executed(result)
+    }
+}
+
+impl From<Order> for OpResult {
+    fn from(order: Order) -> Self {
+        OpResult::Cancelled(order)
+    }
+}
+
+impl From<Vec<Order>> for OpResult {
+    fn from(orders: Vec<Order>) -> Self {
+        OpResult::Orders(orders)
+    }
+}
+
+impl From<ModifyResult> for OpResult {
+    fn from(result: ModifyResult) -> Self {
+        OpResult::Modified(result)
+    }
+}
+
+impl From<Vec<Trade>> for OpResult {
+    fn from(trades: Vec<Trade>) -> Self {
+        OpResult::Trades(trades)
+    }
+}
+
+impl LimitOrderBook {
+    /// Applies one operation, recording it in the op log first if the log is enabled
+    ///
+    /// Once applied, the op and its result are reported to the listener.
+    pub fn apply(&mut self, op: OrderBookOp) -> OpResult {
+        if let OrderBookOp::Create { symbol, .. } = &op {
+            return OpResult::Rejected(MatchingEngineError::DuplicateSymbol(symbol.clone()));
+        }
+        self.op_sequence += 1;
+        if let Some(log) = &mut self.op_log {
+            log.push(LoggedOp { sequence: self.op_sequence, op: op.clone() });
+        }
+        let observed = self.has_listener().then(|| op.clone());
+
+        let result = match op {
+            OrderBookOp::Create { .. } => unreachable!("rejected above"),
+            OrderBookOp::SetClock(clock) => {
+                self.clock = clock;
+                OpResult::Done
+            },
+            OrderBookOp::Submit(order) => self.submit(order).into(),
+            OrderBookOp::SubmitBracket { entry, take_profit, stop_loss, take_profit_id, stop_loss_id } => {
+                self.place_bracket(entry, take_profit, stop_loss, take_profit_id, stop_loss_id).into()
+            },
+            OrderBookOp::Seed(orders) => self.seed(orders).into(),
+            OrderBookOp::Cancel(order_id) => self.cancel(order_id).into(),
+            OrderBookOp::CancelByClientId { user_id, client_order_id } => {
+                self.cancel_client_order(&user_id, &client_order_id).into()
+            },
+            OrderBookOp::Modify { order_id, new_price, new_quantity, context } => {
+                self.modify(order_id, new_price, new_quantity, context).into()
+            },
+            OrderBookOp::Reduce { order_id, new_quantity } => self.reduce(order_id, new_quantity).into(),
+            OrderBookOp::CancelAll => OpResult::Orders(self.cancel_every_order()),
+            OrderBookOp::CancelAllSide(side) => OpResult::Orders(self.cancel_side(side)),
+            OrderBookOp::CancelInRange { side, from, to } => OpResult::Orders(self.cancel_range(side, from, to)),
+            OrderBookOp::CancelOlderThan { cutoff, timestamp, user_id } => {
+                OpResult::Orders(self.cancel_aged(cutoff, timestamp, user_id.as_ref()))
+            },
+            OrderBookOp::PurgeExpired(now) => OpResult::Orders(self.expire_due(now)),
+            OrderBookOp::ActivatePending(now) => OpResult::Activated(self.activate_due(now)),
+            OrderBookOp::SetState(state) => {
+                self.state = state;
+                OpResult::Done
+            },
+            OrderBookOp::Uncross => self.run_opening_auction().into(),
+            OrderBookOp::RunClosingAuction => self.run_closing_cross().into(),
+            OrderBookOp::EndOfDay => OpResult::Orders(self.expire_day()),
+            OrderBookOp::RollSession(session_id) => OpResult::SessionRolled(Box::new(self.roll(session_id))),
+            OrderBookOp::SetNetPosition { user_id, position } => {
+                self.override_net_position(user_id, position);
+                OpResult::Done
+            },
+            OrderBookOp::ResetStats => OpResult::Stats(std::mem::take(&mut self.stats)),
+            OrderBookOp::ResetSessionStats => OpResult::SessionStats(self.restart_session_stats()),
+            OrderBookOp::ResetFeeTotals => OpResult::FeeTotals(std::mem::take(&mut self.fees_charged)),
+            OrderBookOp::SetMaxRecentTrades(max_recent_trades) => {
+                self.max_recent_trades = max_recent_trades;
+                self.evict_old_trades();
+                OpResult::Done
+            },
+        };
+        if let Some(op) = observed {
+            self.listener.op_applied(self.op_sequence, &op, &result);
+        }
+        result
+    }
+
+    /// Rebuilds a book by applying a log's ops in order
+    ///
+    /// The log must start with [`OrderBookOp::Create`], contain no other
+    /// `Create`, and number its ops without gaps. Ops the book rejects are
+    /// part of its history and don't stop the replay.
+    pub fn replay(ops: impl IntoIterator<Item = LoggedOp>) -> crate::Result<Self> {
+        let mut ops = ops.into_iter();
+        let Some(LoggedOp { sequence, op: OrderBookOp::Create { symbol, clock } }) = ops.next() else {
+            return Err(MatchingEngineError::InvariantViolation("Op log does not start with Create".to_string()));
+        };
+        let mut book = Self::with_clock(symbol, clock)?;
+        book.op_sequence = sequence;
+        for entry in ops {
+            book.apply_logged(entry)?;
+        }
+        Ok(book)
+    }
+
+    /// Restores a snapshot and brings it up to date from the op log
+    ///
+    /// The snapshot may be JSON, enveloped or bare, or a binary snapshot
+    /// when that feature is enabled; loading it migrates and validates it
+    /// like any other restore. Ops the snapshot already includes, those at or
+    /// below its op sequence, are skipped, so the log may start anywhere
+    /// before the snapshot. The rest must follow on from it without gaps:
+    /// a log that starts after the snapshot, or is missing an op, fails
+    /// rather than recovering a book that silently lost history.
+    pub fn recover(snapshot: &[u8], ops_since_snapshot: impl IntoIterator<Item = LoggedOp>) -> crate::Result<Self> {
+        let mut book = Self::load_snapshot(snapshot)?;
+        for entry in ops_since_snapshot {
+            if entry.sequence > book.op_sequence {
+                book.apply_logged(entry)?;
+            }
+        }
+        Ok(book)
+    }
+
+    /// Gets the number of ops applied, the op sequence of the last one
+    pub fn op_sequence(&self) -> u64 {
+        self.op_sequence
+    }
+
+    /// Starts recording every applied op, beginning with a `Create` for this book
+    ///
+    /// Enable it on a fresh book: replaying the log rebuilds the book from
+    /// empty, so anything applied before logging started is missing from it.
+    pub fn enable_op_log(&mut self) {
+        if self.op_log.is_none() {
+            let create = OrderBookOp::Create { symbol: self.symbol.to_string(), clock: self.clock };
+            self.op_log = Some(vec![LoggedOp { sequence: self.op_sequence, op: create }]);
+        }
+    }
+
+    /// Stops recording ops and discards any not yet taken
+    pub fn disable_op_log(&mut self) {
+        self.op_log = None;
+    }
+
+    /// Takes the ops recorded since the last call, oldest first
+    pub fn take_ops(&mut self) -> Vec<LoggedOp> {
+        self.op_log.as_mut().map(std::mem::take).unwrap_or_default()
+    }
+
+    /// Sets the clock the book reads the time from
+    pub fn set_clock(&mut self, clock: Clock) {
+        self.apply(OrderBookOp::SetClock(clock));
+    }
+
+    /// Applies the next op of a log, checking it is the op the book expects
+    pub(crate) fn apply_logged(&mut self, entry: LoggedOp) -> crate::Result<()> {
+        if matches!(entry.op, OrderBookOp::Create { .. }) {
+            return Err(MatchingEngineError::InvariantViolation("Op log creates the book twice".to_string()));
+        }
+        if entry.sequence != self.op_sequence + 1 {
+            return Err(MatchingEngineError::InvariantViolation(format!(
+                "Op log expected op sequence {} but found {}",
+                self.op_sequence + 1,
+                entry.sequence
+            )));
+        }
+        self.apply(entry.op);
+        Ok(())
+    }
+}
+
+/// Appends ops to a JSON Lines op log
+#[derive(Debug)]
+pub struct OpLogWriter<W: Write> {
+    writer: W,
+}
+
+impl<W: Write> OpLogWriter<W> {
+    pub fn new(writer: W) -> Self {
+        Self { writer }
+    }
+
+    /// Writes one op as a line
+    pub fn append(&mut self, op: &LoggedOp) -> crate::Result<()> {
+        let mut line = serde_json::to_vec(op).map_err(|e| MatchingEngineError::SerializationError(e.to_string()))?;
+        line.push(b'\n');
+        self.writer.write_all(&line).map_err(|e| MatchingEngineError::SerializationError(e.to_string()))
+    }
+
+    /// Flushes the underlying writer
+    pub fn flush(&mut self) -> crate::Result<()> {
+        self.writer.flush().map_err(|e| MatchingEngineError::SerializationError(e.to_string()))
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+/// Reads ops back from a JSON Lines op log, skipping blank lines
+///
+/// A final line torn by a crash reads as an error; every op before it is intact.
+#[derive(Debug)]
+pub struct OpLogReader<R: BufRead> {
+    lines: std::io::Lines<R>,
+}
+
+impl<R: BufRead> OpLogReader<R> {
+    pub fn new(reader: R) -> Self {
+        Self { lines: reader.lines() }
+    }
+}
+
+impl<R: BufRead> Iterator for OpLogReader<R> {
+    type Item = crate::Result<LoggedOp>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let line = loop {
+            match self.lines.next()? {
+                Ok(line) if line.trim().is_empty() => continue,
+                Ok(line) => break line,
+                Err(e) => return Some(Err(MatchingEngineError::DeserializationError(e.to_string()))),
+            }
+        };
+        Some(serde_json::from_str(&line).map_err(|e| MatchingEngineError::DeserializationError(e.to_string())))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{OrderStatus, TimeInForce};
+    use chrono::Duration;
+    use crate::order_book::test_support::create_order;
+
+    /// Drives a logged book through every kind of op on a fixed clock
+    fn logged_session() -> LimitOrderBook {
+        let start = Utc::now();
+        let mut book = LimitOrderBook::with_clock("OPLOG".to_string(), Clock::Fixed(start)).unwrap();
+        book.enable_op_log();
+
+        let bid = create_order(OrderSide::Buy, 9900, 100, "alice");
+        let bid_id = bid.id;
+        book.add_order(bid).unwrap();
+        book.add_order(create_order(OrderSide::Sell, 10100, 50, "bob").with_expiry(start + Duration::minutes(1))).unwrap();
+        book.add_order(create_order(OrderSide::Sell, 10200, 70, "carol").with_time_in_force(TimeInForce::Day)).unwrap();
+        book.set_clock(Clock::Fixed(start + Duration::seconds(10)));
+        book.add_order(create_order(OrderSide::Buy, 10100, 20, "dave")).unwrap();
+        book.modify_order(bid_id, Some(Price::from_cents(9950).unwrap()), None).unwrap();
+        book.submit_bracket(create_order(OrderSide::Buy, 9800, 10, "erin"), Price::from_cents(10500).unwrap(), Price::from_cents(9500).unwrap()).unwrap();
+        assert!(book.cancel_order(OrderId::new()).is_err());
+        book.halt();
+        book.resume();
+        book.set_clock(Clock::Fixed(start + Duration::minutes(2)));
+        book.purge_expired(start + Duration::minutes(2));
+        book.roll_session("day-2");
+        book.set_net_position(UserId::new("grace".to_string()), 250);
+        book.reset_stats();
+        book.reset_session_stats();
+        book.reset_fee_totals();
+        book.set_max_recent_trades(1);
+        book.add_order(create_order(OrderSide::Sell, 9950, 30, "frank")).unwrap();
+        book
+    }
+
+    #[test]
+    fn test_replay_reproduces_the_snapshot_exactly() {
+        let mut book = logged_session();
+        let ops = book.take_ops();
+        assert!(matches!(ops[0].op, OrderBookOp::Create { .. }));
+        assert!(ops.iter().any(|entry| entry.op == OrderBookOp::SetState(BookState::Halted)));
+        assert!(ops.iter().any(|entry| entry.op == OrderBookOp::SetMaxRecentTrades(1)));
+        assert!(ops.iter().enumerate().all(|(i, entry)| entry.sequence == i as u64));
+        assert!(book.take_ops().is_empty());
+
+        let replayed = LimitOrderBook::replay(ops.clone()).unwrap();
+        assert_eq!(replayed.to_json().unwrap(), book.to_json().unwrap());
+        assert_eq!(LimitOrderBook::replay(ops).unwrap().to_json().unwrap(), book.to_json().unwrap());
+    }
+
+    #[test]
+    fn test_rejected_ops_are_logged_and_replayed() {
+        let mut book = LimitOrderBook::with_clock("OPLOG".to_string(), Clock::Fixed(Utc::now())).unwrap();
+        book.enable_op_log();
+        book.close();
+        let order = create_order(OrderSide::Buy, 9900, 100, "alice");
+        let order_id = order.id;
+        assert!(matches!(book.apply(OrderBookOp::Submit(order)), OpResult::Rejected(MatchingEngineError::BookClosed(_))));
+        assert!(matches!(book.apply(OrderBookOp::Create { symbol: "OPLOG".to_string(), clock: Clock::System }), OpResult::Rejected(_)));
+
+        let replayed = LimitOrderBook::replay(book.take_ops()).unwrap();
+        assert_eq!(replayed.state(), BookState::Closed);
+        assert!(replayed.get_order(order_id).is_none());
+    }
+
+    #[test]
+    fn test_jsonl_round_trip_and_malformed_logs() {
+        let mut book = logged_session();
+        let ops = book.take_ops();
+        let mut writer = OpLogWriter::new(Vec::new());
+        for op in &ops {
+            writer.append(op).unwrap();
+        }
+        writer.flush().unwrap();
+        let bytes = writer.into_inner();
+        assert_eq!(bytes.iter().filter(|&&byte| byte == b'\n').count(), ops.len());
+
+        let read: Vec<LoggedOp> = OpLogReader::new(bytes.as_slice()).collect::<crate::Result<_>>().unwrap();
+        assert_eq!(read, ops);
+        assert_eq!(LimitOrderBook::replay(read).unwrap().to_json().unwrap(), book.to_json().unwrap());
+
+        // A torn last line fails; a log without Create, or with a second one, doesn't replay
+        let torn = &bytes[..bytes.len() - 5];
+        let results: Vec<_> = OpLogReader::new(torn).collect();
+        assert!(results[..results.len() - 1].iter().all(Result::is_ok));
+        assert!(matches!(results.last(), Some(Err(MatchingEngineError::DeserializationError(_)))));
+        assert!(LimitOrderBook::replay(ops[1..].to_vec()).is_err());
+        assert!(LimitOrderBook::replay([ops[0].clone(), ops[0].clone()]).is_err());
+    }
+
+    /// Ops 1 through `count` of a busy session: orders, cancels of earlier orders, ticks of the clock, and a halt
+    fn scripted_ops(start: DateTime<Utc>, count: u64) -> Vec<OrderBookOp> {
+        let mut ids = Vec::new();
+        (1..=count)
+            .map(|i| match i % 10 {
+                0 => OrderBookOp::SetClock(Clock::Fixed(start + Duration::milliseconds(i as i64))),
+                3 | 7 if !ids.is_empty() => OrderBookOp::Cancel(ids[(i as usize * 7) % ids.len()]),
+                _ if i % 250 == 0 => OrderBookOp::SetState(BookState::Halted),
+                _ if i % 250 == 1 && i > 1 => OrderBookOp::SetState(BookState::Open),
+                _ => {
+                    let side = if i % 2 == 0 { OrderSide::Buy } else { OrderSide::Sell };
+                    let order = create_order(side, 9990 + (i * 13 % 21) as i64, 1 + i % 40, "trader");
+                    ids.push(order.id);
+                    OrderBookOp::Submit(order)
+                },
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_recover_from_snapshot_and_overlapping_log_tail() {
+        let start = Utc::now();
+        let mut book = LimitOrderBook::with_clock("RECOVER".to_string(), Clock::Fixed(start)).unwrap();
+        book.enable_op_log();
+        let mut snapshots = Vec::new();
+        for op in scripted_ops(start, 700) {
+            book.apply(op);
+            if book.op_sequence() == 500 {
+                snapshots.push(book.to_snapshot().unwrap().to_json().unwrap().into_bytes());
+                #[cfg(feature = "binary-snapshots")]
+                snapshots.push(book.to_bytes().unwrap());
+            }
+        }
+        let log = book.take_ops();
+        assert_eq!(log.last().map(|entry| entry.sequence), Some(700));
+        assert!(book.stats().trade_count > 0);
+
+        // The process died after op 700; the log survives from op 400 on
+        let tail: Vec<LoggedOp> = log.iter().filter(|entry| entry.sequence >= 400).cloned().collect();
+        let expected = book.to_json().unwrap();
+        for snapshot in &snapshots {
+            let recovered = LimitOrderBook::recover(snapshot, tail.clone()).unwrap();
+            assert_eq!(recovered.op_sequence(), 700);
+            assert_eq!(recovered.to_json().unwrap(), expected);
+        }
+
+        // The whole log works too, and a snapshot with nothing after it is just restored
+        assert_eq!(LimitOrderBook::recover(&snapshots[0], log.clone()).unwrap().to_json().unwrap(), expected);
+        assert_eq!(LimitOrderBook::recover(&snapshots[0], Vec::new()).unwrap().op_sequence(), 500);
+
+        // A log that starts after the snapshot, or has a hole, can't bring it up to date
+        let late: Vec<LoggedOp> = log.iter().filter(|entry| entry.sequence > 501).cloned().collect();
+        assert!(matches!(LimitOrderBook::recover(&snapshots[0], late), Err(MatchingEngineError::InvariantViolation(_))));
+        let holed: Vec<LoggedOp> = tail.iter().filter(|entry| entry.sequence != 600).cloned().collect();
+        assert!(LimitOrderBook::recover(&snapshots[0], holed).is_err());
+        assert!(matches!(LimitOrderBook::recover(b"not a snapshot", tail), Err(MatchingEngineError::DeserializationError(_))));
+    }
+
+    #[test]
+    fn test_mutators_report_what_apply_returns() {
+        let mut book = LimitOrderBook::new("OPLOG".to_string()).unwrap();
+        let resting = create_order(OrderSide::Sell, 10000, 40, "alice");
+        let resting_id = resting.id;
+        book.add_order(resting).unwrap();
+
+        let result = book.apply(OrderBookOp::Submit(create_order(OrderSide::Buy, 10000, 15, "bob")));
+        let OpResult::Executed(result) = result else { panic!("expected an execution result") };
+        assert_eq!(result.trades.len(), 1);
+        let OpResult::Cancelled(order) = book.apply(OrderBookOp::Cancel(resting_id)) else { panic!("expected a cancel") };
+        assert_eq!((order.status, order.remaining_quantity.value()), (OrderStatus::Cancelled, 25));
+        assert_eq!(book.apply(OrderBookOp::CancelAll), OpResult::Orders(Vec::new()));
+    }
+}
@@ -0,0 +1,325 @@
+//! Net position tracking and per-user position limits
+//!
+//! Every trade updates the buyer's and seller's net position on the book.
+//! A position limit caps the worst case a user could reach: their current
+//! net position plus the incoming order's remaining quantity plus all of
+//! their open orders, parked orders, and pending stops on the same side.
+//! Opposite-side open orders are not counted as offsets, since they may be
+//! cancelled at any time. Each user's open quantity is kept up to date as
+//! orders join, fill, and leave, so the check never scans the book.
+//!
+//! Checking once on entry is enough. While an order matches, each fill moves
+//! quantity from the order into the position without changing the worst
+//! case, and a user's resting orders on the same side cannot trade against
+//! the incoming order. A stop is checked once more when released, since
+//! fills of the user's other orders may have moved the position meanwhile.
+
+use super::{levels::{UserTotals, UserTotalsMap}, LimitOrderBook, OrderBookOp};
+use crate::{
+    events::{CancelReason, OrderEvent},
+    types::{OrderId, UserId},
+    MatchingEngineError, Order, OrderSide, Quantity,
+};
+use serde::{Deserialize, Serialize};
+
+/// What to do with an order whose worst case would breach a position limit
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum PositionLimitAction {
+    /// Reject the order outright
+    #[default]
+    Reject,
+    /// Reduce the order to the remaining headroom, rejecting it if there is none
+    Trim,
+}
+
+/// Hard limits on a user's net position in one book
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PositionLimit {
+    /// Largest net long position allowed
+    pub max_long: u64,
+    /// Largest net short position allowed (as a positive quantity)
+    pub max_short: u64,
+    #[serde(default)]
+    pub action: PositionLimitAction,
+}
+
+impl PositionLimit {
+    /// Creates limits that reject breaching orders
+    pub fn new(max_long: u64, max_short: u64) -> Self {
+        Self { max_long, max_short, action: PositionLimitAction::Reject }
+    }
+    
+    /// Sets the action taken on breaching orders
+    pub fn with_action(mut self, action: PositionLimitAction) -> Self {
+        self.action = action;
+        self
+    }
+}
+
+impl LimitOrderBook {
+    /// Gets a user's net position (positive long, negative short)
+    pub fn net_position(&self, user_id: &UserId) -> i64 {
+        self.positions.get(user_id).copied().unwrap_or(0)
+    }
+    
+    /// Overrides a user's net position, e.g. to carry in a start-of-day position
+    pub fn set_net_position(&mut self, user_id: UserId, position: i64) {
+        self.apply(OrderBookOp::SetNetPosition { user_id, position });
+    }
+    
+    pub(super) fn override_net_position(&mut self, user_id: UserId, position: i64) {
+        if position == 0 {
+            self.positions.remove(&user_id);
+        } else {
+            self.positions.insert(user_id, position);
+        }
+    }
+    
+    /// Gets a user's position limit
+    pub fn position_limit(&self, user_id: &UserId) -> Option<PositionLimit> {
+        self.position_limits.get(user_id).copied()
+    }
+    
+    /// Sets (`Some`) or removes (`None`) a user's position limit
+    pub fn set_position_limit(&mut self, user_id: UserId, limit: Option<PositionLimit>) {
+        match limit {
+            Some(limit) => self.position_limits.insert(user_id, limit),
+            None => self.position_limits.remove(&user_id),
+        };
+    }
+    
+    /// Applies a trade to the buyer's and seller's net positions
+    pub(super) fn record_position(&mut self, buyer: &UserId, seller: &UserId, quantity: Quantity) {
+        let quantity = quantity.value() as i64;
+        *self.positions.entry(buyer.clone()).or_default() += quantity;
+        *self.positions.entry(seller.clone()).or_default() -= quantity;
+    }
+    
+    /// Checks an order against its user's position limit
+    ///
+    /// Returns how much the order must be trimmed by to fit (zero if it fits
+    /// as is). Trimming is only offered when `allow_trim` is set and the
+    /// limit's action is [`PositionLimitAction::Trim`]; otherwise a breach is
+    /// an error naming the limit and the computed worst case.
+    pub(super) fn check_position_limit(&self, order: &Order, allow_trim: bool) -> crate::Result<u64> {
+        let Some(limit) = self.position_limits.get(&order.user_id) else {
+            return Ok(0);
+        };
+        
+        let exposure = (self.open_quantity(&order.user_id, order.side, order.id)
+            + order.remaining_quantity.value()) as i64;
+        let position = self.net_position(&order.user_id);
+        let (name, max, worst_case, excess) = match order.side {
+            OrderSide::Buy => {
+                let worst_case = position + exposure;
+                ("max_long", limit.max_long, worst_case, worst_case - limit.max_long as i64)
+            },
+            OrderSide::Sell => {
+                let worst_case = position - exposure;
+                ("max_short", limit.max_short, worst_case, -worst_case - limit.max_short as i64)
+            },
+        };
+        
+        if excess <= 0 {
+            return Ok(0);
+        }
+        let trimmable = allow_trim
+            && limit.action == PositionLimitAction::Trim
+            && (excess as u64) < order.remaining_quantity.value();
+        if trimmable {
+            return Ok(excess as u64);
+        }
+        Err(MatchingEngineError::PositionLimitExceeded {
+            user_id: order.user_id.to_string(),
+            limit: name.to_string(),
+            max,
+            worst_case,
+        })
+    }
+    
+    /// Reduces an incoming order by the excess found by [`check_position_limit`](Self::check_position_limit)
+    pub(super) fn trim_to_position_limit(
+        &mut self,
+        order: &mut Order,
+        excess: u64,
+        events: &mut Vec<OrderEvent>,
+    ) -> crate::Result<()> {
+        let now = self.now();
+        if excess == 0 {
+            return Ok(());
+        }
+        let quantity = Quantity::new(excess)?;
+        order.reduce_at(quantity, now)?;
+        events.push(OrderEvent::Decremented { order_id: order.id, quantity, reason: CancelReason::PositionLimit });
+        Ok(())
+    }
+    
+    /// Remaining quantity of a user's open orders, parked orders, and pending stops on one side
+    ///
+    /// A resting `except` is left out, so an amendment is counted at its new size only.
+    fn open_quantity(&self, user_id: &UserId, side: OrderSide, except: OrderId) -> u64 {
+        let levels = match side {
+            OrderSide::Buy => &self.bids,
+            OrderSide::Sell => &self.asks,
+        };
+        let excluded = self.resting_order(except)
+            .filter(|order| order.is_active() && order.side == side && &order.user_id == user_id)
+            .map_or(0, |order| order.remaining_quantity.value());
+        levels.user_totals(user_id).quantity
+            + self.stops.open_quantity(user_id, side)
+            + self.parked_totals.get(user_id, side).quantity
+            - excluded
+    }
+}
+
+/// Remaining quantity of each user's pending orders (stops or parked), by side
+#[derive(Debug, Clone, Default)]
+pub(super) struct PendingTotals {
+    buys: UserTotalsMap,
+    sells: UserTotalsMap,
+}
+
+impl PendingTotals {
+    /// Counts pending orders from scratch
+    pub(super) fn of<'a>(orders: impl IntoIterator<Item = &'a Order>) -> Self {
+        let mut totals = Self::default();
+        for order in orders {
+            totals.add(order);
+        }
+        totals
+    }
+    
+    pub(super) fn get(&self, user_id: &UserId, side: OrderSide) -> UserTotals {
+        self.side(side).get(user_id)
+    }
+    
+    pub(super) fn add(&mut self, order: &Order) {
+        self.replace(None, Some(order));
+    }
+    
+    pub(super) fn remove(&mut self, order: &Order) {
+        self.replace(Some(order), None);
+    }
+    
+    /// Swaps a pending order's contribution for its amended version's, either of which may be absent
+    pub(super) fn replace(&mut self, before: Option<&Order>, after: Option<&Order>) {
+        let Some(order) = before.or(after) else {
+            return;
+        };
+        let contribution = |order: Option<&Order>| order.map(UserTotals::of_pending).unwrap_or_default();
+        let side = match order.side {
+            OrderSide::Buy => &mut self.buys,
+            OrderSide::Sell => &mut self.sells,
+        };
+        side.adjust(&order.user_id, contribution(before), contribution(after));
+    }
+    
+    fn side(&self, side: OrderSide) -> &UserTotalsMap {
+        match side {
+            OrderSide::Buy => &self.buys,
+            OrderSide::Sell => &self.sells,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Price;
+    use crate::order_book::test_support::create_order;
+
+    fn user(name: &str) -> UserId {
+        UserId::new(name.to_string())
+    }
+
+    /// alice is long 900 against a 1,000 long limit
+    fn limited_book(action: PositionLimitAction) -> LimitOrderBook {
+        let mut book = LimitOrderBook::new("POS".to_string()).unwrap();
+        book.add_order(create_order(OrderSide::Sell, 10000, 900, "bob")).unwrap();
+        book.add_order(create_order(OrderSide::Buy, 10000, 900, "alice")).unwrap();
+        book.set_position_limit(user("alice"), Some(PositionLimit::new(1_000, 500).with_action(action)));
+        book
+    }
+
+    #[test]
+    fn test_long_limit_counts_open_orders() {
+        let mut book = limited_book(PositionLimitAction::Reject);
+        assert_eq!(book.net_position(&user("alice")), 900);
+        assert_eq!(book.net_position(&user("bob")), -900);
+        
+        let err = book.add_order(create_order(OrderSide::Buy, 9900, 101, "alice")).unwrap_err();
+        assert_eq!(err, MatchingEngineError::PositionLimitExceeded {
+            user_id: "alice".to_string(),
+            limit: "max_long".to_string(),
+            max: 1_000,
+            worst_case: 1_001,
+        });
+        book.add_order(create_order(OrderSide::Buy, 9900, 100, "alice")).unwrap();
+        
+        // The resting 100 uses up the remaining capacity
+        assert!(book.add_order(create_order(OrderSide::Buy, 9800, 1, "alice")).is_err());
+        
+        // Open sells don't offset, but a sale that fills frees capacity
+        book.add_order(create_order(OrderSide::Buy, 9950, 50, "carol")).unwrap();
+        assert!(book.add_order(create_order(OrderSide::Sell, 10100, 50, "alice")).is_ok());
+        assert!(book.add_order(create_order(OrderSide::Buy, 9800, 1, "alice")).is_err());
+        book.add_order(create_order(OrderSide::Sell, 9950, 50, "alice")).unwrap();
+        assert_eq!(book.net_position(&user("alice")), 850);
+        book.add_order(create_order(OrderSide::Buy, 9800, 50, "alice")).unwrap();
+    }
+
+    #[test]
+    fn test_short_limit_and_trim() {
+        let mut book = limited_book(PositionLimitAction::Trim);
+        
+        // From +900 the short limit of 500 allows selling 1,400
+        book.add_order(create_order(OrderSide::Buy, 9900, 2_000, "carol")).unwrap();
+        let result = book.submit_order(create_order(OrderSide::Sell, 9900, 1_500, "alice")).unwrap();
+        assert!(matches!(
+            &result.events[..],
+            [OrderEvent::Decremented { quantity, reason: CancelReason::PositionLimit, .. }] if quantity.value() == 100
+        ));
+        assert_eq!(result.trades[0].quantity.value(), 1_400);
+        assert_eq!(book.net_position(&user("alice")), -500);
+        
+        // No headroom left: nothing to trim to
+        let err = book.add_order(create_order(OrderSide::Sell, 9900, 1, "alice")).unwrap_err();
+        assert!(matches!(err, MatchingEngineError::PositionLimitExceeded { worst_case: -501, .. }));
+    }
+
+    #[test]
+    fn test_modify_size_up_checks_limit() {
+        let mut book = limited_book(PositionLimitAction::Trim);
+        let order = create_order(OrderSide::Buy, 9900, 60, "alice");
+        let order_id = order.id;
+        book.add_order(order).unwrap();
+        
+        assert!(book.modify_order(order_id, None, Some(Quantity::new(100).unwrap())).is_ok());
+        assert!(book.modify_order(order_id, None, Some(Quantity::new(101).unwrap())).is_err());
+        assert_eq!(book.get_order(order_id).unwrap().remaining_quantity.value(), 100);
+    }
+
+    #[test]
+    fn test_limit_counts_parked_orders_and_stops() {
+        let mut book = limited_book(PositionLimitAction::Reject);
+        let alice = user("alice");
+        let parked = create_order(OrderSide::Buy, 9900, 30, "alice").with_activation(book.now() + chrono::Duration::hours(1));
+        let parked_id = parked.id;
+        book.add_order(parked).unwrap();
+        let stop = Order::stop(OrderId::new(), alice.clone(), OrderSide::Buy, Price::from_cents(10500).unwrap(), Quantity::new(30).unwrap());
+        let stop_id = stop.id;
+        book.add_order(stop).unwrap();
+        book.add_order(create_order(OrderSide::Buy, 9900, 40, "alice")).unwrap();
+        assert_eq!(book.open_quantity(&alice, OrderSide::Buy, OrderId::new()), 100);
+        assert!(book.add_order(create_order(OrderSide::Buy, 9800, 1, "alice")).is_err());
+        
+        // Cancelling frees capacity, and so does a fill against the resting order
+        book.cancel_order(parked_id).unwrap();
+        book.cancel_order(stop_id).unwrap();
+        book.add_order(create_order(OrderSide::Sell, 9900, 15, "bob")).unwrap();
+        assert_eq!(book.open_quantity(&alice, OrderSide::Buy, OrderId::new()), 25);
+        assert_eq!(book.net_position(&alice), 915);
+        book.add_order(create_order(OrderSide::Buy, 9800, 60, "alice")).unwrap();
+        assert!(book.add_order(create_order(OrderSide::Buy, 9800, 1, "alice")).is_err());
+    }
+}
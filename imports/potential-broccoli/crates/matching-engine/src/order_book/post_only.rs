@@ -0,0 +1,160 @@
+//! Post-only orders and crossing predicates
+//!
+//! A post-only order that would trade on entry is rejected with the price it
+//! would have crossed, leaving the book untouched. Only live interest counts:
+//! levels whose orders are all inactive or expired are looked through, as
+//! matching would never trade with them. Hidden orders are live interest, so
+//! a price crossing only a hidden level still counts as crossing.
+
+use super::{BookState, LimitOrderBook};
+use crate::{MatchingEngineError, Order, OrderSide, Price};
+
+impl LimitOrderBook {
+    /// Rejects a post-only order that would cross live opposing interest
+    pub(super) fn check_post_only(&self, order: &Order) -> crate::Result<()> {
+        if !order.post_only {
+            return Ok(());
+        }
+        match self.crossed_price(order.side, order.price) {
+            Some(opposing_price) => Err(MatchingEngineError::PostOnlyWouldCross { order_id: order.id, opposing_price }),
+            None => Ok(()),
+        }
+    }
+    
+    /// Checks whether an order on `side` at `price` would trade with live opposing interest
+    pub fn would_cross(&self, side: OrderSide, price: Price) -> bool {
+        self.crossed_price(side, price).is_some()
+    }
+    
+    /// Checks whether the best live bid is above the best live ask
+    /// 
+    /// Matching never leaves the book crossed, so outside the pre-open
+    /// auction (where orders rest without matching) a crossed book means
+    /// corrupted state. Debug builds assert this after every mutation.
+    pub fn is_crossed(&self) -> bool {
+        match (self.best_live_price(OrderSide::Buy), self.best_live_price(OrderSide::Sell)) {
+            (Some(bid), Some(ask)) => bid > ask,
+            _ => false,
+        }
+    }
+    
+    /// Asserts in debug builds that a mutation left an open book uncrossed
+    pub(super) fn debug_assert_uncrossed(&self) {
+        debug_assert!(
+            self.state == BookState::PreOpen || !self.is_crossed(),
+            "Book {} crossed: bid {:?} above ask {:?}",
+            self.symbol,
+            self.best_live_price(OrderSide::Buy),
+            self.best_live_price(OrderSide::Sell),
+        );
+    }
+    
+    /// Gets the opposing price an order on `side` at `price` would trade against first
+    fn crossed_price(&self, side: OrderSide, price: Price) -> Option<Price> {
+        let opposing_price = self.best_live_price(side.opposite())?;
+        let crosses = match side {
+            OrderSide::Buy => price >= opposing_price,
+            OrderSide::Sell => price <= opposing_price,
+        };
+        crosses.then_some(opposing_price)
+    }
+    
+    /// Gets the best price on a side holding at least one order matching could trade with
+    pub(super) fn best_live_price(&self, side: OrderSide) -> Option<Price> {
+        let now = self.now();
+        let levels = match side {
+            OrderSide::Buy => &self.bids,
+            OrderSide::Sell => &self.asks,
+        };
+        levels.iter()
+            .find(|(_, orders)| orders.iter().any(|order| order.is_active() && !order.is_expired_at(now)))
+            .map(|(price, _)| *price)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        LimitOrderBook, MatchingEngineError, OrderSide, Price,
+    };
+    use crate::order_book::test_support::create_order;
+
+    /// 99.00 bid / 101.00 offer
+    fn book() -> LimitOrderBook {
+        let mut book = LimitOrderBook::new("POST".to_string()).unwrap();
+        book.add_order(create_order(OrderSide::Buy, 9900, 100, "maker")).unwrap();
+        book.add_order(create_order(OrderSide::Sell, 10100, 100, "maker")).unwrap();
+        book
+    }
+
+    #[test]
+    fn test_non_crossing_post_only_rests() {
+        let mut book = book();
+        let quote = create_order(OrderSide::Buy, 10000, 50, "maker").with_post_only();
+        let quote_id = quote.id;
+        assert!(book.add_order(quote).unwrap().is_empty());
+        assert_eq!(book.best_bid().unwrap().as_cents(), 10000);
+        assert!(book.get_order(quote_id).unwrap().post_only);
+    }
+
+    #[test]
+    fn test_crossing_post_only_rejected_with_opposing_price() {
+        let mut book = book();
+        let before = book.market_depth(10);
+        
+        let quote = create_order(OrderSide::Sell, 9850, 50, "maker").with_post_only();
+        let quote_id = quote.id;
+        let err = book.add_order(quote).unwrap_err();
+        assert_eq!(err, MatchingEngineError::PostOnlyWouldCross {
+            order_id: quote_id,
+            opposing_price: Price::from_cents(9900).unwrap(),
+        });
+        assert_eq!(book.market_depth(10), before);
+        assert_eq!(book.order_count(), 2);
+        
+        // Repricing a resting post-only order through the touch is rejected too
+        let resting = create_order(OrderSide::Buy, 10000, 50, "maker").with_post_only();
+        let resting_id = resting.id;
+        book.add_order(resting).unwrap();
+        let err = book.modify_order(resting_id, Some(Price::from_cents(10100).unwrap()), None).unwrap_err();
+        assert!(matches!(err, MatchingEngineError::PostOnlyWouldCross { opposing_price, .. } if opposing_price.as_cents() == 10100));
+    }
+
+    #[test]
+    fn test_stale_top_of_book_is_ignored() {
+        let mut book = book();
+        book.add_order(create_order(OrderSide::Sell, 10050, 100, "maker")).unwrap();
+        
+        // Leave only a cancelled order at the 100.50 level
+        book.asks.front_mut(&Price::from_cents(10050).unwrap()).unwrap().cancel();
+        
+        let err = book.add_order(create_order(OrderSide::Buy, 10100, 50, "maker").with_post_only()).unwrap_err();
+        assert!(matches!(err, MatchingEngineError::PostOnlyWouldCross { opposing_price, .. } if opposing_price.as_cents() == 10100));
+        assert!(book.add_order(create_order(OrderSide::Buy, 10050, 50, "maker").with_post_only()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_would_cross_ignores_inactive_levels_but_not_hidden_ones() {
+        let mut book = book();
+        book.add_order(create_order(OrderSide::Sell, 10050, 100, "maker")).unwrap();
+        book.asks.front_mut(&Price::from_cents(10050).unwrap()).unwrap().cancel();
+        book.add_order(create_order(OrderSide::Buy, 9950, 10, "maker").with_hidden()).unwrap();
+        
+        assert!(!book.would_cross(OrderSide::Buy, Price::from_cents(10050).unwrap()));
+        assert!(book.would_cross(OrderSide::Buy, Price::from_cents(10100).unwrap()));
+        assert!(book.would_cross(OrderSide::Sell, Price::from_cents(9950).unwrap()));
+        assert!(!book.would_cross(OrderSide::Sell, Price::from_cents(9951).unwrap()));
+        assert!(!book.is_crossed());
+    }
+    
+    #[test]
+    fn test_pre_open_book_may_rest_crossed() {
+        let mut book = book();
+        book.pre_open();
+        book.add_order(create_order(OrderSide::Buy, 10200, 10, "maker")).unwrap();
+        assert!(book.is_crossed());
+        
+        book.uncross().unwrap();
+        assert!(!book.is_crossed());
+    }
+}
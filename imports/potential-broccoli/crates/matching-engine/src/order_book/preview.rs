@@ -0,0 +1,229 @@
+//! Dry-run match preview
+//!
+//! `preview_order` walks the book exactly as `submit_order` would for an
+//! order that passes its checks — position-limit and reduce-only trims, the
+//! fill-or-kill check, netting, self-trade prevention, and the session volume
+//! limit included — but only reads the book, cloning nothing beyond the order
+//! itself. On an unchanged book the previewed trades are the ones the order
+//! would execute, down to their trade IDs, fees, and sequence number; only
+//! timestamps differ. Trades of stop orders the fills would trigger are not
+//! previewed.
+
+use super::{fees, netting, quantity_scale, state::BookState, stp::StpPolicy, LimitOrderBook, Trade};
+use crate::{Order, OrderSide, OrderType, Quantity, TimeInForce};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+/// What an order would do if submitted now
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MatchPreview {
+    /// Trades the order would execute, in order
+    pub trades: Vec<Trade>,
+    /// Volume-weighted average price of those trades
+    pub average_price: Option<Decimal>,
+    /// Quantity left unfilled once matching stops
+    pub residual_quantity: Quantity,
+    /// Whether the residual would rest in the book (otherwise it is cancelled,
+    /// expired, or held outside the book)
+    pub would_rest: bool,
+}
+
+impl MatchPreview {
+    fn new(incoming: &Order, trades: Vec<Trade>) -> Self {
+        let filled: u64 = trades.iter().map(|trade| trade.quantity.value()).sum();
+        let notional: Decimal = trades.iter().map(|trade| trade.price.value() * Decimal::from(trade.quantity.value())).sum();
+        Self {
+            average_price: (filled > 0).then(|| notional / Decimal::from(filled)),
+            trades,
+            residual_quantity: incoming.remaining_quantity,
+            would_rest: incoming.is_active() && incoming.remaining_quantity.value() > 0,
+        }
+    }
+}
+
+impl LimitOrderBook {
+    /// Simulates submitting an order without changing anything
+    ///
+    /// Assumes the order passes [`validate_order`](Self::validate_order).
+    /// Orders held outside the book — scheduled, at-the-close, or pending
+    /// stops — preview with no trades and do not rest.
+    pub fn preview_order(&self, order: &Order) -> MatchPreview {
+        let now = self.now();
+        let mut incoming = order.clone();
+        let held = incoming.activate_at.is_some_and(|activate_at| activate_at > now)
+            || incoming.time_in_force == TimeInForce::AtTheClose
+            || (incoming.stop_trigger().is_some() && !self.stop_releases(&incoming));
+        if held {
+            return MatchPreview {
+                trades: Vec::new(),
+                average_price: None,
+                residual_quantity: incoming.remaining_quantity,
+                would_rest: false,
+            };
+        }
+        if incoming.stop_trigger().is_some() {
+            incoming.order_type = OrderType::Market;
+        }
+
+        // The same trims submit_order applies before matching
+        let trim = |incoming: &mut Order, excess: u64| {
+            if excess > 0 {
+                let _ = incoming.reduce(Quantity::new_allow_zero(excess));
+            }
+        };
+        trim(&mut incoming, self.check_position_limit(order, true).unwrap_or(0));
+        let excess = self.check_reduce_only(&incoming).unwrap_or(0);
+        trim(&mut incoming, excess);
+        if incoming.is_expired_at(now) {
+            incoming.expire();
+        } else if incoming.time_in_force == TimeInForce::FOK && !self.can_fill_completely(&incoming) {
+            incoming.cancel();
+        }
+
+        let mut trades = Vec::new();
+        if self.state != BookState::PreOpen {
+            self.preview_matching(&mut incoming, &mut trades);
+        }
+        if !incoming.can_rest() && incoming.is_active() {
+            incoming.cancel();
+        }
+        MatchPreview::new(&incoming, trades)
+    }
+
+    /// Walks the opposing side in matching order, appending the trades matching would execute
+    fn preview_matching(&self, incoming: &mut Order, trades: &mut Vec<Trade>) {
+        let levels = match incoming.side {
+            OrderSide::Buy => &self.asks,
+            OrderSide::Sell => &self.bids,
+        };
+        let mut usage = self.volume_usage;
+        let now = self.now();
+
+        'levels: for (price, orders) in levels.iter() {
+            let crosses = match incoming.side {
+                OrderSide::Buy => incoming.price >= *price,
+                OrderSide::Sell => incoming.price <= *price,
+            };
+            if !incoming.is_active() || !crosses {
+                break;
+            }
+
+            for resting in orders {
+                if !resting.is_active() || resting.is_expired_at(now) {
+                    continue;
+                }
+                if netting::nets(&self.netting_users, incoming, resting) {
+                    let _ = incoming.reduce(incoming.remaining_quantity.min(resting.remaining_quantity));
+                    if !incoming.is_active() {
+                        break 'levels;
+                    }
+                    continue;
+                }
+                if let Some(stp) = self.self_trade_prevention {
+                    if stp.scope.applies(&self.accounts, &incoming.user_id, &resting.user_id) {
+                        match stp.policy {
+                            StpPolicy::CancelResting => continue,
+                            StpPolicy::CancelIncoming | StpPolicy::CancelBoth => incoming.cancel(),
+                            StpPolicy::DecrementAndCancel => {
+                                let _ = incoming.reduce(incoming.remaining_quantity.min(resting.remaining_quantity));
+                            },
+                        }
+                        if !incoming.is_active() {
+                            break 'levels;
+                        }
+                        continue;
+                    }
+                }
+
+                let quantity = incoming.remaining_quantity.min(resting.remaining_quantity);
+                let (maker_fee, taker_fee) = fees::trade_fees(
+                    self.fee_schedule.as_ref(),
+                    quantity_scale::notional(*price, quantity.value(), self.quantity_scale),
+                    (&resting.user_id, &incoming.user_id),
+                );
+                let (buy_order, sell_order) = match incoming.side {
+                    OrderSide::Buy => (&*incoming, resting),
+                    OrderSide::Sell => (resting, &*incoming),
+                };
+                trades.push(Trade {
+                    trade_id: self.next_trade_id + trades.len() as u64,
+                    buy_order_id: buy_order.id,
+                    sell_order_id: sell_order.id,
+                    maker_order_id: Some(resting.id),
+                    taker_order_id: Some(incoming.id),
+                    aggressor_side: Some(incoming.side),
+                    buyer_user_id: Some(buy_order.user_id.clone()),
+                    seller_user_id: Some(sell_order.user_id.clone()),
+                    price: *price,
+                    quantity,
+                    timestamp: now,
+                    symbol: Some(self.symbol.clone()),
+                    sequence: self.sequence + 1,
+                    same_firm: self.accounts.same_firm(&incoming.user_id, &resting.user_id),
+                    maker_fee,
+                    taker_fee,
+                    quantity_scale: self.quantity_scale,
+                });
+                let _ = incoming.fill(quantity);
+
+                usage.traded_quantity += quantity.value();
+                usage.traded_notional += quantity_scale::notional(*price, quantity.value(), self.quantity_scale);
+                if let Some(limit) = self.volume_limit {
+                    if !usage.breached && limit.is_reached_by(&usage) {
+                        if incoming.is_active() {
+                            incoming.cancel();
+                        }
+                        break 'levels;
+                    }
+                }
+                if incoming.is_filled() {
+                    break 'levels;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{OrderId, UserId};
+    use crate::order_book::test_support::create_test_order;
+
+    #[test]
+    fn test_preview_against_empty_side() {
+        let mut book = LimitOrderBook::new("PREV".to_string()).unwrap();
+        book.add_order(create_test_order(OrderSide::Buy, 9900, 100)).unwrap();
+
+        let preview = book.preview_order(&create_test_order(OrderSide::Buy, 10000, 50));
+        assert!(preview.trades.is_empty());
+        assert_eq!(preview.average_price, None);
+        assert_eq!((preview.residual_quantity.value(), preview.would_rest), (50, true));
+
+        let market = Order::market(OrderId::new(), UserId::new("taker".to_string()), OrderSide::Buy, Quantity::new(50).unwrap());
+        assert!(!book.preview_order(&market).would_rest);
+    }
+
+    #[test]
+    fn test_partial_cross_previews_then_executes_identically() {
+        let mut book = LimitOrderBook::new("PREV".to_string()).unwrap();
+        book.add_order(create_test_order(OrderSide::Sell, 10000, 30)).unwrap();
+        book.add_order(create_test_order(OrderSide::Sell, 10100, 30)).unwrap();
+        book.add_order(create_test_order(OrderSide::Sell, 10300, 30)).unwrap();
+        let sequence = book.sequence();
+
+        let order = create_test_order(OrderSide::Buy, 10200, 100);
+        let preview = book.preview_order(&order);
+        assert_eq!(book.sequence(), sequence);
+        assert_eq!(preview.average_price, Some(Decimal::new(10050, 2)));
+        assert_eq!((preview.residual_quantity.value(), preview.would_rest), (40, true));
+
+        let trades = book.add_order(order.clone()).unwrap();
+        let stamped: Vec<Trade> = preview.trades.iter()
+            .zip(&trades)
+            .map(|(previewed, trade)| Trade { timestamp: trade.timestamp, ..previewed.clone() })
+            .collect();
+        assert_eq!(stamped, trades);
+        assert_eq!(book.get_order(order.id).unwrap().remaining_quantity, preview.residual_quantity);
+    }
+}
@@ -0,0 +1,156 @@
+//! Fat-finger protection
+//!
+//! With a price band configured, a limit order priced further than the band's
+//! width from the reference price is rejected before it reaches the book. The
+//! reference is the last trade price (see `BookStats`), else the mid price,
+//! else the band's own fallback reference; with none of these (a cold start)
+//! the check is skipped. Market and stop orders carry no price and are never
+//! checked.
+
+use super::LimitOrderBook;
+use crate::{MatchingEngineError, Order, Price};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+/// Maximum distance of an order's price from the reference price
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PriceBand {
+    /// Allowed deviation as a fraction of the reference (0.10 for ±10%)
+    pub width: Decimal,
+    /// Reference used before the book has traded or quoted both sides
+    pub fallback_reference: Option<Price>,
+}
+
+impl PriceBand {
+    /// Creates a band allowing `width` (a fraction) either side of the reference
+    pub fn new(width: Decimal) -> Self {
+        Self { width, fallback_reference: None }
+    }
+
+    /// Sets the reference used on a cold start
+    pub fn with_fallback_reference(mut self, reference: Price) -> Self {
+        self.fallback_reference = Some(reference);
+        self
+    }
+
+    /// Gets the inclusive `(lower, upper)` bounds around a reference price
+    pub fn bounds(&self, reference: Decimal) -> (Decimal, Decimal) {
+        let deviation = reference * self.width;
+        (reference - deviation, reference + deviation)
+    }
+}
+
+impl LimitOrderBook {
+    /// Gets the price band
+    pub fn price_band(&self) -> Option<PriceBand> {
+        self.price_band
+    }
+
+    /// Sets (`Some`) or removes (`None`) the price band (off by default)
+    pub fn set_price_band(&mut self, band: Option<PriceBand>) {
+        self.price_band = band;
+    }
+
+    /// Gets the reference price the band is currently centred on
+    pub fn band_reference(&self) -> Option<Decimal> {
+        let band = self.price_band?;
+        let mid = || Some((self.best_bid()?.value() + self.best_ask()?.value()) / Decimal::TWO);
+        self.stats.last_trade_price.map(|price| price.value())
+            .or_else(mid)
+            .or(band.fallback_reference.map(|price| price.value()))
+    }
+
+    /// Rejects a limit order priced outside the band
+    pub(super) fn check_price_band(&self, order: &Order) -> crate::Result<()> {
+        if order.is_market() || order.stop_trigger().is_some() {
+            return Ok(());
+        }
+        let (Some(band), Some(reference)) = (self.price_band, self.band_reference()) else {
+            return Ok(());
+        };
+        let (lower, upper) = band.bounds(reference);
+        let price = order.price.value();
+        if price < lower || price > upper {
+            return Err(MatchingEngineError::PriceOutsideBand { price: order.price, lower, upper });
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        types::{OrderId, UserId},
+        OrderSide, Quantity,
+    };
+    use crate::order_book::test_support::create_test_order;
+
+    fn ten_percent() -> PriceBand {
+        PriceBand::new(Decimal::new(10, 2))
+    }
+
+    #[test]
+    fn test_band_around_last_trade_accepts_boundary() {
+        let mut book = LimitOrderBook::new("BAND".to_string()).unwrap();
+        book.set_price_band(Some(ten_percent()));
+        book.add_order(create_test_order(OrderSide::Sell, 10000, 10)).unwrap();
+        book.add_order(create_test_order(OrderSide::Buy, 10000, 10)).unwrap();
+
+        // 90.00 and 110.00 are exactly on the band around the 100.00 print
+        book.add_order(create_test_order(OrderSide::Buy, 9000, 10)).unwrap();
+        book.add_order(create_test_order(OrderSide::Sell, 11000, 10)).unwrap();
+        assert_eq!(
+            book.add_order(create_test_order(OrderSide::Sell, 11001, 10)),
+            Err(MatchingEngineError::PriceOutsideBand {
+                price: Price::from_cents(11001).unwrap(),
+                lower: Decimal::new(9000, 2),
+                upper: Decimal::new(11000, 2),
+            })
+        );
+        assert!(matches!(
+            book.add_order(create_test_order(OrderSide::Buy, 8999, 10)),
+            Err(MatchingEngineError::PriceOutsideBand { .. })
+        ));
+        assert_eq!(book.order_count(), 2);
+
+        // Market orders have no price to check
+        let sweep = Order::market(OrderId::new(), UserId::new("test_user".to_string()), OrderSide::Buy, Quantity::new(5).unwrap());
+        assert_eq!(book.add_order(sweep).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_band_falls_back_to_mid_then_reference() {
+        let mut book = LimitOrderBook::new("BAND".to_string()).unwrap();
+        book.set_price_band(Some(ten_percent()));
+
+        // Cold start without a fallback: nothing to check against
+        book.add_order(create_test_order(OrderSide::Buy, 9000, 10)).unwrap();
+        assert_eq!(book.band_reference(), None);
+        book.add_order(create_test_order(OrderSide::Sell, 11000, 10)).unwrap();
+
+        // Mid of 90.00 / 110.00
+        assert_eq!(book.band_reference(), Some(Decimal::new(100, 0)));
+        assert!(book.add_order(create_test_order(OrderSide::Buy, 8900, 10)).is_err());
+
+        let mut empty = LimitOrderBook::new("BAND".to_string()).unwrap();
+        empty.set_price_band(Some(ten_percent().with_fallback_reference(Price::from_cents(5000).unwrap())));
+        assert!(empty.add_order(create_test_order(OrderSide::Buy, 5500, 10)).is_ok());
+        assert!(empty.add_order(create_test_order(OrderSide::Buy, 5501, 10)).is_err());
+    }
+
+    #[test]
+    fn test_amended_price_is_checked() {
+        let mut book = LimitOrderBook::new("BAND".to_string()).unwrap();
+        book.set_price_band(Some(ten_percent().with_fallback_reference(Price::from_cents(10000).unwrap())));
+        let bid = create_test_order(OrderSide::Buy, 9500, 10);
+        let bid_id = bid.id;
+        book.add_order(bid).unwrap();
+
+        assert!(matches!(
+            book.modify_order(bid_id, Some(Price::from_cents(8000).unwrap()), None),
+            Err(MatchingEngineError::PriceOutsideBand { .. })
+        ));
+        assert_eq!(book.get_order(bid_id).unwrap().price.as_cents(), 9500);
+    }
+}
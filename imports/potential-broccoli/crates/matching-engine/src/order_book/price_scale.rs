@@ -0,0 +1,188 @@
+//! Per-book price scale
+//!
+//! A book counts prices in ticks of `10^-scale`: cents by default, finer for
+//! instruments quoted to more decimal places. The price levels are keyed by
+//! these tick counts (see the levels module), so every price that can rest
+//! must be a whole number of ticks. Orders priced finer than the scale are
+//! rejected on entry, whether submitted, parked, queued for the close,
+//! seeded, or repriced; market and stop orders carry no price and are never
+//! checked.
+//!
+//! A book built from a [`BookConfig`](super::BookConfig) also has a tick
+//! size, a coarser grid such as 0.05 or 0.25 that prices must be whole
+//! multiples of. Those books check the tick grid in place of the scale
+//! (every multiple of the tick is a whole number of price ticks), and
+//! reject off-grid prices with [`MatchingEngineError::InvalidTick`].
+
+use super::LimitOrderBook;
+use crate::{
+    price::MAX_PRICE_SCALE,
+    MatchingEngineError, Order, Price,
+};
+use rust_decimal::Decimal;
+
+/// Error for a price that is not a whole number of ticks at `scale`
+pub(super) fn off_scale(price: Price, scale: u32) -> MatchingEngineError {
+    MatchingEngineError::InvalidPrice(format!("Price {} is not a whole number of ticks at {} decimal places", price, scale))
+}
+
+/// Rejects a tick size that is not positive or is finer than `scale` allows
+pub(super) fn check_tick_size(tick_size: Decimal, scale: u32) -> crate::Result<()> {
+    if tick_size <= Decimal::ZERO {
+        return Err(MatchingEngineError::InvalidPrice(format!("Tick size must be positive, got: {}", tick_size)));
+    }
+    if tick_size.normalize().scale() > scale {
+        return Err(MatchingEngineError::InvalidPrice(format!(
+            "Tick size {} is finer than {} decimal places", tick_size, scale
+        )));
+    }
+    Ok(())
+}
+
+impl LimitOrderBook {
+    /// Creates a new empty order book counting prices to `price_scale` decimal places
+    ///
+    /// # Errors
+    /// Returns error if `price_scale` is above [`MAX_PRICE_SCALE`]
+    pub fn with_price_scale(symbol: String, price_scale: u32) -> crate::Result<Self> {
+        let mut book = Self::new(symbol)?;
+        book.price_scale = price_scale;
+        book.apply_price_scale()?;
+        Ok(book)
+    }
+
+    /// Gets the number of decimal places prices are counted to
+    pub fn price_scale(&self) -> u32 {
+        self.price_scale
+    }
+
+    /// Rejects a priced order whose price is not a whole number of ticks
+    pub(super) fn check_price_scale(&self, order: &Order) -> crate::Result<()> {
+        if order.is_market() || order.stop_trigger().is_some() {
+            return Ok(());
+        }
+        self.check_on_scale(order.price)
+    }
+
+    /// Gets the tick size prices must be whole multiples of, if the book has one
+    pub fn tick_size(&self) -> Option<Decimal> {
+        self.tick_size
+    }
+
+    /// Rejects a price that is off the tick grid or not a whole number of ticks
+    pub(super) fn check_on_scale(&self, price: Price) -> crate::Result<()> {
+        if let Some(tick_size) = self.tick_size {
+            if !price.is_on_tick(tick_size) {
+                return Err(MatchingEngineError::InvalidTick { price, tick_size });
+            }
+        }
+        match price.to_ticks(self.price_scale) {
+            Some(_) => Ok(()),
+            None => Err(off_scale(price, self.price_scale)),
+        }
+    }
+
+    /// Checks the tick size fits the book's price scale, then re-keys both sides' levels at it
+    pub(super) fn apply_price_scale(&mut self) -> crate::Result<()> {
+        if self.price_scale > MAX_PRICE_SCALE {
+            return Err(MatchingEngineError::InvalidPrice(format!(
+                "Price scale {} is above the maximum of {}", self.price_scale, MAX_PRICE_SCALE
+            )));
+        }
+        if let Some(tick_size) = self.tick_size {
+            check_tick_size(tick_size, self.price_scale)?;
+        }
+        self.bids.set_scale(self.price_scale)?;
+        self.asks.set_scale(self.price_scale)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        types::{OrderId, UserId},
+        OrderSide, Quantity,
+    };
+    use serde_json::Value;
+    use crate::order_book::test_support::create_order_at;
+
+    fn price(price: &str) -> Price {
+        Price::from_str(price).unwrap()
+    }
+
+    fn is_off_scale(result: crate::Result<impl std::fmt::Debug>) -> bool {
+        matches!(result, Err(MatchingEngineError::InvalidPrice(message)) if message.contains("not a whole number of ticks"))
+    }
+
+    #[test]
+    fn test_cents_book_rejects_finer_prices() {
+        let mut book = LimitOrderBook::new("SCALE".to_string()).unwrap();
+        assert_eq!(book.price_scale(), 2);
+        let resting = create_order_at(OrderSide::Buy, "100.00", 10);
+        let resting_id = resting.id;
+        book.add_order(resting).unwrap();
+
+        assert!(is_off_scale(book.submit_order(create_order_at(OrderSide::Buy, "100.005", 10))));
+        assert!(is_off_scale(book.submit_order(create_order_at(OrderSide::Sell, "150.010000001", 10))));
+        assert!(is_off_scale(book.modify_order(resting_id, Some(price("99.999")), None)));
+        assert!(is_off_scale(book.seed_orders(vec![create_order_at(OrderSide::Sell, "101.001", 10)])));
+        assert!(is_off_scale(book.submit_bracket(create_order_at(OrderSide::Buy, "99.00", 10), price("105.005"), price("95.00"))));
+        assert_eq!(book.bracket_count(), 0);
+
+        // Trailing zeros are not extra precision, and unpriced orders are never checked
+        book.add_order(create_order_at(OrderSide::Buy, "100.0000", 5)).unwrap();
+        assert_eq!(book.orders_at_price(OrderSide::Buy, price("100")).unwrap().len(), 2);
+        let market = Order::market(OrderId::new(), UserId::new("taker".to_string()), OrderSide::Sell, Quantity::new(15).unwrap());
+        assert_eq!(book.submit_order(market).unwrap().trades.len(), 2);
+        assert!(book.is_empty());
+
+        // Finer prices have no level to find
+        book.add_order(create_order_at(OrderSide::Sell, "101.00", 10)).unwrap();
+        assert!(book.orders_at_price(OrderSide::Sell, price("101.001")).is_none());
+        assert!(book.cancel_orders_in_range(OrderSide::Sell, price("100.995"), price("100.999")).is_empty());
+        assert_eq!(book.cancel_orders_in_range(OrderSide::Sell, price("100.995"), price("101.005")).len(), 1);
+    }
+
+    #[test]
+    fn test_finer_scale_orders_match_and_restore() {
+        assert!(LimitOrderBook::with_price_scale("SCALE".to_string(), MAX_PRICE_SCALE + 1).is_err());
+        let mut book = LimitOrderBook::with_price_scale("SCALE".to_string(), 4).unwrap();
+        book.add_order(create_order_at(OrderSide::Sell, "1.2346", 10)).unwrap();
+        book.add_order(create_order_at(OrderSide::Sell, "1.2345", 10)).unwrap();
+        book.add_order(create_order_at(OrderSide::Buy, "1.2344", 10)).unwrap();
+        assert!(is_off_scale(book.submit_order(create_order_at(OrderSide::Buy, "1.23445", 10))));
+        assert_eq!(book.best_ask(), Some(price("1.2345")));
+
+        let trades = book.add_order(create_order_at(OrderSide::Buy, "1.2346", 15)).unwrap();
+        assert_eq!(trades.iter().map(|trade| trade.price).collect::<Vec<_>>(), vec![price("1.2345"), price("1.2346")]);
+
+        // The scale travels with the snapshot
+        let restored = LimitOrderBook::from_json(&book.to_json().unwrap()).unwrap();
+        assert_eq!(restored.price_scale(), 4);
+        assert_eq!(restored.market_depth(10), book.market_depth(10));
+        assert!(is_off_scale(restored.validate_order(&create_order_at(OrderSide::Buy, "1.23441", 1))));
+    }
+
+    #[test]
+    fn test_snapshot_levels_must_fit_the_scale() {
+        let mut book = LimitOrderBook::with_price_scale("SCALE".to_string(), 3).unwrap();
+        book.add_order(create_order_at(OrderSide::Buy, "99.995", 10)).unwrap();
+        book.add_order(create_order_at(OrderSide::Sell, "101", 10)).unwrap();
+        let snapshot: Value = serde_json::to_value(&book).unwrap();
+
+        // Snapshots from before the scale was recorded are read as cents
+        let mut unscaled = snapshot.clone();
+        unscaled.as_object_mut().unwrap().remove("price_scale");
+        let error = LimitOrderBook::from_json(&unscaled.to_string()).unwrap_err().to_string();
+        assert!(error.contains("Price 99.995 is not a whole number of ticks at 2 decimal places"), "{error}");
+        unscaled["bids"] = serde_json::json!({});
+        let restored = LimitOrderBook::from_json(&unscaled.to_string()).unwrap();
+        assert_eq!(restored.price_scale(), 2);
+        assert_eq!(restored.best_ask(), Some(price("101.00")));
+
+        let mut too_fine = snapshot;
+        too_fine["price_scale"] = Value::from(MAX_PRICE_SCALE + 1);
+        assert!(LimitOrderBook::from_json(&too_fine.to_string()).is_err());
+    }
+}
@@ -0,0 +1,183 @@
+//! Per-book quantity scale
+//!
+//! A book counts quantities in lots of `10^-scale` shares: whole shares by
+//! default, finer for instruments traded in fractions. Every [`Quantity`] in
+//! the book, from orders and levels to trades, limits and statistics, is a
+//! lot count, so matching and the conservation invariants work on whole
+//! numbers whatever the scale. Use [`Quantity::from_decimal`] and
+//! [`Quantity::to_decimal`] with the book's scale to convert at the edges.
+//! Notional values are the one place the scale enters the book's own
+//! arithmetic: a trade of `lots` at `price` is worth `price * lots * 10^-scale`.
+//!
+//! What leaves the book says what its lots are: trades, depth levels, depth
+//! updates, and VWAP results carry the scale, the CSV export and FIX
+//! execution reports write decimal shares, and the protobuf and binary
+//! encodings keep lot counts beside the scale they are counted to. Coming
+//! in, a FIX NewOrderSingle's OrderQty is read as decimal shares at the
+//! scale it is decoded for.
+
+use super::{LimitOrderBook, MarketLevel, Trade, WindowVwap};
+use crate::{
+    quantity::MAX_QUANTITY_SCALE,
+    MatchingEngineError, Price, Quantity,
+};
+use rust_decimal::Decimal;
+
+impl MarketLevel {
+    /// Gets the level's quantity as a decimal number of shares
+    pub fn shares(&self) -> Decimal {
+        self.quantity.to_decimal(self.quantity_scale)
+    }
+}
+
+impl Trade {
+    /// Gets the traded quantity as a decimal number of shares
+    pub fn shares(&self) -> Decimal {
+        self.quantity.to_decimal(self.quantity_scale)
+    }
+}
+
+impl WindowVwap {
+    /// Gets the volume as a decimal number of shares
+    pub fn shares(&self) -> Decimal {
+        Decimal::from_i128_with_scale(i128::from(self.volume), self.quantity_scale)
+    }
+}
+
+/// Value of `lots` lots of `10^-scale` at `price`
+pub(crate) fn notional(price: Price, lots: u64, scale: u32) -> Decimal {
+    price.value() * Decimal::from_i128_with_scale(i128::from(lots), scale)
+}
+
+impl LimitOrderBook {
+    /// Creates a new empty order book counting quantities in lots of `10^-quantity_scale`
+    ///
+    /// # Errors
+    /// Returns error if `quantity_scale` is above [`MAX_QUANTITY_SCALE`]
+    pub fn with_quantity_scale(symbol: String, quantity_scale: u32) -> crate::Result<Self> {
+        let mut book = Self::new(symbol)?;
+        book.quantity_scale = quantity_scale;
+        book.check_quantity_scale()?;
+        Ok(book)
+    }
+
+    /// Gets the number of decimal places quantities are counted to
+    pub fn quantity_scale(&self) -> u32 {
+        self.quantity_scale
+    }
+
+    /// Gets a quantity of this book as a decimal number of shares
+    pub fn quantity_to_decimal(&self, quantity: Quantity) -> Decimal {
+        quantity.to_decimal(self.quantity_scale)
+    }
+
+    /// Rejects a quantity scale above [`MAX_QUANTITY_SCALE`]
+    pub(super) fn check_quantity_scale(&self) -> crate::Result<()> {
+        if self.quantity_scale > MAX_QUANTITY_SCALE {
+            return Err(MatchingEngineError::InvalidQuantity(format!(
+                "Quantity scale {} is above the maximum of {}", self.quantity_scale, MAX_QUANTITY_SCALE
+            )));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        order_book::FeeSchedule,
+        types::{OrderId, UserId},
+        Order, OrderSide,
+    };
+    use serde_json::Value;
+
+    fn decimal(value: &str) -> Decimal {
+        value.parse().unwrap()
+    }
+
+    fn create_test_order(side: OrderSide, price: &str, shares: &str, user: &str) -> Order {
+        Order::new(
+            OrderId::new(),
+            UserId::new(user.to_string()),
+            side,
+            Price::from_str(price).unwrap(),
+            Quantity::from_decimal(decimal(shares), 3).unwrap(),
+        )
+    }
+
+    #[test]
+    fn test_fractional_orders_match_exactly() {
+        assert!(LimitOrderBook::with_quantity_scale("FRAC".to_string(), MAX_QUANTITY_SCALE + 1).is_err());
+        let mut book = LimitOrderBook::with_quantity_scale("FRAC".to_string(), 3).unwrap();
+        assert_eq!(book.quantity_scale(), 3);
+        book.set_fee_schedule(Some(FeeSchedule::new(Decimal::ONE_HUNDRED, Decimal::ONE_HUNDRED)));
+        book.add_order(create_test_order(OrderSide::Sell, "200.00", "0.125", "maker")).unwrap();
+        book.add_order(create_test_order(OrderSide::Sell, "200.00", "0.3", "maker")).unwrap();
+
+        let trades = book.add_order(create_test_order(OrderSide::Buy, "200.00", "0.2", "taker")).unwrap();
+        let filled: Vec<Decimal> = trades.iter().map(Trade::shares).collect();
+        assert_eq!(filled, vec![decimal("0.125"), decimal("0.075")]);
+        let level = &book.market_depth(1).asks[0];
+        assert_eq!(level.shares(), decimal("0.225"));
+        assert_eq!(book.quantity_to_decimal(level.quantity), decimal("0.225"));
+
+        // Notional values count shares, not lots
+        assert_eq!(book.session_stats().notional, decimal("40.000"));
+        assert_eq!(trades[0].maker_fee, decimal("0.25"));
+        let sweep = book.cost_to_buy(Quantity::from_decimal(decimal("0.225"), 3).unwrap()).unwrap();
+        assert_eq!((sweep.total_notional, sweep.average_price), (decimal("45"), decimal("200")));
+        let vwap = book.vwap(chrono::Duration::hours(1)).unwrap();
+        assert_eq!((vwap.vwap, vwap.volume, vwap.shares()), (decimal("200"), 200, decimal("0.2")));
+        assert_eq!(book.verify_integrity(), Ok(()));
+    }
+
+    #[test]
+    fn test_scale_travels_with_the_snapshot() {
+        let mut book = LimitOrderBook::with_quantity_scale("FRAC".to_string(), 3).unwrap();
+        book.add_order(create_test_order(OrderSide::Buy, "99.00", "1.5", "maker")).unwrap();
+        let restored = LimitOrderBook::from_json(&book.to_json().unwrap()).unwrap();
+        assert_eq!(restored.quantity_scale(), 3);
+        assert_eq!(restored.market_depth(1), book.market_depth(1));
+
+        // Snapshots from before the scale was recorded count whole shares
+        let mut snapshot: Value = serde_json::to_value(&book).unwrap();
+        snapshot.as_object_mut().unwrap().remove("quantity_scale");
+        assert_eq!(LimitOrderBook::from_json(&snapshot.to_string()).unwrap().quantity_scale(), 0);
+        snapshot["quantity_scale"] = Value::from(MAX_QUANTITY_SCALE + 1);
+        assert!(LimitOrderBook::from_json(&snapshot.to_string()).is_err());
+    }
+
+    #[test]
+    fn test_outputs_say_what_their_lots_are() {
+        let mut book = LimitOrderBook::with_quantity_scale("FRAC".to_string(), 3).unwrap();
+        book.enable_delta_feed();
+        book.add_order(create_test_order(OrderSide::Buy, "99.00", "1.5", "maker")).unwrap();
+
+        // Levels and depth updates carry the scale, in memory and as JSON
+        let level = &book.market_depth(1).bids[0];
+        assert_eq!((level.quantity.value(), level.shares()), (1_500, decimal("1.5")));
+        assert_eq!(serde_json::to_value(level).unwrap()["quantity_scale"], Value::from(3));
+        let update = &book.take_deltas()[0].updates[0];
+        assert_eq!(update.quantity_scale, 3);
+        let legacy: MarketLevel = serde_json::from_str(r#"{"price":"99.00","quantity":15,"order_count":1}"#).unwrap();
+        assert_eq!(legacy.shares(), decimal("15"));
+    }
+
+    #[cfg(feature = "csv")]
+    #[test]
+    fn test_csv_quantities_are_shares() {
+        let mut book = LimitOrderBook::with_quantity_scale("FRAC".to_string(), 3).unwrap();
+        book.add_order(create_test_order(OrderSide::Buy, "99.00", "1.5", "maker")).unwrap();
+
+        // The export writes shares, read back at the importing book's scale
+        let mut csv = Vec::new();
+        book.export_orders_csv(&mut csv).unwrap();
+        assert!(String::from_utf8(csv.clone()).unwrap().contains(",99.00,1.500,1.500,ACTIVE,"));
+        let mut imported = LimitOrderBook::with_quantity_scale("FRAC".to_string(), 3).unwrap();
+        assert_eq!(imported.import_orders_csv(csv.as_slice()), Ok(1));
+        assert_eq!(imported.market_depth(1), book.market_depth(1));
+        let mut whole = LimitOrderBook::new("FRAC".to_string()).unwrap();
+        assert!(whole.import_orders_csv(csv.as_slice()).is_err());
+    }
+}
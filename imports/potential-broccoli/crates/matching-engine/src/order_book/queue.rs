@@ -0,0 +1,88 @@
+//! Queue position of a resting order
+//!
+//! Position is counted in matching priority within the order's level: every
+//! active order queued ahead of it, hidden ones included, trades first.
+//! Cancelled or filled orders still sitting in the level are not counted.
+
+use super::LimitOrderBook;
+use crate::{types::OrderId, Order, OrderSide};
+use serde::{Deserialize, Serialize};
+
+/// Where a resting order sits in its price level's queue
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QueuePosition {
+    /// Active orders that trade before this one
+    pub orders_ahead: usize,
+    /// Remaining quantity of the orders ahead
+    pub quantity_ahead: u64,
+    /// Remaining quantity of every active order at the level, this one and hidden ones included
+    pub level_quantity: u64,
+}
+
+impl LimitOrderBook {
+    /// Gets the queue position of a resting order, or `None` if it isn't resting
+    pub fn queue_position(&self, order_id: OrderId) -> Option<QueuePosition> {
+        let location = self.orders.get(&order_id)?;
+        let orders = match location.side {
+            OrderSide::Buy => self.bids.get(&location.price),
+            OrderSide::Sell => self.asks.get(&location.price),
+        }?;
+        let index = orders.entries().position(|(handle, _)| handle == location.handle)?;
+
+        let active = |order: &&Order| order.is_active();
+        let (orders_ahead, quantity_ahead) = orders.iter().take(index)
+            .filter(active)
+            .fold((0, 0), |(count, quantity), order| (count + 1, quantity + order.remaining_quantity.value()));
+        let level_quantity = orders.iter().filter(active).map(|order| order.remaining_quantity.value()).sum();
+        Some(QueuePosition { orders_ahead, quantity_ahead, level_quantity })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    use crate::order_book::test_support::create_test_order;
+
+    #[test]
+    fn test_position_advances_on_cancels_and_fills_ahead() {
+        let mut book = LimitOrderBook::new("QUEUE".to_string()).unwrap();
+        let ids: Vec<OrderId> = [30, 20, 50, 40]
+            .into_iter()
+            .map(|quantity| {
+                let order = create_test_order(OrderSide::Sell, 10000, quantity);
+                let order_id = order.id;
+                book.add_order(order).unwrap();
+                order_id
+            })
+            .collect();
+        let position = |book: &LimitOrderBook| book.queue_position(ids[3]).unwrap();
+        assert_eq!(position(&book), QueuePosition { orders_ahead: 3, quantity_ahead: 100, level_quantity: 140 });
+
+        book.cancel_order(ids[1]).unwrap();
+        assert_eq!(position(&book), QueuePosition { orders_ahead: 2, quantity_ahead: 80, level_quantity: 120 });
+
+        // Fill the head and part of the next order
+        book.add_order(create_test_order(OrderSide::Buy, 10000, 45)).unwrap();
+        assert_eq!(position(&book), QueuePosition { orders_ahead: 1, quantity_ahead: 35, level_quantity: 75 });
+        assert_eq!(book.queue_position(ids[2]).unwrap().orders_ahead, 0);
+    }
+
+    #[test]
+    fn test_hidden_orders_queue_behind_displayed() {
+        let mut book = LimitOrderBook::new("QUEUE".to_string()).unwrap();
+        let hidden = create_test_order(OrderSide::Buy, 9900, 25).with_hidden();
+        let displayed = create_test_order(OrderSide::Buy, 9900, 10);
+        let (hidden_id, displayed_id) = (hidden.id, displayed.id);
+        book.add_order(hidden).unwrap();
+        book.add_order(displayed).unwrap();
+
+        assert_eq!(book.queue_position(displayed_id).unwrap().orders_ahead, 0);
+        assert_eq!(book.queue_position(hidden_id).unwrap(), QueuePosition { orders_ahead: 1, quantity_ahead: 10, level_quantity: 35 });
+
+        // Unknown and no longer resting orders have no position
+        assert_eq!(book.queue_position(OrderId::new()), None);
+        book.cancel_order(displayed_id).unwrap();
+        assert_eq!(book.queue_position(displayed_id), None);
+    }
+}
@@ -0,0 +1,286 @@
+//! Reduce-only orders
+//!
+//! A reduce-only order may only trade against its user's net position on
+//! the book: a sell needs a long position and a buy a short one. What it may
+//! reduce is the position less the user's other resting reduce-only orders
+//! on the same side, so several of them together can't flip the position.
+//! An order is rejected when nothing is left to reduce, and trimmed when
+//! larger than what is left.
+//!
+//! Fills move the position after entry, so every trade re-trims the resting
+//! reduce-only orders of both users to fit: the lowest-priority orders are
+//! cut first, and those with nothing left to reduce are cancelled. Pending
+//! stops and parked orders aren't counted; they are checked again when
+//! released. Each side keeps every user's resting reduce-only quantity up
+//! to date, so neither the check nor a re-trim that has nothing to cut
+//! scans the book.
+
+use super::LimitOrderBook;
+use crate::{
+    events::{CancelReason, OrderEvent},
+    types::{OrderId, UserId},
+    MatchingEngineError, Order, OrderSide, Price, Quantity,
+};
+
+impl LimitOrderBook {
+    /// Checks a reduce-only order against its user's net position
+    ///
+    /// Returns how much the order exceeds what is left to reduce by (zero for
+    /// orders that fit or aren't reduce-only), or an error if nothing is left.
+    pub(super) fn check_reduce_only(&self, order: &Order) -> crate::Result<u64> {
+        if !order.reduce_only {
+            return Ok(0);
+        }
+        let reducible = self.reducible(&order.user_id, order.side)
+            - self.reduce_only_exposure(&order.user_id, order.side, order.id) as i64;
+        if reducible <= 0 {
+            return Err(self.reduce_only_error(order));
+        }
+        Ok(order.remaining_quantity.value().saturating_sub(reducible as u64))
+    }
+    
+    /// Trims an incoming reduce-only order down to what its user can still reduce
+    pub(super) fn trim_to_reduce_only(&mut self, order: &mut Order, events: &mut Vec<OrderEvent>) -> crate::Result<()> {
+        let now = self.now();
+        let excess = self.check_reduce_only(order)?;
+        if excess == 0 {
+            return Ok(());
+        }
+        let quantity = Quantity::new(excess)?;
+        order.reduce_at(quantity, now)?;
+        events.push(OrderEvent::Decremented { order_id: order.id, quantity, reason: CancelReason::ReduceOnly });
+        Ok(())
+    }
+    
+    /// Cuts the users' resting reduce-only orders back to their positions after a fill
+    ///
+    /// Returns whether any order was reduced or cancelled.
+    pub(super) fn retrim_reduce_only(&mut self, users: [&UserId; 2], events: &mut Vec<OrderEvent>) -> crate::Result<bool> {
+        let mut changed = false;
+        for user_id in users {
+            for side in [OrderSide::Buy, OrderSide::Sell] {
+                changed |= self.retrim_side(user_id, side, events)?;
+            }
+        }
+        Ok(changed)
+    }
+    
+    fn retrim_side(&mut self, user_id: &UserId, side: OrderSide, events: &mut Vec<OrderEvent>) -> crate::Result<bool> {
+        let now = self.now();
+        let levels = match side {
+            OrderSide::Buy => &self.bids,
+            OrderSide::Sell => &self.asks,
+        };
+        let reducible = self.reducible(user_id, side).max(0) as u64;
+        if levels.user_totals(user_id).reduce_only_quantity <= reducible {
+            return Ok(false);
+        }
+        // In priority order, so cutting from the back spares the orders nearest to trading
+        let resting: Vec<(OrderId, Price, u64)> = levels.iter()
+            .flat_map(|(_, orders)| orders)
+            .filter(|order| order.reduce_only && order.is_active() && &order.user_id == user_id)
+            .map(|order| (order.id, order.price, order.remaining_quantity.value()))
+            .collect();
+        let mut excess = resting.iter().map(|(_, _, remaining)| remaining).sum::<u64>().saturating_sub(reducible);
+        let changed = excess > 0;
+        
+        for (order_id, price, remaining) in resting.into_iter().rev() {
+            if excess == 0 {
+                break;
+            }
+            if excess < remaining {
+                let quantity = Quantity::new(excess)?;
+                self.resting_order_mut(order_id, side, price)?.reduce_at(quantity, now)?;
+                self.touch_level(side, price);
+                events.push(OrderEvent::Decremented { order_id, quantity, reason: CancelReason::ReduceOnly });
+                break;
+            }
+            let mut order = self.take_resting_order(order_id, side, price)?;
+            order.cancel_at(now);
+            events.push(OrderEvent::Cancelled { order, reason: CancelReason::ReduceOnly });
+            excess -= remaining;
+        }
+        Ok(changed)
+    }
+    
+    /// Gets how much of a user's position orders on `side` would reduce (negative if they'd add to it)
+    fn reducible(&self, user_id: &UserId, side: OrderSide) -> i64 {
+        match side {
+            OrderSide::Buy => -self.net_position(user_id),
+            OrderSide::Sell => self.net_position(user_id),
+        }
+    }
+    
+    /// Sums the remaining quantity of a user's resting reduce-only orders on one side, excluding `except`
+    fn reduce_only_exposure(&self, user_id: &UserId, side: OrderSide, except: OrderId) -> u64 {
+        let levels = match side {
+            OrderSide::Buy => &self.bids,
+            OrderSide::Sell => &self.asks,
+        };
+        let excluded = self.resting_order(except)
+            .filter(|order| order.reduce_only && order.is_active() && order.side == side && &order.user_id == user_id)
+            .map_or(0, |order| order.remaining_quantity.value());
+        levels.user_totals(user_id).reduce_only_quantity - excluded
+    }
+    
+    pub(super) fn reduce_only_error(&self, order: &Order) -> MatchingEngineError {
+        MatchingEngineError::ReduceOnlyWouldIncrease {
+            order_id: order.id,
+            user_id: order.user_id.to_string(),
+            position: self.net_position(&order.user_id),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        events::{CancelReason, OrderEvent},
+        types::{OrderId, UserId},
+        LimitOrderBook, MatchingEngineError, Order, OrderSide, Price, Quantity,
+    };
+    use crate::order_book::test_support::create_order;
+
+    /// alice is long 100 and carol bids for 500
+    fn book() -> LimitOrderBook {
+        let mut book = LimitOrderBook::new("RO".to_string()).unwrap();
+        book.add_order(create_order(OrderSide::Sell, 10000, 100, "bob")).unwrap();
+        book.add_order(create_order(OrderSide::Buy, 10000, 100, "alice")).unwrap();
+        book.add_order(create_order(OrderSide::Buy, 9900, 500, "carol")).unwrap();
+        book
+    }
+
+    #[test]
+    fn test_reduce_only_is_trimmed_to_position() {
+        let mut book = book();
+        
+        let result = book.submit_order(create_order(OrderSide::Sell, 9900, 150, "alice").with_reduce_only()).unwrap();
+        assert!(matches!(
+            &result.events[..],
+            [OrderEvent::Decremented { quantity, reason: CancelReason::ReduceOnly, .. }] if quantity.value() == 50
+        ));
+        assert_eq!(result.trades[0].quantity.value(), 100);
+        assert_eq!(book.net_position(&UserId::new("alice".to_string())), 0);
+    }
+
+    #[test]
+    fn test_reduce_only_rejected_without_opposite_position() {
+        let mut book = book();
+        let sequence = book.sequence();
+        
+        // carol is flat and alice is long, so neither buy can reduce anything
+        for user in ["carol", "alice"] {
+            let err = book.add_order(create_order(OrderSide::Buy, 9800, 10, user).with_reduce_only()).unwrap_err();
+            assert!(matches!(err, MatchingEngineError::ReduceOnlyWouldIncrease { .. }));
+        }
+        assert_eq!(book.sequence(), sequence);
+        
+        // bob is short 100, so his buy reduces
+        assert!(book.add_order(create_order(OrderSide::Buy, 9800, 10, "bob").with_reduce_only()).is_ok());
+    }
+
+    #[test]
+    fn test_modify_cannot_grow_past_position() {
+        let mut book = book();
+        let order = create_order(OrderSide::Sell, 10100, 60, "alice").with_reduce_only();
+        let order_id = order.id;
+        book.add_order(order).unwrap();
+        
+        assert!(book.modify_order(order_id, None, Some(Quantity::new(100).unwrap())).is_ok());
+        assert!(matches!(
+            book.modify_order(order_id, None, Some(Quantity::new(101).unwrap())),
+            Err(MatchingEngineError::ReduceOnlyWouldIncrease { position: 100, .. })
+        ));
+    }
+
+    #[test]
+    fn test_resting_reduce_only_orders_share_the_position() {
+        let mut book = book();
+        book.add_order(create_order(OrderSide::Sell, 10100, 100, "alice").with_reduce_only()).unwrap();
+        
+        // The first sell covers the whole long, so a second has nothing left to reduce
+        let err = book.add_order(create_order(OrderSide::Sell, 10200, 100, "alice").with_reduce_only()).unwrap_err();
+        assert!(matches!(err, MatchingEngineError::ReduceOnlyWouldIncrease { position: 100, .. }));
+        
+        // Resized down, it leaves room that the next one is trimmed to
+        let resting_id = book.orders_at_price(OrderSide::Sell, Price::from_cents(10100).unwrap()).unwrap()[0].id;
+        book.reduce_quantity(resting_id, Quantity::new(60).unwrap()).unwrap();
+        let result = book.submit_order(create_order(OrderSide::Sell, 10200, 100, "alice").with_reduce_only()).unwrap();
+        assert!(matches!(
+            &result.events[..],
+            [OrderEvent::Decremented { quantity, reason: CancelReason::ReduceOnly, .. }] if quantity.value() == 60
+        ));
+        
+        // Sweeping both only brings alice flat
+        let trades = book.add_order(create_order(OrderSide::Buy, 10200, 200, "dave")).unwrap();
+        assert_eq!(trades.iter().map(|trade| trade.quantity.value()).sum::<u64>(), 100);
+        assert_eq!(book.net_position(&UserId::new("alice".to_string())), 0);
+    }
+
+    #[test]
+    fn test_fills_retrim_resting_reduce_only_orders() {
+        let mut book = book();
+        let order = create_order(OrderSide::Sell, 10100, 100, "alice").with_reduce_only();
+        let order_id = order.id;
+        book.add_order(order).unwrap();
+        
+        // An ordinary sale shrinks the long, and the reduce-only sell with it
+        let result = book.submit_order(create_order(OrderSide::Sell, 9900, 60, "alice")).unwrap();
+        assert!(result.events.iter().any(|event| matches!(
+            event,
+            OrderEvent::Decremented { order_id: id, quantity, reason: CancelReason::ReduceOnly } if *id == order_id && quantity.value() == 60
+        )));
+        assert_eq!(book.get_order(order_id).unwrap().remaining_quantity.value(), 40);
+        
+        // Once flat, it is cancelled
+        let result = book.submit_order(create_order(OrderSide::Sell, 9900, 40, "alice")).unwrap();
+        assert!(result.events.iter().any(|event| matches!(
+            event,
+            OrderEvent::Cancelled { order, reason: CancelReason::ReduceOnly } if order.id == order_id
+        )));
+        assert!(book.get_order(order_id).is_none());
+        assert_eq!(book.verify_integrity(), Ok(()));
+    }
+
+    #[test]
+    fn test_sweep_cannot_flip_a_position_through_reduce_only_orders() {
+        let mut book = book();
+        book.add_order(create_order(OrderSide::Sell, 10050, 100, "alice")).unwrap();
+        book.add_order(create_order(OrderSide::Sell, 10100, 100, "alice").with_reduce_only()).unwrap();
+        
+        // Filling the ordinary sell flattens alice, which cancels the reduce-only one mid-sweep
+        let result = book.submit_order(create_order(OrderSide::Buy, 10100, 200, "dave")).unwrap();
+        assert_eq!(result.trades.len(), 1);
+        assert!(result.events.iter().any(|event| matches!(
+            event,
+            OrderEvent::Cancelled { reason: CancelReason::ReduceOnly, .. }
+        )));
+        assert_eq!(book.net_position(&UserId::new("alice".to_string())), 0);
+        assert_eq!(book.best_bid(), Some(Price::from_cents(10100).unwrap()));
+        assert_eq!(book.best_ask(), None);
+    }
+
+    #[test]
+    fn test_released_stop_counts_resting_reduce_only_orders() {
+        let mut book = book();
+        book.add_order(create_order(OrderSide::Sell, 10100, 100, "alice").with_reduce_only()).unwrap();
+        let stop = Order::stop(
+            OrderId::new(),
+            UserId::new("alice".to_string()),
+            OrderSide::Sell,
+            Price::from_cents(9900).unwrap(),
+            Quantity::new(100).unwrap(),
+        ).with_reduce_only();
+        book.set_net_position(UserId::new("alice".to_string()), 200);
+        book.add_order(stop).unwrap();
+        
+        // By release the long is back to 100, all of it covered by the resting sell
+        book.set_net_position(UserId::new("alice".to_string()), 100);
+        let result = book.submit_order(create_order(OrderSide::Sell, 9900, 10, "bob")).unwrap();
+        assert!(result.events.iter().any(|event| matches!(
+            event,
+            OrderEvent::Cancelled { reason: CancelReason::ReduceOnly, .. }
+        )));
+        assert_eq!(book.net_position(&UserId::new("alice".to_string())), 100);
+    }
+}
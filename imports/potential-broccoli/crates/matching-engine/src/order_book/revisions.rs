@@ -0,0 +1,202 @@
+//! Per-order amendment history
+//!
+//! When enabled, every accepted modification appends an [`OrderRevision`] to
+//! a side table keyed by order ID. Histories outlive the order itself, so the
+//! amendments behind a disputed fill can be retrieved after the order has
+//! left the book. Retention is bounded by the number of orders tracked: once
+//! exceeded, the order whose history was started first is evicted.
+
+use super::LimitOrderBook;
+use crate::{types::OrderId, Price, Quantity};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+
+/// One accepted change to an order's price or size
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OrderRevision {
+    /// Book sequence number of the modification
+    pub sequence: u64,
+    pub timestamp: DateTime<Utc>,
+    pub old_price: Price,
+    pub new_price: Price,
+    /// Total order quantity before the change (filled plus remaining)
+    pub old_qty: Quantity,
+    /// Total order quantity after the change
+    pub new_qty: Quantity,
+    /// Set when the change moved the order to the back of the queue
+    pub priority_lost: bool,
+    /// Caller-supplied note, e.g. the reason for the amendment
+    pub context: Option<String>,
+}
+
+/// Amendment histories for a bounded number of orders
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(super) struct RevisionLog {
+    retention: usize,
+    revisions: HashMap<OrderId, Vec<OrderRevision>>,
+    /// Orders in the order their history was started, oldest first
+    tracked: VecDeque<OrderId>,
+}
+
+impl RevisionLog {
+    fn new(retention: usize) -> Self {
+        Self { retention, revisions: HashMap::new(), tracked: VecDeque::new() }
+    }
+    
+    pub(super) fn record(&mut self, order_id: OrderId, revision: OrderRevision) {
+        if let Some(history) = self.revisions.get_mut(&order_id) {
+            history.push(revision);
+            return;
+        }
+        
+        self.revisions.insert(order_id, vec![revision]);
+        self.tracked.push_back(order_id);
+        self.evict();
+    }
+    
+    fn evict(&mut self) {
+        while self.tracked.len() > self.retention {
+            if let Some(oldest) = self.tracked.pop_front() {
+                self.revisions.remove(&oldest);
+            }
+        }
+    }
+    
+    /// Estimated bytes held by the log (see the memory module for methodology)
+    pub(super) fn estimated_bytes(&self) -> usize {
+        let entries = self.revisions.values()
+            .map(|history| {
+                history.capacity() * std::mem::size_of::<OrderRevision>()
+                    + history.iter().map(|r| r.context.as_ref().map_or(0, String::len)).sum::<usize>()
+            })
+            .sum::<usize>();
+        entries
+            + self.revisions.capacity() * (std::mem::size_of::<(OrderId, Vec<OrderRevision>)>() + 1)
+            + self.tracked.capacity() * std::mem::size_of::<OrderId>()
+    }
+}
+
+impl LimitOrderBook {
+    /// Starts recording order amendments, keeping histories for up to `retention` orders
+    pub fn enable_revision_log(&mut self, retention: usize) {
+        match self.revision_log.as_mut() {
+            Some(log) => {
+                log.retention = retention;
+                log.evict();
+            },
+            None => self.revision_log = Some(RevisionLog::new(retention)),
+        }
+    }
+    
+    /// Stops recording order amendments and discards existing histories
+    pub fn disable_revision_log(&mut self) {
+        self.revision_log = None;
+    }
+    
+    /// Gets the amendment history of an order, oldest first
+    ///
+    /// Histories remain available after the order is filled or cancelled,
+    /// until evicted by the retention limit.
+    pub fn order_revisions(&self, order_id: OrderId) -> &[OrderRevision] {
+        self.revision_log
+            .as_ref()
+            .and_then(|log| log.revisions.get(&order_id))
+            .map_or(&[], Vec::as_slice)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        LimitOrderBook, OrderSide, Price, Quantity,
+    };
+    use crate::order_book::test_support::create_test_order;
+
+    fn price(cents: i64) -> Price {
+        Price::from_cents(cents).unwrap()
+    }
+
+    fn qty(value: u64) -> Quantity {
+        Quantity::new(value).unwrap()
+    }
+
+    #[test]
+    fn test_amend_reduce_and_cancel_replace_are_recorded() {
+        let mut book = LimitOrderBook::new("REV".to_string()).unwrap();
+        book.enable_revision_log(100);
+        let order = create_test_order(OrderSide::Buy, 10000, 100);
+        let order_id = order.id;
+        book.add_order(order).unwrap();
+        book.add_order(create_test_order(OrderSide::Sell, 10100, 30)).unwrap();
+        
+        // Price amend
+        book.modify_order(order_id, Some(price(10050)), None).unwrap();
+        // Quantity reduce, keeping priority
+        book.modify_order_with_context(order_id, None, Some(qty(80)), "client size-down").unwrap();
+        // Cancel-replace into the ask: 30 trade, 70 rest at the new price
+        let result = book.modify_order(order_id, Some(price(10100)), Some(qty(100))).unwrap();
+        assert_eq!(result.trades.len(), 1);
+        
+        let revisions = book.order_revisions(order_id);
+        assert_eq!(revisions.len(), 3);
+        
+        assert_eq!((revisions[0].old_price, revisions[0].new_price), (price(10000), price(10050)));
+        assert_eq!((revisions[0].old_qty, revisions[0].new_qty), (qty(100), qty(100)));
+        assert!(revisions[0].priority_lost);
+        
+        assert_eq!((revisions[1].old_price, revisions[1].new_price), (price(10050), price(10050)));
+        assert_eq!((revisions[1].old_qty, revisions[1].new_qty), (qty(100), qty(80)));
+        assert!(!revisions[1].priority_lost);
+        assert_eq!(revisions[1].context.as_deref(), Some("client size-down"));
+        
+        assert_eq!((revisions[2].old_price, revisions[2].new_price), (price(10050), price(10100)));
+        assert_eq!((revisions[2].old_qty, revisions[2].new_qty), (qty(80), qty(100)));
+        assert!(revisions[2].priority_lost);
+        
+        assert!(revisions.windows(2).all(|pair| pair[0].sequence < pair[1].sequence));
+        assert_eq!(revisions[2].sequence, book.sequence());
+    }
+
+    #[test]
+    fn test_history_outlives_order_until_evicted() {
+        let mut book = LimitOrderBook::new("REV".to_string()).unwrap();
+        book.enable_revision_log(1);
+        let first = create_test_order(OrderSide::Buy, 10000, 100);
+        let first_id = first.id;
+        book.add_order(first).unwrap();
+        book.modify_order(first_id, None, Some(qty(50))).unwrap();
+        book.cancel_order(first_id).unwrap();
+        assert_eq!(book.order_revisions(first_id).len(), 1);
+        
+        let second = create_test_order(OrderSide::Buy, 10000, 100);
+        let second_id = second.id;
+        book.add_order(second).unwrap();
+        book.modify_order(second_id, None, Some(qty(50))).unwrap();
+        assert!(book.order_revisions(first_id).is_empty());
+        assert_eq!(book.order_revisions(second_id).len(), 1);
+    }
+
+    #[test]
+    fn test_rejected_modification_leaves_no_revision() {
+        let mut book = LimitOrderBook::new("REV".to_string()).unwrap();
+        book.enable_revision_log(10);
+        let order = create_test_order(OrderSide::Sell, 10000, 100);
+        let order_id = order.id;
+        book.add_order(order).unwrap();
+        book.add_order(create_test_order(OrderSide::Buy, 10000, 60)).unwrap();
+        
+        // Below the 60 already filled
+        assert!(book.modify_order(order_id, None, Some(qty(50))).is_err());
+        assert!(book.order_revisions(order_id).is_empty());
+        assert_eq!(book.get_order(order_id).unwrap().remaining_quantity.value(), 40);
+        
+        // Disabled by default
+        let mut plain = LimitOrderBook::new("REV".to_string()).unwrap();
+        let order = create_test_order(OrderSide::Sell, 10000, 100);
+        let order_id = order.id;
+        plain.add_order(order).unwrap();
+        plain.modify_order(order_id, Some(price(10100)), None).unwrap();
+        assert!(plain.order_revisions(order_id).is_empty());
+    }
+}
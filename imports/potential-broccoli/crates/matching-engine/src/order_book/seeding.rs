@@ -0,0 +1,69 @@
+//! Seeding the book with resting orders
+//!
+//! A seed places orders straight into their queues, in the order given,
+//! without matching them: it recreates a book rather than trading into one.
+//! Only the identity and price scale checks of a submission apply. A seed is all or nothing,
+//! and is rejected if it would leave the book crossed.
+
+use super::{LimitOrderBook, OrderBookOp};
+use crate::{MatchingEngineError, Order, OrderSide};
+use std::collections::HashSet;
+
+impl LimitOrderBook {
+    /// Rests orders in the book without matching them, returning them as rested
+    ///
+    /// Each order must be a limit order that can rest, ACTIVE or
+    /// PARTIALLY_FILLED with quantity remaining, and joins the back of its
+    /// queue. If any order is rejected, or the orders would cross each other
+    /// or the book, none of them are rested.
+    pub fn seed_orders(&mut self, orders: Vec<Order>) -> crate::Result<Vec<Order>> {
+        self.apply(OrderBookOp::Seed(orders)).seeded()
+    }
+    
+    pub(super) fn seed(&mut self, mut orders: Vec<Order>) -> crate::Result<Vec<Order>> {
+        for order in &mut orders {
+            order.symbol.get_or_insert_with(|| self.symbol.clone());
+        }
+        let mut order_ids = HashSet::new();
+        let mut client_order_ids = HashSet::new();
+        let (mut best_bid, mut best_ask) = (self.bids.best(), self.asks.best());
+        for order in &orders {
+            self.check_identity(order)?;
+            self.check_price_scale(order)?;
+            self.check_lot_size(order)?;
+            if !order_ids.insert(order.id) {
+                return Err(MatchingEngineError::DuplicateOrderId(order.id));
+            }
+            if let Some(client_order_id) = &order.client_order_id {
+                if !client_order_ids.insert((&order.user_id, client_order_id)) {
+                    return Err(MatchingEngineError::DuplicateClientOrderId {
+                        user_id: order.user_id.to_string(),
+                        client_order_id: client_order_id.clone(),
+                    });
+                }
+            }
+            if !order.can_rest() || !order.is_active() || order.remaining_quantity.value() == 0 {
+                return Err(MatchingEngineError::InvariantViolation(format!("Order {} cannot rest in the book", order.id)));
+            }
+            match order.side {
+                OrderSide::Buy => best_bid = best_bid.max(Some(order.price)),
+                OrderSide::Sell => best_ask = Some(best_ask.map_or(order.price, |ask| ask.min(order.price))),
+            }
+        }
+        if let (Some(bid), Some(ask)) = (best_bid, best_ask) {
+            if bid >= ask {
+                return Err(MatchingEngineError::InvalidPrice(format!(
+                    "Seeded orders would cross the book: bid {bid} is at or above ask {ask}"
+                )));
+            }
+        }
+
+        self.begin_mutation();
+        for order in &orders {
+            self.track_client_order_id(order);
+            self.insert_order(order.clone())?;
+        }
+        self.end_mutation();
+        Ok(orders)
+    }
+}
@@ -0,0 +1,455 @@
+//! Trading sessions and daily rollover
+//!
+//! Live statistics, per-user counters, and trade history accumulate for the
+//! current session only. [`LimitOrderBook::roll_session`] closes them out into
+//! an immutable [`SessionArchive`], expires Day orders, re-anchors the
+//! session volume limit, and starts the next session from zero. Book sequence
+//! numbers and trade IDs are never reset, so they stay monotonic across
+//! sessions. [`LimitOrderBook::reset_session_stats`] zeroes only the
+//! statistics and counters, leaving the rest of the session in place.
+
+use super::{quantity_scale, LimitOrderBook, OrderBookOp, Trade, VolumeUsage};
+use crate::{
+    types::{Symbol, UserId},
+    Order, OrderSide, Price, Quantity, TimeInForce,
+};
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+
+/// Trade statistics accumulated over a session
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct SessionStats {
+    pub trade_count: u64,
+    pub volume: u64,
+    pub notional: Decimal,
+    pub open: Option<Price>,
+    pub high: Option<Price>,
+    pub low: Option<Price>,
+    pub close: Option<Price>,
+    /// Number of orders submitted
+    pub orders_submitted: u64,
+    /// Number of orders cancelled on request
+    #[serde(default)]
+    pub orders_cancelled: u64,
+    /// Number of orders that failed pre-trade checks
+    #[serde(default)]
+    pub orders_rejected: u64,
+    /// Number of distinct users who submitted, cancelled, or traded
+    #[serde(default)]
+    pub unique_users: u64,
+}
+
+impl SessionStats {
+    fn record_trade(&mut self, price: Price, quantity: Quantity, notional: Decimal) {
+        self.trade_count += 1;
+        self.volume += quantity.value();
+        self.notional += notional;
+        self.open.get_or_insert(price);
+        self.high = Some(self.high.map_or(price, |high| high.max(price)));
+        self.low = Some(self.low.map_or(price, |low| low.min(price)));
+        self.close = Some(price);
+    }
+}
+
+/// Per-user activity accumulated over a session
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct UserSessionCounters {
+    pub orders_submitted: u64,
+    #[serde(default)]
+    pub orders_cancelled: u64,
+    #[serde(default)]
+    pub orders_rejected: u64,
+    pub trade_count: u64,
+    pub bought: u64,
+    pub sold: u64,
+    pub notional: Decimal,
+}
+
+/// Live accumulators for the session in progress
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(super) struct Session {
+    id: String,
+    started_at: DateTime<Utc>,
+    /// Book sequence number when the session started
+    start_sequence: u64,
+    stats: SessionStats,
+    user_counters: HashMap<UserId, UserSessionCounters>,
+}
+
+impl Session {
+    pub(super) fn new(id: String, start_sequence: u64, started_at: DateTime<Utc>) -> Self {
+        Self {
+            id,
+            started_at,
+            start_sequence,
+            stats: SessionStats::default(),
+            user_counters: HashMap::new(),
+        }
+    }
+}
+
+impl Session {
+    /// Gets a user's counters, counting the user as seen on first use
+    fn user_counters_mut(&mut self, user_id: &UserId) -> &mut UserSessionCounters {
+        if !self.user_counters.contains_key(user_id) {
+            self.stats.unique_users += 1;
+        }
+        self.user_counters.entry(user_id.clone()).or_default()
+    }
+}
+
+impl Default for Session {
+    fn default() -> Self {
+        Self::new(String::new(), 0, Utc::now())
+    }
+}
+
+/// Frozen record of a completed session
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SessionArchive {
+    pub symbol: Symbol,
+    pub session_id: String,
+    pub started_at: DateTime<Utc>,
+    pub ended_at: DateTime<Utc>,
+    /// Book sequence numbers bounding the session: (start, end]
+    pub start_sequence: u64,
+    pub end_sequence: u64,
+    pub stats: SessionStats,
+    pub user_counters: HashMap<UserId, UserSessionCounters>,
+    /// Volume counted against the session volume limit
+    pub volume_usage: VolumeUsage,
+    /// Trades executed during the session, oldest first (bounded like `recent_trades`)
+    pub trades: Vec<Trade>,
+    /// Day orders expired by the rollover
+    pub expired_orders: Vec<Order>,
+}
+
+impl SessionArchive {
+    /// Estimated bytes held by the archive (see the memory module for methodology)
+    pub(super) fn estimated_bytes(&self) -> usize {
+        use std::mem::size_of;
+        size_of::<Self>()
+            + self.trades.capacity() * size_of::<Trade>()
+            + self.expired_orders.capacity() * size_of::<Order>()
+            + self.user_counters.capacity() * (size_of::<(UserId, UserSessionCounters)>() + 1)
+    }
+}
+
+impl LimitOrderBook {
+    /// Gets the ID of the current session
+    pub fn session_id(&self) -> &str {
+        &self.session.id
+    }
+
+    /// Gets the trade statistics of the current session
+    pub fn session_stats(&self) -> &SessionStats {
+        &self.session.stats
+    }
+
+    /// Starts the session's statistics and user counters over, returning the finished statistics
+    ///
+    /// Resting orders, recent trades, and the session ID are left alone.
+    pub fn reset_session_stats(&mut self) -> SessionStats {
+        self.apply(OrderBookOp::ResetSessionStats).session_stats()
+    }
+    
+    pub(super) fn restart_session_stats(&mut self) -> SessionStats {
+        self.session.user_counters.clear();
+        std::mem::take(&mut self.session.stats)
+    }
+
+    /// Gets a user's activity in the current session
+    pub fn user_session_counters(&self, user_id: &UserId) -> Option<&UserSessionCounters> {
+        self.session.user_counters.get(user_id)
+    }
+
+    /// Gets the archives of the most recently completed sessions, oldest first
+    pub fn session_archives(&self) -> &VecDeque<SessionArchive> {
+        &self.session_archives
+    }
+
+    /// Sets how many completed session archives the book retains
+    pub fn set_session_archive_retention(&mut self, retention: usize) {
+        self.session_archive_retention = retention;
+        while self.session_archives.len() > retention {
+            self.session_archives.pop_front();
+        }
+    }
+
+    /// Closes the current session and starts `new_session_id`
+    ///
+    /// Expires every resting Day order, freezes the session's statistics,
+    /// user counters, and trades into the returned archive, and resets them
+    /// for the new session. The session volume limit starts counting again
+    /// from zero; a halt it triggered stays in force until resumed. GTC orders
+    /// keep resting with their queue priority. The archive is also retained
+    /// on the book, up to the configured retention.
+    pub fn roll_session(&mut self, new_session_id: impl Into<String>) -> SessionArchive {
+        self.apply(OrderBookOp::RollSession(new_session_id.into())).session_archive()
+    }
+
+    pub(super) fn roll(&mut self, new_session_id: String) -> SessionArchive {
+        let expired_orders = self.expire_day();
+
+        let next = Session::new(new_session_id, self.sequence, self.now());
+        let session = std::mem::replace(&mut self.session, next);
+        let archive = SessionArchive {
+            symbol: self.symbol.clone(),
+            session_id: session.id,
+            started_at: session.started_at,
+            ended_at: self.session.started_at,
+            start_sequence: session.start_sequence,
+            end_sequence: self.sequence,
+            stats: session.stats,
+            user_counters: session.user_counters,
+            volume_usage: std::mem::take(&mut self.volume_usage),
+            trades: self.take_recent_trades(),
+            expired_orders,
+        };
+
+        if self.session_archive_retention > 0 {
+            if self.session_archives.len() == self.session_archive_retention {
+                self.session_archives.pop_front();
+            }
+            self.session_archives.push_back(archive.clone());
+        }
+        archive
+    }
+
+    /// Expires every Day order without rolling the session
+    ///
+    /// Parked Day orders and pending Day stops expire along with resting ones.
+    /// Returns the expired orders, marked `Expired`. GTC orders keep resting
+    /// with their queue priority. Only levels holding a Day order are
+    /// rewritten, and each of those in a single pass.
+    pub fn end_of_day(&mut self) -> Vec<Order> {
+        self.apply(OrderBookOp::EndOfDay).orders()
+    }
+    
+    pub(super) fn expire_day(&mut self) -> Vec<Order> {
+        self.begin_mutation();
+        let expired = self.expire_day_orders();
+        self.prune_client_order_ids();
+        self.end_mutation();
+        expired
+    }
+
+    /// Counts an order submission toward the session
+    pub(super) fn record_session_order(&mut self, user_id: &UserId) {
+        self.session.stats.orders_submitted += 1;
+        self.session.user_counters_mut(user_id).orders_submitted += 1;
+    }
+
+    /// Counts a requested cancellation toward the session
+    pub(super) fn record_session_cancel(&mut self, user_id: &UserId) {
+        self.session.stats.orders_cancelled += 1;
+        self.session.user_counters_mut(user_id).orders_cancelled += 1;
+    }
+
+    /// Counts an order rejected by pre-trade checks toward the session
+    pub(super) fn record_session_reject(&mut self, user_id: &UserId) {
+        self.session.stats.orders_rejected += 1;
+        self.session.user_counters_mut(user_id).orders_rejected += 1;
+    }
+
+    /// Counts a committed trade toward the session and both counterparties
+    pub(super) fn record_session_trade(
+        &mut self,
+        price: Price,
+        quantity: Quantity,
+        incoming: (&UserId, OrderSide),
+        resting: &UserId,
+    ) {
+        let notional = quantity_scale::notional(price, quantity.value(), self.quantity_scale);
+        self.session.stats.record_trade(price, quantity, notional);
+
+        let (incoming_user, incoming_side) = incoming;
+        for (user_id, side) in [(incoming_user, incoming_side), (resting, incoming_side.opposite())] {
+            let counters = self.session.user_counters_mut(user_id);
+            counters.trade_count += 1;
+            counters.notional += notional;
+            match side {
+                OrderSide::Buy => counters.bought += quantity.value(),
+                OrderSide::Sell => counters.sold += quantity.value(),
+            }
+        }
+    }
+
+    /// Removes every resting, parked, and pending stop Day order, preserving FIFO among the survivors
+    ///
+    /// At-the-close orders are never Day orders, so the closing book is left as is.
+    fn expire_day_orders(&mut self) -> Vec<Order> {
+        let now = self.now();
+        let mut expired = self.stops.expire_day_orders(now);
+        let (parked, kept): (Vec<Order>, Vec<Order>) = std::mem::take(&mut self.parked)
+            .into_iter()
+            .partition(|order| order.time_in_force == TimeInForce::Day);
+        self.parked = kept;
+        for mut order in parked {
+            self.parked_totals.remove(&order);
+            order.expire_at(now);
+            expired.push(order);
+        }
+        let resting = self.extract_resting(|order| order.time_in_force == TimeInForce::Day);
+        expired.extend(resting.into_iter().map(|mut order| {
+            order.expire_at(now);
+            order
+        }));
+        self.prune_brackets();
+        expired
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{types::OrderId, OrderStatus, VolumeLimit};
+    use crate::order_book::test_support::create_order;
+
+    #[test]
+    fn test_rollover_freezes_old_session_and_starts_from_zero() {
+        let mut book = LimitOrderBook::new("SES".to_string()).unwrap();
+        book.set_session_archive_retention(2);
+        book.set_volume_limit(Some(VolumeLimit::quantity(1_000)));
+        book.add_order(create_order(OrderSide::Sell, 10000, 100, "alice")).unwrap();
+        let day_trades = book.add_order(create_order(OrderSide::Buy, 10000, 60, "bob")).unwrap();
+
+        let archive = book.roll_session("day-2");
+
+        assert_eq!(archive.stats.trade_count, 1);
+        assert_eq!(archive.stats.volume, 60);
+        assert_eq!(archive.stats.orders_submitted, 2);
+        assert_eq!(archive.stats.close, Some(Price::from_cents(10000).unwrap()));
+        assert_eq!(archive.user_counters[&UserId::new("alice".to_string())].sold, 60);
+        assert_eq!(archive.user_counters[&UserId::new("bob".to_string())].bought, 60);
+        assert_eq!(archive.volume_usage.traded_quantity, 60);
+        assert_eq!(archive.trades, day_trades);
+        assert_eq!(archive.end_sequence, book.sequence());
+
+        // The new session starts from zero while the resting GTC order survives
+        assert_eq!(book.session_id(), "day-2");
+        assert_eq!(book.session_stats(), &SessionStats::default());
+        assert!(book.user_session_counters(&UserId::new("alice".to_string())).is_none());
+        assert_eq!(book.volume_usage().traded_quantity, 0);
+        assert_eq!(book.recent_trades().len(), 0);
+        assert_eq!(book.best_ask_quantity().unwrap().value(), 40);
+
+        // Trades in the new session continue the global numbering
+        let trades = book.add_order(create_order(OrderSide::Buy, 10000, 40, "carol")).unwrap();
+        assert_eq!(trades[0].trade_id, day_trades[0].trade_id + 1);
+        assert!(trades[0].sequence > archive.end_sequence);
+        assert_eq!(book.session_stats().volume, 40);
+
+        // The archive retained on the book is unaffected by later trading
+        assert_eq!(book.session_archives().back(), Some(&archive));
+        assert_eq!(book.session_archives()[0].stats.volume, 60);
+    }
+
+    #[test]
+    fn test_partial_fill_then_cancel_counts_once_per_bucket() {
+        let mut book = LimitOrderBook::new("SES".to_string()).unwrap();
+        let resting = create_order(OrderSide::Sell, 10000, 100, "alice");
+        let (resting_id, duplicate) = (resting.id, resting.clone());
+        book.add_order(resting).unwrap();
+        book.add_order(create_order(OrderSide::Buy, 10000, 30, "bob")).unwrap();
+        book.add_order(create_order(OrderSide::Buy, 10000, 20, "bob")).unwrap();
+        assert!(book.add_order(duplicate).is_err());
+        book.cancel_order(resting_id).unwrap();
+        assert!(book.cancel_order(resting_id).is_err());
+
+        let stats = book.session_stats().clone();
+        assert_eq!((stats.orders_submitted, stats.orders_cancelled, stats.orders_rejected), (3, 1, 1));
+        assert_eq!((stats.trade_count, stats.volume, stats.notional), (2, 50, Decimal::new(5000, 0)));
+        assert_eq!(stats.unique_users, 2);
+        let alice = book.user_session_counters(&UserId::new("alice".to_string())).unwrap();
+        assert_eq!((alice.orders_submitted, alice.orders_cancelled, alice.orders_rejected, alice.sold), (1, 1, 1, 50));
+
+        // The counters survive a snapshot round trip
+        let json = serde_json::to_string(&book).unwrap();
+        let restored: LimitOrderBook = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored.session_stats(), &stats);
+    }
+
+    #[test]
+    fn test_reset_session_stats_keeps_orders_and_trades() {
+        let mut book = LimitOrderBook::new("SES".to_string()).unwrap();
+        book.add_order(create_order(OrderSide::Sell, 10000, 100, "alice")).unwrap();
+        let trades = book.add_order(create_order(OrderSide::Buy, 10000, 40, "bob")).unwrap();
+
+        let finished = book.reset_session_stats();
+        assert_eq!((finished.trade_count, finished.unique_users), (1, 2));
+        assert_eq!(book.session_stats(), &SessionStats::default());
+        assert!(book.user_session_counters(&UserId::new("bob".to_string())).is_none());
+        assert!(book.recent_trades().eq(&trades));
+        assert_eq!(book.best_ask_quantity().unwrap().value(), 60);
+
+        // Users are counted afresh after a reset
+        book.add_order(create_order(OrderSide::Buy, 10000, 10, "bob")).unwrap();
+        assert_eq!((book.session_stats().unique_users, book.session_stats().volume), (2, 10));
+    }
+
+    #[test]
+    fn test_rollover_expires_day_orders_only() {
+        let mut book = LimitOrderBook::new("SES".to_string()).unwrap();
+        let gtc_first = create_order(OrderSide::Buy, 10000, 10, "a");
+        let day = create_order(OrderSide::Buy, 10000, 20, "b").with_time_in_force(TimeInForce::Day);
+        let gtc_last = create_order(OrderSide::Buy, 10000, 30, "c");
+        let day_only = create_order(OrderSide::Sell, 10100, 40, "d").with_time_in_force(TimeInForce::Day);
+        let (first_id, day_id, last_id) = (gtc_first.id, day.id, gtc_last.id);
+        for order in [gtc_first, day, gtc_last, day_only] {
+            book.add_order(order).unwrap();
+        }
+
+        let archive = book.roll_session("next");
+
+        assert_eq!(archive.expired_orders.len(), 2);
+        assert!(archive.expired_orders.iter().all(|order| order.status == OrderStatus::Expired));
+        assert!(book.get_order(day_id).is_none());
+        assert!(book.best_ask().is_none());
+        assert_eq!(book.order_count(), 2);
+
+        // FIFO among the surviving GTC orders is preserved
+        let trades = book.add_order(create_order(OrderSide::Sell, 10000, 40, "e")).unwrap();
+        assert_eq!(trades[0].buy_order_id, first_id);
+        assert_eq!(trades[1].buy_order_id, last_id);
+
+        // Retention defaults to zero: the archive is only handed to the caller
+        assert!(book.session_archives().is_empty());
+    }
+
+    #[test]
+    fn test_end_of_day_keeps_session_and_gtc_queue() {
+        let mut book = LimitOrderBook::new("SES".to_string()).unwrap();
+        let mut gtc_ids = Vec::new();
+        for i in 0..1_000 {
+            let time_in_force = if i % 3 == 0 { TimeInForce::GTC } else { TimeInForce::Day };
+            let order = create_order(OrderSide::Buy, 10000 - (i % 5), 10, "maker").with_time_in_force(time_in_force);
+            if time_in_force == TimeInForce::GTC && i % 5 == 0 {
+                gtc_ids.push(order.id);
+            }
+            book.add_order(order).unwrap();
+        }
+        book.add_order(create_order(OrderSide::Sell, 10100, 10, "maker").with_time_in_force(TimeInForce::Day)).unwrap();
+        let later = chrono::Utc::now() + chrono::Duration::hours(1);
+        book.add_order(create_order(OrderSide::Sell, 10200, 10, "maker").with_time_in_force(TimeInForce::Day).with_activation(later)).unwrap();
+        book.add_order(create_order(OrderSide::Sell, 10200, 10, "maker").with_activation(later)).unwrap();
+        let before = chrono::Utc::now();
+
+        let expired = book.end_of_day();
+
+        assert_eq!(expired.len(), 668);
+        assert_eq!(book.parked_order_count(), 1);
+        assert!(expired.iter().all(|order| order.status == OrderStatus::Expired && order.updated_at >= before));
+        assert_eq!(book.order_count(), 334);
+        assert!(book.best_ask().is_none());
+        assert_eq!(book.market_depth(10).bids.len(), 5);
+        assert_eq!(book.session_stats().orders_submitted, 1_001);
+
+        // Survivors at the top level still trade in arrival order
+        let trades = book.add_order(create_order(OrderSide::Sell, 10000, 10 * gtc_ids.len() as u64, "taker")).unwrap();
+        let buyers: Vec<OrderId> = trades.iter().map(|trade| trade.buy_order_id).collect();
+        assert_eq!(buyers, gtc_ids);
+    }
+}
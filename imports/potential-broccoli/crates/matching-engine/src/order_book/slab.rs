@@ -0,0 +1,182 @@
+//! Slab storage for resting orders
+//!
+//! Each side of the book keeps its resting orders in one slab: a `Vec` of
+//! slots, each empty or holding an order, with a free list of the empty
+//! ones. An order keeps its slot for as long as it rests, so its handle, the
+//! slot's index, stays valid until it leaves. Price levels queue handles
+//! rather than orders, and the order lookup maps each ID to its handle, so
+//! reaching a resting order by ID is one hash lookup and one index.
+//!
+//! Each slot also links its order to the ones before and after it in its
+//! level's queue, so a queue is a doubly linked list through the slab. An
+//! order found by handle leaves its queue by relinking its two neighbours,
+//! in O(1) wherever it sits, and the orders behind it keep their places.
+//!
+//! A removed order's slot goes on the free list for the next order to take,
+//! so a book that churns through cancels stops allocating once its slab has
+//! grown to the most orders it has held at once.
+
+use crate::{types::OrderId, Order, OrderSide, Price};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::HashMap;
+use std::ops::{Index, IndexMut};
+
+/// Slot of a resting order in its side's slab
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(super) struct OrderHandle(u32);
+
+/// One slot: an order, if held, and its neighbours in its level's queue
+#[derive(Debug, Clone)]
+struct Slot {
+    order: Option<Order>,
+    prev: Option<OrderHandle>,
+    next: Option<OrderHandle>,
+}
+
+/// Orders of one side, each in a slot that stays put while it rests
+#[derive(Debug, Clone, Default)]
+pub(super) struct OrderSlab {
+    slots: Vec<Slot>,
+    /// Indices of the empty slots, reused most recently freed first
+    free: Vec<u32>,
+}
+
+impl OrderSlab {
+    pub(super) fn with_capacity(capacity: usize) -> Self {
+        Self { slots: Vec::with_capacity(capacity), free: Vec::new() }
+    }
+
+    /// Stores an order in a free slot between two queue neighbours, growing the slab if there is none
+    ///
+    /// Only the new slot's links are set; the caller relinks the neighbours.
+    pub(super) fn insert(&mut self, order: Order, prev: Option<OrderHandle>, next: Option<OrderHandle>) -> OrderHandle {
+        let slot = Slot { order: Some(order), prev, next };
+        if let Some(index) = self.free.pop() {
+            self.slots[index as usize] = slot;
+            return OrderHandle(index);
+        }
+        let index = u32::try_from(self.slots.len()).expect("order slab holds at most u32::MAX orders");
+        self.slots.push(slot);
+        OrderHandle(index)
+    }
+
+    /// Takes the order out of its slot, freeing the slot for reuse
+    ///
+    /// Returns the order with its former neighbours, which the caller relinks.
+    pub(super) fn remove(&mut self, handle: OrderHandle) -> Option<(Order, Option<OrderHandle>, Option<OrderHandle>)> {
+        let slot = self.slots.get_mut(handle.0 as usize)?;
+        let order = slot.order.take()?;
+        let (prev, next) = (slot.prev.take(), slot.next.take());
+        self.free.push(handle.0);
+        Some((order, prev, next))
+    }
+
+    pub(super) fn get(&self, handle: OrderHandle) -> Option<&Order> {
+        self.slots.get(handle.0 as usize)?.order.as_ref()
+    }
+
+    pub(super) fn get_mut(&mut self, handle: OrderHandle) -> Option<&mut Order> {
+        self.slots.get_mut(handle.0 as usize)?.order.as_mut()
+    }
+
+    /// The order queued before the one in slot `handle`
+    pub(super) fn prev(&self, handle: OrderHandle) -> Option<OrderHandle> {
+        self.slots[handle.0 as usize].prev
+    }
+
+    /// The order queued after the one in slot `handle`
+    pub(super) fn next(&self, handle: OrderHandle) -> Option<OrderHandle> {
+        self.slots[handle.0 as usize].next
+    }
+
+    pub(super) fn set_prev(&mut self, handle: OrderHandle, prev: Option<OrderHandle>) {
+        self.slots[handle.0 as usize].prev = prev;
+    }
+
+    pub(super) fn set_next(&mut self, handle: OrderHandle, next: Option<OrderHandle>) {
+        self.slots[handle.0 as usize].next = next;
+    }
+
+    /// Bytes taken by one slot, held or free
+    pub(super) const fn slot_size() -> usize {
+        std::mem::size_of::<Slot>()
+    }
+
+    /// Number of orders held
+    pub(super) fn len(&self) -> usize {
+        self.slots.len() - self.free.len()
+    }
+
+    /// Number of slots allocated, held or not
+    pub(super) fn capacity(&self) -> usize {
+        self.slots.capacity()
+    }
+}
+
+impl Index<OrderHandle> for OrderSlab {
+    type Output = Order;
+
+    /// Reads a queued order; a level only ever queues handles to held slots
+    fn index(&self, handle: OrderHandle) -> &Order {
+        self.get(handle).expect("order handle refers to an empty slot")
+    }
+}
+
+impl IndexMut<OrderHandle> for OrderSlab {
+    fn index_mut(&mut self, handle: OrderHandle) -> &mut Order {
+        self.get_mut(handle).expect("order handle refers to an empty slot")
+    }
+}
+
+/// Where a resting order is: its side, its level's price, and its slot
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) struct OrderLocation {
+    pub(super) side: OrderSide,
+    pub(super) price: Price,
+    pub(super) handle: OrderHandle,
+}
+
+impl Serialize for OrderLocation {
+    /// Serializes as the side and price alone, as before orders had slots;
+    /// handles are rebuilt from the levels on restore
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        (self.side, self.price).serialize(serializer)
+    }
+}
+
+/// Reads the order lookup of a snapshot, which restoring rebuilds from the levels
+pub(super) fn deserialize_locations<'de, D: Deserializer<'de>>(deserializer: D) -> Result<HashMap<OrderId, OrderLocation>, D::Error> {
+    HashMap::<OrderId, (OrderSide, Price)>::deserialize(deserializer)?;
+    Ok(HashMap::new())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    use crate::order_book::test_support::create_test_order;
+
+    #[test]
+    fn test_freed_slots_are_reused() {
+        let mut slab = OrderSlab::default();
+        let orders: Vec<Order> = (0..3).map(|i| create_test_order(OrderSide::Buy, 10000 + i, 10)).collect();
+        let handles: Vec<OrderHandle> = orders.iter().cloned().map(|order| slab.insert(order, None, None)).collect();
+        assert_eq!(slab.len(), 3);
+        assert_eq!(slab[handles[1]].id, orders[1].id);
+
+        slab.set_next(handles[1], Some(handles[2]));
+        let (removed, prev, next) = slab.remove(handles[1]).unwrap();
+        assert_eq!((removed.id, prev, next), (orders[1].id, None, Some(handles[2])));
+        assert!(slab.remove(handles[1]).is_none());
+        assert_eq!(slab.get(handles[1]), None);
+        assert_eq!(slab.len(), 2);
+
+        // The freed slot is taken before the slab grows, and the others stay put
+        let next = create_test_order(OrderSide::Buy, 10003, 10);
+        assert_eq!(slab.insert(next.clone(), Some(handles[0]), None), handles[1]);
+        assert_eq!(slab.prev(handles[1]), Some(handles[0]));
+        assert_eq!(slab.slots.len(), 3);
+        assert_eq!(slab[handles[1]].id, next.id);
+        assert_eq!(slab[handles[2]].id, orders[2].id);
+    }
+}
@@ -0,0 +1,137 @@
+//! Execution quality: slippage of fills against the arrival benchmark
+
+use super::LimitOrderBook;
+use crate::{events::ExecutionResult, OrderSide, Price, Quantity, types::OrderId};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+/// Slippage of a single fill against the arrival benchmark
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FillSlippage {
+    pub trade_id: u64,
+    pub price: Price,
+    pub quantity: Quantity,
+    /// Fill price minus the arrival benchmark
+    pub slippage: Decimal,
+}
+
+/// Quantity-weighted slippage of an order's fills
+/// 
+/// Slippage is signed as `fill price - benchmark`: positive values are adverse
+/// for buys and favourable for sells.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SlippageReport {
+    pub order_id: OrderId,
+    pub side: OrderSide,
+    /// Benchmark price captured when the order arrived
+    pub benchmark: Decimal,
+    pub fills: Vec<FillSlippage>,
+    pub filled_quantity: u64,
+    /// Quantity-weighted average slippage across all fills
+    pub weighted_slippage: Decimal,
+}
+
+impl ExecutionResult {
+    /// Computes the slippage of this order's fills against its arrival benchmark
+    /// 
+    /// Returns `None` if no benchmark was available or nothing was filled.
+    pub fn slippage_report(&self) -> Option<SlippageReport> {
+        let benchmark = self.arrival_benchmark?;
+        if self.trades.is_empty() {
+            return None;
+        }
+        
+        let fills: Vec<FillSlippage> = self.trades.iter()
+            .map(|trade| FillSlippage {
+                trade_id: trade.trade_id,
+                price: trade.price,
+                quantity: trade.quantity,
+                slippage: trade.price.value() - benchmark,
+            })
+            .collect();
+        let filled_quantity: u64 = fills.iter().map(|fill| fill.quantity.value()).sum();
+        let weighted_total: Decimal = fills.iter()
+            .map(|fill| fill.slippage * Decimal::from(fill.quantity.value()))
+            .sum();
+        
+        Some(SlippageReport {
+            order_id: self.order_id,
+            side: self.side,
+            benchmark,
+            fills,
+            filled_quantity,
+            weighted_slippage: weighted_total / Decimal::from(filled_quantity),
+        })
+    }
+}
+
+impl LimitOrderBook {
+    /// Benchmark price an incoming order on `side` is measured against
+    /// 
+    /// The mid price when both sides are quoted, otherwise the opposite touch.
+    pub(super) fn arrival_benchmark(&self, side: OrderSide) -> Option<Decimal> {
+        match (self.best_bid(), self.best_ask()) {
+            (Some(bid), Some(ask)) => Some((bid.value() + ask.value()) / Decimal::TWO),
+            (Some(bid), None) if side == OrderSide::Sell => Some(bid.value()),
+            (None, Some(ask)) if side == OrderSide::Buy => Some(ask.value()),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{LimitOrderBook, OrderSide};
+    use rust_decimal::Decimal;
+    use crate::order_book::test_support::create_test_order;
+
+    fn three_level_asks() -> LimitOrderBook {
+        let mut book = LimitOrderBook::new("SLIP".to_string()).unwrap();
+        book.add_order(create_test_order(OrderSide::Sell, 10000, 100)).unwrap();
+        book.add_order(create_test_order(OrderSide::Sell, 10050, 200)).unwrap();
+        book.add_order(create_test_order(OrderSide::Sell, 10100, 100)).unwrap();
+        book
+    }
+
+    #[test]
+    fn test_sweep_slippage_against_mid() {
+        let mut book = three_level_asks();
+        book.add_order(create_test_order(OrderSide::Buy, 9900, 100)).unwrap(); // mid = 99.50
+        
+        let result = book.submit_order(create_test_order(OrderSide::Buy, 10100, 400)).unwrap();
+        let report = result.slippage_report().unwrap();
+        
+        assert_eq!(report.benchmark, Decimal::new(9950, 2));
+        let per_fill: Vec<Decimal> = report.fills.iter().map(|f| f.slippage).collect();
+        assert_eq!(per_fill, vec![Decimal::new(50, 2), Decimal::new(100, 2), Decimal::new(150, 2)]);
+        assert_eq!(report.filled_quantity, 400);
+        // (0.50 * 100 + 1.00 * 200 + 1.50 * 100) / 400
+        assert_eq!(report.weighted_slippage, Decimal::ONE);
+    }
+
+    #[test]
+    fn test_sweep_slippage_against_touch() {
+        let mut book = three_level_asks();
+        
+        // No bids: the benchmark falls back to the best ask, so the first fill has zero slippage
+        let result = book.submit_order(create_test_order(OrderSide::Buy, 10100, 400)).unwrap();
+        let report = result.slippage_report().unwrap();
+        
+        assert_eq!(report.benchmark, Decimal::new(10000, 2));
+        assert_eq!(report.fills[0].slippage, Decimal::ZERO);
+        // (0 * 100 + 0.50 * 200 + 1.00 * 100) / 400
+        assert_eq!(report.weighted_slippage, Decimal::new(50, 2));
+    }
+
+    #[test]
+    fn test_no_report_without_fills_or_benchmark() {
+        let mut book = LimitOrderBook::new("SLIP".to_string()).unwrap();
+        let result = book.submit_order(create_test_order(OrderSide::Sell, 10000, 100)).unwrap();
+        assert!(result.arrival_benchmark.is_none());
+        assert!(result.slippage_report().is_none());
+        
+        let result = book.submit_order(create_test_order(OrderSide::Buy, 9900, 100)).unwrap();
+        assert!(result.arrival_benchmark.is_some());
+        assert!(result.slippage_report().is_none());
+    }
+}
@@ -0,0 +1,221 @@
+//! Snapshot serialization with validation on restore
+//!
+//! The price levels are the source of truth in a snapshot. The order lookup
+//! and the expiry index are still written out, but on restore both are
+//! rebuilt from the levels rather than trusted, and the result must pass
+//! [`verify_integrity`](LimitOrderBook::verify_integrity). A hand-edited or
+//! corrupted snapshot therefore fails to deserialize with the violations
+//! listed, instead of loading into a book that silently misbehaves. The
+//! levels are keyed by price as read and re-keyed at the book's price scale
+//! once it is known; a level priced finer than that scale is rejected too.
+//!
+//! Besides JSON, the `binary-snapshots` feature adds a compact CBOR encoding
+//! behind a four-byte magic number and a format version byte. Both formats
+//! carry exactly the same state and are validated the same way.
+
+use super::{positions::PendingTotals, slab::OrderLocation, LimitOrderBook, SnapshotEnvelope};
+use crate::{MatchingEngineError, OrderSide};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+/// Leading bytes of every binary snapshot
+#[cfg(feature = "binary-snapshots")]
+const SNAPSHOT_MAGIC: &[u8; 4] = b"MEOB";
+
+/// Binary snapshot format written by this version
+#[cfg(feature = "binary-snapshots")]
+const SNAPSHOT_VERSION: u8 = 1;
+
+impl Serialize for LimitOrderBook {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        LimitOrderBook::serialize(self, serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for LimitOrderBook {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let mut book = LimitOrderBook::deserialize(deserializer)?;
+        book.rebuild_indices().map_err(de::Error::custom)?;
+        Ok(book)
+    }
+}
+
+impl LimitOrderBook {
+    /// Serializes the book to a JSON snapshot
+    ///
+    /// Object keys are written in sorted order, so books in the same state
+    /// produce byte-identical snapshots whatever their maps' iteration order.
+    pub fn to_json(&self) -> crate::Result<String> {
+        serde_json::to_value(self)
+            .and_then(|value| serde_json::to_string(&value))
+            .map_err(|e| MatchingEngineError::SerializationError(e.to_string()))
+    }
+
+    /// Restores a book from a JSON snapshot, rejecting inconsistent ones
+    pub fn from_json(json: &str) -> crate::Result<Self> {
+        serde_json::from_str(json).map_err(|e| MatchingEngineError::DeserializationError(e.to_string()))
+    }
+
+    /// Serializes the book to a binary snapshot
+    #[cfg(feature = "binary-snapshots")]
+    pub fn to_bytes(&self) -> crate::Result<Vec<u8>> {
+        let mut bytes = SNAPSHOT_MAGIC.to_vec();
+        bytes.push(SNAPSHOT_VERSION);
+        ciborium::into_writer(self, &mut bytes).map_err(|e| MatchingEngineError::SerializationError(e.to_string()))?;
+        Ok(bytes)
+    }
+
+    /// Restores a book from a binary snapshot, rejecting foreign, newer, truncated, or inconsistent ones
+    #[cfg(feature = "binary-snapshots")]
+    pub fn from_bytes(bytes: &[u8]) -> crate::Result<Self> {
+        let body = bytes.strip_prefix(SNAPSHOT_MAGIC.as_slice())
+            .ok_or_else(|| MatchingEngineError::DeserializationError("Not a binary order book snapshot".to_string()))?;
+        let (&version, body) = body.split_first()
+            .ok_or_else(|| MatchingEngineError::DeserializationError("Snapshot ends before its format version".to_string()))?;
+        if version != SNAPSHOT_VERSION {
+            return Err(MatchingEngineError::DeserializationError(format!("Unsupported snapshot format version {version}")));
+        }
+        ciborium::from_reader(body).map_err(|e| MatchingEngineError::DeserializationError(e.to_string()))
+    }
+
+    /// Restores a book from a snapshot in any format this library writes
+    pub(super) fn load_snapshot(bytes: &[u8]) -> crate::Result<Self> {
+        #[cfg(feature = "binary-snapshots")]
+        if bytes.starts_with(SNAPSHOT_MAGIC) {
+            return Self::from_bytes(bytes);
+        }
+        let json = std::str::from_utf8(bytes).map_err(|e| MatchingEngineError::DeserializationError(e.to_string()))?;
+        Self::from_snapshot(SnapshotEnvelope::from_json(json)?)
+    }
+
+    /// Rebuilds the order lookup, expiry index, and pending totals, then validates the book
+    pub(super) fn rebuild_indices(&mut self) -> crate::Result<()> {
+        self.apply_price_scale().map_err(|e| MatchingEngineError::DeserializationError(e.to_string()))?;
+        self.check_quantity_scale().map_err(|e| MatchingEngineError::DeserializationError(e.to_string()))?;
+        self.orders.clear();
+        self.expiries.clear();
+        for (side, levels) in [(OrderSide::Buy, &self.bids), (OrderSide::Sell, &self.asks)] {
+            for (&price, orders) in levels.iter() {
+                for (handle, order) in orders.entries() {
+                    self.orders.insert(order.id, OrderLocation { side, price, handle });
+                    if let Some(expires_at) = order.expires_at {
+                        self.expiries.entry(expires_at).or_default().push(order.id);
+                    }
+                }
+            }
+        }
+        self.stops.rebuild_indices();
+        self.parked_totals = PendingTotals::of(&self.parked);
+
+        self.verify_integrity().map_err(|errors| {
+            let details: Vec<String> = errors.iter().map(ToString::to_string).collect();
+            MatchingEngineError::DeserializationError(details.join("; "))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::OrderId;
+    use chrono::{Duration, Utc};
+    use serde_json::Value;
+    use crate::order_book::test_support::create_test_order;
+
+    /// 99.00 bid (expiring) and 101.00 offer
+    fn snapshot() -> Value {
+        let mut book = LimitOrderBook::new("SNAP".to_string()).unwrap();
+        book.add_order(create_test_order(OrderSide::Buy, 9900, 100).with_expiry(Utc::now() + Duration::hours(1))).unwrap();
+        book.add_order(create_test_order(OrderSide::Sell, 10100, 100)).unwrap();
+        serde_json::to_value(&book).unwrap()
+    }
+
+    fn restore(snapshot: &Value) -> crate::Result<LimitOrderBook> {
+        LimitOrderBook::from_json(&snapshot.to_string())
+    }
+
+    fn deserialization_error(result: crate::Result<LimitOrderBook>) -> String {
+        match result {
+            Err(MatchingEngineError::DeserializationError(details)) => details,
+            other => panic!("expected a deserialization error, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_stale_lookup_and_expiry_index_are_rebuilt() {
+        let mut snapshot = snapshot();
+        snapshot["orders"] = serde_json::json!({});
+        snapshot["expiries"] = serde_json::json!({});
+
+        let mut book = restore(&snapshot).unwrap();
+        assert_eq!(book.order_count(), 2);
+        assert!(book.next_expiry().is_some());
+        let bid_id = book.l3_snapshot(1).bids[0].order_id;
+        assert!(book.cancel_order(bid_id).is_ok());
+        assert_eq!(book.next_expiry(), None);
+    }
+
+    #[test]
+    fn test_inconsistent_snapshots_are_rejected() {
+        let original = snapshot();
+        let first_order = |snapshot: &mut Value, side: &str| -> Value {
+            snapshot[side].as_object().unwrap().values().next().unwrap()[0].clone()
+        };
+
+        // The same order queued at two prices
+        let mut duplicated = original.clone();
+        let bid = first_order(&mut duplicated, "bids");
+        duplicated["bids"]["98.00"] = serde_json::json!([bid]);
+        let details = deserialization_error(restore(&duplicated));
+        assert!(details.contains("queued more than once"), "{details}");
+
+        // A bid resting above the offer
+        let mut crossed = original.clone();
+        let mut bid = first_order(&mut crossed, "bids");
+        bid["id"] = serde_json::to_value(OrderId::new()).unwrap();
+        crossed["bids"]["102.00"] = serde_json::json!([bid]);
+        let details = deserialization_error(restore(&crossed));
+        assert!(details.contains("Book is crossed: bid 102.00 above ask 101.00"), "{details}");
+
+        // A resting order with nothing left, alongside an empty level
+        let mut exhausted = original;
+        let ask = exhausted["asks"].as_object_mut().unwrap().values_mut().next().unwrap();
+        ask[0]["remaining_quantity"] = serde_json::json!(0);
+        exhausted["asks"]["105.00"] = serde_json::json!([]);
+        let details = deserialization_error(restore(&exhausted));
+        assert!(details.contains("has no remaining quantity") && details.contains("Empty price level"), "{details}");
+
+        // Plain serde reports the same problem instead of loading the book
+        assert!(serde_json::from_value::<LimitOrderBook>(duplicated).is_err());
+    }
+
+    #[cfg(feature = "binary-snapshots")]
+    #[test]
+    fn test_binary_round_trip_matches_json() {
+        let book = LimitOrderBook::from_json(&snapshot().to_string()).unwrap();
+        let bytes = book.to_bytes().unwrap();
+        assert_eq!(&bytes[..5], b"MEOB\x01");
+        assert!(bytes.len() < book.to_json().unwrap().len());
+
+        let restored = LimitOrderBook::from_bytes(&bytes).unwrap();
+        assert_eq!(serde_json::to_value(&restored).unwrap(), serde_json::to_value(&book).unwrap());
+    }
+
+    #[cfg(feature = "binary-snapshots")]
+    #[test]
+    fn test_foreign_newer_and_truncated_bytes_are_rejected() {
+        let bytes = LimitOrderBook::from_json(&snapshot().to_string()).unwrap().to_bytes().unwrap();
+        let mut newer = bytes.clone();
+        newer[4] = 2;
+
+        for (input, expected) in [
+            (&b"{\"symbol\""[..], "Not a binary order book snapshot"),
+            (&bytes[..4], "Snapshot ends before its format version"),
+            (&newer[..], "Unsupported snapshot format version 2"),
+        ] {
+            assert!(deserialization_error(LimitOrderBook::from_bytes(input)).contains(expected));
+        }
+        for len in [5, bytes.len() / 2, bytes.len() - 1] {
+            deserialization_error(LimitOrderBook::from_bytes(&bytes[..len]));
+        }
+    }
+}
@@ -0,0 +1,267 @@
+//! Snapshot files written atomically
+//!
+//! [`LimitOrderBook::save_to_file`] writes a temporary file beside the target,
+//! syncs it to disk, and renames it over the target, so readers only ever see
+//! the previous file or the complete new one. The snapshot is framed by a
+//! header naming its format, its length, and its CRC32; a file cut short or
+//! damaged some other way, say copied by hand mid-write, fails to load with
+//! the reason rather than loading a book that is missing part of its state.
+//!
+//! Layout, integers big-endian:
+//!
+//! | bytes | field |
+//! |-------|-------|
+//! | 4 | magic `MEOF` |
+//! | 1 | file layout version, 1 |
+//! | 1 | snapshot format: `J` for an enveloped JSON snapshot, `B` for binary |
+//! | 8 | snapshot length |
+//! | 4 | CRC32 (IEEE) of the snapshot |
+//! | n | snapshot |
+
+use super::{LimitOrderBook, SnapshotEnvelope};
+use crate::MatchingEngineError;
+use std::{fs, io::Write, path::Path};
+
+/// Leading bytes of every snapshot file
+const FILE_MAGIC: &[u8; 4] = b"MEOF";
+
+/// File layout written by this version
+const FILE_VERSION: u8 = 1;
+
+const HEADER_LEN: usize = 18;
+
+/// Encoding of the snapshot inside a snapshot file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SnapshotFormat {
+    /// Versioned JSON, as `to_snapshot`
+    Json,
+    /// Compact binary, as `to_bytes`
+    #[cfg(feature = "binary-snapshots")]
+    Binary,
+}
+
+impl SnapshotFormat {
+    fn code(self) -> u8 {
+        match self {
+            SnapshotFormat::Json => b'J',
+            #[cfg(feature = "binary-snapshots")]
+            SnapshotFormat::Binary => b'B',
+        }
+    }
+}
+
+impl LimitOrderBook {
+    /// Writes a snapshot file, replacing any file at `path` atomically
+    ///
+    /// On error the previous file, if any, is left as it was and the
+    /// temporary file is removed.
+    pub fn save_to_file(&self, path: &Path, format: SnapshotFormat) -> crate::Result<()> {
+        let snapshot = match format {
+            SnapshotFormat::Json => self.to_snapshot()?.to_json()?.into_bytes(),
+            #[cfg(feature = "binary-snapshots")]
+            SnapshotFormat::Binary => self.to_bytes()?,
+        };
+        let mut contents = Vec::with_capacity(HEADER_LEN + snapshot.len());
+        contents.extend_from_slice(FILE_MAGIC);
+        contents.push(FILE_VERSION);
+        contents.push(format.code());
+        contents.extend_from_slice(&(snapshot.len() as u64).to_be_bytes());
+        contents.extend_from_slice(&crc32fast::hash(&snapshot).to_be_bytes());
+        contents.extend_from_slice(&snapshot);
+
+        let file_name = path.file_name()
+            .ok_or_else(|| MatchingEngineError::SerializationError(format!("{} does not name a file", path.display())))?;
+        let directory = path.parent().filter(|parent| !parent.as_os_str().is_empty()).unwrap_or(Path::new("."));
+        let temporary = directory.join(format!(".{}.{}.tmp", file_name.to_string_lossy(), uuid::Uuid::new_v4()));
+        let written = write_synced(&temporary, &contents).and_then(|()| fs::rename(&temporary, path));
+        if let Err(e) = written {
+            let _ = fs::remove_file(&temporary);
+            return Err(MatchingEngineError::SerializationError(format!("Writing {}: {e}", path.display())));
+        }
+
+        // Make the rename itself durable
+        #[cfg(unix)]
+        fs::File::open(directory)
+            .and_then(|directory| directory.sync_all())
+            .map_err(|e| MatchingEngineError::SerializationError(format!("Syncing {}: {e}", directory.display())))?;
+        Ok(())
+    }
+
+    /// Restores a book from a snapshot file, checking its header and CRC before its contents
+    pub fn load_from_file(path: &Path) -> crate::Result<Self> {
+        let error = |message: String| MatchingEngineError::DeserializationError(format!("{}: {message}", path.display()));
+        let contents = fs::read(path).map_err(|e| error(e.to_string()))?;
+        let Some((header, snapshot)) = contents.split_first_chunk::<HEADER_LEN>() else {
+            return Err(error(match contents.starts_with(FILE_MAGIC) || FILE_MAGIC.starts_with(&contents) {
+                true => format!("file is truncated: {} bytes is shorter than the {HEADER_LEN}-byte header", contents.len()),
+                false => "not an order book snapshot file".to_string(),
+            }));
+        };
+        if !header.starts_with(FILE_MAGIC) {
+            return Err(error("not an order book snapshot file".to_string()));
+        }
+        if header[4] != FILE_VERSION {
+            return Err(error(format!("unsupported snapshot file version {}", header[4])));
+        }
+        let format = header[5];
+        let length = u64::from_be_bytes(header[6..14].try_into().expect("eight bytes"));
+        let crc = u32::from_be_bytes(header[14..18].try_into().expect("four bytes"));
+        if snapshot.len() as u64 != length {
+            return Err(error(match (snapshot.len() as u64) < length {
+                true => format!("file is truncated: expected {length} snapshot bytes, found {}", snapshot.len()),
+                false => format!("expected {length} snapshot bytes, found {} more", snapshot.len() as u64 - length),
+            }));
+        }
+        let computed = crc32fast::hash(snapshot);
+        if computed != crc {
+            return Err(error(format!("snapshot CRC32 is {computed:08x}, header says {crc:08x}")));
+        }
+
+        let book = match format {
+            b'J' => std::str::from_utf8(snapshot)
+                .map_err(|e| MatchingEngineError::DeserializationError(e.to_string()))
+                .and_then(SnapshotEnvelope::from_json)
+                .and_then(Self::from_snapshot),
+            #[cfg(feature = "binary-snapshots")]
+            b'B' => Self::from_bytes(snapshot),
+            #[cfg(not(feature = "binary-snapshots"))]
+            b'B' => return Err(error("binary snapshots need the binary-snapshots feature".to_string())),
+            other => return Err(error(format!("unknown snapshot format {:?}", char::from(other)))),
+        };
+        book.map_err(|e| match e {
+            MatchingEngineError::DeserializationError(message) => error(message),
+            other => other,
+        })
+    }
+}
+
+fn write_synced(path: &Path, contents: &[u8]) -> std::io::Result<()> {
+    let mut file = fs::OpenOptions::new().write(true).create_new(true).open(path)?;
+    file.write_all(contents)?;
+    file.sync_all()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        types::OrderId, OrderSide,
+    };
+    use std::path::PathBuf;
+    use crate::order_book::test_support::create_test_order;
+
+    /// An empty directory of the test's own under the system temp directory
+    struct TestDir(PathBuf);
+
+    impl TestDir {
+        fn new() -> Self {
+            let path = std::env::temp_dir().join(format!("snapshot-file-{}", OrderId::new()));
+            fs::create_dir(&path).unwrap();
+            Self(path)
+        }
+
+        fn files(&self) -> Vec<String> {
+            let mut files: Vec<_> = fs::read_dir(&self.0).unwrap().map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned()).collect();
+            files.sort();
+            files
+        }
+    }
+
+    impl Drop for TestDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn formats() -> Vec<SnapshotFormat> {
+        vec![
+            SnapshotFormat::Json,
+            #[cfg(feature = "binary-snapshots")]
+            SnapshotFormat::Binary,
+        ]
+    }
+
+    fn test_book() -> LimitOrderBook {
+        let mut book = LimitOrderBook::new("FILE".to_string()).unwrap();
+        book.add_order(create_test_order(OrderSide::Buy, 9900, 100)).unwrap();
+        book.add_order(create_test_order(OrderSide::Sell, 10100, 100)).unwrap();
+        book.add_order(create_test_order(OrderSide::Buy, 10100, 40)).unwrap();
+        book
+    }
+
+    fn load_error(path: &Path) -> String {
+        match LimitOrderBook::load_from_file(path) {
+            Err(MatchingEngineError::DeserializationError(message)) => message,
+            other => panic!("expected a deserialization error, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_format_is_sniffed_from_the_header() {
+        let dir = TestDir::new();
+        let book = test_book();
+        for (format, code) in formats().into_iter().zip([b'J', b'B']) {
+            let path = dir.0.join(format!("book-{}.snap", char::from(code)));
+            book.save_to_file(&path, format).unwrap();
+            let contents = fs::read(&path).unwrap();
+            assert_eq!(&contents[..6], [b'M', b'E', b'O', b'F', 1, code]);
+
+            let loaded = LimitOrderBook::load_from_file(&path).unwrap();
+            assert_eq!(loaded.to_json().unwrap(), book.to_json().unwrap());
+            assert_eq!(loaded.state_hash(), book.state_hash());
+        }
+        assert_eq!(dir.files().len(), formats().len());
+
+        // Saving again replaces the file and leaves no temporary file behind
+        let path = dir.0.join("book-J.snap");
+        let mut book = book;
+        book.cancel_all();
+        book.save_to_file(&path, SnapshotFormat::Json).unwrap();
+        assert!(LimitOrderBook::load_from_file(&path).unwrap().is_empty());
+        assert_eq!(dir.files().len(), formats().len());
+
+        // A bare snapshot is not a snapshot file
+        fs::write(&path, book.to_json().unwrap()).unwrap();
+        assert!(load_error(&path).ends_with("not an order book snapshot file"));
+    }
+
+    #[test]
+    fn test_torn_and_damaged_files_never_load() {
+        let dir = TestDir::new();
+        for format in formats() {
+            let path = dir.0.join("book.snap");
+            test_book().save_to_file(&path, format).unwrap();
+            let contents = fs::read(&path).unwrap();
+
+            // A crashed writer could leave any prefix of the file
+            for len in [0, 3, 10, HEADER_LEN, HEADER_LEN + 1, contents.len() / 2, contents.len() - 1] {
+                fs::write(&path, &contents[..len]).unwrap();
+                assert!(load_error(&path).contains("file is truncated"), "{format:?} cut to {len} bytes");
+            }
+
+            let mut flipped = contents.clone();
+            *flipped.last_mut().unwrap() ^= 0x20;
+            fs::write(&path, &flipped).unwrap();
+            assert!(load_error(&path).contains("snapshot CRC32 is"), "{format:?}");
+
+            let mut padded = contents.clone();
+            padded.push(b'\n');
+            fs::write(&path, &padded).unwrap();
+            assert!(load_error(&path).contains("found 1 more"), "{format:?}");
+
+            let mut newer = contents.clone();
+            newer[4] = 2;
+            fs::write(&path, &newer).unwrap();
+            assert!(load_error(&path).ends_with("unsupported snapshot file version 2"), "{format:?}");
+        }
+        assert!(load_error(&dir.0.join("missing.snap")).contains("missing.snap"));
+
+        // A failed save leaves the previous file intact
+        let path = dir.0.join("book.snap");
+        test_book().save_to_file(&path, SnapshotFormat::Json).unwrap();
+        let before = fs::read(&path).unwrap();
+        assert!(test_book().save_to_file(&dir.0.join("no-such-dir").join("book.snap"), SnapshotFormat::Json).is_err());
+        assert_eq!(fs::read(&path).unwrap(), before);
+        assert_eq!(dir.files(), vec!["book.snap".to_string()]);
+    }
+}
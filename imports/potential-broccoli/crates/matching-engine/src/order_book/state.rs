@@ -0,0 +1,197 @@
+//! Trading state of the book
+//!
+//! | State        | New orders and amendments | Matching | Cancels |
+//! |--------------|---------------------------|----------|---------|
+//! | `Open`       | accepted                  | yes      | yes     |
+//! | `Halted`     | rejected                  | no       | yes     |
+//! | `CancelOnly` | rejected (size-downs ok)  | no       | yes     |
+//! | `Closed`     | rejected                  | no       | no      |
+//! | `PreOpen`    | accepted, even if crossed | auction  | yes     |
+//!
+//! Resting orders survive every transition. Mass cancels and end-of-day
+//! expiry are administrative and run in any state.
+
+use super::{LimitOrderBook, OrderBookOp};
+use crate::MatchingEngineError;
+use serde::{Deserialize, Serialize};
+
+/// Trading state of a book
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum BookState {
+    /// Orders are accepted and matched normally
+    #[default]
+    Open,
+    /// Every order and amendment is rejected; only cancels are accepted
+    Halted,
+    /// Only cancels and in-place size reductions are accepted
+    CancelOnly,
+    /// Every order, amendment, and cancel is rejected
+    Closed,
+    /// Orders rest without matching until the opening auction uncrosses the book
+    PreOpen,
+}
+
+impl LimitOrderBook {
+    /// Gets the trading state of the book
+    pub fn state(&self) -> BookState {
+        self.state
+    }
+    
+    /// Halts trading: new orders and amendments are rejected until [`resume`](Self::resume)
+    pub fn halt(&mut self) {
+        self.apply(OrderBookOp::SetState(BookState::Halted));
+    }
+    
+    /// Administratively resumes trading after a halt
+    pub fn resume(&mut self) {
+        self.apply(OrderBookOp::SetState(BookState::Open));
+    }
+    
+    /// Opens the book for trading from any state
+    pub fn open(&mut self) {
+        self.apply(OrderBookOp::SetState(BookState::Open));
+    }
+    
+    /// Stops accepting orders while still letting users pull resting ones
+    pub fn set_cancel_only(&mut self) {
+        self.apply(OrderBookOp::SetState(BookState::CancelOnly));
+    }
+    
+    /// Closes the book: nothing is accepted until it is opened again
+    pub fn close(&mut self) {
+        self.apply(OrderBookOp::SetState(BookState::Closed));
+    }
+    
+    /// Rejects a new order, or a cancel-replace, the state doesn't allow
+    pub(super) fn check_state(&self) -> crate::Result<()> {
+        match self.state {
+            BookState::Open | BookState::PreOpen => Ok(()),
+            BookState::Halted => Err(MatchingEngineError::BookHalted(self.symbol.to_string())),
+            BookState::CancelOnly => Err(MatchingEngineError::BookCancelOnly(self.symbol.to_string())),
+            BookState::Closed => Err(MatchingEngineError::BookClosed(self.symbol.to_string())),
+        }
+    }
+    
+    /// Rejects any amendment, size-downs included, while halted or closed
+    pub(super) fn check_amendable(&self) -> crate::Result<()> {
+        match self.state {
+            BookState::Halted => Err(MatchingEngineError::BookHalted(self.symbol.to_string())),
+            _ => self.check_not_closed(),
+        }
+    }
+    
+    /// Rejects any change to resting orders once the book is closed
+    pub(super) fn check_not_closed(&self) -> crate::Result<()> {
+        if self.state == BookState::Closed {
+            return Err(MatchingEngineError::BookClosed(self.symbol.to_string()));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        types::{OrderId, UserId},
+        Order, OrderSide, Price, Quantity,
+    };
+    use crate::order_book::test_support::create_test_order;
+
+    /// 99.00 bid / 101.00 offer, 100 each
+    fn book() -> (LimitOrderBook, OrderId, OrderId) {
+        let mut book = LimitOrderBook::new("STATE".to_string()).unwrap();
+        let bid = create_test_order(OrderSide::Buy, 9900, 100);
+        let ask = create_test_order(OrderSide::Sell, 10100, 100);
+        let ids = (bid.id, ask.id);
+        book.add_order(bid).unwrap();
+        book.add_order(ask).unwrap();
+        (book, ids.0, ids.1)
+    }
+
+    #[test]
+    fn test_halt_mid_stream_leaves_book_untouched_until_reopened() {
+        let (mut book, _, ask_id) = book();
+        book.halt();
+        // Rejections are counted toward the session and the op sequence but touch nothing else
+        let without_session = |book: &LimitOrderBook| {
+            let mut value = serde_json::to_value(book).unwrap();
+            value.as_object_mut().unwrap().remove("session");
+            value.as_object_mut().unwrap().remove("op_sequence");
+            value
+        };
+        let snapshot = without_session(&book);
+        
+        assert_eq!(
+            book.add_order(create_test_order(OrderSide::Buy, 10100, 40)),
+            Err(MatchingEngineError::BookHalted("STATE".to_string()))
+        );
+        assert!(book.modify_order(ask_id, Some(Price::from_cents(9900).unwrap()), None).is_err());
+        assert_eq!(without_session(&book), snapshot);
+        assert_eq!(book.session_stats().orders_rejected, 1);
+        
+        book.open();
+        let trades = book.add_order(create_test_order(OrderSide::Buy, 10100, 40)).unwrap();
+        assert_eq!((trades[0].sell_order_id, trades[0].quantity.value()), (ask_id, 40));
+        assert_eq!(book.best_ask_quantity().unwrap().value(), 60);
+    }
+
+    #[test]
+    fn test_halt_rejects_every_new_order_and_amendment() {
+        let (mut book, bid_id, ask_id) = book();
+        book.halt();
+        let halted = |result: crate::Result<()>| result == Err(MatchingEngineError::BookHalted("STATE".to_string()));
+        
+        let passive = create_test_order(OrderSide::Buy, 9800, 10);
+        let stop = Order::stop(OrderId::new(), UserId::new("test_user".to_string()), OrderSide::Buy, Price::from_cents(10500).unwrap(), Quantity::new(10).unwrap());
+        let parked = create_test_order(OrderSide::Sell, 10200, 10).with_activation(book.now() + chrono::Duration::minutes(5));
+        let on_close = create_test_order(OrderSide::Sell, 10200, 10).with_time_in_force(crate::TimeInForce::AtTheClose);
+        for order in [passive, stop, parked, on_close] {
+            assert!(halted(book.add_order(order).map(|_| ())));
+        }
+        assert!(halted(book.modify_order(bid_id, Some(Price::from_cents(9800).unwrap()), None).map(|_| ())));
+        assert!(halted(book.reduce_quantity(bid_id, Quantity::new(60).unwrap()).map(|_| ())));
+        assert_eq!((book.pending_stop_count(), book.parked_order_count()), (0, 0));
+        assert_eq!(book.best_bid_quantity().unwrap().value(), 100);
+        
+        book.cancel_order(ask_id).unwrap();
+        assert_eq!(book.order_count(), 1);
+    }
+
+    #[test]
+    fn test_cancel_only_accepts_cancels_and_size_downs() {
+        let (mut book, bid_id, ask_id) = book();
+        book.set_cancel_only();
+        
+        assert_eq!(
+            book.add_order(create_test_order(OrderSide::Buy, 9800, 10)),
+            Err(MatchingEngineError::BookCancelOnly("STATE".to_string()))
+        );
+        assert!(matches!(
+            book.modify_order(bid_id, Some(Price::from_cents(9850).unwrap()), None),
+            Err(MatchingEngineError::BookCancelOnly(_))
+        ));
+        book.reduce_quantity(bid_id, Quantity::new(60).unwrap()).unwrap();
+        book.cancel_order(ask_id).unwrap();
+        assert_eq!(book.order_count(), 1);
+        assert_eq!(book.best_bid_quantity().unwrap().value(), 60);
+    }
+
+    #[test]
+    fn test_closed_rejects_everything_and_serializes() {
+        let (mut book, bid_id, _) = book();
+        book.close();
+        
+        let closed = |result: crate::Result<_>| matches!(result, Err(MatchingEngineError::BookClosed(_)));
+        assert!(closed(book.add_order(create_test_order(OrderSide::Sell, 10200, 10)).map(|_| ())));
+        assert!(closed(book.reduce_quantity(bid_id, Quantity::new(50).unwrap()).map(|_| ())));
+        assert!(closed(book.cancel_order(bid_id).map(|_| ())));
+        assert_eq!(book.order_count(), 2);
+        
+        let json = serde_json::to_string(&book).unwrap();
+        let mut restored: LimitOrderBook = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored.state(), BookState::Closed);
+        restored.open();
+        restored.cancel_order(bid_id).unwrap();
+    }
+}
@@ -0,0 +1,109 @@
+//! Running trade statistics
+//!
+//! Updated incrementally as each trade is committed, so reading them never
+//! scans `recent_trades`. Unlike session stats they run until explicitly
+//! reset, letting the caller choose when a new period starts.
+
+use super::{LimitOrderBook, OrderBookOp};
+use crate::{Price, Quantity};
+use serde::{Deserialize, Serialize};
+
+/// Last trade, price range, and volume since the last reset
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct BookStats {
+    pub last_trade_price: Option<Price>,
+    pub last_trade_quantity: Option<Quantity>,
+    pub high: Option<Price>,
+    pub low: Option<Price>,
+    /// Total quantity traded
+    pub volume: u64,
+    pub trade_count: u64,
+    /// Price of the last closing auction, or the last trade price if it didn't cross
+    #[serde(default)]
+    pub official_close: Option<Price>,
+}
+
+impl BookStats {
+    fn record_trade(&mut self, price: Price, quantity: Quantity) {
+        self.last_trade_price = Some(price);
+        self.last_trade_quantity = Some(quantity);
+        self.high = Some(self.high.map_or(price, |high| high.max(price)));
+        self.low = Some(self.low.map_or(price, |low| low.min(price)));
+        self.volume += quantity.value();
+        self.trade_count += 1;
+    }
+}
+
+impl LimitOrderBook {
+    /// Gets the trade statistics since the last reset
+    pub fn stats(&self) -> &BookStats {
+        &self.stats
+    }
+
+    /// Starts a new statistics period, returning the finished one
+    ///
+    /// Stop orders keep triggering off the last trade price, which a reset
+    /// does not forget.
+    pub fn reset_stats(&mut self) -> BookStats {
+        self.apply(OrderBookOp::ResetStats).stats()
+    }
+
+    /// Counts a committed trade toward the statistics
+    pub(super) fn record_stats_trade(&mut self, price: Price, quantity: Quantity) {
+        self.stats.record_trade(price, quantity);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::OrderSide;
+    use crate::order_book::test_support::create_test_order;
+
+    #[test]
+    fn test_stats_track_trades_only() {
+        let mut book = LimitOrderBook::new("STATS".to_string()).unwrap();
+        let resting = create_test_order(OrderSide::Sell, 10100, 100);
+        let resting_id = resting.id;
+        book.add_order(resting).unwrap();
+        book.add_order(create_test_order(OrderSide::Sell, 10000, 100)).unwrap();
+        assert_eq!(*book.stats(), BookStats::default());
+
+        book.add_order(create_test_order(OrderSide::Buy, 10100, 150)).unwrap();
+        book.add_order(create_test_order(OrderSide::Sell, 9900, 20)).unwrap();
+        book.add_order(create_test_order(OrderSide::Buy, 9900, 10)).unwrap();
+        let stats = *book.stats();
+        assert_eq!(stats.last_trade_price.unwrap().as_cents(), 9900);
+        assert_eq!(stats.last_trade_quantity.unwrap().value(), 10);
+        assert_eq!((stats.high.unwrap().as_cents(), stats.low.unwrap().as_cents()), (10100, 9900));
+        assert_eq!((stats.volume, stats.trade_count), (160, 3));
+
+        // Cancels, resting orders, and non-crossing amendments leave them alone
+        book.cancel_order(resting_id).unwrap();
+        let bid = create_test_order(OrderSide::Buy, 9000, 10);
+        let bid_id = bid.id;
+        book.add_order(bid).unwrap();
+        book.modify_order(bid_id, Some(Price::from_cents(9100).unwrap()), Some(Quantity::new(30).unwrap())).unwrap();
+        assert_eq!(*book.stats(), stats);
+
+        // They survive a snapshot round trip
+        let json = serde_json::to_string(&book).unwrap();
+        let restored: LimitOrderBook = serde_json::from_str(&json).unwrap();
+        assert_eq!(*restored.stats(), stats);
+    }
+
+    #[test]
+    fn test_reset_starts_new_period() {
+        let mut book = LimitOrderBook::new("STATS".to_string()).unwrap();
+        book.add_order(create_test_order(OrderSide::Sell, 10000, 100)).unwrap();
+        book.add_order(create_test_order(OrderSide::Buy, 10000, 40)).unwrap();
+
+        let finished = book.reset_stats();
+        assert_eq!(finished.trade_count, 1);
+        assert_eq!(*book.stats(), BookStats::default());
+        assert_eq!(book.last_trade_price().unwrap().as_cents(), 10000);
+
+        book.add_order(create_test_order(OrderSide::Buy, 10000, 25)).unwrap();
+        assert_eq!((book.stats().volume, book.stats().high), (25, Some(Price::from_cents(10000).unwrap())));
+    }
+}
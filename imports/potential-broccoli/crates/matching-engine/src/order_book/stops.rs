@@ -0,0 +1,485 @@
+//! Stop-market orders triggered off the last trade price
+//!
+//! Pending stops are held outside the visible book and never appear in
+//! depth. A buy stop triggers once a trade prints at or above its trigger,
+//! a sell stop at or below. Triggered stops become market orders and match
+//! straight away, within the same call as the trade that triggered them.
+//!
+//! Their own trades can trigger further stops, so triggering runs in waves:
+//! each wave releases every stop the current last price triggers (buys from
+//! the lowest trigger, sells from the highest, first come first served at
+//! each trigger) and matches them in turn, until a wave releases nothing.
+//! A halted book triggers nothing; pending stops wait for the next trade
+//! after it resumes.
+//!
+//! A released stop is checked again against its user's position limit and,
+//! if reduce-only, the position it reduces, since both may have moved while
+//! it waited; it is trimmed or cancelled like an incoming order. A
+//! good-till-date stop past its expiry is expired instead of released, and
+//! [`purge_expired`](LimitOrderBook::purge_expired) sweeps out expired stops
+//! along with resting orders.
+
+use super::{positions::PendingTotals, state::BookState, LimitOrderBook, Trade};
+use crate::{
+    events::{CancelReason, OrderEvent},
+    types::{OrderId, UserId},
+    MatchingEngineError, Order, OrderSide, OrderType, Price, TimeInForce,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Pending stop orders by side and trigger price
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub(super) struct StopBook {
+    buys: BTreeMap<Price, Vec<Order>>,
+    sells: BTreeMap<Price, Vec<Order>>,
+    /// Good-till-date stops by expiry time, rebuilt from the stops on restore
+    #[serde(skip)]
+    expiries: BTreeMap<DateTime<Utc>, Vec<OrderId>>,
+    /// Each user's pending quantity by side, rebuilt from the stops on restore
+    #[serde(skip)]
+    totals: PendingTotals,
+}
+
+impl StopBook {
+    pub(super) fn insert(&mut self, order: Order) {
+        let trigger = order.stop_trigger().expect("only stop orders are held pending");
+        if let Some(expires_at) = order.expires_at {
+            self.expiries.entry(expires_at).or_default().push(order.id);
+        }
+        self.totals.add(&order);
+        self.side_mut(order.side).entry(trigger).or_default().push(order);
+    }
+    
+    pub(super) fn len(&self) -> usize {
+        self.buys.values().chain(self.sells.values()).map(Vec::len).sum()
+    }
+    
+    pub(super) fn iter(&self) -> impl Iterator<Item = &Order> {
+        self.buys.values().chain(self.sells.values()).flatten()
+    }
+    
+    /// Changes a stop in place, keeping its user's pending quantity in step
+    pub(super) fn update<R>(&mut self, order_id: OrderId, change: impl FnOnce(&mut Order) -> R) -> Option<R> {
+        let order = self.buys.values_mut().chain(self.sells.values_mut()).flatten().find(|order| order.id == order_id)?;
+        let before = order.clone();
+        let result = change(order);
+        self.totals.replace(Some(&before), Some(order));
+        Some(result)
+    }
+    
+    /// Remaining quantity of a user's stops on one side
+    pub(super) fn open_quantity(&self, user_id: &UserId, side: OrderSide) -> u64 {
+        self.totals.get(user_id, side).quantity
+    }
+    
+    pub(super) fn remove(&mut self, order_id: OrderId) -> Option<Order> {
+        for levels in [&mut self.buys, &mut self.sells] {
+            let found = levels.iter().find_map(|(trigger, orders)| {
+                orders.iter().position(|order| order.id == order_id).map(|index| (*trigger, index))
+            });
+            if let Some((trigger, index)) = found {
+                let orders = levels.get_mut(&trigger)?;
+                let order = orders.remove(index);
+                if orders.is_empty() {
+                    levels.remove(&trigger);
+                }
+                if let Some(expires_at) = order.expires_at {
+                    untrack(&mut self.expiries, order_id, expires_at);
+                }
+                self.totals.remove(&order);
+                return Some(order);
+            }
+        }
+        None
+    }
+    
+    /// Takes every stop triggered by a trade at `last_price`, in release order
+    ///
+    /// Stops already expired at `now` are taken too, marked expired, and
+    /// returned separately instead of being released.
+    fn take_triggered(&mut self, last_price: Price, now: DateTime<Utc>) -> (Vec<Order>, Vec<Order>) {
+        // Buys trigger at or below the last price, sells at or above it
+        let mut triggered_buys = self.buys.split_off(&last_price);
+        std::mem::swap(&mut triggered_buys, &mut self.buys);
+        if let Some(at_price) = self.buys.remove(&last_price) {
+            triggered_buys.insert(last_price, at_price);
+        }
+        let triggered_sells = self.sells.split_off(&last_price);
+        
+        let (mut expired, triggered): (Vec<Order>, Vec<Order>) = triggered_buys.into_values().flatten()
+            .chain(triggered_sells.into_values().rev().flatten())
+            .partition(|order| order.is_expired_at(now));
+        for order in triggered.iter().chain(&expired) {
+            if let Some(expires_at) = order.expires_at {
+                untrack(&mut self.expiries, order.id, expires_at);
+            }
+            self.totals.remove(order);
+        }
+        for order in &mut expired {
+            order.expire_at(now);
+        }
+        (triggered, expired)
+    }
+    
+    /// Removes every stop whose expiry is at or before `now`, marking it expired
+    ///
+    /// Returns the stops earliest expiry first.
+    pub(super) fn take_expired(&mut self, now: DateTime<Utc>) -> Vec<Order> {
+        let due: Vec<OrderId> = self.expiries
+            .range(..=now)
+            .flat_map(|(_, order_ids)| order_ids.iter().copied())
+            .collect();
+        due.into_iter()
+            .filter_map(|order_id| self.remove(order_id))
+            .map(|mut order| {
+                order.expire_at(now);
+                order
+            })
+            .collect()
+    }
+    
+    /// Rebuilds the expiry index and pending totals from the stops, which snapshots leave out
+    pub(super) fn rebuild_indices(&mut self) {
+        self.totals = PendingTotals::of(self.iter());
+        self.expiries.clear();
+        for order in self.buys.values().chain(self.sells.values()).flatten() {
+            if let Some(expires_at) = order.expires_at {
+                self.expiries.entry(expires_at).or_default().push(order.id);
+            }
+        }
+    }
+    
+    /// Removes every Day stop, marking it expired
+    pub(super) fn expire_day_orders(&mut self, now: DateTime<Utc>) -> Vec<Order> {
+        let mut expired = Vec::new();
+        for levels in [&mut self.buys, &mut self.sells] {
+            for orders in levels.values_mut() {
+                let (day, kept) = std::mem::take(orders)
+                    .into_iter()
+                    .partition(|order| order.time_in_force == TimeInForce::Day);
+                *orders = kept;
+                expired.extend(day);
+            }
+            levels.retain(|_, orders| !orders.is_empty());
+        }
+        for order in &mut expired {
+            if let Some(expires_at) = order.expires_at {
+                untrack(&mut self.expiries, order.id, expires_at);
+            }
+            self.totals.remove(order);
+            order.expire_at(now);
+        }
+        expired
+    }
+    
+    fn side_mut(&mut self, side: OrderSide) -> &mut BTreeMap<Price, Vec<Order>> {
+        match side {
+            OrderSide::Buy => &mut self.buys,
+            OrderSide::Sell => &mut self.sells,
+        }
+    }
+}
+
+/// Drops a stop from an expiry index
+fn untrack(expiries: &mut BTreeMap<DateTime<Utc>, Vec<OrderId>>, order_id: OrderId, expires_at: DateTime<Utc>) {
+    if let Some(order_ids) = expiries.get_mut(&expires_at) {
+        order_ids.retain(|id| *id != order_id);
+        if order_ids.is_empty() {
+            expiries.remove(&expires_at);
+        }
+    }
+}
+
+impl LimitOrderBook {
+    /// Gets the number of stop orders waiting for their trigger
+    pub fn pending_stop_count(&self) -> usize {
+        self.stops.len()
+    }
+    
+    /// Gets a pending stop order by ID
+    pub fn get_pending_stop(&self, order_id: OrderId) -> Option<&Order> {
+        self.stops.iter().find(|order| order.id == order_id)
+    }
+    
+    /// Gets the price of the most recent trade
+    pub fn last_trade_price(&self) -> Option<Price> {
+        self.last_trade_price
+    }
+    
+    /// Converts a stop to a market order if the last trade already triggers it
+    ///
+    /// Returns `false` when the stop must wait.
+    pub(super) fn release_stop(&mut self, order: &mut Order, events: &mut Vec<OrderEvent>) -> bool {
+        let (Some(trigger), Some(last_price)) = (order.stop_trigger(), self.last_trade_price) else {
+            return false;
+        };
+        if self.stop_releases(order) {
+            order.order_type = OrderType::Market;
+            events.push(OrderEvent::StopTriggered { order_id: order.id, trigger, last_price });
+            return true;
+        }
+        false
+    }
+    
+    /// Checks if a stop order would be released on arrival
+    pub(super) fn stop_releases(&self, order: &Order) -> bool {
+        let (Some(trigger), Some(last_price)) = (order.stop_trigger(), self.last_trade_price) else {
+            return false;
+        };
+        let triggered = match order.side {
+            OrderSide::Buy => last_price >= trigger,
+            OrderSide::Sell => last_price <= trigger,
+        };
+        triggered && self.state == BookState::Open
+    }
+    
+    /// Releases and matches triggered stops until no more trigger
+    ///
+    /// Trades of released stops are appended to `trades`.
+    pub(super) fn trigger_stops(&mut self, trades: &mut Vec<Trade>, events: &mut Vec<OrderEvent>) -> crate::Result<()> {
+        let now = self.now();
+        loop {
+            let Some(last_price) = self.last_trade_price else {
+                return Ok(());
+            };
+            if self.state != BookState::Open {
+                return Ok(());
+            }
+            let (wave, expired) = self.stops.take_triggered(last_price, now);
+            events.extend(expired.into_iter().map(|order| OrderEvent::Expired { order }));
+            if wave.is_empty() {
+                return Ok(());
+            }
+            
+            for mut order in wave {
+                let trigger = order.stop_trigger().expect("only stop orders are held pending");
+                order.order_type = OrderType::Market;
+                events.push(OrderEvent::StopTriggered { order_id: order.id, trigger, last_price });
+                if !self.recheck_released_stop(&mut order, events)? {
+                    continue;
+                }
+                trades.extend(self.match_order(&mut order, events)?);
+                if order.is_active() {
+                    order.cancel_at(now);
+                    events.push(OrderEvent::Cancelled { order, reason: CancelReason::Unfilled });
+                }
+            }
+        }
+    }
+    
+    /// Runs a released stop through the position limit and reduce-only checks again
+    ///
+    /// Trims the stop like an incoming order, or cancels it when nothing of
+    /// it fits. Returns whether it is still live.
+    fn recheck_released_stop(&mut self, order: &mut Order, events: &mut Vec<OrderEvent>) -> crate::Result<bool> {
+        let now = self.now();
+        let checked = self.check_reduce_only(order)
+            .map_err(|_| CancelReason::ReduceOnly)
+            .and_then(|_| self.check_position_limit(order, true).map_err(|_| CancelReason::PositionLimit));
+        match checked {
+            Ok(excess) => {
+                self.trim_to_position_limit(order, excess, events)?;
+                self.trim_to_reduce_only(order, events)?;
+                Ok(true)
+            },
+            Err(reason) => {
+                order.cancel_at(now);
+                events.push(OrderEvent::Cancelled { order: order.clone(), reason });
+                Ok(false)
+            },
+        }
+    }
+    
+    /// Cancels a pending stop order
+    pub(super) fn cancel_stop(&mut self, order_id: OrderId) -> crate::Result<Order> {
+        let now = self.now();
+        let mut order = self.stops.remove(order_id)
+            .ok_or_else(|| MatchingEngineError::OrderNotFound(order_id.to_string()))?;
+        self.begin_mutation();
+        order.cancel_at(now);
+        self.prune_brackets();
+        self.end_mutation();
+        Ok(order)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{order_book::Clock, types::UserId, OrderStatus, PositionLimit, Quantity};
+    use chrono::Duration;
+    use crate::order_book::test_support::create_test_order;
+
+    fn stop(side: OrderSide, trigger_cents: i64, quantity: u64) -> Order {
+        Order::stop(
+            OrderId::new(),
+            UserId::new("stopper".to_string()),
+            side,
+            Price::from_cents(trigger_cents).unwrap(),
+            Quantity::new(quantity).unwrap(),
+        )
+    }
+
+    /// Bids at 99.00-97.00 and asks at 101.00-103.00, 100 each, last trade at 100.00
+    fn book() -> LimitOrderBook {
+        let mut book = LimitOrderBook::new("STOP".to_string()).unwrap();
+        for i in 0..3 {
+            book.add_order(create_test_order(OrderSide::Buy, 9900 - 100 * i, 100)).unwrap();
+            book.add_order(create_test_order(OrderSide::Sell, 10100 + 100 * i, 100)).unwrap();
+        }
+        book.add_order(create_test_order(OrderSide::Sell, 10000, 10)).unwrap();
+        book.add_order(create_test_order(OrderSide::Buy, 10000, 10)).unwrap();
+        book
+    }
+
+    #[test]
+    fn test_buy_stop_triggers_on_uptick() {
+        let mut book = book();
+        let buy_stop = stop(OrderSide::Buy, 10100, 50);
+        let stop_id = buy_stop.id;
+        let result = book.submit_order(buy_stop).unwrap();
+        assert_eq!(result.status, OrderStatus::Active);
+        assert_eq!(book.pending_stop_count(), 1);
+        let (depth, untouched) = (book.market_depth(10), self::book().market_depth(10));
+        assert_eq!((depth.bids, depth.asks), (untouched.bids, untouched.asks));
+        
+        // A print at 101.00 triggers the stop, which lifts the rest of that offer
+        let trades = book.add_order(create_test_order(OrderSide::Buy, 10100, 30)).unwrap();
+        assert_eq!(trades.len(), 2);
+        assert_eq!(trades[1].buy_order_id, stop_id);
+        assert_eq!((trades[1].price.as_cents(), trades[1].quantity.value()), (10100, 50));
+        assert_eq!(book.pending_stop_count(), 0);
+        assert_eq!(book.best_ask_quantity().unwrap().value(), 20);
+    }
+
+    #[test]
+    fn test_sell_stop_triggers_on_downtick_and_can_be_cancelled() {
+        let mut book = book();
+        let sell_stop = stop(OrderSide::Sell, 9900, 150);
+        let cancelled = stop(OrderSide::Sell, 9800, 100);
+        let cancelled_id = cancelled.id;
+        book.add_order(sell_stop).unwrap();
+        book.add_order(cancelled).unwrap();
+        
+        let order = book.cancel_order(cancelled_id).unwrap();
+        assert_eq!(order.status, OrderStatus::Cancelled);
+        assert!(book.get_pending_stop(cancelled_id).is_none());
+        
+        // An uptick leaves the sell stop alone; a print at 99.00 releases it
+        book.add_order(create_test_order(OrderSide::Buy, 10100, 10)).unwrap();
+        assert_eq!(book.pending_stop_count(), 1);
+        let result = book.submit_order(create_test_order(OrderSide::Sell, 9900, 10)).unwrap();
+        assert!(result.events.iter().any(|event| matches!(event, OrderEvent::StopTriggered { .. })));
+        let swept: Vec<(i64, u64)> = result.trades[1..].iter()
+            .map(|trade| (trade.price.as_cents(), trade.quantity.value()))
+            .collect();
+        assert_eq!(swept, vec![(9900, 90), (9800, 60)]);
+        assert_eq!(book.last_trade_price().unwrap().as_cents(), 9800);
+    }
+
+    #[test]
+    fn test_cascading_triggers() {
+        let mut book = book();
+        
+        // Each stop's sweep prints through the next stop's trigger
+        let first = stop(OrderSide::Sell, 9900, 100);
+        let second = stop(OrderSide::Sell, 9800, 150);
+        let third = stop(OrderSide::Sell, 9700, 500);
+        let ids = [first.id, second.id, third.id];
+        book.add_order(third).unwrap();
+        book.add_order(second).unwrap();
+        book.add_order(first).unwrap();
+        book.add_order(stop(OrderSide::Sell, 9000, 10)).unwrap();
+        
+        let result = book.submit_order(create_test_order(OrderSide::Sell, 9900, 10)).unwrap();
+        let triggered: Vec<OrderId> = result.events.iter()
+            .filter_map(|event| match event {
+                OrderEvent::StopTriggered { order_id, .. } => Some(*order_id),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(triggered, ids);
+        
+        // The third stop exhausts the bids and its remainder is cancelled
+        assert!(book.best_bid().is_none());
+        assert!(result.events.iter().any(|event| matches!(
+            event,
+            OrderEvent::Cancelled { order, reason: CancelReason::Unfilled } if order.id == ids[2]
+        )));
+        assert_eq!(book.pending_stop_count(), 1);
+    }
+
+    #[test]
+    fn test_expired_stop_is_not_released_and_is_purged() {
+        let start = Utc::now();
+        let mut book = book();
+        book.set_clock(Clock::Fixed(start));
+        let short = stop(OrderSide::Buy, 10100, 50).with_expiry(start + Duration::minutes(5));
+        let long = stop(OrderSide::Buy, 10200, 50).with_expiry(start + Duration::hours(1));
+        let (short_id, long_id) = (short.id, long.id);
+        book.add_order(short).unwrap();
+        book.add_order(long).unwrap();
+        
+        // Past its expiry the stop expires at its trigger instead of buying
+        book.set_clock(Clock::Fixed(start + Duration::minutes(10)));
+        let result = book.submit_order(create_test_order(OrderSide::Buy, 10100, 30)).unwrap();
+        assert_eq!(result.trades.len(), 1);
+        assert!(matches!(&result.events[..], [OrderEvent::Expired { order }] if order.id == short_id));
+        assert_eq!(book.pending_stop_count(), 1);
+        
+        // The sweep removes expired stops that were never triggered
+        book.set_clock(Clock::Fixed(start + Duration::hours(2)));
+        let expired = book.purge_expired(start + Duration::hours(2));
+        assert_eq!((expired[0].id, expired[0].status), (long_id, OrderStatus::Expired));
+        assert_eq!(book.pending_stop_count(), 0);
+    }
+
+    #[test]
+    fn test_pending_stops_count_toward_position_limit() {
+        let mut book = book();
+        let stopper = UserId::new("stopper".to_string());
+        book.set_position_limit(stopper.clone(), Some(PositionLimit::new(100, 100)));
+        book.add_order(stop(OrderSide::Buy, 10100, 100)).unwrap();
+        for _ in 0..4 {
+            assert!(matches!(
+                book.add_order(stop(OrderSide::Buy, 10100, 100)),
+                Err(MatchingEngineError::PositionLimitExceeded { worst_case: 200, .. })
+            ));
+        }
+        
+        book.add_order(create_test_order(OrderSide::Buy, 10100, 10)).unwrap();
+        assert_eq!(book.net_position(&stopper), 100);
+    }
+
+    #[test]
+    fn test_released_stop_is_rechecked() {
+        let mut book = book();
+        let stopper = UserId::new("stopper".to_string());
+        book.set_position_limit(stopper.clone(), Some(PositionLimit::new(100, 100)));
+        let buy_stop = stop(OrderSide::Buy, 10100, 100);
+        book.add_order(buy_stop).unwrap();
+        
+        // The limit tightens while the stop waits, so it is cancelled on release
+        book.set_position_limit(stopper.clone(), Some(PositionLimit::new(50, 100)));
+        let result = book.submit_order(create_test_order(OrderSide::Buy, 10100, 10)).unwrap();
+        assert_eq!(result.trades.len(), 1);
+        assert!(result.events.iter().any(|event| matches!(
+            event,
+            OrderEvent::Cancelled { reason: CancelReason::PositionLimit, .. }
+        )));
+        assert_eq!(book.net_position(&stopper), 0);
+        
+        // A reduce-only stop whose position is gone by release is cancelled too
+        book.set_net_position(stopper.clone(), 100);
+        book.add_order(stop(OrderSide::Sell, 9900, 100).with_reduce_only()).unwrap();
+        book.set_net_position(stopper.clone(), 0);
+        let result = book.submit_order(create_test_order(OrderSide::Sell, 9900, 10)).unwrap();
+        assert!(result.events.iter().any(|event| matches!(
+            event,
+            OrderEvent::Cancelled { reason: CancelReason::ReduceOnly, .. }
+        )));
+        assert_eq!(book.net_position(&stopper), 0);
+    }
+}
@@ -0,0 +1,288 @@
+//! Self-trade prevention (STP)
+//!
+//! When an incoming order would trade against a resting order in the same
+//! scope (same user, desk, or firm), the configured policy is applied instead
+//! of producing a trade.
+
+use super::LimitOrderBook;
+use crate::{
+    account::AccountRegistry,
+    events::{CancelReason, OrderEvent},
+    types::UserId,
+    Order,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Action taken when two orders in the same scope would trade
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum StpPolicy {
+    /// Cancel the resting order and keep matching the incoming order
+    CancelResting,
+    /// Cancel the remainder of the incoming order
+    CancelIncoming,
+    /// Cancel both orders
+    CancelBoth,
+    /// Reduce both orders by the smaller quantity, cancelling whichever reaches zero
+    DecrementAndCancel,
+}
+
+/// Account grouping within which orders may not trade with each other
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum StpScope {
+    /// Orders from the same user
+    User,
+    /// Orders from users on the same desk of the same firm
+    Desk,
+    /// Orders from users of the same firm
+    Firm,
+}
+
+impl StpScope {
+    /// Checks if two users fall within this scope
+    ///
+    /// A user always shares every scope with themselves; users without a
+    /// registry entry share no scope with anyone else.
+    pub fn applies(&self, accounts: &AccountRegistry, a: &UserId, b: &UserId) -> bool {
+        if a == b {
+            return true;
+        }
+        match self {
+            StpScope::User => false,
+            StpScope::Desk => accounts.same_desk(a, b),
+            StpScope::Firm => accounts.same_firm(a, b),
+        }
+    }
+}
+
+/// Self-trade prevention settings for a book
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SelfTradePrevention {
+    pub policy: StpPolicy,
+    pub scope: StpScope,
+}
+
+impl SelfTradePrevention {
+    /// Creates STP settings with the given policy and scope
+    pub fn new(policy: StpPolicy, scope: StpScope) -> Self {
+        Self { policy, scope }
+    }
+}
+
+impl LimitOrderBook {
+    /// Gets the account hierarchy used for STP and surveillance markers
+    pub fn accounts(&self) -> &AccountRegistry {
+        &self.accounts
+    }
+
+    /// Gets mutable access to the account hierarchy for runtime updates
+    pub fn accounts_mut(&mut self) -> &mut AccountRegistry {
+        &mut self.accounts
+    }
+
+    /// Replaces the account hierarchy
+    pub fn set_accounts(&mut self, accounts: AccountRegistry) {
+        self.accounts = accounts;
+    }
+
+    /// Gets the self-trade prevention settings
+    pub fn self_trade_prevention(&self) -> Option<SelfTradePrevention> {
+        self.self_trade_prevention
+    }
+
+    /// Enables (`Some`) or disables (`None`) self-trade prevention
+    pub fn set_self_trade_prevention(&mut self, stp: Option<SelfTradePrevention>) {
+        self.self_trade_prevention = stp;
+    }
+}
+
+/// Applies an STP policy to a conflicting pair of orders
+///
+/// Cancelled or decremented orders are reported through `events`. The caller
+/// is responsible for removing the resting order from the book once it is no
+/// longer active.
+pub(super) fn prevent_self_trade(
+    policy: StpPolicy,
+    incoming: &mut Order,
+    resting: &mut Order,
+    events: &mut Vec<OrderEvent>,
+    now: DateTime<Utc>,
+) -> crate::Result<()> {
+    let reason = CancelReason::SelfTradePrevention;
+    match policy {
+        StpPolicy::CancelResting => {
+            resting.cancel_at(now);
+            events.push(OrderEvent::Cancelled { order: resting.clone(), reason });
+        },
+        StpPolicy::CancelIncoming => {
+            incoming.cancel_at(now);
+            events.push(OrderEvent::Cancelled { order: incoming.clone(), reason });
+        },
+        StpPolicy::CancelBoth => {
+            resting.cancel_at(now);
+            incoming.cancel_at(now);
+            events.push(OrderEvent::Cancelled { order: resting.clone(), reason });
+            events.push(OrderEvent::Cancelled { order: incoming.clone(), reason });
+        },
+        StpPolicy::DecrementAndCancel => {
+            let quantity = incoming.remaining_quantity.min(resting.remaining_quantity);
+            // Every decrement is reported, so events account for all quantity removed
+            for order in [&mut *resting, &mut *incoming] {
+                order.reduce_at(quantity, now)?;
+                events.push(OrderEvent::Decremented { order_id: order.id, quantity, reason });
+                if !order.is_active() {
+                    events.push(OrderEvent::Cancelled { order: order.clone(), reason });
+                }
+            }
+        },
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        types::{DeskId, FirmId},
+        OrderSide, OrderStatus,
+    };
+    use crate::order_book::test_support::create_order;
+
+    fn user(name: &str) -> UserId {
+        UserId::new(name.to_string())
+    }
+
+    /// alice and bob share the ACME rates desk, carol is on the ACME fx desk
+    fn firm_book(policy: StpPolicy, scope: StpScope) -> LimitOrderBook {
+        let mut book = LimitOrderBook::new("STP".to_string()).unwrap();
+        let acme = FirmId::new("ACME".to_string());
+        let accounts = book.accounts_mut();
+        accounts.register(user("alice"), acme.clone(), Some(DeskId::new("rates".to_string())));
+        accounts.register(user("bob"), acme.clone(), Some(DeskId::new("rates".to_string())));
+        accounts.register(user("carol"), acme, Some(DeskId::new("fx".to_string())));
+        book.set_self_trade_prevention(Some(SelfTradePrevention::new(policy, scope)));
+        book
+    }
+
+    #[test]
+    fn test_user_scope_cancel_resting() {
+        let mut book = firm_book(StpPolicy::CancelResting, StpScope::User);
+        let own = create_order(OrderSide::Sell, 10000, 100, "alice");
+        let own_id = own.id;
+        book.add_order(own).unwrap();
+        book.add_order(create_order(OrderSide::Sell, 10000, 100, "dave")).unwrap();
+
+        let result = book.submit_order(create_order(OrderSide::Buy, 10000, 100, "alice")).unwrap();
+
+        assert_eq!(result.trades.len(), 1);
+        assert!(result.trades[0].sell_order_id != own_id);
+        assert!(matches!(
+            &result.events[..],
+            [OrderEvent::Cancelled { order, reason: CancelReason::SelfTradePrevention }] if order.id == own_id
+        ));
+        assert!(book.get_order(own_id).is_none());
+        assert!(book.is_empty());
+    }
+
+    #[test]
+    fn test_user_scope_allows_same_firm_and_marks_trade() {
+        let mut book = firm_book(StpPolicy::CancelIncoming, StpScope::User);
+        book.add_order(create_order(OrderSide::Sell, 10000, 100, "bob")).unwrap();
+
+        let trades = book.add_order(create_order(OrderSide::Buy, 10000, 100, "alice")).unwrap();
+        assert_eq!(trades.len(), 1);
+        assert!(trades[0].same_firm);
+    }
+
+    #[test]
+    fn test_desk_scope() {
+        let mut book = firm_book(StpPolicy::CancelIncoming, StpScope::Desk);
+        book.add_order(create_order(OrderSide::Sell, 10000, 100, "bob")).unwrap();
+
+        // Same desk: incoming is cancelled, resting order survives
+        let result = book.submit_order(create_order(OrderSide::Buy, 10000, 100, "alice")).unwrap();
+        assert!(result.trades.is_empty());
+        assert_eq!(result.status, OrderStatus::Cancelled);
+        assert_eq!(book.best_ask_quantity().unwrap().value(), 100);
+        assert!(book.best_bid().is_none());
+
+        // Different desk of the same firm may trade
+        let trades = book.add_order(create_order(OrderSide::Buy, 10000, 100, "carol")).unwrap();
+        assert_eq!(trades.len(), 1);
+        assert!(trades[0].same_firm);
+    }
+
+    #[test]
+    fn test_firm_scope_cancel_both() {
+        let mut book = firm_book(StpPolicy::CancelBoth, StpScope::Firm);
+        let resting = create_order(OrderSide::Buy, 10000, 100, "carol");
+        let resting_id = resting.id;
+        book.add_order(resting).unwrap();
+
+        let result = book.submit_order(create_order(OrderSide::Sell, 9900, 50, "alice")).unwrap();
+        assert!(result.trades.is_empty());
+        assert_eq!(result.events.len(), 2);
+        assert_eq!(result.status, OrderStatus::Cancelled);
+        assert!(book.get_order(resting_id).is_none());
+        assert!(book.is_empty());
+    }
+
+    #[test]
+    fn test_firm_scope_decrement_and_cancel() {
+        let mut book = firm_book(StpPolicy::DecrementAndCancel, StpScope::Firm);
+        let resting = create_order(OrderSide::Sell, 10000, 100, "bob");
+        let resting_id = resting.id;
+        book.add_order(resting).unwrap();
+        book.add_order(create_order(OrderSide::Sell, 10000, 100, "dave")).unwrap();
+
+        let result = book.submit_order(create_order(OrderSide::Buy, 10000, 150, "carol")).unwrap();
+
+        // bob's order is decremented away, the remaining 50 trades with dave
+        assert!(matches!(
+            &result.events[..],
+            [
+                OrderEvent::Decremented { order_id, quantity: bob_quantity, .. },
+                OrderEvent::Cancelled { order, .. },
+                OrderEvent::Decremented { quantity, .. },
+            ] if *order_id == resting_id && order.id == resting_id
+                && bob_quantity.value() == 100 && quantity.value() == 100
+        ));
+        assert_eq!(result.trades.len(), 1);
+        assert_eq!(result.trades[0].quantity.value(), 50);
+        assert!(!result.trades[0].same_firm);
+        assert_eq!(result.status, OrderStatus::Filled);
+        assert_eq!(book.best_ask_quantity().unwrap().value(), 50);
+    }
+
+    #[test]
+    fn test_unmapped_user_only_matches_user_scope() {
+        let mut book = firm_book(StpPolicy::CancelIncoming, StpScope::Firm);
+        book.add_order(create_order(OrderSide::Sell, 10000, 100, "erin")).unwrap();
+
+        // Unmapped users share no firm with anyone else
+        let trades = book.add_order(create_order(OrderSide::Buy, 10000, 50, "alice")).unwrap();
+        assert_eq!(trades.len(), 1);
+        assert!(!trades[0].same_firm);
+
+        // ...but are still prevented from trading with themselves
+        let result = book.submit_order(create_order(OrderSide::Buy, 10000, 50, "erin")).unwrap();
+        assert!(result.trades.is_empty());
+        assert_eq!(result.status, OrderStatus::Cancelled);
+    }
+
+    #[test]
+    fn test_registry_updates_apply_at_runtime_and_serialize() {
+        let mut book = firm_book(StpPolicy::CancelIncoming, StpScope::Firm);
+        book.add_order(create_order(OrderSide::Sell, 10000, 100, "frank")).unwrap();
+        book.accounts_mut().register(user("frank"), FirmId::new("ACME".to_string()), None);
+
+        let json = serde_json::to_string(&book).unwrap();
+        let mut restored: LimitOrderBook = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored.accounts(), book.accounts());
+        assert_eq!(restored.self_trade_prevention(), book.self_trade_prevention());
+
+        let result = restored.submit_order(create_order(OrderSide::Buy, 10000, 100, "alice")).unwrap();
+        assert!(result.trades.is_empty());
+        assert_eq!(result.status, OrderStatus::Cancelled);
+    }
+}
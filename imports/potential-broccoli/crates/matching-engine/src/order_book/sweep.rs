@@ -0,0 +1,180 @@
+//! Liquidity queries: sweep costs and crossing quantity
+//!
+//! What it would cost to take a quantity from one side of the book at once,
+//! and how much a limit price could reach, counting displayed liquidity
+//! only: hidden, inactive, and expired orders are skipped, just as they are
+//! in depth.
+
+use super::{levels::LevelOrders, quantity_scale, LimitOrderBook};
+use crate::{OrderSide, Price, Quantity};
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+/// Cost of sweeping displayed liquidity for a quantity
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SweepCost {
+    /// Quantity the displayed liquidity covers, at most the requested quantity
+    pub quantity: Quantity,
+    /// Requested quantity the book is too thin to cover
+    pub shortfall: Quantity,
+    /// Sum of price times quantity across the levels swept
+    pub total_notional: Decimal,
+    /// Volume-weighted average price of the sweep
+    pub average_price: Decimal,
+    /// Price of the last level swept
+    pub worst_price: Price,
+    /// Number of price levels the sweep takes liquidity from
+    pub levels_touched: usize,
+}
+
+impl SweepCost {
+    /// Checks if the displayed liquidity covers the whole requested quantity
+    pub fn is_complete(&self) -> bool {
+        self.shortfall.value() == 0
+    }
+}
+
+impl LimitOrderBook {
+    /// Estimates the cost of buying `quantity` from the displayed asks
+    ///
+    /// Returns `None` if no asks are displayed; a book too thin for the whole
+    /// quantity yields a partial estimate with a non-zero shortfall.
+    pub fn cost_to_buy(&self, quantity: Quantity) -> Option<SweepCost> {
+        self.sweep_cost(OrderSide::Sell, quantity)
+    }
+
+    /// Estimates the proceeds of selling `quantity` into the displayed bids
+    ///
+    /// Returns `None` if no bids are displayed; a book too thin for the whole
+    /// quantity yields a partial estimate with a non-zero shortfall.
+    pub fn cost_to_sell(&self, quantity: Quantity) -> Option<SweepCost> {
+        self.sweep_cost(OrderSide::Buy, quantity)
+    }
+
+    /// Gets the displayed quantity a limit order on `side` at `limit` would cross
+    ///
+    /// That is the asks priced at or below `limit` for a buy, and the bids
+    /// priced at or above it for a sell.
+    pub fn quantity_available(&self, side: OrderSide, limit: Price) -> Quantity {
+        let now = self.now();
+        let total: u64 = match side {
+            OrderSide::Buy => self.asks.range(..=limit).map(|(_, orders)| displayed_quantity(orders, now)).sum(),
+            OrderSide::Sell => self.bids.range(limit..).map(|(_, orders)| displayed_quantity(orders, now)).sum(),
+        };
+        Quantity::new_allow_zero(total)
+    }
+
+    /// Walks the levels of `side` from the touch until `quantity` is covered
+    fn sweep_cost(&self, side: OrderSide, quantity: Quantity) -> Option<SweepCost> {
+        let now = self.now();
+        let levels = match side {
+            OrderSide::Buy => &self.bids,
+            OrderSide::Sell => &self.asks,
+        };
+        let mut remaining = quantity.value();
+        let mut total_notional = Decimal::ZERO;
+        let mut worst_price = None;
+        let mut levels_touched = 0;
+
+        for (price, orders) in levels.iter() {
+            if remaining == 0 {
+                break;
+            }
+            let displayed = displayed_quantity(orders, now);
+            if displayed == 0 {
+                continue;
+            }
+            let taken = displayed.min(remaining);
+            remaining -= taken;
+            total_notional += quantity_scale::notional(*price, taken, self.quantity_scale);
+            worst_price = Some(*price);
+            levels_touched += 1;
+        }
+
+        let worst_price = worst_price?;
+        let filled = quantity.value() - remaining;
+        Some(SweepCost {
+            quantity: Quantity::new_allow_zero(filled),
+            shortfall: Quantity::new_allow_zero(remaining),
+            total_notional,
+            average_price: total_notional / Quantity::new_allow_zero(filled).to_decimal(self.quantity_scale),
+            worst_price,
+            levels_touched,
+        })
+    }
+}
+
+/// Active, unexpired, non-hidden quantity at a level
+pub(super) fn displayed_quantity(orders: LevelOrders<'_>, now: DateTime<Utc>) -> u64 {
+    orders.iter()
+        .filter(|order| order.is_active() && !order.hidden && !order.is_expired_at(now))
+        .map(|order| order.remaining_quantity.value())
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    use crate::order_book::test_support::create_test_order;
+
+    fn book_with_asks() -> LimitOrderBook {
+        let mut book = LimitOrderBook::new("SWEEP".to_string()).unwrap();
+        book.add_order(create_test_order(OrderSide::Sell, 10000, 30)).unwrap();
+        book.add_order(create_test_order(OrderSide::Sell, 10000, 20)).unwrap();
+        book.add_order(create_test_order(OrderSide::Sell, 10100, 50)).unwrap();
+        book.add_order(create_test_order(OrderSide::Sell, 10200, 100).with_hidden()).unwrap();
+        book.add_order(create_test_order(OrderSide::Sell, 10300, 100)).unwrap();
+        book
+    }
+
+    #[test]
+    fn test_quantity_exhausting_a_level_stops_there() {
+        let book = book_with_asks();
+
+        let cost = book.cost_to_buy(Quantity::new(50).unwrap()).unwrap();
+        assert!(cost.is_complete());
+        assert_eq!((cost.worst_price.as_cents(), cost.levels_touched), (10000, 1));
+        assert_eq!(cost.total_notional, Decimal::from(5000));
+
+        // The hidden level is skipped
+        let cost = book.cost_to_buy(Quantity::new(150).unwrap()).unwrap();
+        assert_eq!((cost.worst_price.as_cents(), cost.levels_touched), (10300, 3));
+        assert_eq!(cost.total_notional, Decimal::from(5000 + 5050 + 5150));
+        assert_eq!(cost.average_price, Decimal::from(15200) / Decimal::from(150));
+        assert!(book.cost_to_sell(Quantity::new(1).unwrap()).is_none());
+    }
+
+    #[test]
+    fn test_quantity_beyond_depth_reports_shortfall() {
+        let book = book_with_asks();
+
+        let cost = book.cost_to_buy(Quantity::new(500).unwrap()).unwrap();
+        assert!(!cost.is_complete());
+        assert_eq!((cost.quantity.value(), cost.shortfall.value()), (200, 300));
+        assert_eq!((cost.worst_price.as_cents(), cost.levels_touched), (10300, 3));
+        assert_eq!(cost.average_price, Decimal::new(10175, 2));
+    }
+
+    #[test]
+    fn test_quantity_available_at_and_between_levels() {
+        let mut book = book_with_asks();
+        book.add_order(create_test_order(OrderSide::Buy, 9900, 40)).unwrap();
+        book.add_order(create_test_order(OrderSide::Buy, 9800, 60)).unwrap();
+        let available = |side, cents| book.quantity_available(side, Price::from_cents(cents).unwrap()).value();
+
+        // Exact level boundaries are inclusive
+        assert_eq!(available(OrderSide::Buy, 10000), 50);
+        assert_eq!(available(OrderSide::Buy, 10100), 100);
+        assert_eq!(available(OrderSide::Sell, 9900), 40);
+        assert_eq!(available(OrderSide::Sell, 9800), 100);
+
+        // Strictly between levels, and the hidden 102.00 level not counted
+        assert_eq!(available(OrderSide::Buy, 10050), 50);
+        assert_eq!(available(OrderSide::Buy, 10250), 100);
+        assert_eq!(available(OrderSide::Sell, 9850), 40);
+        assert_eq!(available(OrderSide::Buy, 9950), 0);
+        assert_eq!(available(OrderSide::Sell, 9950), 0);
+    }
+}
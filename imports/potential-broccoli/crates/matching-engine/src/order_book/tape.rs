@@ -0,0 +1,173 @@
+//! Trade tape queries
+//!
+//! Queries over `recent_trades`, the bounded history of the book's latest
+//! trades. Trades are appended in execution order, so their timestamps are
+//! non-decreasing and time ranges are found by binary search. Anything older
+//! than the retention limit, or handed to the archive when the session rolled,
+//! is no longer visible here.
+//!
+//! The history is a ring: once full, each new trade pushes out the oldest in
+//! O(1), rather than the retained trades shifting down to make room.
+
+use super::{LimitOrderBook, OrderBookOp, Trade};
+use crate::types::{OrderId, UserId};
+use chrono::{DateTime, Utc};
+
+impl LimitOrderBook {
+    /// Creates a new empty order book retaining up to `max_recent_trades` recent trades
+    pub fn with_max_recent_trades(symbol: String, max_recent_trades: usize) -> crate::Result<Self> {
+        let mut book = Self::new(symbol)?;
+        book.max_recent_trades = max_recent_trades;
+        Ok(book)
+    }
+
+    /// Gets the retained trades executed from `from` to `to`, both inclusive
+    ///
+    /// Pass `DateTime::<Utc>::MIN_UTC` or `MAX_UTC` for an open-ended range.
+    pub fn trades_between(&self, from: DateTime<Utc>, to: DateTime<Utc>) -> impl ExactSizeIterator<Item = &Trade> + DoubleEndedIterator {
+        let start = self.recent_trades.partition_point(|trade| trade.timestamp < from);
+        let end = self.recent_trades.partition_point(|trade| trade.timestamp <= to);
+        self.recent_trades.range(start..end.max(start))
+    }
+
+    /// Gets the retained trades an order took part in, oldest first
+    pub fn trades_for_order(&self, order_id: OrderId) -> impl Iterator<Item = &Trade> {
+        self.recent_trades.iter()
+            .filter(move |trade| trade.buy_order_id == order_id || trade.sell_order_id == order_id)
+    }
+
+    /// Gets the retained trades a user bought or sold in, oldest first
+    pub fn trades_for_user<'a>(&'a self, user_id: &'a UserId) -> impl Iterator<Item = &'a Trade> {
+        self.recent_trades.iter().filter(move |trade| {
+            trade.buyer_user_id.as_ref() == Some(user_id) || trade.seller_user_id.as_ref() == Some(user_id)
+        })
+    }
+
+    /// Gets the number of recent trades retained
+    pub fn max_recent_trades(&self) -> usize {
+        self.max_recent_trades
+    }
+
+    /// Sets the number of recent trades retained, dropping the oldest beyond it
+    pub fn set_max_recent_trades(&mut self, max_recent_trades: usize) {
+        self.apply(OrderBookOp::SetMaxRecentTrades(max_recent_trades));
+    }
+
+    /// Drops the oldest trades beyond the retention limit, remembering the newest dropped
+    pub(super) fn evict_old_trades(&mut self) {
+        if self.recent_trades.len() > self.max_recent_trades {
+            let mut evicted = self.recent_trades.drain(0..self.recent_trades.len() - self.max_recent_trades);
+            self.trades_evicted_through = evicted.next_back().map(|trade| trade.timestamp);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::OrderSide;
+    use chrono::Duration;
+    use crate::order_book::test_support::create_order;
+
+    /// Four trades of 10 against one resting ask of alice, one second apart from `base`
+    fn book_with_tape(base: DateTime<Utc>) -> (LimitOrderBook, OrderId) {
+        let mut book = LimitOrderBook::new("TAPE".to_string()).unwrap();
+        let ask = create_order(OrderSide::Sell, 10000, 40, "alice");
+        let ask_id = ask.id;
+        book.add_order(ask).unwrap();
+        for buyer in ["bob", "carol", "bob", "dave"] {
+            book.add_order(create_order(OrderSide::Buy, 10000, 10, buyer)).unwrap();
+        }
+        for (seconds, trade) in book.recent_trades.iter_mut().enumerate() {
+            trade.timestamp = base + Duration::seconds(seconds as i64);
+        }
+        (book, ask_id)
+    }
+
+    #[test]
+    fn test_time_ranges() {
+        let base = Utc::now();
+        let (book, _) = book_with_tape(base);
+        let ids = |from, to| book.trades_between(from, to).map(|trade| trade.trade_id).collect::<Vec<_>>();
+
+        assert_eq!(ids(base + Duration::seconds(1), base + Duration::seconds(2)), vec![2, 3]);
+        assert_eq!(ids(DateTime::<Utc>::MIN_UTC, base + Duration::seconds(1)), vec![1, 2]);
+        assert_eq!(ids(base + Duration::seconds(3), DateTime::<Utc>::MAX_UTC), vec![4]);
+        assert_eq!(book.trades_between(base + Duration::milliseconds(100), base + Duration::milliseconds(900)).len(), 0);
+        assert_eq!(book.trades_between(base + Duration::seconds(3), base).len(), 0);
+    }
+
+    #[test]
+    fn test_order_and_user_queries() {
+        let (book, ask_id) = book_with_tape(Utc::now());
+
+        assert_eq!(book.trades_for_order(ask_id).count(), 4);
+        let bob = UserId::new("bob".to_string());
+        let bought: Vec<u64> = book.trades_for_user(&bob).map(|trade| trade.trade_id).collect();
+        assert_eq!(bought, vec![1, 3]);
+        assert_eq!(book.trades_for_user(&UserId::new("alice".to_string())).count(), 4);
+    }
+
+    #[test]
+    fn test_retention_eviction_narrows_ranges() {
+        let base = Utc::now() - Duration::minutes(1);
+        let (mut book, ask_id) = book_with_tape(base);
+        assert_eq!(book.max_recent_trades(), 1000);
+
+        book.set_max_recent_trades(2);
+        let everything = book.trades_between(DateTime::<Utc>::MIN_UTC, DateTime::<Utc>::MAX_UTC);
+        assert_eq!(everything.map(|trade| trade.trade_id).collect::<Vec<_>>(), vec![3, 4]);
+        assert_eq!(book.trades_between(base, base + Duration::seconds(1)).len(), 0);
+        assert_eq!(book.trades_for_order(ask_id).count(), 2);
+
+        // New trades keep evicting at the configured limit
+        book.add_order(create_order(OrderSide::Sell, 10000, 5, "alice")).unwrap();
+        book.add_order(create_order(OrderSide::Buy, 10000, 5, "erin")).unwrap();
+        assert_eq!(book.recent_trades().len(), 2);
+        assert_eq!(book.trades_between(base + Duration::seconds(3), DateTime::<Utc>::MAX_UTC).len(), 2);
+    }
+
+    #[test]
+    fn test_history_wraps_in_chronological_order() {
+        let mut book = LimitOrderBook::with_max_recent_trades("TAPE".to_string(), 3).unwrap();
+        book.add_order(create_order(OrderSide::Sell, 10000, 100, "alice")).unwrap();
+        for _ in 0..5 {
+            book.add_order(create_order(OrderSide::Buy, 10000, 1, "bob")).unwrap();
+        }
+        let ids = |book: &LimitOrderBook| book.recent_trades().map(|trade| trade.trade_id).collect::<Vec<_>>();
+        assert_eq!(ids(&book), vec![3, 4, 5]);
+
+        // Serialized oldest first, whatever the ring's internal layout
+        let json = serde_json::to_value(&book).unwrap();
+        let serialized: Vec<u64> = json["recent_trades"].as_array().unwrap().iter()
+            .map(|trade| trade["trade_id"].as_u64().unwrap())
+            .collect();
+        assert_eq!(serialized, vec![3, 4, 5]);
+        let mut restored: LimitOrderBook = serde_json::from_value(json).unwrap();
+        assert_eq!(restored.max_recent_trades(), 3);
+        restored.add_order(create_order(OrderSide::Buy, 10000, 1, "bob")).unwrap();
+        assert_eq!(ids(&restored), vec![4, 5, 6]);
+    }
+
+    #[test]
+    fn test_sweep_larger_than_retention_keeps_newest() {
+        let mut book = LimitOrderBook::with_max_recent_trades("TAPE".to_string(), 3).unwrap();
+        book.add_order(create_order(OrderSide::Sell, 10000, 1, "alice")).unwrap();
+        book.add_order(create_order(OrderSide::Buy, 10000, 1, "bob")).unwrap();
+        for _ in 0..10 {
+            book.add_order(create_order(OrderSide::Sell, 10000, 1, "alice")).unwrap();
+        }
+
+        // Ten fills in one sweep: only the last three are kept, the first trade goes too
+        let trades = book.add_order(create_order(OrderSide::Buy, 10000, 10, "bob")).unwrap();
+        assert_eq!(trades.len(), 10);
+        let ids: Vec<u64> = book.recent_trades().map(|trade| trade.trade_id).collect();
+        assert_eq!(ids, vec![9, 10, 11]);
+        assert_eq!(book.trades_evicted_through, Some(trades[6].timestamp));
+
+        book.set_max_recent_trades(0);
+        book.add_order(create_order(OrderSide::Sell, 10000, 1, "alice")).unwrap();
+        book.add_order(create_order(OrderSide::Buy, 10000, 1, "bob")).unwrap();
+        assert_eq!(book.recent_trades().len(), 0);
+    }
+}
@@ -0,0 +1,33 @@
+//! Order factories shared by the crate's unit tests
+
+use crate::{
+    types::{OrderId, UserId},
+    Order, OrderSide, Price, Quantity,
+};
+
+/// A limit order for `test_user`
+pub(crate) fn create_test_order(side: OrderSide, price_cents: i64, quantity: u64) -> Order {
+    create_order(side, price_cents, quantity, "test_user")
+}
+
+/// A limit order for `user`
+pub(crate) fn create_order(side: OrderSide, price_cents: i64, quantity: u64, user: &str) -> Order {
+    Order::new(
+        OrderId::new(),
+        UserId::new(user.to_string()),
+        side,
+        Price::from_cents(price_cents).unwrap(),
+        Quantity::new(quantity).unwrap(),
+    )
+}
+
+/// A limit order for `test_user` at a decimal price, such as `"100.125"`
+pub(crate) fn create_order_at(side: OrderSide, price: &str, quantity: u64) -> Order {
+    Order::new(
+        OrderId::new(),
+        UserId::new("test_user".to_string()),
+        side,
+        Price::from_str(price).unwrap(),
+        Quantity::new(quantity).unwrap(),
+    )
+}
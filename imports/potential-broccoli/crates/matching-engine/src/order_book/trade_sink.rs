@@ -0,0 +1,335 @@
+//! Durable trade record
+//!
+//! `recent_trades` keeps only the latest trades; a [`TradeSink`] attached to
+//! the book receives every trade as it executes, continuous and auction
+//! alike, in execution order. [`JsonlTradeSink`] appends them to a JSON
+//! Lines file, one trade per line, and [`JsonlTradeReader`] streams them back.
+//!
+//! Recording can't fail a match: the first error a sink returns is kept by
+//! the book and reported by [`LimitOrderBook::flush_trade_sink`]. Like the
+//! listener, the sink is runtime wiring: it is not serialized, and a cloned
+//! book starts without one.
+
+use super::{LimitOrderBook, Trade};
+use crate::MatchingEngineError;
+use std::{
+    fs::{File, OpenOptions},
+    io::{BufRead, BufWriter, Write},
+    path::Path,
+};
+
+/// Receives every trade the book executes
+pub trait TradeSink: Send {
+    /// Records a trade
+    fn record(&mut self, trade: &Trade) -> crate::Result<()>;
+
+    /// Makes every recorded trade durable
+    fn flush(&mut self) -> crate::Result<()>;
+}
+
+/// When a [`JsonlTradeSink`] flushes its writer
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FlushPolicy {
+    /// After every trade
+    EveryTrade,
+    /// After every `n` trades; zero flushes after every trade
+    EveryN(usize),
+    /// Only when flushed explicitly
+    OnDemand,
+}
+
+/// Appends trades to a JSON Lines file
+#[derive(Debug)]
+pub struct JsonlTradeSink<W: Write + Send> {
+    writer: W,
+    policy: FlushPolicy,
+    unflushed: usize,
+}
+
+impl JsonlTradeSink<BufWriter<File>> {
+    /// Opens a file for appending, creating it if needed
+    pub fn create(path: impl AsRef<Path>, policy: FlushPolicy) -> crate::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path).map_err(write_error)?;
+        Ok(Self::new(BufWriter::new(file), policy))
+    }
+}
+
+impl<W: Write + Send> JsonlTradeSink<W> {
+    pub fn new(writer: W, policy: FlushPolicy) -> Self {
+        Self { writer, policy, unflushed: 0 }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+impl<W: Write + Send> TradeSink for JsonlTradeSink<W> {
+    fn record(&mut self, trade: &Trade) -> crate::Result<()> {
+        let mut line = serde_json::to_vec(trade).map_err(|e| MatchingEngineError::SerializationError(e.to_string()))?;
+        line.push(b'\n');
+        self.writer.write_all(&line).map_err(write_error)?;
+        self.unflushed += 1;
+        match self.policy {
+            FlushPolicy::EveryTrade => self.flush(),
+            FlushPolicy::EveryN(n) if self.unflushed >= n => self.flush(),
+            _ => Ok(()),
+        }
+    }
+
+    fn flush(&mut self) -> crate::Result<()> {
+        self.writer.flush().map_err(write_error)?;
+        self.unflushed = 0;
+        Ok(())
+    }
+}
+
+/// A line of a trade file that could not be read as a trade
+#[derive(Debug, Clone, PartialEq)]
+pub struct SkippedLine {
+    /// One-based line number
+    pub line: usize,
+    pub error: String,
+}
+
+/// Streams trades back from a JSON Lines file, skipping blank lines
+///
+/// A corrupt line, including one torn by a crash, is skipped and reported in
+/// [`skipped`](Self::skipped) rather than ending the stream, so the trades
+/// after it are still read. A read error does end the stream, and is
+/// reported the same way.
+#[derive(Debug)]
+pub struct JsonlTradeReader<R: BufRead> {
+    reader: R,
+    buffer: Vec<u8>,
+    line: usize,
+    skipped: Vec<SkippedLine>,
+}
+
+impl<R: BufRead> JsonlTradeReader<R> {
+    pub fn new(reader: R) -> Self {
+        Self { reader, buffer: Vec::new(), line: 0, skipped: Vec::new() }
+    }
+
+    /// Gets the lines skipped so far, in file order
+    pub fn skipped(&self) -> &[SkippedLine] {
+        &self.skipped
+    }
+}
+
+impl<R: BufRead> Iterator for JsonlTradeReader<R> {
+    type Item = Trade;
+
+    fn next(&mut self) -> Option<Trade> {
+        loop {
+            self.buffer.clear();
+            match self.reader.read_until(b'\n', &mut self.buffer) {
+                Ok(0) => return None,
+                Ok(_) => self.line += 1,
+                Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+                Err(e) => {
+                    self.skipped.push(SkippedLine { line: self.line + 1, error: e.to_string() });
+                    return None;
+                },
+            }
+            if self.buffer.trim_ascii().is_empty() {
+                continue;
+            }
+            match serde_json::from_slice(&self.buffer) {
+                Ok(trade) => return Some(trade),
+                Err(e) => self.skipped.push(SkippedLine { line: self.line, error: e.to_string() }),
+            }
+        }
+    }
+}
+
+/// The book's trade sink, if any, and the first error it returned
+#[derive(Default)]
+pub(super) struct TradeSinkSlot {
+    sink: Option<Box<dyn TradeSink>>,
+    error: Option<MatchingEngineError>,
+}
+
+impl std::fmt::Debug for TradeSinkSlot {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TradeSinkSlot")
+            .field("installed", &self.sink.is_some())
+            .field("error", &self.error)
+            .finish()
+    }
+}
+
+impl Clone for TradeSinkSlot {
+    fn clone(&self) -> Self {
+        Self::default()
+    }
+}
+
+impl TradeSinkSlot {
+    pub(super) fn record(&mut self, trade: &Trade) {
+        if let Some(sink) = self.sink.as_mut() {
+            if let Err(error) = sink.record(trade) {
+                self.error.get_or_insert(error);
+            }
+        }
+    }
+}
+
+impl LimitOrderBook {
+    /// Attaches a trade sink, replacing any previous one
+    pub fn set_trade_sink(&mut self, sink: Box<dyn TradeSink>) {
+        self.trade_sink = TradeSinkSlot { sink: Some(sink), error: None };
+    }
+
+    /// Detaches the trade sink, returning it without flushing it
+    pub fn take_trade_sink(&mut self) -> Option<Box<dyn TradeSink>> {
+        std::mem::take(&mut self.trade_sink).sink
+    }
+
+    /// Checks if a trade sink is attached
+    pub fn has_trade_sink(&self) -> bool {
+        self.trade_sink.sink.is_some()
+    }
+
+    /// Flushes the trade sink, first reporting any error it returned since the last flush
+    pub fn flush_trade_sink(&mut self) -> crate::Result<()> {
+        if let Some(error) = self.trade_sink.error.take() {
+            return Err(error);
+        }
+        self.trade_sink.sink.as_mut().map_or(Ok(()), |sink| sink.flush())
+    }
+}
+
+fn write_error(error: std::io::Error) -> MatchingEngineError {
+    MatchingEngineError::SerializationError(error.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        types::OrderId, OrderSide,
+    };
+    use std::{
+        io::BufReader,
+        sync::{Arc, Mutex},
+    };
+    use crate::order_book::test_support::create_test_order;
+
+    /// A writer whose bytes the test can see while the book owns the sink
+    #[derive(Clone, Default)]
+    struct SharedBuffer {
+        bytes: Arc<Mutex<Vec<u8>>>,
+        flushed: Arc<Mutex<usize>>,
+    }
+
+    impl Write for SharedBuffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.bytes.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            *self.flushed.lock().unwrap() = self.bytes.lock().unwrap().len();
+            Ok(())
+        }
+    }
+
+    impl SharedBuffer {
+        fn flushed_lines(&self) -> usize {
+            let flushed = *self.flushed.lock().unwrap();
+            self.bytes.lock().unwrap()[..flushed].iter().filter(|&&byte| byte == b'\n').count()
+        }
+    }
+
+    /// Rests `asks` asks a tick apart and sweeps them, one trade per ask
+    fn trade_through(book: &mut LimitOrderBook, asks: u64) -> Vec<Trade> {
+        for tick in 0..asks as i64 {
+            book.add_order(create_test_order(OrderSide::Sell, 15000 + tick, 10)).unwrap();
+        }
+        book.add_order(create_test_order(OrderSide::Buy, 16000, 10 * asks)).unwrap()
+    }
+
+    #[test]
+    fn test_file_records_every_trade_in_execution_order() {
+        let path = std::env::temp_dir().join(format!("trades-{}.jsonl", OrderId::new()));
+        let mut book = LimitOrderBook::new("AAPL".to_string()).unwrap();
+        book.set_max_recent_trades(2);
+        book.set_trade_sink(Box::new(JsonlTradeSink::create(&path, FlushPolicy::OnDemand).unwrap()));
+        let mut executed = trade_through(&mut book, 3);
+        executed.extend(trade_through(&mut book, 2));
+        book.flush_trade_sink().unwrap();
+
+        // The file holds every trade, not only those recent_trades retains
+        let reader = JsonlTradeReader::new(BufReader::new(File::open(&path).unwrap()));
+        let recorded: Vec<_> = reader.collect();
+        assert_eq!(recorded, executed);
+        assert_eq!(recorded.iter().map(|trade| trade.trade_id).collect::<Vec<_>>(), vec![1, 2, 3, 4, 5]);
+        assert_eq!(book.recent_trades().len(), 2);
+
+        // Reopening appends to the record rather than replacing it
+        let mut book = book.clone();
+        assert!(!book.has_trade_sink());
+        book.set_trade_sink(Box::new(JsonlTradeSink::create(&path, FlushPolicy::EveryTrade).unwrap()));
+        executed.extend(trade_through(&mut book, 1));
+        assert_eq!(JsonlTradeReader::new(BufReader::new(File::open(&path).unwrap())).collect::<Vec<_>>(), executed);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_flush_policies() {
+        let cases = [(FlushPolicy::EveryTrade, [1, 3, 5]), (FlushPolicy::EveryN(2), [0, 2, 4]), (FlushPolicy::OnDemand, [0, 0, 0])];
+        for (policy, expected) in cases {
+            let buffer = SharedBuffer::default();
+            let mut book = LimitOrderBook::new("AAPL".to_string()).unwrap();
+            book.set_trade_sink(Box::new(JsonlTradeSink::new(buffer.clone(), policy)));
+            let flushed: Vec<_> = [1, 2, 2].iter().map(|&asks| {
+                trade_through(&mut book, asks);
+                buffer.flushed_lines()
+            }).collect();
+            assert_eq!(flushed, expected, "{policy:?}");
+            book.flush_trade_sink().unwrap();
+            assert_eq!(buffer.flushed_lines(), 5);
+        }
+    }
+
+    #[test]
+    fn test_corrupt_lines_are_skipped_and_reported() {
+        let buffer = SharedBuffer::default();
+        let mut book = LimitOrderBook::new("AAPL".to_string()).unwrap();
+        book.set_trade_sink(Box::new(JsonlTradeSink::new(buffer.clone(), FlushPolicy::EveryTrade)));
+        let executed = trade_through(&mut book, 5);
+
+        // Garble the second trade, slip a blank line and junk between the rest, and tear the last
+        let text = String::from_utf8(buffer.bytes.lock().unwrap().clone()).unwrap();
+        let mut lines: Vec<_> = text.lines().map(str::to_string).collect();
+        lines[1] = lines[1].replace("\"price\"", "\"pr\u{fffd}ce\"");
+        let torn = lines[4].len() / 2;
+        lines[4].truncate(torn);
+        lines.insert(3, "not json".to_string());
+        lines.insert(2, String::new());
+        let corrupted = lines.join("\n").into_bytes();
+
+        let mut reader = JsonlTradeReader::new(corrupted.as_slice());
+        let recovered: Vec<_> = reader.by_ref().collect();
+        assert_eq!(recovered, vec![executed[0].clone(), executed[2].clone(), executed[3].clone()]);
+        assert_eq!(reader.skipped().iter().map(|skipped| skipped.line).collect::<Vec<_>>(), vec![2, 5, 7]);
+
+        // A sink error is kept for the next flush, and the book carries on
+        struct Failing;
+        impl TradeSink for Failing {
+            fn record(&mut self, _trade: &Trade) -> crate::Result<()> {
+                Err(MatchingEngineError::SerializationError("disk full".to_string()))
+            }
+            fn flush(&mut self) -> crate::Result<()> {
+                Ok(())
+            }
+        }
+        book.set_trade_sink(Box::new(Failing));
+        assert_eq!(trade_through(&mut book, 2).len(), 2);
+        assert_eq!(book.flush_trade_sink(), Err(MatchingEngineError::SerializationError("disk full".to_string())));
+        assert_eq!(book.flush_trade_sink(), Ok(()));
+        assert!(book.take_trade_sink().is_some());
+        assert!(!book.has_trade_sink());
+    }
+}
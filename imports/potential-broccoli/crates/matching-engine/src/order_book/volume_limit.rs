@@ -0,0 +1,197 @@
+//! Session volume limit with automatic halt
+//!
+//! Traded quantity and notional are accumulated as each trade is committed.
+//! Once either reaches its configured maximum the book halts: the breaching
+//! trade completes, the rest of the aggressing order is cancelled, and
+//! further orders and amendments are rejected until an administrative resume.
+//! The limit trips once per session; counters keep accumulating after a resume.
+
+use super::{quantity_scale, state::BookState, LimitOrderBook};
+use crate::{
+    events::{CancelReason, OrderEvent},
+    Order, Price, Quantity,
+};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+/// Maximum volume that may trade in a session before the book halts
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct VolumeLimit {
+    /// Maximum traded quantity
+    pub max_quantity: Option<u64>,
+    /// Maximum traded notional (price × quantity)
+    pub max_notional: Option<Decimal>,
+}
+
+impl VolumeLimit {
+    /// Creates a limit on traded quantity
+    pub fn quantity(max_quantity: u64) -> Self {
+        Self { max_quantity: Some(max_quantity), max_notional: None }
+    }
+    
+    /// Creates a limit on traded notional
+    pub fn notional(max_notional: Decimal) -> Self {
+        Self { max_quantity: None, max_notional: Some(max_notional) }
+    }
+    
+    pub(super) fn is_reached_by(&self, usage: &VolumeUsage) -> bool {
+        self.max_quantity.is_some_and(|max| usage.traded_quantity >= max)
+            || self.max_notional.is_some_and(|max| usage.traded_notional >= max)
+    }
+}
+
+/// Volume traded in the current session
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct VolumeUsage {
+    pub traded_quantity: u64,
+    pub traded_notional: Decimal,
+    /// Set once the limit has tripped this session
+    pub breached: bool,
+}
+
+impl LimitOrderBook {
+    /// Gets the session volume limit
+    pub fn volume_limit(&self) -> Option<VolumeLimit> {
+        self.volume_limit
+    }
+    
+    /// Sets (`Some`) or removes (`None`) the session volume limit
+    ///
+    /// Volume already traded this session counts toward the new limit.
+    pub fn set_volume_limit(&mut self, limit: Option<VolumeLimit>) {
+        self.volume_limit = limit;
+    }
+    
+    /// Gets the volume traded in the current session
+    pub fn volume_usage(&self) -> &VolumeUsage {
+        &self.volume_usage
+    }
+    
+    /// Accumulates a committed trade, halting the book if it breaches the limit
+    ///
+    /// Returns `true` when this trade tripped the limit; the remainder of the
+    /// incoming order is then cancelled and matching must stop.
+    pub(super) fn record_volume(
+        &mut self,
+        price: Price,
+        quantity: Quantity,
+        incoming: &mut Order,
+        events: &mut Vec<OrderEvent>,
+    ) -> bool {
+        let now = self.now();
+        let usage = &mut self.volume_usage;
+        usage.traded_quantity += quantity.value();
+        usage.traded_notional += quantity_scale::notional(price, quantity.value(), self.quantity_scale);
+        
+        let Some(limit) = self.volume_limit else {
+            return false;
+        };
+        if usage.breached || !limit.is_reached_by(usage) {
+            return false;
+        }
+        
+        usage.breached = true;
+        self.state = BookState::Halted;
+        events.push(OrderEvent::VolumeLimitBreached {
+            traded_quantity: usage.traded_quantity,
+            traded_notional: usage.traded_notional,
+        });
+        if incoming.is_active() {
+            incoming.cancel_at(now);
+            events.push(OrderEvent::Cancelled { order: incoming.clone(), reason: CancelReason::Halted });
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        MatchingEngineError, OrderSide, OrderStatus,
+    };
+    use crate::order_book::test_support::create_test_order;
+
+    /// Asks of 100 at 100.00, 100.01, ... 100.04 and a quantity limit of 250
+    fn limited_book() -> LimitOrderBook {
+        let mut book = LimitOrderBook::new("VOL".to_string()).unwrap();
+        book.set_volume_limit(Some(VolumeLimit::quantity(250)));
+        for i in 0..5 {
+            book.add_order(create_test_order(OrderSide::Sell, 10000 + i, 100)).unwrap();
+        }
+        book
+    }
+
+    #[test]
+    fn test_breach_mid_sweep_halts_after_breaching_trade() {
+        let mut book = limited_book();
+        
+        let result = book.submit_order(create_test_order(OrderSide::Buy, 10004, 500)).unwrap();
+        
+        // The third trade crosses 250 and completes in full; nothing trades after it
+        assert_eq!(result.trades.len(), 3);
+        assert_eq!(result.status, OrderStatus::Cancelled);
+        assert!(matches!(
+            &result.events[..],
+            [
+                OrderEvent::VolumeLimitBreached { traded_quantity: 300, .. },
+                OrderEvent::Cancelled { reason: CancelReason::Halted, .. },
+            ]
+        ));
+        assert_eq!(book.state(), BookState::Halted);
+        assert_eq!(book.volume_usage().traded_notional, Decimal::new(3000300, 2));
+        assert_eq!(book.best_ask().unwrap().as_cents(), 10003);
+        assert!(book.best_bid().is_none());
+        
+        // Every new order is rejected, passive or not; resting orders can still be cancelled
+        for price_cents in [10003, 9900] {
+            assert_eq!(
+                book.add_order(create_test_order(OrderSide::Buy, price_cents, 10)),
+                Err(MatchingEngineError::BookHalted("VOL".to_string()))
+            );
+        }
+        let resting = book.orders_at_price(OrderSide::Sell, Price::from_cents(10003).unwrap()).unwrap().iter().next().unwrap().id;
+        book.cancel_order(resting).unwrap();
+    }
+
+    #[test]
+    fn test_resume_and_counters_keep_accumulating() {
+        let mut book = limited_book();
+        book.add_order(create_test_order(OrderSide::Buy, 10004, 500)).unwrap();
+        assert_eq!(book.state(), BookState::Halted);
+        
+        book.resume();
+        let trades = book.add_order(create_test_order(OrderSide::Buy, 10004, 150)).unwrap();
+        
+        // Already breached this session: no second halt, but volume still counts
+        assert_eq!(trades.len(), 2);
+        assert_eq!(book.state(), BookState::Open);
+        assert_eq!(book.volume_usage().traded_quantity, 450);
+        assert!(book.volume_usage().breached);
+    }
+
+    #[test]
+    fn test_notional_limit() {
+        let mut book = limited_book();
+        book.set_volume_limit(Some(VolumeLimit::notional(Decimal::new(5000, 0))));
+        
+        // 50 × 100.00 = 5000.00 reaches the limit exactly
+        let result = book.submit_order(create_test_order(OrderSide::Buy, 10000, 50)).unwrap();
+        assert_eq!(result.status, OrderStatus::Filled);
+        assert!(matches!(&result.events[..], [OrderEvent::VolumeLimitBreached { traded_quantity: 50, .. }]));
+        assert_eq!(book.state(), BookState::Halted);
+    }
+
+    #[test]
+    fn test_limit_and_usage_persist_in_snapshots() {
+        let mut book = limited_book();
+        book.add_order(create_test_order(OrderSide::Buy, 10004, 500)).unwrap();
+        
+        let json = serde_json::to_string(&book).unwrap();
+        let restored: LimitOrderBook = serde_json::from_str(&json).unwrap();
+        
+        assert_eq!(restored.state(), BookState::Halted);
+        assert_eq!(restored.volume_limit(), Some(VolumeLimit::quantity(250)));
+        assert_eq!(restored.volume_usage(), book.volume_usage());
+    }
+}
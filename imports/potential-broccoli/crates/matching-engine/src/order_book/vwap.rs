@@ -0,0 +1,122 @@
+//! Volume-weighted average price over recent trades
+//!
+//! Computed from `recent_trades`, which only keeps the latest trades and is
+//! handed to the archive when the session rolls. The book remembers the
+//! newest trade it has dropped, so a window reaching back past it is flagged
+//! as truncated rather than reported as complete.
+
+use super::{quantity_scale, LimitOrderBook, Trade};
+use chrono::{DateTime, Duration, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+/// VWAP of the trades in a time window
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WindowVwap {
+    pub vwap: Decimal,
+    /// Total quantity of the trades covered, in lots of the book's scale
+    pub volume: u64,
+    /// Decimal places `volume` is counted to (zero on results that predate the field)
+    #[serde(default)]
+    pub quantity_scale: u32,
+    /// Set when trades in the window may have been dropped from the history
+    pub truncated: bool,
+}
+
+impl LimitOrderBook {
+    /// Computes the VWAP of trades in the `window` ending now
+    ///
+    /// Returns `None` if no retained trade falls in the window.
+    pub fn vwap(&self, window: Duration) -> Option<WindowVwap> {
+        self.vwap_as_of(window, self.now())
+    }
+
+    /// Computes the VWAP of trades after `as_of - window` and up to `as_of`
+    ///
+    /// Returns `None` if no retained trade falls in the window.
+    pub fn vwap_as_of(&self, window: Duration, as_of: DateTime<Utc>) -> Option<WindowVwap> {
+        let start = as_of - window;
+        let (volume, notional) = self.recent_trades.iter()
+            .filter(|trade| trade.timestamp > start && trade.timestamp <= as_of)
+            .fold((0u64, Decimal::ZERO), |(volume, notional), trade| {
+                (volume + trade.quantity.value(), notional + quantity_scale::notional(trade.price, trade.quantity.value(), self.quantity_scale))
+            });
+        if volume == 0 {
+            return None;
+        }
+        Some(WindowVwap {
+            vwap: notional / Decimal::from_i128_with_scale(i128::from(volume), self.quantity_scale),
+            volume,
+            quantity_scale: self.quantity_scale,
+            truncated: self.trades_evicted_through.is_some_and(|evicted| evicted > start),
+        })
+    }
+
+    /// Empties the recent trade history, remembering that it was dropped
+    pub(super) fn take_recent_trades(&mut self) -> Vec<Trade> {
+        if let Some(last) = self.recent_trades.back() {
+            self.trades_evicted_through = Some(last.timestamp);
+        }
+        std::mem::take(&mut self.recent_trades).into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::OrderSide;
+    use crate::order_book::test_support::create_test_order;
+
+    /// Trades 10 at 100.00, 20 at 100.03, and 30 at 100.07, one minute apart from `base`
+    fn book_with_trades(base: DateTime<Utc>) -> LimitOrderBook {
+        let mut book = LimitOrderBook::new("VWAP".to_string()).unwrap();
+        for (price_cents, quantity) in [(10000, 10), (10003, 20), (10007, 30)] {
+            book.add_order(create_test_order(OrderSide::Sell, price_cents, quantity)).unwrap();
+            book.add_order(create_test_order(OrderSide::Buy, price_cents, quantity)).unwrap();
+        }
+        for (minutes, trade) in book.recent_trades.iter_mut().enumerate() {
+            trade.timestamp = base + Duration::minutes(minutes as i64);
+        }
+        book
+    }
+
+    #[test]
+    fn test_vwap_over_known_trades() {
+        let base = Utc::now();
+        let book = book_with_trades(base);
+        let as_of = base + Duration::minutes(2);
+
+        // (100.00 * 10 + 100.03 * 20 + 100.07 * 30) / 60, exactly
+        let all = book.vwap_as_of(Duration::minutes(5), as_of).unwrap();
+        assert_eq!((all.vwap, all.volume, all.truncated), (Decimal::new(100_045, 3), 60, false));
+
+        // The window start is exclusive, its end inclusive
+        let last_two = book.vwap_as_of(Duration::minutes(2), as_of).unwrap();
+        assert_eq!((last_two.vwap, last_two.volume), (Decimal::new(100_054, 3), 50));
+        assert!(book.vwap_as_of(Duration::minutes(1), base - Duration::seconds(1)).is_none());
+    }
+
+    #[test]
+    fn test_eviction_flags_truncated_windows() {
+        let base = Utc::now() - Duration::minutes(10);
+        let mut book = book_with_trades(base);
+        book.max_recent_trades = 2;
+        book.add_order(create_test_order(OrderSide::Sell, 10010, 40)).unwrap();
+        book.add_order(create_test_order(OrderSide::Buy, 10010, 40)).unwrap();
+        assert_eq!(book.trades_evicted_through, Some(base + Duration::minutes(1)));
+
+        let wide = book.vwap(Duration::hours(1)).unwrap();
+        assert_eq!((wide.volume, wide.truncated), (70, true));
+
+        // A window starting after the newest evicted trade is complete
+        let as_of = base + Duration::minutes(2);
+        let recent = book.vwap_as_of(Duration::seconds(30), as_of).unwrap();
+        assert_eq!((recent.vwap, recent.volume, recent.truncated), (Decimal::new(10007, 2), 30, false));
+
+        book.roll_session("next");
+        assert!(book.vwap(Duration::hours(1)).is_none());
+        book.add_order(create_test_order(OrderSide::Sell, 10020, 5)).unwrap();
+        book.add_order(create_test_order(OrderSide::Buy, 10020, 5)).unwrap();
+        assert!(book.vwap(Duration::hours(1)).unwrap().truncated);
+    }
+}
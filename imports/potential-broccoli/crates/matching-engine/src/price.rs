@@ -5,11 +5,29 @@ use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
 use std::cmp::Ordering;
 
+/// Decimal places of a book's price ticks unless it asks for others: cents
+pub const DEFAULT_PRICE_SCALE: u32 = 2;
+
+/// Most decimal places a price scale may have, so a tick count of prices
+/// up to 9.2 still fits an `i64`
+pub const MAX_PRICE_SCALE: u32 = 18;
+
+/// Which way [`Price::round_to_tick`] moves a price that is between ticks
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum RoundingMode {
+    /// To the tick above
+    Up,
+    /// To the tick below
+    Down,
+    /// To the closer tick, or the one above when halfway
+    Nearest,
+}
+
 /// High-precision price representation using decimal arithmetic
 /// 
 /// Prices are stored as `Decimal` to avoid floating point precision issues
 /// common in financial calculations.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct Price(Decimal);
 
 impl Price {
@@ -38,6 +56,7 @@ impl Price {
     }
     
     /// Creates a price from a string representation
+    #[allow(clippy::should_implement_trait)]
     pub fn from_str(s: &str) -> crate::Result<Self> {
         let decimal = s.parse::<Decimal>()
             .map_err(|e| crate::MatchingEngineError::InvalidPrice(
@@ -55,6 +74,92 @@ impl Price {
     pub fn as_cents(&self) -> i64 {
         (self.0 * Decimal::ONE_HUNDRED).to_i64().unwrap_or(0)
     }
+    
+    /// Creates a price from a whole number of ticks of `10^-scale`
+    /// 
+    /// # Errors
+    /// Returns error if `ticks` is not positive or `scale` is above [`MAX_PRICE_SCALE`]
+    pub fn from_ticks(ticks: i64, scale: u32) -> crate::Result<Self> {
+        if scale > MAX_PRICE_SCALE {
+            return Err(crate::MatchingEngineError::InvalidPrice(
+                format!("Price scale {} is above the maximum of {}", scale, MAX_PRICE_SCALE)
+            ));
+        }
+        Self::new(Decimal::new(ticks, scale))
+    }
+    
+    /// Checks if the price is a whole multiple of `tick_size`
+    ///
+    /// Exact at any precision, so 150.010000001 is off a 0.01 grid. Always
+    /// false for a tick size that is not positive.
+    pub fn is_on_tick(&self, tick_size: Decimal) -> bool {
+        tick_size > Decimal::ZERO && (self.0 % tick_size).is_zero()
+    }
+    
+    /// Rounds the price onto the grid of whole multiples of `tick_size`
+    ///
+    /// A price already on the grid is returned unchanged whatever the mode.
+    ///
+    /// # Errors
+    /// Returns error if `tick_size` is not positive, or if the rounded price
+    /// is zero (rounding down below the first tick) or overflows
+    pub fn round_to_tick(&self, tick_size: Decimal, mode: RoundingMode) -> crate::Result<Self> {
+        if tick_size <= Decimal::ZERO {
+            return Err(crate::MatchingEngineError::InvalidPrice(
+                format!("Tick size must be positive, got: {}", tick_size)
+            ));
+        }
+        let below_by = self.0 % tick_size;
+        if below_by.is_zero() {
+            return Ok(*self);
+        }
+        let down = self.0 - below_by;
+        let up = match mode {
+            RoundingMode::Up => true,
+            RoundingMode::Down => false,
+            RoundingMode::Nearest => below_by * Decimal::TWO >= tick_size,
+        };
+        let rounded = match up {
+            true => down.checked_add(tick_size).ok_or_else(|| crate::MatchingEngineError::InvalidPrice(
+                format!("Price {} rounded up to a tick of {} overflows", self, tick_size)
+            ))?,
+            false => down,
+        };
+        Self::new(rounded)
+    }
+    
+    /// Gets the price as a whole number of ticks of `10^-scale`
+    /// 
+    /// Returns `None` if the price has more decimal places than `scale`
+    /// allows or its tick count does not fit an `i64`. Tick counts at one
+    /// scale order exactly as the prices do.
+    pub fn to_ticks(&self, scale: u32) -> Option<i64> {
+        match self.ticks_floor(scale) {
+            (ticks, true) => Some(ticks),
+            (_, false) => None,
+        }
+    }
+    
+    /// Gets the tick count of the price rounded down, saturating at the
+    /// bounds of `i64`, and whether it is exact
+    pub(crate) fn ticks_floor(&self, scale: u32) -> (i64, bool) {
+        let mantissa = self.0.mantissa();
+        let (ticks, exact) = match scale.checked_sub(self.0.scale()) {
+            Some(shift) => match 10i128.checked_pow(shift).and_then(|factor| mantissa.checked_mul(factor)) {
+                Some(ticks) => (ticks, true),
+                None => (mantissa.signum() * i128::MAX, false),
+            },
+            None => {
+                let divisor = 10i128.pow(self.0.scale() - scale);
+                (mantissa.div_euclid(divisor), mantissa.rem_euclid(divisor) == 0)
+            },
+        };
+        match i64::try_from(ticks) {
+            Ok(ticks) => (ticks, exact),
+            Err(_) if ticks < 0 => (i64::MIN, false),
+            Err(_) => (i64::MAX, false),
+        }
+    }
 }
 
 impl std::fmt::Display for Price {
@@ -78,6 +183,7 @@ impl Ord for Price {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use proptest::prelude::*;
     use rust_decimal::Decimal;
 
     #[test]
@@ -109,4 +215,98 @@ mod tests {
         assert!(price2 > price1);
         assert_eq!(price1.cmp(&price1), Ordering::Equal);
     }
+
+    #[test]
+    fn test_price_ticks() {
+        let price = Price::from_str("150.05").unwrap();
+        assert_eq!(price.to_ticks(2), Some(15005));
+        assert_eq!(price.to_ticks(4), Some(1500500));
+        assert_eq!(price.to_ticks(1), None);
+        assert_eq!(Price::from_str("150.0500").unwrap().to_ticks(2), Some(15005));
+        assert_eq!(Price::from_str("150.010000001").unwrap().to_ticks(2), None);
+        assert_eq!(Price::from_ticks(15005, 2).unwrap(), price);
+
+        // Too many ticks to count in an i64
+        assert_eq!(Price::new(Decimal::MAX).unwrap().to_ticks(0), None);
+        assert_eq!(Price::from_str("10").unwrap().to_ticks(MAX_PRICE_SCALE), None);
+        assert!(Price::from_ticks(0, 2).is_err());
+        assert!(Price::from_ticks(1, MAX_PRICE_SCALE + 1).is_err());
+
+        // Rounding down, for range bounds between ticks
+        assert_eq!(Price::from_str("150.059").unwrap().ticks_floor(2), (15005, false));
+        assert_eq!(price.ticks_floor(3), (150050, true));
+        assert_eq!(Price::new(Decimal::MAX).unwrap().ticks_floor(2), (i64::MAX, false));
+    }
+
+    #[test]
+    fn test_round_to_tick() {
+        let price = |value: &str| Price::from_str(value).unwrap();
+        let tick = |value: &str| value.parse::<Decimal>().unwrap();
+        let rounded = |value: &str, size: &str, mode| price(value).round_to_tick(tick(size), mode).unwrap();
+
+        assert_eq!(rounded("150.03", "0.05", RoundingMode::Up), price("150.05"));
+        assert_eq!(rounded("150.03", "0.05", RoundingMode::Down), price("150.00"));
+        assert_eq!(rounded("150.03", "0.05", RoundingMode::Nearest), price("150.05"));
+        assert_eq!(rounded("150.025", "0.05", RoundingMode::Nearest), price("150.05"));
+        assert_eq!(rounded("150.02", "0.05", RoundingMode::Nearest), price("150.00"));
+        assert_eq!(rounded("99.80", "0.25", RoundingMode::Nearest), price("99.75"));
+        assert_eq!(rounded("99.75", "0.25", RoundingMode::Up), price("99.75"));
+
+        // Off the grid only far past the tick's precision
+        let fine = price("150.010000001");
+        assert!(!fine.is_on_tick(tick("0.01")));
+        assert_eq!(fine.round_to_tick(tick("0.01"), RoundingMode::Up).unwrap(), price("150.02"));
+        assert_eq!(fine.round_to_tick(tick("0.01"), RoundingMode::Down).unwrap(), price("150.01"));
+        assert!(price("150.0100").is_on_tick(tick("0.01")));
+
+        assert!(price("0.03").round_to_tick(tick("0.05"), RoundingMode::Down).is_err());
+        assert!(price("1").round_to_tick(Decimal::ZERO, RoundingMode::Up).is_err());
+        assert!(price("1").round_to_tick(tick("-0.01"), RoundingMode::Up).is_err());
+        assert!(!price("1").is_on_tick(Decimal::ZERO));
+    }
+
+    /// A price of `ticks` at `scale`, written in one of several equal forms
+    fn written(ticks: i64, scale: u32, form: u8) -> Price {
+        let value = Decimal::new(ticks, scale);
+        Price::new(match form {
+            0 => value,
+            1 => value.normalize(),
+            _ => Decimal::from_i128_with_scale(i128::from(ticks) * 1000, scale + 3),
+        }).unwrap()
+    }
+
+    proptest! {
+        #[test]
+        fn prop_tick_order_agrees_with_price_order(
+            scale in 0..=MAX_PRICE_SCALE,
+            ticks in 1..i64::MAX - 2,
+            delta in prop_oneof![-2i64..=2, 1..i64::MAX],
+            forms in (0..3u8, 0..3u8),
+        ) {
+            let other_ticks = ticks.saturating_add(delta).clamp(1, i64::MAX - 2);
+            let (a, b) = (written(ticks, scale, forms.0), written(other_ticks, scale, forms.1));
+            prop_assert_eq!(a.to_ticks(scale), Some(ticks));
+            prop_assert_eq!(b.to_ticks(scale), Some(other_ticks));
+            prop_assert_eq!(ticks.cmp(&other_ticks), a.cmp(&b));
+            prop_assert_eq!(Price::from_ticks(ticks, scale).unwrap(), a);
+        }
+
+        #[test]
+        fn prop_rounded_ticks_never_reorder_prices(
+            scale in 0..=6u32,
+            mantissas in (1..i64::MAX, 1..i64::MAX),
+            places in (0..=12u32, 0..=12u32),
+        ) {
+            let a = Price::new(Decimal::new(mantissas.0, places.0)).unwrap();
+            let b = Price::new(Decimal::new(mantissas.1, places.1)).unwrap();
+            let ((a_floor, a_exact), (b_floor, b_exact)) = (a.ticks_floor(scale), b.ticks_floor(scale));
+            match a.cmp(&b) {
+                Ordering::Less => prop_assert!(a_floor <= b_floor),
+                Ordering::Equal => prop_assert_eq!(a_floor, b_floor),
+                Ordering::Greater => prop_assert!(a_floor >= b_floor),
+            }
+            prop_assert_eq!(a.to_ticks(scale).is_some(), a_exact);
+            prop_assert_eq!(b.to_ticks(scale).is_some(), b_exact);
+        }
+    }
 }
\ No newline at end of file
@@ -0,0 +1,643 @@
+//! Protobuf representations of the core types
+//!
+//! [`v1`] holds the prost messages generated from
+//! `proto/matching_engine/v1/matching_engine.proto`. Domain types convert
+//! into them with `From`, and back with `TryFrom`, which goes through the
+//! same constructors as the rest of the engine: wire data with a negative
+//! price, a zero quantity, an unspecified enum, or a malformed ID fails to
+//! convert instead of producing an order or trade the book would never
+//! create.
+//!
+//! Prices, fees, and spreads travel as decimal strings. A string holds any
+//! `Decimal` exactly, whereas a (units, nanos) pair loses anything past the
+//! ninth decimal place.
+//!
+//! Quantities travel as lot counts, as the book keeps them. Trades and
+//! levels carry their book's quantity scale alongside, so a receiver can
+//! tell 125 whole shares from 0.125 of a share.
+//!
+//! The generated code is checked in; a test compares its fields, tags, and
+//! enum values against the schema, so the two can't drift apart unnoticed.
+//!
+//! Available with the `proto` feature.
+
+use crate::{
+    order_book::{MarketDepth, MarketLevel, SnapshotEnvelope, Trade},
+    quantity::MAX_QUANTITY_SCALE,
+    types::{OrderId, Symbol, UserId},
+    MatchingEngineError, Order, OrderSide, OrderStatus, OrderType, Price, Quantity, TimeInForce,
+};
+use chrono::{DateTime, Utc};
+use prost_types::Timestamp;
+use rust_decimal::Decimal;
+
+/// Messages generated by prost from the v1 schema
+#[allow(clippy::all)]
+pub mod v1 {
+    include!("proto/matching_engine.v1.rs");
+}
+
+impl From<OrderSide> for v1::Side {
+    fn from(side: OrderSide) -> Self {
+        match side {
+            OrderSide::Buy => v1::Side::Buy,
+            OrderSide::Sell => v1::Side::Sell,
+        }
+    }
+}
+
+impl TryFrom<v1::Side> for OrderSide {
+    type Error = MatchingEngineError;
+
+    fn try_from(side: v1::Side) -> crate::Result<Self> {
+        match side {
+            v1::Side::Buy => Ok(OrderSide::Buy),
+            v1::Side::Sell => Ok(OrderSide::Sell),
+            v1::Side::Unspecified => Err(MatchingEngineError::InvalidOrderSide),
+        }
+    }
+}
+
+impl From<OrderStatus> for v1::OrderStatus {
+    fn from(status: OrderStatus) -> Self {
+        match status {
+            OrderStatus::Active => v1::OrderStatus::Active,
+            OrderStatus::PartiallyFilled => v1::OrderStatus::PartiallyFilled,
+            OrderStatus::Filled => v1::OrderStatus::Filled,
+            OrderStatus::Cancelled => v1::OrderStatus::Cancelled,
+            OrderStatus::Expired => v1::OrderStatus::Expired,
+            OrderStatus::PendingActivation => v1::OrderStatus::PendingActivation,
+        }
+    }
+}
+
+impl TryFrom<v1::OrderStatus> for OrderStatus {
+    type Error = MatchingEngineError;
+
+    fn try_from(status: v1::OrderStatus) -> crate::Result<Self> {
+        match status {
+            v1::OrderStatus::Active => Ok(OrderStatus::Active),
+            v1::OrderStatus::PartiallyFilled => Ok(OrderStatus::PartiallyFilled),
+            v1::OrderStatus::Filled => Ok(OrderStatus::Filled),
+            v1::OrderStatus::Cancelled => Ok(OrderStatus::Cancelled),
+            v1::OrderStatus::Expired => Ok(OrderStatus::Expired),
+            v1::OrderStatus::PendingActivation => Ok(OrderStatus::PendingActivation),
+            v1::OrderStatus::Unspecified => Err(unspecified("status")),
+        }
+    }
+}
+
+impl From<TimeInForce> for v1::TimeInForce {
+    fn from(time_in_force: TimeInForce) -> Self {
+        match time_in_force {
+            TimeInForce::GTC => v1::TimeInForce::Gtc,
+            TimeInForce::Day => v1::TimeInForce::Day,
+            TimeInForce::FOK => v1::TimeInForce::Fok,
+            TimeInForce::AtTheClose => v1::TimeInForce::AtTheClose,
+        }
+    }
+}
+
+impl TryFrom<v1::TimeInForce> for TimeInForce {
+    type Error = MatchingEngineError;
+
+    fn try_from(time_in_force: v1::TimeInForce) -> crate::Result<Self> {
+        match time_in_force {
+            v1::TimeInForce::Gtc => Ok(TimeInForce::GTC),
+            v1::TimeInForce::Day => Ok(TimeInForce::Day),
+            v1::TimeInForce::Fok => Ok(TimeInForce::FOK),
+            v1::TimeInForce::AtTheClose => Ok(TimeInForce::AtTheClose),
+            v1::TimeInForce::Unspecified => Err(unspecified("time_in_force")),
+        }
+    }
+}
+
+impl From<Order> for v1::Order {
+    fn from(order: Order) -> Self {
+        let (order_type, stop_trigger) = match order.order_type {
+            OrderType::Limit => (v1::OrderType::Limit, None),
+            OrderType::Market => (v1::OrderType::Market, None),
+            OrderType::Stop { trigger } => (v1::OrderType::Stop, Some(trigger.value().to_string())),
+        };
+        Self {
+            id: order.id.to_string(),
+            user_id: order.user_id.as_str().to_string(),
+            symbol: order.symbol.map(|symbol| symbol.as_str().to_string()),
+            side: v1::Side::from(order.side).into(),
+            price: order.price.value().to_string(),
+            original_quantity: order.original_quantity.value(),
+            remaining_quantity: order.remaining_quantity.value(),
+            status: v1::OrderStatus::from(order.status).into(),
+            time_in_force: v1::TimeInForce::from(order.time_in_force).into(),
+            order_type: order_type.into(),
+            stop_trigger,
+            expires_at: order.expires_at.map(timestamp),
+            activate_at: order.activate_at.map(timestamp),
+            post_only: order.post_only,
+            reduce_only: order.reduce_only,
+            hidden: order.hidden,
+            client_order_id: order.client_order_id,
+            created_at: Some(timestamp(order.created_at)),
+            updated_at: Some(timestamp(order.updated_at)),
+        }
+    }
+}
+
+impl TryFrom<v1::Order> for Order {
+    type Error = MatchingEngineError;
+
+    /// Converts a wire order, which must be one the engine could hold
+    ///
+    /// Working orders need a remaining quantity, and only a cancelled order,
+    /// reduced to nothing before it traded, may have an original quantity of
+    /// zero. The remaining quantity never exceeds the original.
+    fn try_from(order: v1::Order) -> crate::Result<Self> {
+        let status = OrderStatus::try_from(enumeration::<v1::OrderStatus>("status", order.status)?)?;
+        let working = matches!(status, OrderStatus::Active | OrderStatus::PartiallyFilled | OrderStatus::PendingActivation);
+        let remaining_quantity = match working {
+            true => Quantity::new(order.remaining_quantity)?,
+            false => Quantity::new_allow_zero(order.remaining_quantity),
+        };
+        let original_quantity = match status {
+            OrderStatus::Cancelled => Quantity::new_allow_zero(order.original_quantity),
+            _ => Quantity::new(order.original_quantity)?,
+        };
+        if remaining_quantity > original_quantity {
+            return Err(MatchingEngineError::InvalidQuantity(format!(
+                "Remaining quantity {remaining_quantity} exceeds original quantity {original_quantity}"
+            )));
+        }
+        let order_type = match enumeration::<v1::OrderType>("order_type", order.order_type)? {
+            v1::OrderType::Limit => OrderType::Limit,
+            v1::OrderType::Market => OrderType::Market,
+            v1::OrderType::Stop => {
+                let trigger = order.stop_trigger.ok_or_else(|| missing("stop_trigger"))?;
+                OrderType::Stop { trigger: Price::from_str(&trigger)? }
+            },
+            v1::OrderType::Unspecified => return Err(unspecified("order_type")),
+        };
+
+        Ok(Order {
+            id: order_id("id", &order.id)?,
+            user_id: UserId::new(order.user_id),
+            symbol: order.symbol.map(Symbol::new).transpose()?,
+            side: OrderSide::try_from(enumeration::<v1::Side>("side", order.side)?)?,
+            price: Price::from_str(&order.price)?,
+            original_quantity,
+            remaining_quantity,
+            status,
+            time_in_force: TimeInForce::try_from(enumeration::<v1::TimeInForce>("time_in_force", order.time_in_force)?)?,
+            order_type,
+            expires_at: order.expires_at.map(|at| date_time("expires_at", at)).transpose()?,
+            activate_at: order.activate_at.map(|at| date_time("activate_at", at)).transpose()?,
+            post_only: order.post_only,
+            reduce_only: order.reduce_only,
+            hidden: order.hidden,
+            client_order_id: order.client_order_id,
+            created_at: date_time("created_at", order.created_at.ok_or_else(|| missing("created_at"))?)?,
+            updated_at: date_time("updated_at", order.updated_at.ok_or_else(|| missing("updated_at"))?)?,
+        })
+    }
+}
+
+impl From<Trade> for v1::Trade {
+    fn from(trade: Trade) -> Self {
+        Self {
+            trade_id: trade.trade_id,
+            buy_order_id: trade.buy_order_id.to_string(),
+            sell_order_id: trade.sell_order_id.to_string(),
+            maker_order_id: trade.maker_order_id.map(|id| id.to_string()),
+            taker_order_id: trade.taker_order_id.map(|id| id.to_string()),
+            aggressor_side: trade.aggressor_side.map_or(v1::Side::Unspecified, v1::Side::from).into(),
+            buyer_user_id: trade.buyer_user_id.map(|user| user.as_str().to_string()),
+            seller_user_id: trade.seller_user_id.map(|user| user.as_str().to_string()),
+            price: trade.price.value().to_string(),
+            quantity: trade.quantity.value(),
+            timestamp: Some(timestamp(trade.timestamp)),
+            same_firm: trade.same_firm,
+            maker_fee: trade.maker_fee.to_string(),
+            taker_fee: trade.taker_fee.to_string(),
+            symbol: trade.symbol.map(|symbol| symbol.as_str().to_string()),
+            sequence: trade.sequence,
+            quantity_scale: trade.quantity_scale,
+        }
+    }
+}
+
+impl TryFrom<v1::Trade> for Trade {
+    type Error = MatchingEngineError;
+
+    fn try_from(trade: v1::Trade) -> crate::Result<Self> {
+        let aggressor_side = match enumeration::<v1::Side>("aggressor_side", trade.aggressor_side)? {
+            v1::Side::Unspecified => None,
+            side => Some(OrderSide::try_from(side)?),
+        };
+        Ok(Trade {
+            trade_id: trade.trade_id,
+            buy_order_id: order_id("buy_order_id", &trade.buy_order_id)?,
+            sell_order_id: order_id("sell_order_id", &trade.sell_order_id)?,
+            maker_order_id: trade.maker_order_id.map(|id| order_id("maker_order_id", &id)).transpose()?,
+            taker_order_id: trade.taker_order_id.map(|id| order_id("taker_order_id", &id)).transpose()?,
+            aggressor_side,
+            buyer_user_id: trade.buyer_user_id.map(UserId::new),
+            seller_user_id: trade.seller_user_id.map(UserId::new),
+            price: Price::from_str(&trade.price)?,
+            quantity: Quantity::new(trade.quantity)?,
+            timestamp: date_time("timestamp", trade.timestamp.ok_or_else(|| missing("timestamp"))?)?,
+            same_firm: trade.same_firm,
+            maker_fee: decimal("maker_fee", &trade.maker_fee)?,
+            taker_fee: decimal("taker_fee", &trade.taker_fee)?,
+            symbol: trade.symbol.map(Symbol::new).transpose()?,
+            sequence: trade.sequence,
+            quantity_scale: quantity_scale(trade.quantity_scale)?,
+        })
+    }
+}
+
+impl From<MarketLevel> for v1::MarketLevel {
+    fn from(level: MarketLevel) -> Self {
+        Self {
+            price: level.price.value().to_string(),
+            quantity: level.quantity.value(),
+            order_count: level.order_count as u64,
+            quantity_scale: level.quantity_scale,
+        }
+    }
+}
+
+impl TryFrom<v1::MarketLevel> for MarketLevel {
+    type Error = MatchingEngineError;
+
+    fn try_from(level: v1::MarketLevel) -> crate::Result<Self> {
+        Ok(MarketLevel {
+            price: Price::from_str(&level.price)?,
+            quantity: Quantity::new(level.quantity)?,
+            order_count: usize::try_from(level.order_count)
+                .map_err(|_| MatchingEngineError::DeserializationError(format!("order_count {} is too large", level.order_count)))?,
+            quantity_scale: quantity_scale(level.quantity_scale)?,
+        })
+    }
+}
+
+impl From<MarketDepth> for v1::MarketDepth {
+    fn from(depth: MarketDepth) -> Self {
+        Self {
+            bids: depth.bids.into_iter().map(Into::into).collect(),
+            asks: depth.asks.into_iter().map(Into::into).collect(),
+            spread: depth.spread.map(|spread| spread.to_string()),
+            sequence: depth.sequence,
+        }
+    }
+}
+
+impl TryFrom<v1::MarketDepth> for MarketDepth {
+    type Error = MatchingEngineError;
+
+    fn try_from(depth: v1::MarketDepth) -> crate::Result<Self> {
+        Ok(MarketDepth {
+            bids: depth.bids.into_iter().map(TryInto::try_into).collect::<crate::Result<_>>()?,
+            asks: depth.asks.into_iter().map(TryInto::try_into).collect::<crate::Result<_>>()?,
+            spread: depth.spread.map(|spread| decimal("spread", &spread)).transpose()?,
+            sequence: depth.sequence,
+        })
+    }
+}
+
+impl From<SnapshotEnvelope> for v1::SnapshotEnvelope {
+    fn from(envelope: SnapshotEnvelope) -> Self {
+        Self { version: envelope.version, payload_json: envelope.payload.to_string() }
+    }
+}
+
+impl TryFrom<v1::SnapshotEnvelope> for SnapshotEnvelope {
+    type Error = MatchingEngineError;
+
+    fn try_from(envelope: v1::SnapshotEnvelope) -> crate::Result<Self> {
+        let payload = serde_json::from_str(&envelope.payload_json)
+            .map_err(|e| MatchingEngineError::DeserializationError(format!("payload_json: {e}")))?;
+        Ok(SnapshotEnvelope { version: envelope.version, payload })
+    }
+}
+
+fn timestamp(at: DateTime<Utc>) -> Timestamp {
+    Timestamp { seconds: at.timestamp(), nanos: at.timestamp_subsec_nanos() as i32 }
+}
+
+fn date_time(field: &str, at: Timestamp) -> crate::Result<DateTime<Utc>> {
+    u32::try_from(at.nanos).ok()
+        .and_then(|nanos| DateTime::from_timestamp(at.seconds, nanos))
+        .ok_or_else(|| MatchingEngineError::DeserializationError(format!("{field} is not a valid timestamp")))
+}
+
+fn order_id(field: &str, id: &str) -> crate::Result<OrderId> {
+    uuid::Uuid::parse_str(id)
+        .map(OrderId::from_uuid)
+        .map_err(|e| MatchingEngineError::DeserializationError(format!("{field}: {e}")))
+}
+
+/// Reads a quantity scale, rejecting one no book can have
+fn quantity_scale(scale: u32) -> crate::Result<u32> {
+    if scale > MAX_QUANTITY_SCALE {
+        return Err(MatchingEngineError::DeserializationError(format!(
+            "quantity_scale {scale} is above the maximum of {MAX_QUANTITY_SCALE}"
+        )));
+    }
+    Ok(scale)
+}
+
+fn decimal(field: &str, value: &str) -> crate::Result<Decimal> {
+    value.parse().map_err(|e| MatchingEngineError::DeserializationError(format!("{field}: {e}")))
+}
+
+/// Reads an enum field, rejecting values this schema doesn't define
+fn enumeration<E: TryFrom<i32>>(field: &str, value: i32) -> crate::Result<E> {
+    E::try_from(value).map_err(|_| MatchingEngineError::DeserializationError(format!("{field} has unknown value {value}")))
+}
+
+fn unspecified(field: &str) -> MatchingEngineError {
+    MatchingEngineError::DeserializationError(format!("{field} is unspecified"))
+}
+
+fn missing(field: &str) -> MatchingEngineError {
+    MatchingEngineError::DeserializationError(format!("{field} is missing"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use prost::Message;
+    use proptest::prelude::*;
+    use std::collections::{BTreeSet, HashMap, HashSet};
+
+    fn arb_price() -> impl Strategy<Value = Price> {
+        (1i64..100_000_000, 0u32..8).prop_map(|(mantissa, scale)| Price::new(Decimal::new(mantissa, scale)).unwrap())
+    }
+
+    fn arb_decimal() -> impl Strategy<Value = Decimal> {
+        (any::<i64>(), 0u32..12).prop_map(|(mantissa, scale)| Decimal::new(mantissa, scale))
+    }
+
+    fn arb_time() -> impl Strategy<Value = DateTime<Utc>> {
+        (0i64..4_000_000_000, 0u32..1_000_000_000).prop_map(|(seconds, nanos)| DateTime::from_timestamp(seconds, nanos).unwrap())
+    }
+
+    fn arb_order_id() -> impl Strategy<Value = OrderId> {
+        any::<u128>().prop_map(|bits| OrderId::from_uuid(uuid::Uuid::from_u128(bits)))
+    }
+
+    fn arb_side() -> impl Strategy<Value = OrderSide> {
+        prop_oneof![Just(OrderSide::Buy), Just(OrderSide::Sell)]
+    }
+
+    fn arb_symbol() -> impl Strategy<Value = Symbol> {
+        "[A-Z]{1,10}".prop_map(|symbol| Symbol::new(symbol).unwrap())
+    }
+
+    fn arb_order() -> impl Strategy<Value = Order> {
+        let identity = (arb_order_id(), "[a-z0-9_]{1,12}", proptest::option::of(arb_symbol()), arb_side(), proptest::option::of("[A-Z0-9-]{1,16}"));
+        let status = prop_oneof![
+            Just(OrderStatus::Active),
+            Just(OrderStatus::PartiallyFilled),
+            Just(OrderStatus::Filled),
+            Just(OrderStatus::Cancelled),
+            Just(OrderStatus::Expired),
+            Just(OrderStatus::PendingActivation),
+        ];
+        let sizing = (2u64..1_000_000, 0u64..=100, status);
+        let order_type = prop_oneof![Just(OrderType::Limit), Just(OrderType::Market), arb_price().prop_map(|trigger| OrderType::Stop { trigger })];
+        let time_in_force = prop_oneof![Just(TimeInForce::GTC), Just(TimeInForce::Day), Just(TimeInForce::FOK), Just(TimeInForce::AtTheClose)];
+        let terms = (arb_price(), order_type, time_in_force, any::<(bool, bool, bool)>());
+        let times = (arb_time(), arb_time(), proptest::option::of(arb_time()), proptest::option::of(arb_time()));
+
+        (identity, sizing, terms, times).prop_map(|(identity, sizing, terms, times)| {
+            let (id, user, symbol, side, client_order_id) = identity;
+            let (original, percent, status) = sizing;
+            let (price, order_type, time_in_force, (post_only, reduce_only, hidden)) = terms;
+            let (created_at, updated_at, expires_at, activate_at) = times;
+            let remaining = match status {
+                OrderStatus::Filled => 0,
+                OrderStatus::Active | OrderStatus::PendingActivation => original,
+                OrderStatus::PartiallyFilled => (original * percent / 100).clamp(1, original - 1),
+                OrderStatus::Cancelled | OrderStatus::Expired => original * percent / 100,
+            };
+            Order {
+                id,
+                user_id: UserId::new(user),
+                symbol,
+                side,
+                price,
+                original_quantity: Quantity::new(original).unwrap(),
+                remaining_quantity: Quantity::new_allow_zero(remaining),
+                status,
+                time_in_force,
+                order_type,
+                expires_at,
+                activate_at,
+                post_only,
+                reduce_only,
+                hidden,
+                client_order_id,
+                created_at,
+                updated_at,
+            }
+        })
+    }
+
+    fn arb_trade() -> impl Strategy<Value = Trade> {
+        let ids = (any::<u64>(), arb_order_id(), arb_order_id(), proptest::option::of((arb_order_id(), arb_order_id(), arb_side())));
+        let users = (proptest::option::of("[a-z]{1,8}"), proptest::option::of("[a-z]{1,8}"), proptest::option::of(arb_symbol()), 0..=MAX_QUANTITY_SCALE);
+        let execution = (arb_price(), 1u64..1_000_000, arb_time(), any::<bool>(), arb_decimal(), arb_decimal(), any::<u64>());
+        (ids, users, execution).prop_map(|(ids, users, execution)| {
+            let (trade_id, buy_order_id, sell_order_id, roles) = ids;
+            let (buyer, seller, symbol, quantity_scale) = users;
+            let (price, quantity, timestamp, same_firm, maker_fee, taker_fee, sequence) = execution;
+            Trade {
+                trade_id,
+                buy_order_id,
+                sell_order_id,
+                maker_order_id: roles.map(|(maker, _, _)| maker),
+                taker_order_id: roles.map(|(_, taker, _)| taker),
+                aggressor_side: roles.map(|(_, _, side)| side),
+                buyer_user_id: buyer.map(UserId::new),
+                seller_user_id: seller.map(UserId::new),
+                price,
+                quantity: Quantity::new(quantity).unwrap(),
+                timestamp,
+                same_firm,
+                maker_fee,
+                taker_fee,
+                symbol,
+                sequence,
+                quantity_scale,
+            }
+        })
+    }
+
+    fn arb_level() -> impl Strategy<Value = MarketLevel> {
+        (arb_price(), 1u64..1_000_000, 1usize..1_000, 0..=MAX_QUANTITY_SCALE).prop_map(|(price, quantity, order_count, quantity_scale)| {
+            MarketLevel { price, quantity: Quantity::new(quantity).unwrap(), order_count, quantity_scale }
+        })
+    }
+
+    fn arb_depth() -> impl Strategy<Value = MarketDepth> {
+        let levels = || proptest::collection::vec(arb_level(), 0..6);
+        (levels(), levels(), proptest::option::of(arb_decimal()), any::<u64>())
+            .prop_map(|(bids, asks, spread, sequence)| MarketDepth { bids, asks, spread, sequence })
+    }
+
+    fn arb_envelope() -> impl Strategy<Value = SnapshotEnvelope> {
+        (any::<u32>(), proptest::collection::btree_map("[a-z_]{1,10}", any::<i64>(), 0..6)).prop_map(|(version, fields)| {
+            SnapshotEnvelope { version, payload: serde_json::to_value(fields).unwrap() }
+        })
+    }
+
+    /// Sends a value over the wire and back
+    fn round_trip<T, P>(value: T) -> crate::Result<T>
+    where
+        T: Into<P> + TryFrom<P, Error = MatchingEngineError>,
+        P: Message + Default,
+    {
+        let bytes = value.into().encode_to_vec();
+        T::try_from(P::decode(bytes.as_slice()).unwrap())
+    }
+
+    proptest! {
+        #[test]
+        fn prop_order_round_trips(order in arb_order()) {
+            prop_assert_eq!(round_trip::<_, v1::Order>(order.clone()), Ok(order));
+        }
+
+        #[test]
+        fn prop_trade_round_trips(trade in arb_trade()) {
+            prop_assert_eq!(round_trip::<_, v1::Trade>(trade.clone()), Ok(trade));
+        }
+
+        #[test]
+        fn prop_level_and_depth_round_trip(level in arb_level(), depth in arb_depth()) {
+            prop_assert_eq!(round_trip::<_, v1::MarketLevel>(level.clone()), Ok(level));
+            prop_assert_eq!(round_trip::<_, v1::MarketDepth>(depth.clone()), Ok(depth));
+        }
+
+        #[test]
+        fn prop_envelope_round_trips(envelope in arb_envelope()) {
+            prop_assert_eq!(round_trip::<_, v1::SnapshotEnvelope>(envelope.clone()), Ok(envelope));
+        }
+    }
+
+    #[test]
+    fn test_invalid_wire_data_does_not_convert() {
+        let order = Order::new(OrderId::new(), UserId::new("alice".to_string()), OrderSide::Buy, Price::from_cents(15000).unwrap(), Quantity::new(100).unwrap());
+        let wire = v1::Order::from(order.clone());
+        assert_eq!(Order::try_from(wire.clone()), Ok(order));
+
+        let invalid = [
+            v1::Order { price: "-1".to_string(), ..wire.clone() },
+            v1::Order { price: "abc".to_string(), ..wire.clone() },
+            v1::Order { remaining_quantity: 0, ..wire.clone() },
+            v1::Order { remaining_quantity: 101, ..wire.clone() },
+            v1::Order { id: "not-a-uuid".to_string(), ..wire.clone() },
+            v1::Order { symbol: Some("TOOLONGSYMBOL".to_string()), ..wire.clone() },
+            v1::Order { side: v1::Side::Unspecified.into(), ..wire.clone() },
+            v1::Order { status: 42, ..wire.clone() },
+            v1::Order { order_type: v1::OrderType::Stop.into(), ..wire.clone() },
+            v1::Order { created_at: None, ..wire.clone() },
+            v1::Order { updated_at: Some(Timestamp { seconds: 0, nanos: -1 }), ..wire.clone() },
+        ];
+        for wire in invalid {
+            assert!(Order::try_from(wire.clone()).is_err(), "{wire:?}");
+        }
+
+        let level = v1::MarketLevel { price: "150.00".to_string(), quantity: 0, order_count: 1, quantity_scale: 0 };
+        assert!(matches!(MarketLevel::try_from(level), Err(MatchingEngineError::InvalidQuantity(_))));
+        let level = v1::MarketLevel { price: "150.00".to_string(), quantity: 125, order_count: 1, quantity_scale: MAX_QUANTITY_SCALE + 1 };
+        assert!(matches!(MarketLevel::try_from(level), Err(MatchingEngineError::DeserializationError(_))));
+        let envelope = v1::SnapshotEnvelope { version: 2, payload_json: "{".to_string() };
+        assert!(matches!(SnapshotEnvelope::try_from(envelope), Err(MatchingEngineError::DeserializationError(_))));
+    }
+
+    /// Fields as (message, name, kind, label, tag) and enum values as (enum, name, number)
+    type Schema = (BTreeSet<(String, String, String, String, u32)>, BTreeSet<(String, String, i32)>);
+
+    fn schema_from_proto(source: &str) -> Schema {
+        let enums: HashSet<&str> = source.lines()
+            .filter_map(|line| line.trim().strip_prefix("enum "))
+            .filter_map(|rest| rest.split_whitespace().next())
+            .collect();
+        let (mut fields, mut values) = (BTreeSet::new(), BTreeSet::new());
+        let mut block = None;
+        for line in source.lines().map(str::trim).filter(|line| !line.starts_with("//")) {
+            if let Some(rest) = line.strip_prefix("message ").or_else(|| line.strip_prefix("enum ")) {
+                block = rest.split_whitespace().next();
+                continue;
+            }
+            let (Some(name), Some((declaration, number))) = (block, line.strip_suffix(';').and_then(|line| line.split_once(" = "))) else {
+                continue;
+            };
+            let tokens: Vec<&str> = declaration.split_whitespace().collect();
+            match tokens[..] {
+                [value] => {
+                    values.insert((name.to_string(), value.to_string(), number.parse().unwrap()));
+                },
+                [.., field_type, field] => {
+                    let kind = if enums.contains(field_type) {
+                        "enumeration"
+                    } else if field_type.contains('.') || field_type.starts_with(char::is_uppercase) {
+                        "message"
+                    } else {
+                        field_type
+                    };
+                    let label = match tokens[0] {
+                        "repeated" => "repeated",
+                        "optional" if kind != "message" => "optional",
+                        _ => "",
+                    };
+                    fields.insert((name.to_string(), field.to_string(), kind.to_string(), label.to_string(), number.parse().unwrap()));
+                },
+                _ => {},
+            }
+        }
+        (fields, values)
+    }
+
+    fn schema_from_generated(source: &str) -> Schema {
+        let (mut fields, mut values) = (BTreeSet::new(), BTreeSet::new());
+        let mut variants: HashMap<(String, String), i32> = HashMap::new();
+        let mut block = None;
+        let mut attribute: Option<&str> = None;
+        for line in source.lines().map(str::trim) {
+            if let Some(rest) = line.strip_prefix("pub struct ").or_else(|| line.strip_prefix("pub enum ")).or_else(|| line.strip_prefix("impl ")) {
+                block = rest.split_whitespace().next();
+            } else if let Some(prost) = line.strip_prefix("#[prost(") {
+                attribute = Some(prost);
+            } else if let (Some(message), Some(prost), Some(field)) = (block, attribute.take(), line.strip_prefix("pub ")) {
+                let kind = prost.split([',', ' ']).next().unwrap();
+                let label = if prost.contains("repeated") {
+                    "repeated"
+                } else if prost.contains("optional") && kind != "message" {
+                    "optional"
+                } else {
+                    ""
+                };
+                let tag = prost.split("tag = \"").nth(1).and_then(|rest| rest.split('"').next()).unwrap();
+                let name = field.split(':').next().unwrap();
+                fields.insert((message.to_string(), name.to_string(), kind.to_string(), label.to_string(), tag.parse().unwrap()));
+            } else if let (Some(name), Some((variant, number))) = (block, line.strip_suffix(',').and_then(|line| line.split_once(" = "))) {
+                variants.insert((name.to_string(), variant.to_string()), number.parse().unwrap());
+            } else if let (Some(name), Some((variant, value))) = (block, line.strip_prefix("Self::").and_then(|line| line.split_once(" => \""))) {
+                let number = variants[&(name.to_string(), variant.to_string())];
+                values.insert((name.to_string(), value.trim_end_matches("\",").to_string(), number));
+            }
+        }
+        (fields, values)
+    }
+
+    #[test]
+    fn test_generated_code_matches_the_schema() {
+        let schema = schema_from_proto(include_str!("../proto/matching_engine/v1/matching_engine.proto"));
+        let generated = schema_from_generated(include_str!("proto/matching_engine.v1.rs"));
+        assert!(schema.0.iter().any(|(message, field, ..)| message == "Trade" && field == "quantity_scale"));
+        assert!(schema.1.iter().any(|(name, value, _)| name == "Side" && value == "SIDE_SELL"));
+        assert_eq!(generated.0, schema.0, "regenerate src/proto/matching_engine.v1.rs from the schema");
+        assert_eq!(generated.1, schema.1, "regenerate src/proto/matching_engine.v1.rs from the schema");
+    }
+}
@@ -0,0 +1,249 @@
+// This file is @generated by prost-build.
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Order {
+    /// UUID in its hyphenated form
+    #[prost(string, tag = "1")]
+    pub id: ::prost::alloc::string::String,
+    #[prost(string, tag = "2")]
+    pub user_id: ::prost::alloc::string::String,
+    #[prost(string, optional, tag = "3")]
+    pub symbol: ::core::option::Option<::prost::alloc::string::String>,
+    #[prost(enumeration = "Side", tag = "4")]
+    pub side: i32,
+    #[prost(string, tag = "5")]
+    pub price: ::prost::alloc::string::String,
+    #[prost(uint64, tag = "6")]
+    pub original_quantity: u64,
+    #[prost(uint64, tag = "7")]
+    pub remaining_quantity: u64,
+    #[prost(enumeration = "OrderStatus", tag = "8")]
+    pub status: i32,
+    #[prost(enumeration = "TimeInForce", tag = "9")]
+    pub time_in_force: i32,
+    #[prost(enumeration = "OrderType", tag = "10")]
+    pub order_type: i32,
+    #[prost(string, optional, tag = "11")]
+    pub stop_trigger: ::core::option::Option<::prost::alloc::string::String>,
+    #[prost(message, optional, tag = "12")]
+    pub expires_at: ::core::option::Option<::prost_types::Timestamp>,
+    #[prost(message, optional, tag = "13")]
+    pub activate_at: ::core::option::Option<::prost_types::Timestamp>,
+    #[prost(bool, tag = "14")]
+    pub post_only: bool,
+    #[prost(bool, tag = "15")]
+    pub reduce_only: bool,
+    #[prost(bool, tag = "16")]
+    pub hidden: bool,
+    #[prost(string, optional, tag = "17")]
+    pub client_order_id: ::core::option::Option<::prost::alloc::string::String>,
+    #[prost(message, optional, tag = "18")]
+    pub created_at: ::core::option::Option<::prost_types::Timestamp>,
+    #[prost(message, optional, tag = "19")]
+    pub updated_at: ::core::option::Option<::prost_types::Timestamp>,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Trade {
+    #[prost(uint64, tag = "1")]
+    pub trade_id: u64,
+    #[prost(string, tag = "2")]
+    pub buy_order_id: ::prost::alloc::string::String,
+    #[prost(string, tag = "3")]
+    pub sell_order_id: ::prost::alloc::string::String,
+    #[prost(string, optional, tag = "4")]
+    pub maker_order_id: ::core::option::Option<::prost::alloc::string::String>,
+    #[prost(string, optional, tag = "5")]
+    pub taker_order_id: ::core::option::Option<::prost::alloc::string::String>,
+    /// SIDE_UNSPECIFIED when the trade predates aggressor tracking
+    #[prost(enumeration = "Side", tag = "6")]
+    pub aggressor_side: i32,
+    #[prost(string, optional, tag = "7")]
+    pub buyer_user_id: ::core::option::Option<::prost::alloc::string::String>,
+    #[prost(string, optional, tag = "8")]
+    pub seller_user_id: ::core::option::Option<::prost::alloc::string::String>,
+    #[prost(string, tag = "9")]
+    pub price: ::prost::alloc::string::String,
+    #[prost(uint64, tag = "10")]
+    pub quantity: u64,
+    #[prost(message, optional, tag = "11")]
+    pub timestamp: ::core::option::Option<::prost_types::Timestamp>,
+    #[prost(bool, tag = "12")]
+    pub same_firm: bool,
+    #[prost(string, tag = "13")]
+    pub maker_fee: ::prost::alloc::string::String,
+    #[prost(string, tag = "14")]
+    pub taker_fee: ::prost::alloc::string::String,
+    #[prost(string, optional, tag = "15")]
+    pub symbol: ::core::option::Option<::prost::alloc::string::String>,
+    #[prost(uint64, tag = "16")]
+    pub sequence: u64,
+    #[prost(uint32, tag = "17")]
+    pub quantity_scale: u32,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct MarketLevel {
+    #[prost(string, tag = "1")]
+    pub price: ::prost::alloc::string::String,
+    #[prost(uint64, tag = "2")]
+    pub quantity: u64,
+    #[prost(uint64, tag = "3")]
+    pub order_count: u64,
+    #[prost(uint32, tag = "4")]
+    pub quantity_scale: u32,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct MarketDepth {
+    #[prost(message, repeated, tag = "1")]
+    pub bids: ::prost::alloc::vec::Vec<MarketLevel>,
+    #[prost(message, repeated, tag = "2")]
+    pub asks: ::prost::alloc::vec::Vec<MarketLevel>,
+    #[prost(string, optional, tag = "3")]
+    pub spread: ::core::option::Option<::prost::alloc::string::String>,
+    #[prost(uint64, tag = "4")]
+    pub sequence: u64,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SnapshotEnvelope {
+    #[prost(uint32, tag = "1")]
+    pub version: u32,
+    /// The book snapshot as JSON text, in the format of `version`
+    #[prost(string, tag = "2")]
+    pub payload_json: ::prost::alloc::string::String,
+}
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+#[repr(i32)]
+pub enum Side {
+    Unspecified = 0,
+    Buy = 1,
+    Sell = 2,
+}
+impl Side {
+    /// String value of the enum field names used in the ProtoBuf definition.
+    ///
+    /// The values are not transformed in any way and thus are considered stable
+    /// (if the ProtoBuf definition does not change) and safe for programmatic use.
+    pub fn as_str_name(&self) -> &'static str {
+        match self {
+            Self::Unspecified => "SIDE_UNSPECIFIED",
+            Self::Buy => "SIDE_BUY",
+            Self::Sell => "SIDE_SELL",
+        }
+    }
+    /// Creates an enum from field names used in the ProtoBuf definition.
+    pub fn from_str_name(value: &str) -> ::core::option::Option<Self> {
+        match value {
+            "SIDE_UNSPECIFIED" => Some(Self::Unspecified),
+            "SIDE_BUY" => Some(Self::Buy),
+            "SIDE_SELL" => Some(Self::Sell),
+            _ => None,
+        }
+    }
+}
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+#[repr(i32)]
+pub enum OrderStatus {
+    Unspecified = 0,
+    Active = 1,
+    PartiallyFilled = 2,
+    Filled = 3,
+    Cancelled = 4,
+    Expired = 5,
+    PendingActivation = 6,
+}
+impl OrderStatus {
+    /// String value of the enum field names used in the ProtoBuf definition.
+    ///
+    /// The values are not transformed in any way and thus are considered stable
+    /// (if the ProtoBuf definition does not change) and safe for programmatic use.
+    pub fn as_str_name(&self) -> &'static str {
+        match self {
+            Self::Unspecified => "ORDER_STATUS_UNSPECIFIED",
+            Self::Active => "ORDER_STATUS_ACTIVE",
+            Self::PartiallyFilled => "ORDER_STATUS_PARTIALLY_FILLED",
+            Self::Filled => "ORDER_STATUS_FILLED",
+            Self::Cancelled => "ORDER_STATUS_CANCELLED",
+            Self::Expired => "ORDER_STATUS_EXPIRED",
+            Self::PendingActivation => "ORDER_STATUS_PENDING_ACTIVATION",
+        }
+    }
+    /// Creates an enum from field names used in the ProtoBuf definition.
+    pub fn from_str_name(value: &str) -> ::core::option::Option<Self> {
+        match value {
+            "ORDER_STATUS_UNSPECIFIED" => Some(Self::Unspecified),
+            "ORDER_STATUS_ACTIVE" => Some(Self::Active),
+            "ORDER_STATUS_PARTIALLY_FILLED" => Some(Self::PartiallyFilled),
+            "ORDER_STATUS_FILLED" => Some(Self::Filled),
+            "ORDER_STATUS_CANCELLED" => Some(Self::Cancelled),
+            "ORDER_STATUS_EXPIRED" => Some(Self::Expired),
+            "ORDER_STATUS_PENDING_ACTIVATION" => Some(Self::PendingActivation),
+            _ => None,
+        }
+    }
+}
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+#[repr(i32)]
+pub enum TimeInForce {
+    Unspecified = 0,
+    Gtc = 1,
+    Day = 2,
+    Fok = 3,
+    AtTheClose = 4,
+}
+impl TimeInForce {
+    /// String value of the enum field names used in the ProtoBuf definition.
+    ///
+    /// The values are not transformed in any way and thus are considered stable
+    /// (if the ProtoBuf definition does not change) and safe for programmatic use.
+    pub fn as_str_name(&self) -> &'static str {
+        match self {
+            Self::Unspecified => "TIME_IN_FORCE_UNSPECIFIED",
+            Self::Gtc => "TIME_IN_FORCE_GTC",
+            Self::Day => "TIME_IN_FORCE_DAY",
+            Self::Fok => "TIME_IN_FORCE_FOK",
+            Self::AtTheClose => "TIME_IN_FORCE_AT_THE_CLOSE",
+        }
+    }
+    /// Creates an enum from field names used in the ProtoBuf definition.
+    pub fn from_str_name(value: &str) -> ::core::option::Option<Self> {
+        match value {
+            "TIME_IN_FORCE_UNSPECIFIED" => Some(Self::Unspecified),
+            "TIME_IN_FORCE_GTC" => Some(Self::Gtc),
+            "TIME_IN_FORCE_DAY" => Some(Self::Day),
+            "TIME_IN_FORCE_FOK" => Some(Self::Fok),
+            "TIME_IN_FORCE_AT_THE_CLOSE" => Some(Self::AtTheClose),
+            _ => None,
+        }
+    }
+}
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+#[repr(i32)]
+pub enum OrderType {
+    Unspecified = 0,
+    Limit = 1,
+    Market = 2,
+    /// Requires stop_trigger
+    Stop = 3,
+}
+impl OrderType {
+    /// String value of the enum field names used in the ProtoBuf definition.
+    ///
+    /// The values are not transformed in any way and thus are considered stable
+    /// (if the ProtoBuf definition does not change) and safe for programmatic use.
+    pub fn as_str_name(&self) -> &'static str {
+        match self {
+            Self::Unspecified => "ORDER_TYPE_UNSPECIFIED",
+            Self::Limit => "ORDER_TYPE_LIMIT",
+            Self::Market => "ORDER_TYPE_MARKET",
+            Self::Stop => "ORDER_TYPE_STOP",
+        }
+    }
+    /// Creates an enum from field names used in the ProtoBuf definition.
+    pub fn from_str_name(value: &str) -> ::core::option::Option<Self> {
+        match value {
+            "ORDER_TYPE_UNSPECIFIED" => Some(Self::Unspecified),
+            "ORDER_TYPE_LIMIT" => Some(Self::Limit),
+            "ORDER_TYPE_MARKET" => Some(Self::Market),
+            "ORDER_TYPE_STOP" => Some(Self::Stop),
+            _ => None,
+        }
+    }
+}
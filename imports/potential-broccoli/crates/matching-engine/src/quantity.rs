@@ -1,9 +1,18 @@
 //! Quantity handling with validation and arithmetic operations
 
+use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
 use std::ops::{Add, Sub, Mul, Div};
 
+/// Most decimal places a book can count quantities to
+pub const MAX_QUANTITY_SCALE: u32 = 9;
+
 /// Represents a quantity of shares/units in an order
+///
+/// The value counts lots of the book's quantity increment: whole shares by
+/// default, or `10^-scale` of a share in a book with a quantity scale (see
+/// [`from_decimal`](Self::from_decimal)). Matching only ever compares, adds
+/// and subtracts lot counts, so fractional quantities stay exact.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 pub struct Quantity(u64);
 
@@ -31,7 +40,56 @@ impl Quantity {
         self.0
     }
     
+    /// Creates a quantity of lots of `10^-scale` from a decimal amount
+    ///
+    /// # Errors
+    /// Returns error if `value` is not positive, is not a whole number of
+    /// lots at `scale`, does not fit in a lot count, or `scale` is above
+    /// [`MAX_QUANTITY_SCALE`]
+    pub fn from_decimal(value: Decimal, scale: u32) -> crate::Result<Self> {
+        if scale > MAX_QUANTITY_SCALE {
+            return Err(crate::MatchingEngineError::InvalidQuantity(
+                format!("Quantity scale {} is above the maximum of {}", scale, MAX_QUANTITY_SCALE)
+            ));
+        }
+        if value <= Decimal::ZERO {
+            return Err(crate::MatchingEngineError::InvalidQuantity(
+                format!("Quantity must be greater than zero, got: {}", value)
+            ));
+        }
+        let lots = value.checked_mul(Decimal::from(10u64.pow(scale)))
+            .filter(|lots| lots.fract().is_zero())
+            .ok_or_else(|| crate::MatchingEngineError::InvalidQuantity(
+                format!("Quantity {} is not a whole number of lots at {} decimal places", value, scale)
+            ))?;
+        let lots = u64::try_from(lots).map_err(|_| crate::MatchingEngineError::InvalidQuantity(
+            format!("Quantity {} is too large at {} decimal places", value, scale)
+        ))?;
+        Self::new(lots)
+    }
+
+    /// Gets the quantity as a decimal amount, reading the value as lots of `10^-scale`
+    pub fn to_decimal(&self, scale: u32) -> Decimal {
+        Decimal::from_i128_with_scale(i128::from(self.0), scale)
+    }
+
+    /// Rounds the quantity down to a whole multiple of `step`
+    ///
+    /// # Errors
+    /// Returns error if `step` is zero or the quantity is below one step
+    pub fn round_down_to_step(self, step: u64) -> crate::Result<Self> {
+        if step == 0 {
+            return Err(crate::MatchingEngineError::InvalidQuantity(
+                "Quantity step must be greater than zero".to_string()
+            ));
+        }
+        Self::new(self.0 - self.0 % step).map_err(|_| crate::MatchingEngineError::InvalidQuantity(
+            format!("Quantity {} is below one step of {}", self.0, step)
+        ))
+    }
+
     /// Creates quantity from a string
+    #[allow(clippy::should_implement_trait)]
     pub fn from_str(s: &str) -> crate::Result<Self> {
         let value = s.parse::<u64>()
             .map_err(|e| crate::MatchingEngineError::InvalidQuantity(
@@ -187,4 +245,31 @@ mod tests {
         assert_eq!(qty1.min(qty2), qty2);
         assert_eq!(qty1.max(qty2), qty1);
     }
+
+    #[test]
+    fn test_round_down_to_step() {
+        let qty = Quantity::new(250).unwrap();
+        assert_eq!(qty.round_down_to_step(100).unwrap().value(), 200);
+        assert_eq!(qty.round_down_to_step(50).unwrap(), qty);
+        assert_eq!(qty.round_down_to_step(1).unwrap(), qty);
+        assert!(qty.round_down_to_step(300).is_err());
+        assert!(qty.round_down_to_step(0).is_err());
+    }
+
+    #[test]
+    fn test_decimal_quantities() {
+        let decimal = |value: &str| value.parse::<Decimal>().unwrap();
+        let qty = Quantity::from_decimal(decimal("0.125"), 3).unwrap();
+        assert_eq!(qty.value(), 125);
+        assert_eq!(qty.to_decimal(3), decimal("0.125"));
+        assert_eq!(Quantity::from_decimal(decimal("12.5000"), 1).unwrap().value(), 125);
+        assert_eq!(Quantity::from_decimal(decimal("7"), 0).unwrap().to_decimal(0), decimal("7"));
+
+        // Zero and negative amounts are rejected as in `new`, and so is precision past the scale
+        assert!(Quantity::from_decimal(Decimal::ZERO, 3).is_err());
+        assert!(Quantity::from_decimal(decimal("-0.5"), 3).is_err());
+        assert!(Quantity::from_decimal(decimal("0.0005"), 3).is_err());
+        assert!(Quantity::from_decimal(decimal("1"), MAX_QUANTITY_SCALE + 1).is_err());
+        assert!(Quantity::from_decimal(Decimal::from(u64::MAX), 1).is_err());
+    }
 }
\ No newline at end of file
@@ -0,0 +1,260 @@
+//! Sequence-numbered depth deltas and snapshot-based gap recovery
+//!
+//! Every mutation of a book increments its sequence number. With the delta
+//! feed enabled, each mutation emits one [`BookDelta`] carrying that sequence
+//! and the price levels it changed (possibly none), so the delta stream has no
+//! holes. A downstream [`BookMirror`] applies deltas in order; when it detects
+//! a gap it is resynchronised from a [`RecoverySnapshot`], after which deltas
+//! at or below the snapshot's sequence are ignored and later ones apply
+//! normally.
+
+use crate::{
+    order_book::{MarketDepth, MarketLevel},
+    OrderSide, Price, Quantity, Symbol,
+};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// New state of a single price level
+/// 
+/// A quantity of zero means the level was removed.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DepthUpdate {
+    pub side: OrderSide,
+    pub price: Price,
+    pub quantity: Quantity,
+    pub order_count: usize,
+    pub sequence: u64,
+    /// Decimal places `quantity` is counted to (zero on updates that predate the field)
+    #[serde(default)]
+    pub quantity_scale: u32,
+}
+
+/// All level changes produced by one book mutation
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BookDelta {
+    pub sequence: u64,
+    pub updates: Vec<DepthUpdate>,
+}
+
+/// Full depth of a book at an exact sequence number
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecoverySnapshot {
+    pub symbol: Symbol,
+    pub sequence: u64,
+    pub depth: MarketDepth,
+}
+
+/// A delta arrived whose predecessor was never applied
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Gap {
+    /// Sequence the mirror expected next
+    pub expected: u64,
+    /// Sequence that actually arrived
+    pub received: u64,
+}
+
+impl std::fmt::Display for Gap {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "sequence gap: expected {}, received {}", self.expected, self.received)
+    }
+}
+
+impl std::error::Error for Gap {}
+
+/// Client-side replica of a book's aggregated depth
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BookMirror {
+    symbol: Symbol,
+    sequence: u64,
+    bids: BTreeMap<Price, MarketLevel>,
+    asks: BTreeMap<Price, MarketLevel>,
+}
+
+impl BookMirror {
+    /// Creates a mirror of an empty book at sequence zero
+    pub fn new(symbol: Symbol) -> Self {
+        Self {
+            symbol,
+            sequence: 0,
+            bids: BTreeMap::new(),
+            asks: BTreeMap::new(),
+        }
+    }
+    
+    /// Creates a mirror initialised from a snapshot
+    pub fn from_snapshot(snapshot: &RecoverySnapshot) -> Self {
+        let mut mirror = Self::new(snapshot.symbol.clone());
+        mirror.resync(snapshot);
+        mirror
+    }
+    
+    /// Gets the symbol being mirrored
+    pub fn symbol(&self) -> &Symbol {
+        &self.symbol
+    }
+    
+    /// Gets the sequence of the last applied delta or snapshot
+    pub fn sequence(&self) -> u64 {
+        self.sequence
+    }
+    
+    /// Applies the next delta from the feed
+    /// 
+    /// Deltas at or below the current sequence are ignored, so replays and
+    /// deltas older than a resync snapshot are harmless. A delta that skips
+    /// ahead is rejected with the gap and leaves the mirror unchanged.
+    pub fn apply_delta(&mut self, delta: &BookDelta) -> Result<(), Gap> {
+        if delta.sequence <= self.sequence {
+            return Ok(());
+        }
+        if delta.sequence != self.sequence + 1 {
+            return Err(Gap { expected: self.sequence + 1, received: delta.sequence });
+        }
+        
+        for update in &delta.updates {
+            let levels = match update.side {
+                OrderSide::Buy => &mut self.bids,
+                OrderSide::Sell => &mut self.asks,
+            };
+            if update.quantity.value() == 0 {
+                levels.remove(&update.price);
+            } else {
+                levels.insert(update.price, MarketLevel {
+                    price: update.price,
+                    quantity: update.quantity,
+                    order_count: update.order_count,
+                    quantity_scale: update.quantity_scale,
+                });
+            }
+        }
+        self.sequence = delta.sequence;
+        
+        Ok(())
+    }
+    
+    /// Replaces the mirrored state with a snapshot
+    pub fn resync(&mut self, snapshot: &RecoverySnapshot) {
+        self.symbol = snapshot.symbol.clone();
+        self.sequence = snapshot.sequence;
+        self.bids = snapshot.depth.bids.iter().map(|level| (level.price, level.clone())).collect();
+        self.asks = snapshot.depth.asks.iter().map(|level| (level.price, level.clone())).collect();
+    }
+    
+    /// Gets mirrored depth up to the specified number of levels per side
+    pub fn market_depth(&self, levels: usize) -> MarketDepth {
+        let spread = match (self.bids.keys().next_back(), self.asks.keys().next()) {
+            (Some(bid), Some(ask)) => Some(ask.value() - bid.value()),
+            _ => None,
+        };
+        MarketDepth {
+            bids: self.bids.values().rev().take(levels).cloned().collect(),
+            asks: self.asks.values().take(levels).cloned().collect(),
+            spread,
+            sequence: self.sequence,
+        }
+    }
+    
+    /// Gets the mirrored best bid
+    pub fn best_bid(&self) -> Option<Price> {
+        self.bids.keys().next_back().copied()
+    }
+    
+    /// Gets the mirrored best ask
+    pub fn best_ask(&self) -> Option<Price> {
+        self.asks.keys().next().copied()
+    }
+    
+    /// Gets the mirrored level at a price
+    pub fn level(&self, side: OrderSide, price: Price) -> Option<&MarketLevel> {
+        match side {
+            OrderSide::Buy => self.bids.get(&price),
+            OrderSide::Sell => self.asks.get(&price),
+        }
+    }
+    
+    /// Iterates one side's mirrored levels from the touch outward
+    pub fn levels(&self, side: OrderSide) -> Box<dyn Iterator<Item = &MarketLevel> + '_> {
+        match side {
+            OrderSide::Buy => Box::new(self.bids.values().rev()),
+            OrderSide::Sell => Box::new(self.asks.values()),
+        }
+    }
+    
+    /// Gets the mirrored mid price
+    pub fn mid_price(&self) -> Option<Decimal> {
+        Some((self.best_bid()?.value() + self.best_ask()?.value()) / Decimal::TWO)
+    }
+    
+    /// Computes the depth checksum over the top `levels` levels
+    pub fn checksum(&self, levels: usize) -> u32 {
+        self.market_depth(levels).checksum(levels)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn update(side: OrderSide, price_cents: i64, quantity: u64, sequence: u64) -> DepthUpdate {
+        DepthUpdate {
+            side,
+            price: Price::from_cents(price_cents).unwrap(),
+            quantity: Quantity::new_allow_zero(quantity),
+            order_count: if quantity > 0 { 1 } else { 0 },
+            sequence,
+            quantity_scale: 0,
+        }
+    }
+
+    fn delta(sequence: u64, updates: Vec<DepthUpdate>) -> BookDelta {
+        BookDelta { sequence, updates }
+    }
+
+    #[test]
+    fn test_apply_in_order_and_detect_gap() {
+        let mut mirror = BookMirror::new(Symbol::new("MIR".to_string()).unwrap());
+        mirror.apply_delta(&delta(1, vec![update(OrderSide::Buy, 10000, 100, 1)])).unwrap();
+        mirror.apply_delta(&delta(2, vec![update(OrderSide::Sell, 10100, 50, 2)])).unwrap();
+        assert_eq!(mirror.best_bid().unwrap().as_cents(), 10000);
+        assert_eq!(mirror.mid_price(), Some(Decimal::new(10050, 2)));
+        
+        let gap = mirror.apply_delta(&delta(4, vec![update(OrderSide::Buy, 10000, 0, 4)])).unwrap_err();
+        assert_eq!(gap, Gap { expected: 3, received: 4 });
+        assert_eq!(mirror.sequence(), 2);
+        assert!(mirror.best_bid().is_some());
+        
+        // Replayed deltas are ignored
+        mirror.apply_delta(&delta(1, vec![update(OrderSide::Buy, 9000, 100, 1)])).unwrap();
+        assert_eq!(mirror.market_depth(10).bids.len(), 1);
+    }
+
+    #[test]
+    fn test_resync_then_continue() {
+        let mut mirror = BookMirror::new(Symbol::new("MIR".to_string()).unwrap());
+        let snapshot = RecoverySnapshot {
+            symbol: Symbol::new("MIR".to_string()).unwrap(),
+            sequence: 10,
+            depth: MarketDepth {
+                bids: vec![MarketLevel {
+                    price: Price::from_cents(10000).unwrap(),
+                    quantity: Quantity::new(300).unwrap(),
+                    order_count: 2,
+                    quantity_scale: 0,
+                }],
+                asks: vec![],
+                spread: None,
+                sequence: 10,
+            },
+        };
+        
+        mirror.resync(&snapshot);
+        mirror.apply_delta(&delta(9, vec![update(OrderSide::Buy, 10000, 0, 9)])).unwrap();
+        assert_eq!(mirror.market_depth(10), snapshot.depth);
+        
+        mirror.apply_delta(&delta(11, vec![update(OrderSide::Buy, 10000, 0, 11)])).unwrap();
+        assert!(mirror.best_bid().is_none());
+        assert_eq!(mirror.sequence(), 11);
+    }
+}
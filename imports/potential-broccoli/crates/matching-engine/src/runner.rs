@@ -0,0 +1,232 @@
+//! Command-channel runner for async gateways
+//!
+//! [`EngineTask::new`] pairs a task that owns a book with an
+//! [`EngineHandle`] for sending it commands. Spawn [`EngineTask::run`] on
+//! any executor; clones of the handle can then be used from any task.
+//!
+//! Ordering: the task applies commands one at a time in the order they
+//! enter its queue. Commands sent from one handle clone enter in the order
+//! they were sent; commands from different clones interleave. Each reply
+//! carries the op sequence the book assigned, which is the command's
+//! position in the applied order.
+//!
+//! Backpressure: the command queue is bounded. When it is full, sending
+//! waits until the task takes a command off it, so callers are slowed to
+//! the book's pace rather than buffering without limit.
+//!
+//! Market data: the trades of each command, in execution order, then its
+//! depth deltas are broadcast as [`EngineEvent`]s before its reply is sent.
+//! The broadcast keeps the last `event_capacity` events. A subscriber that
+//! falls further behind loses the oldest events it hasn't received and is
+//! told how many by `RecvError::Lagged`; until then it receives every event
+//! in order. Events sent while nobody is subscribed are dropped.
+//!
+//! Shutdown: [`EngineHandle::shutdown`] closes the queue to new commands,
+//! lets the task apply those already queued, and returns a snapshot of the
+//! final book. The task also stops once every handle is dropped. Either way
+//! `run` returns the book.
+
+use crate::{
+    events::ExecutionResult,
+    order_book::{Activation, LimitOrderBook, OpResult, OrderBookOp, SnapshotEnvelope, Trade},
+    recovery::BookDelta,
+    types::OrderId,
+    MatchingEngineError, Order,
+};
+use tokio::sync::{broadcast, mpsc, oneshot};
+
+/// A command for the engine task, with the channel its reply goes back on
+///
+/// Shutdown is sent once per task, so its small size isn't worth boxing every op.
+#[derive(Debug)]
+#[allow(clippy::large_enum_variant)]
+enum OrderCommand {
+    Apply { op: OrderBookOp, reply: oneshot::Sender<CommandReply> },
+    Shutdown { reply: oneshot::Sender<crate::Result<SnapshotEnvelope>> },
+}
+
+/// What applying one command produced, and where it fell in the applied order
+#[derive(Debug, Clone, PartialEq)]
+pub struct CommandReply {
+    /// The book's op sequence once the command was applied
+    pub op_sequence: u64,
+    pub result: OpResult,
+}
+
+/// Market data broadcast by the engine task
+#[derive(Debug, Clone, PartialEq)]
+pub enum EngineEvent {
+    Trade(Trade),
+    Delta(BookDelta),
+}
+
+/// Sends commands to a running [`EngineTask`]
+///
+/// Clones share the task's queue.
+#[derive(Debug, Clone)]
+pub struct EngineHandle {
+    commands: mpsc::Sender<OrderCommand>,
+    /// Weak, so the broadcast closes once the task stops
+    events: broadcast::WeakSender<EngineEvent>,
+}
+
+impl EngineHandle {
+    /// Applies an op to the book, waiting for queue space if it is full
+    ///
+    /// # Errors
+    /// Returns `EngineStopped` if the task has shut down or stopped before
+    /// applying the op. A rejected op is not an error here; it is reported
+    /// in the reply's result.
+    pub async fn apply(&self, op: OrderBookOp) -> crate::Result<CommandReply> {
+        let (reply, receiver) = oneshot::channel();
+        self.send(OrderCommand::Apply { op, reply }).await?;
+        receiver.await.map_err(|_| MatchingEngineError::EngineStopped)
+    }
+
+    /// Submits an order, as `LimitOrderBook::submit_order`
+    pub async fn submit_order(&self, order: Order) -> crate::Result<ExecutionResult> {
+        match self.apply(OrderBookOp::Submit(order)).await?.result {
+            OpResult::Executed(result) => Ok(result),
+            other => Err(unexpected(other)),
+        }
+    }
+
+    /// Cancels a resting order, as `LimitOrderBook::cancel_order`
+    pub async fn cancel_order(&self, order_id: OrderId) -> crate::Result<Order> {
+        match self.apply(OrderBookOp::Cancel(order_id)).await?.result {
+            OpResult::Cancelled(order) => Ok(order),
+            other => Err(unexpected(other)),
+        }
+    }
+
+    /// Subscribes to the trades and depth deltas of commands applied from now on
+    ///
+    /// The receiver reports `Closed` once the task has stopped and every
+    /// event broadcast before then has been received.
+    pub fn subscribe(&self) -> broadcast::Receiver<EngineEvent> {
+        match self.events.upgrade() {
+            Some(events) => events.subscribe(),
+            None => broadcast::channel(1).1,
+        }
+    }
+
+    /// Stops the task once the commands already queued are applied, returning a snapshot of the final book
+    ///
+    /// # Errors
+    /// Returns `EngineStopped` if the task had already stopped, or the
+    /// snapshot's error if the book couldn't be serialized.
+    pub async fn shutdown(&self) -> crate::Result<SnapshotEnvelope> {
+        let (reply, receiver) = oneshot::channel();
+        self.send(OrderCommand::Shutdown { reply }).await?;
+        receiver.await.map_err(|_| MatchingEngineError::EngineStopped)?
+    }
+
+    async fn send(&self, command: OrderCommand) -> crate::Result<()> {
+        self.commands.send(command).await.map_err(|_| MatchingEngineError::EngineStopped)
+    }
+}
+
+/// Owns a book and applies the commands sent through its [`EngineHandle`]s
+#[derive(Debug)]
+pub struct EngineTask {
+    book: LimitOrderBook,
+    commands: mpsc::Receiver<OrderCommand>,
+    events: broadcast::Sender<EngineEvent>,
+}
+
+impl EngineTask {
+    /// Creates a task for a book, queueing up to `command_capacity` commands and keeping `event_capacity` events
+    ///
+    /// The book's delta feed is enabled so its deltas can be broadcast.
+    ///
+    /// # Panics
+    /// Panics if either capacity is zero.
+    pub fn new(mut book: LimitOrderBook, command_capacity: usize, event_capacity: usize) -> (Self, EngineHandle) {
+        let (command_sender, commands) = mpsc::channel(command_capacity);
+        let (events, _) = broadcast::channel(event_capacity);
+        book.enable_delta_feed();
+        let handle = EngineHandle { commands: command_sender, events: events.downgrade() };
+        (Self { book, commands, events }, handle)
+    }
+
+    /// Applies commands until shut down or every handle is dropped, returning the book
+    pub async fn run(mut self) -> LimitOrderBook {
+        let mut shutdown_replies = Vec::new();
+        while let Some(command) = self.commands.recv().await {
+            match command {
+                OrderCommand::Apply { op, reply } => {
+                    let reply_value = self.apply(op);
+                    let _ = reply.send(reply_value);
+                },
+                OrderCommand::Shutdown { reply } => {
+                    // Refuse new commands but drain the queued ones
+                    self.commands.close();
+                    shutdown_replies.push(reply);
+                },
+            }
+        }
+        if !shutdown_replies.is_empty() {
+            let snapshot = self.book.to_snapshot();
+            for reply in shutdown_replies {
+                let _ = reply.send(snapshot.clone());
+            }
+        }
+        self.book
+    }
+
+    fn apply(&mut self, op: OrderBookOp) -> CommandReply {
+        let result = self.book.apply(op);
+        let trades: Vec<&Trade> = match &result {
+            OpResult::Executed(result) => result.trades.iter().collect(),
+            OpResult::Modified(result) => result.trades.iter().collect(),
+            OpResult::Trades(trades) => trades.iter().collect(),
+            OpResult::Activated(activations) => activations.iter().flat_map(Activation::trades).collect(),
+            _ => Vec::new(),
+        };
+        for trade in trades {
+            let _ = self.events.send(EngineEvent::Trade(trade.clone()));
+        }
+        for delta in self.book.take_deltas() {
+            let _ = self.events.send(EngineEvent::Delta(delta));
+        }
+        CommandReply { op_sequence: self.book.op_sequence(), result }
+    }
+}
+
+fn unexpected(result: OpResult) -> MatchingEngineError {
+    match result {
+        OpResult::Rejected(error) => error,
+        other => MatchingEngineError::InvariantViolation(format!("Unexpected op result {other:?}")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::OrderSide;
+    use crate::order_book::test_support::create_test_order;
+
+    #[tokio::test]
+    async fn test_commands_and_stopped_task() {
+        let (task, handle) = EngineTask::new(LimitOrderBook::new("RUN".to_string()).unwrap(), 2, 16);
+        let runner = tokio::spawn(task.run());
+
+        let ask = create_test_order(OrderSide::Sell, 10010, 50);
+        let ask_id = ask.id;
+        assert!(handle.submit_order(ask).await.unwrap().trades.is_empty());
+        let reply = handle.apply(OrderBookOp::Cancel(OrderId::new())).await.unwrap();
+        assert!(matches!(reply.result, OpResult::Rejected(MatchingEngineError::OrderNotFound(_))));
+        assert_eq!(reply.op_sequence, 2);
+        assert!(matches!(handle.cancel_order(OrderId::new()).await, Err(MatchingEngineError::OrderNotFound(_))));
+        assert_eq!(handle.cancel_order(ask_id).await.unwrap().id, ask_id);
+
+        // Any clone can shut the task down; afterwards subscribers see the broadcast closed
+        let late = handle.clone();
+        drop(handle);
+        assert!(late.shutdown().await.is_ok());
+        let book = runner.await.unwrap();
+        assert_eq!(book.op_sequence(), 4);
+        assert!(matches!(late.subscribe().recv().await, Err(broadcast::error::RecvError::Closed)));
+        assert!(matches!(late.shutdown().await, Err(MatchingEngineError::EngineStopped)));
+    }
+}
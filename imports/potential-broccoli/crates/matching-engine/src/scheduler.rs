@@ -0,0 +1,373 @@
+//! Background snapshot files that don't stall matching
+//!
+//! Serializing a large book takes milliseconds, too long to hold up order
+//! entry. A [`SnapshotScheduler`] moves that work to a background thread.
+//! Capturing takes a copy-on-write [`snapshot`] of the live book's levels,
+//! which copies only the levels changed since the last one, and clones the
+//! rest of the book: positions, stops, parked orders, statistics, settings,
+//! and so on. The background thread puts the two back together into a book
+//! identical to the live one at the capture, writes it to a snapshot file,
+//! and prunes old files. A backlog of captures is written once, at the
+//! latest of them.
+//!
+//! Since the file holds the live book's own state, configuration changed
+//! after the scheduler started is in it, and trades keep the timestamps the
+//! live book gave them. The scheduler leaves the book's op log alone, so a
+//! write-ahead log can drain it as usual; listeners, sinks, and feeds are
+//! not captured.
+//!
+//! Files are named `<symbol>-<op sequence>.snapshot`, zero-padded so they
+//! sort by op sequence, and written atomically by
+//! [`LimitOrderBook::save_to_file`].
+//!
+//! [`snapshot`]: LimitOrderBook::snapshot
+
+use crate::{
+    order_book::{CapturedBook, LimitOrderBook, SnapshotFormat},
+    MatchingEngineError,
+};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::mpsc,
+    thread::JoinHandle,
+    time::{Duration, Instant},
+};
+
+/// Where, how often, and how many snapshot files to write
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SnapshotSchedule {
+    /// Directory the files are written to, which must exist
+    pub directory: PathBuf,
+    /// Least time between captures made by `maybe_capture`
+    pub interval: Duration,
+    /// Number of this book's newest files to keep; zero keeps them all
+    pub retain: usize,
+    /// Encoding of each file
+    pub format: SnapshotFormat,
+}
+
+impl SnapshotSchedule {
+    /// Writes JSON snapshots to `directory` at most every `interval`, keeping every file
+    pub fn new(directory: impl Into<PathBuf>, interval: Duration) -> Self {
+        Self { directory: directory.into(), interval, retain: 0, format: SnapshotFormat::Json }
+    }
+}
+
+/// A snapshot file the background thread finished writing
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WrittenSnapshot {
+    pub path: PathBuf,
+    /// Op sequence of the live book when the snapshot was captured
+    pub op_sequence: u64,
+    /// Book sequence number of the snapshot
+    pub sequence: u64,
+}
+
+/// Callbacks from the background thread; every method defaults to a no-op
+pub trait SnapshotObserver: Send {
+    /// A snapshot file was written and old files pruned
+    fn on_snapshot_written(&mut self, _snapshot: &WrittenSnapshot) {}
+
+    /// Rebuilding the captured book, writing its file, or pruning failed
+    ///
+    /// A failed write leaves the previous files in place; the next capture
+    /// tries again.
+    fn on_snapshot_failed(&mut self, _error: &MatchingEngineError) {}
+}
+
+/// Observer for a scheduler that reports to nobody
+struct Unobserved;
+
+impl SnapshotObserver for Unobserved {}
+
+/// Writes snapshot files of a book from a background thread
+///
+/// The matching thread calls [`maybe_capture`](Self::maybe_capture) between
+/// orders; everything slow happens on the background thread.
+#[derive(Debug)]
+pub struct SnapshotScheduler {
+    captures: Option<mpsc::Sender<CapturedBook>>,
+    worker: Option<JoinHandle<Option<WrittenSnapshot>>>,
+    interval: Duration,
+    last_capture: Instant,
+}
+
+impl SnapshotScheduler {
+    /// Starts writing snapshots of `book` on `schedule`, reporting to nobody
+    pub fn start(book: &LimitOrderBook, schedule: SnapshotSchedule) -> crate::Result<Self> {
+        Self::start_observed(book, schedule, Box::new(Unobserved))
+    }
+
+    /// Starts writing snapshots of `book` on `schedule`, reporting each write to `observer`
+    ///
+    /// # Errors
+    /// Returns error if the schedule's directory doesn't exist or the
+    /// background thread couldn't be started.
+    pub fn start_observed(
+        book: &LimitOrderBook,
+        schedule: SnapshotSchedule,
+        observer: Box<dyn SnapshotObserver>,
+    ) -> crate::Result<Self> {
+        if !schedule.directory.is_dir() {
+            return Err(MatchingEngineError::SerializationError(format!(
+                "Snapshot directory {} does not exist", schedule.directory.display()
+            )));
+        }
+
+        let (captures, received) = mpsc::channel();
+        let interval = schedule.interval;
+        let worker = std::thread::Builder::new()
+            .name(format!("snapshots-{}", book.symbol()))
+            .spawn(move || Worker { schedule, observer, last_written: None }.run(received))
+            .map_err(|e| MatchingEngineError::InvariantViolation(format!("Starting snapshot thread: {e}")))?;
+        Ok(Self { captures: Some(captures), worker: Some(worker), interval, last_capture: Instant::now() })
+    }
+
+    /// Captures a snapshot if the schedule's interval has passed since the last capture
+    ///
+    /// Returns whether it captured one.
+    pub fn maybe_capture(&mut self, book: &mut LimitOrderBook) -> bool {
+        if self.last_capture.elapsed() < self.interval {
+            return false;
+        }
+        self.capture(book);
+        true
+    }
+
+    /// Captures the book as it stands and hands it to the background thread to write
+    pub fn capture(&mut self, book: &mut LimitOrderBook) {
+        self.last_capture = Instant::now();
+        if let Some(captures) = &self.captures {
+            let _ = captures.send(book.capture());
+        }
+    }
+
+    /// Waits for the captures already made to be written, then stops
+    ///
+    /// Returns the last snapshot written, if any was.
+    pub fn shutdown(mut self) -> Option<WrittenSnapshot> {
+        self.stop()
+    }
+
+    fn stop(&mut self) -> Option<WrittenSnapshot> {
+        self.captures = None;
+        self.worker.take()?.join().ok().flatten()
+    }
+}
+
+impl Drop for SnapshotScheduler {
+    /// Waits for the captures already made to be written
+    fn drop(&mut self) {
+        let _ = self.stop();
+    }
+}
+
+/// What the background thread does with each capture
+struct Worker {
+    schedule: SnapshotSchedule,
+    observer: Box<dyn SnapshotObserver>,
+    last_written: Option<WrittenSnapshot>,
+}
+
+impl Worker {
+    fn run(mut self, captures: mpsc::Receiver<CapturedBook>) -> Option<WrittenSnapshot> {
+        while let Ok(captured) = captures.recv() {
+            // Only the latest of the captures already waiting is written
+            let latest = captures.try_iter().last().unwrap_or(captured);
+            match self.write(latest) {
+                Ok(written) => {
+                    self.observer.on_snapshot_written(&written);
+                    self.last_written = Some(written);
+                },
+                Err(error) => self.observer.on_snapshot_failed(&error),
+            }
+        }
+        self.last_written
+    }
+
+    /// Writes the captured book's file, then removes the oldest beyond the retention
+    fn write(&self, captured: CapturedBook) -> crate::Result<WrittenSnapshot> {
+        let op_sequence = captured.op_sequence();
+        let book = captured.into_book()?;
+        let symbol = book.symbol().to_string();
+        let path = self.schedule.directory.join(format!("{symbol}-{op_sequence:020}.snapshot"));
+        book.save_to_file(&path, self.schedule.format)?;
+        if self.schedule.retain > 0 {
+            prune(&self.schedule.directory, &symbol, self.schedule.retain)?;
+        }
+        Ok(WrittenSnapshot { path, op_sequence, sequence: book.sequence() })
+    }
+}
+
+/// Removes all but the newest `retain` snapshot files of `symbol` in `directory`
+fn prune(directory: &Path, symbol: &str, retain: usize) -> crate::Result<()> {
+    let error = |e: std::io::Error| MatchingEngineError::SerializationError(format!("Pruning {}: {e}", directory.display()));
+    let prefix = format!("{symbol}-");
+    let mut files: Vec<PathBuf> = fs::read_dir(directory)
+        .map_err(error)?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.file_name().and_then(|name| name.to_str()).is_some_and(|name| {
+            name.strip_prefix(&prefix)
+                .and_then(|rest| rest.strip_suffix(".snapshot"))
+                .is_some_and(|sequence| sequence.len() == 20 && sequence.bytes().all(|byte| byte.is_ascii_digit()))
+        }))
+        .collect();
+    files.sort();
+    let excess = files.len().saturating_sub(retain);
+    for path in &files[..excess] {
+        fs::remove_file(path).map_err(error)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{types::{OrderId, UserId}, Clock, FeeSchedule, OrderSide, PositionLimit};
+    use chrono::{TimeZone, Utc};
+    use rust_decimal::Decimal;
+    use crate::order_book::test_support::create_test_order;
+
+    /// An empty directory of the test's own under the system temp directory
+    struct TestDir(PathBuf);
+
+    impl TestDir {
+        fn new() -> Self {
+            let path = std::env::temp_dir().join(format!("snapshot-scheduler-{}", OrderId::new()));
+            fs::create_dir(&path).unwrap();
+            Self(path)
+        }
+
+        fn files(&self) -> Vec<PathBuf> {
+            let mut files: Vec<_> = fs::read_dir(&self.0).unwrap().map(|entry| entry.unwrap().path()).collect();
+            files.sort();
+            files
+        }
+    }
+
+    impl Drop for TestDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    /// Forwards every report to the test thread
+    struct Reports(mpsc::Sender<crate::Result<WrittenSnapshot>>);
+
+    impl SnapshotObserver for Reports {
+        fn on_snapshot_written(&mut self, snapshot: &WrittenSnapshot) {
+            let _ = self.0.send(Ok(snapshot.clone()));
+        }
+
+        fn on_snapshot_failed(&mut self, error: &MatchingEngineError) {
+            let _ = self.0.send(Err(error.clone()));
+        }
+    }
+
+    fn burst(book: &mut LimitOrderBook, orders: i64) {
+        for i in 0..orders {
+            let drift = (i / 50) % 20;
+            let side = if i % 2 == 0 { OrderSide::Buy } else { OrderSide::Sell };
+            let order = create_test_order(side, 10000 + drift + (i * 7) % 11 - 5, 1 + (i % 13) as u64);
+            let order_id = order.id;
+            book.add_order(order).unwrap();
+            if i % 3 == 0 {
+                let _ = book.cancel_order(order_id);
+            }
+        }
+    }
+
+    #[test]
+    fn test_snapshots_taken_mid_burst_restore_valid_books() {
+        let dir = TestDir::new();
+        let clock = Clock::Fixed(Utc.with_ymd_and_hms(2024, 3, 1, 14, 30, 0).unwrap());
+        let mut book = LimitOrderBook::with_clock("SCHED".to_string(), clock).unwrap();
+        book.enable_op_log();
+        burst(&mut book, 500);
+        let schedule = SnapshotSchedule { retain: 3, ..SnapshotSchedule::new(&dir.0, Duration::ZERO) };
+        let (reports, received) = mpsc::channel();
+        let mut scheduler = SnapshotScheduler::start_observed(&book, schedule, Box::new(Reports(reports))).unwrap();
+
+        // Capture every 1000 orders while the burst runs
+        for _ in 0..10 {
+            burst(&mut book, 1000);
+            assert!(scheduler.maybe_capture(&mut book));
+        }
+        let last = scheduler.shutdown().unwrap();
+        assert_eq!(last.op_sequence, book.op_sequence());
+
+        // Capturing leaves the op log whole for its owner
+        assert_eq!(LimitOrderBook::replay(book.take_ops()).unwrap().state_hash(), book.state_hash());
+
+        let written: Vec<WrittenSnapshot> = received.try_iter().map(Result::unwrap).collect();
+        assert_eq!(written.last(), Some(&last));
+        assert!(written.windows(2).all(|pair| pair[0].op_sequence < pair[1].op_sequence));
+
+        // Only the newest three files are kept, and each restores a sound book no newer than the live one
+        let files = dir.files();
+        assert_eq!(files.len(), written.len().min(3));
+        assert_eq!(files.last(), Some(&last.path));
+        for (path, snapshot) in files.iter().zip(&written[written.len() - files.len()..]) {
+            let restored = LimitOrderBook::load_from_file(path).unwrap();
+            assert_eq!(restored.verify_integrity(), Ok(()));
+            assert_eq!((restored.op_sequence(), restored.sequence()), (snapshot.op_sequence, snapshot.sequence));
+            assert!(restored.sequence() <= book.sequence());
+        }
+        let latest = LimitOrderBook::load_from_file(files.last().unwrap()).unwrap();
+        assert_eq!(latest.state_hash(), book.state_hash());
+    }
+
+    #[test]
+    fn test_snapshot_is_the_live_state() {
+        let dir = TestDir::new();
+        let mut book = LimitOrderBook::new("SCHED".to_string()).unwrap();
+        burst(&mut book, 200);
+        let mut scheduler = SnapshotScheduler::start(&book, SnapshotSchedule::new(&dir.0, Duration::ZERO)).unwrap();
+
+        // Settings changed after the start are captured, and trades keep the live book's timestamps
+        book.set_max_recent_trades(5);
+        book.set_fee_schedule(Some(FeeSchedule::new(Decimal::ONE, Decimal::TWO)));
+        book.set_position_limit(UserId::new("test_user".to_string()), Some(PositionLimit::new(1_000_000, 1_000_000)));
+        book.add_order(create_test_order(OrderSide::Buy, 9000, 5).with_expiry(Utc::now() + chrono::Duration::hours(1))).unwrap();
+        std::thread::sleep(Duration::from_millis(5));
+        burst(&mut book, 200);
+        scheduler.capture(&mut book);
+        let last = scheduler.shutdown().unwrap();
+
+        let restored = LimitOrderBook::load_from_file(&last.path).unwrap();
+        assert_eq!(restored.to_json().unwrap(), book.to_json().unwrap());
+        assert_eq!(restored.next_expiry(), book.next_expiry());
+    }
+
+    #[test]
+    fn test_interval_and_write_failures() {
+        let missing = std::env::temp_dir().join(format!("snapshot-scheduler-{}", OrderId::new()));
+        let mut book = LimitOrderBook::new("SCHED".to_string()).unwrap();
+        assert!(SnapshotScheduler::start(&book, SnapshotSchedule::new(&missing, Duration::ZERO)).is_err());
+
+        let dir = TestDir::new();
+        let (reports, received) = mpsc::channel();
+        let schedule = SnapshotSchedule::new(&dir.0, Duration::from_secs(3600));
+        let mut scheduler = SnapshotScheduler::start_observed(&book, schedule, Box::new(Reports(reports))).unwrap();
+        burst(&mut book, 100);
+        assert!(!scheduler.maybe_capture(&mut book));
+        scheduler.capture(&mut book);
+        assert!(!scheduler.maybe_capture(&mut book));
+
+        // A write that fails is reported, and the next capture writes again
+        let first = received.recv().unwrap().unwrap();
+        fs::remove_dir_all(&dir.0).unwrap();
+        burst(&mut book, 10);
+        scheduler.capture(&mut book);
+        assert!(matches!(received.recv().unwrap(), Err(MatchingEngineError::SerializationError(_))));
+        fs::create_dir(&dir.0).unwrap();
+        scheduler.capture(&mut book);
+        let recovered = received.recv().unwrap().unwrap();
+        assert!(recovered.op_sequence > first.op_sequence);
+        assert_eq!(dir.files(), vec![recovered.path]);
+
+        drop(scheduler);
+        assert!(received.recv().is_err());
+    }
+}
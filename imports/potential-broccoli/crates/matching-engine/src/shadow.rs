@@ -0,0 +1,483 @@
+//! Paper trading against a live book's market data
+//!
+//! A [`ShadowBook`] holds paper orders for one symbol and decides which of
+//! them would have filled, driven only by the real book's delta feed and
+//! trade prints. It never touches the real book, and paper orders never
+//! affect each other: each one sees the full real volume.
+//!
+//! Fill model:
+//! - An order that crosses the mirrored opposite side when placed fills
+//!   immediately against the displayed levels it crosses, at their prices.
+//! - A resting order records the real quantity displayed at its price when
+//!   it was queued. Prints at its price consume that queue first; only the
+//!   volume beyond it fills the paper order. When the level shrinks below the
+//!   remaining queue (cancellations ahead), the queue shrinks with it.
+//! - Prints strictly through its price fill it at its own price, up to the
+//!   printed quantity.
+//!
+//! Good-till-date paper orders expire as on the real book: once `expires_at`
+//! is at or before the current time they stop filling, and they stay until
+//! [`purge_expired`](ShadowBook::purge_expired) sweeps them out. Placement
+//! reads the time from the shadow's [`Clock`]; a print is judged at its own
+//! timestamp, the real book's time when it traded.
+//!
+//! For each real mutation, feed its trades to [`on_trade`](ShadowBook::on_trade)
+//! before its delta to [`on_delta`](ShadowBook::on_delta). The same stream
+//! always produces the same fills.
+
+use crate::{
+    order_book::{Clock, Trade},
+    recovery::{BookDelta, BookMirror, Gap, RecoverySnapshot},
+    types::{OrderId, Symbol},
+    MatchingEngineError, Order, OrderSide, Price, Quantity, TimeInForce,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Simulated execution of a paper order
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PaperFill {
+    pub order_id: OrderId,
+    pub price: Price,
+    pub quantity: Quantity,
+    /// Paper order quantity left after this fill
+    pub remaining_quantity: Quantity,
+    /// Real book sequence of the event that produced the fill
+    pub sequence: u64,
+}
+
+/// A resting paper order and the real quantity queued ahead of it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct PaperOrder {
+    order: Order,
+    queue_ahead: u64,
+}
+
+/// Paper orders filled against a real book's event stream
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ShadowBook {
+    mirror: BookMirror,
+    /// Resting paper orders in placement order
+    orders: Vec<PaperOrder>,
+    /// Time source for placements and amendments
+    #[serde(default)]
+    clock: Clock,
+}
+
+impl ShadowBook {
+    /// Creates a shadow of an empty book at sequence zero
+    pub fn new(symbol: Symbol) -> Self {
+        Self { mirror: BookMirror::new(symbol), orders: Vec::new(), clock: Clock::System }
+    }
+    
+    /// Creates a shadow starting from a snapshot of the real book
+    pub fn from_snapshot(snapshot: &RecoverySnapshot) -> Self {
+        Self { mirror: BookMirror::from_snapshot(snapshot), orders: Vec::new(), clock: Clock::System }
+    }
+    
+    /// Gets the clock placements and amendments read the time from
+    pub fn clock(&self) -> Clock {
+        self.clock
+    }
+    
+    /// Sets the clock placements and amendments read the time from
+    pub fn set_clock(&mut self, clock: Clock) {
+        self.clock = clock;
+    }
+    
+    /// Gets the mirrored real depth the shadow is working from
+    pub fn mirror(&self) -> &BookMirror {
+        &self.mirror
+    }
+    
+    /// Gets a resting paper order
+    pub fn get_order(&self, order_id: OrderId) -> Option<&Order> {
+        self.find(order_id).map(|index| &self.orders[index].order)
+    }
+    
+    /// Gets the real quantity still queued ahead of a resting paper order
+    pub fn queue_ahead(&self, order_id: OrderId) -> Option<u64> {
+        self.find(order_id).map(|index| self.orders[index].queue_ahead)
+    }
+    
+    /// Gets the number of resting paper orders
+    pub fn order_count(&self) -> usize {
+        self.orders.len()
+    }
+    
+    /// Places a paper order, filling it at once if it crosses the real book
+    ///
+    /// As on the real book, the unfilled remainder of a market order is
+    /// dropped rather than rested, a FOK order that the displayed depth
+    /// cannot fill in full is cancelled without fills, and an order already
+    /// past its expiry is expired without fills.
+    pub fn place_order(&mut self, mut order: Order) -> crate::Result<Vec<PaperFill>> {
+        if self.find(order.id).is_some() {
+            return Err(MatchingEngineError::InvariantViolation(format!(
+                "paper order {} already exists", order.id
+            )));
+        }
+        let now = self.clock.now();
+        if order.is_expired_at(now) {
+            order.expire_at(now);
+            return Ok(Vec::new());
+        }
+        if order.time_in_force == TimeInForce::FOK && !self.displayed_covers(&order) {
+            order.cancel();
+            return Ok(Vec::new());
+        }
+        let fills = self.take_liquidity(&mut order)?;
+        self.enqueue(order);
+        Ok(fills)
+    }
+    
+    /// Cancels a resting paper order
+    pub fn cancel_order(&mut self, order_id: OrderId) -> crate::Result<Order> {
+        let index = self.find(order_id)
+            .ok_or_else(|| MatchingEngineError::OrderNotFound(order_id.to_string()))?;
+        let mut order = self.orders.remove(index).order;
+        order.cancel();
+        Ok(order)
+    }
+    
+    /// Modifies a resting paper order with the same semantics as
+    /// [`LimitOrderBook::modify_order`](crate::LimitOrderBook::modify_order)
+    ///
+    /// Reducing the size in place keeps the order's queue position; a price
+    /// change or size increase requeues it behind the real quantity then
+    /// displayed at its new price, and may fill it at once unless it has
+    /// expired.
+    pub fn modify_order(
+        &mut self,
+        order_id: OrderId,
+        new_price: Option<Price>,
+        new_quantity: Option<Quantity>,
+    ) -> crate::Result<Vec<PaperFill>> {
+        let index = self.find(order_id)
+            .ok_or_else(|| MatchingEngineError::OrderNotFound(order_id.to_string()))?;
+        let previous = &self.orders[index].order;
+        let new_price = new_price.unwrap_or(previous.price);
+        let new_quantity = new_quantity.unwrap_or(previous.original_quantity);
+        let filled = previous.filled_quantity().value();
+        if new_quantity.value() <= filled {
+            return Err(MatchingEngineError::InvalidQuantity(format!(
+                "new quantity {} does not exceed the {} already filled", new_quantity, filled
+            )));
+        }
+        
+        if new_price != previous.price || new_quantity > previous.original_quantity {
+            let mut order = self.orders.remove(index).order;
+            order.price = new_price;
+            order.original_quantity = new_quantity;
+            order.remaining_quantity = Quantity::new_allow_zero(new_quantity.value() - filled);
+            let fills = if order.is_expired_at(self.clock.now()) {
+                Vec::new()
+            } else {
+                self.take_liquidity(&mut order)?
+            };
+            self.enqueue(order);
+            Ok(fills)
+        } else {
+            let reduction = previous.original_quantity.value() - new_quantity.value();
+            self.orders[index].order.reduce(Quantity::new_allow_zero(reduction))?;
+            Ok(Vec::new())
+        }
+    }
+    
+    /// Applies a real trade print, returning the paper fills it causes
+    ///
+    /// Paper orders expired at the print's timestamp don't fill.
+    pub fn on_trade(&mut self, trade: &Trade) -> crate::Result<Vec<PaperFill>> {
+        let mut fills = Vec::new();
+        for paper in &mut self.orders {
+            let order = &mut paper.order;
+            if order.is_expired_at(trade.timestamp) {
+                continue;
+            }
+            let through = match order.side {
+                OrderSide::Buy => trade.price < order.price,
+                OrderSide::Sell => trade.price > order.price,
+            };
+            let available = if through {
+                trade.quantity.value()
+            } else if trade.price == order.price {
+                let consumed = paper.queue_ahead.min(trade.quantity.value());
+                paper.queue_ahead -= consumed;
+                trade.quantity.value() - consumed
+            } else {
+                0
+            };
+            
+            let quantity = available.min(order.remaining_quantity.value());
+            if quantity > 0 {
+                let quantity = Quantity::new(quantity)?;
+                order.fill(quantity)?;
+                fills.push(PaperFill {
+                    order_id: order.id,
+                    price: order.price,
+                    quantity,
+                    remaining_quantity: order.remaining_quantity,
+                    sequence: trade.sequence,
+                });
+            }
+        }
+        self.orders.retain(|paper| paper.order.is_active());
+        Ok(fills)
+    }
+    
+    /// Applies the next real depth delta
+    ///
+    /// A gap leaves the shadow unchanged; resynchronise with
+    /// [`resync`](Self::resync) before continuing.
+    pub fn on_delta(&mut self, delta: &BookDelta) -> Result<(), Gap> {
+        self.mirror.apply_delta(delta)?;
+        for update in &delta.updates {
+            for paper in &mut self.orders {
+                if paper.order.side == update.side && paper.order.price == update.price {
+                    paper.queue_ahead = paper.queue_ahead.min(update.quantity.value());
+                }
+            }
+        }
+        Ok(())
+    }
+    
+    /// Resynchronises the mirrored depth from a snapshot after a gap
+    ///
+    /// Queue positions can only shrink to the snapshot's level sizes, since
+    /// what happened during the gap is unknown.
+    pub fn resync(&mut self, snapshot: &RecoverySnapshot) {
+        self.mirror.resync(snapshot);
+        for paper in &mut self.orders {
+            let displayed = self.mirror.level(paper.order.side, paper.order.price)
+                .map_or(0, |level| level.quantity.value());
+            paper.queue_ahead = paper.queue_ahead.min(displayed);
+        }
+    }
+    
+    /// Removes every resting paper order whose expiry is at or before `now`
+    ///
+    /// Returns the removed orders marked `Expired`, earliest expiry first.
+    pub fn purge_expired(&mut self, now: DateTime<Utc>) -> Vec<Order> {
+        let mut expired = Vec::new();
+        self.orders.retain_mut(|paper| {
+            if !paper.order.is_expired_at(now) {
+                return true;
+            }
+            paper.order.expire_at(now);
+            expired.push(paper.order.clone());
+            false
+        });
+        expired.sort_by_key(|order| order.expires_at);
+        expired
+    }
+    
+    /// Expires every resting Day paper order at the end of the session
+    pub fn roll_session(&mut self) -> Vec<Order> {
+        let mut expired = Vec::new();
+        self.orders.retain_mut(|paper| {
+            if paper.order.time_in_force != TimeInForce::Day {
+                return true;
+            }
+            paper.order.expire();
+            expired.push(paper.order.clone());
+            false
+        });
+        expired
+    }
+    
+    /// Fills an order against the mirrored opposite levels it crosses
+    fn take_liquidity(&self, order: &mut Order) -> crate::Result<Vec<PaperFill>> {
+        let mut fills = Vec::new();
+        for level in self.mirror.levels(order.side.opposite()) {
+            let crosses = match order.side {
+                OrderSide::Buy => level.price <= order.price,
+                OrderSide::Sell => level.price >= order.price,
+            };
+            if !crosses || !order.is_active() {
+                break;
+            }
+            let quantity = level.quantity.min(order.remaining_quantity);
+            order.fill(quantity)?;
+            fills.push(PaperFill {
+                order_id: order.id,
+                price: level.price,
+                quantity,
+                remaining_quantity: order.remaining_quantity,
+                sequence: self.mirror.sequence(),
+            });
+        }
+        Ok(fills)
+    }
+    
+    /// Checks if the mirrored opposite levels crossing the order cover all of it
+    fn displayed_covers(&self, order: &Order) -> bool {
+        let crossing: u64 = self.mirror.levels(order.side.opposite())
+            .take_while(|level| match order.side {
+                OrderSide::Buy => level.price <= order.price,
+                OrderSide::Sell => level.price >= order.price,
+            })
+            .map(|level| level.quantity.value())
+            .sum();
+        crossing >= order.remaining_quantity.value()
+    }
+    
+    /// Rests the unfilled part of an order behind the real quantity at its price
+    fn enqueue(&mut self, order: Order) {
+        if !order.is_active() || !order.can_rest() {
+            return;
+        }
+        let queue_ahead = self.mirror.level(order.side, order.price)
+            .map_or(0, |level| level.quantity.value());
+        self.orders.push(PaperOrder { order, queue_ahead });
+    }
+    
+    fn find(&self, order_id: OrderId) -> Option<usize> {
+        self.orders.iter().position(|paper| paper.order.id == order_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{LimitOrderBook, OrderStatus};
+    use crate::order_book::test_support::create_order;
+
+    /// Real book with 300 bid at 99.00 (200 then 100) and 100 offered at
+    /// 101.00, shadowed from a snapshot; returns the ID of the 200 lot
+    fn setup() -> (LimitOrderBook, ShadowBook, OrderId) {
+        let mut book = LimitOrderBook::new("PAPER".to_string()).unwrap();
+        let first = create_order(OrderSide::Buy, 9900, 200, "mm1");
+        let first_id = first.id;
+        book.add_order(first).unwrap();
+        book.add_order(create_order(OrderSide::Buy, 9900, 100, "mm2")).unwrap();
+        book.add_order(create_order(OrderSide::Sell, 10100, 100, "mm1")).unwrap();
+        book.enable_delta_feed();
+        let shadow = ShadowBook::from_snapshot(&book.recovery_snapshot());
+        (book, shadow, first_id)
+    }
+
+    /// Feeds the real book's new trades and deltas to the shadow
+    fn sync(book: &mut LimitOrderBook, shadow: &mut ShadowBook, trades: &[Trade]) -> Vec<PaperFill> {
+        let mut fills = Vec::new();
+        for trade in trades {
+            fills.extend(shadow.on_trade(trade).unwrap());
+        }
+        for delta in book.take_deltas() {
+            shadow.on_delta(&delta).unwrap();
+        }
+        fills
+    }
+
+    fn real(book: &mut LimitOrderBook, shadow: &mut ShadowBook, order: Order) -> Vec<PaperFill> {
+        let trades = book.add_order(order).unwrap();
+        sync(book, shadow, &trades)
+    }
+
+    #[test]
+    fn test_touch_bid_fills_after_queue_ahead_trades() {
+        let (mut book, mut shadow, _) = setup();
+        let paper = create_order(OrderSide::Buy, 9900, 50, "demo");
+        let paper_id = paper.id;
+        assert!(shadow.place_order(paper).unwrap().is_empty());
+        assert_eq!(shadow.queue_ahead(paper_id), Some(300));
+        
+        // Real interest joining later queues behind the paper order
+        real(&mut book, &mut shadow, create_order(OrderSide::Buy, 9900, 100, "late"));
+        assert_eq!(shadow.queue_ahead(paper_id), Some(300));
+        
+        // 250 prints at 99.00: still 50 real quantity ahead
+        assert!(real(&mut book, &mut shadow, create_order(OrderSide::Sell, 9900, 250, "taker")).is_empty());
+        assert_eq!(shadow.queue_ahead(paper_id), Some(50));
+        
+        // The next 80 exhaust the queue; 30 would have reached the paper order
+        let fills = real(&mut book, &mut shadow, create_order(OrderSide::Sell, 9900, 80, "taker"));
+        assert_eq!(fills.len(), 1);
+        assert_eq!(fills[0].quantity.value(), 30);
+        assert_eq!(fills[0].remaining_quantity.value(), 20);
+        assert_eq!(shadow.get_order(paper_id).unwrap().status, OrderStatus::PartiallyFilled);
+        
+        // The real book never saw the paper order: the late bid took those 30
+        assert!(book.get_order(paper_id).is_none());
+        assert_eq!(book.best_bid_quantity().unwrap().value(), 70);
+    }
+
+    #[test]
+    fn test_cancellations_ahead_and_prints_through() {
+        let (mut book, mut shadow, first_id) = setup();
+        let paper = create_order(OrderSide::Buy, 9900, 150, "demo");
+        let paper_id = paper.id;
+        shadow.place_order(paper).unwrap();
+        real(&mut book, &mut shadow, create_order(OrderSide::Buy, 9800, 500, "mm3"));
+        
+        // Cancelling the 200 lot shrinks the queue to what remains displayed
+        book.cancel_order(first_id).unwrap();
+        sync(&mut book, &mut shadow, &[]);
+        assert_eq!(shadow.queue_ahead(paper_id), Some(100));
+        
+        // A sweep into 98.00 exhausts the queue, then prints through the paper price
+        let fills = real(&mut book, &mut shadow, create_order(OrderSide::Sell, 9800, 200, "taker"));
+        assert_eq!(fills.iter().map(|fill| fill.quantity.value()).sum::<u64>(), 100);
+        assert!(fills.iter().all(|fill| fill.price.as_cents() == 9900));
+        assert_eq!(shadow.get_order(paper_id).unwrap().remaining_quantity.value(), 50);
+        
+        let fills = real(&mut book, &mut shadow, create_order(OrderSide::Sell, 9800, 100, "taker"));
+        assert_eq!(fills[0].quantity.value(), 50);
+        assert!(shadow.get_order(paper_id).is_none());
+    }
+
+    #[test]
+    fn test_marketable_placement_modify_and_day_expiry() {
+        let (_, mut shadow, _) = setup();
+        
+        // Crosses the 100 offered at 101.00 and rests the remainder at 102.00
+        let paper = create_order(OrderSide::Buy, 10200, 150, "demo").with_time_in_force(TimeInForce::Day);
+        let paper_id = paper.id;
+        let fills = shadow.place_order(paper).unwrap();
+        assert_eq!(fills.len(), 1);
+        assert_eq!((fills[0].price.as_cents(), fills[0].quantity.value()), (10100, 100));
+        assert_eq!(shadow.queue_ahead(paper_id), Some(0));
+        
+        // Requeue at 99.00 behind the real bids, then cut the remaining 50 to 20
+        assert!(shadow.modify_order(paper_id, Some(Price::from_cents(9900).unwrap()), None).unwrap().is_empty());
+        assert_eq!(shadow.queue_ahead(paper_id), Some(300));
+        shadow.modify_order(paper_id, None, Some(Quantity::new(120).unwrap())).unwrap();
+        assert_eq!(shadow.get_order(paper_id).unwrap().remaining_quantity.value(), 20);
+        assert_eq!(shadow.queue_ahead(paper_id), Some(300));
+        
+        let expired = shadow.roll_session();
+        assert_eq!(expired.len(), 1);
+        assert_eq!(expired[0].status, OrderStatus::Expired);
+        assert_eq!(shadow.order_count(), 0);
+    }
+
+    #[test]
+    fn test_good_till_date_orders_expire_on_the_book_clock() {
+        let start = chrono::Utc::now();
+        let mut book = LimitOrderBook::with_clock("PAPER".to_string(), Clock::Fixed(start)).unwrap();
+        book.enable_delta_feed();
+        let mut shadow = ShadowBook::from_snapshot(&book.recovery_snapshot());
+        shadow.set_clock(Clock::Fixed(start));
+        let expires_at = start + chrono::Duration::minutes(1);
+        
+        // Already expired when placed: dropped without resting
+        let stale = create_order(OrderSide::Buy, 9900, 10, "demo").with_expiry(start);
+        assert!(shadow.place_order(stale).unwrap().is_empty());
+        assert_eq!(shadow.order_count(), 0);
+        
+        let paper = create_order(OrderSide::Buy, 9900, 50, "demo").with_expiry(expires_at);
+        let paper_id = paper.id;
+        shadow.place_order(paper).unwrap();
+        real(&mut book, &mut shadow, create_order(OrderSide::Buy, 9900, 100, "mm"));
+        let fills = real(&mut book, &mut shadow, create_order(OrderSide::Sell, 9800, 20, "taker"));
+        assert_eq!(fills[0].quantity.value(), 20);
+        
+        // A print stamped at the expiry no longer fills it
+        book.set_clock(Clock::Fixed(expires_at));
+        assert!(real(&mut book, &mut shadow, create_order(OrderSide::Sell, 9800, 30, "taker")).is_empty());
+        
+        let expired = shadow.purge_expired(expires_at);
+        assert_eq!((expired[0].id, expired[0].status), (paper_id, OrderStatus::Expired));
+        assert_eq!(expired[0].remaining_quantity.value(), 30);
+        assert_eq!(shadow.order_count(), 0);
+    }
+}
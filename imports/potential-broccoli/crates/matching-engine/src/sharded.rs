@@ -0,0 +1,323 @@
+//! Multi-symbol engine partitioned across worker threads
+//!
+//! [`ShardedEngine::new`] starts one worker thread per shard. Each symbol
+//! belongs to the shard its hash picks, and that shard's worker owns the
+//! symbol's book outright: no book is ever touched by two threads, so
+//! matching takes no locks. Commands reach a worker through its own bounded
+//! queue; when the queue is full, submitting blocks until the worker takes a
+//! command off it.
+//!
+//! Ordering: a worker applies its queue one command at a time, in the order
+//! the commands entered it. Commands for one symbol always enter the same
+//! queue, so commands submitted one after another for a symbol are applied
+//! in that order. Commands for different symbols may interleave, and
+//! commands submitted at the same moment from different threads enter in
+//! whichever order their sends land.
+//!
+//! Cross-book contingencies (OCO links, baskets) need books on one thread
+//! and stay with [`MultiBookEngine`](crate::MultiBookEngine).
+
+use crate::{
+    order_book::{LimitOrderBook, OpResult, OrderBookOp},
+    types::Symbol,
+    MatchingEngineError,
+};
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap},
+    hash::{Hash, Hasher},
+    sync::{
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+        mpsc, Arc,
+    },
+    thread::JoinHandle,
+    time::Instant,
+};
+
+/// Commands each shard queues before submitting blocks
+const SHARD_QUEUE_CAPACITY: usize = 1024;
+
+/// A message for a shard's worker
+///
+/// Applying ops is the traffic, so they aren't boxed to shrink the rare variants.
+#[allow(clippy::large_enum_variant)]
+enum ShardMessage {
+    Apply { symbol: Symbol, op: OrderBookOp, reply: mpsc::Sender<OpResult> },
+    AddBook { book: Box<LimitOrderBook>, reply: mpsc::Sender<crate::Result<()>> },
+    Stop,
+}
+
+/// Counters a shard's worker keeps for [`ShardedHandle::stats`]
+#[derive(Debug, Default)]
+struct ShardCounters {
+    queued: AtomicUsize,
+    applied: AtomicU64,
+    symbols: AtomicUsize,
+}
+
+/// A shard's queue and counters, as seen by handles
+#[derive(Debug, Clone)]
+struct ShardLink {
+    queue: mpsc::SyncSender<ShardMessage>,
+    counters: Arc<ShardCounters>,
+}
+
+/// Load on one shard
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ShardStats {
+    /// Index of the shard
+    pub shard: usize,
+    /// Number of books the shard owns
+    pub symbols: usize,
+    /// Commands submitted but not yet taken by the worker
+    pub queue_depth: usize,
+    /// Commands applied since the engine started
+    pub ops_applied: u64,
+    /// Commands applied per second, averaged since the engine started
+    pub ops_per_sec: f64,
+}
+
+/// A command's result, once its shard has applied it
+#[derive(Debug)]
+pub struct PendingResult {
+    reply: mpsc::Receiver<OpResult>,
+}
+
+impl PendingResult {
+    /// Waits for the result
+    ///
+    /// # Errors
+    /// Returns `EngineStopped` if the engine shut down before applying the command.
+    pub fn wait(self) -> crate::Result<OpResult> {
+        self.reply.recv().map_err(|_| MatchingEngineError::EngineStopped)
+    }
+
+    /// Gets the result if the shard has already applied the command
+    pub fn try_wait(&self) -> Option<OpResult> {
+        self.reply.try_recv().ok()
+    }
+}
+
+/// Thread-safe handle for submitting commands to a [`ShardedEngine`]
+///
+/// Clones share the engine's shards.
+#[derive(Debug, Clone)]
+pub struct ShardedHandle {
+    shards: Arc<[ShardLink]>,
+    started: Instant,
+}
+
+impl ShardedHandle {
+    /// Gets the shard a symbol's book lives on
+    pub fn shard_of(&self, symbol: &Symbol) -> usize {
+        let mut hasher = DefaultHasher::new();
+        symbol.hash(&mut hasher);
+        (hasher.finish() % self.shards.len() as u64) as usize
+    }
+
+    /// Queues an op for the book of `symbol`, blocking while the shard's queue is full
+    ///
+    /// A [`OrderBookOp::Create`] naming `symbol` creates its book on the
+    /// shard; any other op for a symbol without a book is rejected with
+    /// `UnknownSymbol`. Rejections are reported in the result.
+    ///
+    /// # Errors
+    /// Returns `EngineStopped` if the engine has shut down.
+    pub fn submit(&self, symbol: &Symbol, op: OrderBookOp) -> crate::Result<PendingResult> {
+        let (reply, receiver) = mpsc::channel();
+        self.send(symbol, ShardMessage::Apply { symbol: symbol.clone(), op, reply })?;
+        Ok(PendingResult { reply: receiver })
+    }
+
+    /// Moves a book onto its symbol's shard, failing if the shard already has one
+    pub fn add_book(&self, book: LimitOrderBook) -> crate::Result<()> {
+        let (reply, receiver) = mpsc::channel();
+        let symbol = book.symbol().clone();
+        self.send(&symbol, ShardMessage::AddBook { book: Box::new(book), reply })?;
+        receiver.recv().map_err(|_| MatchingEngineError::EngineStopped)?
+    }
+
+    /// Gets the current load on each shard, in shard order
+    pub fn stats(&self) -> Vec<ShardStats> {
+        let elapsed = self.started.elapsed().as_secs_f64();
+        self.shards.iter().enumerate()
+            .map(|(shard, link)| {
+                let ops_applied = link.counters.applied.load(Ordering::Relaxed);
+                ShardStats {
+                    shard,
+                    symbols: link.counters.symbols.load(Ordering::Relaxed),
+                    queue_depth: link.counters.queued.load(Ordering::Relaxed),
+                    ops_applied,
+                    ops_per_sec: if elapsed > 0.0 { ops_applied as f64 / elapsed } else { 0.0 },
+                }
+            })
+            .collect()
+    }
+
+    fn send(&self, symbol: &Symbol, message: ShardMessage) -> crate::Result<()> {
+        let link = &self.shards[self.shard_of(symbol)];
+        link.counters.queued.fetch_add(1, Ordering::Relaxed);
+        link.queue.send(message).map_err(|_| {
+            link.counters.queued.fetch_sub(1, Ordering::Relaxed);
+            MatchingEngineError::EngineStopped
+        })
+    }
+}
+
+/// Books for many symbols, each owned by the worker thread of its shard
+#[derive(Debug)]
+pub struct ShardedEngine {
+    handle: ShardedHandle,
+    workers: Vec<JoinHandle<HashMap<Symbol, LimitOrderBook>>>,
+}
+
+impl ShardedEngine {
+    /// Starts an engine with `num_shards` worker threads and no books
+    ///
+    /// # Panics
+    /// Panics if `num_shards` is zero or a worker thread can't be started.
+    pub fn new(num_shards: usize) -> Self {
+        assert!(num_shards > 0, "a sharded engine needs at least one shard");
+        let (links, workers): (Vec<_>, Vec<_>) = (0..num_shards)
+            .map(|shard| {
+                let (queue, messages) = mpsc::sync_channel(SHARD_QUEUE_CAPACITY);
+                let counters = Arc::new(ShardCounters::default());
+                let worker = Worker { books: HashMap::new(), counters: Arc::clone(&counters) };
+                let worker = std::thread::Builder::new()
+                    .name(format!("shard-{shard}"))
+                    .spawn(move || worker.run(messages))
+                    .expect("failed to start shard worker");
+                (ShardLink { queue, counters }, worker)
+            })
+            .unzip();
+        Self { handle: ShardedHandle { shards: links.into(), started: Instant::now() }, workers }
+    }
+
+    /// Gets a handle for submitting commands from any thread
+    pub fn handle(&self) -> ShardedHandle {
+        self.handle.clone()
+    }
+
+    /// Gets the number of shards
+    pub fn num_shards(&self) -> usize {
+        self.workers.len()
+    }
+
+    /// Stops every worker once the commands already queued are applied, returning the books
+    ///
+    /// Handles submitting afterwards get `EngineStopped`.
+    pub fn shutdown(self) -> HashMap<Symbol, LimitOrderBook> {
+        for link in self.handle.shards.iter() {
+            // Counted like any other message, since the worker uncounts it on receipt
+            link.counters.queued.fetch_add(1, Ordering::Relaxed);
+            if link.queue.send(ShardMessage::Stop).is_err() {
+                link.counters.queued.fetch_sub(1, Ordering::Relaxed);
+            }
+        }
+        self.workers.into_iter()
+            .flat_map(|worker| worker.join().expect("shard worker panicked"))
+            .collect()
+    }
+}
+
+/// A shard's books, owned by its worker thread
+struct Worker {
+    books: HashMap<Symbol, LimitOrderBook>,
+    counters: Arc<ShardCounters>,
+}
+
+impl Worker {
+    fn run(mut self, messages: mpsc::Receiver<ShardMessage>) -> HashMap<Symbol, LimitOrderBook> {
+        while let Ok(message) = messages.recv() {
+            self.counters.queued.fetch_sub(1, Ordering::Relaxed);
+            match message {
+                ShardMessage::Apply { symbol, op, reply } => {
+                    let result = self.apply(symbol, op);
+                    self.counters.applied.fetch_add(1, Ordering::Relaxed);
+                    let _ = reply.send(result);
+                },
+                ShardMessage::AddBook { book, reply } => {
+                    let _ = reply.send(self.add_book(*book));
+                },
+                ShardMessage::Stop => break,
+            }
+        }
+        self.books
+    }
+
+    fn apply(&mut self, symbol: Symbol, op: OrderBookOp) -> OpResult {
+        if let Some(book) = self.books.get_mut(&symbol) {
+            return book.apply(op);
+        }
+        match op {
+            OrderBookOp::Create { symbol: created, clock } if created == symbol.as_str() => {
+                match LimitOrderBook::with_clock(created, clock).and_then(|book| self.add_book(book)) {
+                    Ok(()) => OpResult::Done,
+                    Err(error) => OpResult::Rejected(error),
+                }
+            },
+            _ => OpResult::Rejected(MatchingEngineError::UnknownSymbol(symbol.to_string())),
+        }
+    }
+
+    fn add_book(&mut self, book: LimitOrderBook) -> crate::Result<()> {
+        let symbol = book.symbol().clone();
+        if self.books.contains_key(&symbol) {
+            return Err(MatchingEngineError::DuplicateSymbol(symbol.to_string()));
+        }
+        self.books.insert(symbol, book);
+        self.counters.symbols.store(self.books.len(), Ordering::Relaxed);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Clock, OrderSide};
+    use crate::order_book::test_support::create_test_order;
+
+    fn symbol(name: &str) -> Symbol {
+        Symbol::new(name.to_string()).unwrap()
+    }
+
+    #[test]
+    fn test_commands_reach_their_symbols_book() {
+        let engine = ShardedEngine::new(4);
+        let handle = engine.handle();
+        let (aapl, msft) = (symbol("AAPL"), symbol("MSFT"));
+        handle.add_book(LimitOrderBook::new("AAPL".to_string()).unwrap()).unwrap();
+        assert_eq!(handle.add_book(LimitOrderBook::new("AAPL".to_string()).unwrap()), Err(MatchingEngineError::DuplicateSymbol("AAPL".to_string())));
+
+        // Ops for a symbol without a book are rejected until a Create makes one
+        let order = create_test_order(OrderSide::Sell, 10000, 10);
+        let rejected = handle.submit(&msft, OrderBookOp::Submit(order.clone())).unwrap().wait().unwrap();
+        assert_eq!(rejected, OpResult::Rejected(MatchingEngineError::UnknownSymbol("MSFT".to_string())));
+        let create = OrderBookOp::Create { symbol: "MSFT".to_string(), clock: Clock::System };
+        assert_eq!(handle.submit(&msft, create.clone()).unwrap().wait().unwrap(), OpResult::Done);
+        assert!(matches!(handle.submit(&msft, create).unwrap().wait().unwrap(), OpResult::Rejected(MatchingEngineError::DuplicateSymbol(_))));
+        let misrouted = OrderBookOp::Create { symbol: "IBM".to_string(), clock: Clock::System };
+        assert!(matches!(handle.submit(&msft, misrouted).unwrap().wait().unwrap(), OpResult::Rejected(_)));
+
+        handle.submit(&msft, OrderBookOp::Submit(order)).unwrap().wait().unwrap();
+        let crossing = handle.submit(&msft, OrderBookOp::Submit(create_test_order(OrderSide::Buy, 10000, 4))).unwrap();
+        let OpResult::Executed(result) = crossing.wait().unwrap() else { panic!("expected an execution") };
+        assert_eq!(result.trades.len(), 1);
+        handle.submit(&aapl, OrderBookOp::Submit(create_test_order(OrderSide::Buy, 9900, 5))).unwrap().wait().unwrap();
+
+        let stats = handle.stats();
+        assert_eq!(stats.len(), engine.num_shards());
+        assert_eq!(stats.iter().map(|shard| shard.symbols).sum::<usize>(), 2);
+        assert_eq!(stats.iter().map(|shard| shard.ops_applied).sum::<u64>(), 7);
+        assert!(stats.iter().all(|shard| shard.queue_depth == 0));
+        let shared_shard = handle.shard_of(&aapl) == handle.shard_of(&msft);
+        assert_eq!(stats[handle.shard_of(&aapl)].symbols, if shared_shard { 2 } else { 1 });
+
+        let books = engine.shutdown();
+        assert_eq!(books[&msft].best_ask_quantity().unwrap().value(), 6);
+        assert_eq!(books[&aapl].best_bid().unwrap().as_cents(), 9900);
+        assert!(handle.stats().iter().all(|shard| shard.queue_depth == 0));
+        assert!(matches!(handle.submit(&aapl, OrderBookOp::CancelAll), Err(MatchingEngineError::EngineStopped)));
+        assert!(handle.stats().iter().all(|shard| shard.queue_depth == 0));
+        assert!(handle.add_book(LimitOrderBook::new("IBM".to_string()).unwrap()).is_err());
+    }
+}
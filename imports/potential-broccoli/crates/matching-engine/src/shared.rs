@@ -0,0 +1,182 @@
+//! A book shared between one matching thread and many readers
+//!
+//! [`SharedOrderBook`] is a cloneable handle. Writes lock the book, apply
+//! the change, and publish a [`BookSnapshot`] of the result before
+//! unlocking, so writes are applied one at a time in lock order. Reads never
+//! touch the book: they clone the latest published snapshot under a brief
+//! read lock and answer from it, so a depth poll never waits on matching,
+//! and matching only waits for readers long enough to swap one pointer.
+//!
+//! Every read answers from a single snapshot, so bid and ask always come
+//! from the same sequence number and a reader never sees one side updated
+//! without the other. Take a [`view`](SharedOrderBook::view) to answer
+//! several queries from the same snapshot. Snapshots judge order expiry as
+//! of the time they were published.
+//!
+//! Readers only ever receive an owned snapshot, never a guard, so the
+//! publication slot can move to a lock-free swap without changing callers.
+
+use crate::{
+    order_book::{Bbo, BookSnapshot, BookStats, MarketDepth, Trade},
+    types::OrderId,
+    LimitOrderBook, Order,
+};
+use std::sync::{Arc, Mutex, RwLock};
+
+#[derive(Debug)]
+struct Shared {
+    book: Mutex<LimitOrderBook>,
+    /// Snapshot of the book after the last write
+    published: RwLock<Arc<BookSnapshot>>,
+}
+
+/// Handle to an order book shared across threads
+///
+/// Clones are handles to the same book.
+#[derive(Debug, Clone)]
+pub struct SharedOrderBook {
+    shared: Arc<Shared>,
+}
+
+impl SharedOrderBook {
+    /// Shares a book, publishing its current state to readers
+    pub fn new(book: LimitOrderBook) -> Self {
+        let published = RwLock::new(Arc::new(book.snapshot()));
+        Self { shared: Arc::new(Shared { book: Mutex::new(book), published }) }
+    }
+
+    /// Applies a change to the book and publishes the result
+    ///
+    /// Other writers wait until `write` returns; readers don't. The result
+    /// is published even if `write` fails partway, so readers always see
+    /// the book as it is.
+    pub fn write<R>(&self, write: impl FnOnce(&mut LimitOrderBook) -> R) -> R {
+        let mut book = self.shared.book.lock().unwrap_or_else(|e| e.into_inner());
+        let result = write(&mut book);
+        let snapshot = Arc::new(book.snapshot());
+        *self.shared.published.write().unwrap_or_else(|e| e.into_inner()) = snapshot;
+        result
+    }
+
+    /// Adds an order to the book, returning the trades it executed
+    pub fn add_order(&self, order: Order) -> crate::Result<Vec<Trade>> {
+        self.write(|book| book.add_order(order))
+    }
+
+    /// Cancels a resting order, returning it
+    pub fn cancel_order(&self, order_id: OrderId) -> crate::Result<Order> {
+        self.write(|book| book.cancel_order(order_id))
+    }
+
+    /// Gets the snapshot published by the latest write
+    ///
+    /// Every query on it answers from the same sequence number.
+    pub fn view(&self) -> Arc<BookSnapshot> {
+        Arc::clone(&self.shared.published.read().unwrap_or_else(|e| e.into_inner()))
+    }
+
+    /// Gets the book sequence number of the latest published write
+    pub fn sequence(&self) -> u64 {
+        self.view().sequence()
+    }
+
+    /// Gets the best bid and offer, or `None` if neither side is quoted
+    pub fn bbo(&self) -> Option<Bbo> {
+        self.view().bbo()
+    }
+
+    /// Gets the top `levels` displayed levels per side
+    pub fn market_depth(&self, levels: usize) -> MarketDepth {
+        self.view().market_depth(levels)
+    }
+
+    /// Computes the checksum of the top `levels` displayed levels per side
+    pub fn checksum(&self, levels: usize) -> u32 {
+        self.view().checksum(levels)
+    }
+
+    /// Gets the trade statistics
+    pub fn stats(&self) -> BookStats {
+        *self.view().stats()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::OrderSide;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::thread;
+    use crate::order_book::test_support::create_test_order;
+
+    #[test]
+    fn test_reads_follow_writes() {
+        let shared = SharedOrderBook::new(LimitOrderBook::new("SHARED".to_string()).unwrap());
+        assert!(shared.bbo().is_none());
+
+        let ask = create_test_order(OrderSide::Sell, 10010, 50);
+        let ask_id = ask.id;
+        shared.add_order(ask).unwrap();
+        shared.add_order(create_test_order(OrderSide::Buy, 10000, 50)).unwrap();
+        let trades = shared.add_order(create_test_order(OrderSide::Buy, 10010, 20)).unwrap();
+        assert_eq!(trades.len(), 1);
+
+        let view = shared.view();
+        let book_depth = shared.write(|book| book.market_depth(10));
+        assert_eq!(view.market_depth(10), book_depth);
+        assert_eq!(shared.checksum(10), book_depth.checksum(10));
+        assert_eq!(shared.stats().volume, 20);
+        assert_eq!(shared.bbo().unwrap().ask.unwrap().quantity.value(), 30);
+
+        // A failed write still leaves the published view current
+        shared.cancel_order(ask_id).unwrap();
+        assert!(shared.cancel_order(ask_id).is_err());
+        assert!(shared.bbo().unwrap().ask.is_none());
+        assert_eq!(view.bbo().unwrap().ask.unwrap().quantity.value(), 30);
+        assert!(shared.sequence() > view.sequence());
+    }
+
+    #[test]
+    fn test_one_writer_many_readers_never_see_crossed_book() {
+        let shared = SharedOrderBook::new(LimitOrderBook::new("SHARED".to_string()).unwrap());
+        let done = Arc::new(AtomicBool::new(false));
+        let readers: Vec<_> = (0..4).map(|_| {
+            let (shared, done) = (shared.clone(), Arc::clone(&done));
+            thread::spawn(move || {
+                let mut last_sequence = 0;
+                let mut reads = 0;
+                while !done.load(Ordering::Acquire) || reads < 100 {
+                    let view = shared.view();
+                    assert!(view.sequence() >= last_sequence, "sequence went backwards");
+                    last_sequence = view.sequence();
+                    if let Some(Bbo { bid: Some(bid), ask: Some(ask), .. }) = view.bbo() {
+                        assert!(bid.price < ask.price, "crossed book at sequence {}", view.sequence());
+                    }
+                    let depth = view.market_depth(5);
+                    assert_eq!(depth.sequence, view.sequence());
+                    assert_eq!(view.checksum(5), depth.checksum(5));
+                    reads += 1;
+                }
+                reads
+            })
+        }).collect();
+
+        // Quotes walk up and down and regularly trade through each other
+        for i in 0..3_000i64 {
+            let drift = (i / 50) % 20;
+            let side = if i % 2 == 0 { OrderSide::Buy } else { OrderSide::Sell };
+            let order = create_test_order(side, 10000 + drift + (i * 7) % 11 - 5, 1 + (i % 13) as u64);
+            let order_id = order.id;
+            shared.add_order(order).unwrap();
+            if i % 3 == 0 {
+                let _ = shared.cancel_order(order_id);
+            }
+        }
+        done.store(true, Ordering::Release);
+
+        for reader in readers {
+            assert!(reader.join().unwrap() >= 100);
+        }
+        assert_eq!(shared.view().market_depth(100), shared.write(|book| book.market_depth(100)));
+    }
+}
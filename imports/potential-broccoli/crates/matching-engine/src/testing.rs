@@ -0,0 +1,17 @@
+//! Model-based testing support
+//!
+//! [`ReferenceBook`] is a deliberately naive order book — one `Vec` of resting
+//! orders, linear scans, no caching or indexes — whose behavior is easy to
+//! check by reading it. [`ModelHarness`] applies the same [`Command`]s to a
+//! [`LimitOrderBook`](crate::LimitOrderBook) and the reference, failing on the
+//! first difference in trades, rejections, depth, or best bid/offer. The
+//! proptest strategies in this module generate command streams whose failures
+//! shrink to a minimal diverging sequence.
+//!
+//! Available to downstream crates with the `testing` feature.
+
+mod harness;
+mod reference;
+
+pub use harness::{command_strategy, commands_strategy, Command, Divergence, ModelHarness};
+pub use reference::ReferenceBook;
@@ -0,0 +1,244 @@
+//! Differential harness comparing a `LimitOrderBook` against the reference
+
+use super::ReferenceBook;
+use crate::{
+    order_book::Trade,
+    types::{OrderId, UserId},
+    LimitOrderBook, Order, OrderSide, Price, Quantity, TimeInForce,
+};
+use proptest::prelude::*;
+
+/// A book operation, with targets given as indexes into the orders added so far
+#[derive(Debug, Clone)]
+pub enum Command {
+    Add(Order),
+    /// Cancel the n-th added order (modulo the number added)
+    Cancel(usize),
+    /// Modify the n-th added order (modulo the number added)
+    Modify {
+        target: usize,
+        price: Option<Price>,
+        quantity: Option<Quantity>,
+    },
+    /// Roll the session, expiring Day orders
+    RollSession,
+}
+
+/// First observed difference between the book and the reference
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Divergence {
+    /// Index of the command after which the difference appeared
+    pub step: usize,
+    pub description: String,
+}
+
+impl std::fmt::Display for Divergence {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "diverged at command {}: {}", self.step, self.description)
+    }
+}
+
+impl std::error::Error for Divergence {}
+
+/// Drives a book and the reference in lockstep
+pub struct ModelHarness {
+    book: LimitOrderBook,
+    model: ReferenceBook,
+    added: Vec<OrderId>,
+    step: usize,
+}
+
+impl ModelHarness {
+    /// Creates a harness around an empty book
+    ///
+    /// The book may use any storage or performance configuration, but must
+    /// keep default matching behavior (no STP, netting, or volume limits).
+    pub fn new(book: LimitOrderBook) -> Self {
+        Self { book, model: ReferenceBook::new(), added: Vec::new(), step: 0 }
+    }
+    
+    /// Gets the book under test
+    pub fn book(&self) -> &LimitOrderBook {
+        &self.book
+    }
+    
+    /// Applies every command, stopping at the first divergence
+    pub fn run<'a>(&mut self, commands: impl IntoIterator<Item = &'a Command>) -> Result<(), Divergence> {
+        commands.into_iter().try_for_each(|command| self.apply(command))
+    }
+    
+    /// Applies one command to both books and compares the outcome and resulting state
+    pub fn apply(&mut self, command: &Command) -> Result<(), Divergence> {
+        match command {
+            Command::Add(order) => {
+                self.added.push(order.id);
+                let actual = self.book.add_order(order.clone());
+                let expected = self.model.add_order(order.clone());
+                self.compare_outcome(actual, expected)?;
+            },
+            Command::Cancel(n) => {
+                if let Some(order_id) = self.target(*n) {
+                    let actual = self.book.cancel_order(order_id).map(|_| Vec::new());
+                    let expected = self.model.cancel_order(order_id).map(|_| Vec::new());
+                    self.compare_outcome(actual, expected)?;
+                }
+            },
+            Command::Modify { target, price, quantity } => {
+                if let Some(order_id) = self.target(*target) {
+                    let actual = self.book.modify_order(order_id, *price, *quantity).map(|result| result.trades);
+                    let expected = self.model.modify_order(order_id, *price, *quantity);
+                    self.compare_outcome(actual, expected)?;
+                }
+            },
+            Command::RollSession => {
+                let mut actual: Vec<OrderId> = self.book.roll_session("next").expired_orders.iter().map(|o| o.id).collect();
+                let mut expected: Vec<OrderId> = self.model.expire_day_orders().iter().map(|o| o.id).collect();
+                actual.sort_by_key(|id| id.to_string());
+                expected.sort_by_key(|id| id.to_string());
+                self.check(actual == expected, || format!("expired {:?}, expected {:?}", actual, expected))?;
+            },
+        }
+        
+        self.compare_state()?;
+        self.step += 1;
+        Ok(())
+    }
+    
+    fn target(&self, n: usize) -> Option<OrderId> {
+        (!self.added.is_empty()).then(|| self.added[n % self.added.len()])
+    }
+    
+    fn compare_outcome(
+        &self,
+        actual: crate::Result<Vec<Trade>>,
+        expected: crate::Result<Vec<Trade>>,
+    ) -> Result<(), Divergence> {
+        match (actual, expected) {
+            (Ok(actual), Ok(expected)) => {
+                let key = |t: &Trade| (t.trade_id, t.buy_order_id, t.sell_order_id, t.maker_order_id, t.price, t.quantity);
+                let actual: Vec<_> = actual.iter().map(key).collect();
+                let expected: Vec<_> = expected.iter().map(key).collect();
+                self.check(actual == expected, || format!("trades {:?}, expected {:?}", actual, expected))
+            },
+            (Err(actual), Err(expected)) => self.check(
+                std::mem::discriminant(&actual) == std::mem::discriminant(&expected),
+                || format!("rejected with {:?}, expected {:?}", actual, expected),
+            ),
+            (actual, expected) => self.check(false, || format!("got {:?}, expected {:?}", actual, expected)),
+        }
+    }
+    
+    fn compare_state(&self) -> Result<(), Divergence> {
+        let (bid, ask) = (self.book.best_bid(), self.book.best_ask());
+        let (model_bid, model_ask) = (self.model.best_bid(), self.model.best_ask());
+        self.check((bid, ask) == (model_bid, model_ask), || {
+            format!("BBO {:?}/{:?}, expected {:?}/{:?}", bid, ask, model_bid, model_ask)
+        })?;
+        
+        let depth = self.book.market_depth(usize::MAX);
+        for (side, actual) in [(OrderSide::Buy, &depth.bids), (OrderSide::Sell, &depth.asks)] {
+            let expected = self.model.depth(side);
+            self.check(*actual == expected, || format!("{} depth {:?}, expected {:?}", side, actual, expected))?;
+        }
+        Ok(())
+    }
+    
+    fn check(&self, ok: bool, description: impl FnOnce() -> String) -> Result<(), Divergence> {
+        if ok {
+            Ok(())
+        } else {
+            Err(Divergence { step: self.step, description: description() })
+        }
+    }
+}
+
+/// Generates orders on a narrow price grid so they share levels and cross often
+fn order_strategy() -> impl Strategy<Value = Order> {
+    (
+        prop_oneof![Just(OrderSide::Buy), Just(OrderSide::Sell)],
+        9_990i64..10_010,
+        1u64..200,
+        0usize..4,
+        prop_oneof![6 => Just(TimeInForce::GTC), 2 => Just(TimeInForce::Day), 1 => Just(TimeInForce::FOK)],
+        prop::bool::weighted(0.1),
+    )
+        .prop_map(|(side, cents, quantity, user, time_in_force, market)| {
+            let (id, user_id, quantity) = (OrderId::new(), UserId::new(format!("user{}", user)), Quantity::new(quantity).unwrap());
+            let order = if market {
+                Order::market(id, user_id, side, quantity)
+            } else {
+                Order::new(id, user_id, side, Price::from_cents(cents).unwrap(), quantity)
+            };
+            order.with_time_in_force(time_in_force)
+        })
+}
+
+/// Generates a single command
+pub fn command_strategy() -> impl Strategy<Value = Command> {
+    prop_oneof![
+        6 => order_strategy().prop_map(Command::Add),
+        2 => any::<usize>().prop_map(Command::Cancel),
+        2 => (
+            any::<usize>(),
+            proptest::option::of((9_990i64..10_010).prop_map(|cents| Price::from_cents(cents).unwrap())),
+            proptest::option::of((1u64..300).prop_map(|q| Quantity::new(q).unwrap())),
+        )
+            .prop_map(|(target, price, quantity)| Command::Modify { target, price, quantity }),
+        1 => Just(Command::RollSession),
+    ]
+}
+
+/// Generates command sequences of up to `max_len` commands
+pub fn commands_strategy(max_len: usize) -> impl Strategy<Value = Vec<Command>> {
+    prop::collection::vec(command_strategy(), 0..max_len)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TieredStorage;
+
+    fn new_book() -> LimitOrderBook {
+        LimitOrderBook::new("MODEL".to_string()).unwrap()
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(500))]
+        
+        #[test]
+        fn prop_book_matches_reference(commands in commands_strategy(120)) {
+            let mut harness = ModelHarness::new(new_book());
+            if let Err(divergence) = harness.run(&commands) {
+                prop_assert!(false, "{}", divergence);
+            }
+        }
+        
+        #[test]
+        fn prop_tiered_book_matches_reference(commands in commands_strategy(120), hot_levels in 1usize..4) {
+            let mut book = new_book();
+            book.set_tiered_storage(Some(TieredStorage::new(hot_levels)));
+            let mut harness = ModelHarness::new(book);
+            if let Err(divergence) = harness.run(&commands) {
+                prop_assert!(false, "{}", divergence);
+            }
+        }
+    }
+
+    #[test]
+    fn test_harness_reports_divergence() {
+        // A book that already holds an order the reference doesn't know about
+        let mut book = new_book();
+        book.add_order(Order::new(
+            OrderId::new(),
+            UserId::new("ghost".to_string()),
+            OrderSide::Sell,
+            Price::from_cents(10_000).unwrap(),
+            Quantity::new(10).unwrap(),
+        )).unwrap();
+        
+        let mut harness = ModelHarness::new(book);
+        let divergence = harness.apply(&Command::RollSession).unwrap_err();
+        assert_eq!(divergence.step, 0);
+        assert!(divergence.description.contains("BBO"));
+    }
+}
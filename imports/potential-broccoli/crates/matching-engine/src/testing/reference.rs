@@ -0,0 +1,213 @@
+//! Naive reference order book
+
+use crate::{
+    order_book::{MarketLevel, Trade},
+    types::OrderId,
+    MatchingEngineError, Order, OrderSide, Price, Quantity, TimeInForce,
+};
+use rust_decimal::Decimal;
+
+/// Obviously-correct order book used as the model in model-based tests
+///
+/// Resting orders live in a single `Vec` in queue order: an order's priority
+/// is its price, then its position in the `Vec`. Supports the default
+/// matching configuration only (no self-trade prevention, netting, or limits).
+#[derive(Debug, Clone)]
+pub struct ReferenceBook {
+    resting: Vec<Order>,
+    next_trade_id: u64,
+}
+
+impl Default for ReferenceBook {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ReferenceBook {
+    /// Creates an empty reference book
+    pub fn new() -> Self {
+        Self { resting: Vec::new(), next_trade_id: 1 }
+    }
+    
+    /// Matches an order against the book, resting any remainder
+    pub fn add_order(&mut self, mut order: Order) -> crate::Result<Vec<Trade>> {
+        if order.time_in_force == TimeInForce::FOK {
+            let available: u64 = self.resting.iter()
+                .filter(|resting| resting.side != order.side && Self::crosses(&order, resting))
+                .map(|resting| resting.remaining_quantity.value())
+                .sum();
+            if available < order.remaining_quantity.value() {
+                return Ok(Vec::new());
+            }
+        }
+        let trades = self.match_order(&mut order)?;
+        if order.is_active() && order.can_rest() {
+            self.resting.push(order);
+        }
+        Ok(trades)
+    }
+    
+    /// Removes a resting order
+    pub fn cancel_order(&mut self, order_id: OrderId) -> crate::Result<Order> {
+        let index = self.position(order_id)?;
+        let mut order = self.resting.remove(index);
+        order.cancel();
+        Ok(order)
+    }
+    
+    /// Modifies a resting order with the same semantics as `LimitOrderBook::modify_order`
+    pub fn modify_order(
+        &mut self,
+        order_id: OrderId,
+        new_price: Option<Price>,
+        new_quantity: Option<Quantity>,
+    ) -> crate::Result<Vec<Trade>> {
+        let index = self.position(order_id)?;
+        let current = &self.resting[index];
+        let new_price = new_price.unwrap_or(current.price);
+        let new_quantity = new_quantity.unwrap_or(current.original_quantity);
+        let filled = current.original_quantity.value() - current.remaining_quantity.value();
+        if new_quantity.value() <= filled {
+            return Err(MatchingEngineError::InvalidQuantity(format!(
+                "new quantity {} does not exceed the {} already filled", new_quantity, filled
+            )));
+        }
+        
+        if new_price != current.price || new_quantity > current.original_quantity {
+            // Cancel-replace: leave the queue and re-enter at the back
+            let mut order = self.resting.remove(index);
+            order.price = new_price;
+            order.original_quantity = new_quantity;
+            order.remaining_quantity = Quantity::new_allow_zero(new_quantity.value() - filled);
+            return self.add_order(order);
+        }
+        
+        // Size-down in place
+        let order = &mut self.resting[index];
+        let remaining = new_quantity.value() - filled;
+        order.original_quantity = new_quantity;
+        order.remaining_quantity = Quantity::new_allow_zero(remaining);
+        Ok(Vec::new())
+    }
+    
+    /// Expires every resting Day order
+    pub fn expire_day_orders(&mut self) -> Vec<Order> {
+        let (expired, kept) = std::mem::take(&mut self.resting)
+            .into_iter()
+            .partition(|order| order.time_in_force == TimeInForce::Day);
+        self.resting = kept;
+        expired
+    }
+    
+    /// Gets a resting order by ID
+    pub fn get_order(&self, order_id: OrderId) -> Option<&Order> {
+        self.resting.iter().find(|order| order.id == order_id)
+    }
+    
+    /// Gets the highest resting buy price
+    pub fn best_bid(&self) -> Option<Price> {
+        self.side(OrderSide::Buy).map(|order| order.price).max()
+    }
+    
+    /// Gets the lowest resting sell price
+    pub fn best_ask(&self) -> Option<Price> {
+        self.side(OrderSide::Sell).map(|order| order.price).min()
+    }
+    
+    /// Aggregates every level on one side, best price first
+    pub fn depth(&self, side: OrderSide) -> Vec<MarketLevel> {
+        let mut prices: Vec<Price> = self.side(side).map(|order| order.price).collect();
+        prices.sort();
+        prices.dedup();
+        if side == OrderSide::Buy {
+            prices.reverse();
+        }
+        
+        prices.into_iter()
+            .map(|price| {
+                let at_price = self.side(side).filter(|order| order.price == price);
+                MarketLevel {
+                    price,
+                    quantity: Quantity::new_allow_zero(at_price.clone().map(|o| o.remaining_quantity.value()).sum()),
+                    order_count: at_price.count(),
+                    quantity_scale: 0,
+                }
+            })
+            .collect()
+    }
+    
+    fn side(&self, side: OrderSide) -> impl Iterator<Item = &Order> + Clone {
+        self.resting.iter().filter(move |order| order.side == side)
+    }
+    
+    fn position(&self, order_id: OrderId) -> crate::Result<usize> {
+        self.resting.iter()
+            .position(|order| order.id == order_id)
+            .ok_or_else(|| MatchingEngineError::OrderNotFound(order_id.to_string()))
+    }
+    
+    fn crosses(incoming: &Order, resting: &Order) -> bool {
+        incoming.is_market() || match incoming.side {
+            OrderSide::Buy => resting.price <= incoming.price,
+            OrderSide::Sell => resting.price >= incoming.price,
+        }
+    }
+    
+    fn match_order(&mut self, incoming: &mut Order) -> crate::Result<Vec<Trade>> {
+        let mut trades = Vec::new();
+        
+        while incoming.is_active() {
+            // Best-priced crossing order, earliest in the queue on ties
+            let mut best: Option<usize> = None;
+            for (index, resting) in self.resting.iter().enumerate() {
+                let crosses = resting.side != incoming.side && Self::crosses(incoming, resting);
+                let better = best.is_none_or(|b| match incoming.side {
+                    OrderSide::Buy => resting.price < self.resting[b].price,
+                    OrderSide::Sell => resting.price > self.resting[b].price,
+                });
+                if crosses && better {
+                    best = Some(index);
+                }
+            }
+            let Some(index) = best else {
+                break;
+            };
+            
+            let resting = &mut self.resting[index];
+            let quantity = incoming.remaining_quantity.min(resting.remaining_quantity);
+            let (buy_order, sell_order) = match incoming.side {
+                OrderSide::Buy => (&*incoming, &*resting),
+                OrderSide::Sell => (&*resting, &*incoming),
+            };
+            trades.push(Trade {
+                trade_id: self.next_trade_id,
+                buy_order_id: buy_order.id,
+                sell_order_id: sell_order.id,
+                maker_order_id: Some(resting.id),
+                taker_order_id: Some(incoming.id),
+                aggressor_side: Some(incoming.side),
+                buyer_user_id: Some(buy_order.user_id.clone()),
+                seller_user_id: Some(sell_order.user_id.clone()),
+                price: resting.price,
+                quantity,
+                timestamp: chrono::Utc::now(),
+                same_firm: false,
+                maker_fee: Decimal::ZERO,
+                taker_fee: Decimal::ZERO,
+                symbol: None,
+                sequence: 0,
+                quantity_scale: 0,
+            });
+            self.next_trade_id += 1;
+            
+            incoming.fill(quantity)?;
+            resting.fill(quantity)?;
+            if resting.is_filled() {
+                self.resting.remove(index);
+            }
+        }
+        
+        Ok(trades)
+    }
+}
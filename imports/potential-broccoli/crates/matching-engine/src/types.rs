@@ -1,6 +1,8 @@
 //! Common types used throughout the matching engine
 
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::HashSet;
+use std::sync::Arc;
 use uuid::Uuid;
 
 /// Unique identifier for orders
@@ -37,8 +39,11 @@ impl std::fmt::Display for OrderId {
 }
 
 /// Trading symbol identifier
-#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
-pub struct Symbol(String);
+///
+/// Shares its string like [`UserId`], so stamping the book's symbol on each
+/// trade never allocates. Serializes as the plain string.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Symbol(Arc<str>);
 
 impl Symbol {
     /// Creates a new symbol with validation
@@ -53,7 +58,7 @@ impl Symbol {
                 "Symbol too long (max 10 characters)".to_string(),
             ));
         }
-        Ok(Self(symbol.to_uppercase()))
+        Ok(Self(symbol.to_uppercase().into()))
     }
     
     /// Gets the symbol string
@@ -68,24 +73,181 @@ impl std::fmt::Display for Symbol {
     }
 }
 
+impl Serialize for Symbol {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for Symbol {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer).map(|symbol| Self(symbol.into()))
+    }
+}
+
 /// User identifier
-#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
-pub struct UserId(String);
+///
+/// Shares its string: cloning a user ID, as every order and trade does,
+/// never allocates. IDs from a [`UserIdPool`] also share one allocation per
+/// distinct user. Serializes as the plain string.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct UserId(Arc<str>);
 
 impl UserId {
     /// Creates a new user ID
     pub fn new(user_id: String) -> Self {
-        Self(user_id)
+        Self(user_id.into())
     }
     
     /// Gets the user ID string
     pub fn as_str(&self) -> &str {
         &self.0
     }
+    
+    /// Address of the shared string, identifying IDs that share one allocation
+    pub(crate) fn storage(&self) -> *const u8 {
+        self.0.as_ptr()
+    }
 }
 
 impl std::fmt::Display for UserId {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "{}", self.0)
     }
-}
\ No newline at end of file
+}
+
+impl Serialize for UserId {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for UserId {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer).map(Self::new)
+    }
+}
+
+/// Interns user IDs, so a user's orders all share one copy of their ID
+///
+/// A gateway serving the same few users keeps one pool and takes each
+/// order's ID from it, allocating once per distinct user rather than once
+/// per order.
+#[derive(Debug, Clone, Default)]
+pub struct UserIdPool {
+    ids: HashSet<Arc<str>>,
+}
+
+impl UserIdPool {
+    /// Creates an empty pool
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// Gets the pooled ID for `user_id`, adding it on first use
+    pub fn get_or_intern(&mut self, user_id: &str) -> UserId {
+        if let Some(interned) = self.ids.get(user_id) {
+            return UserId(Arc::clone(interned));
+        }
+        let interned: Arc<str> = user_id.into();
+        self.ids.insert(Arc::clone(&interned));
+        UserId(interned)
+    }
+    
+    /// Number of distinct user IDs interned
+    pub fn len(&self) -> usize {
+        self.ids.len()
+    }
+    
+    pub fn is_empty(&self) -> bool {
+        self.ids.is_empty()
+    }
+}
+
+/// Sub-account identifier used when allocating omnibus fills
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct SubAccountId(String);
+
+impl SubAccountId {
+    /// Creates a new sub-account ID
+    pub fn new(sub_account_id: String) -> Self {
+        Self(sub_account_id)
+    }
+    
+    /// Gets the sub-account ID string
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl std::fmt::Display for SubAccountId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// Firm identifier grouping the accounts of one legal entity
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct FirmId(String);
+
+impl FirmId {
+    /// Creates a new firm ID
+    pub fn new(firm_id: String) -> Self {
+        Self(firm_id)
+    }
+    
+    /// Gets the firm ID string
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl std::fmt::Display for FirmId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// Desk identifier within a firm
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct DeskId(String);
+
+impl DeskId {
+    /// Creates a new desk ID
+    pub fn new(desk_id: String) -> Self {
+        Self(desk_id)
+    }
+    
+    /// Gets the desk ID string
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl std::fmt::Display for DeskId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pooled_user_ids_share_storage() {
+        let mut pool = UserIdPool::new();
+        let first = pool.get_or_intern("alice");
+        let again = pool.get_or_intern("alice");
+        assert_eq!(first.storage(), again.storage());
+        assert_eq!(pool.get_or_intern("bob").as_str(), "bob");
+        assert_eq!(pool.len(), 2);
+
+        // Equal to an unpooled ID, and serialized as the bare string either way
+        let fresh = UserId::new("alice".to_string());
+        assert_ne!(fresh.storage(), first.storage());
+        assert_eq!(fresh, first);
+        assert_eq!(serde_json::to_string(&first).unwrap(), "\"alice\"");
+        assert_eq!(serde_json::from_str::<UserId>("\"alice\"").unwrap(), first);
+    }
+}
@@ -0,0 +1,17 @@
+//! Helpers shared by the integration tests
+
+use matching_engine::{
+    types::{OrderId, UserId},
+    Order, OrderSide, Price, Quantity,
+};
+
+/// A limit order for `user`
+pub fn create_order(side: OrderSide, price_cents: i64, quantity: u64, user: &str) -> Order {
+    Order::new(
+        OrderId::new(),
+        UserId::new(user.to_string()),
+        side,
+        Price::from_cents(price_cents).unwrap(),
+        Quantity::new(quantity).unwrap(),
+    )
+}
@@ -0,0 +1,113 @@
+//! Integration tests for the async command-channel runner
+//!
+//! Run with `cargo test --features async`.
+
+#![cfg(feature = "async")]
+
+mod common;
+
+use matching_engine::{
+    EngineEvent, EngineTask, LimitOrderBook, MatchingEngineError, OpResult, OrderBookOp, OrderSide,
+    types::OrderId,
+};
+use tokio::sync::broadcast::error::RecvError;
+use common::create_order;
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_concurrent_submissions_apply_in_reply_order_and_broadcast_every_trade() {
+    let mut book = LimitOrderBook::new("ASYNC".to_string()).unwrap();
+    book.enable_op_log();
+    let (task, handle) = EngineTask::new(book, 8, 100_000);
+    let mut events = handle.subscribe();
+    let runner = tokio::spawn(task.run());
+
+    // Eight clients race buys and sells through a narrow band, so orders cross constantly
+    let clients: Vec<_> = (0..8).map(|client| {
+        let handle = handle.clone();
+        tokio::spawn(async move {
+            let mut replies = Vec::new();
+            for i in 0..200i64 {
+                let side = if (client + i) % 2 == 0 { OrderSide::Buy } else { OrderSide::Sell };
+                let order = create_order(side, 10000 + (client * 3 + i) % 7, 1 + (i % 5) as u64, &format!("client{client}"));
+                let order_id = order.id;
+                replies.push((order_id, handle.apply(OrderBookOp::Submit(order)).await.unwrap()));
+            }
+            replies
+        })
+    }).collect();
+    let mut replies = Vec::new();
+    for client in clients {
+        let client_replies = client.await.unwrap();
+
+        // One client's commands are applied in the order it sent them
+        assert!(client_replies.windows(2).all(|pair| pair[0].1.op_sequence < pair[1].1.op_sequence));
+        replies.extend(client_replies);
+    }
+
+    let snapshot = handle.shutdown().await.unwrap();
+    let mut book = runner.await.unwrap();
+    assert_eq!(LimitOrderBook::from_snapshot(snapshot).unwrap().state_hash(), book.state_hash());
+    assert!(matches!(handle.submit_order(create_order(OrderSide::Buy, 10000, 1, "late")).await, Err(MatchingEngineError::EngineStopped)));
+
+    // Replies cover every op sequence once, and the book's log applied the same orders in that order
+    replies.sort_by_key(|(_, reply)| reply.op_sequence);
+    assert_eq!(replies.iter().map(|(_, reply)| reply.op_sequence).collect::<Vec<_>>(), (1..=1600).collect::<Vec<_>>());
+    let logged: Vec<OrderId> = book.take_ops().into_iter()
+        .filter_map(|logged| match logged.op {
+            OrderBookOp::Submit(order) => Some(order.id),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(logged, replies.iter().map(|(order_id, _)| *order_id).collect::<Vec<_>>());
+
+    // The broadcast kept up, so it carried every replied trade in application order
+    let replied_trades: Vec<u64> = replies.iter()
+        .flat_map(|(_, reply)| match &reply.result {
+            OpResult::Executed(result) => result.trades.iter().map(|trade| trade.trade_id).collect::<Vec<_>>(),
+            other => panic!("unexpected result {other:?}"),
+        })
+        .collect();
+    assert!(!replied_trades.is_empty());
+    let mut broadcast_trades = Vec::new();
+    let mut deltas = 0;
+    loop {
+        match events.recv().await {
+            Ok(EngineEvent::Trade(trade)) => broadcast_trades.push(trade.trade_id),
+            Ok(EngineEvent::Delta(_)) => deltas += 1,
+            Err(RecvError::Closed) => break,
+            Err(RecvError::Lagged(missed)) => panic!("lagged by {missed} events"),
+        }
+    }
+    assert_eq!(broadcast_trades, replied_trades);
+    assert!(deltas > 0);
+}
+
+#[tokio::test]
+async fn test_slow_subscriber_is_told_how_many_events_it_lost() {
+    let (task, handle) = EngineTask::new(LimitOrderBook::new("ASYNC".to_string()).unwrap(), 4, 4);
+    let mut events = handle.subscribe();
+    let runner = tokio::spawn(task.run());
+
+    // Ten resting asks, then one buy sweeping them all: 20 deltas and 10 trades in all
+    for i in 0..10 {
+        handle.submit_order(create_order(OrderSide::Sell, 10000 + i, 1, "maker")).await.unwrap();
+    }
+    let result = handle.submit_order(create_order(OrderSide::Buy, 10009, 10, "taker")).await.unwrap();
+    assert_eq!(result.trades.len(), 10);
+    drop(handle);
+    runner.await.unwrap();
+
+    // Only the newest four events survive; the rest are counted as missed
+    let mut received = Vec::new();
+    let mut missed = 0;
+    loop {
+        match events.recv().await {
+            Ok(event) => received.push(event),
+            Err(RecvError::Lagged(count)) => missed += count,
+            Err(RecvError::Closed) => break,
+        }
+    }
+    assert_eq!(received.len(), 4);
+    assert_eq!(missed as usize + received.len(), 10 + 10 + 1);
+    assert!(matches!(received.last(), Some(EngineEvent::Delta(_))));
+}
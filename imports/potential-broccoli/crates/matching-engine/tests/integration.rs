@@ -311,4 +311,112 @@ fn test_serialization_with_complex_state() {
     let trades2 = book2.add_order(test_order).unwrap();
     
     assert_eq!(trades1.len(), trades2.len());
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_omnibus_allocation_reconciles_to_the_penny() {
+    use matching_engine::{AllocationEngine, SubAccountId, TradeKey};
+
+    let mut book = LimitOrderBook::new("ALLOC".to_string()).unwrap();
+
+    // Liquidity at three price levels
+    book.add_order(create_order(OrderSide::Sell, 10001, 300, "seller1")).unwrap();
+    book.add_order(create_order(OrderSide::Sell, 10003, 500, "seller2")).unwrap();
+    book.add_order(create_order(OrderSide::Sell, 10007, 200, "seller3")).unwrap();
+
+    // Omnibus parent order sweeps all three levels
+    let trades = book.add_order(create_order(OrderSide::Buy, 10007, 1000, "omnibus")).unwrap();
+    assert_eq!(trades.len(), 3);
+
+    let mut engine = AllocationEngine::new();
+    engine.record_trades(trades.iter().cloned()).unwrap();
+    assert_eq!(engine.unallocated_trades().len(), 3);
+
+    let accounts: Vec<SubAccountId> = ["fund_a", "fund_b", "fund_c"]
+        .iter()
+        .map(|name| SubAccountId::new(name.to_string()))
+        .collect();
+
+    // Split each fill 50/30/20 across the sub-accounts
+    for trade in &trades {
+        let total = trade.quantity.value();
+        let a = total / 2;
+        let b = total * 3 / 10;
+        let c = total - a - b;
+        engine
+            .allocate(&TradeKey::from(trade), vec![
+                (accounts[0].clone(), Quantity::new(a).unwrap()),
+                (accounts[1].clone(), Quantity::new(b).unwrap()),
+                (accounts[2].clone(), Quantity::new(c).unwrap()),
+            ])
+            .unwrap();
+    }
+
+    assert!(engine.unallocated_trades().is_empty());
+
+    let traded_volume: Decimal = trades.iter().map(|t| t.shares()).sum();
+    let traded_notional: Decimal = trades
+        .iter()
+        .map(|t| t.price.value() * Decimal::from(t.quantity.value()))
+        .sum();
+
+    let allocated_volume: Decimal = accounts.iter().map(|a| engine.allocated_volume(a)).sum();
+    let allocated_notional: Decimal = accounts.iter().map(|a| engine.allocated_notional(a)).sum();
+
+    assert_eq!(allocated_volume, traded_volume);
+    assert_eq!(allocated_notional, traded_notional);
+    assert_eq!(traded_notional, Decimal::new(10_003_200, 2)); // 30003.00 + 50015.00 + 20014.00
+    assert_eq!(engine.allocated_notional(&accounts[0]), Decimal::new(5_001_600, 2));
+}
+
+#[test]
+fn test_bracket_order_lifecycle() {
+    use matching_engine::{BracketState, CancelReason, OrderEvent};
+
+    let mut book = LimitOrderBook::new("BRKT".to_string()).unwrap();
+    book.add_order(create_order(OrderSide::Sell, 10000, 60, "mm")).unwrap();
+    book.add_order(create_order(OrderSide::Buy, 9800, 50, "mm")).unwrap();
+    book.add_order(create_order(OrderSide::Buy, 9700, 100, "mm")).unwrap();
+
+    // Market entry for 100 only finds 60, so the exits go live at 60
+    let entry = Order::market(OrderId::new(), UserId::new("trader".to_string()), OrderSide::Buy, Quantity::new(100).unwrap());
+    let entry_id = entry.id;
+    let result = book.submit_bracket(entry, Price::from_cents(10500).unwrap(), Price::from_cents(9800).unwrap()).unwrap();
+    assert_eq!(result.status, OrderStatus::Cancelled);
+    assert_eq!(result.trades[0].quantity.value(), 60);
+    let (take_profit_id, stop_loss_id) = match result.events.last() {
+        Some(OrderEvent::BracketUpdated { take_profit_id, stop_loss_id, state: BracketState::Active, open_quantity, .. }) => {
+            assert_eq!(open_quantity.value(), 60);
+            (*take_profit_id, *stop_loss_id)
+        },
+        other => panic!("expected an active bracket, got {:?}", other),
+    };
+    assert_eq!(book.get_order(take_profit_id).unwrap().remaining_quantity.value(), 60);
+    assert_eq!(book.get_pending_stop(stop_loss_id).unwrap().remaining_quantity.value(), 60);
+    assert_eq!(book.best_ask().unwrap().as_cents(), 10500);
+    assert_eq!(book.bracket(entry_id).unwrap().entry_filled, 60);
+
+    // A print at 98.00 triggers the stop-loss, which sells the position into the bids
+    let result = book.submit_order(create_order(OrderSide::Sell, 9800, 10, "seller")).unwrap();
+    assert!(result.events.iter().any(|event| matches!(
+        event,
+        OrderEvent::StopTriggered { order_id, .. } if *order_id == stop_loss_id
+    )));
+    let fills: Vec<(i64, u64)> = result.trades.iter()
+        .map(|trade| (trade.price.as_cents(), trade.quantity.value()))
+        .collect();
+    assert_eq!(fills, vec![(9800, 10), (9800, 40), (9700, 20)]);
+
+    // The take-profit is cancelled and the bracket closes
+    assert!(result.events.iter().any(|event| matches!(
+        event,
+        OrderEvent::Cancelled { order, reason: CancelReason::Contingency } if order.id == take_profit_id
+    )));
+    assert!(matches!(
+        result.events.last(),
+        Some(OrderEvent::BracketUpdated { state: BracketState::Closed, open_quantity, .. }) if open_quantity.value() == 0
+    ));
+    assert!(book.get_order(take_profit_id).is_none());
+    assert!(book.best_ask().is_none());
+    assert_eq!(book.bracket_count(), 0);
+}
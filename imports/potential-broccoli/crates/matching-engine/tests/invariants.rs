@@ -4,11 +4,14 @@
 //! across all possible operation sequences, using property-based testing.
 
 use matching_engine::{
-    LimitOrderBook, Order, OrderSide, Price, Quantity,
+    BookListener, BookMirror, CancelReason, Clock, FeeRounding, FeeSchedule, L3Detail, L3Entry, LimitOrderBook, OpResult, Order, OrderBookOp,
+    OrderEvent, OrderSide, OrderStatus, Price, Quantity, SelfTradePrevention, StpPolicy, StpScope, TieredStorage,
     types::{OrderId, UserId},
 };
 use proptest::prelude::*;
 use rust_decimal::Decimal;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
 
 // === Property Test Generators ===
 
@@ -22,6 +25,14 @@ fn quantity_strategy() -> impl Strategy<Value = Quantity> {
     (1u64..10_000).prop_map(|q| Quantity::new(q).unwrap())
 }
 
+/// Decimal places of the fractional-mode books
+const FRACTIONAL_SCALE: u32 = 3;
+
+/// Generate fractional quantities (0.001 to 100.000 shares, in thousandths)
+fn fractional_quantity_strategy() -> impl Strategy<Value = Quantity> {
+    (1i64..=100_000).prop_map(|lots| Quantity::from_decimal(Decimal::new(lots, FRACTIONAL_SCALE), FRACTIONAL_SCALE).unwrap())
+}
+
 /// Generate order sides
 fn side_strategy() -> impl Strategy<Value = OrderSide> {
     prop_oneof![Just(OrderSide::Buy), Just(OrderSide::Sell)]
@@ -41,41 +52,199 @@ fn order_strategy() -> impl Strategy<Value = Order> {
         })
 }
 
+/// Generate limit orders mixed with occasional market orders
+fn mixed_order_strategy() -> impl Strategy<Value = Order> {
+    prop_oneof![
+        4 => order_strategy(),
+        1 => (side_strategy(), quantity_strategy()).prop_map(|(side, quantity)| {
+            Order::market(OrderId::new(), UserId::new("test_user".to_string()), side, quantity)
+        }),
+    ]
+}
+
+/// Generate sequences of fractional-quantity orders over a narrow band, so they cross often
+fn fractional_order_sequence_strategy() -> impl Strategy<Value = Vec<Order>> {
+    let order = (side_strategy(), 9_990i64..10_010, fractional_quantity_strategy())
+        .prop_map(|(side, cents, quantity)| {
+            Order::new(OrderId::new(), UserId::new("test_user".to_string()), side, Price::from_cents(cents).unwrap(), quantity)
+        });
+    prop::collection::vec(order, 0..50)
+}
+
 /// Generate sequences of orders for complex scenarios
 fn order_sequence_strategy() -> impl Strategy<Value = Vec<Order>> {
     prop::collection::vec(order_strategy(), 0..50)
 }
 
+/// Operations for workloads mixing mutations and depth queries
+#[derive(Debug, Clone)]
+enum BookOp {
+    Add(Order),
+    /// Cancel the n-th previously added order (modulo the number added)
+    Cancel(usize),
+    Depth(usize),
+}
+
+fn book_op_strategy() -> impl Strategy<Value = BookOp> {
+    prop_oneof![
+        4 => order_strategy().prop_map(BookOp::Add),
+        2 => any::<usize>().prop_map(BookOp::Cancel),
+        2 => (1usize..15).prop_map(BookOp::Depth),
+    ]
+}
+
+/// Commands for driving the book through `apply` alone
+#[derive(Debug, Clone)]
+enum Command {
+    Submit(Order),
+    /// Cancel the n-th previously submitted order (modulo the number submitted)
+    Cancel(usize),
+    /// Reduce the n-th previously submitted order to the given quantity
+    Reduce(usize, Quantity),
+    CancelAllSide(OrderSide),
+}
+
+fn command_strategy() -> impl Strategy<Value = Command> {
+    prop_oneof![
+        6 => mixed_order_strategy().prop_map(Command::Submit),
+        3 => any::<usize>().prop_map(Command::Cancel),
+        1 => (any::<usize>(), quantity_strategy()).prop_map(|(n, quantity)| Command::Reduce(n, quantity)),
+        1 => side_strategy().prop_map(Command::CancelAllSide),
+    ]
+}
+
+/// Records the op sequence of every op the book reports applying
+#[derive(Clone, Default)]
+struct AppliedOps(Arc<Mutex<Vec<u64>>>);
+
+impl BookListener for AppliedOps {
+    fn on_op_applied(&mut self, op_sequence: u64, _op: &OrderBookOp, _result: &OpResult) {
+        self.0.lock().unwrap().push(op_sequence);
+    }
+}
+
+/// Generate orders from a handful of users around a narrow price band, so
+/// that users regularly meet their own resting orders
+fn multi_user_order_strategy() -> impl Strategy<Value = Order> {
+    (order_strategy(), 0usize..3).prop_map(|(mut order, user)| {
+        order.user_id = UserId::new(["alice", "bob", "carol"][user].to_string());
+        order.price = Price::from_cents(order.price.as_cents() % 20 + 10000).unwrap();
+        order
+    })
+}
+
+fn stp_policy_strategy() -> impl Strategy<Value = StpPolicy> {
+    prop_oneof![
+        Just(StpPolicy::CancelResting),
+        Just(StpPolicy::CancelIncoming),
+        Just(StpPolicy::CancelBoth),
+        Just(StpPolicy::DecrementAndCancel),
+    ]
+}
+
+/// Generate fee schedules of up to 1% per side, at any rounding and precision
+fn fee_schedule_strategy() -> impl Strategy<Value = FeeSchedule> {
+    let rounding = prop_oneof![Just(FeeRounding::HalfUp), Just(FeeRounding::HalfEven)];
+    (0i64..=10_000, 0i64..=10_000, rounding, 0u32..=4).prop_map(|(maker, taker, rounding, decimal_places)| {
+        FeeSchedule::new(Decimal::new(maker, 2), Decimal::new(taker, 2)).with_rounding(rounding, decimal_places)
+    })
+}
+
+/// Run every invariant both untiered and with a small hot tier, so that
+/// deep levels regularly live in (and are promoted from) the cold tier
+fn tiering_strategy() -> impl Strategy<Value = Option<TieredStorage>> {
+    prop_oneof![
+        Just(None),
+        (1usize..6).prop_map(|hot_levels| Some(TieredStorage::new(hot_levels))),
+    ]
+}
+
+fn new_book(tiering: Option<TieredStorage>) -> LimitOrderBook {
+    let mut book = LimitOrderBook::new("TEST".to_string()).unwrap();
+    book.set_tiered_storage(tiering);
+    book
+}
+
+/// Quantity and order count of each level in an L3 side
+fn count_levels(entries: &[L3Entry]) -> HashMap<Price, (u64, usize)> {
+    let mut levels: HashMap<Price, (u64, usize)> = HashMap::new();
+    for entry in entries {
+        let level = levels.entry(entry.price).or_default();
+        level.0 += entry.remaining_quantity.value();
+        level.1 += 1;
+    }
+    levels
+}
+
+/// Checks the book's structural integrity, and its running side totals and cached
+/// level totals, against a from-scratch count of the resting orders
+fn check_integrity(book: &LimitOrderBook) -> Result<(), TestCaseError> {
+    prop_assert_eq!(book.verify_integrity(), Ok(()));
+    let snapshot = book.l3_snapshot_with(usize::MAX, L3Detail { include_hidden: true, include_user_ids: false });
+    let count = |entries: &[L3Entry]| {
+        let quantity: u64 = entries.iter().map(|entry| entry.remaining_quantity.value()).sum();
+        let levels = entries.iter().map(|entry| entry.price).collect::<HashSet<_>>().len();
+        (quantity, entries.len(), levels)
+    };
+    prop_assert_eq!(
+        (book.total_bid_quantity(), book.bid_order_count(), book.price_level_count(OrderSide::Buy)),
+        count(&snapshot.bids)
+    );
+    prop_assert_eq!(
+        (book.total_ask_quantity(), book.ask_order_count(), book.price_level_count(OrderSide::Sell)),
+        count(&snapshot.asks)
+    );
+    prop_assert_eq!(book.bid_order_count() + book.ask_order_count(), book.order_count());
+    
+    // Hidden orders included, per level
+    for (side, entries) in [(OrderSide::Buy, &snapshot.bids), (OrderSide::Sell, &snapshot.asks)] {
+        for (price, (quantity, _)) in count_levels(entries) {
+            prop_assert_eq!(book.level_quantity(side, price).map(|total| total.value()), Some(quantity));
+        }
+    }
+    
+    // Displayed only, per level, as depth and at the touch
+    let displayed = book.l3_snapshot(usize::MAX);
+    let depth = book.market_depth(usize::MAX);
+    for (levels, entries, best_price, best_quantity) in [
+        (&depth.bids, &displayed.bids, book.best_bid(), book.best_bid_quantity()),
+        (&depth.asks, &displayed.asks, book.best_ask(), book.best_ask_quantity()),
+    ] {
+        let counted = count_levels(entries);
+        prop_assert_eq!(levels.len(), counted.len());
+        for level in levels {
+            prop_assert_eq!(Some(&(level.quantity.value(), level.order_count)), counted.get(&level.price));
+        }
+        let counted_best = best_price.map(|price| counted.get(&price).map_or(0, |level| level.0));
+        prop_assert_eq!(best_quantity.map(|total| total.value()), counted_best);
+    }
+    Ok(())
+}
+
 // === Invariant Properties ===
 
 proptest! {
     #![proptest_config(ProptestConfig::with_cases(1000))]
     
-    /// **Invariant**: Best bid is always less than or equal to best ask (no crossed market)
+    /// **Invariant**: The book stays intact and uncrossed after every order, and a
+    /// limit order trades exactly when `would_cross` said it would
     #[test]
-    fn prop_no_crossed_market(orders in order_sequence_strategy()) {
-        let mut book = LimitOrderBook::new("TEST".to_string()).unwrap();
+    fn prop_no_crossed_market(orders in order_sequence_strategy(), tiering in tiering_strategy()) {
+        let mut book = new_book(tiering);
         
         // Add all orders
         for order in orders {
-            let _ = book.add_order(order);
-        }
-        
-        // Check invariant
-        if let (Some(best_bid), Some(best_ask)) = (book.best_bid(), book.best_ask()) {
-            prop_assert!(
-                best_bid <= best_ask,
-                "Crossed market detected: bid {} > ask {}",
-                best_bid,
-                best_ask
-            );
+            let would_cross = book.would_cross(order.side, order.price);
+            let trades = book.add_order(order).unwrap();
+            prop_assert_eq!(would_cross, !trades.is_empty());
+            check_integrity(&book)?;
         }
     }
     
     /// **Invariant**: Order book quantities are always consistent after operations
     #[test]
-    fn prop_quantity_consistency(orders in order_sequence_strategy()) {
-        let mut book = LimitOrderBook::new("TEST".to_string()).unwrap();
+    fn prop_quantity_consistency(orders in order_sequence_strategy(), tiering in tiering_strategy()) {
+        let mut book = new_book(tiering);
         let mut total_added_quantity = 0u64;
         let mut total_traded_quantity = 0u64;
         
@@ -110,12 +279,45 @@ proptest! {
             total_remaining,
             total_traded_quantity
         );
+        check_integrity(&book)?;
+    }
+    
+    /// **Invariant**: Fractional quantities are conserved exactly, and notional
+    /// counts shares rather than lots
+    #[test]
+    fn prop_fractional_quantity_consistency(orders in fractional_order_sequence_strategy()) {
+        let mut book = LimitOrderBook::with_quantity_scale("TEST".to_string(), FRACTIONAL_SCALE).unwrap();
+        let mut total_added = Decimal::ZERO;
+        let mut total_traded = Decimal::ZERO;
+        let mut traded_notional = Decimal::ZERO;
+
+        for order in orders {
+            total_added += book.quantity_to_decimal(order.original_quantity);
+            for trade in book.add_order(order).unwrap() {
+                let shares = book.quantity_to_decimal(trade.quantity);
+                total_traded += shares;
+                traded_notional += trade.price.value() * shares;
+            }
+            check_integrity(&book)?;
+        }
+
+        let depth = book.market_depth(1000);
+        let total_remaining: Decimal = depth.bids.iter().chain(&depth.asks)
+            .map(|level| book.quantity_to_decimal(level.quantity))
+            .sum();
+        prop_assert_eq!(total_added, total_remaining + total_traded * Decimal::TWO);
+        prop_assert_eq!(book.session_stats().notional, traded_notional);
+
+        // The lots survive a snapshot round trip untouched
+        let restored = LimitOrderBook::from_json(&book.to_json().unwrap()).unwrap();
+        prop_assert_eq!(restored.quantity_scale(), FRACTIONAL_SCALE);
+        prop_assert_eq!(restored.market_depth(1000), depth);
     }
     
     /// **Invariant**: Price-time priority is maintained
     #[test]
-    fn prop_price_time_priority(orders in order_sequence_strategy()) {
-        let mut book = LimitOrderBook::new("TEST".to_string()).unwrap();
+    fn prop_price_time_priority(orders in order_sequence_strategy(), tiering in tiering_strategy()) {
+        let mut book = new_book(tiering);
         
         // Filter to only buy orders at same price for simplicity
         let same_price = Price::from_cents(15000).unwrap();
@@ -150,8 +352,8 @@ proptest! {
     
     /// **Invariant**: Spread is always non-negative
     #[test]
-    fn prop_non_negative_spread(orders in order_sequence_strategy()) {
-        let mut book = LimitOrderBook::new("TEST".to_string()).unwrap();
+    fn prop_non_negative_spread(orders in order_sequence_strategy(), tiering in tiering_strategy()) {
+        let mut book = new_book(tiering);
         
         for order in orders {
             let _ = book.add_order(order);
@@ -169,8 +371,8 @@ proptest! {
     
     /// **Invariant**: Market depth quantities sum correctly
     #[test]
-    fn prop_market_depth_consistency(orders in order_sequence_strategy()) {
-        let mut book = LimitOrderBook::new("TEST".to_string()).unwrap();
+    fn prop_market_depth_consistency(orders in order_sequence_strategy(), tiering in tiering_strategy()) {
+        let mut book = new_book(tiering);
         
         for order in orders {
             let _ = book.add_order(order);
@@ -208,8 +410,8 @@ proptest! {
     
     /// **Invariant**: Order cancellation never fails for existing orders
     #[test]
-    fn prop_cancellation_consistency(orders in order_sequence_strategy()) {
-        let mut book = LimitOrderBook::new("TEST".to_string()).unwrap();
+    fn prop_cancellation_consistency(orders in order_sequence_strategy(), tiering in tiering_strategy()) {
+        let mut book = new_book(tiering);
         let mut added_order_ids = Vec::new();
         
         // Add orders and collect IDs, tracking which ones weren't immediately filled
@@ -219,7 +421,7 @@ proptest! {
             
             // Only track orders that weren't fully filled (remain in the book)
             let was_fully_filled = trades.iter().any(|t| 
-                (t.buy_order_id == order_id || t.sell_order_id == order_id)
+                t.buy_order_id == order_id || t.sell_order_id == order_id
             );
             
             // Check if order is still in the book by trying to get it
@@ -243,12 +445,69 @@ proptest! {
         
         // After canceling all tracked orders, book should be empty or contain only untraceable orders
         // (orders that were partially filled or created during matching)
+        check_integrity(&book)?;
+    }
+    
+    /// **Invariant**: Driven only through `apply`, cancels succeed exactly for
+    /// resting orders, and every command is counted and reported once
+    #[test]
+    fn prop_cancellation_consistency_through_apply(
+        commands in prop::collection::vec(command_strategy(), 0..80),
+        tiering in tiering_strategy(),
+    ) {
+        let mut book = new_book(tiering);
+        let applied = AppliedOps::default();
+        book.set_listener(Box::new(applied.clone()));
+        let mut submitted: Vec<OrderId> = Vec::new();
+        let command_count = commands.len() as u64;
+
+        for command in commands {
+            let op_sequence = book.op_sequence();
+            match command {
+                Command::Submit(order) => {
+                    let order_id = order.id;
+                    let result = book.apply(OrderBookOp::Submit(order));
+                    prop_assert!(matches!(result, OpResult::Executed(_)), "submit failed: {:?}", result);
+                    submitted.push(order_id);
+                },
+                Command::Cancel(n) if !submitted.is_empty() => {
+                    let order_id = submitted[n % submitted.len()];
+                    let resting = book.get_order(order_id).is_some();
+                    let result = book.apply(OrderBookOp::Cancel(order_id));
+                    prop_assert_eq!(matches!(result, OpResult::Cancelled(_)), resting);
+                    prop_assert!(book.get_order(order_id).is_none());
+                },
+                Command::Cancel(_) => {
+                    let result = book.apply(OrderBookOp::Cancel(OrderId::new()));
+                    prop_assert!(matches!(result, OpResult::Rejected(_)));
+                },
+                Command::Reduce(n, new_quantity) if !submitted.is_empty() => {
+                    let order_id = submitted[n % submitted.len()];
+                    book.apply(OrderBookOp::Reduce { order_id, new_quantity });
+                    if let Some(order) = book.get_order(order_id) {
+                        prop_assert!(order.remaining_quantity.value() > 0);
+                    }
+                },
+                Command::Reduce(_, _) => {
+                    // Nothing to reduce yet; exercise the mass cancel instead
+                    book.apply(OrderBookOp::CancelAll);
+                },
+                Command::CancelAllSide(side) => {
+                    book.apply(OrderBookOp::CancelAllSide(side));
+                    prop_assert_eq!(book.price_level_count(side), 0);
+                },
+            }
+            prop_assert_eq!(book.op_sequence(), op_sequence + 1);
+            check_integrity(&book)?;
+        }
+
+        prop_assert_eq!(applied.0.lock().unwrap().clone(), (1..=command_count).collect::<Vec<_>>());
     }
     
     /// **Invariant**: Serialization round-trip preserves order book state
     #[test]
-    fn prop_serialization_roundtrip(orders in order_sequence_strategy()) {
-        let mut book1 = LimitOrderBook::new("TEST".to_string()).unwrap();
+    fn prop_serialization_roundtrip(orders in order_sequence_strategy(), tiering in tiering_strategy()) {
+        let mut book1 = new_book(tiering);
         
         // Add orders to first book
         for order in orders {
@@ -258,6 +517,14 @@ proptest! {
         // Serialize and deserialize
         let json = serde_json::to_string(&book1).unwrap();
         let book2: LimitOrderBook = serde_json::from_str(&json).unwrap();
+        check_integrity(&book2)?;
+        
+        // The binary format carries exactly what JSON does
+        #[cfg(feature = "binary-snapshots")]
+        {
+            let book3 = LimitOrderBook::from_bytes(&book1.to_bytes().unwrap()).unwrap();
+            prop_assert_eq!(serde_json::to_value(&book3).unwrap(), serde_json::to_value(&book2).unwrap());
+        }
         
         // Compare key properties
         prop_assert_eq!(book1.best_bid(), book2.best_bid());
@@ -271,30 +538,549 @@ proptest! {
         prop_assert_eq!(depth1, depth2);
     }
     
+    /// **Invariant**: Cached market depth always equals freshly computed depth
+    #[test]
+    fn prop_cached_depth_matches_fresh(
+        ops in prop::collection::vec(book_op_strategy(), 0..100),
+        tiering in tiering_strategy(),
+    ) {
+        let mut book = new_book(tiering);
+        let mut added = Vec::new();
+        
+        for op in ops {
+            match op {
+                BookOp::Add(mut order) => {
+                    // Narrow price range so orders share levels and cross often
+                    order.price = Price::from_cents(order.price.as_cents() % 40 + 10000).unwrap();
+                    added.push(order.id);
+                    book.add_order(order).unwrap();
+                },
+                BookOp::Cancel(n) if !added.is_empty() => {
+                    let _ = book.cancel_order(added[n % added.len()]);
+                },
+                BookOp::Cancel(_) => {},
+                BookOp::Depth(levels) => {
+                    let _ = book.market_depth(levels);
+                },
+            }
+            
+            // Clones start with an empty cache, so they always compute from scratch
+            for levels in [1, 5, 10] {
+                prop_assert_eq!(book.market_depth(levels), book.clone().market_depth(levels));
+            }
+        }
+    }
+    
+    /// **Invariant**: Copy-on-write snapshots match the book when taken and never change after
+    #[test]
+    fn prop_snapshots_match_and_stay_frozen(
+        ops in prop::collection::vec(book_op_strategy(), 0..100),
+        tiering in tiering_strategy(),
+    ) {
+        let mut book = new_book(tiering);
+        let mut added = Vec::new();
+        let mut taken = Vec::new();
+        
+        for op in ops {
+            match op {
+                BookOp::Add(mut order) => {
+                    order.price = Price::from_cents(order.price.as_cents() % 40 + 10000).unwrap();
+                    added.push(order.id);
+                    book.add_order(order).unwrap();
+                },
+                BookOp::Cancel(n) if !added.is_empty() => {
+                    let _ = book.cancel_order(added[n % added.len()]);
+                },
+                BookOp::Cancel(_) => {},
+                BookOp::Depth(_) => {
+                    let snapshot = book.snapshot();
+                    let depth = book.market_depth(usize::MAX);
+                    prop_assert_eq!(&snapshot.market_depth(usize::MAX), &depth);
+                    prop_assert_eq!(snapshot.bbo(), book.bbo());
+                    taken.push((snapshot, depth));
+                },
+            }
+            
+            for (snapshot, depth) in &taken {
+                prop_assert_eq!(&snapshot.market_depth(usize::MAX), depth);
+            }
+        }
+        prop_assert_eq!(book.snapshot().market_depth(usize::MAX), book.market_depth(usize::MAX));
+    }
+    
+    /// **Invariant**: Deltas applied to an earlier snapshot reproduce a fresh depth after every mutation
+    #[test]
+    fn prop_deltas_reconcile_with_depth(
+        orders in prop::collection::vec(mixed_order_strategy(), 1..80),
+        cancels in prop::collection::vec(any::<usize>(), 0..20),
+        snapshot_at in 0usize..40,
+        tiering in tiering_strategy(),
+    ) {
+        let mut book = new_book(tiering);
+        let mut mirror = None;
+        let mut added = Vec::new();
+        
+        for (index, mut order) in orders.into_iter().enumerate() {
+            if index == snapshot_at {
+                book.enable_delta_feed();
+                mirror = Some(BookMirror::from_snapshot(&book.recovery_snapshot()));
+            }
+            if !order.is_market() {
+                order.price = Price::from_cents(order.price.as_cents() % 30 + 10000).unwrap();
+            }
+            if index % 7 == 3 {
+                order = order.with_hidden();
+            }
+            added.push(order.id);
+            book.add_order(order).unwrap();
+            if let Some(n) = cancels.get(index) {
+                let _ = book.cancel_order(added[n % added.len()]);
+            }
+            
+            let Some(mirror) = mirror.as_mut() else { continue };
+            for delta in book.take_deltas() {
+                for update in delta.updates.iter().filter(|update| update.quantity.value() == 0) {
+                    prop_assert_eq!(update.order_count, 0, "Removed level {} still counts orders", update.price);
+                }
+                mirror.apply_delta(&delta).unwrap();
+            }
+            prop_assert_eq!(mirror.sequence(), book.sequence());
+            prop_assert_eq!(mirror.market_depth(usize::MAX), book.market_depth(usize::MAX));
+            check_integrity(&book)?;
+        }
+    }
+    
+    /// **Invariant**: A mirror recovering from a dropped range of deltas converges with the book
+    #[test]
+    fn prop_mirror_gap_recovery(
+        ops in prop::collection::vec(book_op_strategy(), 1..80),
+        drop_start in 0usize..80,
+        drop_len in 1usize..20,
+        tiering in tiering_strategy(),
+    ) {
+        let mut book = new_book(tiering);
+        book.enable_delta_feed();
+        let mut mirror = BookMirror::new(book.symbol().clone());
+        let mut added = Vec::new();
+        let mut delivered = 0usize;
+        
+        for op in ops {
+            match op {
+                BookOp::Add(mut order) => {
+                    order.price = Price::from_cents(order.price.as_cents() % 40 + 10000).unwrap();
+                    added.push(order.id);
+                    book.add_order(order).unwrap();
+                },
+                BookOp::Cancel(n) if !added.is_empty() => {
+                    let _ = book.cancel_order(added[n % added.len()]);
+                },
+                _ => {},
+            }
+            
+            for delta in book.take_deltas() {
+                let index = delivered;
+                delivered += 1;
+                if (drop_start..drop_start + drop_len).contains(&index) {
+                    continue; // Lost in transit
+                }
+                if mirror.apply_delta(&delta).is_err() {
+                    mirror.resync(&book.recovery_snapshot());
+                    // The delta that exposed the gap is older than the snapshot
+                    mirror.apply_delta(&delta).unwrap();
+                }
+            }
+            
+            // Until the gap is detected the mirror lags; once in sync it must match exactly
+            if mirror.sequence() == book.sequence() {
+                prop_assert_eq!(mirror.market_depth(usize::MAX), book.market_depth(usize::MAX));
+                prop_assert_eq!(mirror.checksum(10), book.market_depth(10).checksum(10));
+            }
+        }
+    }
+    
     /// **Invariant**: Order matching is deterministic
     #[test]
-    fn prop_deterministic_matching(orders in order_sequence_strategy()) {
+    fn prop_deterministic_matching(orders in order_sequence_strategy(), tiering in tiering_strategy()) {
         if orders.is_empty() {
             return Ok(());
         }
         
-        // Run the same sequence twice
-        let mut book1 = LimitOrderBook::new("TEST".to_string()).unwrap();
-        let mut book2 = LimitOrderBook::new("TEST".to_string()).unwrap();
+        // Run the same sequence twice through `apply`, on the same fixed clock
+        let start = chrono::Utc::now();
+        let clocked_book = || {
+            let mut book = LimitOrderBook::with_clock("TEST".to_string(), Clock::Fixed(start)).unwrap();
+            book.set_tiered_storage(tiering);
+            book
+        };
+        let mut book1 = clocked_book();
+        let mut book2 = clocked_book();
+        book1.enable_op_log();
         
         let mut trades1 = Vec::new();
         let mut trades2 = Vec::new();
         
-        for order in &orders {
-            trades1.extend(book1.add_order(order.clone()).unwrap());
-            trades2.extend(book2.add_order(order.clone()).unwrap());
+        for (i, order) in orders.iter().enumerate() {
+            let ops = [
+                OrderBookOp::SetClock(Clock::Fixed(start + chrono::Duration::milliseconds(i as i64))),
+                OrderBookOp::Submit(order.clone()),
+            ];
+            for op in ops {
+                if let OpResult::Executed(result) = book1.apply(op.clone()) {
+                    trades1.extend(result.trades);
+                }
+                if let OpResult::Executed(result) = book2.apply(op) {
+                    trades2.extend(result.trades);
+                }
+            }
         }
         
-        // Results should be identical
+        // Results should be identical, down to the snapshot bytes
         prop_assert_eq!(trades1.len(), trades2.len());
         prop_assert_eq!(book1.best_bid(), book2.best_bid());
         prop_assert_eq!(book1.best_ask(), book2.best_ask());
         prop_assert_eq!(book1.order_count(), book2.order_count());
+        prop_assert_eq!(book1.to_json().unwrap(), book2.to_json().unwrap());
+        
+        // Replaying the log rebuilds the same book (tiering is configuration, not logged)
+        if tiering.is_none() {
+            let replayed = LimitOrderBook::replay(book1.take_ops()).unwrap();
+            prop_assert_eq!(replayed.to_json().unwrap(), book1.to_json().unwrap());
+        }
+    }
+    
+    /// **Invariant**: Tiered storage changes nothing observable
+    #[test]
+    fn prop_tiered_matches_untiered(
+        ops in prop::collection::vec(book_op_strategy(), 0..100),
+        hot_levels in 1usize..6,
+    ) {
+        let mut plain = new_book(None);
+        let mut tiered = new_book(Some(TieredStorage::new(hot_levels)));
+        let mut added = Vec::new();
+        
+        for op in ops {
+            match op {
+                BookOp::Add(mut order) => {
+                    order.price = Price::from_cents(order.price.as_cents() % 200 + 10000).unwrap();
+                    added.push(order.id);
+                    let plain_trades = plain.add_order(order.clone()).unwrap();
+                    let tiered_trades = tiered.add_order(order).unwrap();
+                    prop_assert_eq!(plain_trades.len(), tiered_trades.len());
+                    for (a, b) in plain_trades.iter().zip(&tiered_trades) {
+                        prop_assert_eq!((a.trade_id, a.buy_order_id, a.sell_order_id), (b.trade_id, b.buy_order_id, b.sell_order_id));
+                        prop_assert_eq!((a.price, a.quantity), (b.price, b.quantity));
+                    }
+                },
+                BookOp::Cancel(n) if !added.is_empty() => {
+                    let id = added[n % added.len()];
+                    prop_assert_eq!(plain.cancel_order(id).is_ok(), tiered.cancel_order(id).is_ok());
+                },
+                BookOp::Cancel(_) => {},
+                BookOp::Depth(levels) => {
+                    prop_assert_eq!(plain.market_depth(levels), tiered.market_depth(levels));
+                },
+            }
+            
+            prop_assert_eq!(plain.market_depth(usize::MAX), tiered.market_depth(usize::MAX));
+            prop_assert_eq!(plain.order_count(), tiered.order_count());
+            for id in &added {
+                let state = |book: &LimitOrderBook| book.get_order(*id).map(|o| (o.remaining_quantity, o.status));
+                prop_assert_eq!(state(&plain), state(&tiered));
+            }
+        }
+    }
+    
+    /// **Invariant**: The order lookup holds exactly the orders queued in the book,
+    /// even when submissions reuse IDs
+    #[test]
+    fn prop_lookup_matches_book(
+        ops in prop::collection::vec((book_op_strategy(), any::<bool>()), 0..80),
+        tiering in tiering_strategy(),
+    ) {
+        let mut book = new_book(tiering);
+        let mut added: Vec<Order> = Vec::new();
+        
+        for (op, reuse_id) in ops {
+            match op {
+                BookOp::Add(mut order) => {
+                    order.price = Price::from_cents(order.price.as_cents() % 40 + 10000).unwrap();
+                    if let Some(earlier) = added.last().filter(|_| reuse_id) {
+                        order.id = earlier.id;
+                    }
+                    let live = book.get_order(order.id).is_some();
+                    let result = book.add_order(order.clone());
+                    prop_assert_eq!(result.is_err(), live, "Duplicate ID accepted or fresh ID rejected");
+                    added.push(order);
+                },
+                BookOp::Cancel(n) if !added.is_empty() => {
+                    let _ = book.cancel_order(added[n % added.len()].id);
+                },
+                _ => {},
+            }
+            
+            check_integrity(&book)?;
+        }
+    }
+    
+    /// **Invariant**: With user-scoped STP enabled no trade has the same user on
+    /// both sides, and every unit of quantity submitted is resting, traded, or
+    /// removed by STP
+    #[test]
+    fn prop_stp_prevents_self_trades(
+        orders in prop::collection::vec(multi_user_order_strategy(), 0..60),
+        policy in stp_policy_strategy(),
+        tiering in tiering_strategy(),
+    ) {
+        let mut book = new_book(tiering);
+        book.set_self_trade_prevention(Some(SelfTradePrevention::new(policy, StpScope::User)));
+        let mut users = HashMap::new();
+        let (mut added, mut traded, mut stp_removed) = (0u64, 0u64, 0u64);
+        
+        for order in orders {
+            users.insert(order.id, order.user_id.clone());
+            added += order.original_quantity.value();
+            let result = book.submit_order(order).unwrap();
+            
+            for trade in &result.trades {
+                prop_assert_ne!(&users[&trade.buy_order_id], &users[&trade.sell_order_id], "Self-trade {:?}", trade);
+                traded += trade.quantity.value();
+            }
+            for event in &result.events {
+                match event {
+                    OrderEvent::Cancelled { order, reason: CancelReason::SelfTradePrevention } => {
+                        stp_removed += order.remaining_quantity.value();
+                    },
+                    OrderEvent::Decremented { quantity, reason: CancelReason::SelfTradePrevention, .. } => {
+                        stp_removed += quantity.value();
+                    },
+                    _ => {},
+                }
+            }
+        }
+        
+        let depth = book.market_depth(usize::MAX);
+        let remaining: u64 = depth.bids.iter().chain(&depth.asks).map(|level| level.quantity.value()).sum();
+        prop_assert_eq!(
+            added,
+            remaining + 2 * traded + stp_removed,
+            "Quantity conservation violated: added={}, remaining={}, traded={}, stp_removed={}",
+            added,
+            remaining,
+            traded,
+            stp_removed
+        );
+        check_integrity(&book)?;
+    }
+    
+    /// **Invariant**: Every mutation advances the book sequence, trade IDs
+    /// strictly increase, and a restored snapshot continues both numberings
+    #[test]
+    fn prop_sequence_strictly_increasing(
+        ops in prop::collection::vec(book_op_strategy(), 0..80),
+        tiering in tiering_strategy(),
+    ) {
+        let mut book = new_book(tiering);
+        let mut added: Vec<OrderId> = Vec::new();
+        let mut last_trade_id = 0;
+        
+        for (i, op) in ops.into_iter().enumerate() {
+            let before = book.sequence();
+            match op {
+                BookOp::Add(mut order) => {
+                    order.price = Price::from_cents(order.price.as_cents() % 40 + 10000).unwrap();
+                    added.push(order.id);
+                    for trade in book.add_order(order).unwrap() {
+                        prop_assert!(trade.trade_id > last_trade_id, "Trade ID {} after {}", trade.trade_id, last_trade_id);
+                        prop_assert_eq!(trade.sequence, book.sequence());
+                        last_trade_id = trade.trade_id;
+                    }
+                    prop_assert!(book.sequence() > before, "Add did not advance the sequence");
+                },
+                BookOp::Cancel(n) if !added.is_empty() => {
+                    let cancelled = book.cancel_order(added[n % added.len()]).is_ok();
+                    prop_assert_eq!(book.sequence() > before, cancelled, "Sequence moved without a mutation or vice versa");
+                },
+                BookOp::Depth(levels) => {
+                    prop_assert_eq!(book.market_depth(levels).sequence, before);
+                },
+                _ => {},
+            }
+            prop_assert!(book.sequence() >= before);
+            
+            if i % 20 == 19 {
+                let sequence = book.sequence();
+                let json = serde_json::to_string(&book).unwrap();
+                book = serde_json::from_str(&json).unwrap();
+                prop_assert_eq!(book.sequence(), sequence);
+            }
+        }
+        
+        // A restored book carries on from where the original left off
+        let json = serde_json::to_string(&book).unwrap();
+        let mut restored: LimitOrderBook = serde_json::from_str(&json).unwrap();
+        let sequence = book.sequence();
+        let resting = restored.best_ask().map(|price| (OrderSide::Buy, price))
+            .or(restored.best_bid().map(|price| (OrderSide::Sell, price)));
+        if let Some((side, price)) = resting {
+            let order = Order::new(OrderId::new(), UserId::new("test_user".to_string()), side, price, Quantity::new(1).unwrap());
+            let trades = restored.add_order(order).unwrap();
+            prop_assert_eq!(restored.sequence(), sequence + 1);
+            prop_assert!(trades[0].trade_id > last_trade_id);
+            prop_assert_eq!(trades[0].sequence, sequence + 1);
+        }
+    }
+    
+    /// **Invariant**: Fees are non-negative, within half a rounding unit of the
+    /// rate applied to notional, and the per-user totals add up to them
+    #[test]
+    fn prop_fees_proportional_to_notional(
+        orders in prop::collection::vec(multi_user_order_strategy(), 0..60),
+        schedule in fee_schedule_strategy(),
+    ) {
+        let mut book = new_book(None);
+        book.set_fee_schedule(Some(schedule.clone()));
+        let half_unit = Decimal::new(5, schedule.decimal_places + 1);
+        let mut charged = Decimal::ZERO;
+        
+        for order in orders {
+            for trade in book.add_order(order).unwrap() {
+                let notional = trade.price.value() * Decimal::from(trade.quantity.value());
+                let exact = |bps: Decimal| notional * bps / Decimal::from(10_000);
+                for (fee, bps) in [(trade.maker_fee, schedule.rates.maker_bps), (trade.taker_fee, schedule.rates.taker_bps)] {
+                    prop_assert!(fee >= Decimal::ZERO, "Negative fee {}", fee);
+                    prop_assert!((fee - exact(bps)).abs() <= half_unit, "Fee {} for {} bps of {}", fee, bps, notional);
+                }
+                charged += trade.maker_fee + trade.taker_fee;
+            }
+        }
+        
+        let totals: Decimal = book.fee_totals().values().sum();
+        prop_assert_eq!(totals, charged);
+    }
+    
+    /// **Invariant**: A preview agrees exactly with executing the same order
+    /// on the unchanged book, apart from trade timestamps
+    #[test]
+    fn prop_preview_matches_execution(
+        orders in prop::collection::vec(multi_user_order_strategy(), 0..40),
+        policy in prop::option::of(stp_policy_strategy()),
+        fee_schedule in prop::option::of(fee_schedule_strategy()),
+        incoming in prop_oneof![
+            3 => multi_user_order_strategy(),
+            1 => (multi_user_order_strategy(), quantity_strategy()).prop_map(|(order, quantity)| {
+                Order::market(OrderId::new(), order.user_id, order.side, quantity)
+            }),
+        ],
+    ) {
+        let mut book = new_book(None);
+        book.set_self_trade_prevention(policy.map(|policy| SelfTradePrevention::new(policy, StpScope::User)));
+        book.set_fee_schedule(fee_schedule);
+        for order in orders {
+            book.add_order(order).unwrap();
+        }
+        
+        let preview = book.preview_order(&incoming);
+        let order_id = incoming.id;
+        let trades = book.submit_order(incoming).unwrap().trades;
+        prop_assert_eq!(preview.trades.len(), trades.len());
+        for (previewed, trade) in preview.trades.iter().zip(&trades) {
+            let mut previewed = previewed.clone();
+            previewed.timestamp = trade.timestamp;
+            prop_assert_eq!(&previewed, trade);
+        }
+        
+        let resting = book.get_order(order_id);
+        prop_assert_eq!(preview.would_rest, resting.is_some());
+        if let Some(resting) = resting {
+            prop_assert_eq!(preview.residual_quantity, resting.remaining_quantity);
+        }
+    }
+    
+    /// **Invariant**: Mid price, weighted mid, and microprice lie within [bid, ask]
+    #[test]
+    fn prop_mid_prices_within_touch(orders in order_sequence_strategy(), tiering in tiering_strategy()) {
+        let mut book = new_book(tiering);
+        for order in orders {
+            book.add_order(order).unwrap();
+        }
+        
+        let mids = [book.mid_price(), book.weighted_mid(), book.microprice()];
+        match (book.best_bid(), book.best_ask()) {
+            (Some(bid), Some(ask)) => {
+                for mid in mids {
+                    let mid = mid.unwrap();
+                    prop_assert!(bid.value() <= mid && mid <= ask.value(), "{} outside [{}, {}]", mid, bid, ask);
+                }
+            },
+            _ => prop_assert_eq!(mids, [None, None, None]),
+        }
+    }
+    
+    /// **Invariant**: Market orders never rest and never leave a crossed market
+    #[test]
+    fn prop_market_orders_never_rest(
+        orders in prop::collection::vec(mixed_order_strategy(), 0..60),
+        tiering in tiering_strategy(),
+    ) {
+        let mut book = new_book(tiering);
+        
+        for mut order in orders {
+            if !order.is_market() {
+                order.price = Price::from_cents(order.price.as_cents() % 50 + 10000).unwrap();
+            }
+            let (order_id, is_market) = (order.id, order.is_market());
+            let result = book.submit_order(order).unwrap();
+            
+            if is_market {
+                prop_assert!(book.get_order(order_id).is_none(), "Market order rested");
+                prop_assert!(!matches!(result.status, OrderStatus::Active | OrderStatus::PartiallyFilled));
+            }
+            if let (Some(best_bid), Some(best_ask)) = (book.best_bid(), book.best_ask()) {
+                prop_assert!(best_bid < best_ask, "Crossed market: bid {} >= ask {}", best_bid, best_ask);
+            }
+        }
+    }
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(64))]
+    
+    /// **Invariant**: An order past its good-till-date expiry never trades
+    #[test]
+    fn prop_expired_orders_never_trade(
+        orders in prop::collection::vec((order_strategy(), proptest::option::of(0i64..6)), 0..60),
+        tiering in tiering_strategy(),
+    ) {
+        let mut book = new_book(tiering);
+        let mut now = chrono::Utc::now();
+        book.set_clock(Clock::Fixed(now));
+        let mut expiries = HashMap::new();
+        
+        for (i, (mut order, expiry_ms)) in orders.into_iter().enumerate() {
+            order.price = Price::from_cents(order.price.as_cents() % 20 + 10000).unwrap();
+            if let Some(ms) = expiry_ms {
+                let expires_at = now + chrono::Duration::milliseconds(ms);
+                expiries.insert(order.id, expires_at);
+                order = order.with_expiry(expires_at);
+            }
+            // Step the clock so that short expiries pass mid-sequence
+            if i % 10 == 9 {
+                now += chrono::Duration::milliseconds(1);
+                book.set_clock(Clock::Fixed(now));
+            }
+            
+            for trade in book.add_order(order).unwrap() {
+                for order_id in [trade.buy_order_id, trade.sell_order_id] {
+                    if let Some(expires_at) = expiries.get(&order_id) {
+                        prop_assert!(*expires_at > trade.timestamp, "Order {} traded after expiring", order_id);
+                    }
+                }
+            }
+        }
+        
+        book.purge_expired(now);
+        prop_assert_eq!(book.next_expiry().is_some_and(|at| at <= now), false);
+        prop_assert_eq!(book.market_depth(usize::MAX), book.clone().market_depth(usize::MAX));
     }
 }
 
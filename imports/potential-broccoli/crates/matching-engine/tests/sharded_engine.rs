@@ -0,0 +1,92 @@
+//! Stress test for the sharded multi-symbol engine
+
+mod common;
+
+use chrono::{TimeZone, Utc};
+use matching_engine::{
+    Clock, LimitOrderBook, OpResult, OrderBookOp, OrderSide, Quantity, ShardedEngine,
+    types::Symbol,
+};
+use std::thread;
+use common::create_order;
+
+fn clock() -> Clock {
+    Clock::Fixed(Utc.with_ymd_and_hms(2024, 3, 1, 14, 30, 0).unwrap())
+}
+
+/// Crossing flow with cancels and amendments of earlier orders
+fn flow(producer: i64, orders: i64) -> Vec<OrderBookOp> {
+    let mut ops = Vec::new();
+    let mut ids = Vec::new();
+    for i in 0..orders {
+        let side = if (producer + i) % 2 == 0 { OrderSide::Buy } else { OrderSide::Sell };
+        let order = create_order(side, 10000 + (producer * 3 + i) % 9 - 4, 1 + (i % 7) as u64, &format!("producer{producer}"));
+        ids.push(order.id);
+        ops.push(OrderBookOp::Submit(order));
+        match i % 5 {
+            2 => ops.push(OrderBookOp::Cancel(ids[(i as usize * 7) % ids.len()])),
+            4 => ops.push(OrderBookOp::Reduce { order_id: ids[(i as usize * 3) % ids.len()], new_quantity: Quantity::new(1).unwrap() }),
+            _ => {},
+        }
+    }
+    ops
+}
+
+#[test]
+fn test_each_book_matches_a_single_threaded_replay_of_its_commands() {
+    const SYMBOLS: usize = 16;
+    const PRODUCERS: usize = 8;
+    let engine = ShardedEngine::new(4);
+    let symbols: Vec<Symbol> = (0..SYMBOLS).map(|i| Symbol::new(format!("SYM{i:02}")).unwrap()).collect();
+
+    // Each producer owns two symbols and interleaves its flow across them, racing
+    // the other producers through the shared shard queues
+    let producers: Vec<_> = (0..PRODUCERS).map(|producer| {
+        let handle = engine.handle();
+        let owned: Vec<Symbol> = symbols.iter().skip(producer).step_by(PRODUCERS).cloned().collect();
+        thread::spawn(move || {
+            let flows: Vec<Vec<OrderBookOp>> = owned.iter().enumerate()
+                .map(|(n, symbol)| {
+                    let create = OrderBookOp::Create { symbol: symbol.to_string(), clock: clock() };
+                    std::iter::once(create).chain(flow((producer * 2 + n) as i64, 400)).collect()
+                })
+                .collect();
+            let mut pending: Vec<Vec<_>> = owned.iter().map(|_| Vec::new()).collect();
+            let longest = flows.iter().map(Vec::len).max().unwrap();
+            for step in 0..longest {
+                for (n, symbol) in owned.iter().enumerate() {
+                    if let Some(op) = flows[n].get(step) {
+                        pending[n].push(handle.submit(symbol, op.clone()).unwrap());
+                    }
+                }
+            }
+            owned.into_iter().zip(flows).zip(pending)
+                .map(|((symbol, ops), pending)| {
+                    let results: Vec<OpResult> = pending.into_iter().map(|result| result.wait().unwrap()).collect();
+                    (symbol, ops, results)
+                })
+                .collect::<Vec<_>>()
+        })
+    }).collect();
+    let submitted: Vec<_> = producers.into_iter().flat_map(|producer| producer.join().unwrap()).collect();
+
+    let stats = engine.handle().stats();
+    let total_ops: usize = submitted.iter().map(|(_, ops, _)| ops.len()).sum();
+    assert_eq!(stats.iter().map(|shard| shard.ops_applied).sum::<u64>(), total_ops as u64);
+    assert_eq!(stats.iter().map(|shard| shard.symbols).sum::<usize>(), SYMBOLS);
+    assert!(stats.iter().all(|shard| shard.queue_depth == 0));
+    let books = engine.shutdown();
+    assert_eq!(books.len(), SYMBOLS);
+
+    for (symbol, ops, results) in submitted {
+        let mut replay = LimitOrderBook::with_clock(symbol.to_string(), clock()).unwrap();
+        assert_eq!(results[0], OpResult::Done);
+        for (op, result) in ops.into_iter().zip(&results).skip(1) {
+            assert_eq!(&replay.apply(op), result, "{symbol} diverged");
+        }
+        let book = &books[&symbol];
+        assert!(book.stats().trade_count > 0, "{symbol} never traded");
+        assert_eq!(book.state_hash(), replay.state_hash(), "{symbol} diverged");
+        assert_eq!(book.verify_integrity(), Ok(()));
+    }
+}